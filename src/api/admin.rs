@@ -0,0 +1,2340 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::{DefaultBodyLimit, Path, Query, Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post, put},
+    Extension, Json, Router,
+};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+use crate::api::response::{ApiError, ApiResponse};
+use crate::config::CONFIG;
+use crate::database::models::{AdminOperator, DeepLink, ErrorGrouping, ErrorStatsQuery, MobileChangeAudit, NotificationTemplate, OutboxStatus, PromoCode, PromoItem, PromoReward, ReportCategory, TwoStepReset, RiskFlag, SanctionKind, Season, SeasonReward, SeasonStatus, UserFilter, UserRegister, UserReport, UserSanction};
+use crate::database::backup::BackupManager;
+use crate::database::dead_letter::EventDeadLetters;
+use crate::database::outbox::Outbox;
+use crate::database::envelope::EVENT_SCHEMAS;
+use crate::database::export::{ExportManager, ExportRequest};
+use crate::database::timeline::{TimelineSource, TIMELINE_SOURCES};
+use crate::database::warehouse::WarehouseSink;
+use crate::database::query_monitor::QueryMonitor;
+use crate::database::store::DataStore;
+use crate::managers::game_config::{GameConfigManager, DEFAULT_GAME_TYPE};
+use crate::managers::gifts;
+use crate::managers::health_score::HealthScoreManager;
+use crate::managers::moderation::{ModerationManager, AUTO_MODERATOR, MAX_SANCTION_HOURS};
+use crate::managers::notification_templates::NotificationTemplateManager;
+use crate::managers::notifications::{NotificationCategory, NotificationManager};
+use crate::managers::promos::{self, PromoManager};
+use crate::managers::rbac::{AdminIdentity, AdminRole, Permission, Rbac};
+use crate::managers::retention::RetentionManager;
+use crate::managers::revenue::RevenueManager;
+use crate::managers::seasons::SeasonManager;
+use crate::managers::sessions::SessionMetricsManager;
+use crate::managers::tenant::{TenantManager, TENANT_HEADER};
+
+// Largest CSV accepted by the import endpoint
+const MAX_IMPORT_BYTES: usize = 20 * 1024 * 1024;
+// Row errors listed in an import report; the rest are only counted
+const MAX_REPORTED_ERRORS: usize = 500;
+const DEFAULT_PAGE_SIZE: i64 = 20;
+const MAX_PAGE_SIZE: i64 = 100;
+// Error analytics look back this far unless `from` is given, and no further than the maximum
+const DEFAULT_ERROR_WINDOW_HOURS: i64 = 24;
+const MAX_ERROR_WINDOW_DAYS: i64 = 90;
+const DEFAULT_OFFENDER_LIMIT: i64 = 10;
+const MAX_OFFENDER_LIMIT: i64 = 100;
+// Days covered by the revenue report unless `from` is given, and at most
+const DEFAULT_REVENUE_DAYS: i64 = 30;
+const MAX_REVENUE_DAYS: i64 = 92;
+// Minutes of session history served unless `minutes` is given, and at most
+const DEFAULT_SESSION_MINUTES: i64 = 60;
+const MAX_SESSION_MINUTES: i64 = 7 * 24 * 60;
+
+// Admin REST API. Every route needs `Authorization: Bearer <token>` with
+// ADMIN_API_TOKEN or an operator token, and the permission shown. Requests
+// work on the tenant named by the X-Tenant-Id header (the default tenant
+// without one); operators belong to a tenant, ADMIN_API_TOKEN opens them all.
+//   GET  /api/admin/users/search                  users:read        UserQuery filters, paginated
+//   POST /api/admin/users/import                  users:import      CSV body with a header row; see ImportRow
+//   GET  /api/admin/users/export                  users:export      ?format=csv|jsonl plus UserQuery filters
+//   POST /api/admin/users/:user_id/mobile         users:change_mobile {"new_mobile_no", "reason"}; skips the OTPs of account:change_mobile
+//   POST /api/admin/users/:user_id/two-step/reset users:reset_two_step {"reason"}; removes the user's second factor
+//   GET  /api/admin/users/:user_id/timeline       users:read        ?before&kinds&page_size; logins, errors, matches, ledgers... newest first
+//   GET  /api/admin/errors/stats                  errors:read       ?from&to&group_by=error_code|error_type|field&bucket=hour|day
+//   GET  /api/admin/errors/offenders              errors:read       ?from&to&limit; top devices and app versions
+//   GET  /api/admin/health-score                  metrics:read      this server's deployment health; see HealthScoreManager
+//   GET  /api/admin/sessions                      metrics:read      ?minutes (default 60); live, per-minute and peak concurrent sessions
+//   GET  /api/admin/operators                     operators:manage
+//   POST /api/admin/operators                     operators:manage  {"name", "role"}; returns the operator's token
+//   PUT  /api/admin/operators/:operator_id/role   operators:manage  {"role"}
+//   GET  /api/admin/impersonations                operators:manage  ?user_id&operator_id&page&page_size; audit of impersonate:start
+//   GET  /api/admin/seasons                       seasons:manage
+//   POST /api/admin/seasons                       seasons:manage    {"name", "start_at", "end_at", "rewards"}
+//   GET  /api/admin/risk/flags                    risk:review       ?status=open|confirmed|dismissed&page&page_size
+//   POST /api/admin/risk/flags/:flag_id/resolve   risk:review       {"status": "confirmed" | "dismissed"}
+//   GET  /api/admin/risk/users/:user_id           risk:review
+//   GET  /api/admin/reports                       moderation:manage ?status=open|actioned|dismissed&category&page&page_size
+//   POST /api/admin/reports/:report_id/resolve    moderation:manage {"action": "dismiss" | "warn" | "mute" | "ban", "duration_hours", "reason"}
+//   GET  /api/admin/users/:user_id/sanctions      moderation:manage
+//   POST /api/admin/users/:user_id/sanctions      moderation:manage {"action": "warn" | "mute" | "ban", "duration_hours", "reason"}
+//   POST /api/admin/sanctions/:sanction_id/lift   moderation:manage
+//   GET  /api/admin/game-configs                  game_configs:manage
+//   GET  /api/admin/game-configs/:game_type       game_configs:manage  current rules and every stored version
+//   PUT  /api/admin/game-configs/:game_type       game_configs:manage  any of the GameRules fields; stores the next version
+//   DELETE /api/admin/game-configs/:game_type     game_configs:manage  back to the built-in rules
+//   GET  /api/admin/notification-templates        notifications:manage every notification type with its stored templates
+//   PUT  /api/admin/notification-templates/:notification_type/:language_code
+//                                                 notifications:manage {"title", "body"}
+//   DELETE /api/admin/notification-templates/:notification_type/:language_code
+//                                                 notifications:manage back to the fallback languages
+//   GET  /api/admin/backups                       backups:manage    running and recent backups
+//   POST /api/admin/backups                       backups:manage    starts a background backup; see BackupManager
+//   GET  /api/admin/backups/:backup_id            backups:manage    progress of a backup started on this server
+//   GET  /api/admin/exports                       events:export     running and recent event exports
+//   POST /api/admin/exports                       events:export     {"collections", "from", "to", "format": "parquet" | "csv"}; see ExportManager
+//   GET  /api/admin/exports/:export_id            events:export     progress of an export started on this server
+//   GET  /api/admin/warehouse                     events:export     warehouse sink checkpoints per collection; see WarehouseSink
+//   GET  /api/admin/warehouse/dead-letters        events:export     newest batches the warehouse kept rejecting
+//   POST /api/admin/warehouse/dead-letters/:dead_letter_id/replay
+//                                                 events:export     ships the batch again and removes it on success
+//   GET  /api/admin/event-dead-letters            events:export     newest event writes that could not be stored
+//   POST /api/admin/event-dead-letters/replay     events:export     stores them again; see EventDeadLetters
+//   GET  /api/admin/outbox                        events:export     ?status=pending|delivered|failed; side effects waiting or done, see Outbox
+//   POST /api/admin/outbox/:message_id/retry      events:export     puts a failed message back in line
+//   GET  /api/admin/database/index-report         database:read     unused indexes and slow queries without one
+//   GET  /api/admin/revenue/daily                 finance:read      ?from&to (YYYY-MM-DD)&game_type&refresh; daily commission rollups
+//   GET  /api/admin/revenue/ledger                finance:read      ?day&game_type&page&page_size; one entry per released escrow
+//   GET  /api/admin/promo-codes                   promos:manage
+//   POST /api/admin/promo-codes                   promos:manage     {"code", "description", "coins", "items", "max_uses", "per_user_limit", "starts_at", "expires_at"}
+//   PUT  /api/admin/promo-codes/:code/active      promos:manage     {"active"}; disabled codes cannot be redeemed
+//   GET  /api/admin/promo-codes/:code/redemptions promos:manage     ?page&page_size; newest first, with device and IP
+pub fn router(data_service: Arc<dyn DataStore>) -> Router {
+    let guard = |permission: Permission| {
+        middleware::from_fn_with_state(AccessCheck { data_service: data_service.clone(), permission }, check_access)
+    };
+    Router::new()
+        .route("/api/admin/users/search", get(search_users).route_layer(guard(Permission::UsersRead)))
+        .route("/api/admin/users/import", post(import_users).route_layer(guard(Permission::UsersImport)))
+        .route("/api/admin/users/export", get(export_users).route_layer(guard(Permission::UsersExport)))
+        .route("/api/admin/users/:user_id/mobile", post(change_user_mobile).route_layer(guard(Permission::UsersChangeMobile)))
+        .route("/api/admin/users/:user_id/two-step/reset", post(reset_two_step).route_layer(guard(Permission::UsersResetTwoStep)))
+        .route("/api/admin/users/:user_id/timeline", get(user_timeline).route_layer(guard(Permission::UsersRead)))
+        .route("/api/admin/errors/stats", get(error_stats).route_layer(guard(Permission::ErrorsRead)))
+        .route("/api/admin/errors/offenders", get(error_offenders).route_layer(guard(Permission::ErrorsRead)))
+        .route("/api/admin/analytics/retention", get(retention_report).route_layer(guard(Permission::MetricsRead)))
+        .route("/api/admin/health-score", get(health_score).route_layer(guard(Permission::MetricsRead)))
+        .route("/api/admin/sessions", get(session_metrics).route_layer(guard(Permission::MetricsRead)))
+        .route("/api/admin/operators", get(list_operators).post(create_operator).route_layer(guard(Permission::OperatorsManage)))
+        .route("/api/admin/operators/:operator_id/role", put(set_operator_role).route_layer(guard(Permission::OperatorsManage)))
+        .route("/api/admin/impersonations", get(list_impersonations).route_layer(guard(Permission::OperatorsManage)))
+        .route("/api/admin/seasons", get(list_seasons).post(create_season).route_layer(guard(Permission::SeasonsManage)))
+        .route("/api/admin/risk/flags", get(list_risk_flags).route_layer(guard(Permission::RiskReview)))
+        .route("/api/admin/risk/flags/:flag_id/resolve", post(resolve_risk_flag).route_layer(guard(Permission::RiskReview)))
+        .route("/api/admin/risk/users/:user_id", get(user_risk).route_layer(guard(Permission::RiskReview)))
+        .route("/api/admin/reports", get(list_reports).route_layer(guard(Permission::ModerationManage)))
+        .route("/api/admin/reports/:report_id/resolve", post(resolve_report).route_layer(guard(Permission::ModerationManage)))
+        .route("/api/admin/users/:user_id/sanctions", get(list_sanctions).post(sanction_user).route_layer(guard(Permission::ModerationManage)))
+        .route("/api/admin/sanctions/:sanction_id/lift", post(lift_sanction).route_layer(guard(Permission::ModerationManage)))
+        .route("/api/admin/game-configs", get(list_game_configs).route_layer(guard(Permission::GameConfigsManage)))
+        .route(
+            "/api/admin/game-configs/:game_type",
+            get(game_config_history).put(update_game_config).delete(retire_game_config).route_layer(guard(Permission::GameConfigsManage)),
+        )
+        .route("/api/admin/notification-templates", get(list_notification_templates).route_layer(guard(Permission::NotificationsManage)))
+        .route(
+            "/api/admin/notification-templates/:notification_type/:language_code",
+            put(update_notification_template).delete(delete_notification_template).route_layer(guard(Permission::NotificationsManage)),
+        )
+        .route("/api/admin/backups", get(list_backups).post(start_backup).route_layer(guard(Permission::BackupsManage)))
+        .route("/api/admin/backups/:backup_id", get(backup_progress).route_layer(guard(Permission::BackupsManage)))
+        .route("/api/admin/exports", get(list_exports).post(start_export).route_layer(guard(Permission::EventsExport)))
+        .route("/api/admin/exports/:export_id", get(export_progress).route_layer(guard(Permission::EventsExport)))
+        .route("/api/admin/warehouse", get(warehouse_status).route_layer(guard(Permission::EventsExport)))
+        .route("/api/admin/warehouse/dead-letters", get(list_dead_letters).route_layer(guard(Permission::EventsExport)))
+        .route("/api/admin/warehouse/dead-letters/:dead_letter_id/replay", post(replay_dead_letter).route_layer(guard(Permission::EventsExport)))
+        .route("/api/admin/event-dead-letters", get(list_event_dead_letters).route_layer(guard(Permission::EventsExport)))
+        .route("/api/admin/event-dead-letters/replay", post(replay_event_dead_letters).route_layer(guard(Permission::EventsExport)))
+        .route("/api/admin/outbox", get(list_outbox).route_layer(guard(Permission::EventsExport)))
+        .route("/api/admin/outbox/:message_id/retry", post(retry_outbox_message).route_layer(guard(Permission::EventsExport)))
+        .route("/api/admin/database/index-report", get(index_report).route_layer(guard(Permission::DatabaseRead)))
+        .route("/api/admin/revenue/daily", get(revenue_daily).route_layer(guard(Permission::FinanceRead)))
+        .route("/api/admin/revenue/ledger", get(revenue_ledger).route_layer(guard(Permission::FinanceRead)))
+        .route("/api/admin/promo-codes", get(list_promo_codes).post(create_promo_code).route_layer(guard(Permission::PromosManage)))
+        .route("/api/admin/promo-codes/:code/active", put(set_promo_code_active).route_layer(guard(Permission::PromosManage)))
+        .route("/api/admin/promo-codes/:code/redemptions", get(list_promo_redemptions).route_layer(guard(Permission::PromosManage)))
+        .layer(DefaultBodyLimit::max(MAX_IMPORT_BYTES))
+        .layer(middleware::from_fn(tenant_scope))
+        .with_state(data_service)
+}
+
+// Runs the request in the scope of the tenant it names
+async fn tenant_scope(request: Request, next: Next) -> Response {
+    let requested = request.headers().get(TENANT_HEADER).and_then(|h| h.to_str().ok()).map(str::to_string);
+    let tenant = match requested {
+        None => TenantManager::default_tenant(),
+        Some(tenant_id) => match TenantManager::get(&tenant_id) {
+            Some(tenant) => tenant,
+            None => {
+                let error = ApiError::new("TENANT_UNKNOWN", "VALIDATION_ERROR", "x-tenant-id", "Unknown tenant")
+                    .with_details(json!({ "tenant_id": tenant_id }));
+                return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+            }
+        },
+    };
+    TenantManager::scope(tenant, next.run(request)).await
+}
+
+#[derive(Clone)]
+struct AccessCheck {
+    data_service: Arc<dyn DataStore>,
+    permission: Permission,
+}
+
+// Authenticates the bearer token and checks the route's permission. The
+// caller's AdminIdentity is passed on to the handler as a request extension.
+async fn check_access(State(check): State<AccessCheck>, mut request: Request, next: Next) -> Response {
+    let token = request.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "));
+    let target = format!("{} {}", request.method(), request.uri().path());
+    let Some(identity) = Rbac::authenticate(&*check.data_service, token).await else {
+        warn!("🚫 Refused admin request to {}", target);
+        let error = ApiError::new("ADMIN_AUTH_REQUIRED", "AUTHENTICATION_ERROR", "authorization", "Admin authentication required");
+        return (StatusCode::UNAUTHORIZED, Json(error)).into_response();
+    };
+    if !Rbac::authorize(&*check.data_service, &identity, check.permission, &target).await {
+        return (StatusCode::FORBIDDEN, Json(permission_denied(&identity, check.permission))).into_response();
+    }
+    request.extensions_mut().insert(identity);
+    next.run(request).await
+}
+
+fn permission_denied(identity: &AdminIdentity, permission: Permission) -> ApiError {
+    ApiError::new("ADMIN_PERMISSION_DENIED", "AUTHORIZATION_ERROR", "role", "Your role does not allow this action")
+        .with_details(json!({ "role": identity.role.as_str(), "required_permission": permission.as_str() }))
+}
+
+// One CSV row. Columns other than mobile_no are optional and unknown columns
+// are ignored, so an export can be imported as-is.
+#[derive(Debug, Deserialize)]
+struct ImportRow {
+    mobile_no: String,
+    full_name: Option<String>,
+    email: Option<String>,
+    state: Option<String>,
+    language_code: Option<String>,
+    language_name: Option<String>,
+    region_code: Option<String>,
+    timezone: Option<String>,
+    referral_code: Option<String>,
+    device_id: Option<String>,
+    fcm_token: Option<String>,
+    created_at: Option<String>,     // RFC 3339; defaults to the import time
+    is_active: Option<bool>,
+}
+
+impl ImportRow {
+    fn into_user(self) -> Result<UserRegister, (&'static str, String)> {
+        let mobile_no = self.mobile_no.trim().to_string();
+        if mobile_no.len() < 10 || mobile_no.len() > 15 || !mobile_no.chars().all(|c| c.is_ascii_digit()) {
+            return Err(("mobile_no", "mobile_no must be 10 to 15 digits".to_string()));
+        }
+        let email = non_empty(self.email);
+        if email.as_deref().is_some_and(|e| !e.contains('@')) {
+            return Err(("email", "email is not a valid address".to_string()));
+        }
+        let created_at = match non_empty(self.created_at) {
+            Some(value) => match chrono::DateTime::parse_from_rfc3339(&value) {
+                Ok(parsed) => Some(bson::DateTime::from_millis(parsed.timestamp_millis())),
+                Err(_) => return Err(("created_at", format!("created_at must be an RFC 3339 timestamp, got {}", value))),
+            },
+            None => None,
+        };
+
+        // user_number is assigned by the store
+        let mut user = UserRegister::new(
+            mobile_no,
+            non_empty(self.device_id).unwrap_or_else(|| "unknown".to_string()),
+            non_empty(self.fcm_token).unwrap_or_else(|| "unknown".to_string()),
+            email,
+            0,
+        );
+        user.full_name = non_empty(self.full_name);
+        user.state = non_empty(self.state);
+        user.language_code = non_empty(self.language_code);
+        user.language_name = non_empty(self.language_name);
+        user.region_code = non_empty(self.region_code);
+        user.timezone = non_empty(self.timezone);
+        user.referral_code = non_empty(self.referral_code);
+        user.is_active = self.is_active.unwrap_or(true);
+        user.last_login_at = None;
+        if let Some(created_at) = created_at {
+            user.created_at = created_at;
+        }
+        Ok(user)
+    }
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+#[derive(Deserialize)]
+struct MobileChangeOverride {
+    new_mobile_no: String,
+    reason: Option<String>,
+}
+
+// Support override for users who lost the old number: moves the account as a
+// confirmed account:change_mobile would, recorded with the operator and reason
+async fn change_user_mobile(
+    State(data_service): State<Arc<dyn DataStore>>,
+    Extension(identity): Extension<AdminIdentity>,
+    Path(user_id): Path<String>,
+    Json(body): Json<MobileChangeOverride>,
+) -> Response {
+    let new_mobile_no = body.new_mobile_no.trim();
+    if !new_mobile_no.chars().all(|c| c.is_ascii_digit()) || !(10..=15).contains(&new_mobile_no.len()) {
+        let error = ApiError::new("INVALID_FORMAT", "VALIDATION_ERROR", "new_mobile_no", "new_mobile_no must be 10 to 15 digits");
+        return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+    }
+    let Some(reason) = non_empty(body.reason) else {
+        let error = ApiError::new("INVALID_REASON", "VALIDATION_ERROR", "reason", "A reason is required");
+        return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+    };
+    let user = match data_service.get_user_by_id(&user_id).await {
+        Ok(Some(user)) => user,
+        Ok(None) => {
+            let error = ApiError::new("USER_NOT_FOUND", "VALIDATION_ERROR", "user_id", "No such user");
+            return (StatusCode::NOT_FOUND, Json(error)).into_response();
+        }
+        Err(e) => {
+            error!("❌ Failed to look up user {}: {}", user_id, e);
+            let error = ApiError::system("MOBILE_CHANGE_FAILED", "user_id", "Failed to change the mobile number", &e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response();
+        }
+    };
+    let audit = MobileChangeAudit {
+        id: None,
+        request_id: None,
+        change_id: None,
+        user_id: user_id.clone(),
+        old_mobile_no: user.mobile_no.clone(),
+        new_mobile_no: new_mobile_no.to_string(),
+        method: "admin".to_string(),
+        operator_id: Some(identity.operator_id.clone()),
+        reason: Some(reason),
+        changed_at: bson::DateTime::now(),
+    };
+    match data_service.change_user_mobile(audit).await {
+        Ok(true) => {
+            info!("🛠️ {} moved user {} from mobile {} to {}", identity.operator_id, user_id, user.mobile_no, new_mobile_no);
+            Json(ApiResponse::success("admin:user:mobile_changed", json!({
+                "user_id": user_id,
+                "old_mobile_no": user.mobile_no,
+                "mobile_no": new_mobile_no
+            }))).into_response()
+        }
+        Ok(false) => {
+            let error = ApiError::new("MOBILE_IN_USE", "VALIDATION_ERROR", "new_mobile_no", "This mobile number already belongs to an account");
+            (StatusCode::CONFLICT, Json(error)).into_response()
+        }
+        Err(e) => {
+            error!("❌ Failed to change mobile of user {}: {}", user_id, e);
+            let error = ApiError::system("MOBILE_CHANGE_FAILED", "new_mobile_no", "Failed to change the mobile number", &e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TwoStepResetRequest {
+    reason: Option<String>,
+}
+
+// Recovery for users who lost their PIN or authenticator app, once support
+// has confirmed who they are. The user can set up a new factor afterwards.
+async fn reset_two_step(
+    State(data_service): State<Arc<dyn DataStore>>,
+    Extension(identity): Extension<AdminIdentity>,
+    Path(user_id): Path<String>,
+    Json(body): Json<TwoStepResetRequest>,
+) -> Response {
+    let Some(reason) = non_empty(body.reason) else {
+        let error = ApiError::new("INVALID_REASON", "VALIDATION_ERROR", "reason", "A reason is required");
+        return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+    };
+    let user = match data_service.get_user_by_id(&user_id).await {
+        Ok(Some(user)) => user,
+        Ok(None) => {
+            let error = ApiError::new("USER_NOT_FOUND", "VALIDATION_ERROR", "user_id", "No such user");
+            return (StatusCode::NOT_FOUND, Json(error)).into_response();
+        }
+        Err(e) => {
+            error!("❌ Failed to look up user {}: {}", user_id, e);
+            let error = ApiError::system("TWO_STEP_RESET_FAILED", "user_id", "Failed to reset two-step verification", &e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response();
+        }
+    };
+    let Some(factor) = user.two_step else {
+        let error = ApiError::new("TWO_STEP_NOT_ENABLED", "VALIDATION_ERROR", "user_id", "The user has no two-step verification");
+        return (StatusCode::NOT_FOUND, Json(error)).into_response();
+    };
+    let reset = TwoStepReset {
+        id: None,
+        user_id: user_id.clone(),
+        mobile_no: user.mobile_no.clone(),
+        method: factor.method,
+        operator_id: identity.operator_id.clone(),
+        reason,
+        reset_at: bson::DateTime::now(),
+    };
+    let result = async {
+        data_service.update_two_step(&user.mobile_no, None).await?;
+        data_service.store_two_step_reset(reset).await
+    }.await;
+    match result {
+        Ok(()) => {
+            info!("🛠️ {} reset two-step verification of user {}", identity.operator_id, user_id);
+            Json(ApiResponse::success("admin:user:two_step_reset", json!({
+                "user_id": user_id
+            }))).into_response()
+        }
+        Err(e) => {
+            error!("❌ Failed to reset two-step verification of user {}: {}", user_id, e);
+            let error = ApiError::system("TWO_STEP_RESET_FAILED", "user_id", "Failed to reset two-step verification", &e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TimelineQuery {
+    before: Option<chrono::DateTime<chrono::Utc>>,  // Entries strictly older; next_before of the previous page
+    kinds: Option<String>,                          // Comma-separated, e.g. "login,error"; every kind when absent
+    page_size: Option<i64>,
+}
+
+// Everything stored about a user in one feed, newest first, so support does
+// not have to query each collection. Pages by time: pass next_before on.
+async fn user_timeline(State(data_service): State<Arc<dyn DataStore>>, Path(user_id): Path<String>, Query(query): Query<TimelineQuery>) -> Response {
+    let mut sources = Vec::new();
+    for kind in query.kinds.as_deref().unwrap_or_default().split(',').map(str::trim).filter(|k| !k.is_empty()) {
+        let Some(source) = TimelineSource::by_kind(kind) else {
+            let error = ApiError::new("INVALID_TIMELINE_KIND", "VALIDATION_ERROR", "kinds", "Unknown timeline kind")
+                .with_details(json!({
+                    "received_value": kind,
+                    "allowed_values": TIMELINE_SOURCES.iter().map(|s| s.kind).collect::<Vec<_>>()
+                }));
+            return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+        };
+        sources.push(source);
+    }
+    if sources.is_empty() {
+        sources = TIMELINE_SOURCES.iter().collect();
+    }
+    let page_size = query.page_size.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
+    let user = match data_service.get_user_by_id(&user_id).await {
+        Ok(Some(user)) => user,
+        Ok(None) => {
+            let error = ApiError::new("USER_NOT_FOUND", "VALIDATION_ERROR", "user_id", "No such user");
+            return (StatusCode::NOT_FOUND, Json(error)).into_response();
+        }
+        Err(e) => {
+            error!("❌ Failed to look up user {}: {}", user_id, e);
+            let error = ApiError::system("TIMELINE_FETCH_FAILED", "user_id", "Failed to load the user timeline", &e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response();
+        }
+    };
+    let before = query.before.map(|before| bson::DateTime::from_millis(before.timestamp_millis()));
+    match data_service.user_timeline(&user, &sources, before, page_size).await {
+        Ok(entries) => {
+            let rfc3339 = |date: bson::DateTime| date.try_to_rfc3339_string().unwrap_or_default();
+            // A short page is the last one
+            let next_before = entries.last().filter(|_| entries.len() as i64 == page_size).map(|entry| rfc3339(entry.at));
+            let entries: Vec<serde_json::Value> = entries.into_iter().map(|entry| json!({
+                "kind": entry.kind,
+                "source": entry.source,
+                "at": rfc3339(entry.at),
+                "details": entry.details,
+            })).collect();
+            Json(ApiResponse::success("admin:user:timeline", json!({
+                "user_id": user.user_id,
+                "entries": entries,
+                "page_size": page_size,
+                "next_before": next_before
+            }))).into_response()
+        }
+        Err(e) => {
+            error!("❌ Failed to load the timeline of user {}: {}", user_id, e);
+            let error = ApiError::system("TIMELINE_FETCH_FAILED", "user_id", "Failed to load the user timeline", &e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+// Validates every row, skips mobile numbers seen earlier in the file or
+// already registered, and stores the rest. Invalid rows do not stop the import.
+async fn import_users(State(data_service): State<Arc<dyn DataStore>>, body: Bytes) -> Response {
+    let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::All).from_reader(body.as_ref());
+    let mut seen = HashSet::new();
+    let (mut rows, mut imported, mut existing, mut duplicates, mut invalid) = (0, 0, 0, 0, 0);
+    let mut errors = Vec::new();
+
+    for (index, record) in reader.deserialize::<ImportRow>().enumerate() {
+        rows += 1;
+        let line = index + 2;   // After the header row
+        let user = match record.map_err(|e| ("row", e.to_string())).and_then(ImportRow::into_user) {
+            Ok(user) => user,
+            Err((field, message)) => {
+                invalid += 1;
+                if errors.len() < MAX_REPORTED_ERRORS {
+                    errors.push(json!({ "line": line, "field": field, "message": message }));
+                }
+                continue;
+            }
+        };
+        if !seen.insert(user.mobile_no.clone()) {
+            duplicates += 1;
+            continue;
+        }
+        let mobile_no = user.mobile_no.clone();
+        match data_service.import_user(user).await {
+            Ok(Some(_)) => imported += 1,
+            Ok(None) => existing += 1,
+            Err(e) => {
+                error!("❌ User import stopped at line {} ({}): {}", line, mobile_no, e);
+                let error = ApiError::system("USER_IMPORT_FAILED", "body", "Import stopped due to a storage error", &e)
+                    .with_details(json!({ "error": e.to_string(), "line": line, "imported": imported }));
+                return (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response();
+            }
+        }
+    }
+
+    info!("📥 User import: {} rows, {} imported, {} already registered, {} duplicates, {} invalid",
+        rows, imported, existing, duplicates, invalid);
+    Json(ApiResponse::success("admin:users:import", json!({
+        "rows": rows,
+        "imported": imported,
+        "skipped_existing": existing,
+        "skipped_duplicates": duplicates,
+        "invalid": invalid,
+        "errors": errors
+    }))).into_response()
+}
+
+// Query string of the search and export routes
+#[derive(Debug, Deserialize)]
+struct UserQuery {
+    q: Option<String>,          // Mobile number prefix, or full name prefix
+    is_active: Option<bool>,
+    is_banned: Option<bool>,
+    state: Option<String>,
+    language_code: Option<String>,
+    created_after: Option<chrono::DateTime<chrono::Utc>>,
+    created_before: Option<chrono::DateTime<chrono::Utc>>,
+    page: Option<u64>,          // Search only; starts at 0
+    page_size: Option<i64>,     // Search only
+    format: Option<String>,     // Export only: csv (default) or jsonl
+}
+
+impl UserQuery {
+    fn filter(&self) -> UserFilter {
+        UserFilter {
+            text: self.q.clone(),
+            is_active: self.is_active,
+            is_banned: self.is_banned,
+            state: self.state.clone(),
+            language_code: self.language_code.clone(),
+            created_after: self.created_after,
+            created_before: self.created_before,
+        }
+    }
+}
+
+// One page of matching users, newest first
+async fn search_users(State(data_service): State<Arc<dyn DataStore>>, Query(query): Query<UserQuery>) -> Response {
+    let page = query.page.unwrap_or(0);
+    let page_size = query.page_size.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let filter = query.filter();
+    let (users, total) = match data_service.search_users(&filter, page, page_size).await {
+        Ok(result) => result,
+        Err(e) => {
+            error!("❌ User search failed: {}", e);
+            let error = ApiError::system("USER_SEARCH_FAILED", "query", "Failed to search users", &e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response();
+        }
+    };
+    let users: Vec<UserRow> = users.into_iter().map(UserRow::from).collect();
+    Json(ApiResponse::success("admin:users:search", json!({
+        "users": users,
+        "page": page,
+        "page_size": page_size,
+        "total": total,
+        "has_more": (page + 1) * (page_size as u64) < total
+    }))).into_response()
+}
+
+// Search results and export columns; CSV and JSONL share them
+#[derive(Debug, Serialize)]
+struct UserRow {
+    user_id: String,
+    user_number: u64,
+    mobile_no: String,
+    full_name: Option<String>,
+    email: Option<String>,
+    state: Option<String>,
+    language_code: Option<String>,
+    language_name: Option<String>,
+    region_code: Option<String>,
+    timezone: Option<String>,
+    referral_code: Option<String>,
+    referred_by: Option<String>,
+    device_id: String,
+    fcm_token: String,
+    created_at: String,
+    last_login_at: Option<String>,
+    total_logins: i32,
+    is_active: bool,
+    is_banned: bool,
+}
+
+impl From<UserRegister> for UserRow {
+    fn from(user: UserRegister) -> Self {
+        let rfc3339 = |date: bson::DateTime| date.try_to_rfc3339_string().unwrap_or_default();
+        Self {
+            user_id: user.user_id,
+            user_number: user.user_number,
+            mobile_no: user.mobile_no,
+            full_name: user.full_name,
+            email: user.email,
+            state: user.state,
+            language_code: user.language_code,
+            language_name: user.language_name,
+            region_code: user.region_code,
+            timezone: user.timezone,
+            referral_code: user.referral_code,
+            referred_by: user.referred_by,
+            device_id: user.device_id,
+            fcm_token: user.fcm_token,
+            created_at: rfc3339(user.created_at),
+            last_login_at: user.last_login_at.map(rfc3339),
+            total_logins: user.total_logins,
+            is_active: user.is_active,
+            is_banned: user.is_banned,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum ExportFormat {
+    Csv,
+    Jsonl,
+}
+
+impl ExportFormat {
+    fn encode(self, row: &UserRow, with_header: bool) -> Result<Bytes, Box<dyn std::error::Error + Send + Sync>> {
+        match self {
+            ExportFormat::Csv => {
+                let mut writer = csv::WriterBuilder::new().has_headers(with_header).from_writer(Vec::new());
+                writer.serialize(row)?;
+                Ok(Bytes::from(writer.into_inner().map_err(|e| e.to_string())?))
+            }
+            ExportFormat::Jsonl => {
+                let mut line = serde_json::to_vec(row)?;
+                line.push(b'\n');
+                Ok(Bytes::from(line))
+            }
+        }
+    }
+}
+
+// Streams matching users without loading them all into memory
+async fn export_users(State(data_service): State<Arc<dyn DataStore>>, Query(query): Query<UserQuery>) -> Response {
+    let (format, content_type, extension) = match query.format.as_deref().unwrap_or("csv") {
+        "csv" => (ExportFormat::Csv, "text/csv", "csv"),
+        "jsonl" => (ExportFormat::Jsonl, "application/x-ndjson", "jsonl"),
+        other => {
+            let error = ApiError::new("INVALID_EXPORT_FORMAT", "VALIDATION_ERROR", "format", "format must be csv or jsonl")
+                .with_details(json!({ "received_value": other }));
+            return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+        }
+    };
+    let filter = query.filter();
+    let users = match data_service.export_users(&filter).await {
+        Ok(users) => users,
+        Err(e) => {
+            error!("❌ User export failed: {}", e);
+            let error = ApiError::system("USER_EXPORT_FAILED", "query", "Failed to read users", &e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response();
+        }
+    };
+    info!("📤 Exporting users as {} ({:?})", extension, filter);
+
+    // The CSV header goes with the first row
+    let body = users
+        .enumerate()
+        .map(move |(index, user)| user.and_then(|user| format.encode(&UserRow::from(user), index == 0)));
+    (
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"users.{}\"", extension)),
+        ],
+        Body::from_stream(body),
+    ).into_response()
+}
+
+// Query string of the error analytics routes
+#[derive(Debug, Deserialize)]
+struct ErrorWindowQuery {
+    from: Option<chrono::DateTime<chrono::Utc>>,
+    to: Option<chrono::DateTime<chrono::Utc>>,
+    group_by: Option<String>,   // Stats only: error_code (default), error_type or field
+    bucket: Option<String>,     // Stats only: hour (default) or day
+    limit: Option<i64>,         // Offenders only
+}
+
+impl ErrorWindowQuery {
+    // [from, to), defaulting to the last DEFAULT_ERROR_WINDOW_HOURS; None when out of bounds
+    fn window(&self) -> Option<(chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>)> {
+        let to = self.to.unwrap_or_else(chrono::Utc::now);
+        let from = self.from.unwrap_or(to - chrono::Duration::hours(DEFAULT_ERROR_WINDOW_HOURS));
+        (from < to && to - from <= chrono::Duration::days(MAX_ERROR_WINDOW_DAYS)).then_some((from, to))
+    }
+}
+
+fn invalid_time_range(query: &ErrorWindowQuery) -> Response {
+    let error = ApiError::new("INVALID_TIME_RANGE", "VALIDATION_ERROR", "from",
+        &format!("from must be before to, at most {} days apart", MAX_ERROR_WINDOW_DAYS))
+        .with_details(json!({ "from": query.from, "to": query.to }));
+    (StatusCode::BAD_REQUEST, Json(error)).into_response()
+}
+
+fn rfc3339_millis(ms: i64) -> String {
+    bson::DateTime::from_millis(ms).try_to_rfc3339_string().unwrap_or_default()
+}
+
+// Connection error counts per group value, in total and per time bucket
+async fn error_stats(State(data_service): State<Arc<dyn DataStore>>, Query(query): Query<ErrorWindowQuery>) -> Response {
+    let Some((from, to)) = query.window() else {
+        return invalid_time_range(&query);
+    };
+    let group_by = query.group_by.as_deref().unwrap_or("error_code");
+    let Some(grouping) = ErrorGrouping::parse(group_by) else {
+        let error = ApiError::new("INVALID_GROUP_BY", "VALIDATION_ERROR", "group_by", "group_by must be error_code, error_type or field")
+            .with_details(json!({ "received_value": group_by }));
+        return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+    };
+    let bucket = query.bucket.as_deref().unwrap_or("hour");
+    let bucket_ms = match bucket {
+        "hour" => 60 * 60 * 1000,
+        "day" => 24 * 60 * 60 * 1000,
+        other => {
+            let error = ApiError::new("INVALID_BUCKET", "VALIDATION_ERROR", "bucket", "bucket must be hour or day")
+                .with_details(json!({ "received_value": other }));
+            return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+        }
+    };
+
+    let stats_query = ErrorStatsQuery { from, to, group_by: grouping, bucket_ms };
+    let buckets = match data_service.connection_error_counts(&stats_query).await {
+        Ok(buckets) => buckets,
+        Err(e) => {
+            error!("❌ Connection error stats failed: {}", e);
+            let error = ApiError::system("ERROR_STATS_FAILED", "query", "Failed to aggregate connection errors", &e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response();
+        }
+    };
+
+    let mut totals: Vec<(String, u64)> = Vec::new();
+    for bucket in &buckets {
+        match totals.iter_mut().find(|(key, _)| *key == bucket.key) {
+            Some((_, count)) => *count += bucket.count,
+            None => totals.push((bucket.key.clone(), bucket.count)),
+        }
+    }
+    totals.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    Json(ApiResponse::success("admin:errors:stats", json!({
+        "from": from.to_rfc3339(),
+        "to": to.to_rfc3339(),
+        "group_by": grouping.field(),
+        "bucket": bucket,
+        "total": totals.iter().map(|(_, count)| count).sum::<u64>(),
+        "totals": totals.iter().map(|(key, count)| json!({ "key": key, "count": count })).collect::<Vec<_>>(),
+        "series": buckets.iter().map(|b| json!({
+            "bucket_start": rfc3339_millis(b.bucket_start),
+            "key": b.key,
+            "count": b.count
+        })).collect::<Vec<_>>()
+    }))).into_response()
+}
+
+// Devices and app versions with the most connection errors
+async fn error_offenders(State(data_service): State<Arc<dyn DataStore>>, Query(query): Query<ErrorWindowQuery>) -> Response {
+    let Some((from, to)) = query.window() else {
+        return invalid_time_range(&query);
+    };
+    let limit = query.limit.unwrap_or(DEFAULT_OFFENDER_LIMIT).clamp(1, MAX_OFFENDER_LIMIT);
+    match data_service.connection_error_offenders(from, to, limit).await {
+        Ok(offenders) => Json(ApiResponse::success("admin:errors:offenders", json!({
+            "from": from.to_rfc3339(),
+            "to": to.to_rfc3339(),
+            "devices": offenders.devices,
+            "app_versions": offenders.app_versions
+        }))).into_response(),
+        Err(e) => {
+            error!("❌ Connection error offenders failed: {}", e);
+            let error = ApiError::system("ERROR_STATS_FAILED", "query", "Failed to aggregate connection errors", &e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RetentionQuery {
+    #[serde(default)]
+    refresh: bool,
+}
+
+// Weekly registration cohorts with their D1/D7/D30 return rates, from the
+// daily report; `refresh=true` computes it again first
+async fn retention_report(State(data_service): State<Arc<dyn DataStore>>, Query(query): Query<RetentionQuery>) -> Response {
+    let report = if query.refresh {
+        RetentionManager::generate(&*data_service).await
+    } else {
+        RetentionManager::latest(&*data_service).await
+    };
+    match report {
+        Ok(report) => Json(ApiResponse::success("admin:analytics:retention", json!({
+            "report_date": report.report_date,
+            "generated_at": report.generated_at.try_to_rfc3339_string().unwrap_or_default(),
+            "weeks": report.weeks,
+            "cohorts": report.cohorts
+        }))).into_response(),
+        Err(e) => {
+            error!("❌ Retention report failed: {}", e);
+            let error = ApiError::system("RETENTION_REPORT_FAILED", "query", "Failed to compute the retention report", &e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+// This server's deployment health score for rollout automation
+async fn health_score() -> Response {
+    Json(ApiResponse::success("admin:health_score", HealthScoreManager::latest())).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct SessionMetricsQuery {
+    minutes: Option<i64>,
+}
+
+// Concurrent sessions of the tenant: this server's live counts, and every
+// server's samples added up per minute with the peak among them
+async fn session_metrics(State(data_service): State<Arc<dyn DataStore>>, Query(query): Query<SessionMetricsQuery>) -> Response {
+    let minutes = query.minutes.unwrap_or(DEFAULT_SESSION_MINUTES).clamp(1, MAX_SESSION_MINUTES);
+    let to = chrono::Utc::now();
+    let from = to - chrono::Duration::minutes(minutes);
+    let samples = match data_service.session_samples(bson::DateTime::from_millis(from.timestamp_millis()), bson::DateTime::from_millis(to.timestamp_millis())).await {
+        Ok(samples) => samples,
+        Err(e) => {
+            error!("❌ Failed to load session metrics: {}", e);
+            let error = ApiError::system("SESSION_METRICS_FAILED", "query", "Failed to load the session metrics", &e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response();
+        }
+    };
+    let per_minute: Vec<serde_json::Value> = samples.chunk_by(|a, b| a.minute == b.minute).map(|minute| json!({
+        "minute": minute[0].minute.try_to_rfc3339_string().unwrap_or_default(),
+        "servers": minute.len(),
+        "sessions": minute.iter().map(|s| s.sessions).sum::<u64>(),
+        "peak_sessions": minute.iter().map(|s| s.peak_sessions).sum::<u64>(),
+        "connections": minute.iter().map(|s| s.connections).sum::<u64>(),
+    })).collect();
+    // Servers peak at different moments of a minute, so the sum is an upper bound
+    let peak = per_minute.iter().max_by_key(|m| m["peak_sessions"].as_u64().unwrap_or(0)).map(|m| json!({
+        "minute": m["minute"],
+        "sessions": m["peak_sessions"],
+    }));
+    Json(ApiResponse::success("admin:sessions", json!({
+        "server": SessionMetricsManager::live().await,
+        "latest": per_minute.last(),
+        "peak": peak,
+        "minutes": per_minute,
+    }))).into_response()
+}
+
+#[derive(Debug, Serialize)]
+struct OperatorRow {
+    operator_id: String,
+    name: String,
+    role: String,
+    created_by: String,
+    created_at: String,
+    updated_at: String,
+}
+
+impl From<AdminOperator> for OperatorRow {
+    fn from(operator: AdminOperator) -> Self {
+        Self {
+            operator_id: operator.operator_id,
+            name: operator.name,
+            role: operator.role,
+            created_by: operator.created_by,
+            created_at: operator.created_at.try_to_rfc3339_string().unwrap_or_default(),
+            updated_at: operator.updated_at.try_to_rfc3339_string().unwrap_or_default(),
+        }
+    }
+}
+
+fn invalid_role(role: &str) -> Response {
+    let error = ApiError::new("INVALID_ROLE", "VALIDATION_ERROR", "role", "role must be admin, support or moderator")
+        .with_details(json!({ "received_value": role }));
+    (StatusCode::BAD_REQUEST, Json(error)).into_response()
+}
+
+async fn list_operators(State(data_service): State<Arc<dyn DataStore>>) -> Response {
+    match data_service.list_admin_operators().await {
+        Ok(operators) => {
+            let operators: Vec<OperatorRow> = operators.into_iter().map(OperatorRow::from).collect();
+            Json(ApiResponse::success("admin:operators", json!({ "operators": operators }))).into_response()
+        }
+        Err(e) => {
+            error!("❌ Failed to list admin operators: {}", e);
+            let error = ApiError::system("OPERATOR_LIST_FAILED", "operators", "Failed to list operators", &e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateOperator {
+    name: String,
+    role: String,
+}
+
+// The token is only returned here; listing operators never shows it
+async fn create_operator(
+    State(data_service): State<Arc<dyn DataStore>>,
+    Extension(identity): Extension<AdminIdentity>,
+    Json(body): Json<CreateOperator>,
+) -> Response {
+    let Some(role) = AdminRole::parse(&body.role) else {
+        return invalid_role(&body.role);
+    };
+    let name = body.name.trim();
+    if name.is_empty() {
+        let error = ApiError::new("INVALID_OPERATOR_NAME", "VALIDATION_ERROR", "name", "name cannot be empty");
+        return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+    }
+
+    let (operator, token) = Rbac::new_operator(name, role, &identity.operator_id);
+    if let Err(e) = data_service.create_admin_operator(operator.clone()).await {
+        error!("❌ Failed to add admin operator {}: {}", name, e);
+        let error = ApiError::system("OPERATOR_CREATE_FAILED", "operators", "Failed to add operator", &e);
+        return (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response();
+    }
+    info!("🛠️ {} added admin operator {} as {}", identity.operator_id, operator.operator_id, role.as_str());
+    (StatusCode::CREATED, Json(ApiResponse::success("admin:operator:created", json!({
+        "operator": OperatorRow::from(operator),
+        "token": token
+    })))).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct SetRole {
+    role: String,
+}
+
+async fn set_operator_role(
+    State(data_service): State<Arc<dyn DataStore>>,
+    Extension(identity): Extension<AdminIdentity>,
+    Path(operator_id): Path<String>,
+    Json(body): Json<SetRole>,
+) -> Response {
+    let Some(role) = AdminRole::parse(&body.role) else {
+        return invalid_role(&body.role);
+    };
+    match data_service.set_admin_operator_role(&operator_id, role.as_str()).await {
+        Ok(true) => {
+            info!("🛠️ {} set admin operator {} to {}", identity.operator_id, operator_id, role.as_str());
+            Json(ApiResponse::success("admin:operator:role", json!({
+                "operator_id": operator_id,
+                "role": role.as_str()
+            }))).into_response()
+        }
+        Ok(false) => {
+            let error = ApiError::new("OPERATOR_NOT_FOUND", "VALIDATION_ERROR", "operator_id", "No operator with this id");
+            (StatusCode::NOT_FOUND, Json(error)).into_response()
+        }
+        Err(e) => {
+            error!("❌ Failed to set role of admin operator {}: {}", operator_id, e);
+            let error = ApiError::system("OPERATOR_UPDATE_FAILED", "role", "Failed to update operator role", &e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ImpersonationQuery {
+    user_id: Option<String>,
+    operator_id: Option<String>,
+    page: Option<u64>,          // Starts at 0
+    page_size: Option<i64>,
+}
+
+// Who watched whom, when, why and for how long; newest first
+async fn list_impersonations(State(data_service): State<Arc<dyn DataStore>>, Query(query): Query<ImpersonationQuery>) -> Response {
+    let page = query.page.unwrap_or(0);
+    let page_size = query.page_size.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    match data_service.list_impersonations(query.user_id.as_deref(), query.operator_id.as_deref(), page, page_size).await {
+        Ok(sessions) => {
+            let rfc3339 = |date: bson::DateTime| date.try_to_rfc3339_string().unwrap_or_default();
+            let sessions: Vec<serde_json::Value> = sessions.iter().map(|session| json!({
+                "session_id": session.session_id,
+                "operator_id": session.operator_id,
+                "user_id": session.user_id,
+                "reason": session.reason,
+                "disclosed": session.disclosed,
+                "started_at": rfc3339(session.started_at),
+                "expires_at": rfc3339(session.expires_at),
+                "ended_at": session.ended_at.map(rfc3339),
+                "end_reason": session.end_reason,
+                "events_mirrored": session.events_mirrored,
+            })).collect();
+            Json(ApiResponse::success("admin:impersonations", json!({
+                "impersonations": sessions,
+                "page": page,
+                "page_size": page_size
+            }))).into_response()
+        }
+        Err(e) => {
+            error!("❌ Failed to list impersonations: {}", e);
+            let error = ApiError::system("IMPERSONATION_LIST_FAILED", "impersonations", "Failed to list impersonations", &e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+async fn list_seasons(State(data_service): State<Arc<dyn DataStore>>) -> Response {
+    match data_service.list_seasons().await {
+        Ok(seasons) => {
+            let seasons: Vec<serde_json::Value> = seasons.iter().map(SeasonManager::season_view).collect();
+            Json(ApiResponse::success("admin:seasons", json!({ "seasons": seasons }))).into_response()
+        }
+        Err(e) => {
+            error!("❌ Failed to list seasons: {}", e);
+            let error = ApiError::system("SEASON_LIST_FAILED", "seasons", "Failed to list seasons", &e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateSeason {
+    name: String,
+    start_at: chrono::DateTime<chrono::Utc>,
+    end_at: chrono::DateTime<chrono::Utc>,
+    #[serde(default)]
+    rewards: Vec<SeasonReward>,
+}
+
+fn invalid_season(code: &str, field: &str, message: &str, details: serde_json::Value) -> Response {
+    let error = ApiError::new(code, "VALIDATION_ERROR", field, message).with_details(details);
+    (StatusCode::BAD_REQUEST, Json(error)).into_response()
+}
+
+// Schedules a season; the rollover job starts it at start_at. Seasons may not
+// overlap, and reward tiers must have ascending max_rank and positive coins.
+async fn create_season(
+    State(data_service): State<Arc<dyn DataStore>>,
+    Extension(identity): Extension<AdminIdentity>,
+    Json(body): Json<CreateSeason>,
+) -> Response {
+    let name = body.name.trim();
+    if name.is_empty() {
+        return invalid_season("INVALID_SEASON_NAME", "name", "name cannot be empty", json!({}));
+    }
+    if body.start_at >= body.end_at {
+        return invalid_season("INVALID_SEASON_DATES", "end_at", "end_at must be after start_at", json!({
+            "start_at": body.start_at.to_rfc3339(),
+            "end_at": body.end_at.to_rfc3339()
+        }));
+    }
+    if body.end_at <= chrono::Utc::now() {
+        return invalid_season("INVALID_SEASON_DATES", "end_at", "end_at must be in the future", json!({
+            "end_at": body.end_at.to_rfc3339()
+        }));
+    }
+    let mut previous_rank = 0;
+    for tier in &body.rewards {
+        if tier.max_rank <= previous_rank || tier.coins <= 0 {
+            return invalid_season("INVALID_SEASON_REWARDS", "rewards", "reward tiers need ascending max_rank and positive coins", json!({
+                "tier": tier
+            }));
+        }
+        previous_rank = tier.max_rank;
+    }
+
+    let start_at = bson::DateTime::from_millis(body.start_at.timestamp_millis());
+    let end_at = bson::DateTime::from_millis(body.end_at.timestamp_millis());
+    let existing = match data_service.list_seasons().await {
+        Ok(seasons) => seasons,
+        Err(e) => {
+            error!("❌ Failed to list seasons: {}", e);
+            let error = ApiError::system("SEASON_CREATE_FAILED", "seasons", "Failed to check existing seasons", &e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response();
+        }
+    };
+    if let Some(overlap) = existing.iter().find(|s| s.start_at < end_at && start_at < s.end_at) {
+        let error = ApiError::new("SEASON_OVERLAP", "VALIDATION_ERROR", "start_at", "Season overlaps an existing season")
+            .with_details(json!({ "season": SeasonManager::season_view(overlap) }));
+        return (StatusCode::CONFLICT, Json(error)).into_response();
+    }
+
+    let season = Season {
+        id: None,
+        season_id: uuid::Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext)).simple().to_string(),
+        name: name.to_string(),
+        start_at,
+        end_at,
+        rewards: body.rewards,
+        status: SeasonStatus::Scheduled,
+        created_by: identity.operator_id.clone(),
+        created_at: bson::DateTime::now(),
+        ended_at: None,
+    };
+    if let Err(e) = data_service.create_season(season.clone()).await {
+        error!("❌ Failed to create season {}: {}", name, e);
+        let error = ApiError::system("SEASON_CREATE_FAILED", "seasons", "Failed to create season", &e);
+        return (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response();
+    }
+    info!("🛠️ {} scheduled season {} ({})", identity.operator_id, season.name, season.season_id);
+    (StatusCode::CREATED, Json(ApiResponse::success("admin:season:created", json!({
+        "season": SeasonManager::season_view(&season)
+    })))).into_response()
+}
+
+const RISK_FLAG_STATUSES: [&str; 3] = ["open", "confirmed", "dismissed"];
+
+#[derive(Debug, Deserialize)]
+struct RiskFlagQuery {
+    status: Option<String>,     // Any status when absent
+    page: Option<u64>,          // Starts at 0
+    page_size: Option<i64>,
+}
+
+fn risk_flag_view(flag: &RiskFlag) -> serde_json::Value {
+    let rfc3339 = |date: bson::DateTime| date.try_to_rfc3339_string().unwrap_or_default();
+    json!({
+        "flag_id": flag.flag_id,
+        "user_id": flag.user_id,
+        "rule": flag.signal.rule,
+        "counterparty": flag.signal.counterparty,
+        "weight": flag.signal.weight,
+        "details": flag.signal.details,
+        "score": flag.score,
+        "status": flag.status,
+        "created_at": rfc3339(flag.created_at),
+        "resolved_by": flag.resolved_by,
+        "resolved_at": flag.resolved_at.map(rfc3339)
+    })
+}
+
+fn invalid_risk_status(status: &str) -> Response {
+    let error = ApiError::new("INVALID_RISK_STATUS", "VALIDATION_ERROR", "status", "Unknown flag status")
+        .with_details(json!({ "received_value": status, "allowed_values": RISK_FLAG_STATUSES }));
+    (StatusCode::BAD_REQUEST, Json(error)).into_response()
+}
+
+// The review queue, newest first
+async fn list_risk_flags(State(data_service): State<Arc<dyn DataStore>>, Query(query): Query<RiskFlagQuery>) -> Response {
+    if let Some(status) = query.status.as_deref().filter(|s| !RISK_FLAG_STATUSES.contains(s)) {
+        return invalid_risk_status(status);
+    }
+    let page = query.page.unwrap_or(0);
+    let page_size = query.page_size.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    match data_service.list_risk_flags(query.status.as_deref(), page, page_size).await {
+        Ok(flags) => {
+            let flags: Vec<serde_json::Value> = flags.iter().map(risk_flag_view).collect();
+            Json(ApiResponse::success("admin:risk:flags", json!({
+                "flags": flags,
+                "page": page,
+                "page_size": page_size
+            }))).into_response()
+        }
+        Err(e) => {
+            error!("❌ Failed to list risk flags: {}", e);
+            let error = ApiError::system("RISK_FLAG_LIST_FAILED", "flags", "Failed to list risk flags", &e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ResolveRiskFlag {
+    status: String,
+}
+
+// Closes an open flag. A dismissed finding is left out of later scores, so
+// the user's limit lifts at the next scan if nothing else holds it.
+async fn resolve_risk_flag(
+    State(data_service): State<Arc<dyn DataStore>>,
+    Extension(identity): Extension<AdminIdentity>,
+    Path(flag_id): Path<String>,
+    Json(body): Json<ResolveRiskFlag>,
+) -> Response {
+    if !["confirmed", "dismissed"].contains(&body.status.as_str()) {
+        return invalid_risk_status(&body.status);
+    }
+    match data_service.resolve_risk_flag(&flag_id, &body.status, &identity.operator_id).await {
+        Ok(true) => {
+            info!("🛠️ {} marked risk flag {} as {}", identity.operator_id, flag_id, body.status);
+            Json(ApiResponse::success("admin:risk:flag:resolved", json!({
+                "flag_id": flag_id,
+                "status": body.status
+            }))).into_response()
+        }
+        Ok(false) => {
+            let error = ApiError::new("RISK_FLAG_NOT_FOUND", "VALIDATION_ERROR", "flag_id", "No open flag with this id");
+            (StatusCode::NOT_FOUND, Json(error)).into_response()
+        }
+        Err(e) => {
+            error!("❌ Failed to resolve risk flag {}: {}", flag_id, e);
+            let error = ApiError::system("RISK_FLAG_UPDATE_FAILED", "flag_id", "Failed to resolve risk flag", &e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+// A user's score and signals from the latest scan
+async fn user_risk(State(data_service): State<Arc<dyn DataStore>>, Path(user_id): Path<String>) -> Response {
+    match data_service.get_user_risk(&user_id).await {
+        Ok(risk) => {
+            let rfc3339 = |date: bson::DateTime| date.try_to_rfc3339_string().unwrap_or_default();
+            Json(ApiResponse::success("admin:risk:user", json!({
+                "user_id": user_id,
+                "score": risk.as_ref().map_or(0, |r| r.score),
+                "limited": risk.as_ref().is_some_and(|r| r.limited),
+                "signals": risk.as_ref().map(|r| r.signals.clone()).unwrap_or_default(),
+                "updated_at": risk.map(|r| rfc3339(r.updated_at))
+            }))).into_response()
+        }
+        Err(e) => {
+            error!("❌ Failed to load risk of user {}: {}", user_id, e);
+            let error = ApiError::system("RISK_FETCH_FAILED", "user_id", "Failed to load user risk", &e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+const REPORT_STATUSES: [&str; 3] = ["open", "actioned", "dismissed"];
+
+#[derive(Debug, Deserialize)]
+struct ReportQuery {
+    status: Option<String>,     // Any status when absent
+    category: Option<String>,   // Any category when absent; chat flags are "harassment", "hate_speech" and "spam"
+    page: Option<u64>,          // Starts at 0
+    page_size: Option<i64>,
+}
+
+fn report_view(report: &UserReport) -> serde_json::Value {
+    let rfc3339 = |date: bson::DateTime| date.try_to_rfc3339_string().unwrap_or_default();
+    json!({
+        "report_id": report.report_id,
+        "reporter_user_id": report.reporter_user_id,
+        "reported_user_id": report.reported_user_id,
+        "category": report.category.as_str(),
+        "description": report.description,
+        "room_id": report.room_id,
+        "status": report.status,
+        "created_at": rfc3339(report.created_at),
+        "resolution": report.resolution,
+        "resolved_by": report.resolved_by,
+        "resolved_at": report.resolved_at.map(rfc3339)
+    })
+}
+
+fn sanction_view(sanction: &UserSanction) -> serde_json::Value {
+    let rfc3339 = |date: bson::DateTime| date.try_to_rfc3339_string().unwrap_or_default();
+    json!({
+        "sanction_id": sanction.sanction_id,
+        "user_id": sanction.user_id,
+        "kind": sanction.kind.as_str(),
+        "reason": sanction.reason,
+        "report_id": sanction.report_id,
+        "operator_id": sanction.operator_id,
+        "created_at": rfc3339(sanction.created_at),
+        "expires_at": sanction.expires_at.map(rfc3339),
+        "active": sanction.is_active(bson::DateTime::now()),
+        "lifted_by": sanction.lifted_by,
+        "lifted_at": sanction.lifted_at.map(rfc3339)
+    })
+}
+
+fn invalid_moderation(code: &str, field: &str, message: &str, details: serde_json::Value) -> Response {
+    let error = ApiError::new(code, "VALIDATION_ERROR", field, message).with_details(details);
+    (StatusCode::BAD_REQUEST, Json(error)).into_response()
+}
+
+// The report queue, oldest first so reports are handled in the order they came in
+async fn list_reports(State(data_service): State<Arc<dyn DataStore>>, Query(query): Query<ReportQuery>) -> Response {
+    if let Some(status) = query.status.as_deref().filter(|s| !REPORT_STATUSES.contains(s)) {
+        return invalid_moderation("INVALID_REPORT_STATUS", "status", "Unknown report status", json!({
+            "received_value": status, "allowed_values": REPORT_STATUSES
+        }));
+    }
+    let category = match query.category.as_deref() {
+        Some(category) => match ReportCategory::parse(category) {
+            Some(category) => Some(category),
+            None => {
+                let allowed: Vec<&str> = ReportCategory::ALL.iter().map(|c| c.as_str()).collect();
+                return invalid_moderation("INVALID_REPORT_CATEGORY", "category", "Unknown report category", json!({
+                    "received_value": category, "allowed_values": allowed
+                }));
+            }
+        },
+        None => None,
+    };
+    let page = query.page.unwrap_or(0);
+    let page_size = query.page_size.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    match data_service.list_user_reports(query.status.as_deref(), category, page, page_size).await {
+        Ok(reports) => {
+            let reports: Vec<serde_json::Value> = reports.iter().map(report_view).collect();
+            Json(ApiResponse::success("admin:reports", json!({
+                "reports": reports,
+                "page": page,
+                "page_size": page_size
+            }))).into_response()
+        }
+        Err(e) => {
+            error!("❌ Failed to list reports: {}", e);
+            let error = ApiError::system("REPORT_LIST_FAILED", "reports", "Failed to list reports", &e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ModerationAction {
+    action: String,                 // "dismiss" (reports only), "warn", "mute" or "ban"
+    duration_hours: Option<i64>,    // Required for mute and ban
+    reason: Option<String>,
+}
+
+// The sanction an action asks for and how long it lasts
+fn parse_sanction(body: &ModerationAction, allowed_actions: &[&str]) -> Result<(SanctionKind, Option<i64>), Response> {
+    let Some(kind) = SanctionKind::parse(&body.action) else {
+        return Err(invalid_moderation("INVALID_MODERATION_ACTION", "action", "Unknown moderation action", json!({
+            "received_value": body.action, "allowed_values": allowed_actions
+        })));
+    };
+    if kind == SanctionKind::Warn {
+        return Ok((kind, None));
+    }
+    match body.duration_hours {
+        Some(hours) if (1..=MAX_SANCTION_HOURS).contains(&hours) => Ok((kind, Some(hours))),
+        _ => Err(invalid_moderation("INVALID_SANCTION_DURATION", "duration_hours", "Mutes and bans need a duration in hours", json!({
+            "received_value": body.duration_hours, "min": 1, "max": MAX_SANCTION_HOURS
+        }))),
+    }
+}
+
+// Closes an open report. Unless it is dismissed the reported user is
+// sanctioned; either way the reporter hears the outcome in their inbox.
+async fn resolve_report(
+    State(data_service): State<Arc<dyn DataStore>>,
+    Extension(identity): Extension<AdminIdentity>,
+    Path(report_id): Path<String>,
+    Json(body): Json<ModerationAction>,
+) -> Response {
+    let sanction = match body.action.as_str() {
+        "dismiss" => None,
+        _ => match parse_sanction(&body, &["dismiss", "warn", "mute", "ban"]) {
+            Ok(sanction) => Some(sanction),
+            Err(response) => return response,
+        },
+    };
+    let report = match data_service.get_user_report(&report_id).await {
+        Ok(Some(report)) if report.status == "open" => report,
+        Ok(_) => {
+            let error = ApiError::new("REPORT_NOT_FOUND", "VALIDATION_ERROR", "report_id", "No open report with this id");
+            return (StatusCode::NOT_FOUND, Json(error)).into_response();
+        }
+        Err(e) => {
+            error!("❌ Failed to load report {}: {}", report_id, e);
+            let error = ApiError::system("REPORT_UPDATE_FAILED", "report_id", "Failed to resolve report", &e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response();
+        }
+    };
+
+    let status = if sanction.is_some() { "actioned" } else { "dismissed" };
+    match data_service.resolve_user_report(&report_id, status, &body.action, &identity.operator_id).await {
+        Ok(true) => {}
+        // Someone else closed it first
+        Ok(false) => {
+            let error = ApiError::new("REPORT_NOT_FOUND", "VALIDATION_ERROR", "report_id", "No open report with this id");
+            return (StatusCode::NOT_FOUND, Json(error)).into_response();
+        }
+        Err(e) => {
+            error!("❌ Failed to resolve report {}: {}", report_id, e);
+            let error = ApiError::system("REPORT_UPDATE_FAILED", "report_id", "Failed to resolve report", &e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response();
+        }
+    }
+
+    let mut applied = None;
+    if let Some((kind, duration_hours)) = sanction {
+        let reason = non_empty(body.reason.clone()).unwrap_or_else(|| report.category.as_str().to_string());
+        match ModerationManager::apply(&*data_service, &report.reported_user_id, kind, duration_hours, &reason, Some(&report_id), &identity.operator_id).await {
+            Ok(sanction) => applied = Some(sanction),
+            Err(e) => {
+                error!("❌ Failed to sanction user {} for report {}: {}", report.reported_user_id, report_id, e);
+                let error = ApiError::system("SANCTION_FAILED", "action", "Report closed but the sanction failed", &e);
+                return (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response();
+            }
+        }
+    }
+
+    // Reports the server filed itself have nobody to tell
+    if report.reporter_user_id != AUTO_MODERATOR {
+        let notification_type = if applied.is_some() { "report_actioned" } else { "report_dismissed" };
+        let data = json!({ "report_id": report_id, "status": status });
+        if let Err(e) = NotificationManager::dispatch(&*data_service, &report.reporter_user_id, NotificationCategory::System, notification_type, json!({}), data, Some(DeepLink::new("open_report", json!({ "report_id": report_id })))).await {
+            warn!("⚠️ Failed to notify user {} of report {}: {}", report.reporter_user_id, report_id, e);
+        }
+    }
+
+    info!("🛠️ {} resolved report {} with {}", identity.operator_id, report_id, body.action);
+    Json(ApiResponse::success("admin:report:resolved", json!({
+        "report_id": report_id,
+        "status": status,
+        "sanction": applied.as_ref().map(sanction_view)
+    }))).into_response()
+}
+
+// Sanction a user without a report, e.g. for something an operator saw
+async fn sanction_user(
+    State(data_service): State<Arc<dyn DataStore>>,
+    Extension(identity): Extension<AdminIdentity>,
+    Path(user_id): Path<String>,
+    Json(body): Json<ModerationAction>,
+) -> Response {
+    let (kind, duration_hours) = match parse_sanction(&body, &["warn", "mute", "ban"]) {
+        Ok(sanction) => sanction,
+        Err(response) => return response,
+    };
+    let Some(reason) = non_empty(body.reason.clone()) else {
+        return invalid_moderation("INVALID_SANCTION_REASON", "reason", "A reason is required", json!({}));
+    };
+    match data_service.get_user_by_id(&user_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            let error = ApiError::new("USER_NOT_FOUND", "VALIDATION_ERROR", "user_id", "No such user");
+            return (StatusCode::NOT_FOUND, Json(error)).into_response();
+        }
+        Err(e) => {
+            error!("❌ Failed to look up user {}: {}", user_id, e);
+            let error = ApiError::system("SANCTION_FAILED", "user_id", "Failed to sanction user", &e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response();
+        }
+    }
+    match ModerationManager::apply(&*data_service, &user_id, kind, duration_hours, &reason, None, &identity.operator_id).await {
+        Ok(sanction) => (StatusCode::CREATED, Json(ApiResponse::success("admin:sanction:created", json!({
+            "sanction": sanction_view(&sanction)
+        })))).into_response(),
+        Err(e) => {
+            error!("❌ Failed to sanction user {}: {}", user_id, e);
+            let error = ApiError::system("SANCTION_FAILED", "user_id", "Failed to sanction user", &e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+// A user's sanction history, newest first
+async fn list_sanctions(State(data_service): State<Arc<dyn DataStore>>, Path(user_id): Path<String>) -> Response {
+    match data_service.list_sanctions(&user_id).await {
+        Ok(sanctions) => {
+            let sanctions: Vec<serde_json::Value> = sanctions.iter().map(sanction_view).collect();
+            Json(ApiResponse::success("admin:sanctions", json!({
+                "user_id": user_id,
+                "sanctions": sanctions
+            }))).into_response()
+        }
+        Err(e) => {
+            error!("❌ Failed to list sanctions of user {}: {}", user_id, e);
+            let error = ApiError::system("SANCTION_LIST_FAILED", "user_id", "Failed to list sanctions", &e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+// Ends an active mute or ban early
+async fn lift_sanction(
+    State(data_service): State<Arc<dyn DataStore>>,
+    Extension(identity): Extension<AdminIdentity>,
+    Path(sanction_id): Path<String>,
+) -> Response {
+    match data_service.lift_sanction(&sanction_id, &identity.operator_id).await {
+        Ok(true) => {
+            info!("🛠️ {} lifted sanction {}", identity.operator_id, sanction_id);
+            Json(ApiResponse::success("admin:sanction:lifted", json!({
+                "sanction_id": sanction_id
+            }))).into_response()
+        }
+        Ok(false) => {
+            let error = ApiError::new("SANCTION_NOT_FOUND", "VALIDATION_ERROR", "sanction_id", "No active mute or ban with this id");
+            (StatusCode::NOT_FOUND, Json(error)).into_response()
+        }
+        Err(e) => {
+            error!("❌ Failed to lift sanction {}: {}", sanction_id, e);
+            let error = ApiError::system("SANCTION_UPDATE_FAILED", "sanction_id", "Failed to lift sanction", &e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+fn invalid_game_type(game_type: &str) -> Response {
+    let error = ApiError::new("INVALID_GAME_TYPE", "VALIDATION_ERROR", "game_type", "game_type must be 1-32 lowercase letters, digits, '_' or '-'")
+        .with_details(json!({ "game_type": game_type }));
+    (StatusCode::BAD_REQUEST, Json(error)).into_response()
+}
+
+fn game_config_conflict(game_type: &str) -> Response {
+    let error = ApiError::new("GAME_CONFIG_CONFLICT", "VALIDATION_ERROR", "game_type", "The config was changed at the same time; reload it and try again")
+        .with_details(json!({ "game_type": game_type }));
+    (StatusCode::CONFLICT, Json(error)).into_response()
+}
+
+// The latest version of every stored game type, plus the built-in rules of
+// the default game type while it has no stored config
+async fn list_game_configs(State(data_service): State<Arc<dyn DataStore>>) -> Response {
+    let stored = match data_service.list_game_configs(None).await {
+        Ok(stored) => stored,
+        Err(e) => {
+            error!("❌ Failed to list game configs: {}", e);
+            let error = ApiError::system("GAME_CONFIG_LIST_FAILED", "game_configs", "Failed to list game configs", &e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response();
+        }
+    };
+    let mut configs: Vec<serde_json::Value> = Vec::new();
+    let mut seen = HashSet::new();
+    for config in &stored {
+        if seen.insert(config.game_type.as_str()) {
+            configs.push(GameConfigManager::config_view(config));
+        }
+    }
+    if !seen.contains(DEFAULT_GAME_TYPE) {
+        configs.insert(0, GameConfigManager::config_view(&GameConfigManager::builtin(DEFAULT_GAME_TYPE)));
+    }
+    Json(ApiResponse::success("admin:game_configs", json!({ "configs": configs }))).into_response()
+}
+
+// What new rooms of a game type use, and its stored versions newest first
+async fn game_config_history(State(data_service): State<Arc<dyn DataStore>>, Path(game_type): Path<String>) -> Response {
+    if !GameConfigManager::valid_game_type(&game_type) {
+        return invalid_game_type(&game_type);
+    }
+    match data_service.list_game_configs(Some(&game_type)).await {
+        Ok(versions) => {
+            let current = match versions.first() {
+                Some(latest) if !latest.retired => GameConfigManager::config_view(latest),
+                _ => GameConfigManager::config_view(&GameConfigManager::builtin(&game_type)),
+            };
+            let versions: Vec<serde_json::Value> = versions.iter().map(GameConfigManager::config_view).collect();
+            Json(ApiResponse::success("admin:game_config", json!({
+                "game_type": game_type,
+                "current": current,
+                "versions": versions
+            }))).into_response()
+        }
+        Err(e) => {
+            error!("❌ Failed to load game config {}: {}", game_type, e);
+            let error = ApiError::system("GAME_CONFIG_FETCH_FAILED", "game_type", "Failed to load game config", &e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+// Stores the next version of a game type's rules. Fields left out keep their
+// current value. Rooms already playing keep the version they started with.
+async fn update_game_config(
+    State(data_service): State<Arc<dyn DataStore>>,
+    Extension(identity): Extension<AdminIdentity>,
+    Path(game_type): Path<String>,
+    Json(body): Json<serde_json::Value>,
+) -> Response {
+    if !GameConfigManager::valid_game_type(&game_type) {
+        return invalid_game_type(&game_type);
+    }
+    let current = GameConfigManager::current(&game_type);
+    let rules = match GameConfigManager::merge_rules(current.rules.clone(), &body) {
+        Ok(rules) => rules,
+        Err((field, message)) => {
+            let error = ApiError::new("INVALID_GAME_RULES", "VALIDATION_ERROR", field, &message)
+                .with_details(json!({ "game_type": game_type }));
+            return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+        }
+    };
+    match GameConfigManager::publish(&*data_service, &game_type, rules, false, &identity.operator_id).await {
+        Ok(Some(config)) => {
+            info!("🛠️ {} set game config {} to version {}", identity.operator_id, game_type, config.version);
+            Json(ApiResponse::success("admin:game_config:updated", json!({
+                "config": GameConfigManager::config_view(&config)
+            }))).into_response()
+        }
+        Ok(None) => game_config_conflict(&game_type),
+        Err(e) => {
+            error!("❌ Failed to update game config {}: {}", game_type, e);
+            let error = ApiError::system("GAME_CONFIG_UPDATE_FAILED", "game_type", "Failed to update game config", &e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+// Stores a retired version, which sends the game type back to the built-in rules
+async fn retire_game_config(
+    State(data_service): State<Arc<dyn DataStore>>,
+    Extension(identity): Extension<AdminIdentity>,
+    Path(game_type): Path<String>,
+) -> Response {
+    if !GameConfigManager::valid_game_type(&game_type) {
+        return invalid_game_type(&game_type);
+    }
+    let latest = match data_service.list_game_configs(Some(&game_type)).await {
+        Ok(versions) => versions.into_iter().next(),
+        Err(e) => {
+            error!("❌ Failed to load game config {}: {}", game_type, e);
+            let error = ApiError::system("GAME_CONFIG_UPDATE_FAILED", "game_type", "Failed to retire game config", &e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response();
+        }
+    };
+    let Some(latest) = latest.filter(|latest| !latest.retired) else {
+        let error = ApiError::new("GAME_CONFIG_NOT_FOUND", "VALIDATION_ERROR", "game_type", "This game type already uses the built-in rules")
+            .with_details(json!({ "game_type": game_type }));
+        return (StatusCode::NOT_FOUND, Json(error)).into_response();
+    };
+    match GameConfigManager::publish(&*data_service, &game_type, latest.rules, true, &identity.operator_id).await {
+        Ok(Some(config)) => {
+            info!("🛠️ {} retired game config {} at version {}", identity.operator_id, game_type, config.version);
+            Json(ApiResponse::success("admin:game_config:retired", json!({
+                "config": GameConfigManager::config_view(&config),
+                "current": GameConfigManager::config_view(&GameConfigManager::current(&game_type))
+            }))).into_response()
+        }
+        Ok(None) => game_config_conflict(&game_type),
+        Err(e) => {
+            error!("❌ Failed to retire game config {}: {}", game_type, e);
+            let error = ApiError::system("GAME_CONFIG_UPDATE_FAILED", "game_type", "Failed to retire game config", &e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+// Every notification type with its placeholders, built-in text and the
+// languages it has a stored template in
+async fn list_notification_templates(State(data_service): State<Arc<dyn DataStore>>) -> Response {
+    match NotificationTemplateManager::catalog(&*data_service).await {
+        Ok(types) => Json(ApiResponse::success("admin:notification_templates", json!({
+            "default_language": CONFIG.notification_default_language,
+            "types": types
+        }))).into_response(),
+        Err(e) => {
+            error!("❌ Failed to list notification templates: {}", e);
+            let error = ApiError::system("NOTIFICATION_TEMPLATE_LIST_FAILED", "notification_templates", "Failed to list notification templates", &e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct NotificationTemplateUpdate {
+    title: String,
+    body: String,
+}
+
+fn invalid_language_code(language_code: &str) -> Response {
+    let error = ApiError::new("INVALID_LANGUAGE_CODE", "VALIDATION_ERROR", "language_code", "language_code must be a language tag such as hi or pt-BR")
+        .with_details(json!({ "language_code": language_code }));
+    (StatusCode::BAD_REQUEST, Json(error)).into_response()
+}
+
+// Create or replace the template of a notification type in one language
+async fn update_notification_template(
+    State(data_service): State<Arc<dyn DataStore>>,
+    Extension(identity): Extension<AdminIdentity>,
+    Path((notification_type, language_code)): Path<(String, String)>,
+    Json(body): Json<NotificationTemplateUpdate>,
+) -> Response {
+    let Some(language) = NotificationTemplateManager::normalize_language(&language_code) else {
+        return invalid_language_code(&language_code);
+    };
+    if let Err((field, message)) = NotificationTemplateManager::validate(&notification_type, &body.title, &body.body) {
+        let error = ApiError::new("INVALID_NOTIFICATION_TEMPLATE", "VALIDATION_ERROR", field, &message)
+            .with_details(json!({ "notification_type": notification_type, "language_code": language }));
+        return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+    }
+    let template = NotificationTemplate {
+        id: None,
+        notification_type,
+        language_code: language,
+        title: body.title,
+        body: body.body,
+        updated_by: identity.operator_id.clone(),
+        updated_at: bson::DateTime::now(),
+    };
+    if let Err(e) = data_service.upsert_notification_template(template.clone()).await {
+        error!("❌ Failed to store notification template {}/{}: {}", template.notification_type, template.language_code, e);
+        let error = ApiError::system("NOTIFICATION_TEMPLATE_UPDATE_FAILED", "notification_type", "Failed to store notification template", &e);
+        return (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response();
+    }
+    NotificationTemplateManager::invalidate();
+    info!("🛠️ {} set notification template {}/{}", identity.operator_id, template.notification_type, template.language_code);
+    Json(ApiResponse::success("admin:notification_template:updated", json!({
+        "template": NotificationTemplateManager::template_view(&template)
+    }))).into_response()
+}
+
+// Remove a stored template; users of that language get the next one in their fallback chain
+async fn delete_notification_template(
+    State(data_service): State<Arc<dyn DataStore>>,
+    Extension(identity): Extension<AdminIdentity>,
+    Path((notification_type, language_code)): Path<(String, String)>,
+) -> Response {
+    let Some(language) = NotificationTemplateManager::normalize_language(&language_code) else {
+        return invalid_language_code(&language_code);
+    };
+    match data_service.delete_notification_template(&notification_type, &language).await {
+        Ok(true) => {
+            NotificationTemplateManager::invalidate();
+            info!("🛠️ {} removed notification template {}/{}", identity.operator_id, notification_type, language);
+            Json(ApiResponse::success("admin:notification_template:deleted", json!({
+                "notification_type": notification_type,
+                "language_code": language
+            }))).into_response()
+        }
+        Ok(false) => {
+            let error = ApiError::new("NOTIFICATION_TEMPLATE_NOT_FOUND", "VALIDATION_ERROR", "language_code", "No stored template for this notification type and language")
+                .with_details(json!({ "notification_type": notification_type, "language_code": language }));
+            (StatusCode::NOT_FOUND, Json(error)).into_response()
+        }
+        Err(e) => {
+            error!("❌ Failed to delete notification template {}/{}: {}", notification_type, language, e);
+            let error = ApiError::system("NOTIFICATION_TEMPLATE_UPDATE_FAILED", "notification_type", "Failed to delete notification template", &e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+// Starts a backup of userregister, wallets and match history; poll
+// /api/admin/backups/:backup_id for its progress
+async fn start_backup(Extension(identity): Extension<AdminIdentity>) -> Response {
+    match BackupManager::start(&identity.operator_id).await {
+        Ok(job) => (StatusCode::ACCEPTED, Json(ApiResponse::success("admin:backup:started", json!({ "backup": job })))).into_response(),
+        Err("BACKUP_RUNNING") => {
+            let error = ApiError::new("BACKUP_RUNNING", "VALIDATION_ERROR", "backup", "A backup is already running");
+            (StatusCode::CONFLICT, Json(error)).into_response()
+        }
+        Err(code) => {
+            let error = ApiError::new(code, "SYSTEM_ERROR", "backup", "Backups are not configured on this server");
+            (StatusCode::SERVICE_UNAVAILABLE, Json(error)).into_response()
+        }
+    }
+}
+
+async fn list_backups() -> Response {
+    match BackupManager::list().await {
+        Ok(backups) => Json(ApiResponse::success("admin:backups", json!({ "backups": backups }))).into_response(),
+        Err(e) => {
+            error!("❌ Failed to list backups: {}", e);
+            let error = ApiError::system("BACKUP_LIST_FAILED", "backup", "Failed to list backups", &e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+async fn backup_progress(Path(backup_id): Path<String>) -> Response {
+    match BackupManager::job(&backup_id).await {
+        Some(job) => Json(ApiResponse::success("admin:backup", json!({ "backup": job }))).into_response(),
+        None => {
+            let error = ApiError::new("BACKUP_NOT_FOUND", "VALIDATION_ERROR", "backup_id", "No backup with this id was started on this server")
+                .with_details(json!({ "backup_id": backup_id }));
+            (StatusCode::NOT_FOUND, Json(error)).into_response()
+        }
+    }
+}
+
+// Starts an export of event collections for a time range; poll
+// /api/admin/exports/:export_id for its progress
+async fn start_export(Extension(identity): Extension<AdminIdentity>, Json(request): Json<ExportRequest>) -> Response {
+    match ExportManager::start(&identity.operator_id, request).await {
+        Ok(job) => (StatusCode::ACCEPTED, Json(ApiResponse::success("admin:export:started", json!({ "export": job })))).into_response(),
+        Err("EXPORT_UNKNOWN_COLLECTION") => {
+            let collections: Vec<&str> = EVENT_SCHEMAS.iter().map(|schema| schema.collection).collect();
+            let error = ApiError::new("EXPORT_UNKNOWN_COLLECTION", "VALIDATION_ERROR", "collections", "Name one or more event collections to export")
+                .with_details(json!({ "collections": collections }));
+            (StatusCode::BAD_REQUEST, Json(error)).into_response()
+        }
+        Err("EXPORT_INVALID_RANGE") => {
+            let error = ApiError::new("EXPORT_INVALID_RANGE", "VALIDATION_ERROR", "from", "from must be before to");
+            (StatusCode::BAD_REQUEST, Json(error)).into_response()
+        }
+        Err("EXPORT_RUNNING") => {
+            let error = ApiError::new("EXPORT_RUNNING", "VALIDATION_ERROR", "export", "An export is already running");
+            (StatusCode::CONFLICT, Json(error)).into_response()
+        }
+        Err(code) => {
+            let error = ApiError::new(code, "SYSTEM_ERROR", "export", "Event exports are not configured on this server");
+            (StatusCode::SERVICE_UNAVAILABLE, Json(error)).into_response()
+        }
+    }
+}
+
+async fn list_exports() -> Response {
+    match ExportManager::list().await {
+        Ok(exports) => Json(ApiResponse::success("admin:exports", json!({ "exports": exports }))).into_response(),
+        Err(e) => {
+            error!("❌ Failed to list exports: {}", e);
+            let error = ApiError::system("EXPORT_LIST_FAILED", "export", "Failed to list exports", &e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+async fn export_progress(Path(export_id): Path<String>) -> Response {
+    match ExportManager::job(&export_id).await {
+        Some(job) => Json(ApiResponse::success("admin:export", json!({ "export": job }))).into_response(),
+        None => {
+            let error = ApiError::new("EXPORT_NOT_FOUND", "VALIDATION_ERROR", "export_id", "No export with this id was started on this server")
+                .with_details(json!({ "export_id": export_id }));
+            (StatusCode::NOT_FOUND, Json(error)).into_response()
+        }
+    }
+}
+
+async fn warehouse_status() -> Response {
+    if CONFIG.in_memory_store {
+        return Json(ApiResponse::success("admin:warehouse", json!({ "enabled": false }))).into_response();
+    }
+    match WarehouseSink::status().await {
+        Ok(status) => Json(ApiResponse::success("admin:warehouse", status)).into_response(),
+        Err(e) => {
+            error!("❌ Failed to read warehouse sink status: {}", e);
+            let error = ApiError::system("WAREHOUSE_STATUS_FAILED", "warehouse", "Failed to read warehouse sink status", &e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+async fn list_dead_letters() -> Response {
+    if CONFIG.in_memory_store {
+        return Json(ApiResponse::success("admin:warehouse:dead_letters", json!({ "dead_letters": [] }))).into_response();
+    }
+    match WarehouseSink::dead_letters().await {
+        Ok(dead_letters) => Json(ApiResponse::success("admin:warehouse:dead_letters", json!({ "dead_letters": dead_letters }))).into_response(),
+        Err(e) => {
+            error!("❌ Failed to list warehouse dead letters: {}", e);
+            let error = ApiError::system("DEAD_LETTER_LIST_FAILED", "warehouse", "Failed to list dead letters", &e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+async fn replay_dead_letter(Extension(identity): Extension<AdminIdentity>, Path(dead_letter_id): Path<String>) -> Response {
+    if CONFIG.in_memory_store {
+        let error = ApiError::new("WAREHOUSE_UNAVAILABLE", "SYSTEM_ERROR", "warehouse", "The warehouse sink needs MongoDB");
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(error)).into_response();
+    }
+    match WarehouseSink::replay(&dead_letter_id).await {
+        Ok(true) => {
+            info!("🛠️ {} replayed warehouse dead letter {}", identity.operator_id, dead_letter_id);
+            Json(ApiResponse::success("admin:warehouse:dead_letter:replayed", json!({ "dead_letter_id": dead_letter_id }))).into_response()
+        }
+        Ok(false) => {
+            let error = ApiError::new("DEAD_LETTER_NOT_FOUND", "VALIDATION_ERROR", "dead_letter_id", "No dead letter with this id")
+                .with_details(json!({ "dead_letter_id": dead_letter_id }));
+            (StatusCode::NOT_FOUND, Json(error)).into_response()
+        }
+        Err(e) => {
+            warn!("⚠️ Replay of warehouse dead letter {} failed: {}", dead_letter_id, e);
+            let error = ApiError::system("DEAD_LETTER_REPLAY_FAILED", "warehouse", "The warehouse did not take the batch", &e);
+            (StatusCode::BAD_GATEWAY, Json(error)).into_response()
+        }
+    }
+}
+
+async fn list_event_dead_letters() -> Response {
+    if CONFIG.in_memory_store {
+        return Json(ApiResponse::success("admin:event_dead_letters", json!({ "dead_letters": [], "in_file": 0 }))).into_response();
+    }
+    match EventDeadLetters::list(MAX_PAGE_SIZE).await {
+        Ok(dead_letters) => Json(ApiResponse::success("admin:event_dead_letters", json!({
+            "dead_letters": dead_letters,
+            "in_file": EventDeadLetters::file_count().await
+        }))).into_response(),
+        Err(e) => {
+            error!("❌ Failed to list event dead letters: {}", e);
+            let error = ApiError::system("DEAD_LETTER_LIST_FAILED", "dead_letters", "Failed to list dead letters", &e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+async fn replay_event_dead_letters(Extension(identity): Extension<AdminIdentity>) -> Response {
+    if CONFIG.in_memory_store {
+        let error = ApiError::new("DEAD_LETTERS_UNAVAILABLE", "SYSTEM_ERROR", "dead_letters", "Dead letters need MongoDB");
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(error)).into_response();
+    }
+    match EventDeadLetters::replay().await {
+        Ok(result) => {
+            info!("🛠️ {} replayed {} event dead letters ({} remaining)", identity.operator_id, result.replayed, result.remaining);
+            Json(ApiResponse::success("admin:event_dead_letters:replayed", json!(result))).into_response()
+        }
+        Err(e) => {
+            error!("❌ Replay of event dead letters failed: {}", e);
+            let error = ApiError::system("DEAD_LETTER_REPLAY_FAILED", "dead_letters", "Failed to replay dead letters", &e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct OutboxQuery {
+    status: Option<String>,
+    page: Option<u64>,          // Starts at 0
+    page_size: Option<i64>,
+}
+
+async fn list_outbox(Query(query): Query<OutboxQuery>) -> Response {
+    let page = query.page.unwrap_or(0);
+    let page_size = query.page_size.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let status = match query.status.as_deref() {
+        None => None,
+        Some("pending") => Some(OutboxStatus::Pending),
+        Some("delivered") => Some(OutboxStatus::Delivered),
+        Some("failed") => Some(OutboxStatus::Failed),
+        Some(other) => {
+            let error = ApiError::new("INVALID_OUTBOX_STATUS", "VALIDATION_ERROR", "status", "status must be pending, delivered or failed")
+                .with_details(json!({ "status": other }));
+            return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+        }
+    };
+    if CONFIG.in_memory_store {
+        return Json(ApiResponse::success("admin:outbox", json!({ "messages": [], "page": page, "page_size": page_size }))).into_response();
+    }
+    match Outbox::list(status, page, page_size).await {
+        Ok(messages) => {
+            let rfc3339 = |date: bson::DateTime| date.try_to_rfc3339_string().unwrap_or_default();
+            let messages: Vec<serde_json::Value> = messages.iter().map(|message| json!({
+                "message_id": message.message_id,
+                "destination": message.destination,
+                "topic": message.topic,
+                "key": message.key,
+                "payload": message.payload,
+                "status": message.status,
+                "attempts": message.attempts,
+                "next_attempt_at": rfc3339(message.next_attempt_at),
+                "last_error": message.last_error,
+                "created_at": rfc3339(message.created_at),
+                "delivered_at": message.delivered_at.map(rfc3339),
+            })).collect();
+            Json(ApiResponse::success("admin:outbox", json!({
+                "messages": messages,
+                "page": page,
+                "page_size": page_size
+            }))).into_response()
+        }
+        Err(e) => {
+            error!("❌ Failed to list the outbox: {}", e);
+            let error = ApiError::system("OUTBOX_LIST_FAILED", "outbox", "Failed to list the outbox", &e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+async fn retry_outbox_message(Extension(identity): Extension<AdminIdentity>, Path(message_id): Path<String>) -> Response {
+    if CONFIG.in_memory_store {
+        let error = ApiError::new("OUTBOX_UNAVAILABLE", "SYSTEM_ERROR", "outbox", "The outbox needs MongoDB");
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(error)).into_response();
+    }
+    match Outbox::retry(&message_id).await {
+        Ok(true) => {
+            info!("🛠️ {} put outbox message {} back in line", identity.operator_id, message_id);
+            Json(ApiResponse::success("admin:outbox:retried", json!({ "message_id": message_id }))).into_response()
+        }
+        Ok(false) => {
+            let error = ApiError::new("OUTBOX_MESSAGE_NOT_FOUND", "VALIDATION_ERROR", "message_id", "No failed outbox message with this id")
+                .with_details(json!({ "message_id": message_id }));
+            (StatusCode::NOT_FOUND, Json(error)).into_response()
+        }
+        Err(e) => {
+            error!("❌ Failed to retry outbox message {}: {}", message_id, e);
+            let error = ApiError::system("OUTBOX_RETRY_FAILED", "message_id", "Failed to retry the outbox message", &e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+async fn index_report() -> Response {
+    if crate::config::CONFIG.in_memory_store {
+        let error = ApiError::new("INDEX_REPORT_UNAVAILABLE", "SYSTEM_ERROR", "database", "The index report needs MongoDB");
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(error)).into_response();
+    }
+    match QueryMonitor::index_report().await {
+        Ok(report) => Json(ApiResponse::success("admin:index_report", report)).into_response(),
+        Err(e) => {
+            error!("❌ Failed to build the index report: {}", e);
+            let error = ApiError::system("INDEX_REPORT_FAILED", "database", "Failed to build the index report", &e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RevenueQuery {
+    from: Option<String>,       // First day, YYYY-MM-DD UTC; DEFAULT_REVENUE_DAYS before `to` when absent
+    to: Option<String>,         // Last day, included; today when absent
+    game_type: Option<String>,  // Every game type when absent
+    #[serde(default)]
+    refresh: bool,              // Recompute the days from the ledger first
+}
+
+fn invalid_day(field: &str, value: &str) -> Response {
+    let error = ApiError::new("INVALID_DAY", "VALIDATION_ERROR", field, &format!("{} must be a YYYY-MM-DD date", field))
+        .with_details(json!({ "received_value": value }));
+    (StatusCode::BAD_REQUEST, Json(error)).into_response()
+}
+
+// Commission per UTC day, with the totals by game type that make it up, for
+// reconciling against the payment gateway. Days come from revenue_rollups;
+// `refresh=true` recomputes them from revenue_ledger first.
+async fn revenue_daily(State(data_service): State<Arc<dyn DataStore>>, Query(query): Query<RevenueQuery>) -> Response {
+    let to = match query.to.as_deref() {
+        Some(to) => match RevenueManager::parse_day(to) {
+            Some(day) => day,
+            None => return invalid_day("to", to),
+        },
+        None => chrono::Utc::now().date_naive(),
+    };
+    let from = match query.from.as_deref() {
+        Some(from) => match RevenueManager::parse_day(from) {
+            Some(day) => day,
+            None => return invalid_day("from", from),
+        },
+        None => to - chrono::Duration::days(DEFAULT_REVENUE_DAYS - 1),
+    };
+    if from > to || (to - from).num_days() >= MAX_REVENUE_DAYS {
+        let error = ApiError::new("INVALID_TIME_RANGE", "VALIDATION_ERROR", "from",
+            &format!("from must not be after to, at most {} days in all", MAX_REVENUE_DAYS))
+            .with_details(json!({ "from": query.from, "to": query.to }));
+        return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+    }
+
+    if query.refresh {
+        for day in from.iter_days().take_while(|day| *day <= to) {
+            if let Err(e) = RevenueManager::rollup(&*data_service, &day.format("%Y-%m-%d").to_string()).await {
+                error!("❌ Revenue rollup of {} failed: {}", day, e);
+                let error = ApiError::system("REVENUE_ROLLUP_FAILED", "refresh", "Failed to recompute the revenue rollups", &e);
+                return (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response();
+            }
+        }
+    }
+    let (from, to) = (from.format("%Y-%m-%d").to_string(), to.format("%Y-%m-%d").to_string());
+    let rollups = match data_service.revenue_rollups(&from, &to).await {
+        Ok(rollups) => rollups,
+        Err(e) => {
+            error!("❌ Failed to load revenue rollups: {}", e);
+            let error = ApiError::system("REVENUE_REPORT_FAILED", "query", "Failed to load the revenue rollups", &e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response();
+        }
+    };
+
+    let rollups: Vec<_> = rollups.into_iter()
+        .filter(|rollup| query.game_type.as_deref().is_none_or(|game_type| rollup.game_type == game_type))
+        .collect();
+    let mut days: Vec<serde_json::Value> = Vec::new();
+    for day in rollups.chunk_by(|a, b| a.day == b.day) {
+        days.push(json!({
+            "day": day[0].day,
+            "matches": day.iter().map(|r| r.matches).sum::<u64>(),
+            "pot": day.iter().map(|r| r.pot).sum::<i64>(),
+            "rake": day.iter().map(|r| r.rake).sum::<i64>(),
+            "paid_out": day.iter().map(|r| r.paid_out).sum::<i64>(),
+            "computed_at": day.iter().map(|r| r.computed_at).min().and_then(|at| at.try_to_rfc3339_string().ok()),
+            "game_types": day.iter().map(|r| json!({
+                "game_type": r.game_type,
+                "matches": r.matches,
+                "pot": r.pot,
+                "rake": r.rake,
+                "paid_out": r.paid_out,
+            })).collect::<Vec<_>>(),
+        }));
+    }
+    Json(ApiResponse::success("admin:revenue:daily", json!({
+        "from": from,
+        "to": to,
+        "game_type": query.game_type,
+        "days": days,
+        "totals": {
+            "matches": rollups.iter().map(|r| r.matches).sum::<u64>(),
+            "pot": rollups.iter().map(|r| r.pot).sum::<i64>(),
+            "rake": rollups.iter().map(|r| r.rake).sum::<i64>(),
+            "paid_out": rollups.iter().map(|r| r.paid_out).sum::<i64>(),
+        },
+    }))).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct RevenueLedgerQuery {
+    day: Option<String>,        // YYYY-MM-DD UTC; today when absent
+    game_type: Option<String>,
+    page: Option<u64>,          // Starts at 0
+    page_size: Option<i64>,
+}
+
+// The ledger entries of one UTC day, oldest first, to match line by line
+async fn revenue_ledger(State(data_service): State<Arc<dyn DataStore>>, Query(query): Query<RevenueLedgerQuery>) -> Response {
+    let day = match query.day.as_deref() {
+        Some(day) => match RevenueManager::parse_day(day) {
+            Some(day) => day.format("%Y-%m-%d").to_string(),
+            None => return invalid_day("day", day),
+        },
+        None => RevenueManager::date_of(chrono::Utc::now()),
+    };
+    let page = query.page.unwrap_or(0);
+    let page_size = query.page_size.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    match data_service.revenue_entries(&day, query.game_type.as_deref(), page, page_size).await {
+        Ok(entries) => {
+            let entries: Vec<serde_json::Value> = entries.iter().map(|entry| json!({
+                "escrow_id": entry.escrow_id,
+                "room_id": entry.room_id,
+                "game_type": entry.game_type,
+                "entry_fee": entry.entry_fee,
+                "players": entry.players,
+                "pot": entry.pot,
+                "rake": entry.rake,
+                "paid_out": entry.paid_out,
+                "recorded_at": entry.recorded_at.try_to_rfc3339_string().unwrap_or_default(),
+            })).collect();
+            Json(ApiResponse::success("admin:revenue:ledger", json!({
+                "day": day,
+                "entries": entries,
+                "page": page,
+                "page_size": page_size
+            }))).into_response()
+        }
+        Err(e) => {
+            error!("❌ Failed to list revenue ledger entries: {}", e);
+            let error = ApiError::system("REVENUE_LEDGER_FAILED", "day", "Failed to list the revenue ledger", &e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+async fn list_promo_codes(State(data_service): State<Arc<dyn DataStore>>) -> Response {
+    match data_service.list_promo_codes().await {
+        Ok(promos) => {
+            let promos: Vec<serde_json::Value> = promos.iter().map(PromoManager::promo_view).collect();
+            Json(ApiResponse::success("admin:promo_codes", json!({ "promo_codes": promos }))).into_response()
+        }
+        Err(e) => {
+            error!("❌ Failed to list promo codes: {}", e);
+            let error = ApiError::system("PROMO_LIST_FAILED", "promo_codes", "Failed to list promo codes", &e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CreatePromoCode {
+    code: String,
+    description: Option<String>,
+    #[serde(default)]
+    coins: i64,
+    #[serde(default)]
+    items: Vec<PromoItem>,
+    max_uses: Option<u64>,                              // Unlimited when absent
+    per_user_limit: Option<u32>,                        // 1 when absent
+    starts_at: Option<chrono::DateTime<chrono::Utc>>,   // Redeemable at once when absent
+    expires_at: Option<chrono::DateTime<chrono::Utc>>,  // Never expires when absent
+}
+
+// Redemptions a single user may be allowed for one code
+const MAX_PROMO_PER_USER_LIMIT: u32 = 100;
+
+fn invalid_promo(code: &str, field: &str, message: &str, details: serde_json::Value) -> Response {
+    let error = ApiError::new(code, "VALIDATION_ERROR", field, message).with_details(details);
+    (StatusCode::BAD_REQUEST, Json(error)).into_response()
+}
+
+// Creates an active promo code. It needs coins, items or both; item ids use
+// the gift:send format. Codes are stored in upper case and cannot be reused.
+async fn create_promo_code(
+    State(data_service): State<Arc<dyn DataStore>>,
+    Extension(identity): Extension<AdminIdentity>,
+    Json(body): Json<CreatePromoCode>,
+) -> Response {
+    let Some(code) = PromoManager::normalize(&body.code) else {
+        return invalid_promo("INVALID_PROMO_CODE", "code", "code must be letters, digits, '_' or '-'", json!({
+            "min_length": promos::MIN_CODE_LENGTH,
+            "max_length": promos::MAX_CODE_LENGTH
+        }));
+    };
+    if !(0..=promos::MAX_PROMO_COINS).contains(&body.coins) {
+        return invalid_promo("INVALID_PROMO_REWARD", "coins", "coins out of range", json!({
+            "min": 0,
+            "max": promos::MAX_PROMO_COINS,
+            "received_value": body.coins
+        }));
+    }
+    if body.items.len() > promos::MAX_PROMO_ITEMS {
+        return invalid_promo("INVALID_PROMO_REWARD", "items", "Too many reward items", json!({
+            "max_items": promos::MAX_PROMO_ITEMS,
+            "received_items": body.items.len()
+        }));
+    }
+    let mut seen = HashSet::new();
+    for item in &body.items {
+        let valid_id = !item.item_id.is_empty()
+            && item.item_id.len() <= gifts::MAX_ITEM_ID_LENGTH
+            && item.item_id.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-');
+        if !valid_id || !seen.insert(item.item_id.as_str()) || !(1..=promos::MAX_PROMO_ITEM_QUANTITY).contains(&item.quantity) {
+            return invalid_promo("INVALID_PROMO_REWARD", "items", "items need distinct valid item_ids and a quantity in range", json!({
+                "item": item,
+                "max_quantity": promos::MAX_PROMO_ITEM_QUANTITY
+            }));
+        }
+    }
+    if body.coins == 0 && body.items.is_empty() {
+        return invalid_promo("INVALID_PROMO_REWARD", "coins", "A promo code needs coins or items", json!({}));
+    }
+    let per_user_limit = body.per_user_limit.unwrap_or(1);
+    if !(1..=MAX_PROMO_PER_USER_LIMIT).contains(&per_user_limit) {
+        return invalid_promo("INVALID_PROMO_LIMIT", "per_user_limit", "per_user_limit out of range", json!({
+            "min": 1,
+            "max": MAX_PROMO_PER_USER_LIMIT,
+            "received_value": per_user_limit
+        }));
+    }
+    if body.max_uses == Some(0) {
+        return invalid_promo("INVALID_PROMO_LIMIT", "max_uses", "max_uses must be at least 1", json!({}));
+    }
+    if let (Some(starts_at), Some(expires_at)) = (body.starts_at, body.expires_at) {
+        if starts_at >= expires_at {
+            return invalid_promo("INVALID_PROMO_DATES", "expires_at", "expires_at must be after starts_at", json!({
+                "starts_at": starts_at.to_rfc3339(),
+                "expires_at": expires_at.to_rfc3339()
+            }));
+        }
+    }
+
+    let to_bson = |time: chrono::DateTime<chrono::Utc>| bson::DateTime::from_millis(time.timestamp_millis());
+    let now = bson::DateTime::now();
+    let promo = PromoCode {
+        id: None,
+        code: code.clone(),
+        description: body.description.map(|d| d.trim().to_string()).filter(|d| !d.is_empty()),
+        reward: PromoReward { coins: body.coins, items: body.items },
+        max_uses: body.max_uses,
+        uses: 0,
+        per_user_limit,
+        starts_at: body.starts_at.map(to_bson),
+        expires_at: body.expires_at.map(to_bson),
+        active: true,
+        created_by: identity.operator_id.clone(),
+        created_at: now,
+        updated_at: now,
+    };
+    match data_service.create_promo_code(promo.clone()).await {
+        Ok(true) => {}
+        Ok(false) => {
+            let error = ApiError::new("PROMO_CODE_EXISTS", "VALIDATION_ERROR", "code", "A promo code with this code already exists")
+                .with_details(json!({ "code": code }));
+            return (StatusCode::CONFLICT, Json(error)).into_response();
+        }
+        Err(e) => {
+            error!("❌ Failed to create promo code {}: {}", code, e);
+            let error = ApiError::system("PROMO_CREATE_FAILED", "code", "Failed to create promo code", &e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response();
+        }
+    }
+    info!("🛠️ {} created promo code {}", identity.operator_id, code);
+    (StatusCode::CREATED, Json(ApiResponse::success("admin:promo_code:created", json!({
+        "promo_code": PromoManager::promo_view(&promo)
+    })))).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct PromoCodeActive {
+    active: bool,
+}
+
+// Disabling a code stops new redemptions at once; those already made stand
+async fn set_promo_code_active(
+    State(data_service): State<Arc<dyn DataStore>>,
+    Extension(identity): Extension<AdminIdentity>,
+    Path(code): Path<String>,
+    Json(body): Json<PromoCodeActive>,
+) -> Response {
+    let code = PromoManager::normalize(&code).unwrap_or(code);
+    match data_service.set_promo_code_active(&code, body.active).await {
+        Ok(true) => {
+            info!("🛠️ {} {} promo code {}", identity.operator_id, if body.active { "enabled" } else { "disabled" }, code);
+            Json(ApiResponse::success("admin:promo_code:updated", json!({
+                "code": code,
+                "active": body.active
+            }))).into_response()
+        }
+        Ok(false) => {
+            let error = ApiError::new("PROMO_NOT_FOUND", "VALIDATION_ERROR", "code", "No such promo code")
+                .with_details(json!({ "code": code }));
+            (StatusCode::NOT_FOUND, Json(error)).into_response()
+        }
+        Err(e) => {
+            error!("❌ Failed to update promo code {}: {}", code, e);
+            let error = ApiError::system("PROMO_UPDATE_FAILED", "code", "Failed to update promo code", &e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PromoRedemptionQuery {
+    page: Option<u64>,          // Starts at 0
+    page_size: Option<i64>,
+}
+
+async fn list_promo_redemptions(
+    State(data_service): State<Arc<dyn DataStore>>,
+    Path(code): Path<String>,
+    Query(query): Query<PromoRedemptionQuery>,
+) -> Response {
+    let code = PromoManager::normalize(&code).unwrap_or(code);
+    let page = query.page.unwrap_or(0);
+    let page_size = query.page_size.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    match data_service.list_promo_redemptions(&code, page, page_size).await {
+        Ok(redemptions) => {
+            let redemptions: Vec<serde_json::Value> = redemptions.iter().map(PromoManager::redemption_view).collect();
+            Json(ApiResponse::success("admin:promo_code:redemptions", json!({
+                "code": code,
+                "redemptions": redemptions,
+                "page": page,
+                "page_size": page_size
+            }))).into_response()
+        }
+        Err(e) => {
+            error!("❌ Failed to list redemptions of promo code {}: {}", code, e);
+            let error = ApiError::system("PROMO_REDEMPTIONS_FAILED", "code", "Failed to list promo code redemptions", &e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
@@ -0,0 +1,748 @@
+#![cfg_attr(not(feature = "contracts"), allow(dead_code))]
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::managers::settings::GameSettings;
+
+#[cfg(feature = "contracts")]
+use schemars::JsonSchema;
+#[cfg(feature = "contracts")]
+use ts_rs::TS;
+
+// Typed payloads of the client-facing socket events. With the `contracts`
+// feature they derive TypeScript and JSON Schema definitions, exported by
+//   cargo run --features contracts -- export-contracts <dir>
+// Server -> client payloads are sent inside ApiResponse, so the exported
+// response types are ApiResponse<...> instantiations.
+//
+// Every request may also carry an optional `request_id` (see Correlation).
+
+// ===== Requests (client -> server) =====
+// Handlers still validate the raw JSON (ValidationManager); these types only
+// describe it, so without the export they are never constructed.
+
+// device:info
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct DeviceInfoRequest {
+    pub device_id: String,
+    pub device_type: String,            // mobile, tablet, desktop
+    pub timestamp: String,              // ISO 8601
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub manufacturer: Option<String>,
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub model: Option<String>,
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub firmware_version: Option<String>,
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub app_version: Option<String>,        // Client app build, e.g. "2.4.1"
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub capabilities: Option<Vec<String>>,
+}
+
+// login
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct LoginRequest {
+    pub mobile_no: String,
+    pub device_id: String,
+    pub fcm_token: String,
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub email: Option<String>,
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub captcha_token: Option<String>,  // Solved challenge, once login answered CAPTCHA_REQUIRED
+}
+
+// verify:otp
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct VerifyOtpRequest {
+    pub mobile_no: String,
+    pub session_token: String,
+    pub otp: String,                    // otp_length digits of the login policy (6 by default)
+}
+
+// set:profile
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct SetProfileRequest {
+    pub mobile_no: String,
+    pub session_token: String,
+    pub full_name: String,
+    pub state: String,
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub referral_code: Option<String>,
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub referred_by: Option<String>,
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub profile_data: Option<Value>,
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub date_of_birth: Option<String>,  // YYYY-MM-DD; cannot be changed once set
+}
+
+// set:language
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct SetLanguageRequest {
+    pub mobile_no: String,
+    pub session_token: String,
+    pub language_code: String,
+    pub language_name: String,
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub region_code: Option<String>,
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub timezone: Option<String>,
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub user_preferences: Option<Value>,
+}
+
+// preferences:notifications (partial update keyed by category, then channel)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct NotificationPreferencesRequest {
+    pub mobile_no: String,
+    pub session_token: String,
+    pub notifications: Value,
+}
+
+// preferences:get
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct PreferencesGetRequest {
+    pub mobile_no: String,
+    pub session_token: String,
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub namespace: Option<String>,
+}
+
+// preferences:set (`namespace.key` -> value, null removes the key)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct PreferencesSetRequest {
+    pub mobile_no: String,
+    pub session_token: String,
+    pub preferences: Value,
+}
+
+// settings:get
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct SettingsGetRequest {
+    pub mobile_no: String,
+    pub session_token: String,
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub platform: Option<String>,       // mobile, tablet, desktop; defaults to the login device's type
+}
+
+// settings:set (partial GameSettings; null puts a setting back to its default)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct SettingsSetRequest {
+    pub mobile_no: String,
+    pub session_token: String,
+    pub settings: Value,
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub platform: Option<String>,
+}
+
+// progress:get
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct ProgressGetRequest {
+    pub mobile_no: String,
+    pub session_token: String,
+}
+
+// progress:update (at least one of xp, level and game)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct ProgressUpdateRequest {
+    pub mobile_no: String,
+    pub session_token: String,
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub xp: Option<i64>,                // XP gained, 0-100000
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub level: Option<u32>,             // Level reached, 1-1000
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub game: Option<GameResultRequest>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct GameResultRequest {
+    pub game_id: String,                // 1-32 chars of a-z, 0-9, _ and -
+    pub result: String,                 // win, loss, draw
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub score: Option<i64>,
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub room_id: Option<String>,        // The /gameplay room the game was played in
+}
+
+// challenge:today
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct ChallengeTodayRequest {
+    pub mobile_no: String,
+    pub session_token: String,
+}
+
+// challenge:claim
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct ChallengeClaimRequest {
+    pub mobile_no: String,
+    pub session_token: String,
+    pub challenge_id: String,           // From challenge:data
+}
+
+// leaderboard:get (defaults to the active season, else the last ended one)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct LeaderboardRequest {
+    pub mobile_no: String,
+    pub session_token: String,
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub season_id: Option<String>,
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub limit: Option<u32>,             // 1-100, default 20
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub offset: Option<u32>,            // 0-10000
+}
+
+// friend:add and friend:remove
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct FriendRequest {
+    pub mobile_no: String,
+    pub session_token: String,
+    pub friend_user_id: String,
+}
+
+// friend:list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct FriendListRequest {
+    pub mobile_no: String,
+    pub session_token: String,
+}
+
+// gift:send (either coins or item_id)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct GiftSendRequest {
+    pub mobile_no: String,
+    pub session_token: String,
+    pub to_user_id: String,             // Must be a friend
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub coins: Option<i64>,             // 1-GIFT_MAX_COINS
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub item_id: Option<String>,
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub quantity: Option<u32>,          // 1-100, default 1; items only
+}
+
+// inventory:get
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct InventoryGetRequest {
+    pub mobile_no: String,
+    pub session_token: String,
+}
+
+// promo:redeem (needs a payload signature, like gift:send)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct PromoRedeemRequest {
+    pub mobile_no: String,
+    pub session_token: String,
+    pub code: String,                   // 3-32 letters, digits, '_' or '-'; any case
+}
+
+// user:block and user:unblock
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct BlockRequest {
+    pub mobile_no: String,
+    pub session_token: String,
+    pub target_user_id: String,
+}
+
+// user:report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct ReportRequest {
+    pub mobile_no: String,
+    pub session_token: String,
+    pub target_user_id: String,
+    pub category: String,               // cheating, harassment, hate_speech, spam, inappropriate_name, other
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub description: Option<String>,    // Up to 500 chars
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub room_id: Option<String>,        // The /gameplay room the report is about
+}
+
+// devices:list and devices:remove (device_id required for remove)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct DeviceManagementRequest {
+    pub mobile_no: String,
+    pub session_token: String,
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub device_id: Option<String>,
+}
+
+// time:sync
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct TimeSyncRequest {
+    pub client_time: i64,               // ms since epoch
+}
+
+// ping
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct PingRequest {
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub client_time: Option<i64>,
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub rtt_ms: Option<u64>,            // RTT measured on the previous pong
+}
+
+// room:join, room:spectate, room:unspectate, dealer:hand, dealer:reveal and afk:back (/gameplay)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct RoomJoinRequest {
+    pub room_id: String,
+    pub player_id: String,
+}
+
+// room:viewer_list (/gameplay)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct RoomViewerListRequest {
+    pub room_id: String,
+    pub player_id: String,
+    pub enabled: bool,                  // Whether room:spectators lists the spectators
+}
+
+// room:update (/gameplay)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct RoomUpdateRequest {
+    pub room_id: String,
+    pub player_id: String,
+    pub delta: Value,                   // JSON merge patch (RFC 7396) of at most 16 KB
+}
+
+// room:state_ack (/gameplay)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct RoomStateAckRequest {
+    pub room_id: String,
+    pub seq: u64,                       // Last room:state the client applied
+}
+
+// room:state_sync (/gameplay)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct RoomStateSyncRequest {
+    pub room_id: String,
+}
+
+// subscribe and unsubscribe (/gameplay)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct SubscribeRequest {
+    pub room_id: String,
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub paths: Option<Vec<String>>,     // Dot-separated state paths such as "tables.3"; required to subscribe
+}
+
+// dealer:shuffle (/gameplay)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct DealerShuffleRequest {
+    pub room_id: String,
+    pub player_id: String,
+    pub deck_id: String,                // Shuffling an existing deck_id replaces that deck
+    pub cards: Vec<String>,             // 1-520 labels of 1-32 characters
+}
+
+// dealer:draw (/gameplay)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct DealerDrawRequest {
+    pub room_id: String,
+    pub player_id: String,
+    pub deck_id: String,
+    pub count: u32,
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub face_up: Option<bool>,          // Show the cards to the whole room; default false
+}
+
+// dealer:roll (/gameplay)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct DealerRollRequest {
+    pub room_id: String,
+    pub player_id: String,
+    pub dice: u32,                      // 1-20
+    pub sides: u32,                     // 2-1000
+}
+
+// matchmaking:join, matchmaking:leave and matchmaking:population (/gameplay)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct MatchmakingRequest {
+    pub player_id: String,
+}
+
+// party:* (/gameplay); the extra fields depend on the event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct PartyRequest {
+    pub player_id: String,
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub target_player_id: Option<String>,
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub party_id: Option<String>,
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub message: Option<String>,
+}
+
+// chat:send (/gameplay)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct ChatSendRequest {
+    pub room_id: String,
+    pub player_id: String,
+    pub message: String,
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub attachments: Option<Vec<ChatAttachmentRequest>>, // Up to 4 images
+}
+
+// An image in chat:send, already uploaded to one of CHAT_IMAGE_HOSTS
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct ChatAttachmentRequest {
+    pub url: String,                    // https only
+    pub content_type: String,           // image/png, image/jpeg, image/gif or image/webp
+    pub size_bytes: i64,
+}
+
+// chat:edit (/gameplay)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct ChatEditRequest {
+    pub room_id: String,
+    pub player_id: String,
+    pub message_id: String,
+    pub message: String,
+}
+
+// chat:delete (/gameplay)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct ChatDeleteRequest {
+    pub room_id: String,
+    pub player_id: String,
+    pub message_id: String,
+}
+
+// chat:typing (/gameplay)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct ChatTypingRequest {
+    pub room_id: String,
+    pub player_id: String,
+    pub typing: bool,                   // false when the player stops typing
+}
+
+// chat:read (/gameplay)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct ChatReadRequest {
+    pub room_id: String,
+    pub player_id: String,
+    pub message_id: String,             // Newest message the player has seen
+}
+
+// chat:react (/gameplay)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct ChatReactRequest {
+    pub room_id: String,
+    pub player_id: String,
+    pub message_id: String,
+    pub reaction: String,               // like, love, laugh, wow, sad or fire
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub remove: Option<bool>,           // true takes the reaction back
+}
+
+// chat:history (/gameplay)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct ChatHistoryRequest {
+    pub room_id: String,
+    pub player_id: String,
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub before: Option<String>,         // next_before of the previous page
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub limit: Option<i64>,             // 1-100, default 50
+}
+
+// player_action (/gameplay)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct PlayerActionRequest {
+    pub room_id: String,
+    pub player_id: String,
+    pub action: Value,
+}
+
+// ===== Responses (server -> client, sent as ApiResponse<T>) =====
+
+// device:info:ack
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct DeviceInfoAck {
+    pub message: String,
+}
+
+// login:success
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct LoginSuccess {
+    pub message: String,
+    pub mobile_no: String,
+    pub device_id: String,
+    pub session_token: String,
+    pub is_new_user: bool,
+    pub session_reused: bool,
+    pub nonce: String,                  // Send back with verify:otp from the same socket
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub otp: Option<i32>,               // DEV_MODE only
+}
+
+// otp:verified
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct OtpVerified {
+    pub message: String,
+    pub mobile_no: String,
+    pub session_token: String,
+    pub user_id: String,
+    pub user_number: u64,
+    pub user_status: String,            // new_user, existing_user
+    pub jwt_token: String,
+    pub token_type: String,
+    pub expires_in: u64,                // seconds
+    pub signing_key: String,            // base64url HMAC key for signed events
+}
+
+// profile:set
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct ProfileSet {
+    pub message: String,
+    pub mobile_no: String,
+    pub session_token: String,
+    pub full_name: String,
+    pub state: String,
+    pub referral_code: Option<String>,
+    pub referred_by: Option<String>,
+    pub profile_data: Option<Value>,
+    pub date_of_birth: Option<String>,
+    pub welcome_message: String,
+    pub next_steps: String,
+}
+
+// settings:data, settings:updated
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct SettingsData {
+    pub mobile_no: String,
+    pub platform: String,
+    pub settings: GameSettings,         // What the client should apply
+    pub defaults: GameSettings,         // The platform's defaults
+    pub customized: Value,              // The user's own choices, as stored
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub updated_keys: Option<Vec<String>>,  // settings:updated only
+}
+
+// language:set
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct LanguageSet {
+    pub message: String,
+    pub mobile_no: String,
+    pub session_token: String,
+    pub language_code: String,
+    pub language_name: String,
+    pub region_code: Option<String>,
+    pub timezone: Option<String>,
+    pub user_preferences: Option<Value>,
+    pub localized_messages: LocalizedMessages,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "contracts", derive(TS, JsonSchema))]
+pub struct LocalizedMessages {
+    pub welcome: String,
+    pub setup_complete: String,
+    pub ready_to_play: String,
+    pub next_steps: String,
+}
+
+#[cfg(feature = "contracts")]
+pub use export::export_contracts;
+
+#[cfg(feature = "contracts")]
+mod export {
+    use serde_json::json;
+    use std::path::Path;
+
+    use super::*;
+    use crate::api::response::{ApiError, ApiResponse};
+
+    // One socket event and the payload it carries
+    struct EventContract {
+        event: &'static str,
+        direction: &'static str,
+        type_name: fn() -> String,
+        export_ts: fn(&Path) -> Result<(), ts_rs::ExportError>,
+        schema: fn() -> schemars::schema::RootSchema,
+    }
+
+    impl EventContract {
+        fn of<T: TS + JsonSchema + 'static>(event: &'static str, direction: &'static str) -> Self {
+            Self {
+                event,
+                direction,
+                type_name: T::name,
+                export_ts: export_ts::<T>,
+                schema: schema::<T>,
+            }
+        }
+
+        // Server -> client events carry T inside the ApiResponse envelope
+        fn response<T: TS + JsonSchema + 'static>(event: &'static str) -> Self {
+            Self {
+                export_ts: export_response_ts::<T>,
+                ..Self::of::<ApiResponse<T>>(event, OUT)
+            }
+        }
+    }
+
+    fn export_ts<T: TS + 'static>(dir: &Path) -> Result<(), ts_rs::ExportError> {
+        T::export_all_to(dir)
+    }
+
+    // ApiResponse<T> is exported generically, so T needs its own file
+    fn export_response_ts<T: TS + 'static>(dir: &Path) -> Result<(), ts_rs::ExportError> {
+        ApiResponse::<T>::export_all_to(dir)?;
+        T::export_all_to(dir)
+    }
+
+    fn schema<T: JsonSchema>() -> schemars::schema::RootSchema {
+        schemars::schema_for!(T)
+    }
+
+    const IN: &str = "client_to_server";
+    const OUT: &str = "server_to_client";
+
+    fn contracts() -> Vec<EventContract> {
+        vec![
+            EventContract::of::<DeviceInfoRequest>("device:info", IN),
+            EventContract::response::<DeviceInfoAck>("device:info:ack"),
+            EventContract::of::<LoginRequest>("login", IN),
+            EventContract::response::<LoginSuccess>("login:success"),
+            EventContract::of::<VerifyOtpRequest>("verify:otp", IN),
+            EventContract::response::<OtpVerified>("otp:verified"),
+            EventContract::of::<SetProfileRequest>("set:profile", IN),
+            EventContract::response::<ProfileSet>("profile:set"),
+            EventContract::of::<SetLanguageRequest>("set:language", IN),
+            EventContract::response::<LanguageSet>("language:set"),
+            EventContract::of::<NotificationPreferencesRequest>("preferences:notifications", IN),
+            EventContract::of::<PreferencesGetRequest>("preferences:get", IN),
+            EventContract::of::<PreferencesSetRequest>("preferences:set", IN),
+            EventContract::of::<SettingsGetRequest>("settings:get", IN),
+            EventContract::response::<SettingsData>("settings:data"),
+            EventContract::of::<SettingsSetRequest>("settings:set", IN),
+            EventContract::response::<SettingsData>("settings:updated"),
+            EventContract::of::<ProgressGetRequest>("progress:get", IN),
+            EventContract::of::<ProgressUpdateRequest>("progress:update", IN),
+            EventContract::of::<ChallengeTodayRequest>("challenge:today", IN),
+            EventContract::of::<ChallengeClaimRequest>("challenge:claim", IN),
+            EventContract::of::<LeaderboardRequest>("leaderboard:get", IN),
+            EventContract::of::<FriendRequest>("friend:add", IN),
+            EventContract::of::<FriendRequest>("friend:remove", IN),
+            EventContract::of::<FriendListRequest>("friend:list", IN),
+            EventContract::of::<GiftSendRequest>("gift:send", IN),
+            EventContract::of::<InventoryGetRequest>("inventory:get", IN),
+            EventContract::of::<PromoRedeemRequest>("promo:redeem", IN),
+            EventContract::of::<BlockRequest>("user:block", IN),
+            EventContract::of::<BlockRequest>("user:unblock", IN),
+            EventContract::of::<ReportRequest>("user:report", IN),
+            EventContract::of::<DeviceManagementRequest>("devices:list", IN),
+            EventContract::of::<DeviceManagementRequest>("devices:remove", IN),
+            EventContract::of::<TimeSyncRequest>("time:sync", IN),
+            EventContract::of::<PingRequest>("ping", IN),
+            EventContract::of::<RoomJoinRequest>("room:join", IN),
+            EventContract::of::<RoomJoinRequest>("room:spectate", IN),
+            EventContract::of::<RoomJoinRequest>("room:unspectate", IN),
+            EventContract::of::<RoomViewerListRequest>("room:viewer_list", IN),
+            EventContract::of::<RoomUpdateRequest>("room:update", IN),
+            EventContract::of::<RoomStateAckRequest>("room:state_ack", IN),
+            EventContract::of::<RoomStateSyncRequest>("room:state_sync", IN),
+            EventContract::of::<SubscribeRequest>("subscribe", IN),
+            EventContract::of::<SubscribeRequest>("unsubscribe", IN),
+            EventContract::of::<RoomJoinRequest>("afk:back", IN),
+            EventContract::of::<DealerShuffleRequest>("dealer:shuffle", IN),
+            EventContract::of::<DealerDrawRequest>("dealer:draw", IN),
+            EventContract::of::<DealerRollRequest>("dealer:roll", IN),
+            EventContract::of::<RoomJoinRequest>("dealer:hand", IN),
+            EventContract::of::<RoomJoinRequest>("dealer:reveal", IN),
+            EventContract::of::<MatchmakingRequest>("matchmaking:join", IN),
+            EventContract::of::<MatchmakingRequest>("matchmaking:leave", IN),
+            EventContract::of::<MatchmakingRequest>("matchmaking:population", IN),
+            EventContract::of::<PartyRequest>("party:*", IN),
+            EventContract::of::<PlayerActionRequest>("player_action", IN),
+            EventContract::of::<ChatSendRequest>("chat:send", IN),
+            EventContract::of::<ChatEditRequest>("chat:edit", IN),
+            EventContract::of::<ChatDeleteRequest>("chat:delete", IN),
+            EventContract::of::<ChatTypingRequest>("chat:typing", IN),
+            EventContract::of::<ChatReadRequest>("chat:read", IN),
+            EventContract::of::<ChatReactRequest>("chat:react", IN),
+            EventContract::of::<ChatHistoryRequest>("chat:history", IN),
+            EventContract::of::<ApiError>("connection_error", OUT),
+        ]
+    }
+
+    // Writes <dir>/ts/*.ts, one JSON Schema per event under <dir>/schema/, and
+    // <dir>/events.json mapping each event to its type and schema file
+    pub fn export_contracts(dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let ts_dir = dir.join("ts");
+        let schema_dir = dir.join("schema");
+        std::fs::create_dir_all(&ts_dir)?;
+        std::fs::create_dir_all(&schema_dir)?;
+
+        let mut index = Vec::new();
+        for contract in contracts() {
+            (contract.export_ts)(&ts_dir)?;
+            let schema_file = format!("{}.json", contract.event.replace(':', "_").replace('*', "any"));
+            std::fs::write(schema_dir.join(&schema_file), serde_json::to_string_pretty(&(contract.schema)())?)?;
+            index.push(json!({
+                "event": contract.event,
+                "direction": contract.direction,
+                "type": (contract.type_name)(),
+                "schema": format!("schema/{}", schema_file),
+            }));
+        }
+        std::fs::write(dir.join("events.json"), serde_json::to_string_pretty(&index)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+use axum::routing::get;
+use tracing::{info, error};
+
+use crate::api::tls::TlsServer;
+use crate::managers::handler_metrics::HandlerMetrics;
+use crate::managers::handshake_audit::HandshakeAuditManager;
+use crate::managers::latency::LatencyManager;
+use crate::managers::metrics::MetricsManager;
+use crate::managers::room_state::RoomStateManager;
+
+const DEFAULT_METRICS_PORT: u16 = 9090;
+
+async fn metrics_handler() -> String {
+    LatencyManager::export_metrics().await;
+    let mut output = MetricsManager::render().await;
+    output.push_str(&HandlerMetrics::render());
+    output.push_str(&TlsServer::render());
+    output.push_str(&HandshakeAuditManager::render());
+    output.push_str(&RoomStateManager::render());
+    output
+}
+
+// Serve `/metrics` on its own port (the main listener only accepts Socket.IO traffic).
+// Disabled unless ENABLE_METRICS=true.
+pub fn spawn_metrics_server() {
+    let enabled = std::env::var("ENABLE_METRICS")
+        .map(|v| v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false);
+    if !enabled {
+        return;
+    }
+    let port = std::env::var("METRICS_PORT")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_METRICS_PORT);
+
+    tokio::spawn(async move {
+        let app = axum::Router::new().route("/metrics", get(metrics_handler));
+        match tokio::net::TcpListener::bind(("0.0.0.0", port)).await {
+            Ok(listener) => {
+                info!("📈 Metrics listening on 0.0.0.0:{}/metrics", port);
+                if let Err(e) = axum::serve(listener, app).await {
+                    error!("❌ Metrics server error: {}", e);
+                }
+            }
+            Err(e) => error!("❌ Failed to bind metrics port {}: {}", port, e),
+        }
+    });
+}
@@ -1,11 +1,23 @@
 use axum::{
-    extract::Request,
-    http::StatusCode,
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderMap, HeaderName, StatusCode},
     response::Response,
     middleware::Next,
 };
+use once_cell::sync::Lazy;
+use socketioxide::extract::SocketRef;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use tracing::warn;
 
+use crate::config::CONFIG;
+use crate::database::store::DataStore;
+use crate::managers::handshake_audit::HandshakeAuditManager;
+
+// Only Socket.IO, the health probe and the admin API are served; anything
+// else gets 403 and is recorded by HandshakeAuditManager
 pub async fn socket_io_validation(
+    State(data_service): State<Arc<dyn DataStore>>,
     request: Request,
     next: Next,
 ) -> Result<Response, StatusCode> {
@@ -18,9 +30,149 @@ pub async fn socket_io_validation(
         .map(|h| h.to_lowercase().contains("websocket"))
         .unwrap_or(false);
 
-    if !is_socket_io && !is_websocket {
+    // Load balancer / orchestrator health probe
+    let is_health_probe = request.uri().path() == "/health";
+    // Admin REST routes check their own token (api::admin)
+    let is_admin_api = request.uri().path().starts_with("/api/admin/");
+
+    if !is_socket_io && !is_websocket && !is_health_probe && !is_admin_api {
+        HandshakeAuditManager::record(&data_service, &request, "not_socket_io", StatusCode::FORBIDDEN.as_u16());
         return Err(StatusCode::FORBIDDEN);
     }
 
     Ok(next.run(request).await)
-} 
\ No newline at end of file
+} 
+
+// A configured TRUSTED_PROXIES entry: an address or CIDR block
+#[derive(Debug, Clone, Copy)]
+struct ProxyNetwork {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl ProxyNetwork {
+    fn parse(entry: &str) -> Option<Self> {
+        let (address, prefix) = match entry.split_once('/') {
+            Some((address, prefix)) => (address.parse::<IpAddr>().ok()?, Some(prefix.parse::<u8>().ok()?)),
+            None => (entry.parse::<IpAddr>().ok()?, None),
+        };
+        let max = if address.is_ipv4() { 32 } else { 128 };
+        let prefix = prefix.unwrap_or(max);
+        (prefix <= max).then_some(Self { network: address, prefix })
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, ip.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+static TRUSTED_PROXIES: Lazy<Vec<ProxyNetwork>> = Lazy::new(|| {
+    CONFIG.trusted_proxies.iter()
+        .filter_map(|entry| {
+            let network = ProxyNetwork::parse(entry);
+            if network.is_none() {
+                warn!("⚠️ Ignoring invalid TRUSTED_PROXIES entry {:?}", entry);
+            }
+            network
+        })
+        .collect()
+});
+
+// The address a request really came from. Behind a load balancer the TCP
+// peer is the balancer; when it is one of TRUSTED_PROXIES, the client is
+// taken from `Forwarded` (RFC 7239) or else `X-Forwarded-For`, read right to
+// left past further trusted hops, so addresses a client prepends itself are
+// never used. Added to every request by `resolve_client_ip`; sockets read it
+// from their handshake request.
+#[derive(Debug, Clone, Copy)]
+pub struct ClientIp {
+    pub ip: IpAddr,
+    pub via_trusted_proxy: bool,    // Proxy-set headers (forwarding, Geo-IP) can be believed
+}
+
+impl ClientIp {
+    fn is_trusted(ip: IpAddr) -> bool {
+        TRUSTED_PROXIES.iter().any(|network| network.contains(ip))
+    }
+
+    // `for=` addresses of a Forwarded header, nearest hop last
+    fn forwarded_for(value: &str) -> Vec<Option<IpAddr>> {
+        value.split(',')
+            .flat_map(|element| element.split(';'))
+            .filter_map(|pair| pair.split_once('='))
+            .filter(|(name, _)| name.trim().eq_ignore_ascii_case("for"))
+            .map(|(_, node)| {
+                let node = node.trim().trim_matches('"');
+                // [v6]:port, [v6], v4:port or v4; "unknown" and obfuscated names are None
+                let host = match node.strip_prefix('[') {
+                    Some(rest) => rest.split(']').next().unwrap_or_default(),
+                    None => node.split(':').next().unwrap_or_default(),
+                };
+                host.parse().ok()
+            })
+            .collect()
+    }
+
+    fn x_forwarded_for(value: &str) -> Vec<Option<IpAddr>> {
+        value.split(',').map(|hop| hop.trim().parse().ok()).collect()
+    }
+
+    pub fn resolve(peer: IpAddr, headers: &HeaderMap) -> Self {
+        let peer = peer.to_canonical();
+        if !Self::is_trusted(peer) {
+            return Self { ip: peer, via_trusted_proxy: false };
+        }
+        let joined = |name: header::HeaderName| {
+            let values: Vec<&str> = headers.get_all(name).iter().filter_map(|v| v.to_str().ok()).collect();
+            (!values.is_empty()).then(|| values.join(","))
+        };
+        let hops = match (joined(header::FORWARDED), joined(HeaderName::from_static("x-forwarded-for"))) {
+            (Some(forwarded), _) => Self::forwarded_for(&forwarded),
+            (None, Some(forwarded_for)) => Self::x_forwarded_for(&forwarded_for),
+            (None, None) => Vec::new(),
+        };
+        // Walk back from the proxy that connected to us; stop at the first hop it did not add
+        let mut ip = peer;
+        for hop in hops.into_iter().rev() {
+            match hop {
+                Some(hop) => {
+                    ip = hop.to_canonical();
+                    if !Self::is_trusted(ip) {
+                        break;
+                    }
+                }
+                None => break,
+            }
+        }
+        Self { ip, via_trusted_proxy: true }
+    }
+
+    // Resolved in the handshake request; the TCP peer for requests that skipped the middleware
+    pub fn of(socket: &SocketRef) -> Option<Self> {
+        let extensions = &socket.req_parts().extensions;
+        extensions.get::<ClientIp>().copied().or_else(|| {
+            extensions.get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(address)| Self { ip: address.ip().to_canonical(), via_trusted_proxy: false })
+        })
+    }
+}
+
+// Resolve the client address for everything after it (Socket.IO
+// handshakes, the admin API)
+pub async fn resolve_client_ip(mut request: Request, next: Next) -> Response {
+    if let Some(ConnectInfo(peer)) = request.extensions().get::<ConnectInfo<SocketAddr>>().copied() {
+        let client_ip = ClientIp::resolve(peer.ip(), request.headers());
+        request.extensions_mut().insert(client_ip);
+    }
+    next.run(request).await
+}
@@ -1 +1,6 @@
-pub mod middleware; 
\ No newline at end of file
+pub mod admin;
+pub mod contracts;
+pub mod middleware;
+pub mod metrics;
+pub mod response;
+pub mod tls;
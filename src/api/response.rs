@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::managers::correlation::Correlation;
+use crate::managers::validation::ValidationError;
+
+// Envelope carried by every socket response. The payload is flattened next to
+// the envelope fields, so clients see a single flat object:
+//   { "status": "success", "event": "...", "socket_id": "...", "timestamp": "...", ...payload }
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "contracts", derive(ts_rs::TS, schemars::JsonSchema))]
+pub struct ApiResponse<T = Value> {
+    pub status: String,
+    pub event: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub socket_id: Option<String>,      // Not set on room broadcasts
+    pub timestamp: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub request_id: Option<String>,     // Correlation id of the request being answered
+    #[serde(flatten)]
+    pub data: T,
+}
+
+impl<T: Serialize> ApiResponse<T> {
+    pub fn success(event: &str, data: T) -> Self {
+        Self {
+            status: "success".to_string(),
+            event: event.to_string(),
+            socket_id: None,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            request_id: Correlation::current(),
+            data,
+        }
+    }
+
+    // Non-error statuses other than "success" (e.g. "pong", "queued", "timeout")
+    pub fn with_status(mut self, status: &str) -> Self {
+        self.status = status.to_string();
+        self
+    }
+
+    pub fn for_socket(mut self, socket_id: impl ToString) -> Self {
+        self.socket_id = Some(socket_id.to_string());
+        self
+    }
+}
+
+// Error payload shared by connection_error and the per-domain error events
+// (room:error, party:error, ...)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "contracts", derive(ts_rs::TS, schemars::JsonSchema))]
+pub struct ApiError {
+    pub status: String,
+    pub error_code: String,
+    pub error_type: String,
+    pub field: String,
+    pub message: String,
+    pub details: Value,
+    pub timestamp: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub socket_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "contracts", ts(optional))]
+    pub request_id: Option<String>,
+    pub event: String,
+}
+
+impl ApiError {
+    // Defaults to the connection_error event with empty details. Building an
+    // error inside a handler counts that call as failed in the handler metrics.
+    pub fn new(error_code: &str, error_type: &str, field: &str, message: &str) -> Self {
+        Correlation::mark_failed();
+        Self {
+            status: "error".to_string(),
+            error_code: error_code.to_string(),
+            error_type: error_type.to_string(),
+            field: field.to_string(),
+            message: message.to_string(),
+            details: json!({}),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            socket_id: None,
+            request_id: Correlation::current(),
+            event: "connection_error".to_string(),
+        }
+    }
+
+    // SYSTEM_ERROR wrapping a database or other internal failure
+    pub fn system(error_code: &str, field: &str, message: &str, error: &dyn std::fmt::Display) -> Self {
+        Self::new(error_code, "SYSTEM_ERROR", field, message).with_details(json!({"error": error.to_string()}))
+    }
+
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = details;
+        self
+    }
+
+    pub fn on_event(mut self, event: &str) -> Self {
+        self.event = event.to_string();
+        self
+    }
+
+    pub fn for_socket(mut self, socket_id: impl ToString) -> Self {
+        self.socket_id = Some(socket_id.to_string());
+        self
+    }
+}
+
+impl From<Box<ValidationError>> for ApiError {
+    fn from(error: Box<ValidationError>) -> Self {
+        ApiError::new(&error.code, &error.error_type, &error.field, &error.message).with_details(error.details)
+    }
+}
@@ -0,0 +1,253 @@
+use axum::extract::Request;
+use axum::http::{header, StatusCode, Uri};
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::Router;
+use axum_server::accept::{Accept, DefaultAcceptor};
+use axum_server::tls_rustls::{RustlsAcceptor, RustlsConfig};
+use axum_server::Handle;
+use once_cell::sync::{Lazy, OnceCell};
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+use tokio_rustls::server::TlsStream;
+use tracing::{error, info, warn};
+
+use crate::config::CONFIG;
+use crate::managers::handler_metrics::DURATION_BUCKETS_MS;
+
+#[derive(Default)]
+struct HandshakeStats {
+    completed: HashMap<(String, String), u64>,  // By (protocol, cipher suite)
+    failed: HashMap<&'static str, u64>,         // By reason
+    buckets: [u64; DURATION_BUCKETS_MS.len()],
+    count: u64,
+    sum_ms: f64,
+}
+
+static HANDSHAKES: Lazy<Mutex<HandshakeStats>> = Lazy::new(|| Mutex::new(HandshakeStats::default()));
+// Open TLS connections are read from the server's handle
+static HANDLE: OnceCell<Handle> = OnceCell::new();
+
+// Direct HTTPS/WSS for deployments without a TLS-terminating proxy. The main
+// listener speaks TLS when TLS_CERT_PATH + TLS_KEY_PATH are set (PEM files,
+// reloaded when they change so renewals need no restart) or, in builds with
+// the acme feature, when TLS_ACME_DOMAINS is set (certificates from Let's
+// Encrypt via TLS-ALPN-01 on the same port). TLS_REDIRECT_PORT adds a plain
+// HTTP listener that redirects to HTTPS. Handshakes are counted by protocol
+// and cipher suite, failures by reason, for /metrics.
+pub struct TlsServer;
+
+impl TlsServer {
+    pub fn enabled() -> bool {
+        CONFIG.tls_cert_path.is_some() || CONFIG.tls_key_path.is_some() || !CONFIG.tls_acme_domains.is_empty()
+    }
+
+    // Serve `app` over TLS on the bound listener until the server stops
+    pub async fn serve(listener: tokio::net::TcpListener, app: Router) -> io::Result<()> {
+        // reqwest and rustls-acme may bring different rustls providers; pick one
+        let _ = rustls::crypto::ring::default_provider().install_default();
+        let config = Self::rustls_config().await?;
+        if let Some(port) = CONFIG.tls_redirect_port {
+            Self::spawn_redirect(port);
+        }
+
+        let handle = HANDLE.get_or_init(Handle::new).clone();
+        let acceptor = MeteredAcceptor {
+            inner: RustlsAcceptor::new(config).handshake_timeout(Duration::from_secs(CONFIG.tls_handshake_timeout_secs)),
+        };
+        axum_server::from_tcp(listener.into_std()?)
+            .acceptor(acceptor)
+            .handle(handle)
+            .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+            .await
+    }
+
+    async fn rustls_config() -> io::Result<RustlsConfig> {
+        match (&CONFIG.tls_cert_path, &CONFIG.tls_key_path) {
+            (Some(cert), Some(key)) => {
+                let config = RustlsConfig::from_pem_file(cert, key).await?;
+                info!("🔒 TLS enabled with certificate {}", cert);
+                Self::spawn_reload(config.clone(), cert.clone(), key.clone());
+                Ok(config)
+            }
+            (None, None) => Self::acme_config(),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "TLS_CERT_PATH and TLS_KEY_PATH must be set together")),
+        }
+    }
+
+    #[cfg(feature = "acme")]
+    fn acme_config() -> io::Result<RustlsConfig> {
+        use futures_util::StreamExt;
+        use rustls_acme::{caches::DirCache, AcmeConfig};
+
+        let mut state = AcmeConfig::new(CONFIG.tls_acme_domains.clone())
+            .contact(CONFIG.tls_acme_contact.iter().map(|email| format!("mailto:{}", email)))
+            .cache_option(CONFIG.tls_acme_cache_dir.clone().map(DirCache::new))
+            .directory_lets_encrypt(CONFIG.tls_acme_production)
+            .state();
+        let config = RustlsConfig::from_config(state.default_rustls_config());
+        info!("🔒 TLS enabled with ACME certificates for {} ({})",
+              CONFIG.tls_acme_domains.join(", "), if CONFIG.tls_acme_production { "Let's Encrypt" } else { "Let's Encrypt staging" });
+        tokio::spawn(async move {
+            while let Some(event) = state.next().await {
+                match event {
+                    Ok(event) => info!("🔒 ACME: {:?}", event),
+                    Err(e) => error!("❌ ACME: {:?}", e),
+                }
+            }
+        });
+        Ok(config)
+    }
+
+    #[cfg(not(feature = "acme"))]
+    fn acme_config() -> io::Result<RustlsConfig> {
+        Err(io::Error::new(io::ErrorKind::Unsupported,
+            "TLS_ACME_DOMAINS is set but the server was built without the acme feature; set TLS_CERT_PATH and TLS_KEY_PATH instead"))
+    }
+
+    fn modified(path: &str) -> Option<SystemTime> {
+        std::fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+
+    // Pick up renewed certificate files; a broken pair keeps the current one
+    fn spawn_reload(config: RustlsConfig, cert: String, key: String) {
+        tokio::spawn(async move {
+            let mut loaded = (Self::modified(&cert), Self::modified(&key));
+            let mut interval = tokio::time::interval(Duration::from_secs(CONFIG.tls_reload_secs));
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                let current = (Self::modified(&cert), Self::modified(&key));
+                if current == loaded {
+                    continue;
+                }
+                match config.reload_from_pem_file(&cert, &key).await {
+                    Ok(()) => {
+                        info!("🔒 Reloaded TLS certificate {}", cert);
+                        loaded = current;
+                    }
+                    Err(e) => warn!("⚠️ Failed to reload TLS certificate {}: {}", cert, e),
+                }
+            }
+        });
+    }
+
+    async fn redirect(request: Request) -> Response {
+        let Some(host) = request.headers().get(header::HOST).and_then(|h| h.to_str().ok()) else {
+            return (StatusCode::BAD_REQUEST, "Host header required").into_response();
+        };
+        // Drop the plain port; keep IPv6 brackets
+        let host = match host.rsplit_once(':') {
+            Some((name, port)) if !port.contains(']') => name,
+            _ => host,
+        };
+        let port = match CONFIG.server_port {
+            443 => String::new(),
+            port => format!(":{}", port),
+        };
+        let path = request.uri().path_and_query().map_or("/", |p| p.as_str());
+        match format!("https://{}{}{}", host, port, path).parse::<Uri>() {
+            Ok(target) => Redirect::permanent(&target.to_string()).into_response(),
+            Err(_) => (StatusCode::BAD_REQUEST, "Invalid Host header").into_response(),
+        }
+    }
+
+    fn spawn_redirect(port: u16) {
+        tokio::spawn(async move {
+            let app = Router::new().fallback(Self::redirect);
+            match tokio::net::TcpListener::bind((CONFIG.server_host.as_str(), port)).await {
+                Ok(listener) => {
+                    info!("↪️ Redirecting HTTP on {}:{} to HTTPS", CONFIG.server_host, port);
+                    if let Err(e) = axum::serve(listener, app).await {
+                        error!("❌ HTTP redirect server error: {}", e);
+                    }
+                }
+                Err(e) => error!("❌ Failed to bind HTTP redirect port {}: {}", port, e),
+            }
+        });
+    }
+
+    fn record_handshake<I>(result: Result<&TlsStream<I>, &io::Error>, elapsed: Duration) {
+        let mut stats = HANDSHAKES.lock().unwrap_or_else(|e| e.into_inner());
+        match result {
+            Ok(stream) => {
+                let (_, connection) = stream.get_ref();
+                let protocol = connection.protocol_version().map_or("unknown".to_string(), |v| format!("{:?}", v));
+                let cipher = connection.negotiated_cipher_suite().map_or("unknown".to_string(), |c| format!("{:?}", c.suite()));
+                *stats.completed.entry((protocol, cipher)).or_default() += 1;
+                let ms = elapsed.as_secs_f64() * 1000.0;
+                if let Some(bucket) = DURATION_BUCKETS_MS.iter().position(|bound| ms <= *bound) {
+                    stats.buckets[bucket] += 1;
+                }
+                stats.count += 1;
+                stats.sum_ms += ms;
+            }
+            Err(e) => {
+                let reason = match e.kind() {
+                    io::ErrorKind::TimedOut => "timeout",
+                    io::ErrorKind::InvalidData => "protocol",
+                    _ => "io",
+                };
+                *stats.failed.entry(reason).or_default() += 1;
+            }
+        }
+    }
+
+    // Prometheus text for /metrics; empty without TLS
+    pub fn render() -> String {
+        if !Self::enabled() {
+            return String::new();
+        }
+        let stats = HANDSHAKES.lock().unwrap_or_else(|e| e.into_inner());
+        let mut output = String::from("# TYPE tls_connections_active gauge\n");
+        output.push_str(&format!("tls_connections_active {}\n", HANDLE.get().map_or(0, |h| h.connection_count())));
+        output.push_str("# TYPE tls_handshakes_total counter\n");
+        for ((protocol, cipher), count) in &stats.completed {
+            output.push_str(&format!("tls_handshakes_total{{protocol=\"{}\",cipher=\"{}\"}} {}\n", protocol, cipher, count));
+        }
+        output.push_str("# TYPE tls_handshake_failures_total counter\n");
+        for (reason, count) in &stats.failed {
+            output.push_str(&format!("tls_handshake_failures_total{{reason=\"{}\"}} {}\n", reason, count));
+        }
+        output.push_str("# TYPE tls_handshake_duration_ms histogram\n");
+        let mut cumulative = 0;
+        for (bound, count) in DURATION_BUCKETS_MS.iter().zip(stats.buckets) {
+            cumulative += count;
+            output.push_str(&format!("tls_handshake_duration_ms_bucket{{le=\"{}\"}} {}\n", bound, cumulative));
+        }
+        output.push_str(&format!("tls_handshake_duration_ms_bucket{{le=\"+Inf\"}} {}\n", stats.count));
+        output.push_str(&format!("tls_handshake_duration_ms_sum {}\n", stats.sum_ms));
+        output.push_str(&format!("tls_handshake_duration_ms_count {}\n", stats.count));
+        output
+    }
+}
+
+// RustlsAcceptor that records every handshake
+#[derive(Clone)]
+struct MeteredAcceptor {
+    inner: RustlsAcceptor<DefaultAcceptor>,
+}
+
+impl<I, S> Accept<I, S> for MeteredAcceptor
+where
+    I: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+    S: Send + 'static,
+{
+    type Stream = TlsStream<I>;
+    type Service = S;
+    type Future = Pin<Box<dyn Future<Output = io::Result<(TlsStream<I>, S)>> + Send>>;
+
+    fn accept(&self, stream: I, service: S) -> Self::Future {
+        let started = Instant::now();
+        let handshake = self.inner.accept(stream, service);
+        Box::pin(async move {
+            let accepted = handshake.await;
+            TlsServer::record_handshake(accepted.as_ref().map(|(stream, _)| stream), started.elapsed());
+            accepted
+        })
+    }
+}
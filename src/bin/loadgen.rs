@@ -0,0 +1,276 @@
+// Load generator: N simulated players running the full auth and gameplay flow
+// against a running server, then a latency report per step.
+//
+//   cargo run --release --features loadgen --bin loadgen -- --clients 200 --ramp 20
+//
+// The server must run with DEV_MODE=true so login:success carries the OTP.
+// Options (all optional):
+//   --url URL          server address                  (default http://127.0.0.1:3002)
+//   --clients N        simulated players               (default 50)
+//   --ramp N           new players started per second  (default 10)
+//   --turns N          own turns each player plays     (default 5)
+//   --think-ms N       delay before acting on a turn   (default 200)
+//   --mobile-base N    first mobile number is 9 followed by N as 9 digits (default 0)
+//   --timeout-secs N   wait per server response        (default 60; matchmaking may wait for a bot)
+
+use futures_util::FutureExt;
+use rust_socketio::{
+    asynchronous::{Client, ClientBuilder},
+    Payload,
+};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+use tokio::sync::Mutex;
+
+#[derive(Debug, Clone)]
+struct Options {
+    url: String,
+    clients: u32,
+    ramp: u32,
+    turns: u32,
+    think_ms: u64,
+    mobile_base: u64,
+    timeout: Duration,
+}
+
+impl Options {
+    fn from_args() -> Result<Self, String> {
+        let mut options = Options {
+            url: "http://127.0.0.1:3002".to_string(),
+            clients: 50,
+            ramp: 10,
+            turns: 5,
+            think_ms: 200,
+            mobile_base: 0,
+            timeout: Duration::from_secs(60),
+        };
+        let mut args = std::env::args().skip(1);
+        while let Some(flag) = args.next() {
+            let value = args.next().ok_or_else(|| format!("missing value for {}", flag))?;
+            let number = || value.parse::<u64>().map_err(|_| format!("{} expects a number, got {}", flag, value));
+            match flag.as_str() {
+                "--url" => options.url = value.clone(),
+                "--clients" => options.clients = number()? as u32,
+                "--ramp" => options.ramp = (number()? as u32).max(1),
+                "--turns" => options.turns = number()? as u32,
+                "--think-ms" => options.think_ms = number()?,
+                "--mobile-base" => options.mobile_base = number()?,
+                "--timeout-secs" => options.timeout = Duration::from_secs(number()?),
+                _ => return Err(format!("unknown option {}", flag)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+// Payload protocol the simulated clients speak (src/managers/protocol.rs)
+const PROTOCOL_VERSION: u32 = 2;
+
+// Flow steps in report order
+const STEPS: &[&str] = &["connect", "device:info", "login", "verify:otp", "set:profile", "gameplay:connect", "matchmaking", "player_action"];
+
+// Latency samples and failures per flow step
+#[derive(Default)]
+struct Stats {
+    samples: BTreeMap<&'static str, Vec<Duration>>,
+    failures: BTreeMap<&'static str, u32>,
+    completed: u32,
+}
+
+impl Stats {
+    fn print(&self, elapsed: Duration, clients: u32) {
+        println!();
+        println!("{} of {} players completed in {:.1}s", self.completed, clients, elapsed.as_secs_f64());
+        println!("{:<20} {:>7} {:>9} {:>9} {:>9} {:>9} {:>7}", "step", "count", "p50 ms", "p90 ms", "p99 ms", "max ms", "failed");
+        for &step in STEPS {
+            let mut samples = self.samples.get(step).cloned().unwrap_or_default();
+            samples.sort();
+            let percentile = |p: f64| {
+                if samples.is_empty() {
+                    return 0.0;
+                }
+                let index = ((samples.len() as f64 * p).ceil() as usize).clamp(1, samples.len()) - 1;
+                samples[index].as_secs_f64() * 1000.0
+            };
+            println!(
+                "{:<20} {:>7} {:>9.1} {:>9.1} {:>9.1} {:>9.1} {:>7}",
+                step,
+                samples.len(),
+                percentile(0.50),
+                percentile(0.90),
+                percentile(0.99),
+                percentile(1.0),
+                self.failures.get(step).copied().unwrap_or(0)
+            );
+        }
+    }
+}
+
+// Socket.IO connection that queues received events for in-order waiting
+struct SimClient {
+    client: Client,
+    events: UnboundedReceiver<(String, Value)>,
+}
+
+impl SimClient {
+    async fn connect(url: &str, namespace: &str, mut auth: Value) -> Result<Self, String> {
+        let (tx, events) = unbounded_channel();
+        auth["protocol_version"] = json!(PROTOCOL_VERSION);
+        let client = ClientBuilder::new(url)
+            .namespace(namespace)
+            .auth(auth)
+            .on_any(move |event, payload, _| {
+                let tx = tx.clone();
+                async move {
+                    let data = match payload {
+                        Payload::Text(mut values) if !values.is_empty() => values.remove(0),
+                        _ => Value::Null,
+                    };
+                    let _ = tx.send((String::from(event), data));
+                }
+                .boxed()
+            })
+            .connect()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(Self { client, events })
+    }
+
+    async fn emit(&self, event: &str, data: Value) -> Result<(), String> {
+        self.client.emit(event, data).await.map_err(|e| e.to_string())
+    }
+
+    // Next event matching `accept`; error events for the flow end the wait
+    async fn wait_for(&mut self, timeout: Duration, accept: impl Fn(&str, &Value) -> bool) -> Result<(String, Value), String> {
+        let wait = async {
+            while let Some((name, data)) = self.events.recv().await {
+                if accept(&name, &data) {
+                    return Ok((name, data));
+                }
+                if name.ends_with("error") || name == "otp:verification_failed" {
+                    return Err(format!("{}: {}", name, data["error_code"]));
+                }
+            }
+            Err("connection closed".to_string())
+        };
+        tokio::time::timeout(timeout, wait).await.map_err(|_| "timed out".to_string())?
+    }
+
+    async fn request(&mut self, event: &str, data: Value, response: &'static str, timeout: Duration) -> Result<Value, String> {
+        self.emit(event, data).await?;
+        self.wait_for(timeout, |name, _| name == response).await.map(|(_, data)| data)
+    }
+}
+
+// Time one step and record its latency, or its failure
+async fn timed<T>(stats: &Mutex<Stats>, step: &'static str, future: impl std::future::Future<Output = Result<T, String>>) -> Result<T, String> {
+    let started = Instant::now();
+    let result = future.await;
+    let mut stats = stats.lock().await;
+    match &result {
+        Ok(_) => stats.samples.entry(step).or_default().push(started.elapsed()),
+        Err(_) => *stats.failures.entry(step).or_default() += 1,
+    }
+    result.map_err(|e| format!("{}: {}", step, e))
+}
+
+async fn run_player(options: Arc<Options>, stats: Arc<Mutex<Stats>>, index: u32) -> Result<(), String> {
+    let timeout = options.timeout;
+    let mobile_no = format!("9{:09}", options.mobile_base + index as u64);
+    let device_id = format!("loadgen-{}", index);
+    let fcm_token = format!("loadgen-{}-{}", index, "x".repeat(120));
+
+    // Auth flow on the main namespace
+    let mut main = timed(&stats, "connect", SimClient::connect(&options.url, "/", json!({}))).await?;
+    timed(&stats, "device:info", main.request("device:info", json!({
+        "device_id": device_id,
+        "device_type": "loadgen",
+        "timestamp": chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string()
+    }), "device:info:ack", timeout)).await?;
+    let login = timed(&stats, "login", main.request("login", json!({
+        "mobile_no": mobile_no,
+        "device_id": device_id,
+        "fcm_token": fcm_token
+    }), "login:success", timeout)).await?;
+    let session_token = login["session_token"].as_str().unwrap_or_default().to_string();
+    let otp = login["otp"].as_i64().ok_or("login: no otp in login:success (is DEV_MODE on?)")?;
+    let verified = timed(&stats, "verify:otp", main.request("verify:otp", json!({
+        "mobile_no": mobile_no,
+        "session_token": session_token,
+        "otp": otp.to_string(),
+        "device_id": device_id,
+        "fcm_token": fcm_token
+    }), "otp:verified", timeout)).await?;
+    let player_id = verified["user_id"].as_str().unwrap_or_default().to_string();
+    let jwt_token = verified["jwt_token"].as_str().unwrap_or_default().to_string();
+    timed(&stats, "set:profile", main.request("set:profile", json!({
+        "mobile_no": mobile_no,
+        "session_token": session_token,
+        "full_name": format!("Load Player {}", index),
+        "state": "Loadtest"
+    }), "profile:set", timeout)).await?;
+
+    // Gameplay: queue, then act on our own turns. Game namespaces play as the JWT's user
+    let mut gameplay = timed(&stats, "gameplay:connect", SimClient::connect(&options.url, "/gameplay", json!({ "jwt_token": jwt_token }))).await?;
+    let found = timed(&stats, "matchmaking", gameplay.request("matchmaking:join", json!({
+        "player_id": player_id
+    }), "match:found", timeout)).await?;
+    let room_id = found["room_id"].as_str().unwrap_or_default().to_string();
+
+    let mut turns_played = 0;
+    while turns_played < options.turns {
+        let (_, turn) = gameplay.wait_for(timeout, |name, data| {
+            name == "turn:started" && data["player_id"] == player_id.as_str()
+        }).await.map_err(|e| format!("turn:started: {}", e))?;
+        tokio::time::sleep(Duration::from_millis(options.think_ms)).await;
+        let turn_number = turn["turn_number"].clone();
+        timed(&stats, "player_action", async {
+            gameplay.emit("player_action", json!({
+                "room_id": room_id,
+                "player_id": player_id,
+                "action": {"type": "loadgen", "turn": turn_number}
+            })).await?;
+            gameplay.wait_for(timeout, |name, data| name == "player_action" && data["player_id"] == player_id.as_str()).await
+        }).await?;
+        turns_played += 1;
+    }
+
+    let _ = gameplay.client.disconnect().await;
+    let _ = main.client.disconnect().await;
+    stats.lock().await.completed += 1;
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() {
+    let options = match Options::from_args() {
+        Ok(options) => Arc::new(options),
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            std::process::exit(2);
+        }
+    };
+    println!("🚀 {} players against {} ({} per second, {} turns each)", options.clients, options.url, options.ramp, options.turns);
+
+    let stats = Arc::new(Mutex::new(Stats::default()));
+    let started = Instant::now();
+    let spawn_interval = Duration::from_secs_f64(1.0 / options.ramp as f64);
+    let mut players = Vec::new();
+    for index in 0..options.clients {
+        let (options, stats) = (options.clone(), stats.clone());
+        players.push(tokio::spawn(async move {
+            if let Err(e) = run_player(options, stats, index).await {
+                eprintln!("⚠️ player {}: {}", index, e);
+            }
+        }));
+        tokio::time::sleep(spawn_interval).await;
+    }
+    for player in players {
+        let _ = player.await;
+    }
+
+    stats.lock().await.print(started.elapsed(), options.clients);
+}
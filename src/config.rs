@@ -0,0 +1,398 @@
+use once_cell::sync::Lazy;
+
+// Process-wide configuration, read once from the environment on first use
+pub static CONFIG: Lazy<AppConfig> = Lazy::new(AppConfig::from_env);
+
+#[derive(Debug, Clone)]
+pub struct AppConfig {
+    pub server_host: String,
+    pub server_port: u16,
+    pub tls_cert_path: Option<String>,          // PEM certificate chain; serves HTTPS/WSS directly together with TLS_KEY_PATH
+    pub tls_key_path: Option<String>,           // PEM private key
+    pub tls_reload_secs: u64,                   // How often the certificate files are checked for renewals
+    pub tls_acme_domains: Vec<String>,          // Domains to get Let's Encrypt certificates for (needs the acme feature)
+    #[cfg(feature = "acme")]
+    pub tls_acme_contact: Vec<String>,          // Contact emails for the ACME account
+    #[cfg(feature = "acme")]
+    pub tls_acme_cache_dir: Option<String>,     // Keeps ACME account and certificates across restarts
+    #[cfg(feature = "acme")]
+    pub tls_acme_production: bool,              // Let's Encrypt production instead of staging
+    pub tls_redirect_port: Option<u16>,         // Plain HTTP port that redirects to HTTPS; unset serves nothing there
+    pub tls_handshake_timeout_secs: u64,        // Connections that have not finished the TLS handshake by then are dropped
+    pub dev_mode: bool,     // Echoes OTPs back in login:success - never enable in production
+    pub test_otp_mobile_numbers: Vec<String>,   // QA / app-store review accounts
+    pub test_otp_code: Option<i32>,             // Fixed OTP accepted for those accounts
+    pub otp_length: u32,                        // Digits of an OTP, 4-8, where no OTP_POLICIES entry says otherwise
+    pub otp_expiry_secs: i64,                   // How long an OTP is valid, likewise
+    pub otp_max_verify_attempts: u32,           // verify:otp attempts per session, likewise
+    pub geoip_country_header: Option<String>,   // Header the proxy sets to the client IP's country (e.g. CF-IPCountry)
+    pub geoip_region_header: Option<String>,    // Header the proxy sets to the client IP's state code (e.g. CF-Region-Code)
+    pub compliance_restricted_states: Vec<String>,  // Indian states (names or ISO 3166-2 codes) where real-money games are refused
+    pub compliance_allowed_users: Vec<String>,  // User ids let into games from a restricted state, e.g. reviewers
+    pub compliance_blocked_users: Vec<String>,  // User ids never let into games, wherever they are
+    pub handshake_audit_max_bytes: u64,         // Size of the capped rejected_handshakes collection
+    pub handshake_audit_per_minute: u32,        // Rejected requests stored and logged per minute; the rest are only counted
+    pub trusted_proxies: Vec<String>,           // Load balancer addresses/CIDRs whose forwarding headers name the real client
+    pub namespace_allowlist: Vec<String>,       // Namespaces served besides "/"; empty serves every game mode and /admin
+    pub namespace_policies: Vec<String>,        // "<namespace>=<policy>" entries: public, session, jwt or admin
+    pub two_step_issuer: String,                // Account issuer shown in authenticator apps
+    pub age_of_majority: u32,                   // Younger users need parental controls to spend coins
+    pub age_gate_require_dob: bool,             // Users without a date of birth are treated as minors
+    pub captcha_provider: String,               // "turnstile" or "hcaptcha"
+    pub captcha_site_key: Option<String>,       // Sent to clients so they can render the widget
+    pub captcha_secret_key: Option<String>,     // Verifies tokens with the provider; CAPTCHA is off without it
+    pub captcha_window_secs: i64,               // Login attempts are counted over this trailing window
+    pub captcha_mobile_logins: usize,           // Attempts per mobile number within the window before a CAPTCHA is required
+    pub captcha_ip_logins: usize,               // Attempts per client IP within the window before a CAPTCHA is required
+    pub payload_signing_mode: String,           // "off", "report" (log unsigned events) or "enforce" (refuse them, the default)
+    pub payload_signing_secret: Option<String>, // Derives per-session signing keys; required, and not the JWT secret
+    pub payload_signature_max_age_secs: u64,    // Signatures older than this (or this far in the future) are refused
+    pub auth_nonce_required: bool,              // verify:otp without the nonce from login:success is refused
+    pub session_idle_timeout_minutes: i64,      // Session expires after this long without activity
+    pub session_absolute_timeout_hours: i64,    // Session expires this long after login regardless of activity
+    pub in_memory_store: bool,  // DATA_STORE=memory: keep all data in process instead of MongoDB (lost on restart)
+    pub chaos_mode: bool,                   // Fault injection for resilience testing - only honoured with DEV_MODE
+    pub chaos_targets: Vec<String>,         // "mongo" and/or "emit"; empty means both
+    pub chaos_failure_percent: f64,         // Share of targeted calls that fail outright
+    pub chaos_delay_percent: f64,           // Share of targeted calls that are delayed
+    pub chaos_max_delay_ms: u64,            // Injected delays are random up to this
+    pub admin_api_token: Option<String>,    // Shared secret for the /admin namespace; unset disables it
+    pub impersonation_max_minutes: i64,     // Longest a support impersonation may run before it ends itself
+    pub impersonation_disclose: bool,       // Tell users with support:session while their emits are mirrored
+    pub write_queue_capacity: usize,        // Event writes buffered during MongoDB outages; 0 disables buffering
+    pub event_dead_letter_file: String,     // Failed event writes parked while MongoDB cannot take them
+    pub event_dead_letter_max_bytes: u64,   // Size of the capped event_dead_letters collection
+    pub collection_prefix: String,          // Prepended to every MongoDB collection name, e.g. "staging_"
+    pub environment: Option<String>,        // Environment name the databases must be marked with (APP_ENVIRONMENT)
+    pub backup_store_url: Option<String>,   // s3://bucket/prefix or file:///path for admin-triggered backups; unset disables them
+    pub export_store_url: Option<String>,   // s3://bucket/prefix or file:///path for event exports; unset disables them
+    pub export_schedule_collections: Vec<String>, // Event collections exported every day for the previous UTC day; empty disables the daily export
+    pub export_schedule_format: String,     // "parquet" or "csv" for the daily export
+    pub warehouse_collections: Vec<String>, // Event collections the warehouse sink ships; empty ships every one
+    pub warehouse_table_prefix: String,     // Warehouse table of a collection is this prefix plus its name
+    pub warehouse_interval_secs: u64,       // Time between warehouse sink runs
+    pub warehouse_batch_size: usize,        // Events per warehouse request
+    pub warehouse_max_attempts: u32,        // Failed runs before a batch goes to warehouse_dead_letters
+    pub warehouse_settle_secs: u64,         // Events younger than this wait for the next run
+    pub outbox_interval_secs: u64,          // Time between outbox dispatcher runs
+    pub outbox_batch_size: usize,           // Outbox messages delivered per tenant and run at most
+    pub outbox_max_attempts: u32,           // Failed deliveries before an outbox message is marked failed
+    pub user_cache_capacity: usize,         // Users cached per tenant for lookups by mobile number or user id; 0 disables the cache
+    pub user_cache_ttl_secs: u64,           // A cached user is reloaded after this long even without a change event
+    pub snowflake_node_id: u64,             // Node id in the snowflake ids this server makes; unique per server, 0-1023
+    pub startup_fail_fast: bool,            // Abort startup when the self-check finds a problem; false only logs the report
+    // MongoDB client tuning; unset keeps the value from MONGODB_URI or the driver default
+    pub mongo_max_pool_size: Option<u32>,
+    pub mongo_min_pool_size: Option<u32>,
+    pub mongo_connect_timeout_ms: Option<u64>,
+    pub mongo_server_selection_timeout_ms: Option<u64>,
+    pub mongo_max_idle_time_ms: Option<u64>,
+    pub mongo_retry_writes: Option<bool>,
+    pub mongo_health_check_interval_secs: u64,  // How often MongoDB is pinged to track its health
+    pub slow_query_ms: u64,                     // Queries taking at least this long are logged; 0 disables the log
+    pub room_snapshot_interval_secs: u64,       // Changed gameplay rooms are saved this often; 0 disables snapshots
+    pub room_snapshot_every_turns: u32,         // Also save a room as soon as this many turns have ended; 0 turns this off
+    pub room_abandon_after_secs: i64,           // Rooms are closed once their last human player has been gone this long
+    pub room_reaper_interval_secs: u64,         // How often abandoned rooms are looked for
+    pub match_rake_percent: f64,                // Share of a wagered match's pot kept as platform commission
+    pub revenue_rollup_interval_secs: u64,      // How often today's and yesterday's revenue rollups are recomputed
+    pub room_recovery_max_age_secs: i64,        // Snapshots older than this are not restored on startup
+    pub spectator_broadcast_interval_ms: u64,   // Shortest gap between room:spectators updates to a room
+    pub room_update_window_ms: u64,             // room:update deltas within this long of the first are sent as one room:state; 0 sends each
+    pub room_state_keyframe_every: u64,         // Every this many room:state broadcasts go out as keyframes; 0 only when needed
+    pub afk_warn_after_secs: i64,               // Idle time in a running match before afk:warning; 0 turns AFK detection off
+    pub afk_action_after_secs: i64,             // Idle time after which the player forfeits or a bot takes the seat
+    pub afk_action: String,                     // "bot" or "forfeit"; staked matches and players without other humans always forfeit
+    pub afk_penalty_threshold: usize,           // AFK removals within the window before matchmaking cooldowns start
+    pub afk_penalty_window_hours: i64,          // How far back AFK removals count
+    pub afk_penalty_cooldown_secs: i64,         // First cooldown; doubles with every further removal in the window
+    pub daily_challenge_count: usize,           // Challenges generated per UTC day (at most one per kind)
+    pub retention_report_weeks: u32,            // Registration weeks covered by the daily retention report
+    pub season_base_rating: i64,                // Rating of a player's first game in a season
+    pub season_soft_reset_factor: f64,          // Share of the distance from the base rating kept at rollover
+    pub season_check_interval_secs: u64,        // How often the scheduler checks for season start and end
+    pub gift_max_coins: i64,                    // Most coins in a single gift
+    pub gift_daily_limit: usize,                // Gifts a user may send per UTC day
+    pub gift_daily_coin_limit: i64,             // Coins a user may gift per UTC day
+    pub gift_min_account_age_hours: i64,        // Accounts younger than this cannot send gifts
+    pub gift_min_device_age_hours: i64,         // Nor can a device first seen more recently than this
+    pub promo_max_failures_per_hour: usize,     // Unknown promo codes a user may try per hour before being refused
+    pub risk_scan_interval_secs: u64,           // How often the anomaly scan runs
+    pub risk_scan_window_hours: i64,            // Matches and gifts from this far back are scanned
+    pub risk_min_pair_matches: usize,           // Decided matches two players need before their results are judged
+    pub risk_gift_dump_coins: i64,              // One-way coins between two users that count as dumping
+    pub risk_limit_score: u32,                  // Risk score (0-100) at which gifts and ranked results are blocked
+    pub report_hourly_limit: usize,             // Reports a user may file per rolling hour
+    pub report_repeat_window_hours: i64,        // A user cannot report the same user again within this window
+    pub chat_rate_limit: usize,                 // Chat messages a user may send per CHAT_RATE_WINDOW_SECS
+    pub chat_rate_window_secs: i64,
+    pub chat_flood_mute_secs: i64,              // First automatic mute for flooding; doubles with each repeat that day
+    pub chat_flood_max_mute_secs: i64,          // Longest automatic mute
+    pub chat_edit_window_secs: i64,             // How long after sending a message it can be edited or deleted
+    pub chat_retention_days: i64,               // Chat messages older than this are purged
+    pub chat_typing_throttle_ms: i64,           // Shortest gap between relayed chat:typing of one player in a room
+    pub chat_link_policy: String,               // "allow" (all but the denylist), "allowlist" (only the allowlist) or "block"
+    pub chat_link_allowlist: Vec<String>,       // Hosts links may point to under the allowlist policy; subdomains count
+    pub chat_link_denylist: Vec<String>,        // Hosts links may never point to
+    pub safe_browsing_api_key: Option<String>,  // Google Safe Browsing lookup of chat links; skipped without it
+    pub chat_image_hosts: Vec<String>,          // Hosts image attachments may be served from; none means no attachments
+    pub chat_image_max_bytes: i64,              // Largest image attachment
+    pub slo_check_interval_secs: u64,           // How often SLO error budgets are checked
+    pub slo_window_secs: u64,                   // SLOs are judged on the calls of this trailing window
+    pub slo_alert_webhook_url: Option<String>,  // Receives SLO alerts as JSON
+    pub slo_slack_webhook_url: Option<String>,  // Slack incoming webhook for SLO alerts
+    pub funnel_broadcast_interval_ms: u64,      // Shortest gap between funnel:update broadcasts to /admin
+    pub health_score_interval_secs: u64,        // How often the deployment health score is computed and sent
+    pub health_score_window_secs: u64,          // The score judges this trailing window
+    pub health_max_error_rate: f64,             // Handler error rate that scores the error signal 0
+    pub health_p99_target_ms: f64,              // p99 handler latency above this lowers the score; twice it scores 0
+    pub health_min_calls: u64,                  // Fewer calls in the window leave errors and latency unscored
+    pub health_storm_factor: f64,               // Connects in an interval this many times the window average are a reconnect storm
+    pub health_storm_min_connects: u64,         // ... once they reach at least this many
+    pub health_rollback_score: u32,             // Scores below this are unhealthy
+    pub health_rollback_checks: u32,            // Unhealthy checks in a row before a rollback is recommended
+    pub connection_cap: u64,                    // Sockets this server is sized for; 0 turns off the capacity alert
+    pub connection_alert_ratio: f64,            // Share of connection_cap at which the capacity alert fires
+    pub game_config_poll_secs: u64,             // How often game configs are reloaded where MongoDB has no change streams
+    pub rule_plugins_dir: Option<String>,       // Directory of WASM rule plugins (needs the wasm-plugins feature)
+    #[cfg(feature = "wasm-plugins")]
+    pub rule_plugin_fuel: u64,                  // Instructions (wasmtime fuel) a plugin may run per call
+    #[cfg(feature = "wasm-plugins")]
+    pub rule_plugin_memory_mb: usize,           // Linear memory a plugin may grow to
+    #[cfg(feature = "wasm-plugins")]
+    pub rule_plugin_poll_secs: u64,             // How often plugin files are checked for updates
+    pub emit_retry_attempts: u32,               // Retries of an emit that failed to send before giving up
+    pub emit_retry_delay_ms: u64,               // Wait before the first retry; doubles with each one
+    pub notification_default_language: String,  // Template language used when the user's language has none
+    pub notification_template_cache_secs: u64,  // How long stored notification templates are cached
+}
+
+impl AppConfig {
+    fn from_env() -> Self {
+        Self {
+            server_host: std::env::var("SERVER_HOST").unwrap_or_else(|_| "0.0.0.0".to_string()),
+            server_port: env_parse("SERVER_PORT", 3002),
+            tls_cert_path: env_opt::<String>("TLS_CERT_PATH").filter(|p| !p.is_empty()),
+            tls_key_path: env_opt::<String>("TLS_KEY_PATH").filter(|p| !p.is_empty()),
+            tls_reload_secs: env_parse("TLS_RELOAD_SECS", 300_u64).max(10),
+            tls_acme_domains: env_list("TLS_ACME_DOMAINS"),
+            #[cfg(feature = "acme")]
+            tls_acme_contact: env_list("TLS_ACME_CONTACT"),
+            #[cfg(feature = "acme")]
+            tls_acme_cache_dir: env_opt::<String>("TLS_ACME_CACHE_DIR").filter(|d| !d.is_empty()),
+            #[cfg(feature = "acme")]
+            tls_acme_production: env_bool("TLS_ACME_PRODUCTION", false),
+            tls_redirect_port: env_opt::<u16>("TLS_REDIRECT_PORT"),
+            tls_handshake_timeout_secs: env_parse("TLS_HANDSHAKE_TIMEOUT_SECS", 10_u64).max(1),
+            dev_mode: env_bool("DEV_MODE", false),
+            test_otp_mobile_numbers: env_list("TEST_OTP_MOBILE_NUMBERS"),
+            test_otp_code: std::env::var("TEST_OTP_CODE")
+                .ok()
+                .and_then(|v| v.trim().parse().ok())
+                .filter(|code| (1000..=99_999_999).contains(code)),
+            otp_length: env_parse("OTP_LENGTH", 6_u32).clamp(4, 8),
+            otp_expiry_secs: env_parse("OTP_EXPIRY_SECS", 1800_i64).max(60),
+            otp_max_verify_attempts: env_parse("OTP_MAX_VERIFY_ATTEMPTS", 5_u32).max(1),
+            geoip_country_header: env_opt::<String>("GEOIP_COUNTRY_HEADER").filter(|h| !h.is_empty()),
+            geoip_region_header: env_opt::<String>("GEOIP_REGION_HEADER").filter(|h| !h.is_empty()),
+            compliance_restricted_states: env_list("COMPLIANCE_RESTRICTED_STATES"),
+            compliance_allowed_users: env_list("COMPLIANCE_ALLOWED_USERS"),
+            compliance_blocked_users: env_list("COMPLIANCE_BLOCKED_USERS"),
+            handshake_audit_max_bytes: env_parse("HANDSHAKE_AUDIT_MAX_BYTES", 16_777_216_u64).max(4096),
+            handshake_audit_per_minute: env_parse("HANDSHAKE_AUDIT_PER_MINUTE", 600_u32),
+            trusted_proxies: env_list("TRUSTED_PROXIES"),
+            namespace_allowlist: env_list("NAMESPACE_ALLOWLIST"),
+            namespace_policies: env_list("NAMESPACE_POLICIES"),
+            two_step_issuer: env_opt::<String>("TWO_STEP_ISSUER").filter(|v| !v.is_empty()).unwrap_or_else(|| "Game".to_string()),
+            age_of_majority: env_parse("AGE_OF_MAJORITY", 18_u32),
+            age_gate_require_dob: env_bool("AGE_GATE_REQUIRE_DOB", false),
+            captcha_provider: std::env::var("CAPTCHA_PROVIDER").map(|v| v.trim().to_ascii_lowercase())
+                .ok()
+                .filter(|v| v == "hcaptcha")
+                .unwrap_or_else(|| "turnstile".to_string()),
+            captcha_site_key: env_opt::<String>("CAPTCHA_SITE_KEY").filter(|k| !k.is_empty()),
+            captcha_secret_key: env_opt::<String>("CAPTCHA_SECRET_KEY").filter(|k| !k.is_empty()),
+            captcha_window_secs: env_parse("CAPTCHA_WINDOW_SECS", 600_i64).max(1),
+            captcha_mobile_logins: env_parse("CAPTCHA_MOBILE_LOGINS", 3_usize),
+            captcha_ip_logins: env_parse("CAPTCHA_IP_LOGINS", 10_usize),
+            payload_signing_mode: std::env::var("PAYLOAD_SIGNING_MODE").map(|v| v.trim().to_ascii_lowercase())
+                .ok()
+                .filter(|v| !v.is_empty())
+                .unwrap_or_else(|| "enforce".to_string()),
+            payload_signing_secret: env_opt::<String>("PAYLOAD_SIGNING_SECRET").filter(|k| !k.is_empty()),
+            payload_signature_max_age_secs: env_parse("PAYLOAD_SIGNATURE_MAX_AGE_SECS", 120_u64).max(5),
+            auth_nonce_required: env_bool("AUTH_NONCE_REQUIRED", true),
+            session_idle_timeout_minutes: env_parse("SESSION_IDLE_TIMEOUT_MINUTES", 1440),
+            session_absolute_timeout_hours: env_parse("SESSION_ABSOLUTE_TIMEOUT_HOURS", 720),
+            in_memory_store: std::env::var("DATA_STORE").map(|v| v.trim().eq_ignore_ascii_case("memory")).unwrap_or(false),
+            chaos_mode: env_bool("CHAOS_MODE", false),
+            chaos_targets: env_list("CHAOS_TARGETS").into_iter().map(|t| t.to_ascii_lowercase()).collect(),
+            chaos_failure_percent: env_parse("CHAOS_FAILURE_PERCENT", 10.0_f64).clamp(0.0, 100.0),
+            chaos_delay_percent: env_parse("CHAOS_DELAY_PERCENT", 20.0_f64).clamp(0.0, 100.0),
+            chaos_max_delay_ms: env_parse("CHAOS_MAX_DELAY_MS", 2000),
+            admin_api_token: std::env::var("ADMIN_API_TOKEN").ok().filter(|t| !t.trim().is_empty()),
+            impersonation_max_minutes: env_parse("IMPERSONATION_MAX_MINUTES", 30_i64).max(1),
+            impersonation_disclose: env_bool("IMPERSONATION_DISCLOSE", true),
+            write_queue_capacity: env_parse("WRITE_QUEUE_CAPACITY", 10_000),
+            event_dead_letter_file: std::env::var("EVENT_DEAD_LETTER_FILE").map(|v| v.trim().to_string()).ok().filter(|v| !v.is_empty())
+                .unwrap_or_else(|| "dead_letters/events.jsonl".to_string()),
+            event_dead_letter_max_bytes: env_parse("EVENT_DEAD_LETTER_MAX_BYTES", 67_108_864_u64).max(4096),
+            collection_prefix: std::env::var("COLLECTION_PREFIX").map(|v| v.trim().to_string()).unwrap_or_default(),
+            environment: std::env::var("APP_ENVIRONMENT").ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty()),
+            backup_store_url: std::env::var("BACKUP_STORE_URL").ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty()),
+            export_store_url: std::env::var("EXPORT_STORE_URL").ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty()),
+            export_schedule_collections: env_list("EXPORT_SCHEDULE_COLLECTIONS"),
+            export_schedule_format: env_parse("EXPORT_SCHEDULE_FORMAT", "parquet".to_string()).to_ascii_lowercase(),
+            warehouse_collections: env_list("WAREHOUSE_COLLECTIONS"),
+            warehouse_table_prefix: env_parse("WAREHOUSE_TABLE_PREFIX", String::new()),
+            warehouse_interval_secs: env_parse("WAREHOUSE_INTERVAL_SECS", 30_u64).max(1),
+            warehouse_batch_size: env_parse("WAREHOUSE_BATCH_SIZE", 500_usize).clamp(1, 10_000),
+            warehouse_max_attempts: env_parse("WAREHOUSE_MAX_ATTEMPTS", 5_u32).max(1),
+            warehouse_settle_secs: env_parse("WAREHOUSE_SETTLE_SECS", 30_u64),
+            outbox_interval_secs: env_parse("OUTBOX_INTERVAL_SECS", 2_u64).max(1),
+            outbox_batch_size: env_parse("OUTBOX_BATCH_SIZE", 100_usize).clamp(1, 10_000),
+            outbox_max_attempts: env_parse("OUTBOX_MAX_ATTEMPTS", 10_u32).max(1),
+            user_cache_capacity: env_parse("USER_CACHE_CAPACITY", 10_000),
+            user_cache_ttl_secs: env_parse("USER_CACHE_TTL_SECS", 300_u64).max(1),
+            snowflake_node_id: env_parse("SNOWFLAKE_NODE_ID", 0),
+            startup_fail_fast: env_bool("STARTUP_FAIL_FAST", true),
+            mongo_max_pool_size: env_opt("MONGODB_MAX_POOL_SIZE"),
+            mongo_min_pool_size: env_opt("MONGODB_MIN_POOL_SIZE"),
+            mongo_connect_timeout_ms: env_opt("MONGODB_CONNECT_TIMEOUT_MS"),
+            mongo_server_selection_timeout_ms: env_opt("MONGODB_SERVER_SELECTION_TIMEOUT_MS"),
+            mongo_max_idle_time_ms: env_opt("MONGODB_MAX_IDLE_TIME_MS"),
+            mongo_retry_writes: env_bool_opt("MONGODB_RETRY_WRITES"),
+            mongo_health_check_interval_secs: env_parse("MONGODB_HEALTH_CHECK_INTERVAL_SECS", 10),
+            slow_query_ms: env_parse("SLOW_QUERY_MS", 100),
+            room_snapshot_interval_secs: env_parse("ROOM_SNAPSHOT_INTERVAL_SECS", 5),
+            room_snapshot_every_turns: env_parse("ROOM_SNAPSHOT_EVERY_TURNS", 1),
+            room_abandon_after_secs: env_parse("ROOM_ABANDON_AFTER_SECS", 300),
+            room_reaper_interval_secs: env_parse("ROOM_REAPER_INTERVAL_SECS", 60_u64).max(1),
+            match_rake_percent: env_parse("MATCH_RAKE_PERCENT", 10.0_f64).clamp(0.0, 100.0),
+            revenue_rollup_interval_secs: env_parse("REVENUE_ROLLUP_INTERVAL_SECS", 3600_u64).max(60),
+            room_recovery_max_age_secs: env_parse("ROOM_RECOVERY_MAX_AGE_SECS", 600),
+            spectator_broadcast_interval_ms: env_parse("SPECTATOR_BROADCAST_INTERVAL_MS", 2000_u64),
+            room_update_window_ms: env_parse("ROOM_UPDATE_WINDOW_MS", 50_u64).min(1000),
+            room_state_keyframe_every: env_parse("ROOM_STATE_KEYFRAME_EVERY", 100_u64),
+            afk_warn_after_secs: env_parse("AFK_WARN_AFTER_SECS", 60_i64).max(0),
+            afk_action_after_secs: env_parse("AFK_ACTION_AFTER_SECS", 90_i64).max(env_parse("AFK_WARN_AFTER_SECS", 60_i64) + 1),
+            afk_action: std::env::var("AFK_ACTION").map(|v| v.trim().to_ascii_lowercase())
+                .ok()
+                .filter(|v| v == "forfeit")
+                .unwrap_or_else(|| "bot".to_string()),
+            afk_penalty_threshold: env_parse("AFK_PENALTY_THRESHOLD", 3_usize).max(1),
+            afk_penalty_window_hours: env_parse("AFK_PENALTY_WINDOW_HOURS", 24_i64).max(1),
+            afk_penalty_cooldown_secs: env_parse("AFK_PENALTY_COOLDOWN_SECS", 300_i64).max(0),
+            daily_challenge_count: env_parse("DAILY_CHALLENGE_COUNT", 3),
+            retention_report_weeks: env_parse("RETENTION_REPORT_WEEKS", 12_u32).clamp(1, 104),
+            season_base_rating: env_parse("SEASON_BASE_RATING", 1000),
+            season_soft_reset_factor: env_parse("SEASON_SOFT_RESET_FACTOR", 0.5_f64).clamp(0.0, 1.0),
+            season_check_interval_secs: env_parse("SEASON_CHECK_INTERVAL_SECS", 60_u64).max(1),
+            gift_max_coins: env_parse("GIFT_MAX_COINS", 1000_i64).max(1),
+            gift_daily_limit: env_parse("GIFT_DAILY_LIMIT", 10),
+            gift_daily_coin_limit: env_parse("GIFT_DAILY_COIN_LIMIT", 5000),
+            gift_min_account_age_hours: env_parse("GIFT_MIN_ACCOUNT_AGE_HOURS", 72),
+            gift_min_device_age_hours: env_parse("GIFT_MIN_DEVICE_AGE_HOURS", 24),
+            promo_max_failures_per_hour: env_parse("PROMO_MAX_FAILURES_PER_HOUR", 10_usize).max(1),
+            risk_scan_interval_secs: env_parse("RISK_SCAN_INTERVAL_SECS", 3600_u64).max(60),
+            risk_scan_window_hours: env_parse("RISK_SCAN_WINDOW_HOURS", 168_i64).max(1),
+            risk_min_pair_matches: env_parse("RISK_MIN_PAIR_MATCHES", 5_usize).max(2),
+            risk_gift_dump_coins: env_parse("RISK_GIFT_DUMP_COINS", 3000_i64).max(1),
+            risk_limit_score: env_parse("RISK_LIMIT_SCORE", 70_u32).clamp(1, 100),
+            report_hourly_limit: env_parse("REPORT_HOURLY_LIMIT", 5_usize).max(1),
+            report_repeat_window_hours: env_parse("REPORT_REPEAT_WINDOW_HOURS", 24_i64).max(0),
+            chat_rate_limit: env_parse("CHAT_RATE_LIMIT", 5_usize).max(1),
+            chat_rate_window_secs: env_parse("CHAT_RATE_WINDOW_SECS", 10_i64).max(1),
+            chat_flood_mute_secs: env_parse("CHAT_FLOOD_MUTE_SECS", 60_i64).max(1),
+            chat_flood_max_mute_secs: env_parse("CHAT_FLOOD_MAX_MUTE_SECS", 3600_i64).max(1),
+            chat_edit_window_secs: env_parse("CHAT_EDIT_WINDOW_SECS", 300_i64).max(0),
+            chat_retention_days: env_parse("CHAT_RETENTION_DAYS", 30_i64).max(1),
+            chat_typing_throttle_ms: env_parse("CHAT_TYPING_THROTTLE_MS", 2000_i64).max(0),
+            chat_link_policy: std::env::var("CHAT_LINK_POLICY").map(|v| v.trim().to_ascii_lowercase())
+                .ok()
+                .filter(|v| v == "allowlist" || v == "block")
+                .unwrap_or_else(|| "allow".to_string()),
+            chat_link_allowlist: env_list("CHAT_LINK_ALLOWLIST").into_iter().map(|h| h.to_ascii_lowercase()).collect(),
+            chat_link_denylist: env_list("CHAT_LINK_DENYLIST").into_iter().map(|h| h.to_ascii_lowercase()).collect(),
+            safe_browsing_api_key: env_opt::<String>("SAFE_BROWSING_API_KEY").filter(|k| !k.is_empty()),
+            chat_image_hosts: env_list("CHAT_IMAGE_HOSTS").into_iter().map(|h| h.to_ascii_lowercase()).collect(),
+            chat_image_max_bytes: env_parse("CHAT_IMAGE_MAX_BYTES", 5_242_880_i64).max(1),
+            slo_check_interval_secs: env_parse("SLO_CHECK_INTERVAL_SECS", 60_u64).max(1),
+            slo_window_secs: env_parse("SLO_WINDOW_SECS", 3600_u64).max(1),
+            slo_alert_webhook_url: std::env::var("SLO_ALERT_WEBHOOK_URL").ok().filter(|v| !v.trim().is_empty()),
+            slo_slack_webhook_url: std::env::var("SLO_SLACK_WEBHOOK_URL").ok().filter(|v| !v.trim().is_empty()),
+            funnel_broadcast_interval_ms: env_parse("FUNNEL_BROADCAST_INTERVAL_MS", 1000_u64).max(100),
+            health_score_interval_secs: env_parse("HEALTH_SCORE_INTERVAL_SECS", 10_u64).max(1),
+            health_score_window_secs: env_parse("HEALTH_SCORE_WINDOW_SECS", 300_u64).max(1),
+            health_max_error_rate: env_parse("HEALTH_MAX_ERROR_RATE", 0.05_f64).clamp(0.0001, 1.0),
+            health_p99_target_ms: env_parse("HEALTH_P99_TARGET_MS", 500.0_f64).max(1.0),
+            health_min_calls: env_parse("HEALTH_MIN_CALLS", 50_u64),
+            health_storm_factor: env_parse("HEALTH_STORM_FACTOR", 5.0_f64).max(1.0),
+            health_storm_min_connects: env_parse("HEALTH_STORM_MIN_CONNECTS", 50_u64).max(1),
+            health_rollback_score: env_parse("HEALTH_ROLLBACK_SCORE", 50_u32).min(100),
+            health_rollback_checks: env_parse("HEALTH_ROLLBACK_CHECKS", 3_u32).max(1),
+            connection_cap: env_parse("CONNECTION_CAP", 0_u64),
+            connection_alert_ratio: env_parse("CONNECTION_ALERT_RATIO", 0.8_f64).clamp(0.1, 1.0),
+            game_config_poll_secs: env_parse("GAME_CONFIG_POLL_SECS", 30_u64).max(1),
+            rule_plugins_dir: env_opt::<String>("GAME_RULE_PLUGINS_DIR").filter(|d| !d.is_empty()),
+            #[cfg(feature = "wasm-plugins")]
+            rule_plugin_fuel: env_parse("GAME_RULE_PLUGIN_FUEL", 10_000_000_u64).max(1),
+            #[cfg(feature = "wasm-plugins")]
+            rule_plugin_memory_mb: env_parse("GAME_RULE_PLUGIN_MEMORY_MB", 16_usize).clamp(1, 1024),
+            #[cfg(feature = "wasm-plugins")]
+            rule_plugin_poll_secs: env_parse("GAME_RULE_PLUGIN_POLL_SECS", 10_u64).max(1),
+            emit_retry_attempts: env_parse("EMIT_RETRY_ATTEMPTS", 3_u32).min(10),
+            emit_retry_delay_ms: env_parse("EMIT_RETRY_DELAY_MS", 200_u64).max(1),
+            notification_default_language: env_opt::<String>("NOTIFICATION_DEFAULT_LANGUAGE")
+                .map(|language| language.trim().to_lowercase())
+                .filter(|language| !language.is_empty())
+                .unwrap_or_else(|| "en".to_string()),
+            notification_template_cache_secs: env_parse("NOTIFICATION_TEMPLATE_CACHE_SECS", 60_u64),
+        }
+    }
+
+    // APP_ENVIRONMENT=production (or prod); stricter startup checks apply
+    pub fn is_production(&self) -> bool {
+        self.environment.as_deref().is_some_and(|env| env.eq_ignore_ascii_case("production") || env.eq_ignore_ascii_case("prod"))
+    }
+
+    // Fault injection applies to `target` ("mongo" or "emit"). Never active
+    // outside DEV_MODE.
+    pub fn chaos_targets_include(&self, target: &str) -> bool {
+        self.chaos_mode && self.dev_mode
+            && (self.chaos_targets.is_empty() || self.chaos_targets.iter().any(|t| t == target))
+    }
+
+    // Fixed OTP for an allowlisted test number. Test mode needs both the
+    // allowlist and a valid 4-8 digit TEST_OTP_CODE.
+    pub fn test_otp_for(&self, mobile_no: &str) -> Option<i32> {
+        self.test_otp_code
+            .filter(|_| self.test_otp_mobile_numbers.iter().any(|n| n == mobile_no))
+    }
+}
+
+fn env_parse<T: std::str::FromStr>(key: &str, default: T) -> T {
+    env_opt(key).unwrap_or(default)
+}
+
+fn env_opt<T: std::str::FromStr>(key: &str) -> Option<T> {
+    std::env::var(key)
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+}
+
+fn env_list(key: &str) -> Vec<String> {
+    std::env::var(key)
+        .map(|v| v.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+        .unwrap_or_default()
+}
+
+fn env_bool(key: &str, default: bool) -> bool {
+    env_bool_opt(key).unwrap_or(default)
+}
+
+fn env_bool_opt(key: &str) -> Option<bool> {
+    std::env::var(key)
+        .ok()
+        .map(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes" | "on"))
+}
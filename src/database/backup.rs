@@ -0,0 +1,318 @@
+use bson::{doc, Bson, Document};
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use futures_util::TryStreamExt;
+use mongodb::options::ReplaceOptions;
+use object_store::aws::AmazonS3Builder;
+use object_store::local::LocalFileSystem;
+use object_store::path::Path as ObjectPath;
+use object_store::{ObjectStore, PutPayload, WriteMultipart};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::config::CONFIG;
+use crate::database::DatabaseManager;
+use crate::managers::tenant::TenantManager;
+
+// Collections an operator mistake would hurt most: accounts, coin balances
+// with their ledger, and match history
+pub const BACKUP_COLLECTIONS: &[&str] = &["userregister", "wallets", "wallet_transactions", "match_history"];
+// Compressed bytes collected before a part is handed to the uploader
+const PART_BYTES: usize = 8 * 1024 * 1024;
+const UPLOAD_CONCURRENCY: usize = 4;
+// Progress is published every this many documents
+const PROGRESS_EVERY: u64 = 1000;
+// Finished backups listed by the admin API
+const MAX_LISTED_BACKUPS: usize = 20;
+
+type BackupResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupStatus {
+    Running,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollectionBackup {
+    pub collection: String,
+    pub file: String,
+    pub documents: u64,
+    pub estimated_documents: u64,   // Collection size when the backup started, for progress
+    pub compressed_bytes: u64,
+    pub done: bool,
+}
+
+// A backup run. Written as manifest.json next to the collection files once
+// every file is uploaded, so a backup without a manifest is incomplete.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupJob {
+    pub backup_id: String,
+    pub tenant_id: String,
+    pub environment: Option<String>,
+    pub status: BackupStatus,
+    pub requested_by: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub collections: Vec<CollectionBackup>,
+    pub error: Option<String>,
+}
+
+// Backups started on this server, by backup_id
+static JOBS: Lazy<RwLock<HashMap<String, BackupJob>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+// Exports BACKUP_COLLECTIONS to object storage in the background: one
+// gzip-compressed file of canonical extended JSON lines per collection under
+// <BACKUP_STORE_URL>/<tenant_id>/<backup_id>/. BACKUP_STORE_URL is
+// s3://bucket/prefix (credentials, region and endpoint from the usual AWS_*
+// variables) or file:///path. Progress is kept in memory on the server that
+// runs the backup; finished backups are listed from their manifests.
+// `restore-backup` puts the documents back.
+pub struct BackupManager;
+
+impl BackupManager {
+    fn store() -> BackupResult<(Arc<dyn ObjectStore>, ObjectPath)> {
+        Self::store_at(CONFIG.backup_store_url.as_deref(), "BACKUP_STORE_URL")
+    }
+
+    // Object store and root path for an s3:// or file:// URL read from `variable`
+    pub fn store_at(url: Option<&str>, variable: &str) -> BackupResult<(Arc<dyn ObjectStore>, ObjectPath)> {
+        let url = url.ok_or_else(|| format!("{} is not set", variable))?;
+        if let Some(rest) = url.strip_prefix("s3://") {
+            let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+            let store = AmazonS3Builder::from_env().with_bucket_name(bucket).build()?;
+            Ok((Arc::new(store), ObjectPath::from(prefix)))
+        } else if let Some(dir) = url.strip_prefix("file://") {
+            std::fs::create_dir_all(dir)?;
+            Ok((Arc::new(LocalFileSystem::new_with_prefix(dir)?), ObjectPath::default()))
+        } else {
+            Err(format!("{} must start with s3:// or file://, got {}", variable, url).into())
+        }
+    }
+
+    fn backup_path(root: &ObjectPath, tenant_id: &str, backup_id: &str) -> ObjectPath {
+        root.child(tenant_id).child(backup_id)
+    }
+
+    // Start a backup of the current tenant; returns it as first published.
+    // Err carries an error code for the admin API.
+    pub async fn start(requested_by: &str) -> Result<BackupJob, &'static str> {
+        if CONFIG.in_memory_store {
+            return Err("BACKUPS_UNAVAILABLE");
+        }
+        let (store, root) = Self::store().map_err(|e| {
+            warn!("⚠️ Backups unavailable: {}", e);
+            "BACKUPS_UNAVAILABLE"
+        })?;
+        let tenant = TenantManager::current();
+        let mut jobs = JOBS.write().await;
+        if jobs.values().any(|job| job.tenant_id == tenant.tenant_id && job.status == BackupStatus::Running) {
+            return Err("BACKUP_RUNNING");
+        }
+
+        let job = BackupJob {
+            backup_id: Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext)).to_string(),
+            tenant_id: tenant.tenant_id.clone(),
+            environment: CONFIG.environment.clone(),
+            status: BackupStatus::Running,
+            requested_by: requested_by.to_string(),
+            started_at: Utc::now(),
+            finished_at: None,
+            collections: BACKUP_COLLECTIONS.iter().map(|name| CollectionBackup {
+                collection: name.to_string(),
+                file: format!("{}.jsonl.gz", name),
+                documents: 0,
+                estimated_documents: 0,
+                compressed_bytes: 0,
+                done: false,
+            }).collect(),
+            error: None,
+        };
+        jobs.insert(job.backup_id.clone(), job.clone());
+        drop(jobs);
+
+        let backup_id = job.backup_id.clone();
+        TenantManager::spawn(async move {
+            let result = Self::run(&*store, &root, &backup_id).await;
+            let mut jobs = JOBS.write().await;
+            let Some(job) = jobs.get_mut(&backup_id) else { return };
+            job.finished_at = Some(Utc::now());
+            match result {
+                Ok(()) => {
+                    job.status = BackupStatus::Completed;
+                    let documents: u64 = job.collections.iter().map(|c| c.documents).sum();
+                    info!("💾 Backup {} of tenant {} completed: {} documents", backup_id, job.tenant_id, documents);
+                }
+                Err(e) => {
+                    job.status = BackupStatus::Failed;
+                    job.error = Some(e.to_string());
+                    error!("❌ Backup {} of tenant {} failed: {}", backup_id, job.tenant_id, e);
+                }
+            }
+        });
+        info!("💾 Backup {} of tenant {} started by {}", job.backup_id, job.tenant_id, requested_by);
+        Ok(job)
+    }
+
+    async fn update(backup_id: &str, index: usize, apply: impl FnOnce(&mut CollectionBackup)) {
+        if let Some(job) = JOBS.write().await.get_mut(backup_id) {
+            apply(&mut job.collections[index]);
+        }
+    }
+
+    async fn run(store: &dyn ObjectStore, root: &ObjectPath, backup_id: &str) -> BackupResult<()> {
+        let tenant_id = &TenantManager::current().tenant_id;
+        let dir = Self::backup_path(root, tenant_id, backup_id);
+        for (index, name) in BACKUP_COLLECTIONS.iter().enumerate() {
+            let collection = DatabaseManager::collection::<Document>(name);
+            let estimated = collection.estimated_document_count(None).await.unwrap_or(0);
+            Self::update(backup_id, index, |c| c.estimated_documents = estimated).await;
+
+            let mut upload = WriteMultipart::new(store.put_multipart(&dir.child(format!("{}.jsonl.gz", name))).await?);
+            let exported = Self::export_collection(&collection, &mut upload, backup_id, index).await;
+            let (documents, compressed_bytes) = match exported {
+                Ok(counts) => counts,
+                Err(e) => {
+                    let _ = upload.abort().await;
+                    return Err(e);
+                }
+            };
+            upload.finish().await?;
+            Self::update(backup_id, index, |c| {
+                c.documents = documents;
+                c.compressed_bytes = compressed_bytes;
+                c.done = true;
+            }).await;
+        }
+
+        let mut manifest = JOBS.read().await.get(backup_id).cloned().ok_or("backup job disappeared")?;
+        manifest.status = BackupStatus::Completed;
+        manifest.finished_at = Some(Utc::now());
+        store.put(&dir.child("manifest.json"), PutPayload::from(serde_json::to_vec_pretty(&manifest)?)).await?;
+        Ok(())
+    }
+
+    // Stream one collection into an upload; returns documents and compressed bytes written
+    async fn export_collection(collection: &mongodb::Collection<Document>, upload: &mut WriteMultipart, backup_id: &str, index: usize) -> BackupResult<(u64, u64)> {
+        let mut cursor = collection.find(None, None).await?;
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        let (mut documents, mut compressed_bytes) = (0_u64, 0_u64);
+        while let Some(document) = cursor.try_next().await? {
+            serde_json::to_writer(&mut encoder, &Bson::Document(document).into_canonical_extjson())?;
+            encoder.write_all(b"\n")?;
+            documents += 1;
+            if encoder.get_ref().len() >= PART_BYTES {
+                let part = std::mem::take(encoder.get_mut());
+                compressed_bytes += part.len() as u64;
+                upload.write(&part);
+                upload.wait_for_capacity(UPLOAD_CONCURRENCY).await?;
+            }
+            if documents.is_multiple_of(PROGRESS_EVERY) {
+                let bytes = compressed_bytes;
+                Self::update(backup_id, index, |c| {
+                    c.documents = documents;
+                    c.compressed_bytes = bytes;
+                }).await;
+            }
+        }
+        let rest = encoder.finish()?;
+        compressed_bytes += rest.len() as u64;
+        upload.write(&rest);
+        Ok((documents, compressed_bytes))
+    }
+
+    pub async fn job(backup_id: &str) -> Option<BackupJob> {
+        let tenant_id = &TenantManager::current().tenant_id;
+        JOBS.read().await.get(backup_id).filter(|job| &job.tenant_id == tenant_id).cloned()
+    }
+
+    async fn manifest(store: &dyn ObjectStore, path: &ObjectPath) -> BackupResult<BackupJob> {
+        let bytes = store.get(path).await?.bytes().await?;
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    // Backups of the current tenant running on this server, then the newest
+    // finished ones in storage
+    pub async fn list() -> BackupResult<Vec<BackupJob>> {
+        let (store, root) = Self::store()?;
+        let tenant_id = &TenantManager::current().tenant_id;
+        let mut backups: Vec<BackupJob> = JOBS.read().await.values()
+            .filter(|job| &job.tenant_id == tenant_id && job.status == BackupStatus::Running)
+            .cloned()
+            .collect();
+
+        let mut manifests: Vec<ObjectPath> = store.list(Some(&root.child(tenant_id.as_str())))
+            .try_filter(|meta| futures_util::future::ready(meta.location.filename() == Some("manifest.json")))
+            .map_ok(|meta| meta.location)
+            .try_collect()
+            .await?;
+        // backup_ids are UUIDv7, so newest sorts last
+        manifests.sort();
+        for path in manifests.iter().rev().take(MAX_LISTED_BACKUPS) {
+            match Self::manifest(&*store, path).await {
+                Ok(manifest) => backups.push(manifest),
+                Err(e) => warn!("⚠️ Skipping unreadable backup manifest {}: {}", path, e),
+            }
+        }
+        Ok(backups)
+    }
+
+    // Upserts every document of a backup into the current tenant's
+    // collections by _id. Documents created after the backup are kept.
+    pub async fn restore(backup_id: &str, collections: &[String]) -> BackupResult<()> {
+        let (store, root) = Self::store()?;
+        let tenant_id = &TenantManager::current().tenant_id;
+        let dir = Self::backup_path(&root, tenant_id, backup_id);
+        let manifest = Self::manifest(&*store, &dir.child("manifest.json")).await
+            .map_err(|e| format!("No complete backup {} for tenant {}: {}", backup_id, tenant_id, e))?;
+        if manifest.environment != CONFIG.environment {
+            warn!("⚠️ Backup {} was taken in environment {:?}, restoring into {:?}", backup_id, manifest.environment, CONFIG.environment);
+        }
+        if let Some(unknown) = collections.iter().find(|name| !manifest.collections.iter().any(|c| &c.collection == *name)) {
+            return Err(format!("Backup {} has no collection {}", backup_id, unknown).into());
+        }
+
+        let upsert = ReplaceOptions::builder().upsert(true).build();
+        for entry in manifest.collections.iter().filter(|c| collections.is_empty() || collections.contains(&c.collection)) {
+            let compressed = store.get(&dir.child(entry.file.as_str())).await?.bytes().await?;
+            let target = DatabaseManager::collection::<Document>(&entry.collection);
+            let mut restored = 0_u64;
+            for line in BufReader::new(GzDecoder::new(&compressed[..])).lines() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let Bson::Document(document) = Bson::try_from(serde_json::from_str::<serde_json::Value>(&line)?)? else {
+                    return Err(format!("{} holds a line that is not a document", entry.file).into());
+                };
+                let id = document.get("_id").cloned().ok_or_else(|| format!("{} holds a document without _id", entry.file))?;
+                target.replace_one(doc! { "_id": id }, document, upsert.clone()).await?;
+                restored += 1;
+                if restored.is_multiple_of(PROGRESS_EVERY) {
+                    info!("♻️ {}: {}/{} documents restored", entry.collection, restored, entry.documents);
+                }
+            }
+            info!("♻️ Restored {} documents into {}", restored, DatabaseManager::collection_name(&entry.collection));
+        }
+        Ok(())
+    }
+
+    // `restore-backup <tenant_id> <backup_id> [collection...]`
+    pub async fn restore_command(args: &[String]) -> BackupResult<()> {
+        let [tenant_id, backup_id, collections @ ..] = args else {
+            return Err("usage: restore-backup <tenant_id> <backup_id> [collection...]".into());
+        };
+        let tenant = TenantManager::get(tenant_id).ok_or_else(|| format!("Unknown tenant {}", tenant_id))?;
+        TenantManager::scope(tenant, Self::restore(backup_id, collections)).await
+    }
+}
@@ -0,0 +1,874 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+
+use crate::database::models::*;
+use crate::database::timeline::TimelineSource;
+use crate::database::store::{DataStore, GameConfigChanges, UserStream};
+use crate::managers::chaos::FaultInjector;
+
+// DataStore wrapper that runs every call through the fault injector first.
+// Installed in front of the real store when CHAOS_MODE targets "mongo".
+pub struct ChaosDataStore {
+    inner: Arc<dyn DataStore>,
+}
+
+impl ChaosDataStore {
+    pub fn new(inner: Arc<dyn DataStore>) -> Self {
+        Self { inner }
+    }
+}
+
+#[async_trait]
+impl DataStore for ChaosDataStore {
+    async fn store_connect_event(&self, socket_id: &str, token: i32, message: &str, status: &str, ip_address: Option<&str>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("store_connect_event").await?;
+        self.inner.store_connect_event(socket_id, token, message, status, ip_address).await
+    }
+
+    async fn store_device_info_event(&self, socket_id: &str, device_info: &serde_json::Value) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("store_device_info_event").await?;
+        self.inner.store_device_info_event(socket_id, device_info).await
+    }
+
+    async fn store_login_event(&self, socket_id: &str, mobile_no: &str, device_id: &str, fcm_token: &str, email: Option<&str>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("store_login_event").await?;
+        self.inner.store_login_event(socket_id, mobile_no, device_id, fcm_token, email).await
+    }
+
+    async fn store_login_success_event(&self, socket_id: &str, mobile_no: &str, device_id: &str, session_token: &str, otp: i32, otp_expiry_secs: i64, is_new_user: bool) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("store_login_success_event").await?;
+        self.inner.store_login_success_event(socket_id, mobile_no, device_id, session_token, otp, otp_expiry_secs, is_new_user).await
+    }
+
+    async fn store_otp_delivery_request(&self, request: OtpDeliveryRequest) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("store_otp_delivery_request").await?;
+        self.inner.store_otp_delivery_request(request).await
+    }
+
+    async fn store_test_otp_audit_event(&self, socket_id: &str, mobile_no: &str, device_id: Option<&str>, action: &str, is_success: bool) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("store_test_otp_audit_event").await?;
+        self.inner.store_test_otp_audit_event(socket_id, mobile_no, device_id, action, is_success).await
+    }
+
+    async fn list_user_devices(&self, mobile_no: &str) -> Result<Vec<UserDevice>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("list_user_devices").await?;
+        self.inner.list_user_devices(mobile_no).await
+    }
+
+    async fn remove_user_device(&self, mobile_no: &str, device_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("remove_user_device").await?;
+        self.inner.remove_user_device(mobile_no, device_id).await
+    }
+
+    async fn update_two_step(&self, mobile_no: &str, two_step: Option<&TwoStepFactor>) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("update_two_step").await?;
+        self.inner.update_two_step(mobile_no, two_step).await
+    }
+
+    async fn set_date_of_birth(&self, mobile_no: &str, date_of_birth: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("set_date_of_birth").await?;
+        self.inner.set_date_of_birth(mobile_no, date_of_birth).await
+    }
+
+    async fn update_parental_controls(&self, mobile_no: &str, controls: Option<&ParentalControls>) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("update_parental_controls").await?;
+        self.inner.update_parental_controls(mobile_no, controls).await
+    }
+
+    async fn store_two_step_reset(&self, reset: TwoStepReset) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("store_two_step_reset").await?;
+        self.inner.store_two_step_reset(reset).await
+    }
+
+    async fn store_mobile_change(&self, change: MobileChange) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("store_mobile_change").await?;
+        self.inner.store_mobile_change(change).await
+    }
+
+    async fn get_mobile_change(&self, user_id: &str, change_id: &str) -> Result<Option<MobileChange>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("get_mobile_change").await?;
+        self.inner.get_mobile_change(user_id, change_id).await
+    }
+
+    async fn record_mobile_change_attempt(&self, change_id: &str) -> Result<u32, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("record_mobile_change_attempt").await?;
+        self.inner.record_mobile_change_attempt(change_id).await
+    }
+
+    async fn change_user_mobile(&self, audit: MobileChangeAudit) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("change_user_mobile").await?;
+        self.inner.change_user_mobile(audit).await
+    }
+
+    async fn find_reusable_login_session(&self, socket_id: &str, mobile_no: &str, device_id: &str, session_token: Option<&str>) -> Result<Option<LoginSuccessEvent>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("find_reusable_login_session").await?;
+        self.inner.find_reusable_login_session(socket_id, mobile_no, device_id, session_token).await
+    }
+
+    async fn store_otp_verification_event(
+        &self,
+        socket_id: &str,
+        mobile_no: &str,
+        session_token: &str,
+        otp: &str,
+        is_success: bool,
+        user_id: Option<&str>,
+        user_number: Option<u64>,
+        jwt_token: Option<&str>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("store_otp_verification_event").await?;
+        self.inner.store_otp_verification_event(socket_id, mobile_no, session_token, otp, is_success, user_id, user_number, jwt_token).await
+    }
+
+    async fn store_user_registration_event(
+        &self,
+        socket_id: &str,
+        user_id: &str,
+        user_number: u64,
+        mobile_no: &str,
+        device_id: &str,
+        fcm_token: &str,
+        email: Option<&str>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("store_user_registration_event").await?;
+        self.inner.store_user_registration_event(socket_id, user_id, user_number, mobile_no, device_id, fcm_token, email).await
+    }
+
+    async fn store_user_profile_event(
+        &self,
+        socket_id: &str,
+        user_id: &str,
+        user_number: u64,
+        mobile_no: &str,
+        full_name: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("store_user_profile_event").await?;
+        self.inner.store_user_profile_event(socket_id, user_id, user_number, mobile_no, full_name).await
+    }
+
+    async fn store_language_setting_event(
+        &self,
+        socket_id: &str,
+        user_id: &str,
+        user_number: u64,
+        mobile_no: &str,
+        language_code: &str,
+        language_name: &str,
+        region_code: Option<&str>,
+        timezone: Option<&str>,
+        user_preferences: &serde_json::Value,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("store_language_setting_event").await?;
+        self.inner.store_language_setting_event(socket_id, user_id, user_number, mobile_no, language_code, language_name, region_code, timezone, user_preferences).await
+    }
+
+    async fn store_connection_error_event(
+        &self,
+        socket_id: &str,
+        error_code: &str,
+        error_type: &str,
+        field: &str,
+        message: &str,
+        payload: bson::Document,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("store_connection_error_event").await?;
+        self.inner.store_connection_error_event(socket_id, error_code, error_type, field, message, payload).await
+    }
+
+    async fn connection_error_counts(&self, query: &ErrorStatsQuery) -> Result<Vec<ErrorCountBucket>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("connection_error_counts").await?;
+        self.inner.connection_error_counts(query).await
+    }
+
+    async fn connection_error_offenders(
+        &self,
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+        limit: i64,
+    ) -> Result<ErrorOffenders, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("connection_error_offenders").await?;
+        self.inner.connection_error_offenders(from, to, limit).await
+    }
+
+    async fn store_turn_timing_event(&self, event: TurnTimingEvent) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("store_turn_timing_event").await?;
+        self.inner.store_turn_timing_event(event).await
+    }
+
+    async fn store_match_record(&self, record: MatchRecord) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("store_match_record").await?;
+        self.inner.store_match_record(record).await
+    }
+
+    async fn save_room_snapshot(&self, snapshot: RoomSnapshot) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("save_room_snapshot").await?;
+        self.inner.save_room_snapshot(snapshot).await
+    }
+
+    async fn load_room_snapshots(&self, since: chrono::DateTime<chrono::Utc>) -> Result<Vec<RoomSnapshot>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("load_room_snapshots").await?;
+        self.inner.load_room_snapshots(since).await
+    }
+
+    async fn archive_room(&self, room: ArchivedRoom) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("archive_room").await?;
+        self.inner.archive_room(room).await
+    }
+
+    async fn create_escrow(&self, escrow: MatchEscrow) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("create_escrow").await?;
+        self.inner.create_escrow(escrow).await
+    }
+
+    async fn get_held_escrow(&self, room_id: &str) -> Result<Option<MatchEscrow>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("get_held_escrow").await?;
+        self.inner.get_held_escrow(room_id).await
+    }
+
+    async fn report_escrow_result(&self, escrow_id: &str, player_id: &str, outcome: GameOutcome) -> Result<Option<MatchEscrow>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("report_escrow_result").await?;
+        self.inner.report_escrow_result(escrow_id, player_id, outcome).await
+    }
+
+    async fn begin_escrow_settlement(&self, escrow_id: &str, status: EscrowStatus, payouts: Vec<EscrowPayout>, rake: i64) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("begin_escrow_settlement").await?;
+        self.inner.begin_escrow_settlement(escrow_id, status, payouts, rake).await
+    }
+
+    async fn finish_escrow_opening(&self, escrow_id: &str, status: EscrowStatus, stakes: Vec<EscrowStake>, payouts: Vec<EscrowPayout>) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("finish_escrow_opening").await?;
+        self.inner.finish_escrow_opening(escrow_id, status, stakes, payouts).await
+    }
+
+    async fn stale_escrow_openings(&self, before: chrono::DateTime<chrono::Utc>) -> Result<Vec<MatchEscrow>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("stale_escrow_openings").await?;
+        self.inner.stale_escrow_openings(before).await
+    }
+
+    async fn finish_escrow_settlement(&self, escrow_id: &str, status: EscrowStatus) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("finish_escrow_settlement").await?;
+        self.inner.finish_escrow_settlement(escrow_id, status).await
+    }
+
+    async fn unfinished_escrow_settlements(&self, before: chrono::DateTime<chrono::Utc>) -> Result<Vec<MatchEscrow>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("unfinished_escrow_settlements").await?;
+        self.inner.unfinished_escrow_settlements(before).await
+    }
+
+    async fn record_revenue(&self, entry: RevenueEntry) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("record_revenue").await?;
+        self.inner.record_revenue(entry).await
+    }
+
+    async fn revenue_entries(&self, day: &str, game_type: Option<&str>, page: u64, page_size: i64) -> Result<Vec<RevenueEntry>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("revenue_entries").await?;
+        self.inner.revenue_entries(day, game_type, page, page_size).await
+    }
+
+    async fn revenue_day_totals(&self, day: &str) -> Result<Vec<RevenueRollup>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("revenue_day_totals").await?;
+        self.inner.revenue_day_totals(day).await
+    }
+
+    async fn save_revenue_rollups(&self, day: &str, rollups: Vec<RevenueRollup>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("save_revenue_rollups").await?;
+        self.inner.save_revenue_rollups(day, rollups).await
+    }
+
+    async fn revenue_rollups(&self, from: &str, to: &str) -> Result<Vec<RevenueRollup>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("revenue_rollups").await?;
+        self.inner.revenue_rollups(from, to).await
+    }
+
+    async fn create_promo_code(&self, promo: PromoCode) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("create_promo_code").await?;
+        self.inner.create_promo_code(promo).await
+    }
+
+    async fn list_promo_codes(&self) -> Result<Vec<PromoCode>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("list_promo_codes").await?;
+        self.inner.list_promo_codes().await
+    }
+
+    async fn set_promo_code_active(&self, code: &str, active: bool) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("set_promo_code_active").await?;
+        self.inner.set_promo_code_active(code, active).await
+    }
+
+    async fn redeem_promo_code(&self, redemption: PromoRedemption) -> Result<PromoRedeem, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("redeem_promo_code").await?;
+        self.inner.redeem_promo_code(redemption).await
+    }
+
+    async fn list_promo_redemptions(&self, code: &str, page: u64, page_size: i64) -> Result<Vec<PromoRedemption>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("list_promo_redemptions").await?;
+        self.inner.list_promo_redemptions(code, page, page_size).await
+    }
+
+    async fn start_impersonation(&self, session: ImpersonationSession) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("start_impersonation").await?;
+        self.inner.start_impersonation(session).await
+    }
+
+    async fn end_impersonation(&self, session_id: &str, end_reason: &str, events_mirrored: u64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("end_impersonation").await?;
+        self.inner.end_impersonation(session_id, end_reason, events_mirrored).await
+    }
+
+    async fn list_impersonations(&self, user_id: Option<&str>, operator_id: Option<&str>, page: u64, page_size: i64) -> Result<Vec<ImpersonationSession>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("list_impersonations").await?;
+        self.inner.list_impersonations(user_id, operator_id, page, page_size).await
+    }
+
+    async fn user_timeline(&self, user: &UserRegister, sources: &[&'static TimelineSource], before: Option<bson::DateTime>, limit: i64) -> Result<Vec<TimelineEntry>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("user_timeline").await?;
+        self.inner.user_timeline(user, sources, before, limit).await
+    }
+
+    async fn record_session_sample(&self, sample: SessionSample) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("record_session_sample").await?;
+        self.inner.record_session_sample(sample).await
+    }
+
+    async fn session_samples(&self, from: bson::DateTime, to: bson::DateTime) -> Result<Vec<SessionSample>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("session_samples").await?;
+        self.inner.session_samples(from, to).await
+    }
+
+    async fn record_compliance_audit(&self, audit: ComplianceAudit) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("record_compliance_audit").await?;
+        self.inner.record_compliance_audit(audit).await
+    }
+
+    async fn record_afk_event(&self, event: AfkEvent) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("record_afk_event").await?;
+        self.inner.record_afk_event(event).await
+    }
+
+    async fn afk_events_since(&self, user_id: &str, since: bson::DateTime) -> Result<Vec<AfkEvent>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("afk_events_since").await?;
+        self.inner.afk_events_since(user_id, since).await
+    }
+
+    async fn save_dealer_audit(&self, audit: DealerAudit) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("save_dealer_audit").await?;
+        self.inner.save_dealer_audit(audit).await
+    }
+
+    async fn retention_cohorts(&self, since: chrono::DateTime<chrono::Utc>, now: chrono::DateTime<chrono::Utc>, days: &[u32]) -> Result<Vec<RetentionCohort>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("retention_cohorts").await?;
+        self.inner.retention_cohorts(since, now, days).await
+    }
+
+    async fn save_retention_report(&self, report: RetentionReport) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("save_retention_report").await?;
+        self.inner.save_retention_report(report).await
+    }
+
+    async fn latest_retention_report(&self) -> Result<Option<RetentionReport>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("latest_retention_report").await?;
+        self.inner.latest_retention_report().await
+    }
+
+    async fn get_gameplay_progress(&self, user_id: &str) -> Result<Option<GameplayProgress>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("get_gameplay_progress").await?;
+        self.inner.get_gameplay_progress(user_id).await
+    }
+
+    async fn update_gameplay_progress(&self, user_id: &str, update: &ProgressUpdate) -> Result<GameplayProgress, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("update_gameplay_progress").await?;
+        self.inner.update_gameplay_progress(user_id, update).await
+    }
+
+    async fn get_daily_challenges(&self, date: &str) -> Result<Option<DailyChallengeSet>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("get_daily_challenges").await?;
+        self.inner.get_daily_challenges(date).await
+    }
+
+    async fn create_daily_challenges(&self, set: DailyChallengeSet) -> Result<DailyChallengeSet, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("create_daily_challenges").await?;
+        self.inner.create_daily_challenges(set).await
+    }
+
+    async fn get_challenge_progress(&self, user_id: &str, date: &str) -> Result<Option<ChallengeProgress>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("get_challenge_progress").await?;
+        self.inner.get_challenge_progress(user_id, date).await
+    }
+
+    async fn add_challenge_progress(&self, user_id: &str, date: &str, increments: &std::collections::HashMap<String, i64>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("add_challenge_progress").await?;
+        self.inner.add_challenge_progress(user_id, date, increments).await
+    }
+
+    async fn mark_challenge_claimed(&self, user_id: &str, date: &str, challenge_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("mark_challenge_claimed").await?;
+        self.inner.mark_challenge_claimed(user_id, date, challenge_id).await
+    }
+
+    async fn get_wallet_balance(&self, user_id: &str) -> Result<i64, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("get_wallet_balance").await?;
+        self.inner.get_wallet_balance(user_id).await
+    }
+
+    async fn credit_wallet(&self, user_id: &str, amount: i64, reason: &str, reference: &str) -> Result<Option<i64>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("credit_wallet").await?;
+        self.inner.credit_wallet(user_id, amount, reason, reference).await
+    }
+
+    async fn create_season(&self, season: Season) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("create_season").await?;
+        self.inner.create_season(season).await
+    }
+
+    async fn list_seasons(&self) -> Result<Vec<Season>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("list_seasons").await?;
+        self.inner.list_seasons().await
+    }
+
+    async fn get_season(&self, season_id: &str) -> Result<Option<Season>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("get_season").await?;
+        self.inner.get_season(season_id).await
+    }
+
+    async fn seasons_with_status(&self, status: SeasonStatus) -> Result<Vec<Season>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("seasons_with_status").await?;
+        self.inner.seasons_with_status(status).await
+    }
+
+    async fn transition_season(&self, season_id: &str, from: SeasonStatus, to: SeasonStatus) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("transition_season").await?;
+        self.inner.transition_season(season_id, from, to).await
+    }
+
+    async fn record_season_result(&self, season_id: &str, user_id: &str, outcome: GameOutcome, rating_change: i64, base_rating: i64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("record_season_result").await?;
+        self.inner.record_season_result(season_id, user_id, outcome, rating_change, base_rating).await
+    }
+
+    async fn season_leaderboard(&self, season_id: &str, offset: u64, limit: i64) -> Result<Vec<SeasonRating>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("season_leaderboard").await?;
+        self.inner.season_leaderboard(season_id, offset, limit).await
+    }
+
+    async fn get_season_rating(&self, season_id: &str, user_id: &str) -> Result<Option<SeasonRating>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("get_season_rating").await?;
+        self.inner.get_season_rating(season_id, user_id).await
+    }
+
+    async fn season_rank(&self, rating: &SeasonRating) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("season_rank").await?;
+        self.inner.season_rank(rating).await
+    }
+
+    async fn carry_over_season_ratings(&self, from: &str, to: &str, base_rating: i64, factor: f64) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("carry_over_season_ratings").await?;
+        self.inner.carry_over_season_ratings(from, to, base_rating, factor).await
+    }
+
+    async fn debit_wallet(&self, user_id: &str, amount: i64, reason: &str, reference: &str, daily_limit: Option<i64>) -> Result<WalletDebit, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("debit_wallet").await?;
+        self.inner.debit_wallet(user_id, amount, reason, reference, daily_limit).await
+    }
+
+    async fn wallet_reference_applied(&self, reference: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("wallet_reference_applied").await?;
+        self.inner.wallet_reference_applied(reference).await
+    }
+
+    async fn wallet_spent_since(&self, user_id: &str, since: chrono::DateTime<chrono::Utc>) -> Result<i64, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("wallet_spent_since").await?;
+        self.inner.wallet_spent_since(user_id, since).await
+    }
+
+    async fn get_inventory(&self, user_id: &str) -> Result<Vec<InventoryItem>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("get_inventory").await?;
+        self.inner.get_inventory(user_id).await
+    }
+
+    async fn add_friend(&self, user_id: &str, friend_user_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("add_friend").await?;
+        self.inner.add_friend(user_id, friend_user_id).await
+    }
+
+    async fn remove_friend(&self, user_id: &str, friend_user_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("remove_friend").await?;
+        self.inner.remove_friend(user_id, friend_user_id).await
+    }
+
+    async fn are_friends(&self, user_id: &str, other_user_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("are_friends").await?;
+        self.inner.are_friends(user_id, other_user_id).await
+    }
+
+    async fn list_friendships(&self, user_id: &str) -> Result<Vec<Friendship>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("list_friendships").await?;
+        self.inner.list_friendships(user_id).await
+    }
+
+    async fn send_gift(&self, gift: Gift, limits: GiftLimits, daily_limit: Option<i64>) -> Result<GiftSend, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("send_gift").await?;
+        self.inner.send_gift(gift, limits, daily_limit).await
+    }
+
+    async fn gifts_since(&self, since: chrono::DateTime<chrono::Utc>) -> Result<Vec<Gift>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("gifts_since").await?;
+        self.inner.gifts_since(since).await
+    }
+
+    async fn record_match_participant(&self, participant: MatchParticipant) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("record_match_participant").await?;
+        self.inner.record_match_participant(participant).await
+    }
+
+    async fn record_match_result(&self, result: MatchResult) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("record_match_result").await?;
+        self.inner.record_match_result(result).await
+    }
+
+    async fn match_participants_since(&self, since: chrono::DateTime<chrono::Utc>) -> Result<Vec<MatchParticipant>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("match_participants_since").await?;
+        self.inner.match_participants_since(since).await
+    }
+
+    async fn match_results_since(&self, since: chrono::DateTime<chrono::Utc>) -> Result<Vec<MatchResult>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("match_results_since").await?;
+        self.inner.match_results_since(since).await
+    }
+
+    async fn get_user_risk(&self, user_id: &str) -> Result<Option<UserRisk>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("get_user_risk").await?;
+        self.inner.get_user_risk(user_id).await
+    }
+
+    async fn save_user_risk(&self, risk: UserRisk) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("save_user_risk").await?;
+        self.inner.save_user_risk(risk).await
+    }
+
+    async fn clear_stale_user_risk(&self, before: bson::DateTime) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("clear_stale_user_risk").await?;
+        self.inner.clear_stale_user_risk(before).await
+    }
+
+    async fn create_risk_flag(&self, flag: RiskFlag) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("create_risk_flag").await?;
+        self.inner.create_risk_flag(flag).await
+    }
+
+    async fn list_risk_flags(&self, status: Option<&str>, page: u64, page_size: i64) -> Result<Vec<RiskFlag>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("list_risk_flags").await?;
+        self.inner.list_risk_flags(status, page, page_size).await
+    }
+
+    async fn resolve_risk_flag(&self, flag_id: &str, status: &str, operator_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("resolve_risk_flag").await?;
+        self.inner.resolve_risk_flag(flag_id, status, operator_id).await
+    }
+
+    async fn dismissed_risk_flag_keys(&self) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("dismissed_risk_flag_keys").await?;
+        self.inner.dismissed_risk_flag_keys().await
+    }
+
+    async fn block_user(&self, user_id: &str, blocked_user_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("block_user").await?;
+        self.inner.block_user(user_id, blocked_user_id).await
+    }
+
+    async fn unblock_user(&self, user_id: &str, blocked_user_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("unblock_user").await?;
+        self.inner.unblock_user(user_id, blocked_user_id).await
+    }
+
+    async fn list_blocks(&self, user_id: &str) -> Result<Vec<UserBlock>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("list_blocks").await?;
+        self.inner.list_blocks(user_id).await
+    }
+
+    async fn create_user_report(&self, report: UserReport) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("create_user_report").await?;
+        self.inner.create_user_report(report).await
+    }
+
+    async fn reports_filed_since(&self, reporter_user_id: &str, since: chrono::DateTime<chrono::Utc>) -> Result<Vec<UserReport>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("reports_filed_since").await?;
+        self.inner.reports_filed_since(reporter_user_id, since).await
+    }
+
+    async fn list_user_reports(&self, status: Option<&str>, category: Option<ReportCategory>, page: u64, page_size: i64) -> Result<Vec<UserReport>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("list_user_reports").await?;
+        self.inner.list_user_reports(status, category, page, page_size).await
+    }
+
+    async fn get_user_report(&self, report_id: &str) -> Result<Option<UserReport>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("get_user_report").await?;
+        self.inner.get_user_report(report_id).await
+    }
+
+    async fn resolve_user_report(&self, report_id: &str, status: &str, resolution: &str, operator_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("resolve_user_report").await?;
+        self.inner.resolve_user_report(report_id, status, resolution, operator_id).await
+    }
+
+    async fn create_sanction(&self, sanction: UserSanction) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("create_sanction").await?;
+        self.inner.create_sanction(sanction).await
+    }
+
+    async fn list_sanctions(&self, user_id: &str) -> Result<Vec<UserSanction>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("list_sanctions").await?;
+        self.inner.list_sanctions(user_id).await
+    }
+
+    async fn lift_sanction(&self, sanction_id: &str, operator_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("lift_sanction").await?;
+        self.inner.lift_sanction(sanction_id, operator_id).await
+    }
+
+    async fn revoke_user_sessions(&self, mobile_no: &str) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("revoke_user_sessions").await?;
+        self.inner.revoke_user_sessions(mobile_no).await
+    }
+
+    async fn save_chat_message(&self, message: ChatMessage) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("save_chat_message").await?;
+        self.inner.save_chat_message(message).await
+    }
+
+    async fn get_chat_message(&self, message_id: &str) -> Result<Option<ChatMessage>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("get_chat_message").await?;
+        self.inner.get_chat_message(message_id).await
+    }
+
+    async fn edit_chat_message(&self, message_id: &str, message: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("edit_chat_message").await?;
+        self.inner.edit_chat_message(message_id, message).await
+    }
+
+    async fn delete_chat_message(&self, message_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("delete_chat_message").await?;
+        self.inner.delete_chat_message(message_id).await
+    }
+
+    async fn chat_history(&self, room_id: &str, before: Option<&str>, excluded_user_ids: &[String], limit: i64) -> Result<Vec<ChatMessage>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("chat_history").await?;
+        self.inner.chat_history(room_id, before, excluded_user_ids, limit).await
+    }
+
+    async fn purge_chat_messages(&self, before: chrono::DateTime<chrono::Utc>) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("purge_chat_messages").await?;
+        self.inner.purge_chat_messages(before).await
+    }
+
+    async fn react_to_chat_message(&self, reaction: ChatReaction, add: bool) -> Result<Option<i64>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("react_to_chat_message").await?;
+        self.inner.react_to_chat_message(reaction, add).await
+    }
+
+    async fn mark_chat_read(&self, room_id: &str, user_id: &str, message_id: &str) -> Result<ChatReadReceipt, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("mark_chat_read").await?;
+        self.inner.mark_chat_read(room_id, user_id, message_id).await
+    }
+
+    async fn chat_read_receipts(&self, room_id: &str, excluded_user_ids: &[String]) -> Result<Vec<ChatReadReceipt>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("chat_read_receipts").await?;
+        self.inner.chat_read_receipts(room_id, excluded_user_ids).await
+    }
+
+    async fn record_chat_audit(&self, audit: ChatMessageAudit) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("record_chat_audit").await?;
+        self.inner.record_chat_audit(audit).await
+    }
+
+    async fn list_game_configs(&self, game_type: Option<&str>) -> Result<Vec<GameConfig>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("list_game_configs").await?;
+        self.inner.list_game_configs(game_type).await
+    }
+
+    async fn insert_game_config(&self, config: GameConfig) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("insert_game_config").await?;
+        self.inner.insert_game_config(config).await
+    }
+
+    async fn watch_game_configs(&self) -> Result<Option<GameConfigChanges>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("watch_game_configs").await?;
+        self.inner.watch_game_configs().await
+    }
+
+    async fn list_notification_templates(&self) -> Result<Vec<NotificationTemplate>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("list_notification_templates").await?;
+        self.inner.list_notification_templates().await
+    }
+
+    async fn upsert_notification_template(&self, template: NotificationTemplate) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("upsert_notification_template").await?;
+        self.inner.upsert_notification_template(template).await
+    }
+
+    async fn delete_notification_template(&self, notification_type: &str, language_code: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("delete_notification_template").await?;
+        self.inner.delete_notification_template(notification_type, language_code).await
+    }
+
+    async fn get_notification_preferences(&self, user_id: &str) -> Result<NotificationPreferences, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("get_notification_preferences").await?;
+        self.inner.get_notification_preferences(user_id).await
+    }
+
+    async fn update_notification_preferences(&self, user_id: &str, mobile_no: &str, notifications: &NotificationPreferences) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("update_notification_preferences").await?;
+        self.inner.update_notification_preferences(user_id, mobile_no, notifications).await
+    }
+
+    async fn store_inbox_notification(&self, notification: InboxNotification, push: Option<OutboxMessage>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("store_inbox_notification").await?;
+        self.inner.store_inbox_notification(notification, push).await
+    }
+
+    async fn count_unread_notifications(&self, user_id: &str) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("count_unread_notifications").await?;
+        self.inner.count_unread_notifications(user_id).await
+    }
+
+    async fn user_exists(&self, mobile_no: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("user_exists").await?;
+        self.inner.user_exists(mobile_no).await
+    }
+
+    async fn get_user_by_mobile(&self, mobile_no: &str) -> Result<Option<UserRegister>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("get_user_by_mobile").await?;
+        self.inner.get_user_by_mobile(mobile_no).await
+    }
+
+    async fn get_user_by_id(&self, user_id: &str) -> Result<Option<UserRegister>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("get_user_by_id").await?;
+        self.inner.get_user_by_id(user_id).await
+    }
+
+    async fn get_user_identity(&self, mobile_no: &str) -> Result<Option<UserIdentity>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("get_user_identity").await?;
+        self.inner.get_user_identity(mobile_no).await
+    }
+
+    async fn get_user_profile(&self, mobile_no: &str) -> Result<Option<UserProfile>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("get_user_profile").await?;
+        self.inner.get_user_profile(mobile_no).await
+    }
+
+    async fn register_new_user(
+        &self,
+        mobile_no: &str,
+        device_id: &str,
+        fcm_token: &str,
+        email: Option<&str>,
+    ) -> Result<(String, u64), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("register_new_user").await?;
+        self.inner.register_new_user(mobile_no, device_id, fcm_token, email).await
+    }
+
+    async fn import_user(&self, user: UserRegister) -> Result<Option<(String, u64)>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("import_user").await?;
+        self.inner.import_user(user).await
+    }
+
+    async fn search_users(&self, filter: &UserFilter, page: u64, page_size: i64) -> Result<(Vec<UserRegister>, u64), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("search_users").await?;
+        self.inner.search_users(filter, page, page_size).await
+    }
+
+    async fn get_admin_operator(&self, operator_id: &str) -> Result<Option<AdminOperator>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("get_admin_operator").await?;
+        self.inner.get_admin_operator(operator_id).await
+    }
+
+    async fn list_admin_operators(&self) -> Result<Vec<AdminOperator>, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("list_admin_operators").await?;
+        self.inner.list_admin_operators().await
+    }
+
+    async fn create_admin_operator(&self, operator: AdminOperator) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("create_admin_operator").await?;
+        self.inner.create_admin_operator(operator).await
+    }
+
+    async fn set_admin_operator_role(&self, operator_id: &str, role: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("set_admin_operator_role").await?;
+        self.inner.set_admin_operator_role(operator_id, role).await
+    }
+
+    async fn store_admin_access_denied_event(&self, operator_id: &str, role: &str, permission: &str, target: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("store_admin_access_denied_event").await?;
+        self.inner.store_admin_access_denied_event(operator_id, role, permission, target).await
+    }
+
+    async fn store_rejected_handshake(&self, event: RejectedHandshakeEvent) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("store_rejected_handshake").await?;
+        self.inner.store_rejected_handshake(event).await
+    }
+
+    async fn export_users(&self, filter: &UserFilter) -> Result<UserStream, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("export_users").await?;
+        self.inner.export_users(filter).await
+    }
+
+    async fn update_user_login_info(&self, mobile_no: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("update_user_login_info").await?;
+        self.inner.update_user_login_info(mobile_no).await
+    }
+
+    async fn update_user_language_in_register(
+        &self,
+        mobile_no: &str,
+        language_code: Option<String>,
+        language_name: Option<String>,
+        region_code: Option<String>,
+        timezone: Option<String>,
+        user_preferences: serde_json::Value,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("update_user_language_in_register").await?;
+        self.inner.update_user_language_in_register(mobile_no, language_code, language_name, region_code, timezone, user_preferences).await
+    }
+
+    async fn merge_user_preferences(&self, mobile_no: &str, updates: &serde_json::Map<String, serde_json::Value>) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("merge_user_preferences").await?;
+        self.inner.merge_user_preferences(mobile_no, updates).await
+    }
+
+    async fn verify_otp(&self, socket_id: &str, mobile_no: &str, session_token: &str, otp: &str) -> Result<OtpVerificationResult, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("verify_otp").await?;
+        self.inner.verify_otp(socket_id, mobile_no, session_token, otp).await
+    }
+
+    async fn verify_session_and_mobile(&self, mobile_no: &str, session_token: &str) -> Result<SessionStatus, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("verify_session_and_mobile").await?;
+        self.inner.verify_session_and_mobile(mobile_no, session_token).await
+    }
+
+    async fn check_referral_code_exists(&self, referral_code: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("check_referral_code_exists").await?;
+        self.inner.check_referral_code_exists(referral_code).await
+    }
+
+    async fn generate_unique_referral_code(&self, mobile_no: &str) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("generate_unique_referral_code").await?;
+        self.inner.generate_unique_referral_code(mobile_no).await
+    }
+
+    async fn update_user_profile_in_register(
+        &self,
+        mobile_no: &str,
+        full_name: Option<String>,
+        state: Option<String>,
+        referral_code: Option<String>,
+        referred_by: Option<String>,
+        profile_data: Option<serde_json::Value>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("update_user_profile_in_register").await?;
+        self.inner.update_user_profile_in_register(mobile_no, full_name, state, referral_code, referred_by, profile_data).await
+    }
+
+    async fn check_otp_attempts(&self, mobile_no: &str, session_token: &str, max_attempts: u32) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        FaultInjector::before_mongo("check_otp_attempts").await?;
+        self.inner.check_otp_attempts(mobile_no, session_token, max_attempts).await
+    }
+}
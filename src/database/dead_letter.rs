@@ -0,0 +1,194 @@
+use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt;
+use mongodb::bson::{doc, Bson, Document};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tokio::sync::Mutex;
+use tracing::{error, info, warn};
+
+use crate::config::CONFIG;
+use crate::database::health::DatabaseHealth;
+use crate::database::write_queue::WriteQueue;
+use crate::database::DatabaseManager;
+use crate::managers::snowflake::Snowflake;
+use crate::managers::tenant::{Tenant, TenantManager};
+
+const DEAD_LETTERS: &str = "event_dead_letters";
+
+type DeadLetterResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+// An event write that could not be stored
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventDeadLetter {
+    pub dead_letter_id: String,
+    pub tenant_id: String,
+    pub collection: String,
+    pub document: String,       // Canonical extended JSON, with the event's _id
+    pub error: String,
+    pub failed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct DeadLetterReplay {
+    pub replayed: usize,
+    pub remaining: usize,
+}
+
+// Serialises appends to and replays of EVENT_DEAD_LETTER_FILE on this server
+static FILE: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+// Event writes that failed for good: rejected by MongoDB, or neither stored nor
+// buffered by the WriteQueue (full, disabled, or a buffered write MongoDB then
+// refused). Instead of only a log line, the payload is parked in the capped
+// event_dead_letters collection of its tenant, or appended to
+// EVENT_DEAD_LETTER_FILE on this server while MongoDB cannot take it. replay()
+// stores them in their collections again; each keeps its _id, so one stored
+// by an earlier attempt is not stored twice.
+pub struct EventDeadLetters;
+
+impl EventDeadLetters {
+    // Park a failed write; never fails, the payload is logged when it cannot be kept
+    pub fn park(tenant: &'static Tenant, collection: &str, document: Document, error: String) {
+        let dead_letter = EventDeadLetter {
+            dead_letter_id: Snowflake::generate(),
+            tenant_id: tenant.tenant_id.clone(),
+            collection: collection.to_string(),
+            document: Bson::Document(document).into_canonical_extjson().to_string(),
+            error,
+            failed_at: Utc::now(),
+        };
+        tokio::spawn(async move {
+            if DatabaseHealth::is_healthy() {
+                match DatabaseManager::tenant_collection::<EventDeadLetter>(tenant, DEAD_LETTERS).insert_one(&dead_letter, None).await {
+                    Ok(_) => {
+                        warn!("🪦 Parked failed {} write in {} ({})", dead_letter.collection, DEAD_LETTERS, dead_letter.dead_letter_id);
+                        return;
+                    }
+                    Err(e) => warn!("⚠️ Failed to store dead letter {} in MongoDB: {}", dead_letter.dead_letter_id, e),
+                }
+            }
+            let _file = FILE.lock().await;
+            match Self::append(&Self::path(), std::slice::from_ref(&dead_letter)) {
+                Ok(()) => warn!("🪦 Parked failed {} write in {} ({})", dead_letter.collection, CONFIG.event_dead_letter_file, dead_letter.dead_letter_id),
+                Err(e) => error!("❌ Lost {} write, dead letter file unavailable ({}): {}", dead_letter.collection, e, dead_letter.document),
+            }
+        });
+    }
+
+    fn path() -> PathBuf {
+        PathBuf::from(&CONFIG.event_dead_letter_file)
+    }
+
+    fn append(path: &Path, dead_letters: &[EventDeadLetter]) -> DeadLetterResult<()> {
+        let lines = dead_letters.iter().map(serde_json::to_string).collect::<Result<Vec<_>, _>>()?;
+        Self::append_lines(path, &lines)
+    }
+
+    fn append_lines(path: &Path, lines: &[String]) -> DeadLetterResult<()> {
+        if lines.is_empty() {
+            return Ok(());
+        }
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+        for line in lines {
+            writeln!(file, "{}", line)?;
+        }
+        Ok(())
+    }
+
+    // Newest dead letters in the collection of the tenant in scope
+    pub async fn list(limit: i64) -> DeadLetterResult<Vec<EventDeadLetter>> {
+        let options = mongodb::options::FindOptions::builder().sort(doc! { "$natural": -1 }).limit(limit).build();
+        Ok(DatabaseManager::collection::<EventDeadLetter>(DEAD_LETTERS).find(None, options).await?.try_collect().await?)
+    }
+
+    // Dead letters waiting in this server's file
+    pub async fn file_count() -> usize {
+        let _file = FILE.lock().await;
+        std::fs::read_to_string(Self::path()).map(|text| text.lines().filter(|line| !line.trim().is_empty()).count()).unwrap_or(0)
+    }
+
+    // Store one dead letter in its collection again; false when it still cannot be stored
+    async fn store(dead_letter: &EventDeadLetter) -> bool {
+        let Some(tenant) = TenantManager::get(&dead_letter.tenant_id) else {
+            warn!("⚠️ Dead letter {} is for unknown tenant {}", dead_letter.dead_letter_id, dead_letter.tenant_id);
+            return false;
+        };
+        let document = serde_json::from_str::<serde_json::Value>(&dead_letter.document).ok().and_then(|value| Bson::try_from(value).ok());
+        let Some(Bson::Document(document)) = document else {
+            warn!("⚠️ Dead letter {} does not hold a document", dead_letter.dead_letter_id);
+            return false;
+        };
+        match DatabaseManager::tenant_collection::<Document>(tenant, &dead_letter.collection).insert_one(document, None).await {
+            Ok(_) => true,
+            Err(e) if WriteQueue::is_duplicate_key(&e) => true,     // Stored by an earlier attempt
+            Err(e) => {
+                warn!("⚠️ Dead letter {} for {} still failing: {}", dead_letter.dead_letter_id, dead_letter.collection, e);
+                false
+            }
+        }
+    }
+
+    // Store every dead letter of this server's file and of every tenant's
+    // collection again, removing those that were stored
+    pub async fn replay() -> DeadLetterResult<DeadLetterReplay> {
+        let mut result = DeadLetterReplay::default();
+
+        // The file is moved aside first, so writes parked meanwhile (by another
+        // process too) start a new one. One left by an interrupted replay is
+        // finished before the file is moved again.
+        let file = FILE.lock().await;
+        let path = Self::path();
+        let replaying = path.with_extension("replaying");
+        if path.exists() && !replaying.exists() {
+            std::fs::rename(&path, &replaying)?;
+        }
+        if replaying.exists() {
+            let mut kept = Vec::new();
+            for line in std::fs::read_to_string(&replaying)?.lines().filter(|line| !line.trim().is_empty()) {
+                let stored = match serde_json::from_str::<EventDeadLetter>(line) {
+                    Ok(dead_letter) => Self::store(&dead_letter).await,
+                    Err(e) => {
+                        warn!("⚠️ Unreadable line in {}: {}", replaying.display(), e);
+                        false
+                    }
+                };
+                if stored {
+                    result.replayed += 1;
+                } else {
+                    kept.push(line.to_string());
+                }
+            }
+            result.remaining += kept.len();
+            Self::append_lines(&path, &kept)?;
+            std::fs::remove_file(&replaying)?;
+        }
+        drop(file);
+
+        for tenant in TenantManager::tenants() {
+            let dead_letters = DatabaseManager::tenant_collection::<EventDeadLetter>(tenant, DEAD_LETTERS);
+            let parked: Vec<EventDeadLetter> = dead_letters.find(None, None).await?.try_collect().await?;
+            for dead_letter in parked {
+                if Self::store(&dead_letter).await {
+                    dead_letters.delete_one(doc! { "dead_letter_id": &dead_letter.dead_letter_id }, None).await?;
+                    result.replayed += 1;
+                } else {
+                    result.remaining += 1;
+                }
+            }
+        }
+        info!("🪦 Replayed {} dead-lettered event writes ({} remaining)", result.replayed, result.remaining);
+        Ok(result)
+    }
+
+    // Replay tool, not a server mode: replay-dead-letters
+    pub async fn replay_command() -> DeadLetterResult<()> {
+        let result = Self::replay().await?;
+        println!("🪦 Replayed {} dead-lettered event writes, {} remaining", result.replayed, result.remaining);
+        Ok(())
+    }
+}
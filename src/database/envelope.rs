@@ -0,0 +1,122 @@
+use bson::Document;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::database::models::*;
+use crate::database::repository::MongoDocument;
+
+// An append-only event model. Bump SCHEMA_VERSION whenever the stored shape
+// changes so readers can tell old documents from new ones.
+pub trait EventSchema: MongoDocument {
+    const EVENT_TYPE: &'static str;
+    const SCHEMA_VERSION: u32;
+}
+
+impl EventSchema for ConnectEvent { const EVENT_TYPE: &'static str = "connect"; const SCHEMA_VERSION: u32 = 2; }
+impl EventSchema for DeviceInfoEvent { const EVENT_TYPE: &'static str = "device_info"; const SCHEMA_VERSION: u32 = 1; }
+impl EventSchema for ConnectionErrorEvent { const EVENT_TYPE: &'static str = "connection_error"; const SCHEMA_VERSION: u32 = 1; }
+impl EventSchema for LoginEvent { const EVENT_TYPE: &'static str = "login"; const SCHEMA_VERSION: u32 = 1; }
+impl EventSchema for LoginSuccessEvent { const EVENT_TYPE: &'static str = "login_success"; const SCHEMA_VERSION: u32 = 1; }
+impl EventSchema for OtpVerificationEvent { const EVENT_TYPE: &'static str = "otp_verification"; const SCHEMA_VERSION: u32 = 1; }
+impl EventSchema for UserRegistrationEvent { const EVENT_TYPE: &'static str = "user_registration"; const SCHEMA_VERSION: u32 = 1; }
+impl EventSchema for UserProfileEvent { const EVENT_TYPE: &'static str = "user_profile"; const SCHEMA_VERSION: u32 = 1; }
+impl EventSchema for LanguageSettingEvent { const EVENT_TYPE: &'static str = "language_setting"; const SCHEMA_VERSION: u32 = 1; }
+impl EventSchema for TurnTimingEvent { const EVENT_TYPE: &'static str = "turn_timing"; const SCHEMA_VERSION: u32 = 1; }
+impl EventSchema for TestOtpAuditEvent { const EVENT_TYPE: &'static str = "test_otp_audit"; const SCHEMA_VERSION: u32 = 1; }
+impl EventSchema for AdminAccessDeniedEvent { const EVENT_TYPE: &'static str = "admin_access_denied"; const SCHEMA_VERSION: u32 = 1; }
+impl EventSchema for RejectedHandshakeEvent { const EVENT_TYPE: &'static str = "rejected_handshake"; const SCHEMA_VERSION: u32 = 1; }
+
+// Stored form of an event: the event's own fields plus its type and schema
+// version, side by side in one document so existing queries keep working.
+// Documents written before envelopes existed have neither field and read as
+// schema_version 0. Read envelopes through MongoRepository::find_events, which
+// validates each document (serde's flatten cannot deserialize BSON dates).
+#[derive(Debug, Clone, Serialize)]
+pub struct EventEnvelope<T> {
+    pub event_type: String,
+    pub schema_version: u32,
+    #[serde(flatten)]
+    pub payload: T,
+}
+
+impl<T: EventSchema> EventEnvelope<T> {
+    pub fn wrap(payload: T) -> Self {
+        Self {
+            event_type: T::EVENT_TYPE.to_string(),
+            schema_version: T::SCHEMA_VERSION,
+            payload,
+        }
+    }
+}
+
+// Registered schema of one event type
+pub struct EventSchemaInfo {
+    pub event_type: &'static str,
+    pub collection: &'static str,
+    pub schema_version: u32,
+    validate: fn(&Document) -> Result<(), String>,
+}
+
+impl EventSchemaInfo {
+    const fn of<T: EventSchema>() -> Self {
+        Self {
+            event_type: T::EVENT_TYPE,
+            collection: T::COLLECTION,
+            schema_version: T::SCHEMA_VERSION,
+            validate: deserializes_as::<T>,
+        }
+    }
+}
+
+fn deserializes_as<T: DeserializeOwned>(document: &Document) -> Result<(), String> {
+    bson::from_document::<T>(document.clone()).map(|_| ()).map_err(|e| e.to_string())
+}
+
+// Every event collection and the shape its documents must have
+pub static EVENT_SCHEMAS: &[EventSchemaInfo] = &[
+    EventSchemaInfo::of::<ConnectEvent>(),
+    EventSchemaInfo::of::<DeviceInfoEvent>(),
+    EventSchemaInfo::of::<ConnectionErrorEvent>(),
+    EventSchemaInfo::of::<LoginEvent>(),
+    EventSchemaInfo::of::<LoginSuccessEvent>(),
+    EventSchemaInfo::of::<OtpVerificationEvent>(),
+    EventSchemaInfo::of::<UserRegistrationEvent>(),
+    EventSchemaInfo::of::<UserProfileEvent>(),
+    EventSchemaInfo::of::<LanguageSettingEvent>(),
+    EventSchemaInfo::of::<TurnTimingEvent>(),
+    EventSchemaInfo::of::<TestOtpAuditEvent>(),
+    EventSchemaInfo::of::<AdminAccessDeniedEvent>(),
+];
+
+pub struct EventRegistry;
+
+impl EventRegistry {
+    pub fn for_collection(collection: &str) -> Option<&'static EventSchemaInfo> {
+        EVENT_SCHEMAS.iter().find(|s| s.collection == collection)
+    }
+
+    // Check a stored document against its collection's schema and return its
+    // schema version. Rejects other event types, versions newer than this
+    // server knows, and documents whose fields do not match the model.
+    pub fn validate(collection: &str, document: &Document) -> Result<u32, String> {
+        let schema = Self::for_collection(collection)
+            .ok_or_else(|| format!("no event schema registered for collection {}", collection))?;
+
+        if let Ok(event_type) = document.get_str("event_type") {
+            if event_type != schema.event_type {
+                return Err(format!("event_type {} does not belong in {}", event_type, collection));
+            }
+        }
+        let version = match document.get("schema_version") {
+            None => 0,
+            Some(value) => value.as_i32().map(i64::from).or_else(|| value.as_i64())
+                .and_then(|v| u32::try_from(v).ok())
+                .ok_or_else(|| format!("invalid schema_version {}", value))?,
+        };
+        if version > schema.schema_version {
+            return Err(format!("{} schema_version {} is newer than supported version {}", schema.event_type, version, schema.schema_version));
+        }
+
+        (schema.validate)(document).map_err(|e| format!("{} v{} does not match schema: {}", schema.event_type, version, e))?;
+        Ok(version)
+    }
+}
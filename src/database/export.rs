@@ -0,0 +1,608 @@
+use arrow_array::{ArrayRef, BooleanArray, Float64Array, Int64Array, RecordBatch, StringArray, TimestampMillisecondArray};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use bson::{doc, Bson, Document};
+use chrono::{DateTime, Duration, Utc};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use futures_util::TryStreamExt;
+use mongodb::options::FindOptions;
+use object_store::path::Path as ObjectPath;
+use object_store::{ObjectStore, PutPayload, WriteMultipart};
+use once_cell::sync::Lazy;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression as ParquetCompression;
+use parquet::file::properties::WriterProperties;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::config::CONFIG;
+use crate::database::backup::BackupManager;
+use crate::database::envelope::{EventRegistry, EVENT_SCHEMAS};
+use crate::database::DatabaseManager;
+use crate::managers::scheduler::Scheduler;
+use crate::managers::tenant::TenantManager;
+
+// Documents read and converted at a time
+const BATCH_ROWS: usize = 8192;
+// Rows per Parquet row group; a row group is encoded in memory before it is uploaded
+const ROW_GROUP_ROWS: usize = 128 * 1024;
+// Encoded bytes collected before a part is handed to the uploader
+const PART_BYTES: usize = 8 * 1024 * 1024;
+const UPLOAD_CONCURRENCY: usize = 4;
+// Finished exports listed by the admin API
+const MAX_LISTED_EXPORTS: usize = 20;
+// requested_by of the nightly export
+pub const SCHEDULED_EXPORT: &str = "scheduler";
+
+type ExportResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Parquet,
+    Csv,        // gzip-compressed, with a header row
+}
+
+impl ExportFormat {
+    pub fn parse(format: &str) -> Option<Self> {
+        match format {
+            "parquet" => Some(ExportFormat::Parquet),
+            "csv" => Some(ExportFormat::Csv),
+            _ => None,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Parquet => "parquet",
+            ExportFormat::Csv => "csv.gz",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportStatus {
+    Running,
+    Completed,
+    Failed,
+}
+
+// Type of an exported column. Parquet stores it as such; CSV writes
+// timestamps as RFC 3339 and everything else as text.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnType {
+    Int64,
+    Float64,
+    Boolean,
+    Timestamp,      // Milliseconds, UTC
+    String,         // Strings, ObjectIds as hex, documents and arrays as relaxed extended JSON
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportColumn {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub column_type: ColumnType,
+    pub source_types: Vec<String>,  // BSON types the field had in the exported range
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollectionExport {
+    pub collection: String,
+    pub event_type: String,
+    pub schema_version: u32,        // Newest event schema; older documents may lack fields
+    pub file: String,
+    pub columns: Vec<ExportColumn>,
+    pub rows: u64,
+    pub expected_rows: u64,         // Documents in the range when the collection was started, for progress
+    pub bytes: u64,
+    pub done: bool,
+}
+
+// What to export
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExportRequest {
+    pub collections: Vec<String>,
+    pub from: DateTime<Utc>,        // Inclusive, on the event's timestamp
+    pub to: DateTime<Utc>,          // Exclusive
+    #[serde(default = "default_format")]
+    pub format: ExportFormat,
+}
+
+fn default_format() -> ExportFormat {
+    ExportFormat::Parquet
+}
+
+// An export run. Written as manifest.json next to the files once every file
+// is uploaded, so an export without a manifest is incomplete; the manifest
+// holds each file's columns and their types.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportJob {
+    pub export_id: String,
+    pub tenant_id: String,
+    pub environment: Option<String>,
+    pub status: ExportStatus,
+    pub requested_by: String,
+    pub format: ExportFormat,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub collections: Vec<CollectionExport>,
+    pub error: Option<String>,
+}
+
+// Exports started on this server, by export_id
+static JOBS: Lazy<RwLock<HashMap<String, ExportJob>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+// Encodes one file of an export in memory, a part at a time
+enum FileWriter {
+    Parquet(ArrowWriter<Vec<u8>>),
+    Csv(GzEncoder<Vec<u8>>),
+}
+
+impl FileWriter {
+    fn new(format: ExportFormat, columns: &[ExportColumn]) -> ExportResult<Self> {
+        match format {
+            ExportFormat::Parquet => {
+                let properties = WriterProperties::builder()
+                    .set_compression(ParquetCompression::SNAPPY)
+                    .set_max_row_group_size(ROW_GROUP_ROWS)
+                    .build();
+                Ok(FileWriter::Parquet(ArrowWriter::try_new(Vec::new(), ExportManager::arrow_schema(columns), Some(properties))?))
+            }
+            ExportFormat::Csv => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                let header = ExportManager::csv_lines(std::iter::once(columns.iter().map(|c| c.name.clone()).collect()))?;
+                encoder.write_all(&header)?;
+                Ok(FileWriter::Csv(encoder))
+            }
+        }
+    }
+
+    fn write(&mut self, columns: &[ExportColumn], rows: &[Document]) -> ExportResult<()> {
+        match self {
+            FileWriter::Parquet(writer) => {
+                let arrays = columns.iter().map(|column| ExportManager::arrow_column(column, rows)).collect();
+                writer.write(&RecordBatch::try_new(ExportManager::arrow_schema(columns), arrays)?)?;
+            }
+            FileWriter::Csv(encoder) => {
+                let lines = rows.iter().map(|row| {
+                    columns.iter().map(|column| ExportManager::csv_cell(column, row.get(&column.name))).collect()
+                });
+                encoder.write_all(&ExportManager::csv_lines(lines)?)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Encoded bytes ready for upload, once there are enough for a part
+    fn take_part(&mut self) -> Option<Vec<u8>> {
+        let buffer = match self {
+            FileWriter::Parquet(writer) => writer.inner_mut(),
+            FileWriter::Csv(encoder) => encoder.get_mut(),
+        };
+        (buffer.len() >= PART_BYTES).then(|| std::mem::take(buffer))
+    }
+
+    // The rest of the file, footer included
+    fn finish(self) -> ExportResult<Vec<u8>> {
+        match self {
+            FileWriter::Parquet(writer) => Ok(writer.into_inner()?),
+            FileWriter::Csv(encoder) => Ok(encoder.finish()?),
+        }
+    }
+}
+
+// Bulk export of event collections for the data team: every event of the
+// chosen collections with a timestamp in [from, to), one Parquet or gzip CSV
+// file per collection under <EXPORT_STORE_URL>/<tenant_id>/<export_id>/, with
+// the columns and their types in manifest.json. Columns are the fields the
+// documents of the range have; a value that does not fit its column's type is
+// exported as null. Admin-triggered exports run in the background with
+// progress kept in memory like backups; EXPORT_SCHEDULE_COLLECTIONS adds a
+// nightly export of the previous UTC day as export_id daily-<date>.
+pub struct ExportManager;
+
+impl ExportManager {
+    fn store() -> ExportResult<(Arc<dyn ObjectStore>, ObjectPath)> {
+        BackupManager::store_at(CONFIG.export_store_url.as_deref(), "EXPORT_STORE_URL")
+    }
+
+    fn export_path(root: &ObjectPath, tenant_id: &str, export_id: &str) -> ObjectPath {
+        root.child(tenant_id).child(export_id)
+    }
+
+    // Start an export of the current tenant in the background; returns it as
+    // first published. Err carries an error code for the admin API.
+    pub async fn start(requested_by: &str, request: ExportRequest) -> Result<ExportJob, &'static str> {
+        let export_id = Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext)).to_string();
+        let (store, root, job) = Self::prepare(requested_by, request, export_id).await?;
+        let export_id = job.export_id.clone();
+        TenantManager::spawn(async move {
+            let _ = Self::complete(&*store, &root, &export_id).await;
+        });
+        info!("📦 Export {} of tenant {} started by {}", job.export_id, job.tenant_id, requested_by);
+        Ok(job)
+    }
+
+    // Check the request and publish the job as running
+    async fn prepare(requested_by: &str, request: ExportRequest, export_id: String) -> Result<(Arc<dyn ObjectStore>, ObjectPath, ExportJob), &'static str> {
+        if CONFIG.in_memory_store {
+            return Err("EXPORTS_UNAVAILABLE");
+        }
+        if request.collections.is_empty() || request.collections.iter().any(|name| EventRegistry::for_collection(name).is_none()) {
+            return Err("EXPORT_UNKNOWN_COLLECTION");
+        }
+        if request.from >= request.to {
+            return Err("EXPORT_INVALID_RANGE");
+        }
+        let (store, root) = Self::store().map_err(|e| {
+            warn!("⚠️ Event exports unavailable: {}", e);
+            "EXPORTS_UNAVAILABLE"
+        })?;
+        let tenant = TenantManager::current();
+        let mut jobs = JOBS.write().await;
+        if jobs.values().any(|job| job.tenant_id == tenant.tenant_id && job.status == ExportStatus::Running) {
+            return Err("EXPORT_RUNNING");
+        }
+
+        let mut collections: Vec<&String> = Vec::new();
+        for name in &request.collections {
+            if !collections.contains(&name) {
+                collections.push(name);
+            }
+        }
+        let job = ExportJob {
+            export_id,
+            tenant_id: tenant.tenant_id.clone(),
+            environment: CONFIG.environment.clone(),
+            status: ExportStatus::Running,
+            requested_by: requested_by.to_string(),
+            format: request.format,
+            from: request.from,
+            to: request.to,
+            started_at: Utc::now(),
+            finished_at: None,
+            collections: collections.into_iter().filter_map(|name| EventRegistry::for_collection(name)).map(|schema| CollectionExport {
+                collection: schema.collection.to_string(),
+                event_type: schema.event_type.to_string(),
+                schema_version: schema.schema_version,
+                file: format!("{}.{}", schema.collection, request.format.extension()),
+                columns: Vec::new(),
+                rows: 0,
+                expected_rows: 0,
+                bytes: 0,
+                done: false,
+            }).collect(),
+            error: None,
+        };
+        jobs.insert(job.export_id.clone(), job.clone());
+        Ok((store, root, job))
+    }
+
+    // Run a prepared export and publish how it ended
+    async fn complete(store: &dyn ObjectStore, root: &ObjectPath, export_id: &str) -> Result<(), String> {
+        let result = Self::run(store, root, export_id).await;
+        let mut jobs = JOBS.write().await;
+        let Some(job) = jobs.get_mut(export_id) else { return Ok(()) };
+        job.finished_at = Some(Utc::now());
+        match result {
+            Ok(()) => {
+                job.status = ExportStatus::Completed;
+                let rows: u64 = job.collections.iter().map(|c| c.rows).sum();
+                info!("📦 Export {} of tenant {} completed: {} rows", export_id, job.tenant_id, rows);
+                Ok(())
+            }
+            Err(e) => {
+                job.status = ExportStatus::Failed;
+                job.error = Some(e.to_string());
+                error!("❌ Export {} of tenant {} failed: {}", export_id, job.tenant_id, e);
+                Err(e.to_string())
+            }
+        }
+    }
+
+    async fn update(export_id: &str, index: usize, apply: impl FnOnce(&mut CollectionExport)) {
+        if let Some(job) = JOBS.write().await.get_mut(export_id) {
+            apply(&mut job.collections[index]);
+        }
+    }
+
+    async fn run(store: &dyn ObjectStore, root: &ObjectPath, export_id: &str) -> ExportResult<()> {
+        let tenant_id = &TenantManager::current().tenant_id;
+        let dir = Self::export_path(root, tenant_id, export_id);
+        let job = JOBS.read().await.get(export_id).cloned().ok_or("export job disappeared")?;
+        let filter = doc! {
+            "timestamp": {
+                "$gte": bson::DateTime::from_millis(job.from.timestamp_millis()),
+                "$lt": bson::DateTime::from_millis(job.to.timestamp_millis()),
+            }
+        };
+        for (index, entry) in job.collections.iter().enumerate() {
+            let collection = DatabaseManager::collection::<Document>(&entry.collection);
+            let columns = Self::columns(&collection, &filter).await?;
+            let expected = collection.count_documents(filter.clone(), None).await?;
+            let listed = columns.clone();
+            Self::update(export_id, index, |c| {
+                c.columns = listed;
+                c.expected_rows = expected;
+            }).await;
+
+            let mut upload = WriteMultipart::new(store.put_multipart(&dir.child(entry.file.as_str())).await?);
+            let exported = Self::export_collection(&collection, &filter, &columns, job.format, &mut upload, export_id, index).await;
+            let (rows, bytes) = match exported {
+                Ok(counts) => counts,
+                Err(e) => {
+                    let _ = upload.abort().await;
+                    return Err(e);
+                }
+            };
+            upload.finish().await?;
+            Self::update(export_id, index, |c| {
+                c.rows = rows;
+                c.bytes = bytes;
+                c.done = true;
+            }).await;
+        }
+
+        let mut manifest = JOBS.read().await.get(export_id).cloned().ok_or("export job disappeared")?;
+        manifest.status = ExportStatus::Completed;
+        manifest.finished_at = Some(Utc::now());
+        store.put(&dir.child("manifest.json"), PutPayload::from(serde_json::to_vec_pretty(&manifest)?)).await?;
+        Ok(())
+    }
+
+    // Every field the documents of the range have, with the BSON types seen,
+    // computed by the server. A range without documents exports _id only.
+    async fn columns(collection: &mongodb::Collection<Document>, filter: &Document) -> ExportResult<Vec<ExportColumn>> {
+        let pipeline = vec![
+            doc! { "$match": filter.clone() },
+            doc! { "$project": { "fields": { "$objectToArray": "$$ROOT" } } },
+            doc! { "$unwind": "$fields" },
+            doc! { "$group": { "_id": "$fields.k", "types": { "$addToSet": { "$type": "$fields.v" } } } },
+            doc! { "$sort": { "_id": 1 } },
+        ];
+        let mut cursor = collection.aggregate(pipeline, None).await?;
+        let mut columns = Vec::new();
+        while let Some(field) = cursor.try_next().await? {
+            let Ok(name) = field.get_str("_id") else { continue };
+            let mut source_types: Vec<String> = field.get_array("types").map(|types| {
+                types.iter().filter_map(|t| t.as_str().map(str::to_string)).collect()
+            }).unwrap_or_default();
+            source_types.sort();
+            columns.push(ExportColumn {
+                name: name.to_string(),
+                column_type: Self::column_type(&source_types),
+                source_types,
+            });
+        }
+        if columns.is_empty() {
+            columns.push(ExportColumn { name: "_id".to_string(), column_type: ColumnType::String, source_types: vec!["objectId".to_string()] });
+        }
+        Ok(columns)
+    }
+
+    fn column_type(source_types: &[String]) -> ColumnType {
+        let types: Vec<&str> = source_types.iter().map(String::as_str).filter(|t| *t != "null").collect();
+        if types.is_empty() {
+            ColumnType::String
+        } else if types.iter().all(|t| matches!(*t, "int" | "long")) {
+            ColumnType::Int64
+        } else if types.iter().all(|t| matches!(*t, "int" | "long" | "double")) {
+            ColumnType::Float64
+        } else if types.iter().all(|t| *t == "bool") {
+            ColumnType::Boolean
+        } else if types.iter().all(|t| *t == "date") {
+            ColumnType::Timestamp
+        } else {
+            ColumnType::String
+        }
+    }
+
+    // Stream one collection's range into an upload; returns rows and bytes written
+    async fn export_collection(
+        collection: &mongodb::Collection<Document>,
+        filter: &Document,
+        columns: &[ExportColumn],
+        format: ExportFormat,
+        upload: &mut WriteMultipart,
+        export_id: &str,
+        index: usize,
+    ) -> ExportResult<(u64, u64)> {
+        let options = FindOptions::builder().batch_size(BATCH_ROWS as u32).build();
+        let mut batches = collection.find(filter.clone(), options).await?.try_chunks(BATCH_ROWS);
+        let mut writer = FileWriter::new(format, columns)?;
+        let (mut rows, mut bytes) = (0_u64, 0_u64);
+        while let Some(batch) = batches.try_next().await.map_err(|e| e.1)? {
+            writer.write(columns, &batch)?;
+            rows += batch.len() as u64;
+            if let Some(part) = writer.take_part() {
+                bytes += part.len() as u64;
+                upload.write(&part);
+                upload.wait_for_capacity(UPLOAD_CONCURRENCY).await?;
+            }
+            let written = bytes;
+            Self::update(export_id, index, |c| {
+                c.rows = rows;
+                c.bytes = written;
+            }).await;
+        }
+        let rest = writer.finish()?;
+        bytes += rest.len() as u64;
+        upload.write(&rest);
+        Ok((rows, bytes))
+    }
+
+    fn arrow_schema(columns: &[ExportColumn]) -> Arc<Schema> {
+        let fields: Vec<Field> = columns.iter().map(|column| {
+            let data_type = match column.column_type {
+                ColumnType::Int64 => DataType::Int64,
+                ColumnType::Float64 => DataType::Float64,
+                ColumnType::Boolean => DataType::Boolean,
+                ColumnType::Timestamp => DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())),
+                ColumnType::String => DataType::Utf8,
+            };
+            Field::new(column.name.as_str(), data_type, true)
+        }).collect();
+        Arc::new(Schema::new(fields))
+    }
+
+    fn arrow_column(column: &ExportColumn, rows: &[Document]) -> ArrayRef {
+        let values = rows.iter().map(|row| row.get(&column.name));
+        match column.column_type {
+            ColumnType::Int64 => Arc::new(values.map(|v| v.and_then(Self::as_i64)).collect::<Int64Array>()),
+            ColumnType::Float64 => Arc::new(values.map(|v| v.and_then(Self::as_f64)).collect::<Float64Array>()),
+            ColumnType::Boolean => Arc::new(values.map(|v| v.and_then(Bson::as_bool)).collect::<BooleanArray>()),
+            ColumnType::Timestamp => Arc::new(
+                values.map(|v| v.and_then(Bson::as_datetime).map(|d| d.timestamp_millis()))
+                    .collect::<TimestampMillisecondArray>()
+                    .with_timezone("UTC"),
+            ),
+            ColumnType::String => Arc::new(values.map(|v| v.and_then(Self::as_text)).collect::<StringArray>()),
+        }
+    }
+
+    fn as_i64(value: &Bson) -> Option<i64> {
+        match value {
+            Bson::Int32(n) => Some(*n as i64),
+            Bson::Int64(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    fn as_f64(value: &Bson) -> Option<f64> {
+        match value {
+            Bson::Double(n) => Some(*n),
+            Bson::Int32(n) => Some(*n as f64),
+            Bson::Int64(n) => Some(*n as f64),
+            _ => None,
+        }
+    }
+
+    fn timestamp_text(millis: i64) -> Option<String> {
+        DateTime::from_timestamp_millis(millis).map(|time| time.to_rfc3339())
+    }
+
+    fn as_text(value: &Bson) -> Option<String> {
+        match value {
+            Bson::Null | Bson::Undefined => None,
+            Bson::String(s) => Some(s.clone()),
+            Bson::ObjectId(id) => Some(id.to_hex()),
+            Bson::DateTime(time) => Self::timestamp_text(time.timestamp_millis()),
+            Bson::Boolean(b) => Some(b.to_string()),
+            Bson::Int32(n) => Some(n.to_string()),
+            Bson::Int64(n) => Some(n.to_string()),
+            Bson::Double(n) => Some(n.to_string()),
+            other => Some(other.clone().into_relaxed_extjson().to_string()),
+        }
+    }
+
+    // A CSV field holds what the Parquet column would: empty for null or a
+    // value of another type
+    fn csv_cell(column: &ExportColumn, value: Option<&Bson>) -> String {
+        let Some(value) = value else { return String::new() };
+        let cell = match column.column_type {
+            ColumnType::Int64 => Self::as_i64(value).map(|n| n.to_string()),
+            ColumnType::Float64 => Self::as_f64(value).map(|n| n.to_string()),
+            ColumnType::Boolean => value.as_bool().map(|b| b.to_string()),
+            ColumnType::Timestamp => value.as_datetime().and_then(|time| Self::timestamp_text(time.timestamp_millis())),
+            ColumnType::String => Self::as_text(value),
+        };
+        cell.unwrap_or_default()
+    }
+
+    fn csv_lines(lines: impl Iterator<Item = Vec<String>>) -> ExportResult<Vec<u8>> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        for line in lines {
+            writer.write_record(&line)?;
+        }
+        Ok(writer.into_inner().map_err(|e| e.into_error())?)
+    }
+
+    pub async fn job(export_id: &str) -> Option<ExportJob> {
+        let tenant_id = &TenantManager::current().tenant_id;
+        JOBS.read().await.get(export_id).filter(|job| &job.tenant_id == tenant_id).cloned()
+    }
+
+    async fn manifest(store: &dyn ObjectStore, path: &ObjectPath) -> ExportResult<ExportJob> {
+        let bytes = store.get(path).await?.bytes().await?;
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    // Exports of the current tenant running on this server, then the newest
+    // finished ones in storage
+    pub async fn list() -> ExportResult<Vec<ExportJob>> {
+        let (store, root) = Self::store()?;
+        let tenant_id = &TenantManager::current().tenant_id;
+        let mut exports: Vec<ExportJob> = JOBS.read().await.values()
+            .filter(|job| &job.tenant_id == tenant_id && job.status == ExportStatus::Running)
+            .cloned()
+            .collect();
+
+        // Nightly export ids are not UUIDv7, so order by when the manifest was written
+        let mut manifests: Vec<_> = store.list(Some(&root.child(tenant_id.as_str())))
+            .try_filter(|meta| futures_util::future::ready(meta.location.filename() == Some("manifest.json")))
+            .try_collect()
+            .await?;
+        manifests.sort_by_key(|meta| std::cmp::Reverse(meta.last_modified));
+        for meta in manifests.iter().take(MAX_LISTED_EXPORTS) {
+            match Self::manifest(&*store, &meta.location).await {
+                Ok(manifest) => exports.push(manifest),
+                Err(e) => warn!("⚠️ Skipping unreadable export manifest {}: {}", meta.location, e),
+            }
+        }
+        Ok(exports)
+    }
+
+    // Half past midnight UTC, once the previous day's events are stored
+    fn next_run(now: DateTime<Utc>) -> DateTime<Utc> {
+        let tomorrow = now.date_naive() + Duration::days(1);
+        tomorrow.and_hms_opt(0, 30, 0).unwrap_or_default().and_utc()
+    }
+
+    // Export EXPORT_SCHEDULE_COLLECTIONS for the previous UTC day at startup
+    // and after every midnight, unless that day's export is already stored
+    pub fn spawn_daily_export() {
+        if CONFIG.export_schedule_collections.is_empty() || CONFIG.in_memory_store {
+            return;
+        }
+        let Some(format) = ExportFormat::parse(&CONFIG.export_schedule_format) else {
+            error!("❌ EXPORT_SCHEDULE_FORMAT must be parquet or csv, got {} - nightly export disabled", CONFIG.export_schedule_format);
+            return;
+        };
+        if let Some(unknown) = CONFIG.export_schedule_collections.iter().find(|name| EventRegistry::for_collection(name).is_none()) {
+            let known: Vec<&str> = EVENT_SCHEMAS.iter().map(|schema| schema.collection).collect();
+            error!("❌ EXPORT_SCHEDULE_COLLECTIONS names {}, which is not one of {} - nightly export disabled", unknown, known.join(", "));
+            return;
+        }
+        Scheduler::at("event-export", Self::next_run, move || async move {
+            let day = Utc::now().date_naive() - Duration::days(1);
+            let export_id = format!("daily-{}", day);
+            let (store, root) = Self::store()?;
+            let tenant_id = &TenantManager::current().tenant_id;
+            let manifest = Self::export_path(&root, tenant_id, &export_id).child("manifest.json");
+            if store.head(&manifest).await.is_ok() {
+                return Ok(());
+            }
+            let from = day.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+            let request = ExportRequest {
+                collections: CONFIG.export_schedule_collections.clone(),
+                from,
+                to: from + Duration::days(1),
+                format,
+            };
+            let (store, root, job) = Self::prepare(SCHEDULED_EXPORT, request, export_id).await?;
+            info!("📦 Nightly export {} of tenant {} started", job.export_id, job.tenant_id);
+            Self::complete(&*store, &root, &job.export_id).await?;
+            Ok(())
+        });
+    }
+}
@@ -1,29 +1,64 @@
-use mongodb::Database;
+use bson::{doc, Document};
+use mongodb::options::{FindOneAndUpdateOptions, ReturnDocument};
+use mongodb::Collection;
 use tracing::info;
 
-pub struct GameplayService {
-    database: &'static Database,
-}
+use crate::database::models::{GameplayProgress, ProgressUpdate};
+use crate::database::DatabaseManager;
+
+pub struct GameplayService;
 
 impl GameplayService {
-    pub fn new(database: &'static Database) -> Self {
-        Self { database }
+    pub fn new() -> Self {
+        Self
+    }
+
+    // Collection in the database of the tenant in scope, named with COLLECTION_PREFIX
+    fn collection<T>(&self, name: &str) -> Collection<T> {
+        DatabaseManager::collection(name)
     }
 
-    pub async fn initialize_gameplay_data(&self, user_id: &str) -> Result<(), Box<dyn std::error::Error>> {
-        info!("🎮 Initializing gameplay data for user: {}", user_id);
-        
-        // Add your gameplay initialization logic here
-        // For now, just log the initialization
-        
-        Ok(())
+    fn progress(&self) -> Collection<GameplayProgress> {
+        self.collection("gameplay_progress")
     }
 
-    pub async fn update_gameplay_progress(&self, user_id: &str, _progress_data: serde_json::Value) -> Result<(), Box<dyn std::error::Error>> {
-        info!("📊 Updating gameplay progress for user: {}", user_id);
-        
-        // Add your gameplay progress update logic here
-        
-        Ok(())
+    pub async fn get_gameplay_progress(&self, user_id: &str) -> Result<Option<GameplayProgress>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.progress().find_one(doc! { "user_id": user_id }, None).await?)
     }
-} 
\ No newline at end of file
+
+    // Upsert a user's progress: xp and game counters with $inc, level and best
+    // score with $max. Returns the document after the update.
+    pub async fn update_gameplay_progress(&self, user_id: &str, update: &ProgressUpdate) -> Result<GameplayProgress, Box<dyn std::error::Error + Send + Sync>> {
+        let now = bson::DateTime::now();
+        let mut inc = doc! { "xp": update.xp };
+        let mut max = doc! { "level": update.level.unwrap_or(GameplayProgress::first_level()) as i64 };
+        let mut set = doc! { "updated_at": now };
+        if let Some(game) = &update.game {
+            let path = format!("games.{}", game.game_id);
+            inc.insert(format!("{}.played", path), 1i64);
+            inc.insert(format!("{}.{}", path, game.outcome.counter()), 1i64);
+            if let Some(score) = game.score {
+                inc.insert(format!("{}.total_score", path), score);
+                max.insert(format!("{}.best_score", path), score);
+            }
+            set.insert(format!("{}.last_played_at", path), now);
+        }
+        let update_doc: Document = doc! {
+            "$setOnInsert": { "created_at": now },
+            "$inc": inc,
+            "$max": max,
+            "$set": set,
+        };
+        let options = FindOneAndUpdateOptions::builder()
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .build();
+        let progress = self.progress()
+            .find_one_and_update(doc! { "user_id": user_id }, update_doc, options)
+            .await?
+            .ok_or("gameplay progress upsert returned no document")?;
+
+        info!("📊 Updated gameplay progress for user: {} (level {}, xp {})", user_id, progress.level, progress.xp);
+        Ok(progress)
+    }
+}
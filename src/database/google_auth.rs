@@ -0,0 +1,77 @@
+use chrono::{DateTime, Utc};
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+const HTTP_TIMEOUT_SECS: u64 = 30;
+
+// Google service account key file, as downloaded from the console
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServiceAccount {
+    client_email: String,
+    private_key: String,
+    #[serde(default = "default_token_uri")]
+    token_uri: String,
+}
+
+fn default_token_uri() -> String {
+    "https://oauth2.googleapis.com/token".to_string()
+}
+
+struct AccessToken {
+    token: String,
+    expires_at: DateTime<Utc>,
+}
+
+static HTTP: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(HTTP_TIMEOUT_SECS))
+        .build()
+        .unwrap_or_default()
+});
+// Access tokens by service account and scope
+static ACCESS_TOKENS: Lazy<Mutex<HashMap<(String, &'static str), AccessToken>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+impl ServiceAccount {
+    pub fn load(path: &str) -> Result<Self, String> {
+        std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|raw| serde_json::from_str::<ServiceAccount>(&raw).map_err(|e| e.to_string()))
+    }
+
+    // OAuth token for `scope`, cached until shortly before it expires
+    pub async fn access_token(&self, scope: &'static str) -> Result<String, String> {
+        let mut cached = ACCESS_TOKENS.lock().await;
+        let key = (self.client_email.clone(), scope);
+        if let Some(cached) = cached.get(&key).filter(|c| c.expires_at > Utc::now() + chrono::Duration::seconds(60)) {
+            return Ok(cached.token.clone());
+        }
+        let now = Utc::now().timestamp();
+        let claims = json!({
+            "iss": self.client_email,
+            "scope": scope,
+            "aud": self.token_uri,
+            "iat": now,
+            "exp": now + 3600,
+        });
+        let key_pem = EncodingKey::from_rsa_pem(self.private_key.as_bytes()).map_err(|e| format!("Invalid service account key: {}", e))?;
+        let assertion = jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, &key_pem).map_err(|e| e.to_string())?;
+        let response = HTTP.post(&self.token_uri)
+            .form(&[("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"), ("assertion", assertion.as_str())])
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        let status = response.status();
+        let answer: Value = response.json().await.unwrap_or(Value::Null);
+        let Some(token) = answer["access_token"].as_str().filter(|_| status.is_success()) else {
+            return Err(format!("Google token endpoint answered {}: {}", status, answer["error_description"].as_str().unwrap_or("")));
+        };
+        let expires_at = Utc::now() + chrono::Duration::seconds(answer["expires_in"].as_i64().unwrap_or(3600));
+        cached.insert(key, AccessToken { token: token.to_string(), expires_at });
+        Ok(token.to_string())
+    }
+}
@@ -0,0 +1,79 @@
+use mongodb::bson::doc;
+use once_cell::sync::Lazy;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+use crate::config::CONFIG;
+use crate::database::DatabaseManager;
+use crate::managers::metrics::MetricsManager;
+
+// A ping slower than this counts as a failure
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
+// This many up/down changes within FLAP_WINDOW is reported as flapping
+const FLAP_THRESHOLD: usize = 4;
+const FLAP_WINDOW: Duration = Duration::from_secs(300);
+
+// Assume healthy until the first ping says otherwise (initialize() has
+// already connected successfully)
+static HEALTHY: AtomicBool = AtomicBool::new(true);
+// When recent up/down changes happened, for flap detection
+static TRANSITIONS: Lazy<Mutex<VecDeque<Instant>>> = Lazy::new(|| Mutex::new(VecDeque::new()));
+
+// Tracks whether MongoDB is answering pings. The driver reconnects on its
+// own once the server is back; this only tells the rest of the app whether
+// it is worth waiting on a database call. Read by `/health`, the
+// `health_check` event and the WriteQueue, which buffers event writes
+// instead of trying MongoDB while it is down.
+pub struct DatabaseHealth;
+
+impl DatabaseHealth {
+    // Always true with DATA_STORE=memory, where the monitor never runs
+    pub fn is_healthy() -> bool {
+        HEALTHY.load(Ordering::Relaxed)
+    }
+
+    pub fn spawn_monitor() {
+        let period = Duration::from_secs(CONFIG.mongo_health_check_interval_secs.max(1));
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                let result = tokio::time::timeout(PING_TIMEOUT,
+                    DatabaseManager::get_database().run_command(doc! { "ping": 1 }, None)).await;
+                match result {
+                    Ok(Ok(_)) => Self::update(true, None).await,
+                    Ok(Err(e)) => Self::update(false, Some(e.to_string())).await,
+                    Err(_) => Self::update(false, Some(format!("no reply within {:?}", PING_TIMEOUT))).await,
+                }
+            }
+        });
+        info!("💓 MongoDB health check every {:?}", period);
+    }
+
+    async fn update(healthy: bool, reason: Option<String>) {
+        MetricsManager::set_gauge("mongo_up", if healthy { 1.0 } else { 0.0 }).await;
+        if HEALTHY.swap(healthy, Ordering::Relaxed) == healthy {
+            return;
+        }
+
+        if healthy {
+            info!("✅ MongoDB is reachable again");
+        } else {
+            error!("🚨 MongoDB is unreachable: {}", reason.unwrap_or_default());
+        }
+
+        let mut transitions = TRANSITIONS.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        transitions.push_back(now);
+        while transitions.front().is_some_and(|t| now.duration_since(*t) > FLAP_WINDOW) {
+            transitions.pop_front();
+        }
+        if transitions.len() >= FLAP_THRESHOLD {
+            warn!("🚨 MongoDB is flapping: {} up/down changes in the last {} minutes",
+                transitions.len(), FLAP_WINDOW.as_secs() / 60);
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use bson::doc;
+use futures_util::TryStreamExt;
+use mongodb::options::{FindOneAndUpdateOptions, ReturnDocument};
+use mongodb::{ClientSession, Collection};
+
+use crate::database::models::{InventoryItem, InventoryTransaction};
+use crate::database::DatabaseManager;
+use crate::managers::snowflake::Snowflake;
+
+// Item counts per user (`inventory`) and their ledger
+// (`inventory_transactions`), kept the same way as WalletService keeps coins.
+pub struct InventoryService;
+
+impl InventoryService {
+    pub fn new() -> Self {
+        Self
+    }
+
+    // Collection in the database of the tenant in scope, named with COLLECTION_PREFIX
+    fn collection<T>(&self, name: &str) -> Collection<T> {
+        DatabaseManager::collection(name)
+    }
+
+    fn items(&self) -> Collection<InventoryItem> {
+        self.collection("inventory")
+    }
+
+    fn transactions(&self) -> Collection<InventoryTransaction> {
+        self.collection("inventory_transactions")
+    }
+
+    fn transaction(user_id: &str, item_id: &str, quantity: i64, reason: &str, reference: &str) -> InventoryTransaction {
+        InventoryTransaction {
+            id: None,
+            transaction_id: Snowflake::generate(),
+            user_id: user_id.to_string(),
+            item_id: item_id.to_string(),
+            quantity,
+            reason: reason.to_string(),
+            reference: reference.to_string(),
+            created_at: bson::DateTime::now(),
+        }
+    }
+
+    // Items the user holds at least one of
+    pub async fn list(&self, user_id: &str) -> Result<Vec<InventoryItem>, Box<dyn std::error::Error + Send + Sync>> {
+        let options = mongodb::options::FindOptions::builder().sort(doc! { "item_id": 1 }).build();
+        let cursor = self.items().find(doc! { "user_id": user_id, "quantity": { "$gt": 0 } }, options).await?;
+        Ok(cursor.try_collect().await?)
+    }
+
+    // Add items within the caller's transaction. The ledger's unique reference
+    // fails the transaction if it was already applied. Returns the new quantity.
+    pub async fn grant_in_session(&self, session: &mut ClientSession, user_id: &str, item_id: &str, quantity: i64, reason: &str, reference: &str) -> Result<i64, Box<dyn std::error::Error + Send + Sync>> {
+        self.transactions().insert_one_with_session(&Self::transaction(user_id, item_id, quantity, reason, reference), None, session).await?;
+        let options = FindOneAndUpdateOptions::builder()
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .build();
+        let item = self.items()
+            .find_one_and_update_with_session(
+                doc! { "user_id": user_id, "item_id": item_id },
+                doc! { "$inc": { "quantity": quantity }, "$set": { "updated_at": bson::DateTime::now() } },
+                options,
+                session,
+            )
+            .await?
+            .ok_or("inventory upsert returned no document")?;
+        Ok(item.quantity)
+    }
+
+    // Remove items within the caller's transaction if the user holds enough.
+    // Returns the remaining quantity, or None if they do not.
+    pub async fn take_in_session(&self, session: &mut ClientSession, user_id: &str, item_id: &str, quantity: i64, reason: &str, reference: &str) -> Result<Option<i64>, Box<dyn std::error::Error + Send + Sync>> {
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        let Some(item) = self.items()
+            .find_one_and_update_with_session(
+                doc! { "user_id": user_id, "item_id": item_id, "quantity": { "$gte": quantity } },
+                doc! { "$inc": { "quantity": -quantity }, "$set": { "updated_at": bson::DateTime::now() } },
+                options,
+                session,
+            )
+            .await?
+        else {
+            return Ok(None);
+        };
+        self.transactions().insert_one_with_session(&Self::transaction(user_id, item_id, -quantity, reason, reference), None, session).await?;
+        Ok(Some(item.quantity))
+    }
+}
@@ -0,0 +1,1857 @@
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use tokio::sync::{MappedMutexGuard, Mutex, MutexGuard};
+use tracing::info;
+
+use crate::config::CONFIG;
+use crate::database::{models::*, store::{DataStore, GameConfigChanges, UserStream}};
+use crate::database::outbox::Outbox;
+use crate::database::timeline::{Timeline, TimelineSource, TimelineSubject};
+use crate::database::envelope::{EventEnvelope, EventSchema};
+use crate::managers::correlation::Correlation;
+use crate::managers::tenant::TenantManager;
+use crate::managers::token::TokenGenerator;
+use crate::managers::snowflake::Snowflake;
+
+fn now() -> bson::DateTime {
+    bson::DateTime::from_millis(chrono::Utc::now().timestamp_millis())
+}
+
+// Set or remove (null) a dotted path inside a JSON object, creating objects on the way
+fn apply_path(target: &mut Value, path: &str, value: &Value) {
+    let mut node = target;
+    let mut segments = path.split('.').peekable();
+    while let Some(segment) = segments.next() {
+        if !node.is_object() {
+            *node = Value::Object(Map::new());
+        }
+        let Some(object) = node.as_object_mut() else { return };
+        if segments.peek().is_none() {
+            if value.is_null() {
+                object.remove(segment);
+            } else {
+                object.insert(segment.to_string(), value.clone());
+            }
+            return;
+        }
+        node = object.entry(segment).or_insert_with(|| Value::Object(Map::new()));
+    }
+}
+
+#[derive(Default)]
+struct Tables {
+    // Append-only event collections, keyed by their MongoDB collection name
+    events: HashMap<&'static str, Vec<Value>>,
+    login_sessions: Vec<LoginSuccessEvent>,
+    sessions: Vec<UserSession>,
+    users: Vec<UserRegister>,
+    devices: Vec<UserDevice>,
+    notification_preferences: HashMap<String, NotificationPreferences>,
+    admin_operators: Vec<AdminOperator>,
+    room_snapshots: HashMap<String, RoomSnapshot>,
+    escrows: Vec<MatchEscrow>,
+    revenue_ledger: Vec<RevenueEntry>,
+    revenue_rollups: Vec<RevenueRollup>,
+    promo_codes: Vec<PromoCode>,
+    promo_redemptions: Vec<PromoRedemption>,
+    impersonation_sessions: Vec<ImpersonationSession>,
+    dealer_audits: Vec<DealerAudit>,
+    session_samples: Vec<SessionSample>,
+    retention_reports: Vec<RetentionReport>,
+    gameplay_progress: HashMap<String, GameplayProgress>,
+    daily_challenges: HashMap<String, DailyChallengeSet>,
+    challenge_progress: HashMap<(String, String), ChallengeProgress>,
+    wallets: HashMap<String, i64>,
+    wallet_transactions: Vec<WalletTransaction>,
+    seasons: Vec<Season>,
+    season_ratings: Vec<SeasonRating>,
+    inventory: HashMap<(String, String), i64>,
+    inventory_transactions: Vec<InventoryTransaction>,
+    friendships: Vec<Friendship>,
+    gifts: Vec<Gift>,
+    match_participants: Vec<MatchParticipant>,
+    match_results: Vec<MatchResult>,
+    user_risk: HashMap<String, UserRisk>,
+    risk_flags: Vec<RiskFlag>,
+    user_blocks: Vec<UserBlock>,
+    user_reports: Vec<UserReport>,
+    user_sanctions: Vec<UserSanction>,
+    chat_messages: Vec<ChatMessage>,
+    chat_audit: Vec<ChatMessageAudit>,
+    chat_read_receipts: Vec<ChatReadReceipt>,
+    chat_reactions: Vec<ChatReaction>,
+    mobile_changes: Vec<MobileChange>,
+    game_configs: Vec<GameConfig>,
+    notification_templates: Vec<NotificationTemplate>,
+    user_counter: u64,
+}
+
+impl Tables {
+    fn record(&mut self, collection: &'static str, document: impl Serialize) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.events.entry(collection).or_default().push(serde_json::to_value(document)?);
+        Ok(())
+    }
+
+    // Record an event in its collection, wrapped in its EventEnvelope as in MongoDB
+    fn record_event<T: EventSchema>(&mut self, event: T) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.record(T::COLLECTION, EventEnvelope::wrap(event))
+    }
+
+    fn challenge_progress_mut(&mut self, user_id: &str, date: &str) -> &mut ChallengeProgress {
+        self.challenge_progress.entry((user_id.to_string(), date.to_string())).or_insert_with(|| ChallengeProgress {
+            id: None,
+            user_id: user_id.to_string(),
+            date: date.to_string(),
+            progress: HashMap::new(),
+            claimed: Vec::new(),
+            updated_at: now(),
+        })
+    }
+
+    // Wallet and inventory changes, kept apart from the DataStore methods so
+    // send_gift can make several under one lock
+    fn credit(&mut self, user_id: &str, amount: i64, reason: &str, reference: &str) -> Option<i64> {
+        if self.wallet_transactions.iter().any(|t| t.reference == reference) {
+            return None;
+        }
+        self.wallet_transactions.push(WalletTransaction {
+            id: None,
+            transaction_id: Snowflake::generate(),
+            user_id: user_id.to_string(),
+            amount,
+            reason: reason.to_string(),
+            reference: reference.to_string(),
+            created_at: now(),
+        });
+        let balance = self.wallets.entry(user_id.to_string()).or_insert(0);
+        *balance += amount;
+        Some(*balance)
+    }
+
+    fn debit(&mut self, user_id: &str, amount: i64, reason: &str, reference: &str, daily_limit: Option<i64>) -> WalletDebit {
+        if self.wallet_transactions.iter().any(|t| t.reference == reference) {
+            return WalletDebit::Debited(self.wallets.get(user_id).copied().unwrap_or(0));
+        }
+        if let Some(daily_limit) = daily_limit {
+            let day_start = chrono::Utc::now().date_naive().and_hms_opt(0, 0, 0).unwrap_or_default().and_utc().timestamp_millis();
+            let spent_today: i64 = self.wallet_transactions.iter()
+                .filter(|t| t.user_id == user_id && t.amount < 0 && t.created_at.timestamp_millis() >= day_start)
+                .map(|t| -t.amount)
+                .sum();
+            if spent_today + amount > daily_limit {
+                return WalletDebit::SpendLimitReached { spent_today, daily_limit };
+            }
+        }
+        let Some(balance) = self.wallets.get_mut(user_id).filter(|balance| **balance >= amount) else {
+            return WalletDebit::InsufficientBalance;
+        };
+        *balance -= amount;
+        let balance = *balance;
+        self.wallet_transactions.push(WalletTransaction {
+            id: None,
+            transaction_id: Snowflake::generate(),
+            user_id: user_id.to_string(),
+            amount: -amount,
+            reason: reason.to_string(),
+            reference: reference.to_string(),
+            created_at: now(),
+        });
+        WalletDebit::Debited(balance)
+    }
+
+    fn grant_item(&mut self, user_id: &str, item_id: &str, quantity: i64, reason: &str, reference: &str) -> Option<i64> {
+        if self.inventory_transactions.iter().any(|t| t.reference == reference) {
+            return None;
+        }
+        self.inventory_transactions.push(InventoryTransaction {
+            id: None,
+            transaction_id: Snowflake::generate(),
+            user_id: user_id.to_string(),
+            item_id: item_id.to_string(),
+            quantity,
+            reason: reason.to_string(),
+            reference: reference.to_string(),
+            created_at: now(),
+        });
+        let held = self.inventory.entry((user_id.to_string(), item_id.to_string())).or_insert(0);
+        *held += quantity;
+        Some(*held)
+    }
+
+    fn take_item(&mut self, user_id: &str, item_id: &str, quantity: i64, reason: &str, reference: &str) -> Option<i64> {
+        let key = (user_id.to_string(), item_id.to_string());
+        let held = self.inventory.get_mut(&key).filter(|held| **held >= quantity)?;
+        *held -= quantity;
+        let held = *held;
+        self.inventory_transactions.push(InventoryTransaction {
+            id: None,
+            transaction_id: Snowflake::generate(),
+            user_id: user_id.to_string(),
+            item_id: item_id.to_string(),
+            quantity: -quantity,
+            reason: reason.to_string(),
+            reference: reference.to_string(),
+            created_at: now(),
+        });
+        Some(held)
+    }
+
+    fn user_mut(&mut self, mobile_no: &str) -> Option<&mut UserRegister> {
+        self.users.iter_mut().find(|u| u.mobile_no == mobile_no)
+    }
+
+    // Documents of a collection as MongoDB would return them
+    fn documents(&self, collection: &str) -> Vec<bson::Document> {
+        fn all<T: Serialize>(rows: &[T]) -> Vec<bson::Document> {
+            rows.iter().filter_map(|row| bson::to_document(row).ok()).collect()
+        }
+        match collection {
+            "match_results" => all(&self.match_results),
+            "wallet_transactions" => all(&self.wallet_transactions),
+            "inventory_transactions" => all(&self.inventory_transactions),
+            "gifts" => all(&self.gifts),
+            "promo_redemptions" => all(&self.promo_redemptions),
+            "user_sanctions" => all(&self.user_sanctions),
+            // Event collections hold extended JSON, which bson reads back with its dates
+            _ => self.events.get(collection).map(|events| events.iter()
+                .filter_map(|event| bson::Bson::try_from(event.clone()).ok())
+                .filter_map(|event| event.as_document().cloned())
+                .collect()).unwrap_or_default(),
+        }
+    }
+
+    // Stored connection errors with from <= timestamp < to
+    fn connection_errors(&self, from: chrono::DateTime<chrono::Utc>, to: chrono::DateTime<chrono::Utc>) -> Vec<ConnectionErrorEvent> {
+        let (from, to) = (from.timestamp_millis(), to.timestamp_millis());
+        self.events.get("connection_error_events")
+            .map(|events| events.iter()
+                .filter_map(|e| serde_json::from_value::<ConnectionErrorEvent>(e.clone()).ok())
+                .filter(|e| (from..to).contains(&e.timestamp.timestamp_millis()))
+                .collect())
+            .unwrap_or_default()
+    }
+}
+
+// DataStore kept entirely in process. Behaves like DataService (expiry,
+// attempt limits, session renewal, device tracking) without needing MongoDB,
+// so handlers can be exercised in tests or run locally without a database.
+#[derive(Default)]
+pub struct InMemoryDataStore {
+    tables: Mutex<HashMap<String, Tables>>,     // By tenant id
+}
+
+impl InMemoryDataStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Tables of the tenant in scope
+    async fn tables(&self) -> MappedMutexGuard<'_, Tables> {
+        let tenant_id = TenantManager::current().tenant_id.as_str();
+        MutexGuard::map(self.tables.lock().await, |tables| tables.entry(tenant_id.to_string()).or_default())
+    }
+}
+
+#[async_trait]
+impl DataStore for InMemoryDataStore {
+    async fn store_connect_event(&self, socket_id: &str, token: i32, message: &str, status: &str, ip_address: Option<&str>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let event = ConnectEvent::new(socket_id.to_string(), token, message.to_string(), status.to_string(), ip_address.map(str::to_string));
+        self.tables().await.record_event(event)
+    }
+
+    async fn store_device_info_event(&self, socket_id: &str, device_info: &serde_json::Value) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let event = DeviceInfoEvent::new(socket_id.to_string(), device_info.clone());
+        self.tables().await.record_event(event)
+    }
+
+    async fn store_login_event(&self, socket_id: &str, mobile_no: &str, device_id: &str, fcm_token: &str, email: Option<&str>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut event = LoginEvent::new(socket_id.to_string(), mobile_no.to_string(), device_id.to_string(), fcm_token.to_string());
+        event.email = email.map(|e| e.to_string());
+        self.tables().await.record_event(event)
+    }
+
+    async fn store_login_success_event(&self, socket_id: &str, mobile_no: &str, device_id: &str, session_token: &str, otp: i32, otp_expiry_secs: i64, is_new_user: bool) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut event = LoginSuccessEvent::new(socket_id.to_string(), mobile_no.to_string(), device_id.to_string(), session_token.to_string(), otp, otp_expiry_secs);
+        event.is_new_user = is_new_user;
+        let mut tables = self.tables().await;
+        tables.record_event(event.clone())?;
+        tables.login_sessions.push(event);
+        Ok(())
+    }
+
+    async fn store_otp_delivery_request(&self, request: OtpDeliveryRequest) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.tables().await.record("otp_delivery_queue", request)
+    }
+
+    async fn store_test_otp_audit_event(&self, socket_id: &str, mobile_no: &str, device_id: Option<&str>, action: &str, is_success: bool) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let event = TestOtpAuditEvent {
+            id: None,
+            request_id: Correlation::current(),
+            socket_id: socket_id.to_string(),
+            mobile_no: mobile_no.to_string(),
+            device_id: device_id.map(|d| d.to_string()),
+            action: action.to_string(),
+            is_success,
+            timestamp: now(),
+        };
+        self.tables().await.record_event(event)
+    }
+
+    async fn list_user_devices(&self, mobile_no: &str) -> Result<Vec<UserDevice>, Box<dyn std::error::Error + Send + Sync>> {
+        let tables = self.tables().await;
+        let mut devices: Vec<UserDevice> = tables.devices.iter()
+            .filter(|d| d.mobile_no == mobile_no && d.removed_at.is_none())
+            .cloned()
+            .collect();
+        devices.sort_by_key(|d| std::cmp::Reverse(d.last_seen_at));
+        Ok(devices)
+    }
+
+    async fn remove_user_device(&self, mobile_no: &str, device_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        let Some(device) = tables.devices.iter_mut().find(|d| d.mobile_no == mobile_no && d.device_id == device_id && d.removed_at.is_none()) else {
+            return Ok(false);
+        };
+        device.removed_at = Some(now());
+        for session in tables.sessions.iter_mut().filter(|s| s.mobile_no == mobile_no && s.device_id == device_id) {
+            session.revoked = true;
+        }
+        Ok(true)
+    }
+
+    async fn update_two_step(&self, mobile_no: &str, two_step: Option<&TwoStepFactor>) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        let Some(user) = tables.users.iter_mut().find(|u| u.mobile_no == mobile_no) else {
+            return Ok(false);
+        };
+        user.two_step = two_step.cloned();
+        user.updated_at = now();
+        Ok(true)
+    }
+
+    async fn set_date_of_birth(&self, mobile_no: &str, date_of_birth: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        let Some(user) = tables.users.iter_mut().find(|u| u.mobile_no == mobile_no && u.date_of_birth.is_none()) else {
+            return Ok(false);
+        };
+        user.date_of_birth = Some(date_of_birth.to_string());
+        user.updated_at = now();
+        Ok(true)
+    }
+
+    async fn update_parental_controls(&self, mobile_no: &str, controls: Option<&ParentalControls>) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        let Some(user) = tables.users.iter_mut().find(|u| u.mobile_no == mobile_no) else {
+            return Ok(false);
+        };
+        user.parental_controls = controls.cloned();
+        user.updated_at = now();
+        Ok(true)
+    }
+
+    async fn store_two_step_reset(&self, reset: TwoStepReset) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.tables().await.record("two_step_resets", reset)
+    }
+
+    async fn store_mobile_change(&self, change: MobileChange) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.tables().await.mobile_changes.push(change);
+        Ok(())
+    }
+
+    async fn get_mobile_change(&self, user_id: &str, change_id: &str) -> Result<Option<MobileChange>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.tables().await.mobile_changes.iter().find(|c| c.user_id == user_id && c.change_id == change_id).cloned())
+    }
+
+    async fn record_mobile_change_attempt(&self, change_id: &str) -> Result<u32, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        let change = tables.mobile_changes.iter_mut().find(|c| c.change_id == change_id).ok_or("mobile change not found")?;
+        change.attempts += 1;
+        Ok(change.attempts)
+    }
+
+    // Everything happens under the one tables lock, so it is as atomic as the MongoDB transaction
+    async fn change_user_mobile(&self, audit: MobileChangeAudit) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        if tables.users.iter().any(|u| u.mobile_no == audit.new_mobile_no) {
+            return Ok(false);
+        }
+        let current = now();
+        let Some(user) = tables.users.iter_mut().find(|u| u.user_id == audit.user_id && u.mobile_no == audit.old_mobile_no) else {
+            return Ok(false);
+        };
+        user.mobile_no = audit.new_mobile_no.clone();
+        user.updated_at = current;
+        for device in tables.devices.iter_mut().filter(|d| d.mobile_no == audit.old_mobile_no) {
+            device.mobile_no = audit.new_mobile_no.clone();
+        }
+        for session in tables.sessions.iter_mut().filter(|s| s.mobile_no == audit.old_mobile_no) {
+            session.revoked = true;
+        }
+        if let Some(change) = tables.mobile_changes.iter_mut().find(|c| Some(&c.change_id) == audit.change_id.as_ref()) {
+            change.completed_at = Some(current);
+        }
+        tables.record("mobile_change_audit", audit)?;
+        Ok(true)
+    }
+
+    async fn find_reusable_login_session(&self, socket_id: &str, mobile_no: &str, device_id: &str, session_token: Option<&str>) -> Result<Option<LoginSuccessEvent>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        let current = now();
+        let session = tables.login_sessions.iter_mut()
+            .filter(|s| s.mobile_no == mobile_no && s.device_id == device_id && s.verified_at.is_none() && s.expires_at > current)
+            .max_by_key(|s| s.timestamp)
+            .filter(|s| s.socket_id == socket_id || session_token.is_some_and(|token| TokenGenerator::constant_time_eq(&s.session_token, token)));
+        Ok(session.map(|s| {
+            s.socket_id = socket_id.to_string();
+            s.clone()
+        }))
+    }
+
+    async fn store_otp_verification_event(
+        &self,
+        socket_id: &str,
+        mobile_no: &str,
+        session_token: &str,
+        otp: &str,
+        is_success: bool,
+        user_id: Option<&str>,
+        user_number: Option<u64>,
+        jwt_token: Option<&str>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut event = OtpVerificationEvent::new(
+            socket_id.to_string(),
+            mobile_no.to_string(),
+            session_token.to_string(),
+            otp.to_string(),
+            is_success,
+            user_id.map(|id| id.to_string()),
+            user_number,
+        );
+        event.jwt_token = jwt_token.map(|token| token.to_string());
+        self.tables().await.record_event(event)
+    }
+
+    async fn store_user_registration_event(
+        &self,
+        socket_id: &str,
+        user_id: &str,
+        user_number: u64,
+        mobile_no: &str,
+        device_id: &str,
+        fcm_token: &str,
+        email: Option<&str>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let event = UserRegistrationEvent {
+            id: None,
+            request_id: Correlation::current(),
+            socket_id: socket_id.to_string(),
+            user_id: user_id.to_string(),
+            user_number,
+            mobile_no: mobile_no.to_string(),
+            device_id: device_id.to_string(),
+            fcm_token: fcm_token.to_string(),
+            email: email.map(|e| e.to_string()),
+            timestamp: now(),
+        };
+        self.tables().await.record_event(event)
+    }
+
+    async fn store_user_profile_event(
+        &self,
+        socket_id: &str,
+        user_id: &str,
+        user_number: u64,
+        mobile_no: &str,
+        full_name: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let event = UserProfileEvent {
+            id: None,
+            request_id: Correlation::current(),
+            socket_id: socket_id.to_string(),
+            user_id: user_id.to_string(),
+            user_number,
+            mobile_no: mobile_no.to_string(),
+            full_name: full_name.to_string(),
+            timestamp: now(),
+        };
+        self.tables().await.record_event(event)
+    }
+
+    async fn store_language_setting_event(
+        &self,
+        socket_id: &str,
+        user_id: &str,
+        user_number: u64,
+        mobile_no: &str,
+        language_code: &str,
+        language_name: &str,
+        region_code: Option<&str>,
+        timezone: Option<&str>,
+        user_preferences: &serde_json::Value,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let event = LanguageSettingEvent {
+            id: None,
+            request_id: Correlation::current(),
+            socket_id: socket_id.to_string(),
+            user_id: user_id.to_string(),
+            user_number,
+            mobile_no: mobile_no.to_string(),
+            language_code: language_code.to_string(),
+            language_name: language_name.to_string(),
+            region_code: region_code.map(|r| r.to_string()),
+            timezone: timezone.map(|t| t.to_string()),
+            user_preferences: user_preferences.clone(),
+            timestamp: now(),
+        };
+        self.tables().await.record_event(event)
+    }
+
+    async fn store_connection_error_event(
+        &self,
+        socket_id: &str,
+        error_code: &str,
+        error_type: &str,
+        field: &str,
+        message: &str,
+        payload: bson::Document,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let event = ConnectionErrorEvent::new(
+            socket_id.to_string(),
+            error_code.to_string(),
+            error_type.to_string(),
+            field.to_string(),
+            message.to_string(),
+            payload,
+        );
+        self.tables().await.record_event(event)
+    }
+
+    async fn connection_error_counts(&self, query: &ErrorStatsQuery) -> Result<Vec<ErrorCountBucket>, Box<dyn std::error::Error + Send + Sync>> {
+        let errors = self.tables().await.connection_errors(query.from, query.to);
+        let mut counts: HashMap<(i64, String), u64> = HashMap::new();
+        for error in &errors {
+            let bucket = query.bucket_start(error.timestamp.timestamp_millis());
+            *counts.entry((bucket, query.group_by.key_of(error).to_string())).or_default() += 1;
+        }
+        let mut buckets: Vec<ErrorCountBucket> = counts.into_iter()
+            .map(|((bucket_start, key), count)| ErrorCountBucket { bucket_start, key, count })
+            .collect();
+        buckets.sort_by(|a, b| a.bucket_start.cmp(&b.bucket_start).then(b.count.cmp(&a.count)).then(a.key.cmp(&b.key)));
+        Ok(buckets)
+    }
+
+    async fn connection_error_offenders(
+        &self,
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+        limit: i64,
+    ) -> Result<ErrorOffenders, Box<dyn std::error::Error + Send + Sync>> {
+        let tables = self.tables().await;
+        let errors = tables.connection_errors(from, to);
+        let device_infos = tables.events.get("device_info_events");
+        let mut devices: HashMap<[Option<String>; 4], u64> = HashMap::new();
+        let mut app_versions: HashMap<Option<String>, u64> = HashMap::new();
+        for error in &errors {
+            // Latest device:info sent on the failing socket
+            let device_info = device_infos
+                .and_then(|events| events.iter().rev().find(|e| e["socket_id"] == error.socket_id.as_str()))
+                .map(|e| e["device_info"].clone())
+                .unwrap_or_default();
+            let field = |name: &str| device_info.get(name).and_then(|v| v.as_str()).map(|v| v.to_string());
+            *devices.entry([field("device_id"), field("manufacturer"), field("model"), field("app_version")]).or_default() += 1;
+            *app_versions.entry(field("app_version")).or_default() += 1;
+        }
+
+        let limit = limit.max(0) as usize;
+        let mut devices: Vec<ErrorOffender> = devices.into_iter()
+            .map(|([device_id, manufacturer, model, app_version], count)| ErrorOffender { device_id, manufacturer, model, app_version, count })
+            .collect();
+        devices.sort_by_key(|d| std::cmp::Reverse(d.count));
+        devices.truncate(limit);
+        let mut app_versions: Vec<AppVersionErrors> = app_versions.into_iter()
+            .map(|(app_version, count)| AppVersionErrors { app_version, count })
+            .collect();
+        app_versions.sort_by_key(|v| std::cmp::Reverse(v.count));
+        app_versions.truncate(limit);
+        Ok(ErrorOffenders { devices, app_versions })
+    }
+
+    async fn store_turn_timing_event(&self, event: TurnTimingEvent) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.tables().await.record_event(event)
+    }
+
+    async fn store_match_record(&self, record: MatchRecord) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.tables().await.record("match_history", record)
+    }
+
+    async fn save_room_snapshot(&self, snapshot: RoomSnapshot) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        let newer_saved = tables.room_snapshots.get(&snapshot.room_id).is_some_and(|saved| saved.saved_at > snapshot.saved_at);
+        if !newer_saved {
+            tables.room_snapshots.insert(snapshot.room_id.clone(), snapshot);
+        }
+        Ok(())
+    }
+
+    async fn load_room_snapshots(&self, since: chrono::DateTime<chrono::Utc>) -> Result<Vec<RoomSnapshot>, Box<dyn std::error::Error + Send + Sync>> {
+        let tables = self.tables().await;
+        let mut snapshots: Vec<RoomSnapshot> = tables.room_snapshots.values()
+            .filter(|s| s.saved_at.timestamp_millis() >= since.timestamp_millis())
+            .cloned()
+            .collect();
+        snapshots.sort_by_key(|s| s.saved_at);
+        Ok(snapshots)
+    }
+
+    async fn archive_room(&self, room: ArchivedRoom) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        tables.room_snapshots.remove(&room.room_id);
+        tables.record("archived_rooms", room)
+    }
+
+    async fn create_escrow(&self, escrow: MatchEscrow) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.tables().await.escrows.push(escrow);
+        Ok(())
+    }
+
+    async fn get_held_escrow(&self, room_id: &str) -> Result<Option<MatchEscrow>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.tables().await.escrows.iter().find(|e| e.room_id == room_id && e.status == EscrowStatus::Held).cloned())
+    }
+
+    async fn report_escrow_result(&self, escrow_id: &str, player_id: &str, outcome: GameOutcome) -> Result<Option<MatchEscrow>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        let escrow = tables.escrows.iter_mut().find(|e| {
+            e.escrow_id == escrow_id && e.status == EscrowStatus::Held && e.stakes.iter().any(|s| s.player_id == player_id)
+        });
+        Ok(escrow.map(|escrow| {
+            escrow.reports.insert(player_id.to_string(), outcome);
+            escrow.clone()
+        }))
+    }
+
+    async fn begin_escrow_settlement(&self, escrow_id: &str, status: EscrowStatus, payouts: Vec<EscrowPayout>, rake: i64) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        let Some(escrow) = tables.escrows.iter_mut().find(|e| e.escrow_id == escrow_id && e.status == EscrowStatus::Held) else {
+            return Ok(false);
+        };
+        escrow.status = status;
+        escrow.payouts = payouts;
+        escrow.rake = rake;
+        escrow.settling_at = Some(bson::DateTime::now());
+        Ok(true)
+    }
+
+    async fn finish_escrow_opening(&self, escrow_id: &str, status: EscrowStatus, stakes: Vec<EscrowStake>, payouts: Vec<EscrowPayout>) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        let Some(escrow) = tables.escrows.iter_mut().find(|e| e.escrow_id == escrow_id && e.status == EscrowStatus::Opening) else {
+            return Ok(false);
+        };
+        escrow.status = status;
+        escrow.stakes = stakes;
+        escrow.payouts = payouts;
+        if status == EscrowStatus::Refunding {
+            escrow.settling_at = Some(bson::DateTime::now());
+        }
+        Ok(true)
+    }
+
+    async fn stale_escrow_openings(&self, before: chrono::DateTime<chrono::Utc>) -> Result<Vec<MatchEscrow>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.tables().await.escrows.iter()
+            .filter(|e| e.status == EscrowStatus::Opening && e.created_at.timestamp_millis() < before.timestamp_millis())
+            .cloned()
+            .collect())
+    }
+
+    async fn finish_escrow_settlement(&self, escrow_id: &str, status: EscrowStatus) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if let Some(escrow) = self.tables().await.escrows.iter_mut().find(|e| e.escrow_id == escrow_id) {
+            escrow.status = status;
+            escrow.settled_at = Some(bson::DateTime::now());
+        }
+        Ok(())
+    }
+
+    async fn unfinished_escrow_settlements(&self, before: chrono::DateTime<chrono::Utc>) -> Result<Vec<MatchEscrow>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.tables().await.escrows.iter()
+            .filter(|e| matches!(e.status, EscrowStatus::Releasing | EscrowStatus::Refunding))
+            .filter(|e| e.settling_at.is_some_and(|at| at.timestamp_millis() < before.timestamp_millis()))
+            .cloned()
+            .collect())
+    }
+
+    async fn record_revenue(&self, entry: RevenueEntry) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        if tables.revenue_ledger.iter().any(|e| e.escrow_id == entry.escrow_id) {
+            return Ok(false);
+        }
+        tables.revenue_ledger.push(entry);
+        Ok(true)
+    }
+
+    async fn revenue_entries(&self, day: &str, game_type: Option<&str>, page: u64, page_size: i64) -> Result<Vec<RevenueEntry>, Box<dyn std::error::Error + Send + Sync>> {
+        let tables = self.tables().await;
+        Ok(tables.revenue_ledger.iter()
+            .filter(|e| e.day == day && game_type.is_none_or(|game_type| e.game_type == game_type))
+            .skip((page * page_size.max(0) as u64) as usize)
+            .take(page_size.max(0) as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn revenue_day_totals(&self, day: &str) -> Result<Vec<RevenueRollup>, Box<dyn std::error::Error + Send + Sync>> {
+        let tables = self.tables().await;
+        let mut totals: std::collections::BTreeMap<&str, RevenueRollup> = std::collections::BTreeMap::new();
+        for entry in tables.revenue_ledger.iter().filter(|e| e.day == day) {
+            let rollup = totals.entry(&entry.game_type).or_insert_with(|| RevenueRollup {
+                id: None,
+                day: day.to_string(),
+                game_type: entry.game_type.clone(),
+                matches: 0,
+                pot: 0,
+                rake: 0,
+                paid_out: 0,
+                computed_at: bson::DateTime::now(),
+            });
+            rollup.matches += 1;
+            rollup.pot += entry.pot;
+            rollup.rake += entry.rake;
+            rollup.paid_out += entry.paid_out;
+        }
+        Ok(totals.into_values().collect())
+    }
+
+    async fn save_revenue_rollups(&self, day: &str, rollups: Vec<RevenueRollup>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        tables.revenue_rollups.retain(|r| r.day != day);
+        tables.revenue_rollups.extend(rollups);
+        Ok(())
+    }
+
+    async fn revenue_rollups(&self, from: &str, to: &str) -> Result<Vec<RevenueRollup>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut rollups: Vec<RevenueRollup> = self.tables().await.revenue_rollups.iter()
+            .filter(|r| (from..=to).contains(&r.day.as_str()))
+            .cloned()
+            .collect();
+        rollups.sort_by(|a, b| a.day.cmp(&b.day).then_with(|| a.game_type.cmp(&b.game_type)));
+        Ok(rollups)
+    }
+
+    async fn create_promo_code(&self, promo: PromoCode) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        if tables.promo_codes.iter().any(|p| p.code == promo.code) {
+            return Ok(false);
+        }
+        tables.promo_codes.push(promo);
+        Ok(true)
+    }
+
+    async fn list_promo_codes(&self) -> Result<Vec<PromoCode>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.tables().await.promo_codes.iter().rev().cloned().collect())
+    }
+
+    async fn set_promo_code_active(&self, code: &str, active: bool) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        let Some(promo) = tables.promo_codes.iter_mut().find(|p| p.code == code) else {
+            return Ok(false);
+        };
+        promo.active = active;
+        promo.updated_at = now();
+        Ok(true)
+    }
+
+    async fn redeem_promo_code(&self, mut redemption: PromoRedemption) -> Result<PromoRedeem, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        let Some(promo) = tables.promo_codes.iter().find(|p| p.code == redemption.code).cloned() else {
+            return Ok(PromoRedeem::NotFound);
+        };
+        if let Some(refusal) = promo.unavailable(now()) {
+            return Ok(refusal);
+        }
+        let redeemed = tables.promo_redemptions.iter().filter(|r| r.code == promo.code && r.user_id == redemption.user_id).count();
+        if redeemed >= promo.per_user_limit as usize {
+            return Ok(PromoRedeem::UserLimitReached { limit: promo.per_user_limit });
+        }
+        if let Some(stored) = tables.promo_codes.iter_mut().find(|p| p.code == promo.code) {
+            stored.uses += 1;
+            stored.updated_at = now();
+        }
+
+        let user_id = redemption.user_id.clone();
+        let reference = format!("promo:{}", redemption.redemption_id);
+        if promo.reward.coins > 0 {
+            tables.wallet_transactions.push(WalletTransaction {
+                id: None,
+                transaction_id: Snowflake::generate(),
+                user_id: user_id.clone(),
+                amount: promo.reward.coins,
+                reason: "promo_code".to_string(),
+                reference: reference.clone(),
+                created_at: now(),
+            });
+            *tables.wallets.entry(user_id.clone()).or_insert(0) += promo.reward.coins;
+        }
+        let mut held = Vec::new();
+        for item in &promo.reward.items {
+            tables.inventory_transactions.push(InventoryTransaction {
+                id: None,
+                transaction_id: Snowflake::generate(),
+                user_id: user_id.clone(),
+                item_id: item.item_id.clone(),
+                quantity: item.quantity,
+                reason: "promo_code".to_string(),
+                reference: format!("{}:{}", reference, item.item_id),
+                created_at: now(),
+            });
+            let quantity = tables.inventory.entry((user_id.clone(), item.item_id.clone())).or_insert(0);
+            *quantity += item.quantity;
+            held.push((item.item_id.clone(), *quantity));
+        }
+        redemption.reward = promo.reward.clone();
+        for message in Outbox::promo_redeemed(&redemption) {
+            tables.record("outbox", message)?;
+        }
+        tables.promo_redemptions.push(redemption);
+        let balance = tables.wallets.get(&user_id).copied().unwrap_or(0);
+        Ok(PromoRedeem::Redeemed { reward: promo.reward, balance, held })
+    }
+
+    async fn list_promo_redemptions(&self, code: &str, page: u64, page_size: i64) -> Result<Vec<PromoRedemption>, Box<dyn std::error::Error + Send + Sync>> {
+        let tables = self.tables().await;
+        Ok(tables.promo_redemptions.iter().rev()
+            .filter(|r| r.code == code)
+            .skip((page * page_size.max(0) as u64) as usize)
+            .take(page_size.max(0) as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn start_impersonation(&self, session: ImpersonationSession) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.tables().await.impersonation_sessions.push(session);
+        Ok(())
+    }
+
+    async fn end_impersonation(&self, session_id: &str, end_reason: &str, events_mirrored: u64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        if let Some(session) = tables.impersonation_sessions.iter_mut().find(|s| s.session_id == session_id && s.ended_at.is_none()) {
+            session.ended_at = Some(now());
+            session.end_reason = Some(end_reason.to_string());
+            session.events_mirrored = events_mirrored;
+        }
+        Ok(())
+    }
+
+    async fn list_impersonations(&self, user_id: Option<&str>, operator_id: Option<&str>, page: u64, page_size: i64) -> Result<Vec<ImpersonationSession>, Box<dyn std::error::Error + Send + Sync>> {
+        let tables = self.tables().await;
+        Ok(tables.impersonation_sessions.iter().rev()
+            .filter(|s| user_id.is_none_or(|user_id| s.user_id == user_id))
+            .filter(|s| operator_id.is_none_or(|operator_id| s.operator_id == operator_id))
+            .skip((page * page_size.max(0) as u64) as usize)
+            .take(page_size.max(0) as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn user_timeline(&self, user: &UserRegister, sources: &[&'static TimelineSource], before: Option<bson::DateTime>, limit: i64) -> Result<Vec<TimelineEntry>, Box<dyn std::error::Error + Send + Sync>> {
+        let tables = self.tables().await;
+        let mut mobile_nos = vec![user.mobile_no.clone()];
+        for change in tables.documents("mobile_change_audit").iter().filter(|c| c.get_str("user_id") == Ok(user.user_id.as_str())) {
+            for mobile_no in [change.get_str("old_mobile_no"), change.get_str("new_mobile_no")].into_iter().flatten() {
+                if !mobile_nos.iter().any(|known| known == mobile_no) {
+                    mobile_nos.push(mobile_no.to_string());
+                }
+            }
+        }
+        let mut socket_ids: Vec<String> = tables.login_sessions.iter()
+            .filter(|login| mobile_nos.contains(&login.mobile_no))
+            .map(|login| login.socket_id.clone())
+            .collect();
+        socket_ids.sort();
+        socket_ids.dedup();
+        let subject = TimelineSubject { user_id: user.user_id.clone(), mobile_nos, socket_ids };
+
+        let entries = sources.iter()
+            .flat_map(|source| tables.documents(source.collection).into_iter()
+                .filter(|document| source.matches(&subject, document, before))
+                .filter_map(|document| source.entry(document)))
+            .collect();
+        Ok(Timeline::merge(entries, limit))
+    }
+
+    async fn record_session_sample(&self, sample: SessionSample) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        tables.session_samples.retain(|s| s.node_id != sample.node_id || s.minute != sample.minute);
+        tables.session_samples.push(sample);
+        Ok(())
+    }
+
+    async fn session_samples(&self, from: bson::DateTime, to: bson::DateTime) -> Result<Vec<SessionSample>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut samples: Vec<SessionSample> = self.tables().await.session_samples.iter()
+            .filter(|s| s.minute >= from && s.minute < to)
+            .cloned()
+            .collect();
+        samples.sort_by_key(|s| (s.minute, s.node_id));
+        Ok(samples)
+    }
+
+    async fn record_compliance_audit(&self, audit: ComplianceAudit) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.tables().await.record("compliance_audit", audit)
+    }
+
+    async fn record_afk_event(&self, event: AfkEvent) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.tables().await.record("afk_events", event)
+    }
+
+    async fn afk_events_since(&self, user_id: &str, since: bson::DateTime) -> Result<Vec<AfkEvent>, Box<dyn std::error::Error + Send + Sync>> {
+        let tables = self.tables().await;
+        let mut events: Vec<AfkEvent> = tables.events.get("afk_events")
+            .map(|events| events.iter()
+                .filter_map(|e| serde_json::from_value::<AfkEvent>(e.clone()).ok())
+                .filter(|e| e.user_id == user_id && e.created_at >= since)
+                .collect())
+            .unwrap_or_default();
+        events.sort_by_key(|e| std::cmp::Reverse(e.created_at));
+        Ok(events)
+    }
+
+    async fn save_dealer_audit(&self, audit: DealerAudit) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.tables().await.dealer_audits.push(audit);
+        Ok(())
+    }
+
+    async fn retention_cohorts(&self, since: chrono::DateTime<chrono::Utc>, now: chrono::DateTime<chrono::Utc>, days: &[u32]) -> Result<Vec<RetentionCohort>, Box<dyn std::error::Error + Send + Sync>> {
+        let (since, now) = (since.timestamp_millis(), now.timestamp_millis());
+        let tables = self.tables().await;
+        // Week start -> users, then (eligible, returned) per entry of `days`
+        let mut weeks: std::collections::BTreeMap<i64, (u64, Vec<(u64, u64)>)> = std::collections::BTreeMap::new();
+        for user in tables.users.iter().filter(|u| (since..now).contains(&u.created_at.timestamp_millis())) {
+            let created = user.created_at.timestamp_millis();
+            let returned_days: Vec<i64> = tables.login_sessions.iter()
+                .filter(|login| login.mobile_no == user.mobile_no && login.verified_at.is_some())
+                .map(|login| (login.timestamp.timestamp_millis() - created).div_euclid(DAY_MS))
+                .collect();
+            let (users, counts) = weeks.entry(week_start_ms(created)).or_insert_with(|| (0, vec![(0, 0); days.len()]));
+            *users += 1;
+            for (&day, (eligible, returned)) in days.iter().zip(counts.iter_mut()) {
+                if created + (day as i64 + 1) * DAY_MS <= now {
+                    *eligible += 1;
+                    if returned_days.contains(&(day as i64)) {
+                        *returned += 1;
+                    }
+                }
+            }
+        }
+        Ok(weeks.into_iter().map(|(week_start, (users, counts))| RetentionCohort {
+            week_start: RetentionCohort::week_label(week_start),
+            users,
+            days: days.iter().zip(counts).map(|(&day, (eligible, returned))| RetentionDay::new(day, eligible, returned)).collect(),
+        }).collect())
+    }
+
+    async fn save_retention_report(&self, report: RetentionReport) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        tables.retention_reports.retain(|r| r.report_date != report.report_date);
+        tables.retention_reports.push(report);
+        Ok(())
+    }
+
+    async fn latest_retention_report(&self) -> Result<Option<RetentionReport>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.tables().await.retention_reports.iter().max_by(|a, b| a.report_date.cmp(&b.report_date)).cloned())
+    }
+
+    async fn get_gameplay_progress(&self, user_id: &str) -> Result<Option<GameplayProgress>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.tables().await.gameplay_progress.get(user_id).cloned())
+    }
+
+    async fn update_gameplay_progress(&self, user_id: &str, update: &ProgressUpdate) -> Result<GameplayProgress, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        let progress = tables.gameplay_progress.entry(user_id.to_string())
+            .or_insert_with(|| GameplayProgress::new(user_id.to_string()));
+        progress.apply(update, now());
+        Ok(progress.clone())
+    }
+
+    async fn get_daily_challenges(&self, date: &str) -> Result<Option<DailyChallengeSet>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.tables().await.daily_challenges.get(date).cloned())
+    }
+
+    async fn create_daily_challenges(&self, set: DailyChallengeSet) -> Result<DailyChallengeSet, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        Ok(tables.daily_challenges.entry(set.date.clone()).or_insert(set).clone())
+    }
+
+    async fn get_challenge_progress(&self, user_id: &str, date: &str) -> Result<Option<ChallengeProgress>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.tables().await.challenge_progress.get(&(user_id.to_string(), date.to_string())).cloned())
+    }
+
+    async fn add_challenge_progress(&self, user_id: &str, date: &str, increments: &HashMap<String, i64>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        let progress = tables.challenge_progress_mut(user_id, date);
+        for (challenge_id, amount) in increments {
+            *progress.progress.entry(challenge_id.clone()).or_default() += amount;
+        }
+        progress.updated_at = now();
+        Ok(())
+    }
+
+    async fn mark_challenge_claimed(&self, user_id: &str, date: &str, challenge_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        let progress = tables.challenge_progress_mut(user_id, date);
+        if !progress.claimed.iter().any(|c| c == challenge_id) {
+            progress.claimed.push(challenge_id.to_string());
+        }
+        progress.updated_at = now();
+        Ok(())
+    }
+
+    async fn get_wallet_balance(&self, user_id: &str) -> Result<i64, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.tables().await.wallets.get(user_id).copied().unwrap_or(0))
+    }
+
+    async fn credit_wallet(&self, user_id: &str, amount: i64, reason: &str, reference: &str) -> Result<Option<i64>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.tables().await.credit(user_id, amount, reason, reference))
+    }
+
+    async fn create_season(&self, season: Season) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.tables().await.seasons.push(season);
+        Ok(())
+    }
+
+    async fn list_seasons(&self) -> Result<Vec<Season>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut seasons = self.tables().await.seasons.clone();
+        seasons.sort_by_key(|s| std::cmp::Reverse(s.start_at));
+        Ok(seasons)
+    }
+
+    async fn get_season(&self, season_id: &str) -> Result<Option<Season>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.tables().await.seasons.iter().find(|s| s.season_id == season_id).cloned())
+    }
+
+    async fn seasons_with_status(&self, status: SeasonStatus) -> Result<Vec<Season>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut seasons: Vec<Season> = self.tables().await.seasons.iter().filter(|s| s.status == status).cloned().collect();
+        seasons.sort_by_key(|s| s.start_at);
+        Ok(seasons)
+    }
+
+    async fn transition_season(&self, season_id: &str, from: SeasonStatus, to: SeasonStatus) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        let Some(season) = tables.seasons.iter_mut().find(|s| s.season_id == season_id && s.status == from) else {
+            return Ok(false);
+        };
+        season.status = to;
+        if to == SeasonStatus::Ended {
+            season.ended_at = Some(now());
+        }
+        Ok(true)
+    }
+
+    async fn record_season_result(&self, season_id: &str, user_id: &str, outcome: GameOutcome, rating_change: i64, base_rating: i64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        let index = match tables.season_ratings.iter().position(|r| r.season_id == season_id && r.user_id == user_id) {
+            Some(index) => index,
+            None => {
+                tables.season_ratings.push(SeasonRating {
+                    id: None,
+                    season_id: season_id.to_string(),
+                    user_id: user_id.to_string(),
+                    rating: base_rating,
+                    wins: 0,
+                    losses: 0,
+                    draws: 0,
+                    updated_at: now(),
+                });
+                tables.season_ratings.len() - 1
+            }
+        };
+        let rating = &mut tables.season_ratings[index];
+        rating.rating = (rating.rating + rating_change).max(0);
+        match outcome {
+            GameOutcome::Win => rating.wins += 1,
+            GameOutcome::Loss => rating.losses += 1,
+            GameOutcome::Draw => rating.draws += 1,
+        }
+        rating.updated_at = now();
+        Ok(())
+    }
+
+    async fn season_leaderboard(&self, season_id: &str, offset: u64, limit: i64) -> Result<Vec<SeasonRating>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut ratings: Vec<SeasonRating> = self.tables().await.season_ratings.iter().filter(|r| r.season_id == season_id).cloned().collect();
+        ratings.sort_by_key(|r| (std::cmp::Reverse(r.rating), r.updated_at));
+        Ok(ratings.into_iter().skip(offset as usize).take(limit.max(0) as usize).collect())
+    }
+
+    async fn get_season_rating(&self, season_id: &str, user_id: &str) -> Result<Option<SeasonRating>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.tables().await.season_ratings.iter().find(|r| r.season_id == season_id && r.user_id == user_id).cloned())
+    }
+
+    async fn season_rank(&self, rating: &SeasonRating) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        let ahead = self.tables().await.season_ratings.iter()
+            .filter(|r| r.season_id == rating.season_id)
+            .filter(|r| r.rating > rating.rating || (r.rating == rating.rating && r.updated_at < rating.updated_at))
+            .count();
+        Ok(ahead as u64 + 1)
+    }
+
+    async fn carry_over_season_ratings(&self, from: &str, to: &str, base_rating: i64, factor: f64) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        let carried: Vec<SeasonRating> = tables.season_ratings.iter()
+            .filter(|r| r.season_id == from)
+            .filter(|r| !tables.season_ratings.iter().any(|existing| existing.season_id == to && existing.user_id == r.user_id))
+            .map(|r| SeasonRating {
+                id: None,
+                season_id: to.to_string(),
+                user_id: r.user_id.clone(),
+                rating: (base_rating as f64 + (r.rating - base_rating) as f64 * factor).round() as i64,
+                wins: 0,
+                losses: 0,
+                draws: 0,
+                updated_at: now(),
+            })
+            .collect();
+        tables.season_ratings.extend(carried);
+        Ok(tables.season_ratings.iter().filter(|r| r.season_id == to).count() as u64)
+    }
+
+    async fn debit_wallet(&self, user_id: &str, amount: i64, reason: &str, reference: &str, daily_limit: Option<i64>) -> Result<WalletDebit, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.tables().await.debit(user_id, amount, reason, reference, daily_limit))
+    }
+
+    async fn wallet_reference_applied(&self, reference: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.tables().await.wallet_transactions.iter().any(|t| t.reference == reference))
+    }
+
+    async fn wallet_spent_since(&self, user_id: &str, since: chrono::DateTime<chrono::Utc>) -> Result<i64, Box<dyn std::error::Error + Send + Sync>> {
+        let since = since.timestamp_millis();
+        Ok(self.tables().await.wallet_transactions.iter()
+            .filter(|t| t.user_id == user_id && t.amount < 0 && t.created_at.timestamp_millis() >= since)
+            .map(|t| -t.amount)
+            .sum())
+    }
+
+    async fn get_inventory(&self, user_id: &str) -> Result<Vec<InventoryItem>, Box<dyn std::error::Error + Send + Sync>> {
+        let tables = self.tables().await;
+        let mut items: Vec<InventoryItem> = tables.inventory.iter()
+            .filter(|((owner, _), quantity)| owner == user_id && **quantity > 0)
+            .map(|((owner, item_id), quantity)| InventoryItem {
+                id: None,
+                user_id: owner.clone(),
+                item_id: item_id.clone(),
+                quantity: *quantity,
+                updated_at: now(),
+            })
+            .collect();
+        items.sort_by(|a, b| a.item_id.cmp(&b.item_id));
+        Ok(items)
+    }
+
+    async fn add_friend(&self, user_id: &str, friend_user_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        if tables.friendships.iter().any(|f| f.user_id == user_id && f.friend_user_id == friend_user_id) {
+            return Ok(false);
+        }
+        tables.friendships.push(Friendship {
+            id: None,
+            user_id: user_id.to_string(),
+            friend_user_id: friend_user_id.to_string(),
+            created_at: now(),
+        });
+        Ok(true)
+    }
+
+    async fn remove_friend(&self, user_id: &str, friend_user_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        let before = tables.friendships.len();
+        tables.friendships.retain(|f| !((f.user_id == user_id && f.friend_user_id == friend_user_id) || (f.user_id == friend_user_id && f.friend_user_id == user_id)));
+        Ok(tables.friendships.len() < before)
+    }
+
+    async fn are_friends(&self, user_id: &str, other_user_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let tables = self.tables().await;
+        let added = |from: &str, to: &str| tables.friendships.iter().any(|f| f.user_id == from && f.friend_user_id == to);
+        Ok(added(user_id, other_user_id) && added(other_user_id, user_id))
+    }
+
+    async fn list_friendships(&self, user_id: &str) -> Result<Vec<Friendship>, Box<dyn std::error::Error + Send + Sync>> {
+        let tables = self.tables().await;
+        Ok(tables.friendships.iter().filter(|f| f.user_id == user_id || f.friend_user_id == user_id).cloned().collect())
+    }
+
+    async fn send_gift(&self, gift: Gift, limits: GiftLimits, daily_limit: Option<i64>) -> Result<GiftSend, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        let day_start = bson::DateTime::from_millis(chrono::Utc::now().date_naive().and_hms_opt(0, 0, 0).unwrap_or_default().and_utc().timestamp_millis());
+        let sent_today: Vec<&Gift> = tables.gifts.iter().filter(|g| g.from_user_id == gift.from_user_id && g.created_at >= day_start).collect();
+        let coins_sent: i64 = sent_today.iter().filter_map(|g| g.coins).sum();
+        if sent_today.len() >= limits.gifts || coins_sent + gift.coins.unwrap_or(0) > limits.coins {
+            return Ok(GiftSend::DailyLimitReached { gifts_sent: sent_today.len(), coins_sent });
+        }
+
+        let sent = format!("gift:{}:sent", gift.gift_id);
+        let received = format!("gift:{}:received", gift.gift_id);
+        let left = match (&gift.item_id, gift.coins) {
+            (Some(item_id), _) => {
+                let Some(left) = tables.take_item(&gift.from_user_id, item_id, gift.quantity, "gift_sent", &sent) else {
+                    return Ok(GiftSend::InsufficientItems);
+                };
+                tables.grant_item(&gift.to_user_id, item_id, gift.quantity, "gift_received", &received);
+                left
+            }
+            (None, coins) => {
+                let coins = coins.unwrap_or(0);
+                let balance = match tables.debit(&gift.from_user_id, coins, "gift_sent", &sent, daily_limit) {
+                    WalletDebit::Debited(balance) => balance,
+                    WalletDebit::InsufficientBalance => return Ok(GiftSend::InsufficientBalance),
+                    WalletDebit::SpendLimitReached { spent_today, daily_limit } => return Ok(GiftSend::SpendLimitReached { spent_today, daily_limit }),
+                };
+                tables.credit(&gift.to_user_id, coins, "gift_received", &received);
+                balance
+            }
+        };
+        tables.gifts.push(gift);
+        Ok(GiftSend::Sent(left))
+    }
+
+    async fn gifts_since(&self, since: chrono::DateTime<chrono::Utc>) -> Result<Vec<Gift>, Box<dyn std::error::Error + Send + Sync>> {
+        let since = bson::DateTime::from_millis(since.timestamp_millis());
+        Ok(self.tables().await.gifts.iter().filter(|g| g.created_at >= since).cloned().collect())
+    }
+
+    async fn record_match_participant(&self, participant: MatchParticipant) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.tables().await.match_participants.push(participant);
+        Ok(())
+    }
+
+    async fn record_match_result(&self, result: MatchResult) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.tables().await.match_results.push(result);
+        Ok(())
+    }
+
+    async fn match_participants_since(&self, since: chrono::DateTime<chrono::Utc>) -> Result<Vec<MatchParticipant>, Box<dyn std::error::Error + Send + Sync>> {
+        let since = bson::DateTime::from_millis(since.timestamp_millis());
+        Ok(self.tables().await.match_participants.iter().filter(|p| p.joined_at >= since).cloned().collect())
+    }
+
+    async fn match_results_since(&self, since: chrono::DateTime<chrono::Utc>) -> Result<Vec<MatchResult>, Box<dyn std::error::Error + Send + Sync>> {
+        let since = bson::DateTime::from_millis(since.timestamp_millis());
+        Ok(self.tables().await.match_results.iter().filter(|r| r.reported_at >= since).cloned().collect())
+    }
+
+    async fn get_user_risk(&self, user_id: &str) -> Result<Option<UserRisk>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.tables().await.user_risk.get(user_id).cloned())
+    }
+
+    async fn save_user_risk(&self, risk: UserRisk) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.tables().await.user_risk.insert(risk.user_id.clone(), risk);
+        Ok(())
+    }
+
+    async fn clear_stale_user_risk(&self, before: bson::DateTime) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        let mut cleared = 0;
+        for risk in tables.user_risk.values_mut().filter(|r| r.updated_at < before && r.score > 0) {
+            risk.score = 0;
+            risk.signals.clear();
+            risk.limited = false;
+            risk.updated_at = now();
+            cleared += 1;
+        }
+        Ok(cleared)
+    }
+
+    async fn create_risk_flag(&self, flag: RiskFlag) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        if tables.risk_flags.iter().any(|f| f.flag_key == flag.flag_key && f.status == "open") {
+            return Ok(false);
+        }
+        tables.risk_flags.push(flag);
+        Ok(true)
+    }
+
+    async fn list_risk_flags(&self, status: Option<&str>, page: u64, page_size: i64) -> Result<Vec<RiskFlag>, Box<dyn std::error::Error + Send + Sync>> {
+        let tables = self.tables().await;
+        Ok(tables.risk_flags.iter().rev()
+            .filter(|f| status.is_none_or(|status| f.status == status))
+            .skip((page * page_size.max(0) as u64) as usize)
+            .take(page_size.max(0) as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn resolve_risk_flag(&self, flag_id: &str, status: &str, operator_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        let Some(flag) = tables.risk_flags.iter_mut().find(|f| f.flag_id == flag_id && f.status == "open") else {
+            return Ok(false);
+        };
+        flag.status = status.to_string();
+        flag.resolved_by = Some(operator_id.to_string());
+        flag.resolved_at = Some(now());
+        Ok(true)
+    }
+
+    async fn dismissed_risk_flag_keys(&self) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+        let tables = self.tables().await;
+        Ok(tables.risk_flags.iter().filter(|f| f.status == "dismissed").map(|f| f.flag_key.clone()).collect())
+    }
+
+    async fn block_user(&self, user_id: &str, blocked_user_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        if tables.user_blocks.iter().any(|b| b.user_id == user_id && b.blocked_user_id == blocked_user_id) {
+            return Ok(false);
+        }
+        tables.user_blocks.push(UserBlock {
+            id: None,
+            user_id: user_id.to_string(),
+            blocked_user_id: blocked_user_id.to_string(),
+            created_at: now(),
+        });
+        Ok(true)
+    }
+
+    async fn unblock_user(&self, user_id: &str, blocked_user_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        let before = tables.user_blocks.len();
+        tables.user_blocks.retain(|b| !(b.user_id == user_id && b.blocked_user_id == blocked_user_id));
+        Ok(tables.user_blocks.len() < before)
+    }
+
+    async fn list_blocks(&self, user_id: &str) -> Result<Vec<UserBlock>, Box<dyn std::error::Error + Send + Sync>> {
+        let tables = self.tables().await;
+        Ok(tables.user_blocks.iter().filter(|b| b.user_id == user_id || b.blocked_user_id == user_id).cloned().collect())
+    }
+
+    async fn create_user_report(&self, report: UserReport) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.tables().await.user_reports.push(report);
+        Ok(())
+    }
+
+    async fn reports_filed_since(&self, reporter_user_id: &str, since: chrono::DateTime<chrono::Utc>) -> Result<Vec<UserReport>, Box<dyn std::error::Error + Send + Sync>> {
+        let since = bson::DateTime::from_millis(since.timestamp_millis());
+        let tables = self.tables().await;
+        Ok(tables.user_reports.iter().rev().filter(|r| r.reporter_user_id == reporter_user_id && r.created_at >= since).cloned().collect())
+    }
+
+    async fn list_user_reports(&self, status: Option<&str>, category: Option<ReportCategory>, page: u64, page_size: i64) -> Result<Vec<UserReport>, Box<dyn std::error::Error + Send + Sync>> {
+        let tables = self.tables().await;
+        Ok(tables.user_reports.iter()
+            .filter(|r| status.is_none_or(|status| r.status == status) && category.is_none_or(|category| r.category == category))
+            .skip((page * page_size.max(0) as u64) as usize)
+            .take(page_size.max(0) as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_user_report(&self, report_id: &str) -> Result<Option<UserReport>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.tables().await.user_reports.iter().find(|r| r.report_id == report_id).cloned())
+    }
+
+    async fn resolve_user_report(&self, report_id: &str, status: &str, resolution: &str, operator_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        let Some(report) = tables.user_reports.iter_mut().find(|r| r.report_id == report_id && r.status == "open") else {
+            return Ok(false);
+        };
+        report.status = status.to_string();
+        report.resolution = Some(resolution.to_string());
+        report.resolved_by = Some(operator_id.to_string());
+        report.resolved_at = Some(now());
+        Ok(true)
+    }
+
+    async fn create_sanction(&self, sanction: UserSanction) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.tables().await.user_sanctions.push(sanction);
+        Ok(())
+    }
+
+    async fn list_sanctions(&self, user_id: &str) -> Result<Vec<UserSanction>, Box<dyn std::error::Error + Send + Sync>> {
+        let tables = self.tables().await;
+        Ok(tables.user_sanctions.iter().rev().filter(|s| s.user_id == user_id).cloned().collect())
+    }
+
+    async fn lift_sanction(&self, sanction_id: &str, operator_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        let current = now();
+        let Some(sanction) = tables.user_sanctions.iter_mut().find(|s| s.sanction_id == sanction_id && s.is_active(current)) else {
+            return Ok(false);
+        };
+        sanction.lifted_by = Some(operator_id.to_string());
+        sanction.lifted_at = Some(current);
+        Ok(true)
+    }
+
+    async fn revoke_user_sessions(&self, mobile_no: &str) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        let mut revoked = 0;
+        for session in tables.sessions.iter_mut().filter(|s| s.mobile_no == mobile_no && !s.revoked) {
+            session.revoked = true;
+            revoked += 1;
+        }
+        Ok(revoked)
+    }
+
+    async fn save_chat_message(&self, message: ChatMessage) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.tables().await.chat_messages.push(message);
+        Ok(())
+    }
+
+    async fn get_chat_message(&self, message_id: &str) -> Result<Option<ChatMessage>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.tables().await.chat_messages.iter().find(|m| m.message_id == message_id).cloned())
+    }
+
+    async fn edit_chat_message(&self, message_id: &str, message: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        let Some(stored) = tables.chat_messages.iter_mut().find(|m| m.message_id == message_id && m.deleted_at.is_none()) else {
+            return Ok(false);
+        };
+        stored.message = message.to_string();
+        stored.edited_at = Some(now());
+        Ok(true)
+    }
+
+    async fn delete_chat_message(&self, message_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        let Some(stored) = tables.chat_messages.iter_mut().find(|m| m.message_id == message_id && m.deleted_at.is_none()) else {
+            return Ok(false);
+        };
+        stored.message.clear();
+        stored.deleted_at = Some(now());
+        Ok(true)
+    }
+
+    async fn chat_history(&self, room_id: &str, before: Option<&str>, excluded_user_ids: &[String], limit: i64) -> Result<Vec<ChatMessage>, Box<dyn std::error::Error + Send + Sync>> {
+        let tables = self.tables().await;
+        let mut messages: Vec<ChatMessage> = tables.chat_messages.iter()
+            .filter(|m| m.room_id == room_id && !excluded_user_ids.contains(&m.user_id))
+            .filter(|m| before.is_none_or(|before| m.message_id.as_str() < before))
+            .cloned()
+            .collect();
+        messages.sort_by(|a, b| b.message_id.cmp(&a.message_id));
+        messages.truncate(limit.max(0) as usize);
+        Ok(messages)
+    }
+
+    async fn purge_chat_messages(&self, before: chrono::DateTime<chrono::Utc>) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        let before = bson::DateTime::from_millis(before.timestamp_millis());
+        let mut tables = self.tables().await;
+        let count = tables.chat_messages.len();
+        tables.chat_messages.retain(|m| m.created_at >= before);
+        tables.chat_reactions.retain(|r| r.created_at >= before);
+        Ok((count - tables.chat_messages.len()) as u64)
+    }
+
+    async fn record_chat_audit(&self, audit: ChatMessageAudit) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.tables().await.chat_audit.push(audit);
+        Ok(())
+    }
+
+    async fn react_to_chat_message(&self, reaction: ChatReaction, add: bool) -> Result<Option<i64>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        let same = |r: &ChatReaction| r.message_id == reaction.message_id && r.user_id == reaction.user_id && r.reaction == reaction.reaction;
+        let had = tables.chat_reactions.iter().any(same);
+        if had == add {
+            return Ok(None);
+        }
+        if add {
+            tables.chat_reactions.push(reaction.clone());
+        } else {
+            tables.chat_reactions.retain(|r| !same(r));
+        }
+        let Some(message) = tables.chat_messages.iter_mut().find(|m| m.message_id == reaction.message_id && m.deleted_at.is_none()) else {
+            return Ok(None);
+        };
+        let count = message.reactions.entry(reaction.reaction).or_default();
+        *count += if add { 1 } else { -1 };
+        Ok(Some(*count))
+    }
+
+    async fn mark_chat_read(&self, room_id: &str, user_id: &str, message_id: &str) -> Result<ChatReadReceipt, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        let receipts = &mut tables.chat_read_receipts;
+        let index = match receipts.iter().position(|r| r.room_id == room_id && r.user_id == user_id) {
+            Some(index) => index,
+            None => {
+                receipts.push(ChatReadReceipt {
+                    id: None,
+                    room_id: room_id.to_string(),
+                    user_id: user_id.to_string(),
+                    message_id: message_id.to_string(),
+                    read_at: now(),
+                });
+                receipts.len() - 1
+            }
+        };
+        let receipt = &mut receipts[index];
+        if receipt.message_id.as_str() < message_id {
+            receipt.message_id = message_id.to_string();
+        }
+        receipt.read_at = now();
+        Ok(receipt.clone())
+    }
+
+    async fn chat_read_receipts(&self, room_id: &str, excluded_user_ids: &[String]) -> Result<Vec<ChatReadReceipt>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.tables().await.chat_read_receipts.iter()
+            .filter(|r| r.room_id == room_id && !excluded_user_ids.contains(&r.user_id))
+            .cloned()
+            .collect())
+    }
+
+    async fn list_game_configs(&self, game_type: Option<&str>) -> Result<Vec<GameConfig>, Box<dyn std::error::Error + Send + Sync>> {
+        let tables = self.tables().await;
+        let mut configs: Vec<GameConfig> = tables.game_configs.iter()
+            .filter(|c| game_type.is_none_or(|game_type| c.game_type == game_type))
+            .cloned()
+            .collect();
+        configs.sort_by(|a, b| a.game_type.cmp(&b.game_type).then(b.version.cmp(&a.version)));
+        Ok(configs)
+    }
+
+    async fn insert_game_config(&self, config: GameConfig) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        if tables.game_configs.iter().any(|c| c.game_type == config.game_type && c.version == config.version) {
+            return Ok(false);
+        }
+        tables.game_configs.push(config);
+        Ok(true)
+    }
+
+    // Nothing else writes to this process's tables; writers refresh the cache themselves
+    async fn watch_game_configs(&self) -> Result<Option<GameConfigChanges>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(None)
+    }
+
+    async fn list_notification_templates(&self) -> Result<Vec<NotificationTemplate>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut templates = self.tables().await.notification_templates.clone();
+        templates.sort_by(|a, b| a.notification_type.cmp(&b.notification_type).then(a.language_code.cmp(&b.language_code)));
+        Ok(templates)
+    }
+
+    async fn upsert_notification_template(&self, template: NotificationTemplate) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        tables.notification_templates.retain(|t| t.notification_type != template.notification_type || t.language_code != template.language_code);
+        tables.notification_templates.push(template);
+        Ok(())
+    }
+
+    async fn delete_notification_template(&self, notification_type: &str, language_code: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        let before = tables.notification_templates.len();
+        tables.notification_templates.retain(|t| t.notification_type != notification_type || t.language_code != language_code);
+        Ok(tables.notification_templates.len() < before)
+    }
+
+    async fn get_notification_preferences(&self, user_id: &str) -> Result<NotificationPreferences, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.tables().await.notification_preferences.get(user_id).cloned().unwrap_or_default())
+    }
+
+    async fn update_notification_preferences(&self, user_id: &str, _mobile_no: &str, notifications: &NotificationPreferences) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.tables().await.notification_preferences.insert(user_id.to_string(), notifications.clone());
+        Ok(())
+    }
+
+    // Nothing delivers outbox messages here; they are only kept
+    async fn store_inbox_notification(&self, notification: InboxNotification, push: Option<OutboxMessage>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        tables.record("notification_inbox", notification)?;
+        if let Some(push) = push {
+            tables.record("outbox", push)?;
+        }
+        Ok(())
+    }
+
+    async fn count_unread_notifications(&self, user_id: &str) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        let tables = self.tables().await;
+        let inbox = tables.events.get("notification_inbox").map(Vec::as_slice).unwrap_or_default();
+        Ok(inbox.iter()
+            .filter(|n| n["user_id"] == user_id && n["read"] == false && n["show_in_inbox"] == true)
+            .count() as u64)
+    }
+
+    async fn user_exists(&self, mobile_no: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.tables().await.users.iter().any(|u| u.mobile_no == mobile_no))
+    }
+
+    async fn get_user_by_mobile(&self, mobile_no: &str) -> Result<Option<UserRegister>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.tables().await.users.iter().find(|u| u.mobile_no == mobile_no).cloned())
+    }
+
+    async fn get_user_by_id(&self, user_id: &str) -> Result<Option<UserRegister>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.tables().await.users.iter().find(|u| u.user_id == user_id).cloned())
+    }
+
+    async fn get_user_identity(&self, mobile_no: &str) -> Result<Option<UserIdentity>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.tables().await.users.iter().find(|u| u.mobile_no == mobile_no).map(UserIdentity::from))
+    }
+
+    async fn get_user_profile(&self, mobile_no: &str) -> Result<Option<UserProfile>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.tables().await.users.iter().find(|u| u.mobile_no == mobile_no).map(UserProfile::from))
+    }
+
+    async fn register_new_user(
+        &self,
+        mobile_no: &str,
+        device_id: &str,
+        fcm_token: &str,
+        email: Option<&str>,
+    ) -> Result<(String, u64), Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        tables.user_counter += 1;
+        let user = UserRegister::new(
+            mobile_no.to_string(),
+            device_id.to_string(),
+            fcm_token.to_string(),
+            email.map(|e| e.to_string()),
+            tables.user_counter,
+        );
+        let registered = (user.user_id.clone(), user.user_number);
+        tables.users.push(user);
+        info!("🆕 Registered new user: {} (number: {}) [in-memory]", registered.0, registered.1);
+        Ok(registered)
+    }
+
+    async fn import_user(&self, mut user: UserRegister) -> Result<Option<(String, u64)>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        if tables.users.iter().any(|u| u.mobile_no == user.mobile_no) {
+            return Ok(None);
+        }
+        tables.user_counter += 1;
+        user.user_number = tables.user_counter;
+        let imported = (user.user_id.clone(), user.user_number);
+        tables.users.push(user);
+        info!("📥 Imported user: {} (number: {}) [in-memory]", imported.0, imported.1);
+        Ok(Some(imported))
+    }
+
+    async fn search_users(&self, filter: &UserFilter, page: u64, page_size: i64) -> Result<(Vec<UserRegister>, u64), Box<dyn std::error::Error + Send + Sync>> {
+        let mut users: Vec<UserRegister> = self.tables().await.users.iter().filter(|u| filter.matches(u)).cloned().collect();
+        users.sort_by_key(|u| std::cmp::Reverse(u.user_number));
+        let total = users.len() as u64;
+        let page_size = page_size.max(0) as usize;
+        let page = users.into_iter().skip(page as usize * page_size).take(page_size).collect();
+        Ok((page, total))
+    }
+
+    async fn get_admin_operator(&self, operator_id: &str) -> Result<Option<AdminOperator>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.tables().await.admin_operators.iter().find(|o| o.operator_id == operator_id).cloned())
+    }
+
+    async fn list_admin_operators(&self) -> Result<Vec<AdminOperator>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.tables().await.admin_operators.clone())
+    }
+
+    async fn create_admin_operator(&self, operator: AdminOperator) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.tables().await.admin_operators.push(operator);
+        Ok(())
+    }
+
+    async fn set_admin_operator_role(&self, operator_id: &str, role: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        let Some(operator) = tables.admin_operators.iter_mut().find(|o| o.operator_id == operator_id) else {
+            return Ok(false);
+        };
+        operator.role = role.to_string();
+        operator.updated_at = now();
+        Ok(true)
+    }
+
+    async fn store_admin_access_denied_event(&self, operator_id: &str, role: &str, permission: &str, target: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let event = AdminAccessDeniedEvent {
+            id: None,
+            request_id: Correlation::current(),
+            operator_id: operator_id.to_string(),
+            role: role.to_string(),
+            permission: permission.to_string(),
+            target: target.to_string(),
+            timestamp: now(),
+        };
+        self.tables().await.record_event(event)
+    }
+
+    async fn store_rejected_handshake(&self, event: RejectedHandshakeEvent) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.tables().await.record_event(event)
+    }
+
+    async fn export_users(&self, filter: &UserFilter) -> Result<UserStream, Box<dyn std::error::Error + Send + Sync>> {
+        let mut users: Vec<UserRegister> = self.tables().await.users.iter().filter(|u| filter.matches(u)).cloned().collect();
+        users.sort_by_key(|u| u.user_number);
+        Ok(futures_util::stream::iter(users.into_iter().map(Ok)).boxed())
+    }
+
+    async fn update_user_login_info(&self, mobile_no: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if let Some(user) = self.tables().await.user_mut(mobile_no) {
+            user.last_login_at = Some(now());
+            user.total_logins += 1;
+            user.is_active = true;
+        }
+        Ok(())
+    }
+
+    async fn update_user_language_in_register(
+        &self,
+        mobile_no: &str,
+        language_code: Option<String>,
+        language_name: Option<String>,
+        region_code: Option<String>,
+        timezone: Option<String>,
+        user_preferences: serde_json::Value,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if let Some(user) = self.tables().await.user_mut(mobile_no) {
+            user.language_code = language_code.or(user.language_code.take());
+            user.language_name = language_name.or(user.language_name.take());
+            user.region_code = region_code.or(user.region_code.take());
+            user.timezone = timezone.or(user.timezone.take());
+            user.updated_at = now();
+        }
+
+        // Same filtering as DataService: keys that cannot be a document path are dropped
+        if let Some(prefs) = user_preferences.as_object() {
+            let prefs: Map<String, Value> = prefs
+                .iter()
+                .filter(|(key, _)| !key.is_empty() && !key.contains('.') && !key.starts_with('$'))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect();
+            if !prefs.is_empty() {
+                self.merge_user_preferences(mobile_no, &prefs).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn merge_user_preferences(&self, mobile_no: &str, updates: &serde_json::Map<String, serde_json::Value>) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        let Some(user) = tables.user_mut(mobile_no) else {
+            return Ok(false);
+        };
+        let preferences = user.user_preferences.get_or_insert_with(|| Value::Object(Map::new()));
+        for (path, value) in updates {
+            apply_path(preferences, path, value);
+        }
+        user.updated_at = now();
+        Ok(true)
+    }
+
+    async fn verify_otp(&self, _socket_id: &str, mobile_no: &str, session_token: &str, otp: &str) -> Result<OtpVerificationResult, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        let Some(login) = tables.login_sessions.iter_mut()
+            .find(|s| s.mobile_no == mobile_no && TokenGenerator::constant_time_eq(&s.session_token, session_token)) else {
+            return Ok(OtpVerificationResult::NotFound);
+        };
+        let current = now();
+        if current > login.expires_at {
+            return Ok(OtpVerificationResult::Expired);
+        }
+        if !TokenGenerator::constant_time_eq(otp, &login.otp.to_string()) {
+            return Ok(OtpVerificationResult::Invalid);
+        }
+        login.verified_at = Some(current);
+        let login = login.clone();
+
+        // Start the session
+        let now_ms = current.timestamp_millis();
+        let absolute_expires_at = now_ms + CONFIG.session_absolute_timeout_hours * 60 * 60 * 1000;
+        let idle_expires_at = (now_ms + CONFIG.session_idle_timeout_minutes * 60 * 1000).min(absolute_expires_at);
+        tables.sessions.push(UserSession {
+            id: Some(bson::oid::ObjectId::new()),
+            session_token: session_token.to_string(),
+            mobile_no: mobile_no.to_string(),
+            device_id: login.device_id.clone(),
+            created_at: current,
+            last_active_at: current,
+            idle_expires_at: bson::DateTime::from_millis(idle_expires_at),
+            absolute_expires_at: bson::DateTime::from_millis(absolute_expires_at),
+            revoked: false,
+        });
+
+        // Record the device, enriched with the login socket's latest device:info
+        let device_info = tables.events.get("device_info_events")
+            .and_then(|events| events.iter().rev().find(|e| e["socket_id"] == login.socket_id.as_str()))
+            .map(|e| e["device_info"].clone())
+            .unwrap_or_default();
+        let field = |name: &str| device_info.get(name).and_then(|v| v.as_str()).map(|v| v.to_string());
+        match tables.devices.iter_mut().find(|d| d.mobile_no == mobile_no && d.device_id == login.device_id) {
+            Some(device) => {
+                device.last_seen_at = current;
+                device.removed_at = None;
+                device.device_type = field("device_type").or(device.device_type.take());
+                device.manufacturer = field("manufacturer").or(device.manufacturer.take());
+                device.model = field("model").or(device.model.take());
+                device.firmware_version = field("firmware_version").or(device.firmware_version.take());
+            }
+            None => tables.devices.push(UserDevice {
+                id: Some(bson::oid::ObjectId::new()),
+                mobile_no: mobile_no.to_string(),
+                device_id: login.device_id.clone(),
+                device_type: field("device_type"),
+                manufacturer: field("manufacturer"),
+                model: field("model"),
+                firmware_version: field("firmware_version"),
+                first_seen_at: current,
+                last_seen_at: current,
+                removed_at: None,
+            }),
+        }
+        Ok(OtpVerificationResult::Success)
+    }
+
+    async fn verify_session_and_mobile(&self, mobile_no: &str, session_token: &str) -> Result<SessionStatus, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tables = self.tables().await;
+        let Some(session) = tables.sessions.iter_mut()
+            .rev()
+            .find(|s| s.mobile_no == mobile_no && TokenGenerator::constant_time_eq(&s.session_token, session_token)) else {
+            return Ok(SessionStatus::Invalid);
+        };
+        if session.revoked {
+            return Ok(SessionStatus::Invalid);
+        }
+
+        let now_ms = chrono::Utc::now().timestamp_millis();
+        if now_ms >= session.idle_expires_at.timestamp_millis() || now_ms >= session.absolute_expires_at.timestamp_millis() {
+            return Ok(SessionStatus::Expired);
+        }
+        let idle_expires_at = (now_ms + CONFIG.session_idle_timeout_minutes * 60 * 1000).min(session.absolute_expires_at.timestamp_millis());
+        session.last_active_at = bson::DateTime::from_millis(now_ms);
+        session.idle_expires_at = bson::DateTime::from_millis(idle_expires_at);
+        Ok(SessionStatus::Valid)
+    }
+
+    async fn check_referral_code_exists(&self, referral_code: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.tables().await.users.iter().any(|u| u.referral_code.as_deref() == Some(referral_code)))
+    }
+
+    async fn generate_unique_referral_code(&self, _mobile_no: &str) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        const MAX_ATTEMPTS: u32 = 10;
+        const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+        for _ in 0..MAX_ATTEMPTS {
+            let code: String = (0..6).map(|_| CHARS[rand::random::<usize>() % CHARS.len()] as char).collect();
+            if !self.check_referral_code_exists(&code).await? {
+                return Ok(code);
+            }
+        }
+        Err("Failed to generate unique referral code after maximum attempts".into())
+    }
+
+    async fn update_user_profile_in_register(
+        &self,
+        mobile_no: &str,
+        full_name: Option<String>,
+        state: Option<String>,
+        referral_code: Option<String>,
+        referred_by: Option<String>,
+        profile_data: Option<serde_json::Value>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if let Some(user) = self.tables().await.user_mut(mobile_no) {
+            user.full_name = full_name.or(user.full_name.take());
+            user.state = state.or(user.state.take());
+            user.referral_code = referral_code.or(user.referral_code.take());
+            user.referred_by = referred_by.or(user.referred_by.take());
+            user.profile_data = profile_data.or(user.profile_data.take());
+            user.updated_at = now();
+        }
+        Ok(())
+    }
+
+    async fn check_otp_attempts(&self, mobile_no: &str, session_token: &str, max_attempts: u32) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let tables = self.tables().await;
+        let attempts = tables.events.get("otp_verification_events")
+            .map(|events| events.iter().filter(|e| e["mobile_no"] == mobile_no && e["session_token"] == session_token).count())
+            .unwrap_or(0);
+        Ok(attempts < max_attempts as usize)
+    }
+}
@@ -1,17 +1,51 @@
 pub mod models;
 pub mod repository;
+pub mod envelope;
 pub mod service;
+pub mod store;
+pub mod memory;
+pub mod chaos;
+pub mod write_queue;
+pub mod dead_letter;
+pub mod health;
 pub mod gameplay_service;
+pub mod wallet_service;
+pub mod inventory_service;
+pub mod backup;
+pub mod export;
+pub mod warehouse;
+pub mod google_auth;
+pub mod outbox;
+pub mod query_monitor;
+pub mod user_cache;
+pub mod timeline;
 
 pub use service::DataService;
+pub use store::DataStore;
+pub use memory::InMemoryDataStore;
+pub use chaos::ChaosDataStore;
 pub use gameplay_service::GameplayService;
+pub use wallet_service::WalletService;
+pub use inventory_service::InventoryService;
 
 use once_cell::sync::OnceCell;
-use mongodb::{Client, Database};
-use tracing::info;
+use mongodb::{bson::{doc, Document}, options::{ClientOptions, CreateCollectionOptions, IndexOptions}, Client, Collection, Database, IndexModel};
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::{info, warn};
 
-// Global static database instance
-static MONGODB_DATABASE: OnceCell<Database> = OnceCell::new();
+use crate::config::CONFIG;
+use crate::managers::tenant::{Tenant, TenantManager};
+
+// Collection holding a database's environment marker (COLLECTION_PREFIX applies)
+const ENVIRONMENT_MARKER_COLLECTION: &str = "environment_marker";
+const MAX_COLLECTION_PREFIX_LENGTH: usize = 32;
+// Document schema this server reads and writes, recorded in the marker
+// collection. Bump it with any change older servers cannot work with.
+pub const SCHEMA_VERSION: i32 = 1;
+
+// Database of every tenant, by tenant id
+static MONGODB_DATABASES: OnceCell<HashMap<String, Database>> = OnceCell::new();
 
 pub struct DatabaseManager;
 
@@ -25,27 +59,396 @@ impl DatabaseManager {
         let mongodb_uri = std::env::var("MONGODB_URI")
             .unwrap_or_else(|_| "mongodb://localhost:27017".to_string());
         
-        let database_name = std::env::var("MONGODB_DATABASE")
-            .unwrap_or_else(|_| "game_admin".to_string());
-        
+        Self::check_collection_prefix()?;
+
         // Create MongoDB client
-        let client = Client::with_uri_str(&mongodb_uri).await?;
+        let options = Self::client_options(&mongodb_uri).await?;
+        let client = Client::with_options(options)?;
         
         // Test the connection
         client.list_database_names(None, None).await?;
         
-        // Get database
-        let database = client.database(&database_name);
+        // One database per tenant, all on the same client
+        let mut databases = HashMap::new();
+        for tenant in TenantManager::tenants() {
+            let database = client.database(&tenant.database);
+            Self::check_environment(&database).await?;
+            Self::ensure_indexes(&database).await;
+            Self::ensure_capped_collections(&database).await;
+            databases.insert(tenant.tenant_id.clone(), database);
+        }
         
         // Store in static variable
-        MONGODB_DATABASE.set(database).expect("Failed to set MongoDB database");
+        MONGODB_DATABASES.set(databases).expect("Failed to set MongoDB databases");
         
-        info!("✅ MongoDB connected successfully to database: {}", database_name);
+        let names: Vec<&str> = TenantManager::tenants().iter().map(|t| t.database.as_str()).collect();
+        info!("✅ MongoDB connected successfully to database: {}", names.join(", "));
+        if !CONFIG.collection_prefix.is_empty() {
+            info!("🏷️ Collection names prefixed with {:?}", CONFIG.collection_prefix);
+        }
         Ok(())
     }
+
+    fn check_collection_prefix() -> Result<(), Box<dyn std::error::Error>> {
+        let prefix = &CONFIG.collection_prefix;
+        if prefix.len() > MAX_COLLECTION_PREFIX_LENGTH
+            || prefix.starts_with("system.")
+            || !prefix.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.')
+        {
+            return Err(format!("COLLECTION_PREFIX {:?} must be at most {} letters, digits, '_', '-' or '.' and not start with \"system.\"", prefix, MAX_COLLECTION_PREFIX_LENGTH).into());
+        }
+        Ok(())
+    }
+
+    // Refuse to start on a database marked for another environment, so a
+    // staging server pointed at the production cluster with the wrong
+    // COLLECTION_PREFIX cannot touch production data. The first start with
+    // APP_ENVIRONMENT set marks the database; without APP_ENVIRONMENT only
+    // unmarked databases are accepted.
+    async fn check_environment(database: &Database) -> Result<(), Box<dyn std::error::Error>> {
+        let markers = database.collection::<Document>(&Self::collection_name(ENVIRONMENT_MARKER_COLLECTION));
+        let marked = markers.find_one(doc! { "_id": "environment" }, None).await?
+            .and_then(|marker| marker.get_str("environment").ok().map(str::to_string));
+        match (marked, &CONFIG.environment) {
+            (Some(marked), Some(expected)) if &marked == expected => Ok(()),
+            (Some(marked), expected) => Err(format!(
+                "Database {} (collection prefix {:?}) is marked for environment {:?} but APP_ENVIRONMENT is {:?} - refusing to start",
+                database.name(), CONFIG.collection_prefix, marked, expected.as_deref().unwrap_or("")
+            ).into()),
+            (None, Some(expected)) => {
+                markers.insert_one(doc! {
+                    "_id": "environment",
+                    "environment": expected,
+                    "collection_prefix": &CONFIG.collection_prefix,
+                    "marked_at": bson::DateTime::now(),
+                }, None).await?;
+                info!("🏷️ Marked database {} for environment {}", database.name(), expected);
+                Ok(())
+            }
+            (None, None) => Ok(()),
+        }
+    }
+
+    // Schema version recorded in a database; None before the first server
+    // that records it started there
+    pub async fn schema_version(database: &Database) -> Result<Option<i32>, Box<dyn std::error::Error + Send + Sync>> {
+        let markers = database.collection::<Document>(&Self::collection_name(ENVIRONMENT_MARKER_COLLECTION));
+        Ok(markers.find_one(doc! { "_id": "schema" }, None).await?
+            .and_then(|marker| marker.get_i32("version").ok()))
+    }
+
+    pub async fn record_schema_version(database: &Database) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let markers = database.collection::<Document>(&Self::collection_name(ENVIRONMENT_MARKER_COLLECTION));
+        markers.update_one(
+            doc! { "_id": "schema" },
+            doc! { "$set": { "version": SCHEMA_VERSION, "recorded_at": bson::DateTime::now() } },
+            mongodb::options::UpdateOptions::builder().upsert(true).build(),
+        ).await?;
+        Ok(())
+    }
+
+    // A collection's name with COLLECTION_PREFIX applied
+    pub fn collection_name(name: &str) -> String {
+        format!("{}{}", CONFIG.collection_prefix, name)
+    }
+
+    // Collection in the database of the tenant in scope
+    pub fn collection<T>(name: &str) -> Collection<T> {
+        Self::get_database().collection(&Self::collection_name(name))
+    }
+
+    pub fn tenant_collection<T>(tenant: &Tenant, name: &str) -> Collection<T> {
+        Self::tenant_database(tenant).collection(&Self::collection_name(name))
+    }
+
+    // MONGODB_DATABASE; the database of the default tenant unless TENANTS says otherwise
+    pub fn database_name() -> String {
+        std::env::var("MONGODB_DATABASE").unwrap_or_else(|_| "game_admin".to_string())
+    }
     
-    // Get the shared database instance
+    // Options from MONGODB_URI with the MONGODB_* tuning variables applied on top
+    async fn client_options(mongodb_uri: &str) -> Result<ClientOptions, Box<dyn std::error::Error>> {
+        let mut options = ClientOptions::parse(mongodb_uri).await?;
+        let config = &*CONFIG;
+        if let Some(size) = config.mongo_max_pool_size {
+            options.max_pool_size = Some(size);
+        }
+        if let Some(size) = config.mongo_min_pool_size {
+            options.min_pool_size = Some(size);
+        }
+        if let Some(ms) = config.mongo_connect_timeout_ms {
+            options.connect_timeout = Some(Duration::from_millis(ms));
+        }
+        if let Some(ms) = config.mongo_server_selection_timeout_ms {
+            options.server_selection_timeout = Some(Duration::from_millis(ms));
+        }
+        if let Some(ms) = config.mongo_max_idle_time_ms {
+            options.max_idle_time = Some(Duration::from_millis(ms));
+        }
+        if let Some(retry) = config.mongo_retry_writes {
+            options.retry_writes = Some(retry);
+        }
+        options.command_event_handler = Some(std::sync::Arc::new(query_monitor::QueryMonitor));
+
+        // Unset values fall back to the driver defaults shown here
+        info!(
+            "🗄️ MongoDB client: pool {}-{}, connect timeout {:?}, server selection timeout {:?}, max idle {:?}, retryable writes {}",
+            options.min_pool_size.unwrap_or(0),
+            options.max_pool_size.unwrap_or(10),
+            options.connect_timeout.unwrap_or(Duration::from_secs(10)),
+            options.server_selection_timeout.unwrap_or(Duration::from_secs(30)),
+            options.max_idle_time,
+            options.retry_writes.unwrap_or(true)
+        );
+        Ok(options)
+    }
+
+    // Indexes behind the admin user search and timeline, error analytics, retention reports, room recovery,
+    // dealer audits, escrows, the revenue ledger, promo codes, impersonation and compliance audits, AFK events, progress, challenges, seasons, wallets, inventory, friends, gifts, the
+    // anomaly scans, moderation, chat with its reactions and read receipts, unread notifications, notification templates, game configs,
+    // warehouse dead letters, the outbox and session metrics. The unique ones also
+    // guard against duplicate documents.
+    pub fn required_indexes() -> Vec<(&'static str, Vec<IndexModel>)> {
+        vec![
+            ("userregister", vec![
+                IndexModel::builder().keys(doc! { "mobile_no": 1 }).build(),
+                IndexModel::builder().keys(doc! { "user_id": 1 }).build(),
+                IndexModel::builder().keys(doc! { "full_name": 1 }).build(),
+                IndexModel::builder().keys(doc! { "user_number": -1 }).build(),
+                IndexModel::builder().keys(doc! { "state": 1, "language_code": 1, "created_at": -1 }).build(),
+                IndexModel::builder().keys(doc! { "is_active": 1, "is_banned": 1, "created_at": -1 }).build(),
+            ]),
+            ("notification_templates", vec![
+                IndexModel::builder().keys(doc! { "notification_type": 1, "language_code": 1 }).options(IndexOptions::builder().unique(true).build()).build(),
+            ]),
+            ("mobile_changes", vec![
+                IndexModel::builder().keys(doc! { "change_id": 1 }).options(IndexOptions::builder().unique(true).build()).build(),
+            ]),
+            ("mobile_change_audit", vec![
+                IndexModel::builder().keys(doc! { "user_id": 1, "changed_at": -1 }).build(),
+            ]),
+            ("connection_error_events", vec![
+                IndexModel::builder().keys(doc! { "timestamp": -1 }).build(),
+                IndexModel::builder().keys(doc! { "socket_id": 1, "timestamp": -1 }).build(),
+            ]),
+            ("otp_verification_events", vec![
+                IndexModel::builder().keys(doc! { "mobile_no": 1, "timestamp": -1 }).build(),
+            ]),
+            ("user_registration_events", vec![
+                IndexModel::builder().keys(doc! { "user_id": 1, "timestamp": -1 }).build(),
+            ]),
+            ("user_profile_events", vec![
+                IndexModel::builder().keys(doc! { "user_id": 1, "timestamp": -1 }).build(),
+            ]),
+            ("language_setting_events", vec![
+                IndexModel::builder().keys(doc! { "user_id": 1, "timestamp": -1 }).build(),
+            ]),
+            ("device_info_events", vec![
+                IndexModel::builder().keys(doc! { "socket_id": 1, "timestamp": -1 }).build(),
+            ]),
+            ("room_snapshots", vec![
+                IndexModel::builder().keys(doc! { "room_id": 1 }).options(IndexOptions::builder().unique(true).build()).build(),
+                IndexModel::builder().keys(doc! { "saved_at": -1 }).build(),
+            ]),
+            ("archived_rooms", vec![
+                IndexModel::builder().keys(doc! { "room_id": 1, "archived_at": -1 }).build(),
+            ]),
+            ("match_escrows", vec![
+                IndexModel::builder().keys(doc! { "escrow_id": 1 }).options(IndexOptions::builder().unique(true).build()).build(),
+                IndexModel::builder().keys(doc! { "room_id": 1, "status": 1 }).build(),
+                IndexModel::builder().keys(doc! { "status": 1, "settling_at": 1 }).build(),
+                IndexModel::builder().keys(doc! { "status": 1, "created_at": 1 }).build(),
+            ]),
+            ("revenue_ledger", vec![
+                IndexModel::builder().keys(doc! { "escrow_id": 1 }).options(IndexOptions::builder().unique(true).build()).build(),
+                IndexModel::builder().keys(doc! { "day": 1, "game_type": 1, "recorded_at": 1 }).build(),
+            ]),
+            ("revenue_rollups", vec![
+                IndexModel::builder().keys(doc! { "day": 1, "game_type": 1 }).options(IndexOptions::builder().unique(true).build()).build(),
+            ]),
+            ("promo_codes", vec![
+                IndexModel::builder().keys(doc! { "code": 1 }).options(IndexOptions::builder().unique(true).build()).build(),
+            ]),
+            ("promo_redemptions", vec![
+                IndexModel::builder().keys(doc! { "code": 1, "user_id": 1 }).build(),
+                IndexModel::builder().keys(doc! { "code": 1, "redeemed_at": -1 }).build(),
+                IndexModel::builder().keys(doc! { "device_id": 1, "redeemed_at": -1 }).build(),
+                IndexModel::builder().keys(doc! { "user_id": 1, "redeemed_at": -1 }).build(),
+            ]),
+            ("impersonation_sessions", vec![
+                IndexModel::builder().keys(doc! { "session_id": 1 }).options(IndexOptions::builder().unique(true).build()).build(),
+                IndexModel::builder().keys(doc! { "user_id": 1, "started_at": -1 }).build(),
+                IndexModel::builder().keys(doc! { "operator_id": 1, "started_at": -1 }).build(),
+            ]),
+            ("login_success_events", vec![
+                IndexModel::builder().keys(doc! { "mobile_no": 1, "timestamp": 1 }).build(),
+            ]),
+            ("compliance_audit", vec![
+                IndexModel::builder().keys(doc! { "user_id": 1, "created_at": -1 }).build(),
+            ]),
+            ("afk_events", vec![
+                IndexModel::builder().keys(doc! { "user_id": 1, "created_at": -1 }).build(),
+            ]),
+            ("session_metrics", vec![
+                IndexModel::builder().keys(doc! { "minute": 1, "node_id": 1 }).options(IndexOptions::builder().unique(true).build()).build(),
+            ]),
+            ("retention_reports", vec![
+                IndexModel::builder().keys(doc! { "report_date": -1 }).options(IndexOptions::builder().unique(true).build()).build(),
+            ]),
+            ("warehouse_dead_letters", vec![
+                IndexModel::builder().keys(doc! { "failed_at": -1 }).build(),
+            ]),
+            ("outbox", vec![
+                IndexModel::builder().keys(doc! { "message_id": 1 }).options(IndexOptions::builder().unique(true).build()).build(),
+                IndexModel::builder().keys(doc! { "status": 1, "next_attempt_at": 1 }).build(),
+                IndexModel::builder().keys(doc! { "status": 1, "created_at": -1 }).build(),
+            ]),
+            ("dealer_audits", vec![
+                IndexModel::builder().keys(doc! { "room_id": 1, "revealed_at": -1 }).build(),
+            ]),
+            ("gameplay_progress", vec![
+                IndexModel::builder().keys(doc! { "user_id": 1 }).options(IndexOptions::builder().unique(true).build()).build(),
+            ]),
+            ("daily_challenges", vec![
+                IndexModel::builder().keys(doc! { "date": 1 }).options(IndexOptions::builder().unique(true).build()).build(),
+            ]),
+            ("challenge_progress", vec![
+                IndexModel::builder().keys(doc! { "user_id": 1, "date": 1 }).options(IndexOptions::builder().unique(true).build()).build(),
+            ]),
+            ("seasons", vec![
+                IndexModel::builder().keys(doc! { "season_id": 1 }).options(IndexOptions::builder().unique(true).build()).build(),
+                IndexModel::builder().keys(doc! { "status": 1, "start_at": 1 }).build(),
+            ]),
+            ("season_ratings", vec![
+                IndexModel::builder().keys(doc! { "season_id": 1, "user_id": 1 }).options(IndexOptions::builder().unique(true).build()).build(),
+                IndexModel::builder().keys(doc! { "season_id": 1, "rating": -1, "updated_at": 1 }).build(),
+            ]),
+            ("wallets", vec![
+                IndexModel::builder().keys(doc! { "user_id": 1 }).options(IndexOptions::builder().unique(true).build()).build(),
+            ]),
+            ("wallet_transactions", vec![
+                IndexModel::builder().keys(doc! { "reference": 1 }).options(IndexOptions::builder().unique(true).build()).build(),
+                IndexModel::builder().keys(doc! { "user_id": 1, "created_at": -1 }).build(),
+            ]),
+            ("inventory", vec![
+                IndexModel::builder().keys(doc! { "user_id": 1, "item_id": 1 }).options(IndexOptions::builder().unique(true).build()).build(),
+            ]),
+            ("inventory_transactions", vec![
+                IndexModel::builder().keys(doc! { "reference": 1 }).options(IndexOptions::builder().unique(true).build()).build(),
+                IndexModel::builder().keys(doc! { "user_id": 1, "created_at": -1 }).build(),
+            ]),
+            ("friendships", vec![
+                IndexModel::builder().keys(doc! { "user_id": 1, "friend_user_id": 1 }).options(IndexOptions::builder().unique(true).build()).build(),
+                IndexModel::builder().keys(doc! { "friend_user_id": 1 }).build(),
+            ]),
+            ("gifts", vec![
+                IndexModel::builder().keys(doc! { "gift_id": 1 }).options(IndexOptions::builder().unique(true).build()).build(),
+                IndexModel::builder().keys(doc! { "from_user_id": 1, "created_at": -1 }).build(),
+                IndexModel::builder().keys(doc! { "to_user_id": 1, "created_at": -1 }).build(),
+                IndexModel::builder().keys(doc! { "created_at": 1 }).build(),
+            ]),
+            ("match_participants", vec![
+                IndexModel::builder().keys(doc! { "joined_at": 1, "room_id": 1 }).build(),
+            ]),
+            ("match_results", vec![
+                IndexModel::builder().keys(doc! { "reported_at": 1 }).build(),
+                IndexModel::builder().keys(doc! { "user_id": 1, "reported_at": -1 }).build(),
+            ]),
+            ("user_risk", vec![
+                IndexModel::builder().keys(doc! { "user_id": 1 }).options(IndexOptions::builder().unique(true).build()).build(),
+            ]),
+            ("risk_flags", vec![
+                IndexModel::builder().keys(doc! { "flag_id": 1 }).options(IndexOptions::builder().unique(true).build()).build(),
+                // One open flag per rule, user and counterparty
+                IndexModel::builder().keys(doc! { "flag_key": 1 }).options(
+                    IndexOptions::builder().unique(true).partial_filter_expression(doc! { "status": "open" }).build()
+                ).build(),
+                IndexModel::builder().keys(doc! { "status": 1, "created_at": -1 }).build(),
+            ]),
+            ("user_blocks", vec![
+                IndexModel::builder().keys(doc! { "user_id": 1, "blocked_user_id": 1 }).options(IndexOptions::builder().unique(true).build()).build(),
+                IndexModel::builder().keys(doc! { "blocked_user_id": 1 }).build(),
+            ]),
+            ("user_reports", vec![
+                IndexModel::builder().keys(doc! { "report_id": 1 }).options(IndexOptions::builder().unique(true).build()).build(),
+                IndexModel::builder().keys(doc! { "reporter_user_id": 1, "created_at": -1 }).build(),
+                IndexModel::builder().keys(doc! { "status": 1, "created_at": -1 }).build(),
+            ]),
+            ("user_sanctions", vec![
+                IndexModel::builder().keys(doc! { "sanction_id": 1 }).options(IndexOptions::builder().unique(true).build()).build(),
+                IndexModel::builder().keys(doc! { "user_id": 1, "created_at": -1 }).build(),
+            ]),
+            ("chat_messages", vec![
+                IndexModel::builder().keys(doc! { "message_id": 1 }).options(IndexOptions::builder().unique(true).build()).build(),
+                IndexModel::builder().keys(doc! { "room_id": 1, "message_id": -1 }).build(),
+                IndexModel::builder().keys(doc! { "created_at": 1 }).build(),
+            ]),
+            ("chat_reactions", vec![
+                IndexModel::builder().keys(doc! { "message_id": 1, "user_id": 1, "reaction": 1 }).options(IndexOptions::builder().unique(true).build()).build(),
+                IndexModel::builder().keys(doc! { "created_at": 1 }).build(),
+            ]),
+            ("chat_read_receipts", vec![
+                IndexModel::builder().keys(doc! { "room_id": 1, "user_id": 1 }).options(IndexOptions::builder().unique(true).build()).build(),
+            ]),
+            ("chat_message_audit", vec![
+                IndexModel::builder().keys(doc! { "message_id": 1, "created_at": 1 }).build(),
+                IndexModel::builder().keys(doc! { "user_id": 1, "created_at": -1 }).build(),
+            ]),
+            ("notification_inbox", vec![
+                IndexModel::builder().keys(doc! { "user_id": 1, "read": 1 }).build(),
+            ]),
+            ("game_configs", vec![
+                IndexModel::builder().keys(doc! { "game_type": 1, "version": -1 }).options(IndexOptions::builder().unique(true).build()).build(),
+            ]),
+        ]
+    }
+
+    // Creating an existing index is a no-op; a failure is logged here and
+    // reported by the startup self-check
+    async fn ensure_indexes(database: &Database) {
+        for (name, indexes) in Self::required_indexes() {
+            match database.collection::<Document>(&Self::collection_name(name)).create_indexes(indexes, None).await {
+                Ok(result) => info!("🗂️ {} indexes ready: {}", name, result.index_names.join(", ")),
+                Err(e) => warn!("⚠️ Failed to create {} indexes: {}", name, e),
+            }
+        }
+    }
+
+    // Audit collections that only need recent documents, with their size limit
+    // in bytes. MongoDB drops the oldest documents once a collection is full.
+    // A collection that already exists uncapped is left alone.
+    async fn ensure_capped_collections(database: &Database) {
+        let capped = [
+            ("rejected_handshakes", CONFIG.handshake_audit_max_bytes),
+            ("event_dead_letters", CONFIG.event_dead_letter_max_bytes),
+        ];
+        for (name, size) in capped {
+            let name = Self::collection_name(name);
+            match database.list_collection_names(doc! { "name": &name }).await {
+                Ok(existing) if !existing.is_empty() => continue,
+                Ok(_) => {}
+                Err(e) => {
+                    warn!("⚠️ Failed to look up collection {}: {}", name, e);
+                    continue;
+                }
+            }
+            let options = CreateCollectionOptions::builder().capped(true).size(size).build();
+            match database.create_collection(&name, options).await {
+                Ok(()) => info!("🗂️ Created capped collection {} ({} bytes)", name, size),
+                Err(e) => warn!("⚠️ Failed to create capped collection {}: {}", name, e),
+            }
+        }
+    }
+
+    // Database of the tenant in scope (see TenantManager)
     pub fn get_database() -> &'static Database {
-        MONGODB_DATABASE.get().expect("MongoDB database not initialized. Call DatabaseManager::initialize() first.")
+        Self::tenant_database(TenantManager::current())
+    }
+
+    pub fn tenant_database(tenant: &Tenant) -> &'static Database {
+        MONGODB_DATABASES.get()
+            .expect("MongoDB database not initialized. Call DatabaseManager::initialize() first.")
+            .get(&tenant.tenant_id)
+            .expect("No database for tenant")
     }
 } 
\ No newline at end of file
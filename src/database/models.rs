@@ -3,15 +3,22 @@ use bson::{oid::ObjectId, DateTime};
 use uuid::Uuid;
 use chrono::Utc;
 
-// Event-specific models for separate collections
+use crate::managers::correlation::Correlation;
+
+// Event-specific models for separate collections. Event documents record the
+// request_id of the socket event that produced them (see Correlation).
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ConnectEvent {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<ObjectId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
     pub socket_id: String,
     pub token: i32,
     pub message: String,
     pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip_address: Option<String>,   // Client address, past TRUSTED_PROXIES
     pub timestamp: DateTime,
 }
 
@@ -19,6 +26,8 @@ pub struct ConnectEvent {
 pub struct DeviceInfoEvent {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<ObjectId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
     pub socket_id: String,
     pub device_info: serde_json::Value,
     pub timestamp: DateTime,
@@ -28,6 +37,8 @@ pub struct DeviceInfoEvent {
 pub struct ConnectionErrorEvent {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<ObjectId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
     pub socket_id: String,
     pub error_code: String,
     pub error_type: String,
@@ -41,6 +52,8 @@ pub struct ConnectionErrorEvent {
 pub struct LoginEvent {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<ObjectId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
     pub socket_id: String,
     pub mobile_no: String,
     pub device_id: String,
@@ -53,19 +66,27 @@ pub struct LoginEvent {
 pub struct LoginSuccessEvent {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<ObjectId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
     pub socket_id: String,
     pub mobile_no: String,
     pub device_id: String,
     pub session_token: String,
     pub otp: i32,
     pub timestamp: DateTime,
-    pub expires_at: DateTime,  // OTP expiration time (30 minutes from creation)
+    pub expires_at: DateTime,  // OTP expiration time (the login policy's otp_expiry_secs from creation)
+    #[serde(default)]
+    pub is_new_user: bool,     // Replayed to retried logins that reuse this session
+    #[serde(default)]
+    pub verified_at: Option<DateTime>,  // Set once the OTP has been verified
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct OtpVerificationEvent {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<ObjectId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
     pub socket_id: String,
     pub mobile_no: String,
     pub session_token: String,
@@ -78,113 +99,1273 @@ pub struct OtpVerificationEvent {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct UserRegistrationEvent {
+pub struct UserRegistrationEvent {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    pub socket_id: String,
+    pub user_id: String,              // UUID v7
+    pub user_number: u64,             // Sequential number
+    pub mobile_no: String,
+    pub device_id: String,
+    pub fcm_token: String,
+    pub email: Option<String>,
+    pub timestamp: DateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserProfileEvent {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    pub socket_id: String,
+    pub user_id: String,              // UUID v7
+    pub user_number: u64,             // Sequential number
+    pub mobile_no: String,
+    pub full_name: String,
+    pub timestamp: DateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LanguageSettingEvent {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    pub socket_id: String,
+    pub user_id: String,              // UUID v7
+    pub user_number: u64,             // Sequential number
+    pub mobile_no: String,
+    pub language_code: String,
+    pub language_name: String,
+    pub region_code: Option<String>,
+    pub timezone: Option<String>,
+    pub user_preferences: serde_json::Value,
+    pub timestamp: DateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct User {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: String,           // UUID v7
+    pub user_number: u64,          // Sequential number
+    pub mobile_no: String,
+    pub device_id: String,
+    pub fcm_token: String,
+    pub email: Option<String>,
+    pub full_name: Option<String>,
+    pub language_code: Option<String>,
+    pub language_name: Option<String>,
+    pub region_code: Option<String>,
+    pub timezone: Option<String>,
+    pub user_preferences: Option<serde_json::Value>,
+    pub created_at: DateTime,
+    pub updated_at: DateTime,
+    pub last_login_at: Option<DateTime>,
+    pub is_active: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoginSession {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub session_id: String,        // UUID v7
+    pub user_id: String,           // UUID v7
+    pub user_number: u64,          // Sequential number
+    pub mobile_no: String,
+    pub device_id: String,
+    pub fcm_token: String,
+    pub session_token: String,
+    pub otp: String,
+    pub is_verified: bool,
+    pub jwt_token: Option<String>, // JWT token after OTP verification
+    pub created_at: DateTime,
+    pub expires_at: DateTime,
+    pub verified_at: Option<DateTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UserRegister {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: String,           // UUID v7
+    pub user_number: u64,          // Sequential number
+    pub mobile_no: String,
+    pub device_id: String,
+    pub fcm_token: String,
+    pub email: Option<String>,
+    pub full_name: Option<String>,
+    pub state: Option<String>,
+    pub referral_code: Option<String>,
+    pub referred_by: Option<String>,
+    pub language_code: Option<String>,
+    pub language_name: Option<String>,
+    pub region_code: Option<String>,
+    pub timezone: Option<String>,
+    pub profile_data: Option<serde_json::Value>,
+    pub user_preferences: Option<serde_json::Value>,
+    pub created_at: DateTime,
+    pub updated_at: DateTime,
+    pub last_login_at: Option<DateTime>,
+    pub total_logins: i32,         // Total number of logins
+    pub is_active: bool,
+    #[serde(default)]
+    pub is_banned: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub two_step: Option<TwoStepFactor>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date_of_birth: Option<String>,  // YYYY-MM-DD; set once from set:profile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parental_controls: Option<ParentalControls>,
+}
+
+// Second factor a user set up for withdrawals and device changes
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TwoStepFactor {
+    pub method: String,                 // "pin" or "totp"
+    pub pin_hash: Option<String>,       // Argon2 PHC string
+    pub totp_secret: Option<String>,    // Base32; a TOTP secret has to be readable to check codes
+    pub totp_last_step: Option<i64>,    // Time step of the last accepted code, so a code works once
+    pub enabled_at: Option<DateTime>,   // None while a TOTP setup waits for its first code
+    pub failed_attempts: u32,           // Wrong codes in a row
+    pub locked_until: Option<DateTime>,
+}
+
+// Spending limit a parent set on the account, guarded by a parental PIN
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ParentalControls {
+    pub pin_hash: String,               // Argon2 PHC string
+    pub daily_spend_limit: i64,         // Coins the account may spend per UTC day
+    pub failed_attempts: u32,           // Wrong PINs in a row
+    pub locked_until: Option<DateTime>,
+    pub updated_at: DateTime,
+}
+
+// A second factor removed by support, in `two_step_resets`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TwoStepReset {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: String,
+    pub mobile_no: String,
+    pub method: String,
+    pub operator_id: String,
+    pub reason: String,
+    pub reset_at: DateTime,
+}
+
+// Projections of UserRegister for lookups that don't need the whole document
+#[derive(Debug, Deserialize, Clone)]
+pub struct UserIdentity {
+    pub user_id: String,
+    pub user_number: u64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct UserProfile {
+    pub user_id: String,
+    pub user_number: u64,
+    pub full_name: Option<String>,
+    pub state: Option<String>,
+    pub referral_code: Option<String>,
+    pub referred_by: Option<String>,
+    pub language_code: Option<String>,
+    pub language_name: Option<String>,
+    pub region_code: Option<String>,
+    pub timezone: Option<String>,
+    pub profile_data: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TurnTimingEvent {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    pub room_id: String,
+    pub player_id: String,
+    pub turn_id: String,
+    pub turn_number: u32,
+    pub started_at: DateTime,
+    pub deadline: DateTime,
+    pub ended_at: DateTime,
+    pub duration_ms: i64,
+    pub timed_out: bool,
+    pub auto_action: Option<String>,  // Action the server played on timeout (e.g. "skip")
+    pub consecutive_timeouts: u32,    // Used for anti-stall detection
+    pub timestamp: DateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MatchRecord {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub match_id: String,             // Room id the match was played in
+    pub player_ids: Vec<String>,
+    pub bot_player_ids: Vec<String>,
+    pub is_bot_match: bool,
+    pub rated: bool,                  // Bot matches never affect real ratings
+    pub game_type: String,
+    pub config_version: u32,          // game_configs version the match was played under; 0 for built-in rules
+    pub created_at: DateTime,
+}
+
+// Latest saved state of a gameplay room, one document per room, used to
+// restore matches after a crash. Socket ids are left out: players get new
+// sockets and take their seats back with room:join.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomSnapshot {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub room_id: String,
+    pub players: Vec<RoomPlayerSnapshot>,
+    pub turn_index: Option<u32>,
+    pub turn_number: u32,
+    pub active_turn: Option<ActiveTurnSnapshot>,
+    pub consecutive_timeouts: std::collections::HashMap<String, u32>,
+    pub is_bot_match: bool,
+    pub config: Option<GameConfig>,   // Rules the room was created with; missing in snapshots from older servers
+    #[serde(default)]
+    pub reports: std::collections::HashMap<String, GameOutcome>,    // Results reported with progress:update, by player_id
+    #[serde(default)]
+    pub settled: bool,                // The players agreed on the result
+    pub created_at: DateTime,
+    pub saved_at: DateTime,           // When the state was captured
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomPlayerSnapshot {
+    pub player_id: String,
+    pub is_bot: bool,
+    pub team: u8,
+    pub joined_at: DateTime,
+}
+
+// A closed gameplay room in `archived_rooms`: its last state and how the
+// match was settled
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedRoom {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub room_id: String,
+    pub game_type: String,
+    pub players: Vec<RoomPlayerSnapshot>,
+    pub turn_number: u32,
+    pub is_bot_match: bool,
+    pub end_reason: String,           // "abandoned" (settled by forfeit), "aborted" (no turn was played) or "cancelled" (entry fees unpaid)
+    pub results: Vec<ForfeitResult>,  // Human players; empty for aborted matches
+    pub created_at: DateTime,
+    pub last_left_at: DateTime,       // When the last human player left; the closing time if some were still there
+    pub archived_at: DateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForfeitResult {
+    pub player_id: String,
+    pub team: u8,
+    pub outcome: GameOutcome,
+    pub forfeited: bool,              // Lost because their team left first
+}
+
+// One random draw from a room's dealer seed: a shuffle or a dice roll
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DealerOperation {
+    pub nonce: u32,
+    pub kind: String,                 // "shuffle" or "roll"
+    pub player_id: String,            // Who asked for it
+    pub deck_id: Option<String>,      // Shuffles: the deck and its cards in the order they were given
+    pub cards: Option<Vec<String>>,
+    pub dice: Option<u32>,            // Rolls: how many dice, their faces and the values rolled
+    pub sides: Option<u32>,
+    pub values: Option<Vec<u32>>,
+    pub at: DateTime,
+}
+
+// A room's dealer seed once revealed, with everything drawn from it, in
+// `dealer_audits`, so shuffles and rolls can be checked after the game
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DealerAudit {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub room_id: String,
+    pub seed_commitment: String,      // SHA-256 of server_seed, sent with every draw
+    pub server_seed: String,
+    pub operations: Vec<DealerOperation>,
+    pub created_at: DateTime,         // First draw from the seed
+    pub revealed_at: DateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActiveTurnSnapshot {
+    pub turn_id: String,
+    pub player_id: String,
+    pub turn_number: u32,
+    pub started_at: DateTime,
+    pub deadline: DateTime,
+    pub is_bot: bool,
+}
+
+// Per-user progression in `gameplay_progress`, one document per user.
+// Counters only grow ($inc) and level/best scores only rise ($max), so
+// concurrent or replayed updates never lose progress.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameplayProgress {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: String,
+    #[serde(default = "GameplayProgress::first_level")]
+    pub level: u32,
+    #[serde(default)]
+    pub xp: i64,
+    #[serde(default)]
+    pub games: std::collections::HashMap<String, GameStats>,   // Keyed by game_id
+    pub created_at: DateTime,
+    pub updated_at: DateTime,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameStats {
+    pub played: i64,
+    pub wins: i64,
+    pub losses: i64,
+    pub draws: i64,
+    pub total_score: i64,
+    pub best_score: Option<i64>,
+    pub last_played_at: Option<DateTime>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameOutcome {
+    Win,
+    Loss,
+    Draw,
+}
+
+// One finished game for progress:update
+#[derive(Debug, Clone)]
+pub struct GameResult {
+    pub game_id: String,
+    pub outcome: GameOutcome,
+    pub score: Option<i64>,
+    pub room_id: Option<String>,      // Gameplay room the game was played in, if reported
+}
+
+// A progress:update: XP gained, the level reached, and optionally a finished game
+#[derive(Debug, Clone)]
+pub struct ProgressUpdate {
+    pub xp: i64,
+    pub level: Option<u32>,
+    pub game: Option<GameResult>,
+}
+
+// What a daily challenge counts
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChallengeKind {
+    PlayGames,
+    WinGames,
+    EarnXp,
+    TakeTurns,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyChallenge {
+    pub challenge_id: String,         // "<date>:<kind>"
+    pub kind: ChallengeKind,
+    pub target: i64,
+    pub reward_coins: i64,
+}
+
+// The challenges of one UTC day in `daily_challenges`, generated once and
+// shared by every server instance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyChallengeSet {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub date: String,                 // YYYY-MM-DD (UTC)
+    pub challenges: Vec<DailyChallenge>,
+    pub created_at: DateTime,
+}
+
+// A user's progress on one day's challenges in `challenge_progress`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChallengeProgress {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: String,
+    pub date: String,
+    #[serde(default)]
+    pub progress: std::collections::HashMap<String, i64>,  // Keyed by challenge_id
+    #[serde(default)]
+    pub claimed: Vec<String>,
+    pub updated_at: DateTime,
+}
+
+// Coin balance per user in `wallets`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Wallet {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: String,
+    pub balance: i64,
+    pub updated_at: DateTime,
+}
+
+// Ledger entry in `wallet_transactions`. `reference` is unique, so a credit
+// retried with the same reference is applied once.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletTransaction {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    #[serde(default)]
+    pub transaction_id: String,       // Snowflake id; empty on transactions recorded before ids were added
+    pub user_id: String,
+    pub amount: i64,
+    pub reason: String,               // e.g. daily_challenge
+    pub reference: String,
+    pub created_at: DateTime,
+}
+
+// Outcome of a wallet debit
+#[derive(Debug, Clone, PartialEq)]
+pub enum WalletDebit {
+    Debited(i64),                                               // New balance
+    InsufficientBalance,
+    SpendLimitReached { spent_today: i64, daily_limit: i64 },   // Parental daily limit
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EscrowStatus {
+    Opening,        // Taking the entry fees; stakes are the players to debit
+    Held,           // Waiting for the result
+    Releasing,      // Paying the winners; payouts are fixed
+    Released,
+    Refunding,      // Giving the stakes back; payouts are fixed
+    Refunded,
+}
+
+// Entry fees of a wagered match, held in `match_escrows` from the first turn
+// until the result is final. Payouts are fixed before any coins move and
+// credited with references derived from escrow_id, so a settlement that was
+// interrupted is finished by paying the same payouts again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchEscrow {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub escrow_id: String,            // Snowflake id
+    pub room_id: String,
+    pub game_type: String,
+    pub entry_fee: i64,
+    pub stakes: Vec<EscrowStake>,
+    pub status: EscrowStatus,
+    #[serde(default)]
+    pub reports: std::collections::HashMap<String, GameOutcome>,    // Results reported with progress:update, by player_id
+    #[serde(default)]
+    pub payouts: Vec<EscrowPayout>,
+    #[serde(default)]
+    pub rake: i64,                    // Platform commission kept from the pot
+    pub created_at: DateTime,
+    pub settling_at: Option<DateTime>,
+    pub settled_at: Option<DateTime>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EscrowStake {
+    pub player_id: String,
+    pub team: u8,
+    pub amount: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EscrowPayout {
+    pub player_id: String,
+    pub amount: i64,
+}
+
+// Platform commission of one released escrow in `revenue_ledger`, written
+// once per escrow_id before the settlement is marked done. Refunded escrows
+// earn nothing and are left out.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevenueEntry {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub escrow_id: String,
+    pub room_id: String,
+    pub game_type: String,
+    pub entry_fee: i64,
+    pub players: u32,                 // Staked players
+    pub pot: i64,                     // Entry fees held
+    pub rake: i64,
+    pub paid_out: i64,                // pot - rake, credited to the winners
+    pub day: String,                  // UTC date of the settlement, YYYY-MM-DD
+    pub recorded_at: DateTime,
+}
+
+// One UTC day's revenue_ledger totals for a game type, in `revenue_rollups`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevenueRollup {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub day: String,
+    pub game_type: String,
+    pub matches: u64,
+    pub pot: i64,
+    pub rake: i64,
+    pub paid_out: i64,
+    pub computed_at: DateTime,
+}
+
+// Items a user owns in `inventory`, one document per user and item
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InventoryItem {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: String,
+    pub item_id: String,
+    pub quantity: i64,
+    pub updated_at: DateTime,
+}
+
+// Ledger entry in `inventory_transactions`, like WalletTransaction for items
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InventoryTransaction {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    #[serde(default)]
+    pub transaction_id: String,       // Snowflake id; empty on transactions recorded before ids were added
+    pub user_id: String,
+    pub item_id: String,
+    pub quantity: i64,                // Negative when items leave the inventory
+    pub reason: String,
+    pub reference: String,
+    pub created_at: DateTime,
+}
+
+// What a promo code gives: coins, items, or both
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PromoReward {
+    #[serde(default)]
+    pub coins: i64,
+    #[serde(default)]
+    pub items: Vec<PromoItem>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromoItem {
+    pub item_id: String,
+    pub quantity: i64,
+}
+
+// An admin-defined promo code in `promo_codes`. `uses` counts redemptions
+// against `max_uses`; each user may redeem it `per_user_limit` times.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromoCode {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub code: String,                 // Upper case; redeemed case-insensitively
+    pub description: Option<String>,
+    pub reward: PromoReward,
+    pub max_uses: Option<u64>,        // No cap when None
+    pub uses: u64,
+    pub per_user_limit: u32,
+    pub starts_at: Option<DateTime>,
+    pub expires_at: Option<DateTime>,
+    pub active: bool,                 // False once disabled by an operator
+    pub created_by: String,           // operator_id
+    pub created_at: DateTime,
+    pub updated_at: DateTime,
+}
+
+impl PromoCode {
+    // Why the code cannot be redeemed at `now`, if it cannot; per-user limits
+    // are checked against the user's redemptions
+    pub fn unavailable(&self, now: DateTime) -> Option<PromoRedeem> {
+        if !self.active {
+            Some(PromoRedeem::NotFound)
+        } else if self.starts_at.is_some_and(|starts_at| now < starts_at) {
+            Some(PromoRedeem::NotStarted)
+        } else if self.expires_at.is_some_and(|expires_at| now >= expires_at) {
+            Some(PromoRedeem::Expired)
+        } else if self.max_uses.is_some_and(|max_uses| self.uses >= max_uses) {
+            Some(PromoRedeem::Exhausted)
+        } else {
+            None
+        }
+    }
+}
+
+// One redemption in `promo_redemptions`, kept for fraud review. Its ledger
+// entries reference `promo:<redemption_id>`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromoRedemption {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub redemption_id: String,        // Snowflake id
+    pub code: String,
+    pub user_id: String,
+    pub device_id: Option<String>,
+    pub ip_address: Option<String>,
+    pub reward: PromoReward,
+    pub redeemed_at: DateTime,
+}
+
+// Outcome of a promo code redemption
+#[derive(Debug, Clone, PartialEq)]
+pub enum PromoRedeem {
+    Redeemed { reward: PromoReward, balance: i64, held: Vec<(String, i64)> },  // New balance and quantity of each item
+    NotFound,                                               // Unknown or disabled
+    NotStarted,
+    Expired,
+    Exhausted,                                              // max_uses reached
+    UserLimitReached { limit: u32 },
+}
+
+// Audit record of a support impersonation in `impersonation_sessions`: an
+// operator watching the emits sent to one user's sockets
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImpersonationSession {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub session_id: String,           // Snowflake id
+    pub operator_id: String,
+    pub user_id: String,
+    pub reason: String,
+    pub disclosed: bool,              // Whether the user was told with support:session
+    pub started_at: DateTime,
+    pub expires_at: DateTime,
+    pub ended_at: Option<DateTime>,
+    pub end_reason: Option<String>,   // "stopped", "expired" or "operator_disconnected"
+    pub events_mirrored: u64,
+}
+
+// A gameplay entry judged against the restricted states, in `compliance_audit`
+// (see ComplianceManager)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComplianceAudit {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: String,              // The player_id sent with the event
+    pub socket_id: String,
+    pub event: String,                // e.g. "matchmaking:join"
+    pub outcome: String,              // "blocked", or "overridden" when COMPLIANCE_ALLOWED_USERS let a restricted user in
+    pub reason: String,               // "profile_state", "geoip_state" or "blocked_user"
+    pub profile_state: Option<String>,
+    pub ip_country: Option<String>,
+    pub ip_state: Option<String>,     // ISO 3166-2 code after the country, e.g. "TG"
+    pub created_at: DateTime,
+}
+
+// A player found idle in a running match, in `afk_events` (see AfkManager)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AfkEvent {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: String,
+    pub room_id: String,
+    pub game_type: String,
+    pub action: String,               // "warned", then "forfeited" or "replaced" (seat taken by a bot)
+    pub idle_secs: i64,               // Time the match had waited on the player
+    pub created_at: DateTime,
+}
+
+// Concurrent sessions of one tenant on one server during a minute, in
+// `session_metrics` (see SessionMetricsManager)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSample {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub node_id: u64,                 // SNOWFLAKE_NODE_ID of the server
+    pub minute: DateTime,             // Start of the minute
+    pub sessions: u64,                // Users with an authenticated socket at the end of the minute
+    pub peak_sessions: u64,           // Most at once during the minute
+    pub connections: u64,             // Sockets on the main namespace at the end of the minute
+}
+
+// One document of a user's support timeline (see database::timeline)
+#[derive(Debug, Clone)]
+pub struct TimelineEntry {
+    pub kind: String,                 // e.g. login, error, match, wallet
+    pub source: String,               // Collection it was read from
+    pub at: DateTime,
+    pub details: serde_json::Value,   // The document's other fields, credentials removed
+}
+
+// One side of a friendship in `friendships`: user_id added friend_user_id.
+// Two users are friends once both have added each other.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Friendship {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: String,
+    pub friend_user_id: String,
+    pub created_at: DateTime,
+}
+
+// A completed gift between friends in `gifts`: either coins or an item
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Gift {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub gift_id: String,              // UUID v7; ledger references derive from it
+    pub from_user_id: String,
+    pub to_user_id: String,
+    pub coins: Option<i64>,
+    pub item_id: Option<String>,
+    pub quantity: i64,                // Items given; 0 for coin gifts
+    pub created_at: DateTime,
+}
+
+// Daily gift limits of the sender, per UTC day
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GiftLimits {
+    pub gifts: usize,
+    pub coins: i64,
+}
+
+// Outcome of sending a gift
+#[derive(Debug, Clone, PartialEq)]
+pub enum GiftSend {
+    Sent(i64),                                                  // Sender's new balance, or what they have left of the item
+    InsufficientBalance,
+    InsufficientItems,
+    SpendLimitReached { spent_today: i64, daily_limit: i64 },   // Parental daily limit
+    DailyLimitReached { gifts_sent: usize, coins_sent: i64 },   // GiftLimits of the sender
+}
+
+// Coins paid at the end of a season to ranks up to `max_rank` (and below
+// the previous tier's max_rank)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeasonReward {
+    pub max_rank: u32,
+    pub coins: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SeasonStatus {
+    Scheduled,
+    Active,
+    Finalizing,                       // Ended; rewards are being paid out
+    Ended,
+}
+
+// A ranked season in `seasons`. The rollover job activates it at start_at and
+// pays its rewards after end_at.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Season {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub season_id: String,            // UUID v7
+    pub name: String,
+    pub start_at: DateTime,
+    pub end_at: DateTime,
+    pub rewards: Vec<SeasonReward>,   // Sorted by max_rank
+    pub status: SeasonStatus,
+    pub created_by: String,           // operator_id
+    pub created_at: DateTime,
+    pub ended_at: Option<DateTime>,   // When rewards finished paying out
+}
+
+// A player's rating in one season, in `season_ratings`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeasonRating {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub season_id: String,
+    pub user_id: String,
+    pub rating: i64,
+    #[serde(default)]
+    pub wins: i64,
+    #[serde(default)]
+    pub losses: i64,
+    #[serde(default)]
+    pub draws: i64,
+    pub updated_at: DateTime,
+}
+
+// A player joining a gameplay room, in `match_participants`. Feeds the
+// anomaly scan's view of who plays whom.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchParticipant {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub room_id: String,
+    pub user_id: String,              // The room's player_id
+    pub ip_address: Option<String>,
+    pub joined_at: DateTime,
+}
+
+// A result reported with progress:update for a known room, in `match_results`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchResult {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub room_id: String,
+    pub user_id: String,
+    pub outcome: GameOutcome,
+    pub reported_at: DateTime,
+}
+
+// One pattern the anomaly scan found for a user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiskSignal {
+    pub rule: String,                 // same_ip_opponents, win_trading, match_dumping, gift_dumping
+    pub counterparty: String,         // The other user_id in the pattern
+    pub weight: u32,
+    pub details: serde_json::Value,
+}
+
+// A user's latest risk score in `user_risk`, rewritten by every scan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserRisk {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: String,
+    pub score: u32,                   // 0-100
+    pub signals: Vec<RiskSignal>,
+    pub limited: bool,                // Score reached RISK_LIMIT_SCORE
+    pub updated_at: DateTime,
+}
+
+// An entry in the admin review queue (`risk_flags`). Only one open flag
+// exists per `flag_key`; a dismissed key is no longer scored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiskFlag {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub flag_id: String,              // UUID v7
+    pub flag_key: String,             // "<rule>:<user_id>:<counterparty>"
+    pub user_id: String,
+    pub signal: RiskSignal,
+    pub score: u32,                   // The user's score when flagged
+    pub status: String,               // open, confirmed, dismissed
+    pub created_at: DateTime,
+    pub resolved_by: Option<String>,  // operator_id
+    pub resolved_at: Option<DateTime>,
+}
+
+// user_id blocked blocked_user_id, in `user_blocks`. A block works both ways.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserBlock {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: String,
+    pub blocked_user_id: String,
+    pub created_at: DateTime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportCategory {
+    Cheating,
+    Harassment,
+    HateSpeech,
+    Spam,
+    InappropriateName,
+    Other,
+}
+
+// A user:report in the moderation queue (`user_reports`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserReport {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub report_id: String,            // UUID v7
+    pub reporter_user_id: String,
+    pub reported_user_id: String,
+    pub category: ReportCategory,
+    pub description: Option<String>,
+    pub room_id: Option<String>,      // Gameplay room the report is about, if any
+    pub status: String,               // open, actioned or dismissed
+    pub created_at: DateTime,
+    #[serde(default)]
+    pub resolution: Option<String>,   // The action taken: warn, mute, ban or dismiss
+    #[serde(default)]
+    pub resolved_by: Option<String>,  // operator_id
+    #[serde(default)]
+    pub resolved_at: Option<DateTime>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SanctionKind {
+    Warn,
+    Mute,                             // No chat until expires_at
+    Ban,                              // No login until expires_at
+}
+
+// A moderator action against a user, in `user_sanctions`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserSanction {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub sanction_id: String,          // UUID v7
+    pub user_id: String,
+    pub kind: SanctionKind,
+    pub reason: String,
+    pub report_id: Option<String>,    // The report that led to it, if any
+    pub operator_id: String,
+    pub created_at: DateTime,
+    pub expires_at: Option<DateTime>, // Mutes and bans only
+    pub lifted_by: Option<String>,    // Set when an operator ends it early
+    pub lifted_at: Option<DateTime>,
+}
+
+// A room chat message, in `chat_messages`. Deleting one leaves a tombstone:
+// the text is cleared and deleted_at set; the original is in the audit log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub message_id: String,           // Snowflake id (UUID v7 before), so ids sort by send time
+    pub room_id: String,
+    pub user_id: String,
+    pub message: String,
+    pub created_at: DateTime,
+    pub edited_at: Option<DateTime>,
+    pub deleted_at: Option<DateTime>,
+    #[serde(default, skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    pub reactions: std::collections::BTreeMap<String, i64>, // Players per reaction (see ChatManager::REACTIONS)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<ChatAttachment>,
+}
+
+// An image sent with a chat message; the client uploads it to one of
+// CHAT_IMAGE_HOSTS and sends the link
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatAttachment {
+    pub url: String,
+    pub content_type: String,         // image/png, image/jpeg, image/gif or image/webp
+    pub size_bytes: i64,
+}
+
+// One player's reaction to a chat message, in `chat_reactions`; unique per
+// message, player and reaction so the counters on the message count players
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatReaction {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub message_id: String,
+    pub room_id: String,
+    pub user_id: String,
+    pub reaction: String,
+    pub created_at: DateTime,
+}
+
+// The newest message a player has read in a room, in `chat_read_receipts`:
+// one document per player per room, so a receipt covers every earlier message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatReadReceipt {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub room_id: String,
+    pub user_id: String,
+    pub message_id: String,           // Only ever moves forward
+    pub read_at: DateTime,            // Last time the player reported reading
+}
+
+// An edit or delete of a chat message, in `chat_message_audit`, kept for moderation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessageAudit {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub message_id: String,
+    pub room_id: String,
+    pub user_id: String,
+    pub action: String,               // "edit" or "delete"
+    pub previous_message: String,
+    pub new_message: Option<String>,  // Edits only
+    pub created_at: DateTime,
+}
+
+// One version of the rules of a game type, in `game_configs`. Versions are
+// never changed: every update inserts the next one, and a retired version
+// sends the game type back to the built-in rules. Rooms keep the version they
+// were created with until the match ends.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameConfig {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub game_type: String,
+    pub version: u32,                 // Starts at 1; 0 is the built-in rules, which are never stored
+    pub rules: GameRules,
+    pub retired: bool,
+    pub updated_by: String,           // Operator id
+    pub created_at: DateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GameRules {
+    pub entry_fee: i64,               // Coins, announced in match:found
+    pub turn_timeout_secs: i64,       // Time a player has to act before their turn is skipped
+    pub stall_timeout_threshold: u32, // Consecutive timeouts after which a player is flagged as stalling
+    pub room_capacity: u32,           // Players an ad-hoc room needs before the turn loop starts
+    pub bot_fallback_secs: u64,       // Matchmaking wait before bots take the empty seats
+    #[serde(default)]
+    pub board: serde_json::Value,     // Board parameters, passed through to clients as-is
+}
+
+// OTP handed to the SMS/email gateway; the gateway worker marks it sent
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OtpDeliveryRequest {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    pub mobile_no: String,
+    pub email: Option<String>,
+    pub channel: String,              // "sms" or "email"
+    pub otp: i32,
+    pub status: String,               // "pending" until the gateway delivers it
+    pub expires_at: DateTime,
+    pub created_at: DateTime,
+}
+
+// Audit trail for logins that used the static test OTP
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TestOtpAuditEvent {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    pub socket_id: String,
+    pub mobile_no: String,
+    pub device_id: Option<String>,
+    pub action: String,               // "login" or "verify"
+    pub is_success: bool,
+    pub timestamp: DateTime,
+}
+
+// Person allowed on the admin surface. Their API token is
+// "<operator_id>.<secret>"; the role decides what they may do (see managers::rbac).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdminOperator {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub operator_id: String,
+    pub name: String,
+    pub role: String,                 // "admin", "support" or "moderator"
+    pub secret: String,
+    pub created_by: String,           // operator_id of the admin who added them
+    pub created_at: DateTime,
+    pub updated_at: DateTime,
+}
+
+// Audit trail for admin requests refused for lack of permission
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AdminAccessDeniedEvent {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<ObjectId>,
-    pub socket_id: String,
-    pub user_id: String,              // UUID v7
-    pub user_number: u64,             // Sequential number
-    pub mobile_no: String,
-    pub device_id: String,
-    pub fcm_token: String,
-    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    pub operator_id: String,
+    pub role: String,
+    pub permission: String,
+    pub target: String,               // REST route or socket event
     pub timestamp: DateTime,
 }
 
+// An HTTP request or handshake refused before it reached Socket.IO, kept in a
+// capped collection so probing or misconfigured clients can be looked up
 #[derive(Debug, Serialize, Deserialize)]
-pub struct UserProfileEvent {
+pub struct RejectedHandshakeEvent {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<ObjectId>,
-    pub socket_id: String,
-    pub user_id: String,              // UUID v7
-    pub user_number: u64,             // Sequential number
-    pub mobile_no: String,
-    pub full_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    pub reason: String,               // Why it was refused, e.g. "not_socket_io"
+    pub status: u16,                  // HTTP status sent back
+    pub method: String,
+    pub path: String,
+    pub ip_address: Option<String>,   // Client address, past TRUSTED_PROXIES
+    pub user_agent: Option<String>,
+    pub origin: Option<String>,
     pub timestamp: DateTime,
 }
 
+// Delivery channels for a single notification category
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct ChannelPreference {
+    pub push: bool,
+    pub inbox: bool,
+}
+
+// Per-category notification preferences stored in `user_preferences`.
+// Categories missing from a stored document fall back to their defaults.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationPreferences {
+    pub match_updates: ChannelPreference,
+    pub turn_reminders: ChannelPreference,
+    pub party_invites: ChannelPreference,
+    pub social: ChannelPreference,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
-pub struct LanguageSettingEvent {
+pub struct UserPreferences {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<ObjectId>,
-    pub socket_id: String,
-    pub user_id: String,              // UUID v7
-    pub user_number: u64,             // Sequential number
+    pub user_id: String,
     pub mobile_no: String,
-    pub language_code: String,
-    pub language_name: String,
-    pub region_code: Option<String>,
-    pub timezone: Option<String>,
-    pub user_preferences: serde_json::Value,
-    pub timestamp: DateTime,
+    #[serde(default)]
+    pub notifications: NotificationPreferences,
+    pub updated_at: DateTime,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct User {
+pub struct InboxNotification {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<ObjectId>,
-    pub user_id: String,           // UUID v7
-    pub user_number: u64,          // Sequential number
-    pub mobile_no: String,
-    pub device_id: String,
-    pub fcm_token: String,
-    pub email: Option<String>,
-    pub full_name: Option<String>,
-    pub language_code: Option<String>,
-    pub language_name: Option<String>,
-    pub region_code: Option<String>,
-    pub timezone: Option<String>,
-    pub user_preferences: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    pub notification_id: String,      // UUID v7
+    pub user_id: String,
+    pub category: String,
+    pub title: String,
+    pub body: String,
+    pub data: serde_json::Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<DeepLink>,     // In-app screen a tap opens
+    pub show_in_inbox: bool,          // False when the user opted out of the inbox for this category
+    pub push_status: Option<String>,  // "pending" when queued for push delivery, then "sent" or "failed" with FCM; None when opted out
+    pub read: bool,
+    pub created_at: DateTime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutboxStatus {
+    Pending,        // Waiting for its first or next attempt
+    Delivered,
+    Failed,         // Gave up after OUTBOX_MAX_ATTEMPTS, or refused for good
+}
+
+// A side effect outside MongoDB (a push, a webhook, a Kafka record), stored
+// in `outbox` in the same transaction as the write it belongs to and
+// delivered by the Outbox dispatcher
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxMessage {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub message_id: String,           // Snowflake; sent with every attempt so receivers can drop repeats
+    pub destination: String,          // "fcm", "webhook" or "kafka"
+    pub topic: String,                // e.g. "push" or "promo.redeemed"
+    pub key: String,                  // Whose it is, usually a user_id; the Kafka record key
+    pub payload: serde_json::Value,
+    pub status: OutboxStatus,
+    pub attempts: u32,
+    pub next_attempt_at: DateTime,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lease_owner: Option<String>,  // Dispatcher delivering it right now
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lease_until: Option<DateTime>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
     pub created_at: DateTime,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delivered_at: Option<DateTime>,
+}
+
+// Tap action of a notification: a registered action and the ids it needs
+// (see DeepLinkManager)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeepLink {
+    pub action: String,
+    pub params: serde_json::Value,
+}
+
+impl DeepLink {
+    pub fn new(action: &str, params: serde_json::Value) -> Self {
+        Self { action: action.to_string(), params }
+    }
+}
+
+// Admin-edited text of one notification type in one language, stored in
+// `notification_templates`. `{name}` placeholders in the title and body are
+// filled from the parameters of each notification.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationTemplate {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub notification_type: String,
+    pub language_code: String,        // Lowercase, e.g. "hi" or "pt-br"
+    pub title: String,
+    pub body: String,
+    pub updated_by: String,           // Operator id
     pub updated_at: DateTime,
-    pub last_login_at: Option<DateTime>,
-    pub is_active: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct LoginSession {
+// Authenticated session, created when the OTP is verified
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UserSession {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<ObjectId>,
-    pub session_id: String,        // UUID v7
-    pub user_id: String,           // UUID v7
-    pub user_number: u64,          // Sequential number
+    pub session_token: String,
     pub mobile_no: String,
     pub device_id: String,
-    pub fcm_token: String,
-    pub session_token: String,
-    pub otp: String,
-    pub is_verified: bool,
-    pub jwt_token: Option<String>, // JWT token after OTP verification
     pub created_at: DateTime,
-    pub expires_at: DateTime,
-    pub verified_at: Option<DateTime>,
+    pub last_active_at: DateTime,
+    pub idle_expires_at: DateTime,      // Pushed forward on every authenticated call
+    pub absolute_expires_at: DateTime,  // Hard limit - renewal never goes past this
+    pub revoked: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct UserRegister {
+// A device a user has logged in from; removing it revokes its sessions
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UserDevice {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<ObjectId>,
-    pub user_id: String,           // UUID v7
-    pub user_number: u64,          // Sequential number
     pub mobile_no: String,
     pub device_id: String,
-    pub fcm_token: String,
-    pub email: Option<String>,
-    pub full_name: Option<String>,
-    pub state: Option<String>,
-    pub referral_code: Option<String>,
-    pub referred_by: Option<String>,
-    pub language_code: Option<String>,
-    pub language_name: Option<String>,
-    pub region_code: Option<String>,
-    pub timezone: Option<String>,
-    pub profile_data: Option<serde_json::Value>,
-    pub user_preferences: Option<serde_json::Value>,
+    pub device_type: Option<String>,
+    pub manufacturer: Option<String>,
+    pub model: Option<String>,
+    pub firmware_version: Option<String>,
+    pub first_seen_at: DateTime,
+    pub last_seen_at: DateTime,
+    pub removed_at: Option<DateTime>,
+}
+
+// A requested account:change_mobile, in `mobile_changes`. Both numbers get an
+// OTP and both must be confirmed before the account moves.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MobileChange {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub change_id: String,            // Snowflake id
+    pub user_id: String,
+    pub old_mobile_no: String,
+    pub new_mobile_no: String,
+    pub old_otp: i32,
+    pub new_otp: i32,
+    pub attempts: u32,                // Confirmations tried so far
+    pub max_attempts: u32,
     pub created_at: DateTime,
-    pub updated_at: DateTime,
-    pub last_login_at: Option<DateTime>,
-    pub total_logins: i32,         // Total number of logins
-    pub is_active: bool,
+    pub expires_at: DateTime,
+    pub completed_at: Option<DateTime>,
+}
+
+// A completed mobile number change, in `mobile_change_audit`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MobileChangeAudit {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    pub change_id: Option<String>,    // The confirmed MobileChange; None for admin overrides
+    pub user_id: String,
+    pub old_mobile_no: String,
+    pub new_mobile_no: String,
+    pub method: String,               // "otp" or "admin"
+    pub operator_id: Option<String>,  // Admin who made the change
+    pub reason: Option<String>,
+    pub changed_at: DateTime,
 }
 
 // OTP verification result enum
@@ -196,16 +1377,36 @@ pub enum OtpVerificationResult {
     NotFound,   // No login session found
 }
 
+// Session check result
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SessionStatus {
+    Valid,      // Session is active (and has been renewed)
+    Expired,    // Idle or absolute expiry passed
+    Invalid,    // Unknown token, revoked, or wrong mobile number
+}
+
+impl SessionStatus {
+    // Error code and message sent to clients for a rejected session
+    pub fn error_details(&self) -> (&'static str, &'static str) {
+        match self {
+            SessionStatus::Expired => ("SESSION_EXPIRED", "Session expired. Please login again."),
+            _ => ("INVALID_SESSION", "Invalid session. Please login again."),
+        }
+    }
+}
+
 // Helper functions for creating new instances
 impl ConnectEvent {
-    pub fn new(socket_id: String, token: i32, message: String, status: String) -> Self {
+    pub fn new(socket_id: String, token: i32, message: String, status: String, ip_address: Option<String>) -> Self {
         Self {
             id: None,
+            request_id: Correlation::current(),
             socket_id,
             timestamp: DateTime::from_millis(Utc::now().timestamp_millis()),
             token,
             message,
             status,
+            ip_address,
         }
     }
 }
@@ -214,6 +1415,7 @@ impl DeviceInfoEvent {
     pub fn new(socket_id: String, device_info: serde_json::Value) -> Self {
         Self {
             id: None,
+            request_id: Correlation::current(),
             socket_id,
             device_info,
             timestamp: DateTime::from_millis(Utc::now().timestamp_millis()),
@@ -225,6 +1427,7 @@ impl ConnectionErrorEvent {
     pub fn new(socket_id: String, error_code: String, error_type: String, field: String, message: String, payload: bson::Document) -> Self {
         Self {
             id: None,
+            request_id: Correlation::current(),
             socket_id,
             error_code,
             error_type,
@@ -240,6 +1443,7 @@ impl LoginEvent {
     pub fn new(socket_id: String, mobile_no: String, device_id: String, fcm_token: String) -> Self {
         Self {
             id: None,
+            request_id: Correlation::current(),
             socket_id,
             timestamp: DateTime::from_millis(Utc::now().timestamp_millis()),
             mobile_no,
@@ -251,16 +1455,19 @@ impl LoginEvent {
 }
 
 impl LoginSuccessEvent {
-    pub fn new(socket_id: String, mobile_no: String, device_id: String, session_token: String, otp: i32) -> Self {
+    pub fn new(socket_id: String, mobile_no: String, device_id: String, session_token: String, otp: i32, otp_expiry_secs: i64) -> Self {
         Self {
             id: None,
+            request_id: Correlation::current(),
             socket_id,
             timestamp: DateTime::from_millis(Utc::now().timestamp_millis()),
             mobile_no,
             device_id,
             session_token,
             otp,
-            expires_at: DateTime::from_millis(Utc::now().timestamp_millis() + (30 * 60 * 1000)), // 30 minutes
+            expires_at: DateTime::from_millis(Utc::now().timestamp_millis() + otp_expiry_secs * 1000),
+            is_new_user: false,
+            verified_at: None,
         }
     }
 }
@@ -269,6 +1476,7 @@ impl OtpVerificationEvent {
     pub fn new(socket_id: String, mobile_no: String, session_token: String, otp: String, is_success: bool, user_id: Option<String>, user_number: Option<u64>) -> Self {
         Self {
             id: None,
+            request_id: Correlation::current(),
             socket_id,
             timestamp: DateTime::from_millis(Utc::now().timestamp_millis()),
             mobile_no,
@@ -287,6 +1495,7 @@ impl UserRegistrationEvent {
         let now = DateTime::from_millis(Utc::now().timestamp_millis());
         Self {
             id: None,
+            request_id: Correlation::current(),
             socket_id,
             user_id: Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext)).to_string(),
             user_number: 1,
@@ -303,6 +1512,7 @@ impl UserProfileEvent {
     pub fn new(socket_id: String, mobile_no: String, full_name: String) -> Self {
         Self {
             id: None,
+            request_id: Correlation::current(),
             socket_id,
             user_id: Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext)).to_string(),
             user_number: 1,
@@ -317,6 +1527,7 @@ impl LanguageSettingEvent {
     pub fn new(socket_id: String, mobile_no: String, language_code: String, language_name: String, region_code: Option<String>, timezone: Option<String>, user_preferences: serde_json::Value) -> Self {
         Self {
             id: None,
+            request_id: Correlation::current(),
             socket_id,
             user_id: Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext)).to_string(),
             user_number: 1,
@@ -405,6 +1616,244 @@ impl LoginSession {
     }
 }
 
+// Which users an admin search or export includes; unset fields match everyone
+#[derive(Debug, Default, Clone)]
+pub struct UserFilter {
+    pub text: Option<String>,   // Mobile number prefix if all digits, otherwise full name prefix (case-insensitive)
+    pub is_active: Option<bool>,
+    pub is_banned: Option<bool>,
+    pub state: Option<String>,
+    pub language_code: Option<String>,
+    pub created_after: Option<chrono::DateTime<Utc>>,
+    pub created_before: Option<chrono::DateTime<Utc>>,
+}
+
+impl UserFilter {
+    // Anchored prefix regexes, so mobile_no and full_name lookups can use their indexes
+    pub fn to_document(&self) -> bson::Document {
+        let mut filter = bson::doc! {};
+        if let Some(text) = self.text.as_deref().map(str::trim).filter(|t| !t.is_empty()) {
+            if text.chars().all(|c| c.is_ascii_digit()) {
+                filter.insert("mobile_no", bson::Regex { pattern: format!("^{}", text), options: String::new() });
+            } else {
+                filter.insert("full_name", bson::Regex { pattern: format!("^{}", escape_regex(text)), options: "i".to_string() });
+            }
+        }
+        if let Some(is_active) = self.is_active {
+            filter.insert("is_active", is_active);
+        }
+        if let Some(is_banned) = self.is_banned {
+            // Users stored before bans existed have no is_banned field
+            filter.insert("is_banned", if is_banned { bson::Bson::Boolean(true) } else { bson::doc! { "$ne": true }.into() });
+        }
+        if let Some(state) = &self.state {
+            filter.insert("state", state);
+        }
+        if let Some(language_code) = &self.language_code {
+            filter.insert("language_code", language_code);
+        }
+        let mut created_at = bson::doc! {};
+        if let Some(after) = self.created_after {
+            created_at.insert("$gte", DateTime::from_millis(after.timestamp_millis()));
+        }
+        if let Some(before) = self.created_before {
+            created_at.insert("$lt", DateTime::from_millis(before.timestamp_millis()));
+        }
+        if !created_at.is_empty() {
+            filter.insert("created_at", created_at);
+        }
+        filter
+    }
+
+    pub fn matches(&self, user: &UserRegister) -> bool {
+        let created_at = user.created_at.timestamp_millis();
+        let text_matches = match self.text.as_deref().map(str::trim).filter(|t| !t.is_empty()) {
+            Some(text) if text.chars().all(|c| c.is_ascii_digit()) => user.mobile_no.starts_with(text),
+            Some(text) => user.full_name.as_ref().is_some_and(|name| name.to_lowercase().starts_with(&text.to_lowercase())),
+            None => true,
+        };
+        text_matches
+            && self.is_active.is_none_or(|active| user.is_active == active)
+            && self.is_banned.is_none_or(|banned| user.is_banned == banned)
+            && self.state.as_ref().is_none_or(|state| user.state.as_ref() == Some(state))
+            && self.language_code.as_ref().is_none_or(|code| user.language_code.as_ref() == Some(code))
+            && self.created_after.is_none_or(|after| created_at >= after.timestamp_millis())
+            && self.created_before.is_none_or(|before| created_at < before.timestamp_millis())
+    }
+}
+
+fn escape_regex(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\^$.|?*+()[]{}".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// Connection error field the analytics endpoints group counts by
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorGrouping {
+    ErrorCode,
+    ErrorType,
+    Field,
+}
+
+impl ErrorGrouping {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "error_code" => Some(ErrorGrouping::ErrorCode),
+            "error_type" => Some(ErrorGrouping::ErrorType),
+            "field" => Some(ErrorGrouping::Field),
+            _ => None,
+        }
+    }
+
+    // Document field, which is also its name in the query string
+    pub fn field(self) -> &'static str {
+        match self {
+            ErrorGrouping::ErrorCode => "error_code",
+            ErrorGrouping::ErrorType => "error_type",
+            ErrorGrouping::Field => "field",
+        }
+    }
+
+    pub fn key_of(self, event: &ConnectionErrorEvent) -> &str {
+        match self {
+            ErrorGrouping::ErrorCode => &event.error_code,
+            ErrorGrouping::ErrorType => &event.error_type,
+            ErrorGrouping::Field => &event.field,
+        }
+    }
+}
+
+// Connection errors in [from, to), counted per `group_by` value and time bucket
+#[derive(Debug, Clone)]
+pub struct ErrorStatsQuery {
+    pub from: chrono::DateTime<Utc>,
+    pub to: chrono::DateTime<Utc>,
+    pub group_by: ErrorGrouping,
+    pub bucket_ms: i64,     // Buckets are aligned to the Unix epoch, so hours and days start on the UTC boundary
+}
+
+impl ErrorStatsQuery {
+    pub fn bucket_start(&self, timestamp_ms: i64) -> i64 {
+        timestamp_ms - timestamp_ms.rem_euclid(self.bucket_ms)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ErrorCountBucket {
+    pub bucket_start: i64,  // Unix millis
+    pub key: String,
+    pub count: u64,
+}
+
+// Errors from sockets that reported the same device. Fields are None when the
+// socket never sent device:info or left them out.
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorOffender {
+    pub device_id: Option<String>,
+    pub manufacturer: Option<String>,
+    pub model: Option<String>,
+    pub app_version: Option<String>,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AppVersionErrors {
+    pub app_version: Option<String>,
+    pub count: u64,
+}
+
+// Devices and app versions with the most connection errors, most first
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ErrorOffenders {
+    pub devices: Vec<ErrorOffender>,
+    pub app_versions: Vec<AppVersionErrors>,
+}
+
+pub const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+// 1970-01-05, the first Monday after the Unix epoch
+pub const FIRST_MONDAY_MS: i64 = 4 * DAY_MS;
+
+// Start (Unix millis) of the UTC week, Monday to Sunday, holding `timestamp_ms`
+pub fn week_start_ms(timestamp_ms: i64) -> i64 {
+    timestamp_ms - (timestamp_ms - FIRST_MONDAY_MS).rem_euclid(7 * DAY_MS)
+}
+
+// Users of a cohort who completed a login N days after registering. Only
+// users registered at least N + 1 days ago are eligible.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionDay {
+    pub day: u32,
+    pub eligible: u64,
+    pub returned: u64,
+    pub rate: Option<f64>,            // returned / eligible; None while no user is eligible
+}
+
+impl RetentionDay {
+    pub fn new(day: u32, eligible: u64, returned: u64) -> Self {
+        let rate = (eligible > 0).then(|| (returned as f64 / eligible as f64 * 10000.0).round() / 10000.0);
+        Self { day, eligible, returned, rate }
+    }
+}
+
+// Users who registered in one UTC week
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionCohort {
+    pub week_start: String,           // Monday, YYYY-MM-DD
+    pub users: u64,
+    pub days: Vec<RetentionDay>,
+}
+
+impl RetentionCohort {
+    // week_start of the week starting at `week_start_ms`
+    pub fn week_label(week_start_ms: i64) -> String {
+        chrono::DateTime::from_timestamp_millis(week_start_ms).unwrap_or_default().format("%Y-%m-%d").to_string()
+    }
+}
+
+// Cohort retention as computed once a day, in `retention_reports`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionReport {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub report_date: String,          // UTC date it was computed for, YYYY-MM-DD
+    pub weeks: u32,                   // Registration weeks covered, the current one included
+    pub cohorts: Vec<RetentionCohort>, // Oldest week first
+    pub generated_at: DateTime,
+}
+
+impl From<&UserRegister> for UserIdentity {
+    fn from(user: &UserRegister) -> Self {
+        Self {
+            user_id: user.user_id.clone(),
+            user_number: user.user_number,
+        }
+    }
+}
+
+impl From<&UserRegister> for UserProfile {
+    fn from(user: &UserRegister) -> Self {
+        Self {
+            user_id: user.user_id.clone(),
+            user_number: user.user_number,
+            full_name: user.full_name.clone(),
+            state: user.state.clone(),
+            referral_code: user.referral_code.clone(),
+            referred_by: user.referred_by.clone(),
+            language_code: user.language_code.clone(),
+            language_name: user.language_name.clone(),
+            region_code: user.region_code.clone(),
+            timezone: user.timezone.clone(),
+            profile_data: user.profile_data.clone(),
+        }
+    }
+}
+
 impl UserRegister {
     pub fn new(
         mobile_no: String,
@@ -437,6 +1886,10 @@ impl UserRegister {
             last_login_at: Some(now),
             total_logins: 0,
             is_active: true,
+            is_banned: false,
+            two_step: None,
+            date_of_birth: None,
+            parental_controls: None,
         }
     }
     
@@ -445,4 +1898,206 @@ impl UserRegister {
         self.last_login_at = Some(DateTime::from_millis(Utc::now().timestamp_millis()));
         self.updated_at = DateTime::from_millis(Utc::now().timestamp_millis());
     }
-} 
\ No newline at end of file
+} 
+
+impl Default for ChannelPreference {
+    fn default() -> Self {
+        Self { push: true, inbox: true }
+    }
+}
+
+impl Default for NotificationPreferences {
+    fn default() -> Self {
+        Self {
+            match_updates: ChannelPreference::default(),
+            turn_reminders: ChannelPreference::default(),
+            party_invites: ChannelPreference::default(),
+            social: ChannelPreference::default(),
+        }
+    }
+}
+
+impl NotificationPreferences {
+    pub const CATEGORIES: [&'static str; 4] = ["match_updates", "turn_reminders", "party_invites", "social"];
+
+    pub fn category(&self, name: &str) -> Option<ChannelPreference> {
+        match name {
+            "match_updates" => Some(self.match_updates),
+            "turn_reminders" => Some(self.turn_reminders),
+            "party_invites" => Some(self.party_invites),
+            "social" => Some(self.social),
+            _ => None,
+        }
+    }
+
+    pub fn category_mut(&mut self, name: &str) -> Option<&mut ChannelPreference> {
+        match name {
+            "match_updates" => Some(&mut self.match_updates),
+            "turn_reminders" => Some(&mut self.turn_reminders),
+            "party_invites" => Some(&mut self.party_invites),
+            "social" => Some(&mut self.social),
+            _ => None,
+        }
+    }
+
+    // Apply a partial update such as {"social": {"push": false}}.
+    // Expects data already checked by ValidationManager.
+    pub fn apply_update(&mut self, update: &serde_json::Value) {
+        let Some(categories) = update.as_object() else { return };
+        for (name, channels) in categories {
+            let Some(pref) = self.category_mut(name) else { continue };
+            if let Some(push) = channels.get("push").and_then(|v| v.as_bool()) {
+                pref.push = push;
+            }
+            if let Some(inbox) = channels.get("inbox").and_then(|v| v.as_bool()) {
+                pref.inbox = inbox;
+            }
+        }
+    }
+}
+
+impl GameOutcome {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "win" => Some(GameOutcome::Win),
+            "loss" => Some(GameOutcome::Loss),
+            "draw" => Some(GameOutcome::Draw),
+            _ => None,
+        }
+    }
+
+    // GameStats counter incremented by this outcome
+    pub fn counter(&self) -> &'static str {
+        match self {
+            GameOutcome::Win => "wins",
+            GameOutcome::Loss => "losses",
+            GameOutcome::Draw => "draws",
+        }
+    }
+}
+
+impl GameplayProgress {
+    pub fn first_level() -> u32 {
+        1
+    }
+
+    // Progress of a user with no stored document
+    pub fn new(user_id: String) -> Self {
+        let now = DateTime::now();
+        Self {
+            id: None,
+            user_id,
+            level: Self::first_level(),
+            xp: 0,
+            games: std::collections::HashMap::new(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    // Apply an update with the same semantics as the MongoDB upsert
+    pub fn apply(&mut self, update: &ProgressUpdate, now: DateTime) {
+        self.xp += update.xp;
+        self.level = self.level.max(update.level.unwrap_or(Self::first_level()));
+        if let Some(game) = &update.game {
+            let stats = self.games.entry(game.game_id.clone()).or_default();
+            stats.played += 1;
+            match game.outcome {
+                GameOutcome::Win => stats.wins += 1,
+                GameOutcome::Loss => stats.losses += 1,
+                GameOutcome::Draw => stats.draws += 1,
+            }
+            if let Some(score) = game.score {
+                stats.total_score += score;
+                stats.best_score = Some(stats.best_score.map_or(score, |best| best.max(score)));
+            }
+            stats.last_played_at = Some(now);
+        }
+        self.updated_at = now;
+    }
+}
+
+impl ChallengeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChallengeKind::PlayGames => "play_games",
+            ChallengeKind::WinGames => "win_games",
+            ChallengeKind::EarnXp => "earn_xp",
+            ChallengeKind::TakeTurns => "take_turns",
+        }
+    }
+}
+
+impl ReportCategory {
+    pub const ALL: [ReportCategory; 6] = [
+        ReportCategory::Cheating,
+        ReportCategory::Harassment,
+        ReportCategory::HateSpeech,
+        ReportCategory::Spam,
+        ReportCategory::InappropriateName,
+        ReportCategory::Other,
+    ];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|category| category.as_str() == value)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReportCategory::Cheating => "cheating",
+            ReportCategory::Harassment => "harassment",
+            ReportCategory::HateSpeech => "hate_speech",
+            ReportCategory::Spam => "spam",
+            ReportCategory::InappropriateName => "inappropriate_name",
+            ReportCategory::Other => "other",
+        }
+    }
+}
+
+impl SanctionKind {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "warn" => Some(SanctionKind::Warn),
+            "mute" => Some(SanctionKind::Mute),
+            "ban" => Some(SanctionKind::Ban),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SanctionKind::Warn => "warn",
+            SanctionKind::Mute => "mute",
+            SanctionKind::Ban => "ban",
+        }
+    }
+}
+
+impl UserSanction {
+    // A mute or ban that has neither expired nor been lifted
+    pub fn is_active(&self, now: DateTime) -> bool {
+        self.lifted_at.is_none() && self.expires_at.is_some_and(|expires_at| expires_at > now)
+    }
+}
+
+impl SeasonStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SeasonStatus::Scheduled => "scheduled",
+            SeasonStatus::Active => "active",
+            SeasonStatus::Finalizing => "finalizing",
+            SeasonStatus::Ended => "ended",
+        }
+    }
+}
+
+impl Season {
+    // Coins earned by finishing at `rank` (1-based), if any
+    pub fn reward_for(&self, rank: u32) -> Option<i64> {
+        self.rewards.iter().find(|tier| rank <= tier.max_rank).map(|tier| tier.coins)
+    }
+
+    pub fn rewarded_ranks(&self) -> u32 {
+        self.rewards.iter().map(|tier| tier.max_rank).max().unwrap_or(0)
+    }
+}
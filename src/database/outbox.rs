@@ -0,0 +1,373 @@
+use bson::{doc, Document};
+use chrono::Utc;
+use futures_util::TryStreamExt;
+use hmac::{Hmac, Mac};
+use mongodb::options::{FindOneAndUpdateOptions, FindOptions, ReturnDocument};
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::time::Duration;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::config::CONFIG;
+use crate::database::google_auth::ServiceAccount;
+use crate::database::models::{InboxNotification, OutboxMessage, OutboxStatus, PromoRedemption};
+use crate::database::DatabaseManager;
+use crate::managers::scheduler::Scheduler;
+use crate::managers::snowflake::Snowflake;
+
+const OUTBOX: &str = "outbox";
+const HTTP_TIMEOUT_SECS: u64 = 30;
+// A message being delivered is left alone by other dispatchers for this long;
+// longer than any request, so only a crashed dispatcher's lease runs out
+const LEASE_SECS: i64 = 120;
+// Wait after a failed attempt doubles from this, up to MAX_RETRY_SECS
+const RETRY_BASE_SECS: i64 = 5;
+const MAX_RETRY_SECS: i64 = 3600;
+const FCM_SCOPE: &str = "https://www.googleapis.com/auth/firebase.messaging";
+
+type OutboxResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+// Where outbox messages go, from the FCM_* and OUTBOX_* variables
+struct Destinations {
+    fcm: Option<(String, ServiceAccount)>,      // Project id and its service account
+    webhook_url: Option<String>,
+    webhook_secret: Option<String>,
+    kafka_rest_url: Option<String>,
+}
+
+// Why an attempt did not deliver a message
+enum Failure {
+    Retry(String),      // Worth trying again later
+    Refused(String),    // Will never succeed, e.g. the push token is gone
+}
+
+static DESTINATIONS: Lazy<Destinations> = Lazy::new(Outbox::load);
+static HTTP: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(HTTP_TIMEOUT_SECS))
+        .build()
+        .unwrap_or_default()
+});
+// Holder of the leases taken by this server
+static OWNER: Lazy<String> = Lazy::new(|| Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext)).to_string());
+
+// Transactional outbox for side effects outside MongoDB. A domain write that
+// needs a push (FCM), a webhook or a Kafka record stores an OutboxMessage in
+// `outbox` in the same transaction, so the side effect exists exactly when the
+// write does. Every OUTBOX_INTERVAL_SECS the dispatcher leases due messages,
+// delivers them and marks them delivered; a crash between delivery and the
+// mark only means the message is delivered again once its lease runs out.
+// Every attempt carries the message_id (Idempotency-Key header, Kafka record
+// value, FCM collapse key), so receivers drop the repeat. Failed attempts are
+// retried with a doubling wait; after OUTBOX_MAX_ATTEMPTS, or when the
+// receiver refuses the message for good, it is marked failed and can be
+// retried through the admin API.
+pub struct Outbox;
+
+impl Outbox {
+    fn load() -> Destinations {
+        let var = |key: &str| std::env::var(key).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        let fcm = match (var("FCM_PROJECT_ID"), var("FCM_CREDENTIALS_FILE")) {
+            (Some(project), Some(credentials)) => match ServiceAccount::load(&credentials) {
+                Ok(account) => Some((project, account)),
+                Err(e) => {
+                    error!("❌ Cannot read FCM service account key {} - push delivery disabled: {}", credentials, e);
+                    None
+                }
+            },
+            (None, None) => None,
+            _ => {
+                error!("❌ Push delivery needs both FCM_PROJECT_ID and FCM_CREDENTIALS_FILE - disabled");
+                None
+            }
+        };
+        Destinations {
+            fcm,
+            webhook_url: var("OUTBOX_WEBHOOK_URL"),
+            webhook_secret: var("OUTBOX_WEBHOOK_SECRET"),
+            kafka_rest_url: var("OUTBOX_KAFKA_REST_URL").map(|url| url.trim_end_matches('/').to_string()),
+        }
+    }
+
+    fn message(destination: &str, topic: &str, key: &str, payload: Value) -> OutboxMessage {
+        let now = bson::DateTime::now();
+        OutboxMessage {
+            id: None,
+            message_id: Snowflake::generate(),
+            destination: destination.to_string(),
+            topic: topic.to_string(),
+            key: key.to_string(),
+            payload,
+            status: OutboxStatus::Pending,
+            attempts: 0,
+            next_attempt_at: now,
+            lease_owner: None,
+            lease_until: None,
+            last_error: None,
+            created_at: now,
+            delivered_at: None,
+        }
+    }
+
+    // The push of an inbox notification; None without FCM, when it is left
+    // pending for an external sender as before
+    pub fn push(notification: &InboxNotification) -> Option<OutboxMessage> {
+        DESTINATIONS.fcm.as_ref()?;
+        Some(Self::message("fcm", "push", &notification.user_id, json!({
+            "notification_id": notification.notification_id,
+            "category": notification.category,
+            "title": notification.title,
+            "body": notification.body,
+            "data": notification.data,
+            "action": notification.action,
+        })))
+    }
+
+    // A domain event, one message for each of the webhook and Kafka that are set up
+    pub fn event(topic: &str, key: &str, payload: Value) -> Vec<OutboxMessage> {
+        let mut messages = Vec::new();
+        if DESTINATIONS.webhook_url.is_some() {
+            messages.push(Self::message("webhook", topic, key, payload.clone()));
+        }
+        if DESTINATIONS.kafka_rest_url.is_some() {
+            messages.push(Self::message("kafka", topic, key, payload));
+        }
+        messages
+    }
+
+    // promo.redeemed, for the CRM and fraud review
+    pub fn promo_redeemed(redemption: &PromoRedemption) -> Vec<OutboxMessage> {
+        Self::event("promo.redeemed", &redemption.user_id, json!({
+            "redemption_id": redemption.redemption_id,
+            "code": redemption.code,
+            "user_id": redemption.user_id,
+            "device_id": redemption.device_id,
+            "reward": redemption.reward,
+            "redeemed_at": redemption.redeemed_at.try_to_rfc3339_string().unwrap_or_default(),
+        }))
+    }
+
+    pub fn spawn_dispatcher() {
+        let destinations = &*DESTINATIONS;
+        if CONFIG.in_memory_store || (destinations.fcm.is_none() && destinations.webhook_url.is_none() && destinations.kafka_rest_url.is_none()) {
+            return;
+        }
+        let period = Duration::from_secs(CONFIG.outbox_interval_secs);
+        Scheduler::every("outbox-dispatcher", period, || async {
+            for _ in 0..CONFIG.outbox_batch_size {
+                let Some(message) = Self::claim().await? else { break };
+                Self::dispatch(message).await?;
+            }
+            Ok(())
+        });
+    }
+
+    // Lease the next due message of the tenant in scope
+    async fn claim() -> OutboxResult<Option<OutboxMessage>> {
+        let now = Utc::now();
+        let filter = doc! {
+            "status": "pending",
+            "next_attempt_at": { "$lte": bson::DateTime::from_millis(now.timestamp_millis()) },
+            "$or": [
+                { "lease_until": { "$exists": false } },
+                { "lease_until": { "$lt": bson::DateTime::from_millis(now.timestamp_millis()) } },
+            ],
+        };
+        let lease_until = now + chrono::Duration::seconds(LEASE_SECS);
+        let update = doc! {
+            "$set": { "lease_owner": OWNER.as_str(), "lease_until": bson::DateTime::from_millis(lease_until.timestamp_millis()) },
+            "$inc": { "attempts": 1 },
+        };
+        let options = FindOneAndUpdateOptions::builder()
+            .sort(doc! { "next_attempt_at": 1 })
+            .return_document(ReturnDocument::After)
+            .build();
+        Ok(DatabaseManager::collection::<OutboxMessage>(OUTBOX).find_one_and_update(filter, update, options).await?)
+    }
+
+    // Deliver a leased message and record how it went
+    async fn dispatch(message: OutboxMessage) -> OutboxResult<()> {
+        let outcome = match message.destination.as_str() {
+            "fcm" => Self::deliver_fcm(&message).await,
+            "webhook" => Self::deliver_webhook(&message).await,
+            "kafka" => Self::deliver_kafka(&message).await,
+            other => Err(Failure::Refused(format!("Unknown destination {}", other))),
+        };
+        let now = bson::DateTime::now();
+        let leased = doc! { "message_id": &message.message_id, "lease_owner": OWNER.as_str() };
+        let (update, status) = match &outcome {
+            Ok(()) => (doc! {
+                "$set": { "status": "delivered", "delivered_at": now },
+                "$unset": { "lease_owner": "", "lease_until": "", "last_error": "" },
+            }, "delivered"),
+            Err(Failure::Retry(e)) if message.attempts < CONFIG.outbox_max_attempts => {
+                let wait = (RETRY_BASE_SECS << message.attempts.saturating_sub(1).min(20)).min(MAX_RETRY_SECS);
+                let next_attempt_at = bson::DateTime::from_millis(now.timestamp_millis() + wait * 1000);
+                warn!("⚠️ Outbox {} message {} failed (attempt {}) - retrying in {}s: {}", message.destination, message.message_id, message.attempts, wait, e);
+                (doc! {
+                    "$set": { "next_attempt_at": next_attempt_at, "last_error": e },
+                    "$unset": { "lease_owner": "", "lease_until": "" },
+                }, "pending")
+            }
+            Err(Failure::Retry(e) | Failure::Refused(e)) => {
+                error!("❌ Outbox {} message {} ({}) failed for good after {} attempt(s): {}", message.destination, message.message_id, message.topic, message.attempts, e);
+                (doc! {
+                    "$set": { "status": "failed", "last_error": e },
+                    "$unset": { "lease_owner": "", "lease_until": "" },
+                }, "failed")
+            }
+        };
+        DatabaseManager::collection::<Document>(OUTBOX).update_one(leased, update, None).await?;
+        if outcome.is_ok() {
+            info!("📮 Delivered outbox {} message {} ({})", message.destination, message.message_id, message.topic);
+        }
+
+        // The inbox shows how the push went
+        if message.topic == "push" && status != "pending" {
+            let push_status = if status == "delivered" { "sent" } else { "failed" };
+            let notification_id = message.payload["notification_id"].as_str().unwrap_or_default();
+            DatabaseManager::collection::<Document>("notification_inbox")
+                .update_one(doc! { "notification_id": notification_id }, doc! { "$set": { "push_status": push_status } }, None)
+                .await?;
+        }
+        Ok(())
+    }
+
+    // 408, 429 and 5xx are worth another attempt; other errors will not go away
+    fn failure(status: reqwest::StatusCode, error: String) -> Failure {
+        if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS || status == reqwest::StatusCode::REQUEST_TIMEOUT {
+            Failure::Retry(error)
+        } else {
+            Failure::Refused(error)
+        }
+    }
+
+    async fn deliver_fcm(message: &OutboxMessage) -> Result<(), Failure> {
+        let Some((project, account)) = DESTINATIONS.fcm.as_ref() else {
+            return Err(Failure::Retry("FCM is not configured on this server".to_string()));
+        };
+        let user = DatabaseManager::collection::<Document>("userregister")
+            .find_one(doc! { "user_id": &message.key }, None)
+            .await
+            .map_err(|e| Failure::Retry(e.to_string()))?;
+        let Some(token) = user.as_ref().and_then(|user| user.get_str("fcm_token").ok()).filter(|token| !token.is_empty()) else {
+            return Err(Failure::Refused(format!("User {} has no FCM token", message.key)));
+        };
+        let access_token = account.access_token(FCM_SCOPE).await.map_err(Failure::Retry)?;
+
+        // FCM data values must be strings; the collapse key makes a repeat replace the first on the device
+        let payload = &message.payload;
+        let mut data = json!({
+            "message_id": message.message_id,
+            "notification_id": payload["notification_id"],
+            "category": payload["category"],
+            "data": payload["data"].to_string(),
+        });
+        if !payload["action"].is_null() {
+            data["action"] = json!(payload["action"].to_string());
+        }
+        let body = json!({
+            "message": {
+                "token": token,
+                "notification": { "title": payload["title"], "body": payload["body"] },
+                "data": data,
+                "android": { "collapse_key": message.message_id },
+                "apns": { "headers": { "apns-collapse-id": message.message_id } },
+            }
+        });
+        let url = format!("https://fcm.googleapis.com/v1/projects/{}/messages:send", project);
+        let response = HTTP.post(url).bearer_auth(access_token).json(&body).send().await.map_err(|e| Failure::Retry(e.to_string()))?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        let answer: Value = response.json().await.unwrap_or(Value::Null);
+        Err(Self::failure(status, format!("FCM answered {}: {}", status, answer["error"]["message"].as_str().unwrap_or(""))))
+    }
+
+    fn envelope(message: &OutboxMessage) -> Value {
+        json!({
+            "message_id": message.message_id,
+            "topic": message.topic,
+            "key": message.key,
+            "payload": message.payload,
+            "created_at": message.created_at.try_to_rfc3339_string().unwrap_or_default(),
+        })
+    }
+
+    // POST the message as JSON; with OUTBOX_WEBHOOK_SECRET the body is signed
+    // in X-Signature (hex HMAC-SHA256)
+    async fn deliver_webhook(message: &OutboxMessage) -> Result<(), Failure> {
+        let Some(url) = DESTINATIONS.webhook_url.as_deref() else {
+            return Err(Failure::Retry("OUTBOX_WEBHOOK_URL is not set on this server".to_string()));
+        };
+        let body = Self::envelope(message).to_string();
+        let mut request = HTTP.post(url)
+            .header("Content-Type", "application/json")
+            .header("Idempotency-Key", &message.message_id);
+        if let Some(secret) = DESTINATIONS.webhook_secret.as_deref() {
+            let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).map_err(|e| Failure::Refused(e.to_string()))?;
+            mac.update(body.as_bytes());
+            let signature: String = mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect();
+            request = request.header("X-Signature", format!("sha256={}", signature));
+        }
+        let response = request.body(body).send().await.map_err(|e| Failure::Retry(e.to_string()))?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        Err(Self::failure(status, format!("Webhook answered {}", status)))
+    }
+
+    // One record to topic `topic` through a Kafka REST proxy (v2 API), keyed by `key`
+    async fn deliver_kafka(message: &OutboxMessage) -> Result<(), Failure> {
+        let Some(url) = DESTINATIONS.kafka_rest_url.as_deref() else {
+            return Err(Failure::Retry("OUTBOX_KAFKA_REST_URL is not set on this server".to_string()));
+        };
+        let body = json!({ "records": [{ "key": message.key, "value": Self::envelope(message) }] });
+        let response = HTTP.post(format!("{}/topics/{}", url, message.topic))
+            .header("Content-Type", "application/vnd.kafka.json.v2+json")
+            .body(body.to_string())
+            .send()
+            .await
+            .map_err(|e| Failure::Retry(e.to_string()))?;
+        let status = response.status();
+        let answer: Value = response.json().await.unwrap_or(Value::Null);
+        if !status.is_success() {
+            return Err(Self::failure(status, format!("Kafka REST proxy answered {}: {}", status, answer["message"].as_str().unwrap_or(""))));
+        }
+        // The proxy answers 200 and reports a record it could not write in its offset
+        match answer["offsets"][0]["error"].as_str() {
+            Some(e) => Err(Failure::Retry(format!("Kafka did not take the record: {}", e))),
+            None => Ok(()),
+        }
+    }
+
+    // Messages of the tenant in scope, newest first
+    pub async fn list(status: Option<OutboxStatus>, page: u64, page_size: i64) -> OutboxResult<Vec<OutboxMessage>> {
+        let filter = match status {
+            Some(status) => doc! { "status": bson::to_bson(&status)? },
+            None => doc! {},
+        };
+        let options = FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .skip(page * page_size.max(0) as u64)
+            .limit(page_size)
+            .build();
+        Ok(DatabaseManager::collection::<OutboxMessage>(OUTBOX).find(filter, options).await?.try_collect().await?)
+    }
+
+    // Put a failed message back in line; false when there is no failed message with this id
+    pub async fn retry(message_id: &str) -> OutboxResult<bool> {
+        let update = doc! {
+            "$set": { "status": "pending", "attempts": 0, "next_attempt_at": bson::DateTime::now() },
+            "$unset": { "last_error": "" },
+        };
+        let result = DatabaseManager::collection::<Document>(OUTBOX)
+            .update_one(doc! { "message_id": message_id, "status": "failed" }, update, None)
+            .await?;
+        Ok(result.modified_count > 0)
+    }
+}
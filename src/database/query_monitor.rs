@@ -0,0 +1,228 @@
+use bson::{doc, Bson, Document};
+use futures_util::TryStreamExt;
+use mongodb::event::command::{CommandEventHandler, CommandFailedEvent, CommandStartedEvent, CommandSucceededEvent};
+use mongodb::Database;
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::config::CONFIG;
+use crate::database::DatabaseManager;
+
+// Commands that read or write documents; the rest (hello, ping, ...) are not timed
+const QUERY_COMMANDS: &[&str] = &["find", "aggregate", "count", "distinct", "update", "delete", "findAndModify", "insert"];
+// Distinct slow query shapes remembered for the index report
+const MAX_SLOW_SHAPES: usize = 200;
+// Slow query shapes explained by one index report, slowest first
+const MAX_EXPLAINED_SHAPES: usize = 50;
+
+// Query of a command that has not finished yet
+struct PendingQuery {
+    db: String,
+    collection: String,
+    command: String,
+    filter: Document,
+}
+
+// Slow queries of one collection, command and filter shape
+#[derive(Clone)]
+struct SlowShape {
+    db: String,
+    collection: String,
+    command: String,
+    shape: Bson,
+    sample: Document,       // Last filter seen, kept in memory only, to explain
+    count: u64,
+    max_ms: u64,
+}
+
+static PENDING: Lazy<Mutex<HashMap<i32, PendingQuery>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static SLOW: Lazy<Mutex<HashMap<String, SlowShape>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// Times every query the repositories and services send, through the driver's
+// command monitoring. Queries slower than SLOW_QUERY_MS are logged with the
+// shape of their filter (field names and operators, values replaced by "?",
+// so phone numbers and tokens stay out of the logs) and remembered for the
+// index report.
+pub struct QueryMonitor;
+
+impl QueryMonitor {
+    fn threshold() -> Option<Duration> {
+        (CONFIG.slow_query_ms > 0).then(|| Duration::from_millis(CONFIG.slow_query_ms))
+    }
+
+    // The document filter of a command; for aggregations the first $match
+    fn filter(command_name: &str, command: &Document) -> Document {
+        let first = |list: &str, key: &str| command.get_array(list).ok()
+            .and_then(|items| items.first())
+            .and_then(|item| item.as_document())
+            .and_then(|item| item.get_document(key).ok())
+            .cloned();
+        let filter = match command_name {
+            "find" => command.get_document("filter").ok().cloned(),
+            "delete" => first("deletes", "q"),
+            "update" => first("updates", "q"),
+            "count" | "distinct" | "findAndModify" => command.get_document("query").ok().cloned(),
+            "aggregate" => command.get_array("pipeline").ok()
+                .and_then(|stages| stages.iter().filter_map(|stage| stage.as_document()).find_map(|stage| stage.get_document("$match").ok()))
+                .cloned(),
+            _ => None,
+        };
+        filter.unwrap_or_default()
+    }
+
+    // Field names and operators of a filter with every value replaced by "?"
+    fn shape(value: &Bson) -> Bson {
+        match value {
+            Bson::Document(document) => Bson::Document(document.iter().map(|(key, value)| (key.clone(), Self::shape(value))).collect()),
+            Bson::Array(items) => Bson::Array(items.first().map(Self::shape).into_iter().collect()),
+            _ => Bson::String("?".to_string()),
+        }
+    }
+
+    fn finished(request_id: i32, duration: Duration) {
+        let Some(query) = PENDING.lock().unwrap_or_else(|e| e.into_inner()).remove(&request_id) else {
+            return;
+        };
+        if Self::threshold().is_none_or(|threshold| duration < threshold) {
+            return;
+        }
+        let ms = duration.as_millis() as u64;
+        let shape = Self::shape(&Bson::Document(query.filter.clone()));
+        warn!("🐢 Slow query: {} on {}.{} took {}ms, filter {}", query.command, query.db, query.collection, ms, shape);
+
+        let key = format!("{}|{}|{}|{}", query.db, query.collection, query.command, shape);
+        let mut slow = SLOW.lock().unwrap_or_else(|e| e.into_inner());
+        if !slow.contains_key(&key) && slow.len() >= MAX_SLOW_SHAPES {
+            return;
+        }
+        let entry = slow.entry(key).or_insert_with(|| SlowShape {
+            db: query.db,
+            collection: query.collection,
+            command: query.command,
+            shape,
+            sample: Document::new(),
+            count: 0,
+            max_ms: 0,
+        });
+        entry.sample = query.filter;
+        entry.count += 1;
+        entry.max_ms = entry.max_ms.max(ms);
+    }
+
+    // Whether a query plan reads the whole collection
+    fn plan_scans_collection(plan: &Document) -> bool {
+        if plan.get_str("stage") == Ok("COLLSCAN") {
+            return true;
+        }
+        let child = plan.get_document("inputStage").ok().is_some_and(Self::plan_scans_collection);
+        let children = plan.get_array("inputStages").ok().is_some_and(|stages| {
+            stages.iter().filter_map(|stage| stage.as_document()).any(Self::plan_scans_collection)
+        });
+        child || children
+    }
+
+    // Indexes of one collection never used since the server (re)started
+    async fn unused_indexes(database: &Database, collection: &str) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
+        let stats: Vec<Document> = database.collection::<Document>(collection)
+            .aggregate(vec![doc! { "$indexStats": {} }], None).await?
+            .try_collect().await?;
+        Ok(stats.iter()
+            .filter(|index| index.get_str("name").is_ok_and(|name| name != "_id_"))
+            .filter(|index| index.get_document("accesses").ok().and_then(|a| a.get("ops")).and_then(Bson::as_i64).unwrap_or(0) == 0)
+            .map(|index| json!({
+                "collection": collection,
+                "index": index.get_str("name").unwrap_or_default(),
+                "keys": index.get_document("key").map(|key| Bson::Document(key.clone()).into_relaxed_extjson()).unwrap_or(Value::Null),
+                "since": index.get_document("accesses").ok().and_then(|a| a.get_datetime("since").ok()).map(|since| since.to_string()),
+            }))
+            .collect())
+    }
+
+    // Index usage of the current tenant's collections: indexes with no
+    // recorded use (`$indexStats`, counted since the MongoDB server started)
+    // and the slow query shapes seen by this server whose plan scans the
+    // whole collection (`explain`), i.e. that lack an index
+    pub async fn index_report() -> Result<Value, Box<dyn std::error::Error + Send + Sync>> {
+        let database = DatabaseManager::get_database();
+        let mut collections: Vec<String> = database.list_collection_names(None).await?
+            .into_iter()
+            .filter(|name| name.starts_with(&CONFIG.collection_prefix) && !name.starts_with("system."))
+            .collect();
+        collections.sort();
+
+        let mut unused = Vec::new();
+        for collection in &collections {
+            match Self::unused_indexes(database, collection).await {
+                Ok(indexes) => unused.extend(indexes),
+                Err(e) => warn!("⚠️ Failed to read index stats of {}: {}", collection, e),
+            }
+        }
+
+        let mut shapes: Vec<SlowShape> = SLOW.lock().unwrap_or_else(|e| e.into_inner())
+            .values()
+            .filter(|slow| slow.db == database.name() && slow.command != "insert")
+            .cloned()
+            .collect();
+        shapes.sort_by_key(|slow| std::cmp::Reverse(slow.max_ms));
+
+        let mut missing = Vec::new();
+        for slow in shapes.into_iter().take(MAX_EXPLAINED_SHAPES) {
+            let explain = doc! { "explain": { "find": &slow.collection, "filter": slow.sample }, "verbosity": "queryPlanner" };
+            let plan = match database.run_command(explain, None).await {
+                Ok(result) => result,
+                Err(e) => {
+                    warn!("⚠️ Failed to explain a slow query on {}: {}", slow.collection, e);
+                    continue;
+                }
+            };
+            let scans = plan.get_document("queryPlanner").ok()
+                .and_then(|planner| planner.get_document("winningPlan").ok())
+                .is_some_and(Self::plan_scans_collection);
+            if scans {
+                missing.push(json!({
+                    "collection": slow.collection,
+                    "command": slow.command,
+                    "filter": slow.shape.into_relaxed_extjson(),
+                    "slow_count": slow.count,
+                    "max_ms": slow.max_ms,
+                }));
+            }
+        }
+
+        Ok(json!({
+            "database": database.name(),
+            "collections": collections,
+            "slow_query_ms": CONFIG.slow_query_ms,
+            "unused_indexes": unused,
+            "missing_indexes": missing,
+        }))
+    }
+}
+
+impl CommandEventHandler for QueryMonitor {
+    fn handle_command_started_event(&self, event: CommandStartedEvent) {
+        if Self::threshold().is_none() || !QUERY_COMMANDS.contains(&event.command_name.as_str()) {
+            return;
+        }
+        let collection = event.command.get_str(&event.command_name).unwrap_or_default().to_string();
+        let query = PendingQuery {
+            filter: Self::filter(&event.command_name, &event.command),
+            db: event.db,
+            collection,
+            command: event.command_name,
+        };
+        PENDING.lock().unwrap_or_else(|e| e.into_inner()).insert(event.request_id, query);
+    }
+
+    fn handle_command_succeeded_event(&self, event: CommandSucceededEvent) {
+        Self::finished(event.request_id, event.duration);
+    }
+
+    fn handle_command_failed_event(&self, event: CommandFailedEvent) {
+        Self::finished(event.request_id, event.duration);
+    }
+}
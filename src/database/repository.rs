@@ -1,7 +1,18 @@
-use mongodb::{Collection, bson::{doc, oid::ObjectId, DateTime, to_bson}};
-use tracing::info;
+use mongodb::{Collection, bson::{doc, oid::ObjectId, Bson, DateTime, Document, to_bson}};
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::{info, warn};
 use futures_util::TryStreamExt;
+use std::marker::PhantomData;
 use crate::database::{DatabaseManager, models::*};
+use crate::database::dead_letter::EventDeadLetters;
+use crate::database::envelope::{EventEnvelope, EventRegistry, EventSchema};
+use crate::database::health::DatabaseHealth;
+use crate::database::write_queue::WriteQueue;
+use crate::managers::tenant::TenantManager;
+use crate::managers::token::TokenGenerator;
+
+// Newest sessions per mobile number considered when matching a session token
+const MAX_SESSIONS_SCANNED: i64 = 50;
 
 // Helper function to safely convert inserted_id to ObjectId
 fn safe_object_id_conversion(inserted_id: mongodb::bson::Bson) -> Result<ObjectId, Box<dyn std::error::Error + Send + Sync>> {
@@ -9,229 +20,1025 @@ fn safe_object_id_conversion(inserted_id: mongodb::bson::Bson) -> Result<ObjectI
         .ok_or_else(|| Box::new(std::io::Error::new(std::io::ErrorKind::InvalidData, "Failed to get ObjectId from inserted document")) as Box<dyn std::error::Error + Send + Sync>)
 }
 
-// Separate repositories for each event type
-pub struct ConnectEventRepository {
-    collection: Collection<ConnectEvent>,
+// A model stored in its own MongoDB collection
+pub trait MongoDocument: Serialize + DeserializeOwned + Unpin + Send + Sync {
+    const COLLECTION: &'static str;
 }
 
-pub struct DeviceInfoEventRepository {
-    collection: Collection<DeviceInfoEvent>,
-}
+impl MongoDocument for ConnectEvent { const COLLECTION: &'static str = "connect_events"; }
+impl MongoDocument for DeviceInfoEvent { const COLLECTION: &'static str = "device_info_events"; }
+impl MongoDocument for ConnectionErrorEvent { const COLLECTION: &'static str = "connection_error_events"; }
+impl MongoDocument for LoginEvent { const COLLECTION: &'static str = "login_events"; }
+impl MongoDocument for LoginSuccessEvent { const COLLECTION: &'static str = "login_success_events"; }
+impl MongoDocument for OtpVerificationEvent { const COLLECTION: &'static str = "otp_verification_events"; }
+impl MongoDocument for LanguageSettingEvent { const COLLECTION: &'static str = "language_setting_events"; }
+impl MongoDocument for UserProfileEvent { const COLLECTION: &'static str = "user_profile_events"; }
+impl MongoDocument for UserRegister { const COLLECTION: &'static str = "userregister"; }
+impl MongoDocument for UserSession { const COLLECTION: &'static str = "sessions"; }
+impl MongoDocument for UserRegistrationEvent { const COLLECTION: &'static str = "user_registration_events"; }
+impl MongoDocument for TurnTimingEvent { const COLLECTION: &'static str = "turn_timing_events"; }
+impl MongoDocument for TestOtpAuditEvent { const COLLECTION: &'static str = "test_otp_audit_events"; }
+impl MongoDocument for AdminOperator { const COLLECTION: &'static str = "admin_operators"; }
+impl MongoDocument for AdminAccessDeniedEvent { const COLLECTION: &'static str = "admin_access_denied_events"; }
+impl MongoDocument for RejectedHandshakeEvent { const COLLECTION: &'static str = "rejected_handshakes"; }
+impl MongoDocument for RoomSnapshot { const COLLECTION: &'static str = "room_snapshots"; }
+impl MongoDocument for ArchivedRoom { const COLLECTION: &'static str = "archived_rooms"; }
+impl MongoDocument for MatchEscrow { const COLLECTION: &'static str = "match_escrows"; }
+impl MongoDocument for RevenueEntry { const COLLECTION: &'static str = "revenue_ledger"; }
+impl MongoDocument for RevenueRollup { const COLLECTION: &'static str = "revenue_rollups"; }
+impl MongoDocument for PromoCode { const COLLECTION: &'static str = "promo_codes"; }
+impl MongoDocument for PromoRedemption { const COLLECTION: &'static str = "promo_redemptions"; }
+impl MongoDocument for ImpersonationSession { const COLLECTION: &'static str = "impersonation_sessions"; }
+impl MongoDocument for DailyChallengeSet { const COLLECTION: &'static str = "daily_challenges"; }
+impl MongoDocument for ChallengeProgress { const COLLECTION: &'static str = "challenge_progress"; }
+impl MongoDocument for Season { const COLLECTION: &'static str = "seasons"; }
+impl MongoDocument for SeasonRating { const COLLECTION: &'static str = "season_ratings"; }
+impl MongoDocument for Friendship { const COLLECTION: &'static str = "friendships"; }
+impl MongoDocument for Gift { const COLLECTION: &'static str = "gifts"; }
+impl MongoDocument for MatchParticipant { const COLLECTION: &'static str = "match_participants"; }
+impl MongoDocument for MatchResult { const COLLECTION: &'static str = "match_results"; }
+impl MongoDocument for UserRisk { const COLLECTION: &'static str = "user_risk"; }
+impl MongoDocument for RiskFlag { const COLLECTION: &'static str = "risk_flags"; }
+impl MongoDocument for UserBlock { const COLLECTION: &'static str = "user_blocks"; }
+impl MongoDocument for UserReport { const COLLECTION: &'static str = "user_reports"; }
+impl MongoDocument for UserSanction { const COLLECTION: &'static str = "user_sanctions"; }
+impl MongoDocument for ChatMessage { const COLLECTION: &'static str = "chat_messages"; }
+impl MongoDocument for ChatMessageAudit { const COLLECTION: &'static str = "chat_message_audit"; }
+impl MongoDocument for ChatReaction { const COLLECTION: &'static str = "chat_reactions"; }
+impl MongoDocument for GameConfig { const COLLECTION: &'static str = "game_configs"; }
 
-pub struct ConnectionErrorEventRepository {
-    collection: Collection<ConnectionErrorEvent>,
+// A subset of a model's fields, read with a MongoDB projection
+pub trait Projection: DeserializeOwned + Unpin + Send + Sync {
+    const FIELDS: &'static [&'static str];
 }
 
-pub struct LoginEventRepository {
-    collection: Collection<LoginEvent>,
+impl Projection for UserIdentity { const FIELDS: &'static [&'static str] = &["user_id", "user_number"]; }
+impl Projection for UserProfile {
+    const FIELDS: &'static [&'static str] = &[
+        "user_id", "user_number", "full_name", "state", "referral_code", "referred_by",
+        "language_code", "language_name", "region_code", "timezone", "profile_data",
+    ];
 }
 
-pub struct LoginSuccessEventRepository {
-    collection: Collection<LoginSuccessEvent>,
+// Operations shared by every collection. Collection-specific queries live in
+// `impl MongoRepository<Model>` blocks below.
+pub struct MongoRepository<T: MongoDocument> {
+    name: String,       // T::COLLECTION with COLLECTION_PREFIX applied
+    model: PhantomData<T>,
 }
 
-pub struct OtpVerificationEventRepository {
-    collection: Collection<OtpVerificationEvent>,
+impl<T: MongoDocument> MongoRepository<T> {
+    pub fn new() -> Self {
+        Self { name: DatabaseManager::collection_name(T::COLLECTION), model: PhantomData }
+    }
+
+    // The collection in the database of the tenant in scope
+    fn collection(&self) -> Collection<T> {
+        DatabaseManager::get_database().collection::<T>(&self.name)
+    }
+
+    pub async fn insert(&self, document: &T) -> Result<ObjectId, Box<dyn std::error::Error + Send + Sync>> {
+        let result = self.collection().insert_one(document, None).await?;
+        info!("🗄️ Stored {} document with ID: {}", T::COLLECTION, result.inserted_id);
+        safe_object_id_conversion(result.inserted_id)
+    }
+
+    pub async fn find_one(&self, filter: Document) -> Result<Option<T>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.collection().find_one(filter, None).await?)
+    }
+
+    // Only the fields of `P` are sent by the server
+    pub async fn find_one_projected<P: Projection>(&self, filter: Document) -> Result<Option<P>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut projection: Document = P::FIELDS.iter().map(|field| (field.to_string(), Bson::Int32(1))).collect();
+        projection.insert("_id", 0);
+        let options = mongodb::options::FindOneOptions::builder().projection(projection).build();
+        Ok(self.collection().clone_with_type::<P>().find_one(filter, options).await?)
+    }
+
+    pub async fn count(&self, filter: Document) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.collection().count_documents(filter, None).await?)
+    }
+
+    // All matching documents in `sort` order, fetched from the server in batches
+    pub async fn find_stream(&self, filter: Document, sort: Document) -> Result<mongodb::Cursor<T>, Box<dyn std::error::Error + Send + Sync>> {
+        let options = mongodb::options::FindOptions::builder().sort(sort).build();
+        Ok(self.collection().find(filter, options).await?)
+    }
+
+    // One page of matching documents in `sort` order (pages start at 0)
+    pub async fn find_page(&self, filter: Document, sort: Document, page: u64, page_size: i64) -> Result<Vec<T>, Box<dyn std::error::Error + Send + Sync>> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(sort)
+            .skip(page * page_size.max(0) as u64)
+            .limit(page_size)
+            .build();
+        Ok(self.collection().find(filter, options).await?.try_collect().await?)
+    }
+
+    pub async fn aggregate(&self, pipeline: Vec<Document>) -> Result<Vec<Document>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.collection().aggregate(pipeline, None).await?.try_collect().await?)
+    }
 }
 
-pub struct LanguageSettingEventRepository {
-    collection: Collection<LanguageSettingEvent>,
+impl<T: EventSchema> MongoRepository<T> {
+    // Store an event wrapped in its EventEnvelope. While MongoDB is
+    // unreachable the write is buffered in the WriteQueue and still succeeds;
+    // one that can be neither stored nor buffered is parked with
+    // EventDeadLetters before the error is returned.
+    pub async fn insert_event(&self, event: T) -> Result<ObjectId, Box<dyn std::error::Error + Send + Sync>> {
+        let id = ObjectId::new();
+        let mut document = mongodb::bson::to_document(&EventEnvelope::wrap(event))?;
+        document.insert("_id", id);
+
+        // Skip MongoDB while it is known to be down or earlier writes are still queued
+        if (WriteQueue::is_buffering() || !DatabaseHealth::is_healthy()) && WriteQueue::enqueue(T::COLLECTION, document.clone()) {
+            return Ok(id);
+        }
+        match self.collection().clone_with_type::<Document>().insert_one(&document, None).await {
+            Ok(_) => {
+                info!("🗄️ Stored {} v{} event with ID: {}", T::EVENT_TYPE, T::SCHEMA_VERSION, id);
+                Ok(id)
+            }
+            Err(e) => {
+                if WriteQueue::is_transient(&e) && WriteQueue::enqueue(T::COLLECTION, document.clone()) {
+                    warn!("📥 Buffered {} event while MongoDB is unavailable: {}", T::EVENT_TYPE, e);
+                    return Ok(id);
+                }
+                EventDeadLetters::park(TenantManager::current(), T::COLLECTION, document, e.to_string());
+                Err(e.into())
+            }
+        }
+    }
+
+    // Events matching `filter`, validated against the schema registry. Documents
+    // that fail validation are logged and left out.
+    pub async fn find_events(&self, filter: Document, sort: Document, limit: i64) -> Result<Vec<EventEnvelope<T>>, Box<dyn std::error::Error + Send + Sync>> {
+        let options = mongodb::options::FindOptions::builder().sort(sort).limit(limit).build();
+        let documents: Vec<Document> = self.collection().clone_with_type::<Document>().find(filter, options).await?.try_collect().await?;
+        let mut events = Vec::with_capacity(documents.len());
+        for document in documents {
+            let id = document.get("_id").cloned();
+            let validated = EventRegistry::validate(T::COLLECTION, &document)
+                .and_then(|version| bson::from_document::<T>(document).map(|payload| (version, payload)).map_err(|e| e.to_string()));
+            match validated {
+                Ok((schema_version, payload)) => events.push(EventEnvelope {
+                    event_type: T::EVENT_TYPE.to_string(),
+                    schema_version,
+                    payload,
+                }),
+                Err(e) => warn!("⚠️ Skipping {} document {:?}: {}", T::COLLECTION, id, e),
+            }
+        }
+        Ok(events)
+    }
 }
 
-pub struct UserProfileEventRepository {
-    collection: Collection<UserProfileEvent>,
+pub type ConnectEventRepository = MongoRepository<ConnectEvent>;
+pub type DeviceInfoEventRepository = MongoRepository<DeviceInfoEvent>;
+pub type ConnectionErrorEventRepository = MongoRepository<ConnectionErrorEvent>;
+pub type LoginEventRepository = MongoRepository<LoginEvent>;
+pub type LoginSuccessEventRepository = MongoRepository<LoginSuccessEvent>;
+pub type OtpVerificationEventRepository = MongoRepository<OtpVerificationEvent>;
+pub type LanguageSettingEventRepository = MongoRepository<LanguageSettingEvent>;
+pub type UserProfileEventRepository = MongoRepository<UserProfileEvent>;
+pub type UserRegisterRepository = MongoRepository<UserRegister>;
+pub type SessionRepository = MongoRepository<UserSession>;
+pub type UserRegistrationEventRepository = MongoRepository<UserRegistrationEvent>;
+pub type TurnTimingEventRepository = MongoRepository<TurnTimingEvent>;
+pub type TestOtpAuditEventRepository = MongoRepository<TestOtpAuditEvent>;
+pub type AdminOperatorRepository = MongoRepository<AdminOperator>;
+pub type AdminAccessDeniedEventRepository = MongoRepository<AdminAccessDeniedEvent>;
+pub type RejectedHandshakeEventRepository = MongoRepository<RejectedHandshakeEvent>;
+pub type RoomSnapshotRepository = MongoRepository<RoomSnapshot>;
+pub type ArchivedRoomRepository = MongoRepository<ArchivedRoom>;
+pub type MatchEscrowRepository = MongoRepository<MatchEscrow>;
+pub type RevenueLedgerRepository = MongoRepository<RevenueEntry>;
+pub type RevenueRollupRepository = MongoRepository<RevenueRollup>;
+pub type PromoCodeRepository = MongoRepository<PromoCode>;
+pub type PromoRedemptionRepository = MongoRepository<PromoRedemption>;
+pub type ImpersonationSessionRepository = MongoRepository<ImpersonationSession>;
+pub type DailyChallengeRepository = MongoRepository<DailyChallengeSet>;
+pub type ChallengeProgressRepository = MongoRepository<ChallengeProgress>;
+pub type SeasonRepository = MongoRepository<Season>;
+pub type SeasonRatingRepository = MongoRepository<SeasonRating>;
+pub type FriendshipRepository = MongoRepository<Friendship>;
+pub type GiftRepository = MongoRepository<Gift>;
+pub type MatchParticipantRepository = MongoRepository<MatchParticipant>;
+pub type MatchResultRepository = MongoRepository<MatchResult>;
+pub type UserRiskRepository = MongoRepository<UserRisk>;
+pub type RiskFlagRepository = MongoRepository<RiskFlag>;
+pub type UserBlockRepository = MongoRepository<UserBlock>;
+pub type UserReportRepository = MongoRepository<UserReport>;
+pub type UserSanctionRepository = MongoRepository<UserSanction>;
+pub type ChatMessageRepository = MongoRepository<ChatMessage>;
+pub type ChatMessageAuditRepository = MongoRepository<ChatMessageAudit>;
+pub type ChatReactionRepository = MongoRepository<ChatReaction>;
+pub type GameConfigRepository = MongoRepository<GameConfig>;
+
+impl LoginSuccessEventRepository {
+    // Find login success event by mobile number and session token. The token is
+    // compared in constant time in-process rather than used as a query filter.
+    pub async fn find_login_success_by_mobile_and_session(&self, mobile_no: &str, session_token: &str) -> Result<Option<LoginSuccessEvent>, Box<dyn std::error::Error + Send + Sync>> {
+        let sessions = self.find_events(doc! { "mobile_no": mobile_no }, doc! { "timestamp": -1 }, MAX_SESSIONS_SCANNED).await?;
+        Ok(sessions.into_iter().map(|e| e.payload).find(|s| TokenGenerator::constant_time_eq(&s.session_token, session_token)))
+    }
+
+    // Find the newest unexpired, unverified session for a mobile number and device
+    pub async fn find_active_unverified_session(&self, mobile_no: &str, device_id: &str) -> Result<Option<LoginSuccessEvent>, Box<dyn std::error::Error + Send + Sync>> {
+        let filter = doc! {
+            "mobile_no": mobile_no,
+            "device_id": device_id,
+            "verified_at": null,
+            "expires_at": { "$gt": DateTime::from_millis(chrono::Utc::now().timestamp_millis()) }
+        };
+        let mut newest = self.find_events(filter, doc! { "timestamp": -1 }, 1).await?;
+        Ok(newest.pop().map(|e| e.payload))
+    }
+
+    // Point a reused session at the socket that retried the login
+    pub async fn update_session_socket(&self, mobile_no: &str, session_token: &str, socket_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let filter = doc! { "mobile_no": mobile_no, "session_token": session_token };
+        let update = doc! { "$set": { "socket_id": socket_id } };
+        self.collection().update_one(filter, update, None).await?;
+        Ok(())
+    }
+
+    // Mark a session as verified so it is no longer reused for login retries
+    pub async fn mark_session_verified(&self, mobile_no: &str, session_token: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let filter = doc! { "mobile_no": mobile_no, "session_token": session_token };
+        let update = doc! { "$set": { "verified_at": DateTime::from_millis(chrono::Utc::now().timestamp_millis()) } };
+        self.collection().update_one(filter, update, None).await?;
+        info!("✅ Marked session verified for mobile: {}", mobile_no);
+        Ok(())
+    }
 }
 
-pub struct UserRegisterRepository {
-    collection: Collection<UserRegister>,
+impl OtpVerificationEventRepository {
+    // Get OTP verification attempts count for a mobile number and session token
+    pub async fn get_verification_attempts_count(&self, mobile_no: &str, session_token: &str) -> Result<i32, Box<dyn std::error::Error + Send + Sync>> {
+        let count = self.count(doc! { "mobile_no": mobile_no, "session_token": session_token }).await?;
+        Ok(count as i32)
+    }
 }
 
-impl ConnectEventRepository {
-    pub fn new() -> Self {
-        let database = DatabaseManager::get_database();
-        let collection = database.collection::<ConnectEvent>("connect_events");
-        Self { collection }
+impl LanguageSettingEventRepository {
+    // Find language setting by mobile number and session token
+    pub async fn find_language_setting_by_mobile_and_session(&self, mobile_no: &str, session_token: &str) -> Result<Option<LanguageSettingEvent>, Box<dyn std::error::Error + Send + Sync>> {
+        self.find_one(doc! { "mobile_no": mobile_no, "session_token": session_token }).await
     }
-    
-    pub async fn store_connect_event(&self, event: ConnectEvent) -> Result<ObjectId, Box<dyn std::error::Error + Send + Sync>> {
-        let result = self.collection.insert_one(event, None).await?;
-        info!("🔌 Connect event stored with ID: {}", result.inserted_id);
-        safe_object_id_conversion(result.inserted_id)
+}
+
+impl UserProfileEventRepository {
+    // Find user profile by mobile number and session token
+    pub async fn find_user_profile_by_mobile_and_session(&self, mobile_no: &str, session_token: &str) -> Result<Option<UserProfileEvent>, Box<dyn std::error::Error + Send + Sync>> {
+        self.find_one(doc! { "mobile_no": mobile_no, "session_token": session_token }).await
     }
 }
 
-impl DeviceInfoEventRepository {
-    pub fn new() -> Self {
-        let database = DatabaseManager::get_database();
-        let collection = database.collection::<DeviceInfoEvent>("device_info_events");
-        Self { collection }
+impl SessionRepository {
+    // Find a session by mobile number, matching the token in constant time
+    pub async fn find_session(&self, mobile_no: &str, session_token: &str) -> Result<Option<UserSession>, Box<dyn std::error::Error + Send + Sync>> {
+        let sessions = self.find_page(doc! { "mobile_no": mobile_no }, doc! { "created_at": -1 }, 0, MAX_SESSIONS_SCANNED).await?;
+        Ok(sessions.into_iter().find(|s| TokenGenerator::constant_time_eq(&s.session_token, session_token)))
     }
-    
-    pub async fn store_device_info_event(&self, event: DeviceInfoEvent) -> Result<ObjectId, Box<dyn std::error::Error + Send + Sync>> {
-        let result = self.collection.insert_one(event, None).await?;
-        info!("📱 Device info event stored with ID: {}", result.inserted_id);
-        safe_object_id_conversion(result.inserted_id)
+
+    // Revoke every session of a user, e.g. when they are banned
+    pub async fn revoke_user_sessions(&self, mobile_no: &str) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        let update = doc! { "$set": { "revoked": true } };
+        let result = self.collection().update_many(doc! { "mobile_no": mobile_no, "revoked": false }, update, None).await?;
+        info!("🔒 Revoked {} session(s) for mobile: {}", result.modified_count, mobile_no);
+        Ok(result.modified_count)
+    }
+
+    // Revoke every session opened from a device
+    pub async fn revoke_device_sessions(&self, mobile_no: &str, device_id: &str) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        let filter = doc! { "mobile_no": mobile_no, "device_id": device_id, "revoked": false };
+        let update = doc! { "$set": { "revoked": true } };
+        let result = self.collection().update_many(filter, update, None).await?;
+        info!("🔒 Revoked {} session(s) for mobile: {} (device: {})", result.modified_count, mobile_no, device_id);
+        Ok(result.modified_count)
+    }
+
+    // Record activity and slide the idle expiry forward
+    pub async fn touch_session(&self, session_id: ObjectId, last_active_at: DateTime, idle_expires_at: DateTime) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let filter = doc! { "_id": session_id };
+        let update = doc! {
+            "$set": {
+                "last_active_at": last_active_at,
+                "idle_expires_at": idle_expires_at
+            }
+        };
+        self.collection().update_one(filter, update, None).await?;
+        Ok(())
     }
 }
 
+// $sum results are Int32 or Int64 depending on their size
+fn count_field(document: &Document) -> u64 {
+    int_field(document, "count")
+}
+
+fn int_field(document: &Document, key: &str) -> u64 {
+    match document.get(key) {
+        Some(Bson::Int32(count)) => *count as u64,
+        Some(Bson::Int64(count)) => *count as u64,
+        _ => 0,
+    }
+}
+
+fn optional_str(document: &Document, key: &str) -> Option<String> {
+    document.get_str(key).ok().map(|value| value.to_string())
+}
+
 impl ConnectionErrorEventRepository {
-    pub fn new() -> Self {
-        let database = DatabaseManager::get_database();
-        let collection = database.collection::<ConnectionErrorEvent>("connection_error_events");
-        Self { collection }
+    pub async fn count_by_bucket(&self, query: &ErrorStatsQuery) -> Result<Vec<ErrorCountBucket>, Box<dyn std::error::Error + Send + Sync>> {
+        let timestamp_ms = doc! { "$toLong": "$timestamp" };
+        let pipeline = vec![
+            doc! { "$match": {
+                "timestamp": { "$gte": DateTime::from_millis(query.from.timestamp_millis()), "$lt": DateTime::from_millis(query.to.timestamp_millis()) }
+            } },
+            doc! { "$group": {
+                "_id": {
+                    "key": format!("${}", query.group_by.field()),
+                    "bucket": { "$subtract": [timestamp_ms.clone(), { "$mod": [timestamp_ms, query.bucket_ms] }] }
+                },
+                "count": { "$sum": 1 }
+            } },
+            doc! { "$sort": { "_id.bucket": 1, "count": -1 } },
+        ];
+        let buckets = self.aggregate(pipeline).await?.iter()
+            .filter_map(|row| {
+                let id = row.get_document("_id").ok()?;
+                Some(ErrorCountBucket {
+                    bucket_start: id.get_i64("bucket").ok()?,
+                    key: optional_str(id, "key").unwrap_or_default(),
+                    count: count_field(row),
+                })
+            })
+            .collect();
+        Ok(buckets)
     }
-    
-    pub async fn store_connection_error_event(&self, event: ConnectionErrorEvent) -> Result<ObjectId, Box<dyn std::error::Error + Send + Sync>> {
-        let result = self.collection.insert_one(event, None).await?;
-        info!("❌ Connection error event stored with ID: {}", result.inserted_id);
-        safe_object_id_conversion(result.inserted_id)
+
+    // Errors are counted per socket first, then joined with the latest
+    // device:info sent on that socket
+    pub async fn top_offenders(&self, from: DateTime, to: DateTime, limit: i64) -> Result<ErrorOffenders, Box<dyn std::error::Error + Send + Sync>> {
+        let pipeline = vec![
+            doc! { "$match": { "timestamp": { "$gte": from, "$lt": to } } },
+            doc! { "$group": { "_id": "$socket_id", "count": { "$sum": 1 } } },
+            doc! { "$lookup": {
+                "from": DatabaseManager::collection_name(DeviceInfoEvent::COLLECTION),
+                "let": { "socket_id": "$_id" },
+                "pipeline": [
+                    { "$match": { "$expr": { "$eq": ["$socket_id", "$$socket_id"] } } },
+                    { "$sort": { "timestamp": -1 } },
+                    { "$limit": 1 },
+                ],
+                "as": "device"
+            } },
+            doc! { "$set": { "device": { "$arrayElemAt": ["$device.device_info", 0] } } },
+            doc! { "$facet": {
+                "devices": [
+                    { "$group": {
+                        "_id": {
+                            "device_id": "$device.device_id",
+                            "manufacturer": "$device.manufacturer",
+                            "model": "$device.model",
+                            "app_version": "$device.app_version"
+                        },
+                        "count": { "$sum": "$count" }
+                    } },
+                    { "$sort": { "count": -1 } },
+                    { "$limit": limit },
+                ],
+                "app_versions": [
+                    { "$group": { "_id": "$device.app_version", "count": { "$sum": "$count" } } },
+                    { "$sort": { "count": -1 } },
+                    { "$limit": limit },
+                ]
+            } },
+        ];
+        let Some(facets) = self.aggregate(pipeline).await?.into_iter().next() else {
+            return Ok(ErrorOffenders::default());
+        };
+        let rows = |name: &str| facets.get_array(name).map(|rows| rows.iter().filter_map(Bson::as_document).cloned().collect::<Vec<_>>()).unwrap_or_default();
+        let devices = rows("devices").iter()
+            .filter_map(|row| {
+                let id = row.get_document("_id").ok()?;
+                Some(ErrorOffender {
+                    device_id: optional_str(id, "device_id"),
+                    manufacturer: optional_str(id, "manufacturer"),
+                    model: optional_str(id, "model"),
+                    app_version: optional_str(id, "app_version"),
+                    count: count_field(row),
+                })
+            })
+            .collect();
+        let app_versions = rows("app_versions").iter()
+            .map(|row| AppVersionErrors { app_version: optional_str(row, "_id"), count: count_field(row) })
+            .collect();
+        Ok(ErrorOffenders { devices, app_versions })
     }
 }
 
-impl LoginEventRepository {
-    pub fn new() -> Self {
-        let database = DatabaseManager::get_database();
-        let collection = database.collection::<LoginEvent>("login_events");
-        Self { collection }
+impl AdminOperatorRepository {
+    pub async fn find_operator(&self, operator_id: &str) -> Result<Option<AdminOperator>, Box<dyn std::error::Error + Send + Sync>> {
+        self.find_one(doc! { "operator_id": operator_id }).await
     }
-    
-    pub async fn store_login_event(&self, event: LoginEvent) -> Result<ObjectId, Box<dyn std::error::Error + Send + Sync>> {
-        let result = self.collection.insert_one(event, None).await?;
-        info!("🔐 Login event stored with ID: {}", result.inserted_id);
-        safe_object_id_conversion(result.inserted_id)
+
+    pub async fn list_operators(&self) -> Result<Vec<AdminOperator>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.find_stream(doc! {}, doc! { "created_at": 1 }).await?.try_collect().await?)
+    }
+
+    // Returns false if there is no such operator
+    pub async fn set_operator_role(&self, operator_id: &str, role: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let update = doc! { "$set": {
+            "role": role,
+            "updated_at": DateTime::from_millis(chrono::Utc::now().timestamp_millis())
+        } };
+        let result = self.collection().update_one(doc! { "operator_id": operator_id }, update, None).await?;
+        Ok(result.matched_count > 0)
     }
 }
 
-impl LoginSuccessEventRepository {
-    pub fn new() -> Self {
-        let database = DatabaseManager::get_database();
-        let collection = database.collection::<LoginSuccessEvent>("login_success_events");
-        Self { collection }
+impl RoomSnapshotRepository {
+    // Replace the room's previous snapshot. A snapshot older than the stored
+    // one (a slow write overtaken by a newer one) is ignored.
+    pub async fn save_snapshot(&self, snapshot: &RoomSnapshot) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let filter = doc! { "room_id": &snapshot.room_id, "saved_at": { "$lte": snapshot.saved_at } };
+        let options = mongodb::options::ReplaceOptions::builder().upsert(true).build();
+        match self.collection().replace_one(filter, snapshot, options).await {
+            Ok(_) => Ok(()),
+            // The upsert collides with the newer snapshot's room_id
+            Err(e) if WriteQueue::is_duplicate_key(&e) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
     }
-    
-    pub async fn store_login_success_event(&self, event: LoginSuccessEvent) -> Result<ObjectId, Box<dyn std::error::Error + Send + Sync>> {
-        let result = self.collection.insert_one(event, None).await?;
-        info!("✅ Login success event stored with ID: {}", result.inserted_id);
-        safe_object_id_conversion(result.inserted_id)
+
+    pub async fn find_saved_since(&self, since: DateTime) -> Result<Vec<RoomSnapshot>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.find_stream(doc! { "saved_at": { "$gte": since } }, doc! { "saved_at": 1 }).await?.try_collect().await?)
     }
-    
-    // Find login success event by mobile number and session token
-    pub async fn find_login_success_by_mobile_and_session(&self, mobile_no: &str, session_token: &str) -> Result<Option<LoginSuccessEvent>, Box<dyn std::error::Error + Send + Sync>> {
-        let filter = doc! { 
-            "mobile_no": mobile_no,
-            "session_token": session_token
-        };
-        let event = self.collection.find_one(filter, None).await?;
-        Ok(event)
+
+    // Drop a closed room's snapshot so it is not restored
+    pub async fn delete_room(&self, room_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.collection().delete_one(doc! { "room_id": room_id }, None).await?;
+        Ok(())
     }
 }
 
-impl OtpVerificationEventRepository {
-    pub fn new() -> Self {
-        let database = DatabaseManager::get_database();
-        let collection = database.collection::<OtpVerificationEvent>("otp_verification_events");
-        Self { collection }
+impl MatchEscrowRepository {
+    pub async fn find_held(&self, room_id: &str) -> Result<Option<MatchEscrow>, Box<dyn std::error::Error + Send + Sync>> {
+        self.find_one(doc! { "room_id": room_id, "status": "held" }).await
     }
-    
-    pub async fn store_otp_verification_event(&self, event: OtpVerificationEvent) -> Result<ObjectId, Box<dyn std::error::Error + Send + Sync>> {
-        let result = self.collection.insert_one(event, None).await?;
-        info!("🔢 OTP verification event stored with ID: {}", result.inserted_id);
-        safe_object_id_conversion(result.inserted_id)
+
+    // Record a player's reported result while the escrow is held; returns the updated escrow
+    pub async fn report_result(&self, escrow_id: &str, player_id: &str, outcome: GameOutcome) -> Result<Option<MatchEscrow>, Box<dyn std::error::Error + Send + Sync>> {
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        Ok(self.collection().find_one_and_update(
+            doc! { "escrow_id": escrow_id, "status": "held", "stakes.player_id": player_id },
+            doc! { "$set": { format!("reports.{}", player_id): bson::to_bson(&outcome)? } },
+            options,
+        ).await?)
     }
-    
-    // Get OTP verification attempts count for a mobile number and session token
-    pub async fn get_verification_attempts_count(&self, mobile_no: &str, session_token: &str) -> Result<i32, Box<dyn std::error::Error + Send + Sync>> {
-        let filter = doc! { 
-            "mobile_no": mobile_no,
-            "session_token": session_token
+
+    // Fix the payouts and move a held escrow to `status`; false when it was
+    // already being settled
+    pub async fn begin_settlement(&self, escrow_id: &str, status: EscrowStatus, payouts: &[EscrowPayout], rake: i64) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let result = self.collection().update_one(
+            doc! { "escrow_id": escrow_id, "status": "held" },
+            doc! { "$set": {
+                "status": bson::to_bson(&status)?,
+                "payouts": bson::to_bson(payouts)?,
+                "rake": rake,
+                "settling_at": DateTime::now(),
+            } },
+            None,
+        ).await?;
+        Ok(result.modified_count > 0)
+    }
+
+    // Move an opening escrow to `status` with the stakes taken; false when it
+    // was already moved
+    pub async fn finish_opening(&self, escrow_id: &str, status: EscrowStatus, stakes: &[EscrowStake], payouts: &[EscrowPayout]) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let mut update = doc! {
+            "status": bson::to_bson(&status)?,
+            "stakes": bson::to_bson(stakes)?,
+            "payouts": bson::to_bson(payouts)?,
         };
-        let count = self.collection.count_documents(filter, None).await?;
-        Ok(count as i32)
+        if status == EscrowStatus::Refunding {
+            update.insert("settling_at", DateTime::now());
+        }
+        let result = self.collection().update_one(
+            doc! { "escrow_id": escrow_id, "status": "opening" },
+            doc! { "$set": update },
+            None,
+        ).await?;
+        Ok(result.modified_count > 0)
+    }
+
+    pub async fn find_opening_before(&self, before: DateTime) -> Result<Vec<MatchEscrow>, Box<dyn std::error::Error + Send + Sync>> {
+        let filter = doc! { "status": "opening", "created_at": { "$lt": before } };
+        Ok(self.find_stream(filter, doc! { "created_at": 1 }).await?.try_collect().await?)
+    }
+
+    pub async fn finish_settlement(&self, escrow_id: &str, status: EscrowStatus) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.collection().update_one(
+            doc! { "escrow_id": escrow_id },
+            doc! { "$set": { "status": bson::to_bson(&status)?, "settled_at": DateTime::now() } },
+            None,
+        ).await?;
+        Ok(())
+    }
+
+    pub async fn find_settling_before(&self, before: DateTime) -> Result<Vec<MatchEscrow>, Box<dyn std::error::Error + Send + Sync>> {
+        let filter = doc! { "status": { "$in": ["releasing", "refunding"] }, "settling_at": { "$lt": before } };
+        Ok(self.find_stream(filter, doc! { "settling_at": 1 }).await?.try_collect().await?)
     }
 }
 
-impl LanguageSettingEventRepository {
-    pub fn new() -> Self {
-        let database = DatabaseManager::get_database();
-        let collection = database.collection::<LanguageSettingEvent>("language_setting_events");
-        Self { collection }
+impl RevenueLedgerRepository {
+    // Store an escrow's commission; false when it was already recorded
+    pub async fn insert_once(&self, entry: &RevenueEntry) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        match self.collection().insert_one(entry, None).await {
+            Ok(_) => Ok(true),
+            Err(e) if WriteQueue::is_duplicate_key(&e) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
     }
-    
-    pub async fn store_language_setting_event(&self, event: LanguageSettingEvent) -> Result<ObjectId, Box<dyn std::error::Error + Send + Sync>> {
-        let result = self.collection.insert_one(event, None).await?;
-        info!("🌐 Language setting event stored with ID: {}", result.inserted_id);
-        safe_object_id_conversion(result.inserted_id)
+
+    // A day's ledger totals by game type
+    pub async fn day_totals(&self, day: &str) -> Result<Vec<RevenueRollup>, Box<dyn std::error::Error + Send + Sync>> {
+        let pipeline = vec![
+            doc! { "$match": { "day": day } },
+            doc! { "$group": {
+                "_id": "$game_type",
+                "matches": { "$sum": 1 },
+                "pot": { "$sum": "$pot" },
+                "rake": { "$sum": "$rake" },
+                "paid_out": { "$sum": "$paid_out" },
+            } },
+            doc! { "$sort": { "_id": 1 } },
+        ];
+        let now = DateTime::now();
+        Ok(self.aggregate(pipeline).await?.iter().map(|row| RevenueRollup {
+            id: None,
+            day: day.to_string(),
+            game_type: row.get_str("_id").unwrap_or_default().to_string(),
+            matches: int_field(row, "matches"),
+            pot: int_field(row, "pot") as i64,
+            rake: int_field(row, "rake") as i64,
+            paid_out: int_field(row, "paid_out") as i64,
+            computed_at: now,
+        }).collect())
     }
-    
-    // Find language setting by mobile number and session token
-    pub async fn find_language_setting_by_mobile_and_session(&self, mobile_no: &str, session_token: &str) -> Result<Option<LanguageSettingEvent>, Box<dyn std::error::Error + Send + Sync>> {
-        let filter = doc! { 
-            "mobile_no": mobile_no,
-            "session_token": session_token
-        };
-        let event = self.collection.find_one(filter, None).await?;
-        Ok(event)
+}
+
+impl PromoCodeRepository {
+    // Store a new code; false when it is taken
+    pub async fn insert_once(&self, promo: &PromoCode) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        match self.collection().insert_one(promo, None).await {
+            Ok(_) => Ok(true),
+            Err(e) if WriteQueue::is_duplicate_key(&e) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn set_active(&self, code: &str, active: bool) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let update = doc! { "$set": { "active": active, "updated_at": DateTime::now() } };
+        let result = self.collection().update_one(doc! { "code": code }, update, None).await?;
+        Ok(result.matched_count > 0)
     }
 }
 
-impl UserProfileEventRepository {
-    pub fn new() -> Self {
-        let database = DatabaseManager::get_database();
-        let collection = database.collection::<UserProfileEvent>("user_profile_events");
-        Self { collection }
+impl ImpersonationSessionRepository {
+    pub async fn end(&self, session_id: &str, end_reason: &str, events_mirrored: u64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let update = doc! { "$set": {
+            "ended_at": DateTime::now(),
+            "end_reason": end_reason,
+            "events_mirrored": events_mirrored as i64,
+        } };
+        self.collection().update_one(doc! { "session_id": session_id, "ended_at": null }, update, None).await?;
+        Ok(())
     }
-    
-    pub async fn store_user_profile_event(&self, event: UserProfileEvent) -> Result<ObjectId, Box<dyn std::error::Error + Send + Sync>> {
-        let result = self.collection.insert_one(event, None).await?;
-        info!("👤 User profile event stored with ID: {}", result.inserted_id);
-        safe_object_id_conversion(result.inserted_id)
+}
+
+impl RevenueRollupRepository {
+    // Replace the stored rollups of `day`
+    pub async fn replace_day(&self, day: &str, rollups: &[RevenueRollup]) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.collection().delete_many(doc! { "day": day }, None).await?;
+        if !rollups.is_empty() {
+            self.collection().insert_many(rollups, None).await?;
+        }
+        Ok(())
     }
-    
-    // Find user profile by mobile number and session token
-    pub async fn find_user_profile_by_mobile_and_session(&self, mobile_no: &str, session_token: &str) -> Result<Option<UserProfileEvent>, Box<dyn std::error::Error + Send + Sync>> {
-        let filter = doc! { 
-            "mobile_no": mobile_no,
-            "session_token": session_token
+}
+
+impl DailyChallengeRepository {
+    // Store a day's challenges unless another instance got there first; returns
+    // the stored set either way
+    pub async fn insert_if_absent(&self, set: &DailyChallengeSet) -> Result<DailyChallengeSet, Box<dyn std::error::Error + Send + Sync>> {
+        match self.collection().insert_one(set, None).await {
+            Ok(_) => {
+                info!("🎯 Stored daily challenges for {}", set.date);
+                Ok(set.clone())
+            }
+            Err(e) if WriteQueue::is_duplicate_key(&e) => self.find_one(doc! { "date": &set.date }).await?
+                .ok_or_else(|| format!("daily challenges for {} vanished after a duplicate insert", set.date).into()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+impl ChallengeProgressRepository {
+    // Add to the user's counters for the day's challenges, creating the document on first progress
+    pub async fn add_progress(&self, user_id: &str, date: &str, increments: &std::collections::HashMap<String, i64>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let inc: Document = increments.iter().map(|(challenge_id, amount)| (format!("progress.{}", challenge_id), Bson::Int64(*amount))).collect();
+        let update = doc! {
+            "$inc": inc,
+            "$set": { "updated_at": DateTime::from_millis(chrono::Utc::now().timestamp_millis()) }
         };
-        let event = self.collection.find_one(filter, None).await?;
-        Ok(event)
+        let options = mongodb::options::UpdateOptions::builder().upsert(true).build();
+        self.collection().update_one(doc! { "user_id": user_id, "date": date }, update, options).await?;
+        Ok(())
     }
-    
-    // Check if referral code already exists
-    pub async fn check_referral_code_exists(&self, referral_code: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
-        let filter = doc! { 
-            "referral_code": referral_code
+
+    pub async fn mark_claimed(&self, user_id: &str, date: &str, challenge_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let update = doc! {
+            "$addToSet": { "claimed": challenge_id },
+            "$set": { "updated_at": DateTime::from_millis(chrono::Utc::now().timestamp_millis()) }
         };
-        let count = self.collection.count_documents(filter, None).await?;
-        Ok(count > 0)
+        let options = mongodb::options::UpdateOptions::builder().upsert(true).build();
+        self.collection().update_one(doc! { "user_id": user_id, "date": date }, update, options).await?;
+        Ok(())
     }
 }
 
-impl UserRegisterRepository {
-    pub fn new() -> Self {
-        let database = DatabaseManager::get_database();
-        let collection = database.collection::<UserRegister>("userregister");
-        Self { collection }
+impl SeasonRepository {
+    // Move a season from one status to the next. Returns false if it was not
+    // in `from`, e.g. because another instance got there first.
+    pub async fn transition(&self, season_id: &str, from: SeasonStatus, to: SeasonStatus) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let mut set = doc! { "status": to.as_str() };
+        if to == SeasonStatus::Ended {
+            set.insert("ended_at", DateTime::from_millis(chrono::Utc::now().timestamp_millis()));
+        }
+        let result = self.collection().update_one(doc! { "season_id": season_id, "status": from.as_str() }, doc! { "$set": set }, None).await?;
+        Ok(result.modified_count == 1)
     }
-    
-    pub async fn store_user_register_event(&self, event: UserRegister) -> Result<ObjectId, Box<dyn std::error::Error + Send + Sync>> {
-        let result = self.collection.insert_one(event, None).await?;
-        info!("👤 User registered with ID: {}", result.inserted_id);
-        safe_object_id_conversion(result.inserted_id)
+}
+
+impl SeasonRatingRepository {
+    // Apply one game result, starting from `base_rating` on the player's first
+    // game of the season. Ratings never drop below 0.
+    pub async fn record_result(&self, season_id: &str, user_id: &str, outcome: GameOutcome, rating_change: i64, base_rating: i64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let counter = |field: &str, hit: bool| doc! { "$add": [{ "$ifNull": [format!("${}", field), 0i64] }, hit as i64] };
+        let update = vec![doc! {
+            "$set": {
+                "rating": { "$max": [0i64, { "$add": [{ "$ifNull": ["$rating", base_rating] }, rating_change] }] },
+                "wins": counter("wins", outcome == GameOutcome::Win),
+                "losses": counter("losses", outcome == GameOutcome::Loss),
+                "draws": counter("draws", outcome == GameOutcome::Draw),
+                "updated_at": DateTime::from_millis(chrono::Utc::now().timestamp_millis())
+            }
+        }];
+        let options = mongodb::options::UpdateOptions::builder().upsert(true).build();
+        self.collection().update_one(doc! { "season_id": season_id, "user_id": user_id }, update, options).await?;
+        Ok(())
     }
-    
-    // Create a new user in the userregister collection
-    pub async fn create_user_register(&self, user: &UserRegister) -> Result<ObjectId, mongodb::error::Error> {
-        let result = self.collection.insert_one(user, None).await?;
-        result.inserted_id.as_object_id()
-            .ok_or_else(|| mongodb::error::Error::from(std::io::Error::new(std::io::ErrorKind::InvalidData, "Failed to get ObjectId from inserted document")))
+
+    // Players ahead of `rating` on the leaderboard: higher rating, or the same
+    // rating reached earlier
+    pub async fn count_ahead(&self, rating: &SeasonRating) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        self.count(doc! {
+            "season_id": &rating.season_id,
+            "$or": [
+                { "rating": { "$gt": rating.rating } },
+                { "rating": rating.rating, "updated_at": { "$lt": rating.updated_at } }
+            ]
+        }).await
     }
-    
+
+    // Soft reset: seed `to` with every rating from `from` moved `factor` of the
+    // way back to `base_rating`. Players who already have a rating in `to` keep it.
+    pub async fn carry_over(&self, from: &str, to: &str, base_rating: i64, factor: f64) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        let pipeline = vec![
+            doc! { "$match": { "season_id": from } },
+            doc! { "$project": {
+                "_id": 0,
+                "season_id": { "$literal": to },
+                "user_id": 1,
+                "rating": { "$toLong": { "$round": [{ "$add": [base_rating, { "$multiply": [{ "$subtract": ["$rating", base_rating] }, factor] }] }, 0] } },
+                "wins": { "$literal": 0i64 },
+                "losses": { "$literal": 0i64 },
+                "draws": { "$literal": 0i64 },
+                "updated_at": { "$literal": DateTime::from_millis(chrono::Utc::now().timestamp_millis()) }
+            } },
+            doc! { "$merge": { "into": DatabaseManager::collection_name(SeasonRating::COLLECTION), "on": ["season_id", "user_id"], "whenMatched": "keepExisting", "whenNotMatched": "insert" } },
+        ];
+        self.aggregate(pipeline).await?;
+        self.count(doc! { "season_id": to }).await
+    }
+}
+
+impl FriendshipRepository {
+    // Store one side of a friendship; false if it was already there
+    pub async fn add(&self, friendship: &Friendship) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        match self.collection().insert_one(friendship, None).await {
+            Ok(_) => Ok(true),
+            Err(e) if WriteQueue::is_duplicate_key(&e) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn pair(user_id: &str, other_user_id: &str) -> Document {
+        doc! { "$or": [
+            { "user_id": user_id, "friend_user_id": other_user_id },
+            { "user_id": other_user_id, "friend_user_id": user_id },
+        ] }
+    }
+
+    // Drop both sides; false if neither existed
+    pub async fn remove_pair(&self, user_id: &str, other_user_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let result = self.collection().delete_many(Self::pair(user_id, other_user_id), None).await?;
+        Ok(result.deleted_count > 0)
+    }
+
+    pub async fn is_mutual(&self, user_id: &str, other_user_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.count(Self::pair(user_id, other_user_id)).await? == 2)
+    }
+
+    // Both sides of every friendship the user is part of, oldest first
+    pub async fn find_involving(&self, user_id: &str) -> Result<Vec<Friendship>, Box<dyn std::error::Error + Send + Sync>> {
+        let filter = doc! { "$or": [{ "user_id": user_id }, { "friend_user_id": user_id }] };
+        Ok(self.find_stream(filter, doc! { "created_at": 1 }).await?.try_collect().await?)
+    }
+}
+
+impl UserRiskRepository {
+    pub async fn save(&self, risk: &UserRisk) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let options = mongodb::options::ReplaceOptions::builder().upsert(true).build();
+        self.collection().replace_one(doc! { "user_id": &risk.user_id }, risk, options).await?;
+        Ok(())
+    }
+
+    pub async fn clear_stale(&self, before: DateTime) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        let update = doc! { "$set": {
+            "score": 0,
+            "signals": [],
+            "limited": false,
+            "updated_at": DateTime::from_millis(chrono::Utc::now().timestamp_millis())
+        } };
+        let result = self.collection().update_many(doc! { "updated_at": { "$lt": before }, "score": { "$gt": 0 } }, update, None).await?;
+        Ok(result.modified_count)
+    }
+}
+
+impl RiskFlagRepository {
+    // Insert unless an open flag has the same key (partial unique index)
+    pub async fn open(&self, flag: &RiskFlag) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        match self.collection().insert_one(flag, None).await {
+            Ok(_) => Ok(true),
+            Err(e) if WriteQueue::is_duplicate_key(&e) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn resolve(&self, flag_id: &str, status: &str, operator_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let update = doc! { "$set": {
+            "status": status,
+            "resolved_by": operator_id,
+            "resolved_at": DateTime::from_millis(chrono::Utc::now().timestamp_millis())
+        } };
+        let result = self.collection().update_one(doc! { "flag_id": flag_id, "status": "open" }, update, None).await?;
+        Ok(result.modified_count == 1)
+    }
+
+    pub async fn dismissed_keys(&self) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+        let keys = self.collection().distinct("flag_key", doc! { "status": "dismissed" }, None).await?;
+        Ok(keys.into_iter().filter_map(|key| key.as_str().map(str::to_string)).collect())
+    }
+}
+
+impl UserBlockRepository {
+    pub async fn add(&self, block: &UserBlock) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        match self.collection().insert_one(block, None).await {
+            Ok(_) => Ok(true),
+            Err(e) if WriteQueue::is_duplicate_key(&e) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn remove(&self, user_id: &str, blocked_user_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let result = self.collection().delete_one(doc! { "user_id": user_id, "blocked_user_id": blocked_user_id }, None).await?;
+        Ok(result.deleted_count > 0)
+    }
+
+    // Blocks made by or against the user, oldest first
+    pub async fn find_involving(&self, user_id: &str) -> Result<Vec<UserBlock>, Box<dyn std::error::Error + Send + Sync>> {
+        let filter = doc! { "$or": [{ "user_id": user_id }, { "blocked_user_id": user_id }] };
+        Ok(self.find_stream(filter, doc! { "created_at": 1 }).await?.try_collect().await?)
+    }
+}
+
+impl UserReportRepository {
+    pub async fn find_by_reporter_since(&self, reporter_user_id: &str, since: DateTime) -> Result<Vec<UserReport>, Box<dyn std::error::Error + Send + Sync>> {
+        let filter = doc! { "reporter_user_id": reporter_user_id, "created_at": { "$gte": since } };
+        Ok(self.find_stream(filter, doc! { "created_at": -1 }).await?.try_collect().await?)
+    }
+
+    pub async fn resolve(&self, report_id: &str, status: &str, resolution: &str, operator_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let update = doc! { "$set": {
+            "status": status,
+            "resolution": resolution,
+            "resolved_by": operator_id,
+            "resolved_at": DateTime::from_millis(chrono::Utc::now().timestamp_millis())
+        } };
+        let result = self.collection().update_one(doc! { "report_id": report_id, "status": "open" }, update, None).await?;
+        Ok(result.modified_count == 1)
+    }
+}
+
+impl ChatMessageRepository {
+    // Edit a message that is not deleted; false if it was deleted meanwhile
+    pub async fn edit(&self, message_id: &str, message: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let update = doc! { "$set": { "message": message, "edited_at": DateTime::now() } };
+        let result = self.collection().update_one(doc! { "message_id": message_id, "deleted_at": null }, update, None).await?;
+        Ok(result.modified_count == 1)
+    }
+
+    // Leave a tombstone in place of the message
+    pub async fn tombstone(&self, message_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let update = doc! { "$set": { "message": "", "deleted_at": DateTime::now() } };
+        let result = self.collection().update_one(doc! { "message_id": message_id, "deleted_at": null }, update, None).await?;
+        Ok(result.modified_count == 1)
+    }
+
+    pub async fn purge_before(&self, before: DateTime) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        let result = self.collection().delete_many(doc! { "created_at": { "$lt": before } }, None).await?;
+        Ok(result.deleted_count)
+    }
+
+    // Add `delta` to a reaction counter of a message that is not deleted;
+    // returns the new count, None if the message was deleted meanwhile
+    pub async fn bump_reaction(&self, message_id: &str, reaction: &str, delta: i64) -> Result<Option<i64>, Box<dyn std::error::Error + Send + Sync>> {
+        let field = format!("reactions.{}", reaction);
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        let message = self.collection()
+            .find_one_and_update(doc! { "message_id": message_id, "deleted_at": null }, doc! { "$inc": { field: delta } }, options)
+            .await?;
+        Ok(message.map(|message| message.reactions.get(reaction).copied().unwrap_or_default()))
+    }
+}
+
+impl ChatReactionRepository {
+    // False if the player already has this reaction on the message
+    pub async fn add(&self, reaction: &ChatReaction) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        match self.collection().insert_one(reaction, None).await {
+            Ok(_) => Ok(true),
+            Err(e) if WriteQueue::is_duplicate_key(&e) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn remove(&self, message_id: &str, user_id: &str, reaction: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let result = self.collection().delete_one(doc! { "message_id": message_id, "user_id": user_id, "reaction": reaction }, None).await?;
+        Ok(result.deleted_count > 0)
+    }
+
+    pub async fn purge_before(&self, before: DateTime) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        let result = self.collection().delete_many(doc! { "created_at": { "$lt": before } }, None).await?;
+        Ok(result.deleted_count)
+    }
+}
+
+impl GameConfigRepository {
+    // False if another operator stored this version first
+    pub async fn insert_version(&self, config: &GameConfig) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        match self.collection().insert_one(config, None).await {
+            Ok(_) => Ok(true),
+            Err(e) if WriteQueue::is_duplicate_key(&e) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    // Change stream on the collection. Fails on a standalone server, which
+    // has no change streams.
+    pub async fn watch(&self) -> Result<mongodb::change_stream::ChangeStream<mongodb::change_stream::event::ChangeStreamEvent<GameConfig>>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.collection().watch(None, None).await?)
+    }
+}
+
+impl UserSanctionRepository {
+    pub async fn lift(&self, sanction_id: &str, operator_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let now = DateTime::from_millis(chrono::Utc::now().timestamp_millis());
+        let filter = doc! { "sanction_id": sanction_id, "lifted_at": null, "expires_at": { "$gt": now } };
+        let update = doc! { "$set": { "lifted_by": operator_id, "lifted_at": now } };
+        let result = self.collection().update_one(filter, update, None).await?;
+        Ok(result.modified_count == 1)
+    }
+}
+
+impl UserRegisterRepository {
+    // Merge preference updates into user_preferences path by path (paths are
+    // relative to user_preferences); `unset` paths are removed. Returns false if
+    // the user does not exist.
+    pub async fn merge_user_preferences(&self, mobile_no: &str, set: Document, unset: Vec<String>) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        // Older documents may hold null here, which dotted updates cannot descend into
+        let filter = doc! { "mobile_no": mobile_no, "user_preferences": { "$not": { "$type": "object" } } };
+        self.collection().update_one(filter, doc! { "$set": { "user_preferences": {} } }, None).await?;
+
+        let mut set_doc = doc! {
+            "updated_at": DateTime::from_millis(chrono::Utc::now().timestamp_millis())
+        };
+        for (path, value) in set {
+            set_doc.insert(format!("user_preferences.{}", path), value);
+        }
+        let mut update_doc = doc! { "$set": set_doc };
+        if !unset.is_empty() {
+            let unset_doc: Document = unset.iter().map(|path| (format!("user_preferences.{}", path), Bson::String(String::new()))).collect();
+            update_doc.insert("$unset", unset_doc);
+        }
+
+        let result = self.collection().update_one(doc! { "mobile_no": mobile_no }, update_doc, None).await?;
+        info!("⚙️ Merged user preferences for mobile: {} (matched: {})", mobile_no, result.matched_count);
+        Ok(result.matched_count > 0)
+    }
+
     // Find user by mobile number
     pub async fn find_user_by_mobile(&self, mobile_no: &str) -> Result<Option<UserRegister>, Box<dyn std::error::Error + Send + Sync>> {
-        let filter = doc! { "mobile_no": mobile_no };
-        let user = self.collection.find_one(filter, None).await?;
-        Ok(user)
+        self.find_one(doc! { "mobile_no": mobile_no }).await
+    }
+
+    // Each user in the window is joined with the days after registration on
+    // which they completed a login (verified OTP), then counted into the week
+    // they registered in
+    pub async fn retention_cohorts(&self, since: chrono::DateTime<chrono::Utc>, now: chrono::DateTime<chrono::Utc>, days: &[u32]) -> Result<Vec<RetentionCohort>, Box<dyn std::error::Error + Send + Sync>> {
+        let now_ms = now.timestamp_millis();
+        let last_day = days.iter().copied().max().unwrap_or_default() as i64;
+        let created_ms = doc! { "$toLong": "$created_at" };
+        let mut group = doc! {
+            "_id": { "$subtract": ["$created_ms", { "$mod": [{ "$subtract": ["$created_ms", FIRST_MONDAY_MS] }, 7 * DAY_MS] }] },
+            "users": { "$sum": 1 },
+        };
+        for &day in days {
+            let eligible = doc! { "$lte": [{ "$add": ["$created_ms", (day as i64 + 1) * DAY_MS] }, now_ms] };
+            group.insert(format!("eligible_{}", day), doc! { "$sum": { "$cond": [eligible.clone(), 1, 0] } });
+            group.insert(format!("returned_{}", day), doc! { "$sum": { "$cond": [{ "$and": [eligible, { "$in": [day as i64, "$return_days"] }] }, 1, 0] } });
+        }
+        let pipeline = vec![
+            doc! { "$match": { "created_at": {
+                "$gte": DateTime::from_millis(since.timestamp_millis()),
+                "$lt": DateTime::from_millis(now_ms)
+            } } },
+            doc! { "$lookup": {
+                "from": DatabaseManager::collection_name(LoginSuccessEvent::COLLECTION),
+                "let": { "mobile_no": "$mobile_no", "created": "$created_at" },
+                "pipeline": [
+                    { "$match": {
+                        "verified_at": { "$ne": null },
+                        "$expr": { "$and": [
+                            { "$eq": ["$mobile_no", "$$mobile_no"] },
+                            { "$gte": ["$timestamp", { "$add": ["$$created", DAY_MS] }] },
+                            { "$lt": ["$timestamp", { "$add": ["$$created", (last_day + 1) * DAY_MS] }] },
+                        ] }
+                    } },
+                    { "$group": { "_id": { "$floor": { "$divide": [{ "$subtract": ["$timestamp", "$$created"] }, DAY_MS] } } } },
+                ],
+                "as": "returns"
+            } },
+            doc! { "$project": { "created_ms": created_ms, "return_days": "$returns._id" } },
+            doc! { "$group": group },
+            doc! { "$sort": { "_id": 1 } },
+        ];
+        let cohorts = self.aggregate(pipeline).await?.iter()
+            .filter_map(|row| {
+                let week_start = row.get_i64("_id").ok()?;
+                Some(RetentionCohort {
+                    week_start: RetentionCohort::week_label(week_start),
+                    users: int_field(row, "users"),
+                    days: days.iter()
+                        .map(|day| RetentionDay::new(*day, int_field(row, &format!("eligible_{}", day)), int_field(row, &format!("returned_{}", day))))
+                        .collect(),
+                })
+            })
+            .collect();
+        Ok(cohorts)
+    }
+
+    pub async fn find_user_by_id(&self, user_id: &str) -> Result<Option<UserRegister>, Box<dyn std::error::Error + Send + Sync>> {
+        self.find_one(doc! { "user_id": user_id }).await
+    }
+
+    // user_id / user_number only
+    pub async fn find_user_identity(&self, mobile_no: &str) -> Result<Option<UserIdentity>, Box<dyn std::error::Error + Send + Sync>> {
+        self.find_one_projected(doc! { "mobile_no": mobile_no }).await
+    }
+
+    // Profile and language fields, without device, token or preference data
+    pub async fn find_user_profile(&self, mobile_no: &str) -> Result<Option<UserProfile>, Box<dyn std::error::Error + Send + Sync>> {
+        self.find_one_projected(doc! { "mobile_no": mobile_no }).await
     }
     
     // Update user login information
@@ -248,7 +1055,7 @@ impl UserRegisterRepository {
                 "total_logins": 1
             }
         };
-        let result = self.collection.update_one(filter, update, None).await?;
+        let result = self.collection().update_one(filter, update, None).await?;
         if result.modified_count > 0 {
             info!("Updated login info for mobile: {}", mobile_no);
         }
@@ -282,7 +1089,7 @@ impl UserRegisterRepository {
         }
         
         let update_doc = doc! { "$set": set_doc };
-        let result = self.collection.update_one(filter, update_doc, None).await?;
+        let result = self.collection().update_one(filter, update_doc, None).await?;
         
         if result.modified_count > 0 {
             info!("✅ Updated profile for mobile: {} (modified: {})", mobile_no, result.modified_count);
@@ -320,7 +1127,7 @@ impl UserRegisterRepository {
         }
         
         let update_doc = doc! { "$set": set_doc };
-        let result = self.collection.update_one(filter, update_doc, None).await?;
+        let result = self.collection().update_one(filter, update_doc, None).await?;
         
         if result.modified_count > 0 {
             info!("✅ Updated language settings for mobile: {} (modified: {})", mobile_no, result.modified_count);
@@ -333,30 +1140,17 @@ impl UserRegisterRepository {
     
     // Check if user exists
     pub async fn user_exists(&self, mobile_no: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
-        let filter = doc! { "mobile_no": mobile_no };
-        let count = self.collection.count_documents(filter, None).await?;
-        Ok(count > 0)
+        Ok(self.count(doc! { "mobile_no": mobile_no }).await? > 0)
     }
     
     // Check if referral code already exists
     pub async fn check_referral_code_exists(&self, referral_code: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
-        let filter = doc! { 
-            "referral_code": referral_code
-        };
-        let count = self.collection.count_documents(filter, None).await?;
-        Ok(count > 0)
-    }
-    
-    // Get user by mobile number (returns mongodb::error::Error for compatibility)
-    pub async fn get_user_by_mobile(&self, mobile_no: &str) -> Result<Option<UserRegister>, mongodb::error::Error> {
-        let filter = doc! { "mobile_no": mobile_no };
-        let user = self.collection.find_one(filter, None).await?;
-        Ok(user)
+        Ok(self.count(doc! { "referral_code": referral_code }).await? > 0)
     }
     
     // Get all users
     pub async fn get_all_users(&self) -> Result<Vec<UserRegister>, Box<dyn std::error::Error + Send + Sync>> {
-        let mut cursor = self.collection.find(None, None).await?;
+        let mut cursor = self.collection().find(None, None).await?;
         let mut users = Vec::new();
         while let Some(user) = cursor.try_next().await? {
             users.push(user);
@@ -366,16 +1160,16 @@ impl UserRegisterRepository {
     
     // Get user statistics
     pub async fn get_user_statistics(&self) -> Result<serde_json::Value, Box<dyn std::error::Error + Send + Sync>> {
-        let total_users = self.collection.count_documents(None, None).await?;
+        let total_users = self.count(doc! {}).await?;
         let today = chrono::Utc::now().date_naive();
         let today_start = DateTime::from_millis(today.and_hms_opt(0, 0, 0)
             .ok_or_else(|| Box::new(std::io::Error::new(std::io::ErrorKind::InvalidData, "Invalid time")) as Box<dyn std::error::Error + Send + Sync>)?
             .and_utc().timestamp_millis());
         let today_filter = doc! { "created_at": { "$gte": today_start } };
-        let new_users_today = self.collection.count_documents(today_filter, None).await?;
+        let new_users_today = self.count(today_filter).await?;
         
         let active_filter = doc! { "is_active": true };
-        let active_users = self.collection.count_documents(active_filter, None).await?;
+        let active_users = self.count(active_filter).await?;
         
         Ok(serde_json::json!({
             "total_users": total_users,
@@ -384,4 +1178,4 @@ impl UserRegisterRepository {
             "last_updated": chrono::Utc::now().to_rfc3339()
         }))
     }
-} 
\ No newline at end of file
+}
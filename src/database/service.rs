@@ -1,13 +1,21 @@
-use tracing::{info, error};
-use crate::database::{models::*, repository::*, DatabaseManager};
+use async_trait::async_trait;
+use tracing::{info, warn, error};
+use crate::managers::correlation::Correlation;
+use crate::database::{models::*, repository::*, store::{DataStore, GameConfigChanges, UserStream}, DatabaseManager, GameplayService, InventoryService, WalletService};
+use crate::database::outbox::Outbox;
+use crate::database::wallet_service::TRANSACTION_ATTEMPTS;
+use crate::database::timeline::{Timeline, TimelineSource, TimelineSubject};
+use crate::database::user_cache::{UserCache, UserKey};
+use crate::config::CONFIG;
+use crate::managers::token::TokenGenerator;
 use chrono;
-use mongodb::{Database, Collection};
+use mongodb::{ClientSession, Collection};
 use bson::doc;
+use futures_util::{StreamExt, TryStreamExt};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
 pub struct DataService {
-    db: &'static Database,
     user_counter: Arc<Mutex<u64>>,
     connect_repo: ConnectEventRepository,
     device_info_repo: DeviceInfoEventRepository,
@@ -18,18 +26,49 @@ pub struct DataService {
     language_setting_repo: LanguageSettingEventRepository,
     user_profile_repo: UserProfileEventRepository,
     user_register_repo: UserRegisterRepository,
+    session_repo: SessionRepository,
+    user_registration_repo: UserRegistrationEventRepository,
+    turn_timing_repo: TurnTimingEventRepository,
+    test_otp_audit_repo: TestOtpAuditEventRepository,
+    admin_operator_repo: AdminOperatorRepository,
+    admin_access_denied_repo: AdminAccessDeniedEventRepository,
+    rejected_handshake_repo: RejectedHandshakeEventRepository,
+    room_snapshot_repo: RoomSnapshotRepository,
+    archived_room_repo: ArchivedRoomRepository,
+    match_escrow_repo: MatchEscrowRepository,
+    revenue_ledger_repo: RevenueLedgerRepository,
+    revenue_rollup_repo: RevenueRollupRepository,
+    promo_code_repo: PromoCodeRepository,
+    promo_redemption_repo: PromoRedemptionRepository,
+    impersonation_repo: ImpersonationSessionRepository,
+    daily_challenge_repo: DailyChallengeRepository,
+    challenge_progress_repo: ChallengeProgressRepository,
+    season_repo: SeasonRepository,
+    season_rating_repo: SeasonRatingRepository,
+    friendship_repo: FriendshipRepository,
+    gift_repo: GiftRepository,
+    match_participant_repo: MatchParticipantRepository,
+    match_result_repo: MatchResultRepository,
+    user_risk_repo: UserRiskRepository,
+    risk_flag_repo: RiskFlagRepository,
+    user_block_repo: UserBlockRepository,
+    user_report_repo: UserReportRepository,
+    user_sanction_repo: UserSanctionRepository,
+    chat_message_repo: ChatMessageRepository,
+    chat_audit_repo: ChatMessageAuditRepository,
+    chat_reaction_repo: ChatReactionRepository,
+    game_config_repo: GameConfigRepository,
+    gameplay: GameplayService,
+    wallet: WalletService,
+    inventory: InventoryService,
 }
 
 impl DataService {
     pub fn new() -> Self {
-        // Get the shared database instance
-        let db = DatabaseManager::get_database();
-        
         // Initialize user counter
         let user_counter = Arc::new(Mutex::new(0));
         
         Self {
-            db,
             user_counter,
             connect_repo: ConnectEventRepository::new(),
             device_info_repo: DeviceInfoEventRepository::new(),
@@ -40,39 +79,293 @@ impl DataService {
             language_setting_repo: LanguageSettingEventRepository::new(),
             user_profile_repo: UserProfileEventRepository::new(),
             user_register_repo: UserRegisterRepository::new(),
+            session_repo: SessionRepository::new(),
+            user_registration_repo: UserRegistrationEventRepository::new(),
+            turn_timing_repo: TurnTimingEventRepository::new(),
+            test_otp_audit_repo: TestOtpAuditEventRepository::new(),
+            admin_operator_repo: AdminOperatorRepository::new(),
+            admin_access_denied_repo: AdminAccessDeniedEventRepository::new(),
+            rejected_handshake_repo: RejectedHandshakeEventRepository::new(),
+            room_snapshot_repo: RoomSnapshotRepository::new(),
+            archived_room_repo: ArchivedRoomRepository::new(),
+            match_escrow_repo: MatchEscrowRepository::new(),
+            revenue_ledger_repo: RevenueLedgerRepository::new(),
+            revenue_rollup_repo: RevenueRollupRepository::new(),
+            promo_code_repo: PromoCodeRepository::new(),
+            promo_redemption_repo: PromoRedemptionRepository::new(),
+            impersonation_repo: ImpersonationSessionRepository::new(),
+            daily_challenge_repo: DailyChallengeRepository::new(),
+            challenge_progress_repo: ChallengeProgressRepository::new(),
+            season_repo: SeasonRepository::new(),
+            season_rating_repo: SeasonRatingRepository::new(),
+            friendship_repo: FriendshipRepository::new(),
+            gift_repo: GiftRepository::new(),
+            match_participant_repo: MatchParticipantRepository::new(),
+            match_result_repo: MatchResultRepository::new(),
+            user_risk_repo: UserRiskRepository::new(),
+            risk_flag_repo: RiskFlagRepository::new(),
+            user_block_repo: UserBlockRepository::new(),
+            user_report_repo: UserReportRepository::new(),
+            user_sanction_repo: UserSanctionRepository::new(),
+            chat_message_repo: ChatMessageRepository::new(),
+            chat_audit_repo: ChatMessageAuditRepository::new(),
+            chat_reaction_repo: ChatReactionRepository::new(),
+            game_config_repo: GameConfigRepository::new(),
+            gameplay: GameplayService::new(),
+            wallet: WalletService::new(),
+            inventory: InventoryService::new(),
+        }
+    }
+
+    // Collection in the database of the tenant in scope, named with
+    // COLLECTION_PREFIX, for queries outside the repositories
+    fn collection<T>(&self, name: &str) -> Collection<T> {
+        DatabaseManager::collection(name)
+    }
+
+    // The user's numbers, old ones included, and the sockets they logged in on
+    async fn timeline_subject(&self, user: &UserRegister) -> Result<TimelineSubject, Box<dyn std::error::Error + Send + Sync>> {
+        let mut mobile_nos = vec![user.mobile_no.clone()];
+        let mut changes = self.collection::<MobileChangeAudit>("mobile_change_audit").find(doc! { "user_id": &user.user_id }, None).await?;
+        while let Some(change) = changes.try_next().await? {
+            for mobile_no in [change.old_mobile_no, change.new_mobile_no] {
+                if !mobile_nos.contains(&mobile_no) {
+                    mobile_nos.push(mobile_no);
+                }
+            }
+        }
+        let socket_ids = self.collection::<bson::Document>("login_success_events")
+            .distinct("socket_id", doc! { "mobile_no": { "$in": &mobile_nos } }, None).await?
+            .into_iter()
+            .filter_map(|id| id.as_str().map(str::to_string))
+            .collect();
+        Ok(TimelineSubject { user_id: user.user_id.clone(), mobile_nos, socket_ids })
+    }
+
+    // The steps of send_gift in the caller's transaction. Every gift locks the
+    // sender's wallet first, so concurrent gifts from one sender conflict and
+    // the retry counts against the new daily totals.
+    async fn send_gift_in_session(&self, session: &mut ClientSession, gift: &Gift, limits: GiftLimits, daily_limit: Option<i64>) -> Result<GiftSend, Box<dyn std::error::Error + Send + Sync>> {
+        self.wallet.lock_in_session(session, &gift.from_user_id).await?;
+        let gifts: Collection<Gift> = self.collection("gifts");
+        let day_start = chrono::Utc::now().date_naive().and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+        let filter = doc! { "from_user_id": &gift.from_user_id, "created_at": { "$gte": bson::DateTime::from_millis(day_start.timestamp_millis()) } };
+        let mut cursor = gifts.find_with_session(filter, None, session).await?;
+        let sent_today: Vec<Gift> = cursor.stream(session).try_collect().await?;
+        let coins_sent: i64 = sent_today.iter().filter_map(|g| g.coins).sum();
+        if sent_today.len() >= limits.gifts || coins_sent + gift.coins.unwrap_or(0) > limits.coins {
+            return Ok(GiftSend::DailyLimitReached { gifts_sent: sent_today.len(), coins_sent });
+        }
+
+        let sent = format!("gift:{}:sent", gift.gift_id);
+        let received = format!("gift:{}:received", gift.gift_id);
+        let left = match (&gift.item_id, gift.coins) {
+            (Some(item_id), _) => {
+                let Some(left) = self.inventory.take_in_session(session, &gift.from_user_id, item_id, gift.quantity, "gift_sent", &sent).await? else {
+                    return Ok(GiftSend::InsufficientItems);
+                };
+                self.inventory.grant_in_session(session, &gift.to_user_id, item_id, gift.quantity, "gift_received", &received).await?;
+                left
+            }
+            (None, coins) => {
+                let coins = coins.unwrap_or(0);
+                let balance = match self.wallet.debit_in_session(session, &gift.from_user_id, coins, "gift_sent", &sent, daily_limit).await? {
+                    WalletDebit::Debited(balance) => balance,
+                    WalletDebit::InsufficientBalance => return Ok(GiftSend::InsufficientBalance),
+                    WalletDebit::SpendLimitReached { spent_today, daily_limit } => return Ok(GiftSend::SpendLimitReached { spent_today, daily_limit }),
+                };
+                self.wallet.credit_in_session(session, &gift.to_user_id, coins, "gift_received", &received).await?;
+                balance
+            }
+        };
+        gifts.insert_one_with_session(gift, None, session).await?;
+        Ok(GiftSend::Sent(left))
+    }
+
+    // The steps of redeem_promo_code in the caller's transaction
+    async fn redeem_promo_code_in_session(&self, session: &mut ClientSession, redemption: &mut PromoRedemption) -> Result<PromoRedeem, Box<dyn std::error::Error + Send + Sync>> {
+        let codes: Collection<PromoCode> = self.collection("promo_codes");
+        let redemptions: Collection<PromoRedemption> = self.collection("promo_redemptions");
+        let now = bson::DateTime::now();
+
+        let Some(promo) = codes.find_one_with_session(doc! { "code": &redemption.code }, None, session).await? else {
+            return Ok(PromoRedeem::NotFound);
+        };
+        if let Some(refusal) = promo.unavailable(now) {
+            return Ok(refusal);
+        }
+        let redeemed = redemptions.count_documents_with_session(doc! { "code": &promo.code, "user_id": &redemption.user_id }, None, session).await?;
+        if redeemed >= promo.per_user_limit as u64 {
+            return Ok(PromoRedeem::UserLimitReached { limit: promo.per_user_limit });
+        }
+        codes.update_one_with_session(doc! { "code": &promo.code }, doc! { "$inc": { "uses": 1 }, "$set": { "updated_at": now } }, None, session).await?;
+        redemption.reward = promo.reward.clone();
+        redemptions.insert_one_with_session(&*redemption, None, session).await?;
+
+        let user_id = &redemption.user_id;
+        let reference = format!("promo:{}", redemption.redemption_id);
+        let mut balance = None;
+        if promo.reward.coins > 0 {
+            balance = Some(self.wallet.credit_in_session(session, user_id, promo.reward.coins, "promo_code", &reference).await?);
+        }
+        let mut held = Vec::new();
+        for item in &promo.reward.items {
+            let item_reference = format!("{}:{}", reference, item.item_id);
+            let quantity = self.inventory.grant_in_session(session, user_id, &item.item_id, item.quantity, "promo_code", &item_reference).await?;
+            held.push((item.item_id.clone(), quantity));
         }
+        let messages = Outbox::promo_redeemed(redemption);
+        if !messages.is_empty() {
+            self.collection::<OutboxMessage>("outbox").insert_many_with_session(messages, None, session).await?;
+        }
+        let balance = match balance {
+            Some(balance) => balance,
+            None => self.wallet.balance(user_id).await?,
+        };
+        Ok(PromoRedeem::Redeemed { reward: promo.reward, balance, held })
     }
-    
+
     // Get next user number
     async fn get_next_user_number(&self) -> u64 {
         let mut counter = self.user_counter.lock().await;
         *counter += 1;
         *counter
     }
-    
+
+    // Record the device a user logged in from, enriched with the login socket's device:info.
+    // Logging in again from a removed device re-adds it.
+    async fn upsert_user_device(&self, socket_id: &str, mobile_no: &str, device_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let device_info_collection: Collection<DeviceInfoEvent> = self.collection("device_info_events");
+        let options = mongodb::options::FindOneOptions::builder().sort(doc! { "timestamp": -1 }).build();
+        let device_info = device_info_collection
+            .find_one(doc! { "socket_id": socket_id }, options)
+            .await?
+            .map(|e| e.device_info)
+            .unwrap_or_default();
+
+        let now = bson::DateTime::from_millis(chrono::Utc::now().timestamp_millis());
+        let mut set = doc! {
+            "last_seen_at": now,
+            "removed_at": bson::Bson::Null
+        };
+        for field in ["device_type", "manufacturer", "model", "firmware_version"] {
+            if let Some(value) = device_info.get(field).and_then(|v| v.as_str()) {
+                set.insert(field, value);
+            }
+        }
+
+        let collection: Collection<UserDevice> = self.collection("user_devices");
+        let filter = doc! { "mobile_no": mobile_no, "device_id": device_id };
+        let update = doc! { "$set": set, "$setOnInsert": { "first_seen_at": now } };
+        let options = mongodb::options::UpdateOptions::builder().upsert(true).build();
+        collection.update_one(filter, update, options).await?;
+        info!("📱 Recorded device {} for mobile: {}", device_id, mobile_no);
+        Ok(())
+    }
+
+    // Update user FCM token
+    pub async fn update_user_fcm_token(&self, mobile_no: &str, fcm_token: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let collection: Collection<UserRegister> = self.collection("userregister");
+        let filter = doc! { "mobile_no": mobile_no };
+        let update = doc! {
+            "$set": {
+                "fcm_token": fcm_token,
+                "updated_at": bson::DateTime::from_millis(chrono::Utc::now().timestamp_millis())
+            }
+        };
+        collection.update_one(filter, update, None).await?;
+        UserCache::invalidate_mobile(mobile_no);
+        info!("🔄 Updated FCM token for mobile: {}", mobile_no);
+        Ok(())
+    }
+
+    // Update user profile
+    pub async fn update_user_profile(&self, mobile_no: &str, full_name: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.user_register_repo.update_user_profile(
+            mobile_no, 
+            Some(full_name.to_string()), 
+            None, 
+            None, 
+            None, 
+            None
+        ).await?;
+        UserCache::invalidate_mobile(mobile_no);
+        Ok(())
+    }
+
+    // Get user by session token (for session verification)
+    pub async fn get_user_by_session_token(&self, session_token: &str) -> Result<Option<UserRegister>, Box<dyn std::error::Error + Send + Sync>> {
+        // In a real implementation, you would store and verify session tokens
+        // For demo purposes, we'll extract mobile number from session token
+        let mobile_no = session_token.chars().take(10).collect::<String>();
+        self.get_user_by_mobile(&mobile_no).await
+    }
+
+    // Start an authenticated session once the OTP has been verified
+    async fn create_session(&self, mobile_no: &str, device_id: &str, session_token: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let now = chrono::Utc::now();
+        let absolute_expires_at = now + chrono::Duration::hours(CONFIG.session_absolute_timeout_hours);
+        let idle_expires_at = (now + chrono::Duration::minutes(CONFIG.session_idle_timeout_minutes)).min(absolute_expires_at);
+        let session = UserSession {
+            id: None,
+            session_token: session_token.to_string(),
+            mobile_no: mobile_no.to_string(),
+            device_id: device_id.to_string(),
+            created_at: bson::DateTime::from_millis(now.timestamp_millis()),
+            last_active_at: bson::DateTime::from_millis(now.timestamp_millis()),
+            idle_expires_at: bson::DateTime::from_millis(idle_expires_at.timestamp_millis()),
+            absolute_expires_at: bson::DateTime::from_millis(absolute_expires_at.timestamp_millis()),
+            revoked: false,
+        };
+        self.session_repo.insert(&session).await?;
+        info!("🔑 Session started for mobile: {} (expires at: {})", mobile_no, absolute_expires_at);
+        Ok(())
+    }
+
+    // Clean up expired OTP sessions
+    pub async fn cleanup_expired_otp_sessions(&self) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        let collection: Collection<LoginSuccessEvent> = self.collection("login_success_events");
+        let now = chrono::Utc::now();
+        let filter = doc! {
+            "expires_at": {
+                "$lt": bson::DateTime::from_millis(now.timestamp_millis())
+            }
+        };
+        
+        let result = collection.delete_many(filter, None).await?;
+        let deleted_count = result.deleted_count;
+        
+        if deleted_count > 0 {
+            info!("🧹 Cleaned up {} expired OTP sessions", deleted_count);
+        }
+        
+        Ok(deleted_count)
+    }
+}
+
+#[async_trait]
+impl DataStore for DataService {
     // Store connect event
-    pub async fn store_connect_event(&self, socket_id: &str, token: i32, message: &str, status: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let collection: Collection<ConnectEvent> = self.db.collection("connect_events");
-        let event = ConnectEvent::new(socket_id.to_string(), token, message.to_string(), status.to_string());
-        collection.insert_one(event, None).await?;
+    async fn store_connect_event(&self, socket_id: &str, token: i32, message: &str, status: &str, ip_address: Option<&str>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let event = ConnectEvent::new(socket_id.to_string(), token, message.to_string(), status.to_string(), ip_address.map(str::to_string));
+        self.connect_repo.insert_event(event).await?;
         info!("📝 Stored connect event for socket: {}", socket_id);
         Ok(())
     }
-    
+
     // Store device info event
-    pub async fn store_device_info_event(&self, socket_id: &str, device_info: &serde_json::Value) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let collection: Collection<DeviceInfoEvent> = self.db.collection("device_info_events");
+    async fn store_device_info_event(&self, socket_id: &str, device_info: &serde_json::Value) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let event = DeviceInfoEvent::new(socket_id.to_string(), device_info.clone());
-        collection.insert_one(event, None).await?;
+        self.device_info_repo.insert_event(event).await?;
         info!("📝 Stored device info event for socket: {}", socket_id);
         Ok(())
     }
-    
+
     // Store login event
-    pub async fn store_login_event(&self, socket_id: &str, mobile_no: &str, device_id: &str, fcm_token: &str, email: Option<&str>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let collection: Collection<LoginEvent> = self.db.collection("login_events");
+    async fn store_login_event(&self, socket_id: &str, mobile_no: &str, device_id: &str, fcm_token: &str, email: Option<&str>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let event = LoginEvent {
             id: None,
+            request_id: Correlation::current(),
             socket_id: socket_id.to_string(),
             mobile_no: mobile_no.to_string(),
             device_id: device_id.to_string(),
@@ -80,26 +373,26 @@ impl DataService {
             email: email.map(|e| e.to_string()),
             timestamp: bson::DateTime::from_millis(chrono::Utc::now().timestamp_millis()),
         };
-        match collection.insert_one(event, None).await {
+        match self.login_repo.insert_event(event).await {
             Ok(_) => {
                 info!("📝 Stored login event for mobile: {}", mobile_no);
                 Ok(())
             }
             Err(e) => {
                 error!("❌ Failed to store login event for mobile {}: {}", mobile_no, e);
-                Err(Box::new(e))
+                Err(e)
             }
         }
     }
-    
+
     // Store login success event
-    pub async fn store_login_success_event(&self, socket_id: &str, mobile_no: &str, device_id: &str, session_token: &str, otp: i32) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let collection: Collection<LoginSuccessEvent> = self.db.collection("login_success_events");
+    async fn store_login_success_event(&self, socket_id: &str, mobile_no: &str, device_id: &str, session_token: &str, otp: i32, otp_expiry_secs: i64, is_new_user: bool) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let now = chrono::Utc::now();
-        let expires_at = now + chrono::Duration::minutes(30); // OTP expires in 30 minutes
+        let expires_at = now + chrono::Duration::seconds(otp_expiry_secs);
         
         let event = LoginSuccessEvent {
             id: None,
+            request_id: Correlation::current(),
             socket_id: socket_id.to_string(),
             mobile_no: mobile_no.to_string(),
             device_id: device_id.to_string(),
@@ -107,21 +400,188 @@ impl DataService {
             otp,
             timestamp: bson::DateTime::from_millis(now.timestamp_millis()),
             expires_at: bson::DateTime::from_millis(expires_at.timestamp_millis()),
+            is_new_user,
+            verified_at: None,
         };
-        match collection.insert_one(event, None).await {
+        match self.login_success_repo.insert_event(event).await {
             Ok(_) => {
                 info!("📝 Stored login success event for mobile: {} (OTP expires at: {})", mobile_no, expires_at);
                 Ok(())
             }
             Err(e) => {
                 error!("❌ Failed to store login success event for mobile {}: {}", mobile_no, e);
-                Err(Box::new(e))
+                Err(e)
             }
         }
     }
-    
+
+    // Queue an OTP for delivery by the SMS/email gateway
+    async fn store_otp_delivery_request(&self, request: OtpDeliveryRequest) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let collection: Collection<OtpDeliveryRequest> = self.collection("otp_delivery_queue");
+        let mobile_no = request.mobile_no.clone();
+        let channel = request.channel.clone();
+        collection.insert_one(request, None).await?;
+        info!("📝 Queued OTP delivery for mobile: {} (channel: {})", mobile_no, channel);
+        Ok(())
+    }
+
+    // Record use of the static test OTP
+    async fn store_test_otp_audit_event(&self, socket_id: &str, mobile_no: &str, device_id: Option<&str>, action: &str, is_success: bool) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let event = TestOtpAuditEvent {
+            id: None,
+            request_id: Correlation::current(),
+            socket_id: socket_id.to_string(),
+            mobile_no: mobile_no.to_string(),
+            device_id: device_id.map(|d| d.to_string()),
+            action: action.to_string(),
+            is_success,
+            timestamp: bson::DateTime::from_millis(chrono::Utc::now().timestamp_millis()),
+        };
+        self.test_otp_audit_repo.insert_event(event).await?;
+        warn!("🧪 Test OTP {} for mobile: {} (success: {}, socket: {})", action, mobile_no, is_success, socket_id);
+        Ok(())
+    }
+
+    // List a user's active (not removed) devices, most recently used first
+    async fn list_user_devices(&self, mobile_no: &str) -> Result<Vec<UserDevice>, Box<dyn std::error::Error + Send + Sync>> {
+        let collection: Collection<UserDevice> = self.collection("user_devices");
+        let filter = doc! { "mobile_no": mobile_no, "removed_at": bson::Bson::Null };
+        let options = mongodb::options::FindOptions::builder().sort(doc! { "last_seen_at": -1 }).build();
+        let devices = collection.find(filter, options).await?.try_collect().await?;
+        Ok(devices)
+    }
+
+    // Remove a device and revoke its sessions. Returns false if the device was not found.
+    async fn remove_user_device(&self, mobile_no: &str, device_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let collection: Collection<UserDevice> = self.collection("user_devices");
+        let filter = doc! { "mobile_no": mobile_no, "device_id": device_id, "removed_at": bson::Bson::Null };
+        let update = doc! { "$set": { "removed_at": bson::DateTime::from_millis(chrono::Utc::now().timestamp_millis()) } };
+        let result = collection.update_one(filter, update, None).await?;
+        if result.modified_count == 0 {
+            return Ok(false);
+        }
+        self.session_repo.revoke_device_sessions(mobile_no, device_id).await?;
+        info!("🗑️ Removed device {} for mobile: {}", device_id, mobile_no);
+        Ok(true)
+    }
+
+    async fn update_two_step(&self, mobile_no: &str, two_step: Option<&TwoStepFactor>) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let users: Collection<UserRegister> = self.collection("userregister");
+        let now = bson::DateTime::from_millis(chrono::Utc::now().timestamp_millis());
+        let update = match two_step {
+            Some(two_step) => doc! { "$set": { "two_step": bson::to_bson(two_step)?, "updated_at": now } },
+            None => doc! { "$unset": { "two_step": "" }, "$set": { "updated_at": now } },
+        };
+        let result = users.update_one(doc! { "mobile_no": mobile_no }, update, None).await?;
+        UserCache::invalidate_mobile(mobile_no);
+        Ok(result.matched_count > 0)
+    }
+
+    async fn set_date_of_birth(&self, mobile_no: &str, date_of_birth: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let users: Collection<UserRegister> = self.collection("userregister");
+        let now = bson::DateTime::from_millis(chrono::Utc::now().timestamp_millis());
+        let result = users.update_one(
+            doc! { "mobile_no": mobile_no, "date_of_birth": { "$exists": false } },
+            doc! { "$set": { "date_of_birth": date_of_birth, "updated_at": now } },
+            None,
+        ).await?;
+        UserCache::invalidate_mobile(mobile_no);
+        Ok(result.matched_count > 0)
+    }
+
+    async fn update_parental_controls(&self, mobile_no: &str, controls: Option<&ParentalControls>) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let users: Collection<UserRegister> = self.collection("userregister");
+        let now = bson::DateTime::from_millis(chrono::Utc::now().timestamp_millis());
+        let update = match controls {
+            Some(controls) => doc! { "$set": { "parental_controls": bson::to_bson(controls)?, "updated_at": now } },
+            None => doc! { "$unset": { "parental_controls": "" }, "$set": { "updated_at": now } },
+        };
+        let result = users.update_one(doc! { "mobile_no": mobile_no }, update, None).await?;
+        UserCache::invalidate_mobile(mobile_no);
+        Ok(result.matched_count > 0)
+    }
+
+    async fn store_two_step_reset(&self, reset: TwoStepReset) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let collection: Collection<TwoStepReset> = self.collection("two_step_resets");
+        collection.insert_one(reset, None).await?;
+        Ok(())
+    }
+
+    async fn store_mobile_change(&self, change: MobileChange) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let collection: Collection<MobileChange> = self.collection("mobile_changes");
+        collection.insert_one(change, None).await?;
+        Ok(())
+    }
+
+    async fn get_mobile_change(&self, user_id: &str, change_id: &str) -> Result<Option<MobileChange>, Box<dyn std::error::Error + Send + Sync>> {
+        let collection: Collection<MobileChange> = self.collection("mobile_changes");
+        Ok(collection.find_one(doc! { "user_id": user_id, "change_id": change_id }, None).await?)
+    }
+
+    async fn record_mobile_change_attempt(&self, change_id: &str) -> Result<u32, Box<dyn std::error::Error + Send + Sync>> {
+        let collection: Collection<MobileChange> = self.collection("mobile_changes");
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        let change = collection.find_one_and_update(doc! { "change_id": change_id }, doc! { "$inc": { "attempts": 1 } }, options).await?
+            .ok_or("mobile change not found")?;
+        Ok(change.attempts)
+    }
+
+    // Needs a replica set (as change streams do) for the transaction
+    async fn change_user_mobile(&self, audit: MobileChangeAudit) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let users: Collection<bson::Document> = self.collection("userregister");
+        if users.find_one(doc! { "mobile_no": &audit.new_mobile_no }, None).await?.is_some() {
+            return Ok(false);
+        }
+        let (old_mobile_no, new_mobile_no) = (audit.old_mobile_no.clone(), audit.new_mobile_no.clone());
+        let now = bson::DateTime::from_millis(chrono::Utc::now().timestamp_millis());
+
+        // Dropping the session on an early return aborts the transaction
+        let mut session = users.client().start_session(None).await?;
+        session.start_transaction(None).await?;
+        let moved = users.update_one_with_session(
+            doc! { "user_id": &audit.user_id, "mobile_no": &old_mobile_no },
+            doc! { "$set": { "mobile_no": &new_mobile_no, "updated_at": now } },
+            None,
+            &mut session,
+        ).await?;
+        if moved.matched_count == 0 {
+            session.abort_transaction().await?;
+            return Ok(false);
+        }
+        let follow = doc! { "$set": { "mobile_no": &new_mobile_no } };
+        self.collection::<bson::Document>("user_preferences")
+            .update_many_with_session(doc! { "user_id": &audit.user_id }, follow.clone(), None, &mut session).await?;
+        self.collection::<bson::Document>("user_devices")
+            .update_many_with_session(doc! { "mobile_no": &old_mobile_no }, follow, None, &mut session).await?;
+        self.collection::<bson::Document>("sessions")
+            .update_many_with_session(doc! { "mobile_no": &old_mobile_no, "revoked": false }, doc! { "$set": { "revoked": true } }, None, &mut session).await?;
+        if let Some(change_id) = &audit.change_id {
+            self.collection::<bson::Document>("mobile_changes")
+                .update_one_with_session(doc! { "change_id": change_id }, doc! { "$set": { "completed_at": now } }, None, &mut session).await?;
+        }
+        self.collection::<MobileChangeAudit>("mobile_change_audit").insert_one_with_session(audit, None, &mut session).await?;
+        session.commit_transaction().await?;
+
+        UserCache::invalidate_mobile(&old_mobile_no);
+        info!("📱 Moved user from mobile {} to {}", old_mobile_no, new_mobile_no);
+        Ok(true)
+    }
+
+    // Find a pending login session to reuse when a client retries `login`
+    async fn find_reusable_login_session(&self, socket_id: &str, mobile_no: &str, device_id: &str, session_token: Option<&str>) -> Result<Option<LoginSuccessEvent>, Box<dyn std::error::Error + Send + Sync>> {
+        let session = self.login_success_repo.find_active_unverified_session(mobile_no, device_id).await?
+            .filter(|s| s.socket_id == socket_id || session_token.is_some_and(|token| TokenGenerator::constant_time_eq(&s.session_token, token)));
+        if let Some(session) = &session {
+            self.login_success_repo.update_session_socket(mobile_no, &session.session_token, socket_id).await?;
+            info!("♻️ Reusing pending login session for mobile: {} (device: {})", mobile_no, device_id);
+        }
+        Ok(session)
+    }
+
     // Store OTP verification event
-    pub async fn store_otp_verification_event(
+    async fn store_otp_verification_event(
         &self,
         socket_id: &str,
         mobile_no: &str,
@@ -132,9 +592,9 @@ impl DataService {
         user_number: Option<u64>,
         jwt_token: Option<&str>,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let collection: Collection<OtpVerificationEvent> = self.db.collection("otp_verification_events");
         let event = OtpVerificationEvent {
             id: None,
+            request_id: Correlation::current(),
             socket_id: socket_id.to_string(),
             mobile_no: mobile_no.to_string(),
             session_token: session_token.to_string(),
@@ -145,13 +605,13 @@ impl DataService {
             jwt_token: jwt_token.map(|token| token.to_string()),
             timestamp: bson::DateTime::from_millis(chrono::Utc::now().timestamp_millis()),
         };
-        collection.insert_one(event, None).await?;
+        self.otp_verification_repo.insert_event(event).await?;
         info!("📝 Stored OTP verification event for mobile: {} (success: {})", mobile_no, is_success);
         Ok(())
     }
-    
+
     // Store user registration event
-    pub async fn store_user_registration_event(
+    async fn store_user_registration_event(
         &self,
         socket_id: &str,
         user_id: &str,
@@ -161,9 +621,9 @@ impl DataService {
         fcm_token: &str,
         email: Option<&str>,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let collection: Collection<UserRegistrationEvent> = self.db.collection("user_registration_events");
         let event = UserRegistrationEvent {
             id: None,
+            request_id: Correlation::current(),
             socket_id: socket_id.to_string(),
             user_id: user_id.to_string(),
             user_number,
@@ -173,13 +633,13 @@ impl DataService {
             email: email.map(|e| e.to_string()),
             timestamp: bson::DateTime::from_millis(chrono::Utc::now().timestamp_millis()),
         };
-        collection.insert_one(event, None).await?;
+        self.user_registration_repo.insert_event(event).await?;
         info!("📝 Stored user registration event for user: {} (number: {})", user_id, user_number);
         Ok(())
     }
-    
+
     // Store user profile event
-    pub async fn store_user_profile_event(
+    async fn store_user_profile_event(
         &self,
         socket_id: &str,
         user_id: &str,
@@ -187,9 +647,9 @@ impl DataService {
         mobile_no: &str,
         full_name: &str,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let collection: Collection<UserProfileEvent> = self.db.collection("user_profile_events");
         let event = UserProfileEvent {
             id: None,
+            request_id: Correlation::current(),
             socket_id: socket_id.to_string(),
             user_id: user_id.to_string(),
             user_number,
@@ -197,13 +657,13 @@ impl DataService {
             full_name: full_name.to_string(),
             timestamp: bson::DateTime::from_millis(chrono::Utc::now().timestamp_millis()),
         };
-        collection.insert_one(event, None).await?;
+        self.user_profile_repo.insert_event(event).await?;
         info!("📝 Stored user profile event for user: {} (number: {})", user_id, user_number);
         Ok(())
     }
-    
+
     // Store language setting event
-    pub async fn store_language_setting_event(
+    async fn store_language_setting_event(
         &self,
         socket_id: &str,
         user_id: &str,
@@ -215,9 +675,9 @@ impl DataService {
         timezone: Option<&str>,
         user_preferences: &serde_json::Value,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let collection: Collection<LanguageSettingEvent> = self.db.collection("language_setting_events");
         let event = LanguageSettingEvent {
             id: None,
+            request_id: Correlation::current(),
             socket_id: socket_id.to_string(),
             user_id: user_id.to_string(),
             user_number,
@@ -229,13 +689,13 @@ impl DataService {
             user_preferences: user_preferences.clone(),
             timestamp: bson::DateTime::from_millis(chrono::Utc::now().timestamp_millis()),
         };
-        collection.insert_one(event, None).await?;
+        self.language_setting_repo.insert_event(event).await?;
         info!("📝 Stored language setting event for user: {} (number: {})", user_id, user_number);
         Ok(())
     }
-    
+
     // Store connection error event
-    pub async fn store_connection_error_event(
+    async fn store_connection_error_event(
         &self,
         socket_id: &str,
         error_code: &str,
@@ -244,7 +704,6 @@ impl DataService {
         message: &str,
         payload: bson::Document,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let collection: Collection<ConnectionErrorEvent> = self.db.collection("connection_error_events");
         let event = ConnectionErrorEvent::new(
             socket_id.to_string(),
             error_code.to_string(),
@@ -253,171 +712,926 @@ impl DataService {
             message.to_string(),
             payload,
         );
-        match collection.insert_one(event, None).await {
+        match self.connection_error_repo.insert_event(event).await {
             Ok(_) => {
                 info!("📝 Stored connection error event for socket: {} (error: {})", socket_id, error_code);
                 Ok(())
             }
             Err(e) => {
                 error!("❌ Failed to store connection error event for socket {}: {}", socket_id, e);
-                Err(Box::new(e))
+                Err(e)
             }
         }
     }
-    
-    // Check if user exists
-    pub async fn user_exists(&self, mobile_no: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
-        self.user_register_repo.user_exists(mobile_no).await
-    }
-    
-    // Get user by mobile number
-    pub async fn get_user_by_mobile(&self, mobile_no: &str) -> Result<Option<UserRegister>, Box<dyn std::error::Error + Send + Sync>> {
-        self.user_register_repo.find_user_by_mobile(mobile_no).await
+
+    async fn connection_error_counts(&self, query: &ErrorStatsQuery) -> Result<Vec<ErrorCountBucket>, Box<dyn std::error::Error + Send + Sync>> {
+        self.connection_error_repo.count_by_bucket(query).await
     }
-    
-    // Register new user with UUID v7 and sequential numbering
-    pub async fn register_new_user(
+
+    async fn connection_error_offenders(
         &self,
-        mobile_no: &str,
-        device_id: &str,
-        fcm_token: &str,
-        email: Option<&str>,
-    ) -> Result<(String, u64), Box<dyn std::error::Error + Send + Sync>> {
-        // Get next user number
-        let user_number = self.get_next_user_number().await;
-        
-        // Create new user with UUID v7
-        let user = UserRegister::new(
-            mobile_no.to_string(),
-            device_id.to_string(),
-            fcm_token.to_string(),
-            email.map(|e| e.to_string()),
-            user_number,
-        );
-        
-        let user_id = user.user_id.clone();
-        
-        // Insert user using the repository
-        self.user_register_repo.create_user_register(&user).await?;
-        
-        info!("🆕 Registered new user: {} (number: {})", user_id, user_number);
-        Ok((user_id, user_number))
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+        limit: i64,
+    ) -> Result<ErrorOffenders, Box<dyn std::error::Error + Send + Sync>> {
+        let from = bson::DateTime::from_millis(from.timestamp_millis());
+        let to = bson::DateTime::from_millis(to.timestamp_millis());
+        self.connection_error_repo.top_offenders(from, to, limit).await
     }
-    
-    // Update user login info
-    pub async fn update_user_login_info(&self, mobile_no: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        self.user_register_repo.update_user_login_info(mobile_no).await
+
+    // Store turn timing data (used for anti-stall detection)
+    async fn store_turn_timing_event(&self, event: TurnTimingEvent) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let room_id = event.room_id.clone();
+        let turn_number = event.turn_number;
+        self.turn_timing_repo.insert_event(event).await?;
+        info!("📝 Stored turn timing event for room: {} (turn: {})", room_id, turn_number);
+        Ok(())
     }
-    
-    // Update user FCM token
-    pub async fn update_user_fcm_token(&self, mobile_no: &str, fcm_token: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let collection: Collection<UserRegister> = self.db.collection("userregister");
-        let filter = doc! { "mobile_no": mobile_no };
-        let update = doc! {
-            "$set": {
-                "fcm_token": fcm_token,
-                "updated_at": bson::DateTime::from_millis(chrono::Utc::now().timestamp_millis())
-            }
-        };
-        collection.update_one(filter, update, None).await?;
-        info!("🔄 Updated FCM token for mobile: {}", mobile_no);
+
+    // Store match history record
+    async fn store_match_record(&self, record: MatchRecord) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let collection: Collection<MatchRecord> = self.collection("match_history");
+        let match_id = record.match_id.clone();
+        let is_bot_match = record.is_bot_match;
+        collection.insert_one(record, None).await?;
+        info!("📝 Stored match record: {} (bot match: {})", match_id, is_bot_match);
         Ok(())
     }
-    
-    // Update user profile
-    pub async fn update_user_profile(&self, mobile_no: &str, full_name: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        self.user_register_repo.update_user_profile(
-            mobile_no, 
-            Some(full_name.to_string()), 
-            None, 
-            None, 
-            None, 
-            None
-        ).await
+
+    async fn save_room_snapshot(&self, snapshot: RoomSnapshot) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.room_snapshot_repo.save_snapshot(&snapshot).await
     }
-    
-    // Update user language settings
-    pub async fn update_user_language_in_register(
-        &self,
-        mobile_no: &str,
-        language_code: Option<String>,
-        language_name: Option<String>,
-        region_code: Option<String>,
-        timezone: Option<String>,
-        user_preferences: serde_json::Value,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        self.user_register_repo.update_user_language_settings(
-            mobile_no,
-            language_code,
-            language_name,
-            region_code,
-            timezone,
-            Some(user_preferences)
-        ).await
+
+    async fn load_room_snapshots(&self, since: chrono::DateTime<chrono::Utc>) -> Result<Vec<RoomSnapshot>, Box<dyn std::error::Error + Send + Sync>> {
+        self.room_snapshot_repo.find_saved_since(bson::DateTime::from_millis(since.timestamp_millis())).await
     }
-    
-    // Verify OTP and return user info
-    pub async fn verify_otp(&self, _socket_id: &str, mobile_no: &str, session_token: &str, otp: &str) -> Result<OtpVerificationResult, Box<dyn std::error::Error + Send + Sync>> {
-        // Find the login success event for this mobile number and session token
-        let login_success_event = self.login_success_repo.find_login_success_by_mobile_and_session(mobile_no, session_token).await?;
-        
-        match login_success_event {
-            Some(event) => {
-                // Check if the OTP session has expired
-                let now = chrono::Utc::now();
-                let expires_at = chrono::DateTime::from_timestamp_millis(event.expires_at.timestamp_millis())
-                    .unwrap_or(chrono::Utc::now());
-                
-                if now > expires_at {
-                    info!("⏰ OTP session expired for mobile: {} (expired at: {}, current time: {})", 
-                          mobile_no, expires_at, now);
-                    return Ok(OtpVerificationResult::Expired);
-                }
-                
-                // Compare the provided OTP with the stored OTP
-                let stored_otp = event.otp.to_string();
-                let provided_otp = otp.to_string();
-                
-                let is_valid = provided_otp == stored_otp;
-                
-                info!("🔢 OTP verification for mobile: {} (provided: {}, stored: {}, valid: {}, expires: {})", 
-                      mobile_no, provided_otp, stored_otp, is_valid, expires_at);
-                
-                if is_valid {
-                    Ok(OtpVerificationResult::Success)
-                } else {
-                    Ok(OtpVerificationResult::Invalid)
+
+    async fn archive_room(&self, room: ArchivedRoom) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.archived_room_repo.insert(&room).await?;
+        self.room_snapshot_repo.delete_room(&room.room_id).await
+    }
+
+    async fn create_escrow(&self, escrow: MatchEscrow) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.match_escrow_repo.insert(&escrow).await?;
+        Ok(())
+    }
+
+    async fn get_held_escrow(&self, room_id: &str) -> Result<Option<MatchEscrow>, Box<dyn std::error::Error + Send + Sync>> {
+        self.match_escrow_repo.find_held(room_id).await
+    }
+
+    async fn report_escrow_result(&self, escrow_id: &str, player_id: &str, outcome: GameOutcome) -> Result<Option<MatchEscrow>, Box<dyn std::error::Error + Send + Sync>> {
+        self.match_escrow_repo.report_result(escrow_id, player_id, outcome).await
+    }
+
+    async fn begin_escrow_settlement(&self, escrow_id: &str, status: EscrowStatus, payouts: Vec<EscrowPayout>, rake: i64) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        self.match_escrow_repo.begin_settlement(escrow_id, status, &payouts, rake).await
+    }
+
+    async fn finish_escrow_opening(&self, escrow_id: &str, status: EscrowStatus, stakes: Vec<EscrowStake>, payouts: Vec<EscrowPayout>) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        self.match_escrow_repo.finish_opening(escrow_id, status, &stakes, &payouts).await
+    }
+
+    async fn stale_escrow_openings(&self, before: chrono::DateTime<chrono::Utc>) -> Result<Vec<MatchEscrow>, Box<dyn std::error::Error + Send + Sync>> {
+        self.match_escrow_repo.find_opening_before(bson::DateTime::from_millis(before.timestamp_millis())).await
+    }
+
+    async fn finish_escrow_settlement(&self, escrow_id: &str, status: EscrowStatus) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.match_escrow_repo.finish_settlement(escrow_id, status).await
+    }
+
+    async fn unfinished_escrow_settlements(&self, before: chrono::DateTime<chrono::Utc>) -> Result<Vec<MatchEscrow>, Box<dyn std::error::Error + Send + Sync>> {
+        self.match_escrow_repo.find_settling_before(bson::DateTime::from_millis(before.timestamp_millis())).await
+    }
+
+    async fn record_revenue(&self, entry: RevenueEntry) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        self.revenue_ledger_repo.insert_once(&entry).await
+    }
+
+    async fn revenue_entries(&self, day: &str, game_type: Option<&str>, page: u64, page_size: i64) -> Result<Vec<RevenueEntry>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut filter = doc! { "day": day };
+        if let Some(game_type) = game_type {
+            filter.insert("game_type", game_type);
+        }
+        self.revenue_ledger_repo.find_page(filter, doc! { "recorded_at": 1 }, page, page_size).await
+    }
+
+    async fn revenue_day_totals(&self, day: &str) -> Result<Vec<RevenueRollup>, Box<dyn std::error::Error + Send + Sync>> {
+        self.revenue_ledger_repo.day_totals(day).await
+    }
+
+    async fn save_revenue_rollups(&self, day: &str, rollups: Vec<RevenueRollup>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.revenue_rollup_repo.replace_day(day, &rollups).await
+    }
+
+    async fn revenue_rollups(&self, from: &str, to: &str) -> Result<Vec<RevenueRollup>, Box<dyn std::error::Error + Send + Sync>> {
+        let filter = doc! { "day": { "$gte": from, "$lte": to } };
+        Ok(self.revenue_rollup_repo.find_stream(filter, doc! { "day": 1, "game_type": 1 }).await?.try_collect().await?)
+    }
+
+    async fn create_promo_code(&self, promo: PromoCode) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        self.promo_code_repo.insert_once(&promo).await
+    }
+
+    async fn list_promo_codes(&self) -> Result<Vec<PromoCode>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.promo_code_repo.find_stream(doc! {}, doc! { "created_at": -1 }).await?.try_collect().await?)
+    }
+
+    async fn set_promo_code_active(&self, code: &str, active: bool) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        self.promo_code_repo.set_active(code, active).await
+    }
+
+    // Needs a replica set for the transaction. Every redemption updates the
+    // code's document, so concurrent ones conflict and are retried against the
+    // new use count; the caps cannot be overrun. The promo.redeemed outbox
+    // messages commit with it.
+    async fn redeem_promo_code(&self, mut redemption: PromoRedemption) -> Result<PromoRedeem, Box<dyn std::error::Error + Send + Sync>> {
+        let mut attempt = 1;
+        loop {
+            // Dropping the session on an early return aborts the transaction
+            let mut session = self.collection::<PromoCode>("promo_codes").client().start_session(None).await?;
+            session.start_transaction(None).await?;
+            let result = match self.redeem_promo_code_in_session(&mut session, &mut redemption).await {
+                Ok(redeemed @ PromoRedeem::Redeemed { .. }) => WalletService::commit(&mut session).await.map(|_| redeemed),
+                Ok(refused) => return Ok(refused),
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(redeemed) => {
+                    if let PromoRedeem::Redeemed { reward, held, .. } = &redeemed {
+                        info!("🎟️ User {} redeemed promo code {} ({} coins, {} items)", redemption.user_id, redemption.code, reward.coins, held.len());
+                    }
+                    return Ok(redeemed);
+                }
+                Err(e) if WalletService::is_conflict(&*e) && attempt < TRANSACTION_ATTEMPTS => attempt += 1,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    async fn list_promo_redemptions(&self, code: &str, page: u64, page_size: i64) -> Result<Vec<PromoRedemption>, Box<dyn std::error::Error + Send + Sync>> {
+        self.promo_redemption_repo.find_page(doc! { "code": code }, doc! { "redeemed_at": -1 }, page, page_size).await
+    }
+
+    async fn start_impersonation(&self, session: ImpersonationSession) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.impersonation_repo.insert(&session).await?;
+        Ok(())
+    }
+
+    async fn end_impersonation(&self, session_id: &str, end_reason: &str, events_mirrored: u64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.impersonation_repo.end(session_id, end_reason, events_mirrored).await
+    }
+
+    async fn list_impersonations(&self, user_id: Option<&str>, operator_id: Option<&str>, page: u64, page_size: i64) -> Result<Vec<ImpersonationSession>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut filter = doc! {};
+        if let Some(user_id) = user_id {
+            filter.insert("user_id", user_id);
+        }
+        if let Some(operator_id) = operator_id {
+            filter.insert("operator_id", operator_id);
+        }
+        self.impersonation_repo.find_page(filter, doc! { "started_at": -1 }, page, page_size).await
+    }
+
+    async fn user_timeline(&self, user: &UserRegister, sources: &[&'static TimelineSource], before: Option<bson::DateTime>, limit: i64) -> Result<Vec<TimelineEntry>, Box<dyn std::error::Error + Send + Sync>> {
+        let subject = self.timeline_subject(user).await?;
+        let mut entries = Vec::new();
+        // `limit` from each source is enough for the newest `limit` overall
+        for source in sources {
+            let Some(filter) = source.filter(&subject, before) else {
+                continue;
+            };
+            let options = mongodb::options::FindOptions::builder().sort(doc! { source.time_field: -1 }).limit(limit).build();
+            let mut cursor = self.collection::<bson::Document>(source.collection).find(filter, options).await?;
+            while let Some(document) = cursor.try_next().await? {
+                entries.extend(source.entry(document));
+            }
+        }
+        Ok(Timeline::merge(entries, limit))
+    }
+
+    async fn record_session_sample(&self, sample: SessionSample) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let collection: Collection<SessionSample> = self.collection("session_metrics");
+        let filter = doc! { "node_id": sample.node_id as i64, "minute": sample.minute };
+        collection.replace_one(filter, sample, mongodb::options::ReplaceOptions::builder().upsert(true).build()).await?;
+        Ok(())
+    }
+
+    async fn session_samples(&self, from: bson::DateTime, to: bson::DateTime) -> Result<Vec<SessionSample>, Box<dyn std::error::Error + Send + Sync>> {
+        let collection: Collection<SessionSample> = self.collection("session_metrics");
+        let options = mongodb::options::FindOptions::builder().sort(doc! { "minute": 1, "node_id": 1 }).build();
+        Ok(collection.find(doc! { "minute": { "$gte": from, "$lt": to } }, options).await?.try_collect().await?)
+    }
+
+    async fn record_compliance_audit(&self, audit: ComplianceAudit) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let collection: Collection<ComplianceAudit> = self.collection("compliance_audit");
+        collection.insert_one(audit, None).await?;
+        Ok(())
+    }
+
+    async fn record_afk_event(&self, event: AfkEvent) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let collection: Collection<AfkEvent> = self.collection("afk_events");
+        collection.insert_one(event, None).await?;
+        Ok(())
+    }
+
+    async fn afk_events_since(&self, user_id: &str, since: bson::DateTime) -> Result<Vec<AfkEvent>, Box<dyn std::error::Error + Send + Sync>> {
+        let collection: Collection<AfkEvent> = self.collection("afk_events");
+        let options = mongodb::options::FindOptions::builder().sort(doc! { "created_at": -1 }).build();
+        Ok(collection.find(doc! { "user_id": user_id, "created_at": { "$gte": since } }, options).await?.try_collect().await?)
+    }
+
+    async fn save_dealer_audit(&self, audit: DealerAudit) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let collection: Collection<DealerAudit> = self.collection("dealer_audits");
+        collection.insert_one(audit, None).await?;
+        Ok(())
+    }
+
+    async fn retention_cohorts(&self, since: chrono::DateTime<chrono::Utc>, now: chrono::DateTime<chrono::Utc>, days: &[u32]) -> Result<Vec<RetentionCohort>, Box<dyn std::error::Error + Send + Sync>> {
+        self.user_register_repo.retention_cohorts(since, now, days).await
+    }
+
+    async fn save_retention_report(&self, report: RetentionReport) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let collection: Collection<RetentionReport> = self.collection("retention_reports");
+        let options = mongodb::options::ReplaceOptions::builder().upsert(true).build();
+        collection.replace_one(doc! { "report_date": &report.report_date }, report, options).await?;
+        Ok(())
+    }
+
+    async fn latest_retention_report(&self) -> Result<Option<RetentionReport>, Box<dyn std::error::Error + Send + Sync>> {
+        let collection: Collection<RetentionReport> = self.collection("retention_reports");
+        let options = mongodb::options::FindOneOptions::builder().sort(doc! { "report_date": -1 }).build();
+        Ok(collection.find_one(doc! {}, options).await?)
+    }
+
+    async fn get_gameplay_progress(&self, user_id: &str) -> Result<Option<GameplayProgress>, Box<dyn std::error::Error + Send + Sync>> {
+        self.gameplay.get_gameplay_progress(user_id).await
+    }
+
+    async fn update_gameplay_progress(&self, user_id: &str, update: &ProgressUpdate) -> Result<GameplayProgress, Box<dyn std::error::Error + Send + Sync>> {
+        self.gameplay.update_gameplay_progress(user_id, update).await
+    }
+
+    async fn get_daily_challenges(&self, date: &str) -> Result<Option<DailyChallengeSet>, Box<dyn std::error::Error + Send + Sync>> {
+        self.daily_challenge_repo.find_one(doc! { "date": date }).await
+    }
+
+    async fn create_daily_challenges(&self, set: DailyChallengeSet) -> Result<DailyChallengeSet, Box<dyn std::error::Error + Send + Sync>> {
+        self.daily_challenge_repo.insert_if_absent(&set).await
+    }
+
+    async fn get_challenge_progress(&self, user_id: &str, date: &str) -> Result<Option<ChallengeProgress>, Box<dyn std::error::Error + Send + Sync>> {
+        self.challenge_progress_repo.find_one(doc! { "user_id": user_id, "date": date }).await
+    }
+
+    async fn add_challenge_progress(&self, user_id: &str, date: &str, increments: &std::collections::HashMap<String, i64>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.challenge_progress_repo.add_progress(user_id, date, increments).await
+    }
+
+    async fn mark_challenge_claimed(&self, user_id: &str, date: &str, challenge_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.challenge_progress_repo.mark_claimed(user_id, date, challenge_id).await
+    }
+
+    async fn get_wallet_balance(&self, user_id: &str) -> Result<i64, Box<dyn std::error::Error + Send + Sync>> {
+        self.wallet.balance(user_id).await
+    }
+
+    async fn credit_wallet(&self, user_id: &str, amount: i64, reason: &str, reference: &str) -> Result<Option<i64>, Box<dyn std::error::Error + Send + Sync>> {
+        self.wallet.credit(user_id, amount, reason, reference).await
+    }
+
+    async fn create_season(&self, season: Season) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.season_repo.insert(&season).await?;
+        Ok(())
+    }
+
+    async fn list_seasons(&self) -> Result<Vec<Season>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.season_repo.find_stream(doc! {}, doc! { "start_at": -1 }).await?.try_collect().await?)
+    }
+
+    async fn get_season(&self, season_id: &str) -> Result<Option<Season>, Box<dyn std::error::Error + Send + Sync>> {
+        self.season_repo.find_one(doc! { "season_id": season_id }).await
+    }
+
+    async fn seasons_with_status(&self, status: SeasonStatus) -> Result<Vec<Season>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.season_repo.find_stream(doc! { "status": status.as_str() }, doc! { "start_at": 1 }).await?.try_collect().await?)
+    }
+
+    async fn transition_season(&self, season_id: &str, from: SeasonStatus, to: SeasonStatus) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        self.season_repo.transition(season_id, from, to).await
+    }
+
+    async fn record_season_result(&self, season_id: &str, user_id: &str, outcome: GameOutcome, rating_change: i64, base_rating: i64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.season_rating_repo.record_result(season_id, user_id, outcome, rating_change, base_rating).await
+    }
+
+    async fn season_leaderboard(&self, season_id: &str, offset: u64, limit: i64) -> Result<Vec<SeasonRating>, Box<dyn std::error::Error + Send + Sync>> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "rating": -1, "updated_at": 1 })
+            .skip(offset)
+            .limit(limit)
+            .build();
+        let collection: Collection<SeasonRating> = self.collection("season_ratings");
+        Ok(collection.find(doc! { "season_id": season_id }, options).await?.try_collect().await?)
+    }
+
+    async fn get_season_rating(&self, season_id: &str, user_id: &str) -> Result<Option<SeasonRating>, Box<dyn std::error::Error + Send + Sync>> {
+        self.season_rating_repo.find_one(doc! { "season_id": season_id, "user_id": user_id }).await
+    }
+
+    async fn season_rank(&self, rating: &SeasonRating) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.season_rating_repo.count_ahead(rating).await? + 1)
+    }
+
+    async fn carry_over_season_ratings(&self, from: &str, to: &str, base_rating: i64, factor: f64) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        self.season_rating_repo.carry_over(from, to, base_rating, factor).await
+    }
+
+    async fn debit_wallet(&self, user_id: &str, amount: i64, reason: &str, reference: &str, daily_limit: Option<i64>) -> Result<WalletDebit, Box<dyn std::error::Error + Send + Sync>> {
+        self.wallet.debit(user_id, amount, reason, reference, daily_limit).await
+    }
+
+    async fn wallet_reference_applied(&self, reference: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.wallet.find_transaction(reference).await?.is_some())
+    }
+
+    async fn wallet_spent_since(&self, user_id: &str, since: chrono::DateTime<chrono::Utc>) -> Result<i64, Box<dyn std::error::Error + Send + Sync>> {
+        self.wallet.spent_since(user_id, since).await
+    }
+
+    async fn get_inventory(&self, user_id: &str) -> Result<Vec<InventoryItem>, Box<dyn std::error::Error + Send + Sync>> {
+        self.inventory.list(user_id).await
+    }
+
+    async fn add_friend(&self, user_id: &str, friend_user_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let friendship = Friendship {
+            id: None,
+            user_id: user_id.to_string(),
+            friend_user_id: friend_user_id.to_string(),
+            created_at: bson::DateTime::from_millis(chrono::Utc::now().timestamp_millis()),
+        };
+        self.friendship_repo.add(&friendship).await
+    }
+
+    async fn remove_friend(&self, user_id: &str, friend_user_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        self.friendship_repo.remove_pair(user_id, friend_user_id).await
+    }
+
+    async fn are_friends(&self, user_id: &str, other_user_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        self.friendship_repo.is_mutual(user_id, other_user_id).await
+    }
+
+    async fn list_friendships(&self, user_id: &str) -> Result<Vec<Friendship>, Box<dyn std::error::Error + Send + Sync>> {
+        self.friendship_repo.find_involving(user_id).await
+    }
+
+    // Needs a replica set for the transaction; a lost write conflict is
+    // retried like a wallet change
+    async fn send_gift(&self, gift: Gift, limits: GiftLimits, daily_limit: Option<i64>) -> Result<GiftSend, Box<dyn std::error::Error + Send + Sync>> {
+        let mut attempt = 1;
+        loop {
+            // Dropping the session on an early return aborts the transaction
+            let mut session = self.collection::<Gift>("gifts").client().start_session(None).await?;
+            session.start_transaction(None).await?;
+            let result = match self.send_gift_in_session(&mut session, &gift, limits, daily_limit).await {
+                Ok(GiftSend::Sent(left)) => WalletService::commit(&mut session).await.map(|_| GiftSend::Sent(left)),
+                Ok(refused) => return Ok(refused),
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(sent) => {
+                    info!("🎁 Gift {} moved from user {} to {}", gift.gift_id, gift.from_user_id, gift.to_user_id);
+                    return Ok(sent);
+                }
+                Err(e) if WalletService::is_conflict(&*e) && attempt < TRANSACTION_ATTEMPTS => attempt += 1,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    async fn gifts_since(&self, since: chrono::DateTime<chrono::Utc>) -> Result<Vec<Gift>, Box<dyn std::error::Error + Send + Sync>> {
+        let filter = doc! { "created_at": { "$gte": bson::DateTime::from_millis(since.timestamp_millis()) } };
+        Ok(self.gift_repo.find_stream(filter, doc! { "created_at": 1 }).await?.try_collect().await?)
+    }
+
+    async fn record_match_participant(&self, participant: MatchParticipant) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.match_participant_repo.insert(&participant).await?;
+        Ok(())
+    }
+
+    async fn record_match_result(&self, result: MatchResult) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.match_result_repo.insert(&result).await?;
+        Ok(())
+    }
+
+    async fn match_participants_since(&self, since: chrono::DateTime<chrono::Utc>) -> Result<Vec<MatchParticipant>, Box<dyn std::error::Error + Send + Sync>> {
+        let filter = doc! { "joined_at": { "$gte": bson::DateTime::from_millis(since.timestamp_millis()) } };
+        Ok(self.match_participant_repo.find_stream(filter, doc! { "joined_at": 1 }).await?.try_collect().await?)
+    }
+
+    async fn match_results_since(&self, since: chrono::DateTime<chrono::Utc>) -> Result<Vec<MatchResult>, Box<dyn std::error::Error + Send + Sync>> {
+        let filter = doc! { "reported_at": { "$gte": bson::DateTime::from_millis(since.timestamp_millis()) } };
+        Ok(self.match_result_repo.find_stream(filter, doc! { "reported_at": 1 }).await?.try_collect().await?)
+    }
+
+    async fn get_user_risk(&self, user_id: &str) -> Result<Option<UserRisk>, Box<dyn std::error::Error + Send + Sync>> {
+        self.user_risk_repo.find_one(doc! { "user_id": user_id }).await
+    }
+
+    async fn save_user_risk(&self, risk: UserRisk) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.user_risk_repo.save(&risk).await
+    }
+
+    async fn clear_stale_user_risk(&self, before: bson::DateTime) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        self.user_risk_repo.clear_stale(before).await
+    }
+
+    async fn create_risk_flag(&self, flag: RiskFlag) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        self.risk_flag_repo.open(&flag).await
+    }
+
+    async fn list_risk_flags(&self, status: Option<&str>, page: u64, page_size: i64) -> Result<Vec<RiskFlag>, Box<dyn std::error::Error + Send + Sync>> {
+        let filter = status.map_or_else(|| doc! {}, |status| doc! { "status": status });
+        self.risk_flag_repo.find_page(filter, doc! { "created_at": -1 }, page, page_size).await
+    }
+
+    async fn resolve_risk_flag(&self, flag_id: &str, status: &str, operator_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        self.risk_flag_repo.resolve(flag_id, status, operator_id).await
+    }
+
+    async fn dismissed_risk_flag_keys(&self) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+        self.risk_flag_repo.dismissed_keys().await
+    }
+
+    async fn block_user(&self, user_id: &str, blocked_user_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let block = UserBlock {
+            id: None,
+            user_id: user_id.to_string(),
+            blocked_user_id: blocked_user_id.to_string(),
+            created_at: bson::DateTime::now(),
+        };
+        self.user_block_repo.add(&block).await
+    }
+
+    async fn unblock_user(&self, user_id: &str, blocked_user_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        self.user_block_repo.remove(user_id, blocked_user_id).await
+    }
+
+    async fn list_blocks(&self, user_id: &str) -> Result<Vec<UserBlock>, Box<dyn std::error::Error + Send + Sync>> {
+        self.user_block_repo.find_involving(user_id).await
+    }
+
+    async fn create_user_report(&self, report: UserReport) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.user_report_repo.insert(&report).await?;
+        Ok(())
+    }
+
+    async fn reports_filed_since(&self, reporter_user_id: &str, since: chrono::DateTime<chrono::Utc>) -> Result<Vec<UserReport>, Box<dyn std::error::Error + Send + Sync>> {
+        self.user_report_repo.find_by_reporter_since(reporter_user_id, bson::DateTime::from_millis(since.timestamp_millis())).await
+    }
+
+    async fn list_user_reports(&self, status: Option<&str>, category: Option<ReportCategory>, page: u64, page_size: i64) -> Result<Vec<UserReport>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut filter = doc! {};
+        if let Some(status) = status {
+            filter.insert("status", status);
+        }
+        if let Some(category) = category {
+            filter.insert("category", category.as_str());
+        }
+        self.user_report_repo.find_page(filter, doc! { "created_at": 1 }, page, page_size).await
+    }
+
+    async fn get_user_report(&self, report_id: &str) -> Result<Option<UserReport>, Box<dyn std::error::Error + Send + Sync>> {
+        self.user_report_repo.find_one(doc! { "report_id": report_id }).await
+    }
+
+    async fn resolve_user_report(&self, report_id: &str, status: &str, resolution: &str, operator_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        self.user_report_repo.resolve(report_id, status, resolution, operator_id).await
+    }
+
+    async fn create_sanction(&self, sanction: UserSanction) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.user_sanction_repo.insert(&sanction).await?;
+        Ok(())
+    }
+
+    async fn list_sanctions(&self, user_id: &str) -> Result<Vec<UserSanction>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.user_sanction_repo.find_stream(doc! { "user_id": user_id }, doc! { "created_at": -1 }).await?.try_collect().await?)
+    }
+
+    async fn lift_sanction(&self, sanction_id: &str, operator_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        self.user_sanction_repo.lift(sanction_id, operator_id).await
+    }
+
+    async fn revoke_user_sessions(&self, mobile_no: &str) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        self.session_repo.revoke_user_sessions(mobile_no).await
+    }
+
+    async fn save_chat_message(&self, message: ChatMessage) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.chat_message_repo.insert(&message).await?;
+        Ok(())
+    }
+
+    async fn get_chat_message(&self, message_id: &str) -> Result<Option<ChatMessage>, Box<dyn std::error::Error + Send + Sync>> {
+        self.chat_message_repo.find_one(doc! { "message_id": message_id }).await
+    }
+
+    async fn edit_chat_message(&self, message_id: &str, message: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        self.chat_message_repo.edit(message_id, message).await
+    }
+
+    async fn delete_chat_message(&self, message_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        self.chat_message_repo.tombstone(message_id).await
+    }
+
+    async fn chat_history(&self, room_id: &str, before: Option<&str>, excluded_user_ids: &[String], limit: i64) -> Result<Vec<ChatMessage>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut filter = doc! { "room_id": room_id, "user_id": { "$nin": excluded_user_ids } };
+        if let Some(before) = before {
+            filter.insert("message_id", doc! { "$lt": before });
+        }
+        self.chat_message_repo.find_page(filter, doc! { "message_id": -1 }, 0, limit).await
+    }
+
+    async fn purge_chat_messages(&self, before: chrono::DateTime<chrono::Utc>) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        let before = bson::DateTime::from_millis(before.timestamp_millis());
+        // Reactions come after their message, so none outlive it for long
+        self.chat_reaction_repo.purge_before(before).await?;
+        self.chat_message_repo.purge_before(before).await
+    }
+
+    async fn react_to_chat_message(&self, reaction: ChatReaction, add: bool) -> Result<Option<i64>, Box<dyn std::error::Error + Send + Sync>> {
+        let changed = if add {
+            self.chat_reaction_repo.add(&reaction).await?
+        } else {
+            self.chat_reaction_repo.remove(&reaction.message_id, &reaction.user_id, &reaction.reaction).await?
+        };
+        if !changed {
+            return Ok(None);
+        }
+        self.chat_message_repo.bump_reaction(&reaction.message_id, &reaction.reaction, if add { 1 } else { -1 }).await
+    }
+
+    async fn record_chat_audit(&self, audit: ChatMessageAudit) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.chat_audit_repo.insert(&audit).await?;
+        Ok(())
+    }
+
+    async fn mark_chat_read(&self, room_id: &str, user_id: &str, message_id: &str) -> Result<ChatReadReceipt, Box<dyn std::error::Error + Send + Sync>> {
+        let collection: Collection<ChatReadReceipt> = self.collection("chat_read_receipts");
+        // Snowflake ids sort as strings, so $max keeps the newest message
+        let update = doc! {
+            "$max": { "message_id": message_id },
+            "$set": { "read_at": bson::DateTime::now() }
+        };
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .upsert(true)
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        let receipt = collection.find_one_and_update(doc! { "room_id": room_id, "user_id": user_id }, update, options).await?;
+        Ok(receipt.ok_or("read receipt was not stored")?)
+    }
+
+    async fn chat_read_receipts(&self, room_id: &str, excluded_user_ids: &[String]) -> Result<Vec<ChatReadReceipt>, Box<dyn std::error::Error + Send + Sync>> {
+        let collection: Collection<ChatReadReceipt> = self.collection("chat_read_receipts");
+        let filter = doc! { "room_id": room_id, "user_id": { "$nin": excluded_user_ids } };
+        Ok(collection.find(filter, None).await?.try_collect().await?)
+    }
+
+    async fn list_game_configs(&self, game_type: Option<&str>) -> Result<Vec<GameConfig>, Box<dyn std::error::Error + Send + Sync>> {
+        let filter = match game_type {
+            Some(game_type) => doc! { "game_type": game_type },
+            None => doc! {},
+        };
+        Ok(self.game_config_repo.find_stream(filter, doc! { "game_type": 1, "version": -1 }).await?.try_collect().await?)
+    }
+
+    async fn insert_game_config(&self, config: GameConfig) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        self.game_config_repo.insert_version(&config).await
+    }
+
+    async fn watch_game_configs(&self) -> Result<Option<GameConfigChanges>, Box<dyn std::error::Error + Send + Sync>> {
+        let changes = self.game_config_repo.watch().await?;
+        Ok(Some(changes.map(|change| change.map(|_| ()).map_err(|e| e.into())).boxed()))
+    }
+
+    async fn list_notification_templates(&self) -> Result<Vec<NotificationTemplate>, Box<dyn std::error::Error + Send + Sync>> {
+        let collection: Collection<NotificationTemplate> = self.collection("notification_templates");
+        let options = mongodb::options::FindOptions::builder().sort(doc! { "notification_type": 1, "language_code": 1 }).build();
+        Ok(collection.find(doc! {}, options).await?.try_collect().await?)
+    }
+
+    async fn upsert_notification_template(&self, template: NotificationTemplate) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let collection: Collection<NotificationTemplate> = self.collection("notification_templates");
+        let filter = doc! { "notification_type": &template.notification_type, "language_code": &template.language_code };
+        let options = mongodb::options::ReplaceOptions::builder().upsert(true).build();
+        collection.replace_one(filter, template, options).await?;
+        Ok(())
+    }
+
+    async fn delete_notification_template(&self, notification_type: &str, language_code: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let collection: Collection<NotificationTemplate> = self.collection("notification_templates");
+        let result = collection.delete_one(doc! { "notification_type": notification_type, "language_code": language_code }, None).await?;
+        Ok(result.deleted_count > 0)
+    }
+
+    // Get a user's notification preferences, falling back to defaults
+    async fn get_notification_preferences(&self, user_id: &str) -> Result<NotificationPreferences, Box<dyn std::error::Error + Send + Sync>> {
+        let collection: Collection<UserPreferences> = self.collection("user_preferences");
+        let prefs = collection.find_one(doc! { "user_id": user_id }, None).await?;
+        Ok(prefs.map(|p| p.notifications).unwrap_or_default())
+    }
+
+    // Create or replace a user's notification preferences
+    async fn update_notification_preferences(&self, user_id: &str, mobile_no: &str, notifications: &NotificationPreferences) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let collection: Collection<UserPreferences> = self.collection("user_preferences");
+        let filter = doc! { "user_id": user_id };
+        let update = doc! {
+            "$set": {
+                "mobile_no": mobile_no,
+                "notifications": bson::to_bson(notifications)?,
+                "updated_at": bson::DateTime::from_millis(chrono::Utc::now().timestamp_millis())
+            }
+        };
+        let options = mongodb::options::UpdateOptions::builder().upsert(true).build();
+        collection.update_one(filter, update, options).await?;
+        info!("🔔 Updated notification preferences for user: {}", user_id);
+        Ok(())
+    }
+
+    // Store a notification in the user's inbox
+    // With a push, needs a replica set for the transaction
+    async fn store_inbox_notification(&self, notification: InboxNotification, push: Option<OutboxMessage>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let collection: Collection<InboxNotification> = self.collection("notification_inbox");
+        let user_id = notification.user_id.clone();
+        let category = notification.category.clone();
+        match push {
+            Some(push) => {
+                let mut session = collection.client().start_session(None).await?;
+                session.start_transaction(None).await?;
+                collection.insert_one_with_session(notification, None, &mut session).await?;
+                self.collection::<OutboxMessage>("outbox").insert_one_with_session(push, None, &mut session).await?;
+                session.commit_transaction().await?;
+            }
+            None => {
+                collection.insert_one(notification, None).await?;
+            }
+        }
+        info!("📝 Stored inbox notification for user: {} (category: {})", user_id, category);
+        Ok(())
+    }
+
+    async fn count_unread_notifications(&self, user_id: &str) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        let collection: Collection<InboxNotification> = self.collection("notification_inbox");
+        Ok(collection.count_documents(doc! { "user_id": user_id, "read": false, "show_in_inbox": true }, None).await?)
+    }
+
+    // Check if user exists
+    async fn user_exists(&self, mobile_no: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        self.user_register_repo.user_exists(mobile_no).await
+    }
+
+    // Get user by mobile number
+    async fn get_user_by_mobile(&self, mobile_no: &str) -> Result<Option<UserRegister>, Box<dyn std::error::Error + Send + Sync>> {
+        UserCache::get_or_load(UserKey::Mobile(mobile_no), || self.user_register_repo.find_user_by_mobile(mobile_no)).await
+    }
+
+    async fn get_user_by_id(&self, user_id: &str) -> Result<Option<UserRegister>, Box<dyn std::error::Error + Send + Sync>> {
+        UserCache::get_or_load(UserKey::UserId(user_id), || self.user_register_repo.find_user_by_id(user_id)).await
+    }
+
+    // Get just the user's id and sequential number
+    async fn get_user_identity(&self, mobile_no: &str) -> Result<Option<UserIdentity>, Box<dyn std::error::Error + Send + Sync>> {
+        self.user_register_repo.find_user_identity(mobile_no).await
+    }
+
+    // Get the user's profile fields
+    async fn get_user_profile(&self, mobile_no: &str) -> Result<Option<UserProfile>, Box<dyn std::error::Error + Send + Sync>> {
+        self.user_register_repo.find_user_profile(mobile_no).await
+    }
+
+    // Register new user with UUID v7 and sequential numbering
+    async fn register_new_user(
+        &self,
+        mobile_no: &str,
+        device_id: &str,
+        fcm_token: &str,
+        email: Option<&str>,
+    ) -> Result<(String, u64), Box<dyn std::error::Error + Send + Sync>> {
+        // Get next user number
+        let user_number = self.get_next_user_number().await;
+        
+        // Create new user with UUID v7
+        let user = UserRegister::new(
+            mobile_no.to_string(),
+            device_id.to_string(),
+            fcm_token.to_string(),
+            email.map(|e| e.to_string()),
+            user_number,
+        );
+        
+        let user_id = user.user_id.clone();
+        
+        // Insert user using the repository
+        self.user_register_repo.insert(&user).await?;
+        
+        info!("🆕 Registered new user: {} (number: {})", user_id, user_number);
+        Ok((user_id, user_number))
+    }
+
+    // Store a migrated user with the next sequential number
+    async fn import_user(&self, mut user: UserRegister) -> Result<Option<(String, u64)>, Box<dyn std::error::Error + Send + Sync>> {
+        if self.user_register_repo.user_exists(&user.mobile_no).await? {
+            return Ok(None);
+        }
+        user.user_number = self.get_next_user_number().await;
+        self.user_register_repo.insert(&user).await?;
+        info!("📥 Imported user: {} (number: {})", user.user_id, user.user_number);
+        Ok(Some((user.user_id, user.user_number)))
+    }
+
+    // One page of matching users, newest first
+    async fn search_users(&self, filter: &UserFilter, page: u64, page_size: i64) -> Result<(Vec<UserRegister>, u64), Box<dyn std::error::Error + Send + Sync>> {
+        let filter = filter.to_document();
+        let total = self.user_register_repo.count(filter.clone()).await?;
+        let users = self.user_register_repo.find_page(filter, doc! { "user_number": -1 }, page, page_size).await?;
+        Ok((users, total))
+    }
+
+    async fn get_admin_operator(&self, operator_id: &str) -> Result<Option<AdminOperator>, Box<dyn std::error::Error + Send + Sync>> {
+        self.admin_operator_repo.find_operator(operator_id).await
+    }
+
+    async fn list_admin_operators(&self) -> Result<Vec<AdminOperator>, Box<dyn std::error::Error + Send + Sync>> {
+        self.admin_operator_repo.list_operators().await
+    }
+
+    async fn create_admin_operator(&self, operator: AdminOperator) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.admin_operator_repo.insert(&operator).await?;
+        info!("🛠️ Added admin operator {} ({}) with role {}", operator.operator_id, operator.name, operator.role);
+        Ok(())
+    }
+
+    async fn set_admin_operator_role(&self, operator_id: &str, role: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        self.admin_operator_repo.set_operator_role(operator_id, role).await
+    }
+
+    // Record an admin request refused for lack of permission
+    async fn store_admin_access_denied_event(&self, operator_id: &str, role: &str, permission: &str, target: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let event = AdminAccessDeniedEvent {
+            id: None,
+            request_id: Correlation::current(),
+            operator_id: operator_id.to_string(),
+            role: role.to_string(),
+            permission: permission.to_string(),
+            target: target.to_string(),
+            timestamp: bson::DateTime::from_millis(chrono::Utc::now().timestamp_millis()),
+        };
+        self.admin_access_denied_repo.insert_event(event).await?;
+        Ok(())
+    }
+
+    async fn store_rejected_handshake(&self, event: RejectedHandshakeEvent) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.rejected_handshake_repo.insert_event(event).await?;
+        Ok(())
+    }
+
+    // Users matching the filter, streamed from a cursor in registration order
+    async fn export_users(&self, filter: &UserFilter) -> Result<UserStream, Box<dyn std::error::Error + Send + Sync>> {
+        let cursor = self.user_register_repo.find_stream(filter.to_document(), doc! { "user_number": 1 }).await?;
+        Ok(cursor.map_err(|e| e.into()).boxed())
+    }
+
+    // Update user login info
+    async fn update_user_login_info(&self, mobile_no: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.user_register_repo.update_user_login_info(mobile_no).await?;
+        UserCache::invalidate_mobile(mobile_no);
+        Ok(())
+    }
+
+    // Update user language settings
+    async fn update_user_language_in_register(
+        &self,
+        mobile_no: &str,
+        language_code: Option<String>,
+        language_name: Option<String>,
+        region_code: Option<String>,
+        timezone: Option<String>,
+        user_preferences: serde_json::Value,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.user_register_repo.update_user_language_settings(
+            mobile_no,
+            language_code,
+            language_name,
+            region_code,
+            timezone,
+            None
+        ).await?;
+        UserCache::invalidate_mobile(mobile_no);
+
+        // Merge rather than overwrite, so values saved via preferences:set survive.
+        // Keys that cannot be used as a document path are dropped.
+        if let Some(prefs) = user_preferences.as_object() {
+            let prefs: serde_json::Map<String, serde_json::Value> = prefs
+                .iter()
+                .filter(|(key, _)| !key.is_empty() && !key.contains('.') && !key.starts_with('$'))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect();
+            if !prefs.is_empty() {
+                self.merge_user_preferences(mobile_no, &prefs).await?;
+            }
+        }
+        Ok(())
+    }
+
+    // Merge preference updates into the user's preferences. Keys are document
+    // paths relative to user_preferences; a null value removes the key.
+    async fn merge_user_preferences(&self, mobile_no: &str, updates: &serde_json::Map<String, serde_json::Value>) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let mut set = bson::Document::new();
+        let mut unset = Vec::new();
+        for (path, value) in updates {
+            if value.is_null() {
+                unset.push(path.clone());
+            } else {
+                set.insert(path.clone(), bson::to_bson(value)?);
+            }
+        }
+        let merged = self.user_register_repo.merge_user_preferences(mobile_no, set, unset).await?;
+        UserCache::invalidate_mobile(mobile_no);
+        Ok(merged)
+    }
+
+    // Verify OTP and return user info
+    async fn verify_otp(&self, _socket_id: &str, mobile_no: &str, session_token: &str, otp: &str) -> Result<OtpVerificationResult, Box<dyn std::error::Error + Send + Sync>> {
+        // Find the login success event for this mobile number and session token
+        let login_success_event = self.login_success_repo.find_login_success_by_mobile_and_session(mobile_no, session_token).await?;
+        
+        match login_success_event {
+            Some(event) => {
+                // Check if the OTP session has expired
+                let now = chrono::Utc::now();
+                let expires_at = chrono::DateTime::from_timestamp_millis(event.expires_at.timestamp_millis())
+                    .unwrap_or(chrono::Utc::now());
+                
+                if now > expires_at {
+                    info!("⏰ OTP session expired for mobile: {} (expired at: {}, current time: {})", 
+                          mobile_no, expires_at, now);
+                    return Ok(OtpVerificationResult::Expired);
+                }
+                
+                // Compare the provided OTP with the stored OTP
+                let stored_otp = event.otp.to_string();
+                let provided_otp = otp.to_string();
+                
+                let is_valid = TokenGenerator::constant_time_eq(&provided_otp, &stored_otp);
+                
+                info!("🔢 OTP verification for mobile: {} (valid: {}, expires: {})", mobile_no, is_valid, expires_at);
+                
+                if is_valid {
+                    self.login_success_repo.mark_session_verified(mobile_no, session_token).await?;
+                    self.create_session(mobile_no, &event.device_id, session_token).await?;
+                    self.upsert_user_device(&event.socket_id, mobile_no, &event.device_id).await?;
+                    Ok(OtpVerificationResult::Success)
+                } else {
+                    Ok(OtpVerificationResult::Invalid)
                 }
             }
             None => {
                 // No login success event found for this mobile number and session token
-                info!("❌ No login success event found for mobile: {} with session token: {}", mobile_no, session_token);
+                info!("❌ No login success event found for mobile: {} with the given session token", mobile_no);
                 Ok(OtpVerificationResult::NotFound)
             }
         }
     }
-    
-    // Get user by session token (for session verification)
-    pub async fn get_user_by_session_token(&self, session_token: &str) -> Result<Option<UserRegister>, Box<dyn std::error::Error + Send + Sync>> {
-        // In a real implementation, you would store and verify session tokens
-        // For demo purposes, we'll extract mobile number from session token
-        let mobile_no = session_token.chars().take(10).collect::<String>();
-        self.get_user_by_mobile(&mobile_no).await
-    }
 
-    // Verify session and mobile number
-    pub async fn verify_session_and_mobile(&self, mobile_no: &str, session_token: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
-        let login_success = self.login_success_repo.find_login_success_by_mobile_and_session(mobile_no, session_token).await?;
-        Ok(login_success.is_some())
+    // Verify session and mobile number. A valid session has its idle expiry
+    // pushed forward (never past the absolute expiry).
+    async fn verify_session_and_mobile(&self, mobile_no: &str, session_token: &str) -> Result<SessionStatus, Box<dyn std::error::Error + Send + Sync>> {
+        let Some(session) = self.session_repo.find_session(mobile_no, session_token).await? else {
+            return Ok(SessionStatus::Invalid);
+        };
+        if session.revoked {
+            return Ok(SessionStatus::Invalid);
+        }
+
+        let now = chrono::Utc::now().timestamp_millis();
+        if now >= session.idle_expires_at.timestamp_millis() || now >= session.absolute_expires_at.timestamp_millis() {
+            info!("⏰ Session expired for mobile: {}", mobile_no);
+            return Ok(SessionStatus::Expired);
+        }
+
+        let idle_expires_at = (now + CONFIG.session_idle_timeout_minutes * 60 * 1000).min(session.absolute_expires_at.timestamp_millis());
+        if let Some(session_id) = session.id {
+            self.session_repo.touch_session(session_id, bson::DateTime::from_millis(now), bson::DateTime::from_millis(idle_expires_at)).await?;
+        }
+        Ok(SessionStatus::Valid)
     }
 
     // Check if referral code exists
-    pub async fn check_referral_code_exists(&self, referral_code: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    async fn check_referral_code_exists(&self, referral_code: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
         self.user_register_repo.check_referral_code_exists(referral_code).await
     }
 
     // Generate unique referral code
-    pub async fn generate_unique_referral_code(&self, _mobile_no: &str) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    async fn generate_unique_referral_code(&self, _mobile_no: &str) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
         let mut attempts = 0;
         const MAX_ATTEMPTS: u32 = 10;
         
@@ -444,7 +1658,7 @@ impl DataService {
     }
 
     // Update user profile in register
-    pub async fn update_user_profile_in_register(
+    async fn update_user_profile_in_register(
         &self,
         mobile_no: &str,
         full_name: Option<String>,
@@ -453,48 +1667,29 @@ impl DataService {
         referred_by: Option<String>,
         profile_data: Option<serde_json::Value>,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        self.user_register_repo.update_user_profile(mobile_no, full_name, state, referral_code, referred_by, profile_data).await
+        self.user_register_repo.update_user_profile(mobile_no, full_name, state, referral_code, referred_by, profile_data).await?;
+        UserCache::invalidate_mobile(mobile_no);
+        Ok(())
     }
 
     // Check OTP verification attempts and implement rate limiting
-    pub async fn check_otp_attempts(&self, mobile_no: &str, session_token: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    async fn check_otp_attempts(&self, mobile_no: &str, session_token: &str, max_attempts: u32) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
         // Get the count of verification attempts for this mobile number and session token
         let attempts_count = self.otp_verification_repo.get_verification_attempts_count(mobile_no, session_token).await?;
         
-        // Allow maximum 5 attempts per session
-        const MAX_ATTEMPTS: i32 = 5;
-        let is_allowed = attempts_count < MAX_ATTEMPTS;
+        // The limit per session comes from the mobile's country policy
+        let is_allowed = (attempts_count.max(0) as u32) < max_attempts;
         
         if !is_allowed {
             info!("🚫 OTP verification attempts exceeded for mobile: {} (attempts: {}, max: {})", 
-                  mobile_no, attempts_count, MAX_ATTEMPTS);
+                  mobile_no, attempts_count, max_attempts);
         } else {
             info!("✅ OTP verification attempt allowed for mobile: {} (attempts: {}/{})", 
-                  mobile_no, attempts_count + 1, MAX_ATTEMPTS);
+                  mobile_no, attempts_count + 1, max_attempts);
         }
         
         Ok(is_allowed)
     }
-
-    // Clean up expired OTP sessions
-    pub async fn cleanup_expired_otp_sessions(&self) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
-        let collection: Collection<LoginSuccessEvent> = self.db.collection("login_success_events");
-        let now = chrono::Utc::now();
-        let filter = doc! {
-            "expires_at": {
-                "$lt": bson::DateTime::from_millis(now.timestamp_millis())
-            }
-        };
-        
-        let result = collection.delete_many(filter, None).await?;
-        let deleted_count = result.deleted_count;
-        
-        if deleted_count > 0 {
-            info!("🧹 Cleaned up {} expired OTP sessions", deleted_count);
-        }
-        
-        Ok(deleted_count)
-    }
 }
 
 #[derive(Debug, Clone)]
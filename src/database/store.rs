@@ -0,0 +1,564 @@
+use async_trait::async_trait;
+use futures_util::stream::BoxStream;
+use crate::database::models::*;
+use crate::database::timeline::TimelineSource;
+
+// Users read one at a time, for exports too large to hold in memory
+pub type UserStream = BoxStream<'static, Result<UserRegister, Box<dyn std::error::Error + Send + Sync>>>;
+
+// One item per change to game_configs, made by this or any other server
+pub type GameConfigChanges = BoxStream<'static, Result<(), Box<dyn std::error::Error + Send + Sync>>>;
+
+// Storage used by the socket handlers. DataService is the MongoDB-backed
+// implementation; InMemoryDataStore keeps everything in process so handlers
+// can be exercised without a database. Handlers hold an Arc<dyn DataStore>.
+#[async_trait]
+pub trait DataStore: Send + Sync {
+    // Store connect event
+    async fn store_connect_event(&self, socket_id: &str, token: i32, message: &str, status: &str, ip_address: Option<&str>) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Store device info event
+    async fn store_device_info_event(&self, socket_id: &str, device_info: &serde_json::Value) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Store login event
+    async fn store_login_event(&self, socket_id: &str, mobile_no: &str, device_id: &str, fcm_token: &str, email: Option<&str>) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Store login success event; the OTP is valid for otp_expiry_secs
+    async fn store_login_success_event(&self, socket_id: &str, mobile_no: &str, device_id: &str, session_token: &str, otp: i32, otp_expiry_secs: i64, is_new_user: bool) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Queue an OTP for delivery by the SMS/email gateway
+    async fn store_otp_delivery_request(&self, request: OtpDeliveryRequest) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Record use of the static test OTP
+    async fn store_test_otp_audit_event(&self, socket_id: &str, mobile_no: &str, device_id: Option<&str>, action: &str, is_success: bool) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // List a user's active (not removed) devices, most recently used first
+    async fn list_user_devices(&self, mobile_no: &str) -> Result<Vec<UserDevice>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Remove a device and revoke its sessions. Returns false if the device was not found.
+    async fn remove_user_device(&self, mobile_no: &str, device_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Set or (with None) remove a user's second factor. Returns false if the user was not found.
+    async fn update_two_step(&self, mobile_no: &str, two_step: Option<&TwoStepFactor>) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Record the date of birth unless one is already set. Returns false if it was.
+    async fn set_date_of_birth(&self, mobile_no: &str, date_of_birth: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Set or (with None) remove a user's parental controls. Returns false if the user was not found.
+    async fn update_parental_controls(&self, mobile_no: &str, controls: Option<&ParentalControls>) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Record a second factor removed by support
+    async fn store_two_step_reset(&self, reset: TwoStepReset) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Store a requested mobile number change
+    async fn store_mobile_change(&self, change: MobileChange) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // A user's mobile number change by its change_id
+    async fn get_mobile_change(&self, user_id: &str, change_id: &str) -> Result<Option<MobileChange>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Count a confirmation attempt; returns the attempts made so far
+    async fn record_mobile_change_attempt(&self, change_id: &str) -> Result<u32, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Move the user from old_mobile_no to new_mobile_no in one transaction:
+    // the userregister entry, notification preferences and devices follow,
+    // sessions of the old number are revoked, the change (if any) is marked
+    // completed and the audit record stored. Returns false if the new number
+    // is already registered or the user no longer has the old one.
+    async fn change_user_mobile(&self, audit: MobileChangeAudit) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Find a pending login session to reuse when a client retries `login`:
+    // only one created on this socket, or whose session_token the client sent
+    // back. Other pending sessions are left alone.
+    async fn find_reusable_login_session(&self, socket_id: &str, mobile_no: &str, device_id: &str, session_token: Option<&str>) -> Result<Option<LoginSuccessEvent>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Store OTP verification event
+    async fn store_otp_verification_event(
+        &self,
+        socket_id: &str,
+        mobile_no: &str,
+        session_token: &str,
+        otp: &str,
+        is_success: bool,
+        user_id: Option<&str>,
+        user_number: Option<u64>,
+        jwt_token: Option<&str>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Store user registration event
+    async fn store_user_registration_event(
+        &self,
+        socket_id: &str,
+        user_id: &str,
+        user_number: u64,
+        mobile_no: &str,
+        device_id: &str,
+        fcm_token: &str,
+        email: Option<&str>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Store user profile event
+    async fn store_user_profile_event(
+        &self,
+        socket_id: &str,
+        user_id: &str,
+        user_number: u64,
+        mobile_no: &str,
+        full_name: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Store language setting event
+    async fn store_language_setting_event(
+        &self,
+        socket_id: &str,
+        user_id: &str,
+        user_number: u64,
+        mobile_no: &str,
+        language_code: &str,
+        language_name: &str,
+        region_code: Option<&str>,
+        timezone: Option<&str>,
+        user_preferences: &serde_json::Value,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Store connection error event
+    async fn store_connection_error_event(
+        &self,
+        socket_id: &str,
+        error_code: &str,
+        error_type: &str,
+        field: &str,
+        message: &str,
+        payload: bson::Document,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Connection error counts per group and time bucket, oldest bucket first
+    async fn connection_error_counts(&self, query: &ErrorStatsQuery) -> Result<Vec<ErrorCountBucket>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Devices and app versions with the most connection errors in [from, to),
+    // matched through the device:info sent on the failing socket
+    async fn connection_error_offenders(
+        &self,
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+        limit: i64,
+    ) -> Result<ErrorOffenders, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Store turn timing data (used for anti-stall detection)
+    async fn store_turn_timing_event(&self, event: TurnTimingEvent) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Store match history record
+    async fn store_match_record(&self, record: MatchRecord) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Save a room's latest state, replacing its previous snapshot
+    async fn save_room_snapshot(&self, snapshot: RoomSnapshot) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Room snapshots saved at or after `since`, oldest first
+    async fn load_room_snapshots(&self, since: chrono::DateTime<chrono::Utc>) -> Result<Vec<RoomSnapshot>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Keep a closed room in archived_rooms and drop its snapshot
+    async fn archive_room(&self, room: ArchivedRoom) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    async fn create_escrow(&self, escrow: MatchEscrow) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // The escrow of a room still waiting for its result
+    async fn get_held_escrow(&self, room_id: &str) -> Result<Option<MatchEscrow>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Record a staked player's result while the escrow is held. Returns the
+    // updated escrow, or None when it is no longer held.
+    async fn report_escrow_result(&self, escrow_id: &str, player_id: &str, outcome: GameOutcome) -> Result<Option<MatchEscrow>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Move a held escrow to Releasing or Refunding with its payouts fixed;
+    // false when another settlement got there first
+    async fn begin_escrow_settlement(&self, escrow_id: &str, status: EscrowStatus, payouts: Vec<EscrowPayout>, rake: i64) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Move an opening escrow to Held, or to Refunding with the stakes that were
+    // taken and their refunds; false when recovery got there first
+    async fn finish_escrow_opening(&self, escrow_id: &str, status: EscrowStatus, stakes: Vec<EscrowStake>, payouts: Vec<EscrowPayout>) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Escrows created before `before` that are still taking their entry fees
+    async fn stale_escrow_openings(&self, before: chrono::DateTime<chrono::Utc>) -> Result<Vec<MatchEscrow>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Mark a settlement done once every payout was credited
+    async fn finish_escrow_settlement(&self, escrow_id: &str, status: EscrowStatus) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Settlements started before `before` that never finished
+    async fn unfinished_escrow_settlements(&self, before: chrono::DateTime<chrono::Utc>) -> Result<Vec<MatchEscrow>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Store an escrow's commission in revenue_ledger; false when that escrow was already recorded
+    async fn record_revenue(&self, entry: RevenueEntry) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+
+    // One page of a day's ledger entries, oldest first, optionally of one game type
+    async fn revenue_entries(&self, day: &str, game_type: Option<&str>, page: u64, page_size: i64) -> Result<Vec<RevenueEntry>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // A day's ledger totals by game type, computed from revenue_ledger
+    async fn revenue_day_totals(&self, day: &str) -> Result<Vec<RevenueRollup>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Replace the stored rollups of `day`
+    async fn save_revenue_rollups(&self, day: &str, rollups: Vec<RevenueRollup>) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Stored rollups of the days from `from` to `to` (inclusive), by day then game type
+    async fn revenue_rollups(&self, from: &str, to: &str) -> Result<Vec<RevenueRollup>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Store a new promo code; false when the code is taken
+    async fn create_promo_code(&self, promo: PromoCode) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Every promo code, newest first
+    async fn list_promo_codes(&self) -> Result<Vec<PromoCode>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Enable or disable a promo code; false when there is no such code
+    async fn set_promo_code_active(&self, code: &str, active: bool) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Check redemption.code against its limits and, all at once, count the use,
+    // store the redemption with the code's reward and credit that reward
+    async fn redeem_promo_code(&self, redemption: PromoRedemption) -> Result<PromoRedeem, Box<dyn std::error::Error + Send + Sync>>;
+
+    // One page of a promo code's redemptions, newest first
+    async fn list_promo_redemptions(&self, code: &str, page: u64, page_size: i64) -> Result<Vec<PromoRedemption>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Record the start of a support impersonation
+    async fn start_impersonation(&self, session: ImpersonationSession) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Close an impersonation's audit record; ended ones are left as they are
+    async fn end_impersonation(&self, session_id: &str, end_reason: &str, events_mirrored: u64) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // One page of impersonations, newest first, optionally of one user or operator
+    async fn list_impersonations(&self, user_id: Option<&str>, operator_id: Option<&str>, page: u64, page_size: i64) -> Result<Vec<ImpersonationSession>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Up to `limit` of the user's documents from `sources` older than
+    // `before`, newest first
+    async fn user_timeline(&self, user: &UserRegister, sources: &[&'static TimelineSource], before: Option<bson::DateTime>, limit: i64) -> Result<Vec<TimelineEntry>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Store a server's sample for a minute, replacing an earlier one of that minute
+    async fn record_session_sample(&self, sample: SessionSample) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Samples of every server with a minute in [from, to), oldest first
+    async fn session_samples(&self, from: bson::DateTime, to: bson::DateTime) -> Result<Vec<SessionSample>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Keep the record of a gameplay entry refused or let in by ComplianceManager
+    async fn record_compliance_audit(&self, audit: ComplianceAudit) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Keep the record of a player warned or removed for being idle in a match
+    async fn record_afk_event(&self, event: AfkEvent) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // A user's AFK events created at or after `since`, newest first
+    async fn afk_events_since(&self, user_id: &str, since: bson::DateTime) -> Result<Vec<AfkEvent>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Keep a room's revealed dealer seed and its draws
+    async fn save_dealer_audit(&self, audit: DealerAudit) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Users registered in [since, now) by registration week, with how many
+    // completed a login on each of `days` after registering
+    async fn retention_cohorts(&self, since: chrono::DateTime<chrono::Utc>, now: chrono::DateTime<chrono::Utc>, days: &[u32]) -> Result<Vec<RetentionCohort>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Store a retention report, replacing the one of the same report_date
+    async fn save_retention_report(&self, report: RetentionReport) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // The most recent retention report, if any was computed
+    async fn latest_retention_report(&self) -> Result<Option<RetentionReport>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // A user's gameplay progress; None until their first progress:update
+    async fn get_gameplay_progress(&self, user_id: &str) -> Result<Option<GameplayProgress>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Add XP and a finished game to a user's progress; returns the updated progress
+    async fn update_gameplay_progress(&self, user_id: &str, update: &ProgressUpdate) -> Result<GameplayProgress, Box<dyn std::error::Error + Send + Sync>>;
+
+    // The challenges generated for a UTC day (YYYY-MM-DD)
+    async fn get_daily_challenges(&self, date: &str) -> Result<Option<DailyChallengeSet>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Store a day's challenges; if the day already has some, those are kept and returned
+    async fn create_daily_challenges(&self, set: DailyChallengeSet) -> Result<DailyChallengeSet, Box<dyn std::error::Error + Send + Sync>>;
+
+    async fn get_challenge_progress(&self, user_id: &str, date: &str) -> Result<Option<ChallengeProgress>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Add to a user's progress on the day's challenges (challenge_id -> amount)
+    async fn add_challenge_progress(&self, user_id: &str, date: &str, increments: &std::collections::HashMap<String, i64>) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    async fn mark_challenge_claimed(&self, user_id: &str, date: &str, challenge_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    async fn get_wallet_balance(&self, user_id: &str) -> Result<i64, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Credit coins once per `reference`; returns the new balance, or None if
+    // the reference was already credited
+    async fn credit_wallet(&self, user_id: &str, amount: i64, reason: &str, reference: &str) -> Result<Option<i64>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Add a season definition
+    async fn create_season(&self, season: Season) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // All seasons, newest start first
+    async fn list_seasons(&self) -> Result<Vec<Season>, Box<dyn std::error::Error + Send + Sync>>;
+
+    async fn get_season(&self, season_id: &str) -> Result<Option<Season>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Seasons in `status`, earliest start first
+    async fn seasons_with_status(&self, status: SeasonStatus) -> Result<Vec<Season>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Compare-and-set a season's status; false if it was not in `from`
+    async fn transition_season(&self, season_id: &str, from: SeasonStatus, to: SeasonStatus) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Apply a game result to a player's season rating, starting at `base_rating`
+    async fn record_season_result(&self, season_id: &str, user_id: &str, outcome: GameOutcome, rating_change: i64, base_rating: i64) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Season ratings in leaderboard order (rating, then who reached it first)
+    async fn season_leaderboard(&self, season_id: &str, offset: u64, limit: i64) -> Result<Vec<SeasonRating>, Box<dyn std::error::Error + Send + Sync>>;
+
+    async fn get_season_rating(&self, season_id: &str, user_id: &str) -> Result<Option<SeasonRating>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // 1-based leaderboard position of a rating
+    async fn season_rank(&self, rating: &SeasonRating) -> Result<u64, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Seed a new season from the previous one's ratings (soft reset); returns the ratings in `to`
+    async fn carry_over_season_ratings(&self, from: &str, to: &str, base_rating: i64, factor: f64) -> Result<u64, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Take coins if the balance, and `daily_limit` with what was spent today (UTC), covers them;
+    // a reference already debited is not taken again
+    async fn debit_wallet(&self, user_id: &str, amount: i64, reason: &str, reference: &str, daily_limit: Option<i64>) -> Result<WalletDebit, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Whether a wallet change with this reference is in the ledger
+    async fn wallet_reference_applied(&self, reference: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Coins the user spent at or after `since`
+    async fn wallet_spent_since(&self, user_id: &str, since: chrono::DateTime<chrono::Utc>) -> Result<i64, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Items the user holds, by item_id
+    async fn get_inventory(&self, user_id: &str) -> Result<Vec<InventoryItem>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Record that user_id added friend_user_id; false if they already had
+    async fn add_friend(&self, user_id: &str, friend_user_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+
+    // End a friendship (or pending request) in both directions; false if there was none
+    async fn remove_friend(&self, user_id: &str, friend_user_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+
+    // True once both users have added each other
+    async fn are_friends(&self, user_id: &str, other_user_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Both directions of every friendship and request the user is part of
+    async fn list_friendships(&self, user_id: &str) -> Result<Vec<Friendship>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Move the gift's coins or item from sender to recipient and record it,
+    // all or nothing. The sender's gifts today (UTC) count against `limits`,
+    // and coins against `daily_limit` (parental controls) like debit_wallet.
+    async fn send_gift(&self, gift: Gift, limits: GiftLimits, daily_limit: Option<i64>) -> Result<GiftSend, Box<dyn std::error::Error + Send + Sync>>;
+
+    // All gifts sent at or after `since`
+    async fn gifts_since(&self, since: chrono::DateTime<chrono::Utc>) -> Result<Vec<Gift>, Box<dyn std::error::Error + Send + Sync>>;
+
+    async fn record_match_participant(&self, participant: MatchParticipant) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    async fn record_match_result(&self, result: MatchResult) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Room joins and reported results at or after `since`, for the anomaly scan
+    async fn match_participants_since(&self, since: chrono::DateTime<chrono::Utc>) -> Result<Vec<MatchParticipant>, Box<dyn std::error::Error + Send + Sync>>;
+    async fn match_results_since(&self, since: chrono::DateTime<chrono::Utc>) -> Result<Vec<MatchResult>, Box<dyn std::error::Error + Send + Sync>>;
+
+    async fn get_user_risk(&self, user_id: &str) -> Result<Option<UserRisk>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Create or replace a user's risk score
+    async fn save_user_risk(&self, risk: UserRisk) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Reset scores not rewritten since `before` (users no longer flagged); returns how many
+    async fn clear_stale_user_risk(&self, before: bson::DateTime) -> Result<u64, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Queue a flag for review; false if an open flag with its flag_key exists
+    async fn create_risk_flag(&self, flag: RiskFlag) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Flags in `status` (all if None), newest first; pages start at 0
+    async fn list_risk_flags(&self, status: Option<&str>, page: u64, page_size: i64) -> Result<Vec<RiskFlag>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Close an open flag as confirmed or dismissed; false if it is not open
+    async fn resolve_risk_flag(&self, flag_id: &str, status: &str, operator_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+
+    // flag_keys an operator dismissed; the scan ignores these signals
+    async fn dismissed_risk_flag_keys(&self) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Record that user_id blocked blocked_user_id; false if they already had
+    async fn block_user(&self, user_id: &str, blocked_user_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Lift a block user_id made; false if there was none
+    async fn unblock_user(&self, user_id: &str, blocked_user_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Blocks the user made or is subject to
+    async fn list_blocks(&self, user_id: &str) -> Result<Vec<UserBlock>, Box<dyn std::error::Error + Send + Sync>>;
+
+    async fn create_user_report(&self, report: UserReport) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Reports the user filed at or after `since`, newest first
+    async fn reports_filed_since(&self, reporter_user_id: &str, since: chrono::DateTime<chrono::Utc>) -> Result<Vec<UserReport>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Reports in `status` (all if None) and `category` (all if None), oldest first; pages start at 0
+    async fn list_user_reports(&self, status: Option<&str>, category: Option<ReportCategory>, page: u64, page_size: i64) -> Result<Vec<UserReport>, Box<dyn std::error::Error + Send + Sync>>;
+
+    async fn get_user_report(&self, report_id: &str) -> Result<Option<UserReport>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Close an open report as actioned or dismissed; false if it is not open
+    async fn resolve_user_report(&self, report_id: &str, status: &str, resolution: &str, operator_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+
+    async fn create_sanction(&self, sanction: UserSanction) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Every sanction against the user, newest first
+    async fn list_sanctions(&self, user_id: &str) -> Result<Vec<UserSanction>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // End an active mute or ban early; false if it is not active
+    async fn lift_sanction(&self, sanction_id: &str, operator_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Revoke all of a user's sessions; returns how many were open
+    async fn revoke_user_sessions(&self, mobile_no: &str) -> Result<u64, Box<dyn std::error::Error + Send + Sync>>;
+
+    async fn save_chat_message(&self, message: ChatMessage) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    async fn get_chat_message(&self, message_id: &str) -> Result<Option<ChatMessage>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Replace the text of a message; false if it has been deleted
+    async fn edit_chat_message(&self, message_id: &str, message: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Turn a message into a tombstone; false if it already is one
+    async fn delete_chat_message(&self, message_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Up to `limit` messages of a room sent before the `before` message (or
+    // the newest), newest first, leaving out messages by `excluded_user_ids`
+    async fn chat_history(&self, room_id: &str, before: Option<&str>, excluded_user_ids: &[String], limit: i64) -> Result<Vec<ChatMessage>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Delete messages sent before `before`; returns how many
+    async fn purge_chat_messages(&self, before: chrono::DateTime<chrono::Utc>) -> Result<u64, Box<dyn std::error::Error + Send + Sync>>;
+
+    async fn record_chat_audit(&self, audit: ChatMessageAudit) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Add (or, with `add` false, take back) a player's reaction to a message;
+    // returns the message's new count of that reaction, None if nothing
+    // changed because the player already had (or did not have) it
+    async fn react_to_chat_message(&self, reaction: ChatReaction, add: bool) -> Result<Option<i64>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Move a player's read receipt in a room up to `message_id` (never back);
+    // returns the receipt as stored
+    async fn mark_chat_read(&self, room_id: &str, user_id: &str, message_id: &str) -> Result<ChatReadReceipt, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Read receipts of a room, leaving out those of `excluded_user_ids`
+    async fn chat_read_receipts(&self, room_id: &str, excluded_user_ids: &[String]) -> Result<Vec<ChatReadReceipt>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Every stored game config version (of one game type, if given), by game
+    // type and then newest version first
+    async fn list_game_configs(&self, game_type: Option<&str>) -> Result<Vec<GameConfig>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Store a new config version; false if that version already exists
+    async fn insert_game_config(&self, config: GameConfig) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Changes to game_configs as they happen, or None if this store cannot
+    // watch for them and has to be polled
+    async fn watch_game_configs(&self) -> Result<Option<GameConfigChanges>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Every stored notification template, by notification type and then language
+    async fn list_notification_templates(&self) -> Result<Vec<NotificationTemplate>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Create or replace the template of a notification type and language
+    async fn upsert_notification_template(&self, template: NotificationTemplate) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Remove a stored template; false if there was none
+    async fn delete_notification_template(&self, notification_type: &str, language_code: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Get a user's notification preferences, falling back to defaults
+    async fn get_notification_preferences(&self, user_id: &str) -> Result<NotificationPreferences, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Create or replace a user's notification preferences
+    async fn update_notification_preferences(&self, user_id: &str, mobile_no: &str, notifications: &NotificationPreferences) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Store a notification in the user's inbox, with the outbox message of its
+    // push (see Outbox) in the same transaction
+    async fn store_inbox_notification(&self, notification: InboxNotification, push: Option<OutboxMessage>) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Unread notifications shown in a user's inbox
+    async fn count_unread_notifications(&self, user_id: &str) -> Result<u64, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Check if user exists
+    async fn user_exists(&self, mobile_no: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Get user by mobile number
+    async fn get_user_by_mobile(&self, mobile_no: &str) -> Result<Option<UserRegister>, Box<dyn std::error::Error + Send + Sync>>;
+
+    async fn get_user_by_id(&self, user_id: &str) -> Result<Option<UserRegister>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Get just the user's id and sequential number
+    async fn get_user_identity(&self, mobile_no: &str) -> Result<Option<UserIdentity>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Get the user's profile fields
+    async fn get_user_profile(&self, mobile_no: &str) -> Result<Option<UserProfile>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Register new user with UUID v7 and sequential numbering
+    async fn register_new_user(
+        &self,
+        mobile_no: &str,
+        device_id: &str,
+        fcm_token: &str,
+        email: Option<&str>,
+    ) -> Result<(String, u64), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Store a user migrated from another system, assigning the next sequential
+    // number. Returns None without storing if the mobile number is already registered.
+    async fn import_user(&self, user: UserRegister) -> Result<Option<(String, u64)>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // One page of users matching `filter`, newest first, plus the total number of matches
+    async fn search_users(&self, filter: &UserFilter, page: u64, page_size: i64) -> Result<(Vec<UserRegister>, u64), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Admin operator by id
+    async fn get_admin_operator(&self, operator_id: &str) -> Result<Option<AdminOperator>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // All admin operators, oldest first
+    async fn list_admin_operators(&self) -> Result<Vec<AdminOperator>, Box<dyn std::error::Error + Send + Sync>>;
+
+    async fn create_admin_operator(&self, operator: AdminOperator) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Change an operator's role. Returns false if the operator was not found.
+    async fn set_admin_operator_role(&self, operator_id: &str, role: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Record an admin request refused for lack of permission
+    async fn store_admin_access_denied_event(&self, operator_id: &str, role: &str, permission: &str, target: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Record a request refused before it reached Socket.IO (capped collection)
+    async fn store_rejected_handshake(&self, event: RejectedHandshakeEvent) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Users matching `filter` in registration order
+    async fn export_users(&self, filter: &UserFilter) -> Result<UserStream, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Update user login info
+    async fn update_user_login_info(&self, mobile_no: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Update user language settings
+    async fn update_user_language_in_register(
+        &self,
+        mobile_no: &str,
+        language_code: Option<String>,
+        language_name: Option<String>,
+        region_code: Option<String>,
+        timezone: Option<String>,
+        user_preferences: serde_json::Value,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Merge preference updates into the user's preferences. Keys are document
+    // paths relative to user_preferences; a null value removes the key.
+    async fn merge_user_preferences(&self, mobile_no: &str, updates: &serde_json::Map<String, serde_json::Value>) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Verify OTP and return user info
+    async fn verify_otp(&self, socket_id: &str, mobile_no: &str, session_token: &str, otp: &str) -> Result<OtpVerificationResult, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Verify session and mobile number. A valid session has its idle expiry
+    // pushed forward (never past the absolute expiry).
+    async fn verify_session_and_mobile(&self, mobile_no: &str, session_token: &str) -> Result<SessionStatus, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Check if referral code exists
+    async fn check_referral_code_exists(&self, referral_code: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Generate unique referral code
+    async fn generate_unique_referral_code(&self, mobile_no: &str) -> Result<String, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Update user profile in register
+    async fn update_user_profile_in_register(
+        &self,
+        mobile_no: &str,
+        full_name: Option<String>,
+        state: Option<String>,
+        referral_code: Option<String>,
+        referred_by: Option<String>,
+        profile_data: Option<serde_json::Value>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Check OTP verification attempts against the session's limit
+    async fn check_otp_attempts(&self, mobile_no: &str, session_token: &str, max_attempts: u32) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+}
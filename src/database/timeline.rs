@@ -0,0 +1,113 @@
+use bson::{doc, Bson, DateTime, Document};
+
+use crate::database::models::TimelineEntry;
+
+// Which of a user's ids a timeline source stores
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimelineKey {
+    UserId,
+    MobileNo,       // Current and earlier numbers, see mobile_change_audit
+    SocketId,       // Sockets the user logged in on
+}
+
+// One collection merged into the support timeline
+pub struct TimelineSource {
+    pub kind: &'static str,
+    pub collection: &'static str,
+    pub key: TimelineKey,
+    pub fields: &'static [&'static str],   // A document is the user's when any of these holds one of their ids
+    pub time_field: &'static str,
+}
+
+pub static TIMELINE_SOURCES: &[TimelineSource] = &[
+    TimelineSource { kind: "registration", collection: "user_registration_events", key: TimelineKey::UserId, fields: &["user_id"], time_field: "timestamp" },
+    TimelineSource { kind: "login", collection: "login_success_events", key: TimelineKey::MobileNo, fields: &["mobile_no"], time_field: "timestamp" },
+    TimelineSource { kind: "otp_verification", collection: "otp_verification_events", key: TimelineKey::MobileNo, fields: &["mobile_no"], time_field: "timestamp" },
+    TimelineSource { kind: "profile", collection: "user_profile_events", key: TimelineKey::UserId, fields: &["user_id"], time_field: "timestamp" },
+    TimelineSource { kind: "language", collection: "language_setting_events", key: TimelineKey::UserId, fields: &["user_id"], time_field: "timestamp" },
+    TimelineSource { kind: "mobile_change", collection: "mobile_change_audit", key: TimelineKey::UserId, fields: &["user_id"], time_field: "changed_at" },
+    TimelineSource { kind: "error", collection: "connection_error_events", key: TimelineKey::SocketId, fields: &["socket_id"], time_field: "timestamp" },
+    TimelineSource { kind: "match", collection: "match_results", key: TimelineKey::UserId, fields: &["user_id"], time_field: "reported_at" },
+    TimelineSource { kind: "wallet", collection: "wallet_transactions", key: TimelineKey::UserId, fields: &["user_id"], time_field: "created_at" },
+    TimelineSource { kind: "inventory", collection: "inventory_transactions", key: TimelineKey::UserId, fields: &["user_id"], time_field: "created_at" },
+    TimelineSource { kind: "gift", collection: "gifts", key: TimelineKey::UserId, fields: &["from_user_id", "to_user_id"], time_field: "created_at" },
+    TimelineSource { kind: "promo", collection: "promo_redemptions", key: TimelineKey::UserId, fields: &["user_id"], time_field: "redeemed_at" },
+    TimelineSource { kind: "sanction", collection: "user_sanctions", key: TimelineKey::UserId, fields: &["user_id"], time_field: "created_at" },
+    TimelineSource { kind: "compliance", collection: "compliance_audit", key: TimelineKey::UserId, fields: &["user_id"], time_field: "created_at" },
+    TimelineSource { kind: "afk", collection: "afk_events", key: TimelineKey::UserId, fields: &["user_id"], time_field: "created_at" },
+];
+
+// Credentials and storage internals never shown to support
+const HIDDEN_FIELDS: &[&str] = &["_id", "event_type", "schema_version", "session_token", "otp", "jwt_token", "fcm_token", "signing_key"];
+
+// The ids a user's documents carry
+#[derive(Debug, Clone, Default)]
+pub struct TimelineSubject {
+    pub user_id: String,
+    pub mobile_nos: Vec<String>,
+    pub socket_ids: Vec<String>,
+}
+
+impl TimelineSubject {
+    fn ids(&self, key: TimelineKey) -> &[String] {
+        match key {
+            TimelineKey::UserId => std::slice::from_ref(&self.user_id),
+            TimelineKey::MobileNo => &self.mobile_nos,
+            TimelineKey::SocketId => &self.socket_ids,
+        }
+    }
+}
+
+impl TimelineSource {
+    pub fn by_kind(kind: &str) -> Option<&'static TimelineSource> {
+        TIMELINE_SOURCES.iter().find(|source| source.kind == kind)
+    }
+
+    // MongoDB filter for the subject's documents older than `before`; None
+    // when the subject has no ids of this source's key
+    pub fn filter(&self, subject: &TimelineSubject, before: Option<DateTime>) -> Option<Document> {
+        let ids = subject.ids(self.key);
+        if ids.is_empty() {
+            return None;
+        }
+        let owners: Vec<Document> = self.fields.iter().map(|field| doc! { *field: { "$in": ids } }).collect();
+        let mut filter = doc! { "$or": owners };
+        if let Some(before) = before {
+            filter.insert(self.time_field, doc! { "$lt": before });
+        }
+        Some(filter)
+    }
+
+    // The in-memory equivalent of filter()
+    pub fn matches(&self, subject: &TimelineSubject, document: &Document, before: Option<DateTime>) -> bool {
+        let ids = subject.ids(self.key);
+        let owned = self.fields.iter().any(|field| document.get_str(field).is_ok_and(|id| ids.iter().any(|owner| owner == id)));
+        owned && before.is_none_or(|before| document.get_datetime(self.time_field).is_ok_and(|at| *at < before))
+    }
+
+    // A stored document as a timeline entry, without its hidden fields
+    pub fn entry(&self, mut document: Document) -> Option<TimelineEntry> {
+        let at = *document.get_datetime(self.time_field).ok()?;
+        document.remove(self.time_field);
+        for field in HIDDEN_FIELDS {
+            document.remove(field);
+        }
+        Some(TimelineEntry {
+            kind: self.kind.to_string(),
+            source: self.collection.to_string(),
+            at,
+            details: Bson::Document(document).into_relaxed_extjson(),
+        })
+    }
+}
+
+pub struct Timeline;
+
+impl Timeline {
+    // Newest `limit` entries of those read from every source
+    pub fn merge(mut entries: Vec<TimelineEntry>, limit: i64) -> Vec<TimelineEntry> {
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.at));
+        entries.truncate(limit.max(0) as usize);
+        entries
+    }
+}
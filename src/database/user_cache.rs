@@ -0,0 +1,217 @@
+use bson::oid::ObjectId;
+use futures_util::StreamExt;
+use mongodb::bson::{doc, Document};
+use mongodb::change_stream::event::OperationType;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::config::CONFIG;
+use crate::database::models::UserRegister;
+use crate::database::DatabaseManager;
+use crate::managers::tenant::TenantManager;
+
+// Wait before reopening a change stream that ended or could not be opened
+const REWATCH_DELAY: Duration = Duration::from_secs(5);
+
+// How a user is looked up
+pub enum UserKey<'a> {
+    Mobile(&'a str),
+    UserId(&'a str),
+}
+
+struct Entry {
+    user: UserRegister,
+    cached_at: Instant,
+}
+
+// Cached users of one tenant. Entries are only served while the change
+// stream is open; every invalidation bumps the generation so a lookup that
+// raced with a change does not cache what it read.
+#[derive(Default)]
+struct TenantCache {
+    live: bool,
+    generation: u64,
+    users: HashMap<ObjectId, Entry>,
+    by_mobile: HashMap<String, ObjectId>,
+    by_user_id: HashMap<String, ObjectId>,
+}
+
+impl TenantCache {
+    fn find(&self, key: &UserKey) -> Option<&Entry> {
+        let id = match key {
+            UserKey::Mobile(mobile_no) => self.by_mobile.get(*mobile_no),
+            UserKey::UserId(user_id) => self.by_user_id.get(*user_id),
+        }?;
+        self.users.get(id).filter(|entry| entry.cached_at.elapsed() < Duration::from_secs(CONFIG.user_cache_ttl_secs))
+    }
+
+    fn remove(&mut self, id: &ObjectId) {
+        if let Some(entry) = self.users.remove(id) {
+            self.by_mobile.remove(&entry.user.mobile_no);
+            self.by_user_id.remove(&entry.user.user_id);
+        }
+    }
+
+    fn insert(&mut self, user: UserRegister) {
+        let Some(id) = user.id else {
+            return;
+        };
+        self.remove(&id);
+        if self.users.len() >= CONFIG.user_cache_capacity {
+            let oldest = self.users.iter().min_by_key(|(_, entry)| entry.cached_at).map(|(id, _)| *id);
+            if let Some(oldest) = oldest {
+                self.remove(&oldest);
+            }
+        }
+        self.by_mobile.insert(user.mobile_no.clone(), id);
+        self.by_user_id.insert(user.user_id.clone(), id);
+        self.users.insert(id, Entry { user, cached_at: Instant::now() });
+    }
+
+    fn invalidate(&mut self, id: Option<&ObjectId>) {
+        self.generation += 1;
+        match id {
+            Some(id) => self.remove(id),
+            None => {
+                self.users.clear();
+                self.by_mobile.clear();
+                self.by_user_id.clear();
+            }
+        }
+    }
+}
+
+// What the cache of the tenant in scope holds for a key
+enum Lookup {
+    Off,                        // The change stream is not open
+    Hit(Box<UserRegister>),
+    Miss(u64),                  // Generation to cache the loaded user under
+}
+
+// Per tenant, by tenant id
+static CACHES: Lazy<Mutex<HashMap<String, TenantCache>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// Read-through cache of `userregister` lookups by mobile number and user id.
+// Admin tools and other services change that collection directly, so every
+// server watches it through a MongoDB change stream and drops the changed
+// user; no message between servers is needed. Where the deployment has no
+// change streams the cache stays off. USER_CACHE_TTL_SECS bounds how long an
+// entry is served even if a change were missed.
+pub struct UserCache;
+
+impl UserCache {
+    fn enabled() -> bool {
+        CONFIG.user_cache_capacity > 0 && !CONFIG.in_memory_store
+    }
+
+    fn with_tenant<R>(f: impl FnOnce(&mut TenantCache) -> R) -> R {
+        let mut caches = CACHES.lock().unwrap_or_else(|e| e.into_inner());
+        f(caches.entry(TenantManager::current().tenant_id.clone()).or_default())
+    }
+
+    // The cached user, or `load` and cache its result
+    pub async fn get_or_load<F, Fut>(key: UserKey<'_>, load: F) -> Result<Option<UserRegister>, Box<dyn std::error::Error + Send + Sync>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Option<UserRegister>, Box<dyn std::error::Error + Send + Sync>>>,
+    {
+        if !Self::enabled() {
+            return load().await;
+        }
+        let lookup = Self::with_tenant(|cache| {
+            if !cache.live {
+                return Lookup::Off;
+            }
+            match cache.find(&key) {
+                Some(entry) => Lookup::Hit(Box::new(entry.user.clone())),
+                None => Lookup::Miss(cache.generation),
+            }
+        });
+        let generation = match lookup {
+            Lookup::Miss(generation) => generation,
+            Lookup::Hit(user) => return Ok(Some(*user)),
+            Lookup::Off => return load().await,
+        };
+
+        let user = load().await?;
+        if let Some(user) = &user {
+            Self::with_tenant(|cache| {
+                if cache.live && cache.generation == generation {
+                    cache.insert(user.clone());
+                }
+            });
+        }
+        Ok(user)
+    }
+
+    // Drop a user this server just changed, before the change stream reports it
+    pub fn invalidate_mobile(mobile_no: &str) {
+        if !Self::enabled() {
+            return;
+        }
+        Self::with_tenant(|cache| {
+            let id = cache.by_mobile.get(mobile_no).copied();
+            cache.generation += 1;
+            if let Some(id) = id {
+                cache.remove(&id);
+            }
+        });
+    }
+
+    pub fn spawn_invalidator() {
+        if !Self::enabled() {
+            return;
+        }
+        for tenant in TenantManager::tenants() {
+            tokio::spawn(TenantManager::scope(tenant, Self::watch()));
+        }
+    }
+
+    async fn watch() {
+        let tenant_id = TenantManager::current().tenant_id.clone();
+        let mut warned = false;
+        loop {
+            // Only the changed document's _id is needed
+            let pipeline = vec![doc! { "$project": { "documentKey": 1, "operationType": 1 } }];
+            match DatabaseManager::collection::<Document>("userregister").watch(pipeline, None).await {
+                Ok(mut changes) => {
+                    info!("👀 Watching userregister of tenant {} to keep the user cache current", tenant_id);
+                    warned = false;
+                    Self::with_tenant(|cache| cache.live = true);
+                    while let Some(change) = changes.next().await {
+                        let change = match change {
+                            Ok(change) => change,
+                            Err(e) => {
+                                warn!("⚠️ userregister change stream failed - user cache off until it reopens: {}", e);
+                                break;
+                            }
+                        };
+                        let id = change.document_key.as_ref().and_then(|key| key.get_object_id("_id").ok());
+                        match change.operation_type {
+                            OperationType::Insert => {}
+                            OperationType::Update | OperationType::Replace | OperationType::Delete if id.is_some() => {
+                                Self::with_tenant(|cache| cache.invalidate(id.as_ref()));
+                            }
+                            _ => Self::with_tenant(|cache| cache.invalidate(None)),
+                        }
+                    }
+                }
+                Err(e) => {
+                    if !warned {
+                        warn!("⚠️ No change stream on userregister of tenant {} - user cache off: {}", tenant_id, e);
+                        warned = true;
+                    }
+                }
+            }
+            Self::with_tenant(|cache| {
+                cache.live = false;
+                cache.invalidate(None);
+            });
+            tokio::time::sleep(REWATCH_DELAY).await;
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use bson::doc;
+use futures_util::TryStreamExt;
+use mongodb::options::{FindOneAndUpdateOptions, ReturnDocument};
+use mongodb::error::{Error as MongoError, TRANSIENT_TRANSACTION_ERROR, UNKNOWN_TRANSACTION_COMMIT_RESULT};
+use mongodb::{ClientSession, Collection};
+use tracing::info;
+
+use crate::database::models::{Wallet, WalletDebit, WalletTransaction};
+use crate::database::write_queue::WriteQueue;
+use crate::database::DatabaseManager;
+use crate::managers::snowflake::Snowflake;
+
+// Attempts at a wallet change that lost a write conflict to a concurrent
+// change of the same wallet
+pub const TRANSACTION_ATTEMPTS: u32 = 5;
+
+// Coin balances (`wallets`) and their ledger (`wallet_transactions`). A
+// balance change and its ledger entry commit in one transaction (this needs
+// a replica set); the ledger's unique `reference` makes every change
+// idempotent.
+pub struct WalletService;
+
+impl WalletService {
+    pub fn new() -> Self {
+        Self
+    }
+
+    // Collection in the database of the tenant in scope, named with COLLECTION_PREFIX
+    fn collection<T>(&self, name: &str) -> Collection<T> {
+        DatabaseManager::collection(name)
+    }
+
+    fn wallets(&self) -> Collection<Wallet> {
+        self.collection("wallets")
+    }
+
+    fn transactions(&self) -> Collection<WalletTransaction> {
+        self.collection("wallet_transactions")
+    }
+
+    fn transaction(user_id: &str, amount: i64, reason: &str, reference: &str) -> WalletTransaction {
+        WalletTransaction {
+            id: None,
+            transaction_id: Snowflake::generate(),
+            user_id: user_id.to_string(),
+            amount,
+            reason: reason.to_string(),
+            reference: reference.to_string(),
+            created_at: bson::DateTime::now(),
+        }
+    }
+
+    fn is_duplicate(error: &(dyn std::error::Error + Send + Sync + 'static)) -> bool {
+        error.downcast_ref::<MongoError>().is_some_and(WriteQueue::is_duplicate_key)
+    }
+
+    // Whether a transaction lost a write conflict and may be retried
+    pub fn is_conflict(error: &(dyn std::error::Error + Send + Sync + 'static)) -> bool {
+        error.downcast_ref::<MongoError>().is_some_and(|e| e.contains_label(TRANSIENT_TRANSACTION_ERROR))
+    }
+
+    // Commit the session's transaction, again while the commit's outcome is
+    // unknown; committing twice is safe, redoing the transaction is not
+    pub async fn commit(session: &mut ClientSession) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut attempt = 1;
+        loop {
+            match session.commit_transaction().await {
+                Ok(()) => return Ok(()),
+                Err(e) if e.contains_label(UNKNOWN_TRANSACTION_COMMIT_RESULT) && attempt < TRANSACTION_ATTEMPTS => attempt += 1,
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    pub async fn balance(&self, user_id: &str) -> Result<i64, Box<dyn std::error::Error + Send + Sync>> {
+        let wallet = self.wallets().find_one(doc! { "user_id": user_id }, None).await?;
+        Ok(wallet.map_or(0, |w| w.balance))
+    }
+
+    // Add `amount` coins to a user's wallet. Returns the new balance, or None
+    // if a credit with this reference was already applied.
+    pub async fn credit(&self, user_id: &str, amount: i64, reason: &str, reference: &str) -> Result<Option<i64>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut attempt = 1;
+        loop {
+            // Dropping the session on an early return aborts the transaction
+            let mut session = self.wallets().client().start_session(None).await?;
+            session.start_transaction(None).await?;
+            let result = match self.credit_in_session(&mut session, user_id, amount, reason, reference).await {
+                Ok(balance) => Self::commit(&mut session).await.map(|_| balance),
+                Err(e) if Self::is_duplicate(&*e) => {
+                    info!("💰 Wallet credit {} already applied for user: {}", reference, user_id);
+                    return Ok(None);
+                }
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(balance) => {
+                    info!("💰 Credited {} coins to user: {} ({}, balance {})", amount, user_id, reason, balance);
+                    return Ok(Some(balance));
+                }
+                Err(e) if Self::is_conflict(&*e) && attempt < TRANSACTION_ATTEMPTS => attempt += 1,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn spent_pipeline(user_id: &str, since: chrono::DateTime<chrono::Utc>) -> Vec<bson::Document> {
+        vec![
+            doc! { "$match": {
+                "user_id": user_id,
+                "amount": { "$lt": 0 },
+                "created_at": { "$gte": bson::DateTime::from_millis(since.timestamp_millis()) }
+            } },
+            doc! { "$group": { "_id": null, "spent": { "$sum": "$amount" } } },
+        ]
+    }
+
+    fn spent_of(rows: &[bson::Document]) -> i64 {
+        let spent = rows.first().and_then(|row| row.get_i64("spent").ok().or_else(|| row.get_i32("spent").ok().map(i64::from)));
+        -spent.unwrap_or(0)
+    }
+
+    // Coins taken from a user's wallet at or after `since`
+    pub async fn spent_since(&self, user_id: &str, since: chrono::DateTime<chrono::Utc>) -> Result<i64, Box<dyn std::error::Error + Send + Sync>> {
+        let rows: Vec<bson::Document> = self.transactions().aggregate(Self::spent_pipeline(user_id, since), None).await?.try_collect().await?;
+        Ok(Self::spent_of(&rows))
+    }
+
+    // Take `amount` coins from a user's wallet. With a `daily_limit` (parental
+    // controls) the coins spent since the start of the UTC day count against
+    // it too. The limit check, the balance change and the ledger entry commit
+    // together; a concurrent debit of the same wallet conflicts and is retried
+    // against the new total, so the limit cannot be overrun. A reference that
+    // was already debited is not taken again and counts as Debited.
+    pub async fn debit(&self, user_id: &str, amount: i64, reason: &str, reference: &str, daily_limit: Option<i64>) -> Result<WalletDebit, Box<dyn std::error::Error + Send + Sync>> {
+        let mut attempt = 1;
+        loop {
+            let mut session = self.wallets().client().start_session(None).await?;
+            session.start_transaction(None).await?;
+            let result = match self.debit_in_session(&mut session, user_id, amount, reason, reference, daily_limit).await {
+                Ok(WalletDebit::Debited(balance)) => Self::commit(&mut session).await.map(|_| WalletDebit::Debited(balance)),
+                Ok(refused) => return Ok(refused),
+                Err(e) if Self::is_duplicate(&*e) => {
+                    info!("💰 Wallet debit {} already applied for user: {}", reference, user_id);
+                    return Ok(WalletDebit::Debited(self.balance(user_id).await?));
+                }
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(debited) => {
+                    info!("💰 Debited {} coins from user: {} ({}, {:?})", amount, user_id, reason, debited);
+                    return Ok(debited);
+                }
+                Err(e) if Self::is_conflict(&*e) && attempt < TRANSACTION_ATTEMPTS => attempt += 1,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    // As debit, within the caller's transaction. A reference already applied
+    // fails the transaction instead of counting as Debited.
+    pub async fn debit_in_session(&self, session: &mut ClientSession, user_id: &str, amount: i64, reason: &str, reference: &str, daily_limit: Option<i64>) -> Result<WalletDebit, Box<dyn std::error::Error + Send + Sync>> {
+        if let Some(daily_limit) = daily_limit {
+            let day_start = chrono::Utc::now().date_naive().and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+            let mut cursor = self.transactions().aggregate_with_session(Self::spent_pipeline(user_id, day_start), None, session).await?;
+            let rows: Vec<bson::Document> = cursor.stream(session).try_collect().await?;
+            let spent_today = Self::spent_of(&rows);
+            if spent_today + amount > daily_limit {
+                info!("👪 Debit of {} coins from user: {} refused by the daily limit ({} of {} spent)", amount, user_id, spent_today, daily_limit);
+                return Ok(WalletDebit::SpendLimitReached { spent_today, daily_limit });
+            }
+        }
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        let Some(wallet) = self.wallets()
+            .find_one_and_update_with_session(
+                doc! { "user_id": user_id, "balance": { "$gte": amount } },
+                doc! { "$inc": { "balance": -amount }, "$set": { "updated_at": bson::DateTime::now() } },
+                options,
+                session,
+            )
+            .await?
+        else {
+            return Ok(WalletDebit::InsufficientBalance);
+        };
+        self.transactions().insert_one_with_session(&Self::transaction(user_id, -amount, reason, reference), None, session).await?;
+        Ok(WalletDebit::Debited(wallet.balance))
+    }
+
+    // The ledger entry with this reference, if it was applied
+    pub async fn find_transaction(&self, reference: &str) -> Result<Option<WalletTransaction>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.transactions().find_one(doc! { "reference": reference }, None).await?)
+    }
+
+    // Write to a user's wallet without changing the balance, creating it if
+    // needed, so that concurrent transactions locking or changing the same
+    // wallet conflict and are retried
+    pub async fn lock_in_session(&self, session: &mut ClientSession, user_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let options = mongodb::options::UpdateOptions::builder().upsert(true).build();
+        self.wallets()
+            .update_one_with_session(
+                doc! { "user_id": user_id },
+                doc! { "$set": { "updated_at": bson::DateTime::now() }, "$setOnInsert": { "balance": 0_i64 } },
+                options,
+                session,
+            )
+            .await?;
+        Ok(())
+    }
+
+    // As credit, within the caller's transaction. A reference already applied
+    // fails the transaction instead of being skipped. Returns the new balance.
+    pub async fn credit_in_session(&self, session: &mut ClientSession, user_id: &str, amount: i64, reason: &str, reference: &str) -> Result<i64, Box<dyn std::error::Error + Send + Sync>> {
+        let options = FindOneAndUpdateOptions::builder()
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .build();
+        let wallet = self.wallets()
+            .find_one_and_update_with_session(
+                doc! { "user_id": user_id },
+                doc! { "$inc": { "balance": amount }, "$set": { "updated_at": bson::DateTime::now() } },
+                options,
+                session,
+            )
+            .await?
+            .ok_or("wallet upsert returned no document")?;
+        self.transactions().insert_one_with_session(&Self::transaction(user_id, amount, reason, reference), None, session).await?;
+        Ok(wallet.balance)
+    }
+}
@@ -0,0 +1,404 @@
+use bson::oid::ObjectId;
+use bson::{doc, Bson, Document};
+use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt;
+use mongodb::options::{FindOneAndUpdateOptions, FindOptions, ReturnDocument, UpdateOptions};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::time::Duration;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::config::CONFIG;
+use crate::database::envelope::{EventRegistry, EVENT_SCHEMAS};
+use crate::database::google_auth::ServiceAccount;
+use crate::database::write_queue::WriteQueue;
+use crate::database::DatabaseManager;
+use crate::managers::scheduler::Scheduler;
+use crate::managers::tenant::TenantManager;
+
+// Where each event collection's shipping stands, one document per collection
+const CHECKPOINTS: &str = "warehouse_checkpoints";
+// Batches the warehouse kept rejecting
+const DEAD_LETTERS: &str = "warehouse_dead_letters";
+const HTTP_TIMEOUT_SECS: u64 = 30;
+// Batches shipped per collection and run, so one busy collection cannot hold the others back for long
+const MAX_BATCHES_PER_RUN: usize = 20;
+// Dead letters listed by the admin API
+const MAX_LISTED_DEAD_LETTERS: i64 = 50;
+const BIGQUERY_SCOPE: &str = "https://www.googleapis.com/auth/bigquery.insertdata";
+
+type SinkResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+// The warehouse events go to, from WAREHOUSE_SINK and its variables
+enum Warehouse {
+    BigQuery { project: String, dataset: String, account: ServiceAccount },
+    ClickHouse { url: String, database: String, user: Option<String>, password: Option<String> },
+}
+
+impl Warehouse {
+    fn name(&self) -> &'static str {
+        match self {
+            Warehouse::BigQuery { .. } => "bigquery",
+            Warehouse::ClickHouse { .. } => "clickhouse",
+        }
+    }
+}
+
+// A batch the warehouse rejected WAREHOUSE_MAX_ATTEMPTS times in a row, with
+// its documents, so it can be replayed once the cause is fixed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetterBatch {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub batch_id: String,
+    pub collection: String,
+    pub warehouse: String,
+    pub rows: u32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub documents: Vec<Document>,
+    pub error: String,
+    pub attempts: u32,
+    pub failed_at: bson::DateTime,
+}
+
+static WAREHOUSE: Lazy<Option<Warehouse>> = Lazy::new(WarehouseSink::load);
+static HTTP: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(HTTP_TIMEOUT_SECS))
+        .build()
+        .unwrap_or_default()
+});
+static COLLECTIONS: Lazy<Vec<&'static str>> = Lazy::new(WarehouseSink::load_collections);
+
+// Holder of the checkpoint leases taken by this server
+static OWNER: Lazy<String> = Lazy::new(|| Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext)).to_string());
+
+// Optional sink that copies stored events into BigQuery (tabledata.insertAll)
+// or ClickHouse (HTTP interface, JSONEachRow). Every WAREHOUSE_INTERVAL_SECS
+// each tenant's event collections are read in _id order from a checkpoint, in
+// batches of WAREHOUSE_BATCH_SIZE, into table <WAREHOUSE_TABLE_PREFIX><collection>.
+// The checkpoint only moves once the warehouse accepted a batch, so a crash
+// or a failed request ships the batch again: delivery is at least once, with
+// BigQuery insertIds and ClickHouse insert_deduplication_token removing most
+// repeats. A batch that fails WAREHOUSE_MAX_ATTEMPTS runs in a row goes to
+// warehouse_dead_letters and the checkpoint moves past it. Each collection's
+// checkpoint is leased to one server at a time. Events younger than
+// WAREHOUSE_SETTLE_SECS wait for the next run, so writes still in flight on
+// other servers are not skipped.
+pub struct WarehouseSink;
+
+impl WarehouseSink {
+    fn load() -> Option<Warehouse> {
+        let kind = std::env::var("WAREHOUSE_SINK").ok().map(|v| v.trim().to_ascii_lowercase()).filter(|v| !v.is_empty())?;
+        let var = |key: &str| std::env::var(key).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        let warehouse = match kind.as_str() {
+            "bigquery" => {
+                let (Some(project), Some(dataset), Some(credentials)) = (var("BIGQUERY_PROJECT"), var("BIGQUERY_DATASET"), var("BIGQUERY_CREDENTIALS_FILE")) else {
+                    error!("❌ WAREHOUSE_SINK=bigquery needs BIGQUERY_PROJECT, BIGQUERY_DATASET and BIGQUERY_CREDENTIALS_FILE - sink disabled");
+                    return None;
+                };
+                match ServiceAccount::load(&credentials) {
+                    Ok(account) => Warehouse::BigQuery { project, dataset, account },
+                    Err(e) => {
+                        error!("❌ Cannot read service account key {} - warehouse sink disabled: {}", credentials, e);
+                        return None;
+                    }
+                }
+            }
+            "clickhouse" => {
+                let Some(url) = var("CLICKHOUSE_URL") else {
+                    error!("❌ WAREHOUSE_SINK=clickhouse needs CLICKHOUSE_URL - sink disabled");
+                    return None;
+                };
+                Warehouse::ClickHouse {
+                    url: url.trim_end_matches('/').to_string(),
+                    database: var("CLICKHOUSE_DATABASE").unwrap_or_else(|| "default".to_string()),
+                    user: var("CLICKHOUSE_USER"),
+                    password: var("CLICKHOUSE_PASSWORD"),
+                }
+            }
+            other => {
+                error!("❌ WAREHOUSE_SINK must be bigquery or clickhouse, got {} - sink disabled", other);
+                return None;
+            }
+        };
+        info!("🏭 Warehouse sink to {} for {} collection(s)", warehouse.name(), COLLECTIONS.len());
+        Some(warehouse)
+    }
+
+    // WAREHOUSE_COLLECTIONS, or every event collection
+    fn load_collections() -> Vec<&'static str> {
+        if CONFIG.warehouse_collections.is_empty() {
+            return EVENT_SCHEMAS.iter().map(|schema| schema.collection).collect();
+        }
+        CONFIG.warehouse_collections.iter()
+            .filter_map(|name| match EventRegistry::for_collection(name) {
+                Some(schema) => Some(schema.collection),
+                None => {
+                    warn!("⚠️ WAREHOUSE_COLLECTIONS names {}, which is not an event collection - skipped", name);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    fn table(collection: &str) -> String {
+        format!("{}{}", CONFIG.warehouse_table_prefix, collection)
+    }
+
+    pub fn spawn_shipper() {
+        if CONFIG.in_memory_store || WAREHOUSE.is_none() {
+            return;
+        }
+        let period = Duration::from_secs(CONFIG.warehouse_interval_secs);
+        Scheduler::every("warehouse-sink", period, || async {
+            // Writes buffered during an outage carry older _ids; ship them first
+            if WriteQueue::is_buffering() {
+                return Ok(());
+            }
+            for collection in COLLECTIONS.iter().copied() {
+                Self::ship_collection(collection).await?;
+            }
+            Ok(())
+        });
+    }
+
+    // Take or renew the lease on a collection's checkpoint; None while another server holds it
+    async fn claim(collection: &str) -> SinkResult<Option<Document>> {
+        let checkpoints = DatabaseManager::collection::<Document>(CHECKPOINTS);
+        let now = Utc::now();
+        let create = UpdateOptions::builder().upsert(true).build();
+        if let Err(e) = checkpoints.update_one(doc! { "_id": collection }, doc! { "$setOnInsert": { "shipped": 0_i64, "attempts": 0 } }, create).await {
+            // Another server created it at the same moment
+            if !WriteQueue::is_duplicate_key(&e) {
+                return Err(e.into());
+            }
+        }
+        let lease_until = now + chrono::Duration::seconds(CONFIG.warehouse_interval_secs as i64 * 2 + 60);
+        let filter = doc! {
+            "_id": collection,
+            "$or": [
+                { "lease_owner": OWNER.as_str() },
+                { "lease_until": { "$exists": false } },
+                { "lease_until": { "$lt": bson::DateTime::from_millis(now.timestamp_millis()) } },
+            ],
+        };
+        let update = doc! { "$set": { "lease_owner": OWNER.as_str(), "lease_until": bson::DateTime::from_millis(lease_until.timestamp_millis()) } };
+        let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build();
+        Ok(checkpoints.find_one_and_update(filter, update, options).await?)
+    }
+
+    async fn ship_collection(collection: &str) -> SinkResult<()> {
+        let Some(warehouse) = WAREHOUSE.as_ref() else { return Ok(()) };
+        let Some(checkpoint) = Self::claim(collection).await? else { return Ok(()) };
+        let mut last_id = checkpoint.get_object_id("last_id").ok();
+        let mut attempts = checkpoint.get_i32("attempts").unwrap_or(0).max(0) as u32;
+
+        // ObjectIds start with their creation second, so this bounds _id by age
+        let settled = Utc::now().timestamp() - CONFIG.warehouse_settle_secs as i64;
+        let mut settled_id = [0_u8; 12];
+        settled_id[..4].copy_from_slice(&(settled.max(0) as u32).to_be_bytes());
+        let settled_id = ObjectId::from_bytes(settled_id);
+
+        let events = DatabaseManager::collection::<Document>(collection);
+        let checkpoints = DatabaseManager::collection::<Document>(CHECKPOINTS);
+        for _ in 0..MAX_BATCHES_PER_RUN {
+            let mut range = doc! { "$lt": settled_id };
+            if let Some(last_id) = last_id {
+                range.insert("$gt", last_id);
+            }
+            let options = FindOptions::builder().sort(doc! { "_id": 1 }).limit(CONFIG.warehouse_batch_size as i64).build();
+            let batch: Vec<Document> = events.find(doc! { "_id": range }, options).await?.try_collect().await?;
+            let (Some(first), Some(last)) = (batch.first().and_then(|d| d.get_object_id("_id").ok()), batch.last().and_then(|d| d.get_object_id("_id").ok())) else {
+                break;
+            };
+            let batch_id = format!("{}:{}-{}", collection, first.to_hex(), last.to_hex());
+
+            match Self::ship(warehouse, collection, &batch_id, &batch).await {
+                Ok(()) => {
+                    attempts = 0;
+                    checkpoints.update_one(doc! { "_id": collection }, doc! {
+                        "$set": { "last_id": last, "attempts": 0, "last_error": Bson::Null, "shipped_at": bson::DateTime::now() },
+                        "$inc": { "shipped": batch.len() as i64 },
+                    }, None).await?;
+                }
+                Err(e) => {
+                    attempts += 1;
+                    if attempts < CONFIG.warehouse_max_attempts {
+                        warn!("⚠️ Warehouse rejected batch {} (attempt {}/{}) - retrying next run: {}", batch_id, attempts, CONFIG.warehouse_max_attempts, e);
+                        checkpoints.update_one(doc! { "_id": collection }, doc! {
+                            "$set": { "attempts": attempts as i32, "last_error": e.as_str() },
+                        }, None).await?;
+                        break;
+                    }
+                    error!("❌ Warehouse rejected batch {} {} times - moving it to {}: {}", batch_id, attempts, DEAD_LETTERS, e);
+                    let dead_letter = DeadLetterBatch {
+                        id: None,
+                        batch_id,
+                        collection: collection.to_string(),
+                        warehouse: warehouse.name().to_string(),
+                        rows: batch.len() as u32,
+                        documents: batch.clone(),
+                        error: e,
+                        attempts,
+                        failed_at: bson::DateTime::now(),
+                    };
+                    DatabaseManager::collection::<DeadLetterBatch>(DEAD_LETTERS).insert_one(dead_letter, None).await?;
+                    attempts = 0;
+                    checkpoints.update_one(doc! { "_id": collection }, doc! {
+                        "$set": { "last_id": last, "attempts": 0 },
+                        "$inc": { "dead_lettered": batch.len() as i64 },
+                    }, None).await?;
+                }
+            }
+            last_id = Some(last);
+            if batch.len() < CONFIG.warehouse_batch_size {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    // Send one batch; Err holds the reason for the checkpoint and the dead letter
+    async fn ship(warehouse: &Warehouse, collection: &str, batch_id: &str, batch: &[Document]) -> Result<(), String> {
+        let tenant_id = &TenantManager::current().tenant_id;
+        let rows: Vec<(String, Value)> = batch.iter().map(|document| {
+            let insert_id = document.get_object_id("_id").map(|id| id.to_hex()).unwrap_or_default();
+            let mut row = match Self::json_value(&Bson::Document(document.clone())) {
+                Value::Object(row) => row,
+                _ => Map::new(),
+            };
+            row.insert("tenant_id".to_string(), json!(tenant_id));
+            (insert_id, Value::Object(row))
+        }).collect();
+
+        match warehouse {
+            Warehouse::BigQuery { project, dataset, account } => {
+                let token = account.access_token(BIGQUERY_SCOPE).await?;
+                let url = format!(
+                    "https://bigquery.googleapis.com/bigquery/v2/projects/{}/datasets/{}/tables/{}/insertAll",
+                    project, dataset, Self::table(collection)
+                );
+                let body = json!({
+                    "ignoreUnknownValues": true,
+                    "rows": rows.into_iter().map(|(insert_id, row)| json!({ "insertId": insert_id, "json": row })).collect::<Vec<_>>(),
+                });
+                let response = HTTP.post(url).bearer_auth(token).json(&body).send().await.map_err(|e| e.to_string())?;
+                let status = response.status();
+                let answer: Value = response.json().await.unwrap_or(Value::Null);
+                if !status.is_success() {
+                    return Err(format!("BigQuery answered {}: {}", status, answer["error"]["message"].as_str().unwrap_or("")));
+                }
+                // insertAll answers 200 and lists the rows it did not take
+                match answer["insertErrors"].as_array() {
+                    Some(errors) if !errors.is_empty() => Err(format!("BigQuery rejected {} row(s): {}", errors.len(), errors[0]["errors"])),
+                    _ => Ok(()),
+                }
+            }
+            Warehouse::ClickHouse { url, database, user, password } => {
+                let mut body = String::new();
+                for (_, row) in &rows {
+                    body.push_str(&row.to_string());
+                    body.push('\n');
+                }
+                let query = format!("INSERT INTO `{}`.`{}` FORMAT JSONEachRow", database, Self::table(collection));
+                let mut request = HTTP.post(format!("{}/", url)).query(&[
+                    ("query", query.as_str()),
+                    ("input_format_skip_unknown_fields", "1"),
+                    ("date_time_input_format", "best_effort"),
+                    ("insert_deduplication_token", batch_id),
+                ]);
+                if let Some(user) = user {
+                    request = request.header("X-ClickHouse-User", user);
+                }
+                if let Some(password) = password {
+                    request = request.header("X-ClickHouse-Key", password);
+                }
+                let response = request.body(body).send().await.map_err(|e| e.to_string())?;
+                let status = response.status();
+                if status.is_success() {
+                    return Ok(());
+                }
+                let answer = response.text().await.unwrap_or_default();
+                Err(format!("ClickHouse answered {}: {}", status, answer.trim()))
+            }
+        }
+    }
+
+    // Plain JSON for warehouse columns: ObjectIds as hex, dates as RFC 3339
+    fn json_value(value: &Bson) -> Value {
+        match value {
+            Bson::Null | Bson::Undefined => Value::Null,
+            Bson::String(s) => json!(s),
+            Bson::Boolean(b) => json!(b),
+            Bson::Int32(n) => json!(n),
+            Bson::Int64(n) => json!(n),
+            Bson::Double(n) => json!(n),
+            Bson::ObjectId(id) => json!(id.to_hex()),
+            Bson::DateTime(time) => DateTime::from_timestamp_millis(time.timestamp_millis())
+                .map_or(Value::Null, |time| json!(time.to_rfc3339_opts(chrono::SecondsFormat::Millis, true))),
+            Bson::Array(values) => Value::Array(values.iter().map(Self::json_value).collect()),
+            Bson::Document(document) => Value::Object(document.iter().map(|(key, value)| (key.clone(), Self::json_value(value))).collect()),
+            other => other.clone().into_relaxed_extjson(),
+        }
+    }
+
+    // Per collection: where shipping stands and who holds the lease
+    pub async fn status() -> SinkResult<Value> {
+        let Some(warehouse) = WAREHOUSE.as_ref() else {
+            return Ok(json!({ "enabled": false }));
+        };
+        let checkpoints: Vec<Document> = DatabaseManager::collection::<Document>(CHECKPOINTS).find(None, None).await?.try_collect().await?;
+        let collections: Vec<Value> = COLLECTIONS.iter().copied().map(|collection| {
+            let checkpoint = checkpoints.iter().find(|c| c.get_str("_id") == Ok(collection));
+            let get = |key: &str| checkpoint.and_then(|c| c.get(key)).map(Self::json_value).unwrap_or(Value::Null);
+            json!({
+                "collection": collection,
+                "table": Self::table(collection),
+                "last_id": get("last_id"),
+                "last_event_at": checkpoint.and_then(|c| c.get_object_id("last_id").ok())
+                    .and_then(|id| DateTime::from_timestamp_millis(id.timestamp().timestamp_millis()))
+                    .map(|time| time.to_rfc3339()),
+                "shipped": get("shipped"),
+                "dead_lettered": get("dead_lettered"),
+                "shipped_at": get("shipped_at"),
+                "attempts": get("attempts"),
+                "last_error": get("last_error"),
+                "lease_owner": get("lease_owner"),
+                "lease_until": get("lease_until"),
+            })
+        }).collect();
+        let dead_letters = DatabaseManager::collection::<Document>(DEAD_LETTERS).count_documents(None, None).await?;
+        Ok(json!({
+            "enabled": true,
+            "warehouse": warehouse.name(),
+            "collections": collections,
+            "dead_letters": dead_letters,
+        }))
+    }
+
+    // Newest dead letters, without their documents
+    pub async fn dead_letters() -> SinkResult<Vec<DeadLetterBatch>> {
+        let options = FindOptions::builder()
+            .sort(doc! { "failed_at": -1 })
+            .limit(MAX_LISTED_DEAD_LETTERS)
+            .projection(doc! { "documents": 0 })
+            .build();
+        Ok(DatabaseManager::collection::<DeadLetterBatch>(DEAD_LETTERS).find(None, options).await?.try_collect().await?)
+    }
+
+    // Ship a dead letter's documents again and remove it once the warehouse
+    // took them. Ok(false) when there is no such dead letter.
+    pub async fn replay(dead_letter_id: &str) -> SinkResult<bool> {
+        let warehouse = WAREHOUSE.as_ref().ok_or("The warehouse sink is not configured")?;
+        let Ok(id) = ObjectId::parse_str(dead_letter_id) else { return Ok(false) };
+        let dead_letters = DatabaseManager::collection::<DeadLetterBatch>(DEAD_LETTERS);
+        let Some(dead_letter) = dead_letters.find_one(doc! { "_id": id }, None).await? else { return Ok(false) };
+        Self::ship(warehouse, &dead_letter.collection, &dead_letter.batch_id, &dead_letter.documents).await?;
+        dead_letters.delete_one(doc! { "_id": id }, None).await?;
+        info!("🏭 Replayed dead letter {} ({} rows of {})", dead_letter.batch_id, dead_letter.rows, dead_letter.collection);
+        Ok(true)
+    }
+}
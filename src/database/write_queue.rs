@@ -0,0 +1,111 @@
+use mongodb::bson::Document;
+use mongodb::error::{Error as MongoError, ErrorKind, WriteFailure, RETRYABLE_WRITE_ERROR};
+use once_cell::sync::Lazy;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::config::CONFIG;
+use crate::database::dead_letter::EventDeadLetters;
+use crate::database::health::DatabaseHealth;
+use crate::database::DatabaseManager;
+use crate::managers::metrics::MetricsManager;
+use crate::managers::tenant::{Tenant, TenantManager};
+
+// How often buffered writes are retried
+const REPLAY_INTERVAL: Duration = Duration::from_secs(2);
+// MongoDB duplicate key error
+const DUPLICATE_KEY: i32 = 11000;
+
+struct PendingWrite {
+    tenant: &'static Tenant,        // Whose database the write goes to
+    collection: &'static str,
+    document: Document,     // Carries its own _id, so a replay never stores it twice
+}
+
+static PENDING: Lazy<Mutex<VecDeque<PendingWrite>>> = Lazy::new(|| Mutex::new(VecDeque::new()));
+
+// Bounded in-memory buffer for event writes that fail while MongoDB is briefly
+// unreachable. Buffered writes are replayed in order once it is back; while
+// anything is buffered, new event writes join the queue instead of waiting on
+// a server that is known to be down. Lost on restart. A buffered write that
+// MongoDB then refuses is parked with EventDeadLetters.
+pub struct WriteQueue;
+
+impl WriteQueue {
+    // Buffer a write; false when buffering is disabled (WRITE_QUEUE_CAPACITY=0) or the queue is full
+    pub fn enqueue(collection: &'static str, document: Document) -> bool {
+        let mut pending = PENDING.lock().unwrap_or_else(|e| e.into_inner());
+        if pending.len() >= CONFIG.write_queue_capacity {
+            if CONFIG.write_queue_capacity > 0 {
+                error!("❌ Write queue full ({} writes) - not buffering {} write", pending.len(), collection);
+            }
+            return false;
+        }
+        if pending.is_empty() {
+            warn!("📥 MongoDB unavailable - buffering event writes (capacity {})", CONFIG.write_queue_capacity);
+        }
+        pending.push_back(PendingWrite { tenant: TenantManager::current(), collection, document });
+        true
+    }
+
+    pub fn is_buffering() -> bool {
+        !PENDING.lock().unwrap_or_else(|e| e.into_inner()).is_empty()
+    }
+
+    // Errors that mean MongoDB is unreachable rather than the write being invalid
+    pub fn is_transient(error: &MongoError) -> bool {
+        matches!(*error.kind, ErrorKind::Io(_) | ErrorKind::ServerSelection { .. } | ErrorKind::ConnectionPoolCleared { .. })
+            || error.contains_label(RETRYABLE_WRITE_ERROR)
+    }
+
+    pub fn is_duplicate_key(error: &MongoError) -> bool {
+        matches!(&*error.kind, ErrorKind::Write(WriteFailure::WriteError(e)) if e.code == DUPLICATE_KEY)
+    }
+
+    pub fn spawn_replayer() {
+        if CONFIG.write_queue_capacity == 0 {
+            return;
+        }
+        tokio::spawn(async {
+            let mut interval = tokio::time::interval(REPLAY_INTERVAL);
+            loop {
+                interval.tick().await;
+                Self::replay().await;
+            }
+        });
+    }
+
+    // Store buffered writes oldest first, stopping at the first one that still
+    // cannot reach MongoDB
+    async fn replay() {
+        if !DatabaseHealth::is_healthy() {
+            return;
+        }
+        let mut replayed = 0;
+        loop {
+            let next = PENDING.lock().unwrap_or_else(|e| e.into_inner())
+                .front()
+                .map(|write| (write.tenant, write.collection, write.document.clone()));
+            let Some((tenant, collection, document)) = next else { break };
+
+            match DatabaseManager::tenant_collection::<Document>(tenant, collection).insert_one(&document, None).await {
+                Ok(_) => replayed += 1,
+                Err(e) if Self::is_duplicate_key(&e) => replayed += 1,     // Stored by an earlier attempt
+                Err(e) if Self::is_transient(&e) => break,
+                Err(e) => {
+                    error!("❌ Dropping buffered {} write: {}", collection, e);
+                    EventDeadLetters::park(tenant, collection, document, e.to_string());
+                }
+            }
+            PENDING.lock().unwrap_or_else(|e| e.into_inner()).pop_front();
+        }
+
+        let depth = PENDING.lock().unwrap_or_else(|e| e.into_inner()).len();
+        if replayed > 0 {
+            info!("📤 Replayed {} buffered event writes ({} still queued)", replayed, depth);
+        }
+        MetricsManager::set_gauge("mongo_write_queue_depth", depth as f64).await;
+    }
+}
@@ -1,23 +1,35 @@
 use axum::{
+    http::StatusCode,
     routing::get,
     middleware,
 };
 use socketioxide::SocketIo;
 use tower_http::cors::CorsLayer;
-use tracing::{info, error};
+use tracing::{info, warn, error};
 use database::DatabaseManager;
+use std::net::SocketAddr;
 use std::sync::Arc;
 
 mod api;
+mod config;
 mod managers;
 mod database;
 
-use api::middleware::socket_io_validation;
+use api::middleware::{resolve_client_ip, socket_io_validation};
 use managers::GameManager;
-use database::service::DataService;
+use database::{ChaosDataStore, DataService, DataStore, InMemoryDataStore};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Build command for client type definitions, not a server mode
+    #[cfg(feature = "contracts")]
+    if std::env::args().nth(1).as_deref() == Some("export-contracts") {
+        let dir = std::env::args().nth(2).unwrap_or_else(|| "contracts".to_string());
+        api::contracts::export_contracts(std::path::Path::new(&dir))?;
+        println!("📝 Client contracts written to {}", dir);
+        return Ok(());
+    }
+
     // Set up enhanced panic hook to handle WebSocket panics
     std::panic::set_hook(Box::new(|panic_info| {
         error!("💥 Application panic: {:?}", panic_info);
@@ -43,12 +55,75 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .init();
 
     info!("🚀 Starting Socket.IO server with panic recovery...");
+    if config::CONFIG.dev_mode {
+        warn!("🧪 DEV_MODE is enabled - OTPs are included in login:success responses");
+    }
+    if config::CONFIG.test_otp_code.is_some() && !config::CONFIG.test_otp_mobile_numbers.is_empty() {
+        warn!("🧪 Static test OTP enabled for {} mobile number(s)", config::CONFIG.test_otp_mobile_numbers.len());
+    }
     
-    // Initialize MongoDB connection first
-    DatabaseManager::initialize().await?;
+    // A bad TENANTS stops startup here rather than on the first connection
+    let tenants = managers::tenant::TenantManager::tenants();
+    if tenants.len() > 1 {
+        info!("🏢 Serving {} tenants", tenants.len());
+    }
+    // Likewise a bad OTP_POLICIES or SETTINGS_DEFAULTS
+    managers::login_policy::LoginPolicyManager::policies();
+    managers::settings::SettingsManager::defaults("mobile");
+    managers::compliance::ComplianceManager::restricted_states();
+
+    // Restore tool, not a server mode: restore-backup <tenant_id> <backup_id> [collection...]
+    if std::env::args().nth(1).as_deref() == Some("restore-backup") {
+        DatabaseManager::initialize().await?;
+        let args: Vec<String> = std::env::args().skip(2).collect();
+        database::backup::BackupManager::restore_command(&args).await.map_err(|e| e as Box<dyn std::error::Error>)?;
+        return Ok(());
+    }
+    // Likewise replay-dead-letters, for event writes parked by EventDeadLetters
+    if std::env::args().nth(1).as_deref() == Some("replay-dead-letters") {
+        DatabaseManager::initialize().await?;
+        database::dead_letter::EventDeadLetters::replay_command().await.map_err(|e| e as Box<dyn std::error::Error>)?;
+        return Ok(());
+    }
+
+    // The server refuses to start with an unknown PAYLOAD_SIGNING_MODE or without PAYLOAD_SIGNING_SECRET
+    let signing_mode = managers::payload_signing::PayloadSigning::settings();
+    if signing_mode != managers::payload_signing::SigningMode::Enforce {
+        warn!("✍️ PAYLOAD_SIGNING_MODE is {} - unsigned {} are accepted", config::CONFIG.payload_signing_mode, managers::payload_signing::SIGNED_EVENTS.join(", "));
+    }
+
+    // Pick the data store first: MongoDB, or in-process storage for local runs without a database
+    let data_service: Arc<dyn DataStore> = if config::CONFIG.in_memory_store {
+        warn!("🧪 DATA_STORE=memory - data is kept in process and lost on restart");
+        Arc::new(InMemoryDataStore::new())
+    } else {
+        DatabaseManager::initialize().await?;
+        database::health::DatabaseHealth::spawn_monitor();
+        // Replays event writes buffered while MongoDB was unreachable
+        database::write_queue::WriteQueue::spawn_replayer();
+        // Drops cached users changed by admin tools or other servers
+        database::user_cache::UserCache::spawn_invalidator();
+        Arc::new(DataService::new())
+    };
+    // Settings, indexes, writable collections and schema version, before serving anyone
+    managers::startup_check::StartupCheck::run().await?;
+    if config::CONFIG.chaos_mode && !config::CONFIG.dev_mode {
+        warn!("⚠️ CHAOS_MODE ignored - fault injection requires DEV_MODE");
+    }
+    if config::CONFIG.chaos_mode && config::CONFIG.dev_mode {
+        warn!("🐒 CHAOS_MODE is enabled - failing {}% and delaying {}% (up to {}ms) of {}",
+            config::CONFIG.chaos_failure_percent, config::CONFIG.chaos_delay_percent, config::CONFIG.chaos_max_delay_ms,
+            if config::CONFIG.chaos_targets.is_empty() { "mongo,emit".to_string() } else { config::CONFIG.chaos_targets.join(",") });
+    }
+    let data_service: Arc<dyn DataStore> = if config::CONFIG.chaos_targets_include("mongo") {
+        Arc::new(ChaosDataStore::new(data_service))
+    } else {
+        data_service
+    };
     
     // Configure Socket.IO with enhanced settings for stability
     let (layer, io) = SocketIo::new_layer();
+    managers::connection::ConnectionManager::attach(&io);
 
     // Configure CORS for WebSocket with more permissive settings
     let cors = CorsLayer::new()
@@ -57,22 +132,63 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .allow_origin(tower_http::cors::Any)
         .allow_credentials(false);
 
-    // Create DataService instance
-    let data_service = Arc::new(DataService::new());
+    // Rule modules shipped as WASM plugins, available to GAME_MODES
+    #[cfg(feature = "wasm-plugins")]
+    managers::wasm_rules::WasmRulesHost::load_plugins();
+    #[cfg(not(feature = "wasm-plugins"))]
+    if let Some(dir) = &config::CONFIG.rule_plugins_dir {
+        warn!("⚠️ GAME_RULE_PLUGINS_DIR is set to {} but the server was built without the wasm-plugins feature - no plugins loaded", dir);
+    }
 
     // Initialize Game Manager with Socket.IO handlers
-    GameManager::initialize(&io, data_service);
+    GameManager::initialize(&io, data_service.clone());
+
+    // Game rules from game_configs, reloaded whenever an operator changes them
+    managers::game_config::GameConfigManager::spawn_hot_reload(data_service.clone()).await;
+
+    // Bring back matches interrupted by a crash, then keep their state saved
+    managers::room_snapshots::RoomSnapshotManager::restore(&io, data_service.clone()).await;
+    managers::room_snapshots::RoomSnapshotManager::spawn_snapshotter(data_service.clone());
+    managers::room_reaper::RoomReaperManager::spawn_reaper(io.clone(), data_service.clone());
+    managers::afk::AfkManager::spawn_scanner(io.clone(), data_service.clone());
+    managers::escrow::EscrowManager::spawn_recovery(data_service.clone());
+    managers::revenue::RevenueManager::spawn_rollups(data_service.clone());
+    managers::impersonation::ImpersonationManager::spawn_expiry(data_service.clone());
+    managers::challenges::ChallengeManager::spawn_generator(data_service.clone());
+    managers::seasons::SeasonManager::spawn_rollover(data_service.clone());
+    managers::risk::RiskManager::spawn_scanner(data_service.clone());
+    managers::chat::ChatManager::spawn_retention(data_service.clone());
+    managers::slo::SloManager::spawn_checker();
+    managers::sessions::SessionMetricsManager::spawn_sampler(data_service.clone());
+    managers::retention::RetentionManager::spawn_daily_report(data_service.clone());
+    database::export::ExportManager::spawn_daily_export();
+    database::warehouse::WarehouseSink::spawn_shipper();
+    database::outbox::Outbox::spawn_dispatcher();
+
+    // Prometheus metrics on METRICS_PORT (when ENABLE_METRICS=true)
+    api::metrics::spawn_metrics_server();
 
     let app = axum::Router::new()
         .route("/", get(|| async { "Socket.IO Game Admin Server - Panic Recovery Enabled" }))
-        .route("/health", get(|| async { "OK" }))
+        .route("/health", get(|| async {
+            if database::health::DatabaseHealth::is_healthy() {
+                (StatusCode::OK, "OK")
+            } else {
+                (StatusCode::SERVICE_UNAVAILABLE, "MongoDB unavailable")
+            }
+        }))
+        .merge(api::admin::router(data_service.clone()))
         .layer(cors)
         .layer(layer)
-        .layer(middleware::from_fn(socket_io_validation));
+        .layer(middleware::from_fn_with_state(data_service, socket_io_validation))
+        .layer(middleware::from_fn(resolve_client_ip));
 
-    info!("✨ Server listening on 0.0.0.0:3002");
+    let address = format!("{}:{}", config::CONFIG.server_host, config::CONFIG.server_port);
+    info!("✨ Server listening on {}", address);
     info!("🛡️ Only accepting Socket.IO connections");
-    info!("🗄️ MongoDB connection established");
+    if !config::CONFIG.in_memory_store {
+        info!("🗄️ MongoDB connection established");
+    }
     info!("🔧 Enhanced debug logging enabled");
     info!("🛡️ Enhanced panic handling with socket disconnection");
     info!("💓 Heartbeat configured: ping every 25s, timeout 20s");
@@ -82,10 +198,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     info!("📦 Max payload size: 1MB");
     info!("⏱️ Connection timeout: 60s");
     
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:3002").await?;
+    let listener = tokio::net::TcpListener::bind(&address).await?;
     
-    // Add enhanced error handling for the server
-    match axum::serve(listener, app).await {
+    // Add enhanced error handling for the server. Peer addresses are kept for
+    // the anomaly scans.
+    let served = if api::tls::TlsServer::enabled() {
+        api::tls::TlsServer::serve(listener, app).await
+    } else {
+        axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await
+    };
+    match served {
         Ok(_) => info!("✅ Server shutdown gracefully"),
         Err(e) => {
             error!("❌ Server error: {}", e);
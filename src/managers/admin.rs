@@ -0,0 +1,210 @@
+use socketioxide::extract::{Data, SocketRef, TryData};
+use socketioxide::socket::DisconnectReason;
+use socketioxide::SocketIo;
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+use crate::api::response::{ApiError, ApiResponse};
+use crate::config::CONFIG;
+use crate::database::store::DataStore;
+use crate::managers::correlation::Correlation;
+use crate::managers::error_responder::ErrorResponder;
+use crate::managers::funnel::FunnelManager;
+use crate::managers::handler_metrics::HandlerMetrics;
+use crate::managers::health_score::{HealthScoreManager, HEALTH_ROOM};
+use crate::managers::impersonation::ImpersonationManager;
+use crate::managers::namespace_policy::{NamespaceCaller, NamespaceGuard};
+use crate::managers::rbac::{AdminIdentity, Permission, Rbac};
+use crate::managers::tenant::TenantManager;
+
+// Authenticated operator per admin socket id
+static IDENTITIES: Lazy<RwLock<HashMap<String, AdminIdentity>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+// `/admin` namespace for the operations dashboard. Connections must send
+// ADMIN_API_TOKEN or an operator token as `admin_token` in the Socket.IO auth
+// payload; each event then checks the operator's role (see managers::rbac).
+pub struct AdminManager;
+
+impl AdminManager {
+    pub fn register_admin_namespace(io: &SocketIo, data_service: Arc<dyn DataStore>) {
+        FunnelManager::spawn_broadcaster(io.clone());
+        HealthScoreManager::spawn_monitor(io.clone());
+        if !NamespaceGuard::allowed("/admin") {
+            info!("🛠️ /admin is not in NAMESPACE_ALLOWLIST; the dashboard namespace is off");
+            return;
+        }
+        io.ns("/admin", move |socket: SocketRef, TryData::<Value>(auth)| {
+            let data_service = data_service.clone();
+            async move {
+                // Operators are per tenant, so they are looked up in the socket's tenant
+                let auth = auth.unwrap_or_default();
+                let Some((_, NamespaceCaller::Admin(identity))) = NamespaceGuard::connect(&socket, &*data_service, &auth).await else {
+                    let _ = socket.disconnect();
+                    return;
+                };
+                info!("🛠️ Admin dashboard connected: {} (operator {}, {})", socket.id, identity.operator_id, identity.role.as_str());
+                IDENTITIES.write().await.insert(socket.id.to_string(), identity);
+
+                // Per-handler call counts, error rates and latency
+                let ds = data_service.clone();
+                socket.on("metrics:handlers", move |s: SocketRef, Data::<Value>(data)| {
+                    let ds = ds.clone();
+                    let request_id = Correlation::request_id_from(&data);
+                    Correlation::scope("metrics:handlers", s.id, request_id, async move {
+                        if !Self::permitted(&s, &*ds, Permission::MetricsRead, "metrics:handlers").await {
+                            return;
+                        }
+                        let response = ApiResponse::success("metrics:handlers", json!({
+                            "handlers": HandlerMetrics::snapshot()
+                        })).for_socket(s.id);
+                        if let Err(e) = s.emit("metrics:handlers", response) {
+                            warn!("⚠️ Failed to send handler metrics to admin socket {}: {}", s.id, e);
+                        }
+                    })
+                });
+
+                // Live sign-in funnel: funnel:update to subscribers while the counts move
+                let ds = data_service.clone();
+                socket.on("funnel:subscribe", move |s: SocketRef, Data::<Value>(data)| {
+                    let ds = ds.clone();
+                    let request_id = Correlation::request_id_from(&data);
+                    Correlation::scope("funnel:subscribe", s.id, request_id, async move {
+                        if !Self::permitted(&s, &*ds, Permission::MetricsRead, "funnel:subscribe").await {
+                            return;
+                        }
+                        let _ = s.join(FunnelManager::room(&TenantManager::current().tenant_id));
+                        let response = ApiResponse::success("funnel:update", FunnelManager::snapshot()).for_socket(s.id);
+                        if let Err(e) = s.emit("funnel:update", response) {
+                            warn!("⚠️ Failed to send the funnel to admin socket {}: {}", s.id, e);
+                        }
+                    })
+                });
+
+                socket.on("funnel:unsubscribe", |s: SocketRef, Data::<Value>(data)| {
+                    let request_id = Correlation::request_id_from(&data);
+                    Correlation::scope("funnel:unsubscribe", s.id, request_id, async move {
+                        let _ = s.leave(FunnelManager::room(&TenantManager::current().tenant_id));
+                    })
+                });
+
+                let ds = data_service.clone();
+                socket.on("funnel:reset", move |s: SocketRef, Data::<Value>(data)| {
+                    let ds = ds.clone();
+                    let request_id = Correlation::request_id_from(&data);
+                    Correlation::scope("funnel:reset", s.id, request_id, async move {
+                        if !Self::permitted(&s, &*ds, Permission::MetricsRead, "funnel:reset").await {
+                            return;
+                        }
+                        FunnelManager::reset();
+                        info!("📉 Funnel reset by admin socket {}", s.id);
+                    })
+                });
+
+                // Deployment health score: health:score to subscribers every HEALTH_SCORE_INTERVAL_SECS
+                let ds = data_service.clone();
+                socket.on("health:subscribe", move |s: SocketRef, Data::<Value>(data)| {
+                    let ds = ds.clone();
+                    let request_id = Correlation::request_id_from(&data);
+                    Correlation::scope("health:subscribe", s.id, request_id, async move {
+                        if !Self::permitted(&s, &*ds, Permission::MetricsRead, "health:subscribe").await {
+                            return;
+                        }
+                        let _ = s.join(HEALTH_ROOM);
+                        let response = ApiResponse::success("health:score", HealthScoreManager::latest()).for_socket(s.id);
+                        if let Err(e) = s.emit("health:score", response) {
+                            warn!("⚠️ Failed to send the health score to admin socket {}: {}", s.id, e);
+                        }
+                    })
+                });
+
+                socket.on("health:unsubscribe", |s: SocketRef, Data::<Value>(data)| {
+                    let request_id = Correlation::request_id_from(&data);
+                    Correlation::scope("health:unsubscribe", s.id, request_id, async move {
+                        let _ = s.leave(HEALTH_ROOM);
+                    })
+                });
+
+                // Read-only support impersonation: impersonate:event for every emit to the user's sockets
+                let ds = data_service.clone();
+                socket.on("impersonate:start", move |s: SocketRef, Data::<Value>(data)| {
+                    let ds = ds.clone();
+                    let request_id = Correlation::request_id_from(&data);
+                    Correlation::scope("impersonate:start", s.id, request_id, async move {
+                        if !Self::permitted(&s, &*ds, Permission::UsersImpersonate, "impersonate:start").await {
+                            return;
+                        }
+                        let Some(identity) = IDENTITIES.read().await.get(&s.id.to_string()).cloned() else {
+                            return;
+                        };
+                        match ImpersonationManager::start(&s, &*ds, &identity, &data).await {
+                            Ok(session) => {
+                                let response = ApiResponse::success("impersonate:started", json!({
+                                    "session_id": session.session_id,
+                                    "user_id": session.user_id,
+                                    "expires_at": session.expires_at.try_to_rfc3339_string().unwrap_or_default(),
+                                    "disclosed": session.disclosed,
+                                })).for_socket(s.id);
+                                if let Err(e) = s.emit("impersonate:started", response) {
+                                    warn!("⚠️ Failed to send impersonate:started to admin socket {}: {}", s.id, e);
+                                }
+                            }
+                            Err(error) => ErrorResponder::send(&s, &*ds, error).await,
+                        }
+                    })
+                });
+
+                let ds = data_service.clone();
+                socket.on("impersonate:stop", move |s: SocketRef, Data::<Value>(data)| {
+                    let ds = ds.clone();
+                    let request_id = Correlation::request_id_from(&data);
+                    Correlation::scope("impersonate:stop", s.id, request_id, async move {
+                        if let Some(session_id) = ImpersonationManager::session_of(&s.id.to_string()) {
+                            ImpersonationManager::stop(&*ds, &session_id, "stopped").await;
+                        }
+                    })
+                });
+
+                let ds = data_service.clone();
+                socket.on_disconnect(move |s: SocketRef, _reason: DisconnectReason| {
+                    let ds = ds.clone();
+                    async move {
+                        if let Some(session_id) = ImpersonationManager::session_of(&s.id.to_string()) {
+                            ImpersonationManager::stop(&*ds, &session_id, "operator_disconnected").await;
+                        }
+                        IDENTITIES.write().await.remove(&s.id.to_string());
+                        TenantManager::remove_socket(&s.id.to_string()).await;
+                    }
+                });
+            }
+        });
+    }
+
+    // Checks the socket operator's role for `event`; sends admin:error when denied
+    async fn permitted(socket: &SocketRef, data_service: &dyn DataStore, permission: Permission, event: &str) -> bool {
+        let Some(identity) = IDENTITIES.read().await.get(&socket.id.to_string()).cloned() else {
+            return false;
+        };
+        if Rbac::authorize(data_service, &identity, permission, event).await {
+            return true;
+        }
+        let error = ApiError::new("ADMIN_PERMISSION_DENIED", "AUTHORIZATION_ERROR", "role", "Your role does not allow this action")
+            .with_details(json!({ "role": identity.role.as_str(), "required_permission": permission.as_str(), "event": event }))
+            .on_event("admin:error");
+        ErrorResponder::send(socket, data_service, error).await;
+        false
+    }
+
+    // Whether `token` is the ADMIN_API_TOKEN (the root admin)
+    pub fn authorized(token: Option<&str>) -> bool {
+        let (Some(expected), Some(token)) = (CONFIG.admin_api_token.as_deref(), token) else {
+            return false;
+        };
+        // Constant-time comparison
+        expected.len() == token.len()
+            && expected.bytes().zip(token.bytes()).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+    }
+}
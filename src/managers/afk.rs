@@ -0,0 +1,310 @@
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use socketioxide::{SocketIo, extract::{Data, SocketRef}};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::api::response::{ApiError, ApiResponse};
+use crate::config::CONFIG;
+use crate::database::models::{AfkEvent, DeepLink, ForfeitResult, GameOutcome, MatchResult};
+use crate::database::store::DataStore;
+use crate::managers::bot::BotPlayer;
+use crate::managers::correlation::Correlation;
+use crate::managers::game_modes::GameModeRegistry;
+use crate::managers::notifications::{NotificationCategory, NotificationManager};
+use crate::managers::party::player_room;
+use crate::managers::room::{GameRoom, RoomManager, RoomPlayer};
+use crate::managers::room_reaper::RoomReaperManager;
+use crate::managers::room_state::RoomStateManager;
+use crate::managers::scheduler::Scheduler;
+use crate::managers::validation::ValidationManager;
+
+const SCAN_SECS: u64 = 5;
+// Doublings of the matchmaking cooldown stop here (it is capped at the window anyway)
+const MAX_COOLDOWN_DOUBLINGS: u32 = 16;
+
+// How long a match has waited on one seated player
+struct Seat {
+    idle: chrono::Duration,
+    warned: bool,
+    last_scan: DateTime<Utc>,
+}
+
+// Seats by (room_id, player_id)
+static SEATS: Lazy<Mutex<HashMap<(String, String), Seat>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// What a scan decided for one player
+enum Verdict {
+    Warn,
+    Act,
+}
+
+// Idle players in running matches. A player is idle while the match waits on
+// them without hearing from them: during their own turns, or all the time in
+// a room played with room:update. A move, a room:update, room:join or afk:back
+// resets it. After AFK_WARN_AFTER_SECS of idle time the player gets
+// afk:warning; after AFK_ACTION_AFTER_SECS a bot takes their seat (afk:replaced
+// to the room, a loss for the player), or with AFK_ACTION=forfeit their team
+// forfeits and the room closes as "afk_forfeit". Staked matches and players
+// with no other human in the room always forfeit. Every warning and removal
+// goes to afk_events; players removed AFK_PENALTY_THRESHOLD times within
+// AFK_PENALTY_WINDOW_HOURS are refused matchmaking with AFK_COOLDOWN for a
+// while, longer with every further removal.
+pub struct AfkManager;
+
+impl AfkManager {
+    pub fn spawn_scanner(io: SocketIo, data_service: Arc<dyn DataStore>) {
+        if CONFIG.afk_warn_after_secs == 0 {
+            info!("💤 AFK detection is off (AFK_WARN_AFTER_SECS=0)");
+            return;
+        }
+        Scheduler::every("afk-scan", Duration::from_secs(SCAN_SECS), move || {
+            let io = io.clone();
+            let data_service = data_service.clone();
+            async move {
+                Self::scan(&io, &*data_service).await;
+                Ok(())
+            }
+        });
+    }
+
+    // The player did something in the room
+    pub fn activity(room_id: &str, player_id: &str) {
+        let mut seats = SEATS.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(seat) = seats.get_mut(&(room_id.to_string(), player_id.to_string())) {
+            seat.idle = chrono::Duration::zero();
+            seat.warned = false;
+        }
+    }
+
+    // Drop the seats of a closed room
+    pub fn forget(room_id: &str) {
+        SEATS.lock().unwrap_or_else(|e| e.into_inner()).retain(|(room, _), _| room != room_id);
+    }
+
+    async fn scan(io: &SocketIo, data_service: &dyn DataStore) {
+        let now = Utc::now();
+        let warn_after = chrono::Duration::seconds(CONFIG.afk_warn_after_secs);
+        let act_after = chrono::Duration::seconds(CONFIG.afk_action_after_secs);
+        for room in RoomManager::running_rooms().await {
+            let realtime = RoomStateManager::has_state(&room.room_id);
+            let waiting_on = room.active_turn.as_ref().map(|turn| turn.player_id.as_str());
+            let verdicts: Vec<(String, Verdict, i64)> = {
+                let mut seats = SEATS.lock().unwrap_or_else(|e| e.into_inner());
+                room.players.iter().filter(|p| !p.is_bot).filter_map(|player| {
+                    let seat = seats.entry((room.room_id.clone(), player.player_id.clone()))
+                        .or_insert(Seat { idle: chrono::Duration::zero(), warned: false, last_scan: now });
+                    if realtime || waiting_on == Some(player.player_id.as_str()) {
+                        seat.idle += now - seat.last_scan;
+                    }
+                    seat.last_scan = now;
+                    let verdict = if seat.idle >= act_after {
+                        Verdict::Act
+                    } else if seat.idle >= warn_after && !seat.warned {
+                        seat.warned = true;
+                        Verdict::Warn
+                    } else {
+                        return None;
+                    };
+                    Some((player.player_id.clone(), verdict, seat.idle.num_seconds()))
+                }).collect()
+            };
+
+            for (player_id, _, idle_secs) in verdicts.iter().filter(|(_, verdict, _)| matches!(verdict, Verdict::Warn)) {
+                Self::warn_player(io, data_service, &room, player_id, *idle_secs).await;
+            }
+            // One removal per room and scan: the room changes (or closes) with it
+            if let Some((player_id, _, idle_secs)) = verdicts.iter().find(|(_, verdict, _)| matches!(verdict, Verdict::Act)) {
+                Self::remove_player(io, data_service, &room, player_id, *idle_secs).await;
+            }
+        }
+    }
+
+    async fn warn_player(io: &SocketIo, data_service: &dyn DataStore, room: &GameRoom, player_id: &str, idle_secs: i64) {
+        let action_in_secs = (CONFIG.afk_action_after_secs - idle_secs).max(0);
+        let warning = ApiResponse::success("afk:warning", json!({
+            "room_id": room.room_id,
+            "player_id": player_id,
+            "idle_secs": idle_secs,
+            "action": Self::action_for(room, player_id),
+            "action_in_secs": action_in_secs,
+        }));
+        if let Some(ns) = io.of(GameModeRegistry::for_game_type(&room.config.game_type).namespace.as_str()) {
+            if let Err(e) = ns.to(player_room(player_id)).emit("afk:warning", warning) {
+                warn!("⚠️ Failed to send afk:warning to player {}: {}", player_id, e);
+            }
+        }
+        // Also a turn reminder, for a player who left the app
+        let data = json!({ "room_id": room.room_id, "action": Self::action_for(room, player_id), "action_in_secs": action_in_secs });
+        let action = DeepLink::new("open_room", json!({ "room_id": room.room_id }));
+        if let Err(e) = NotificationManager::dispatch(data_service, player_id, NotificationCategory::TurnReminders, "turn_reminder", json!({ "seconds": action_in_secs }), data, Some(action)).await {
+            warn!("⚠️ Failed to send a turn reminder to player {}: {}", player_id, e);
+        }
+        info!("💤 Player {} idle for {}s in room {} - warned", player_id, idle_secs, room.room_id);
+        Self::record(data_service, room, player_id, "warned", idle_secs).await;
+    }
+
+    // "replaced" when a bot can take the player's seat, "forfeited" otherwise
+    fn action_for(room: &GameRoom, player_id: &str) -> &'static str {
+        let staked = room.config.rules.entry_fee > 0 && !room.is_bot_match;
+        let other_humans = room.players.iter().any(|p| !p.is_bot && p.player_id != player_id);
+        if CONFIG.afk_action == "bot" && !staked && other_humans {
+            "replaced"
+        } else {
+            "forfeited"
+        }
+    }
+
+    async fn remove_player(io: &SocketIo, data_service: &dyn DataStore, room: &GameRoom, player_id: &str, idle_secs: i64) {
+        let action = Self::action_for(room, player_id);
+        let removed = match action {
+            "replaced" => Self::replace_with_bot(io, data_service, room, player_id).await,
+            _ => Self::forfeit(io, data_service, room, player_id).await,
+        };
+        if removed {
+            Self::record(data_service, room, player_id, action, idle_secs).await;
+        }
+    }
+
+    // Hand the seat to a bot; the player loses and the match goes on
+    async fn replace_with_bot(io: &SocketIo, data_service: &dyn DataStore, room: &GameRoom, player_id: &str) -> bool {
+        let bot_id = BotPlayer::new_bot_id();
+        let replaced = RoomManager::with_room(&room.room_id, |room| {
+            let seat = room.players.iter_mut().find(|p| p.player_id == player_id && !p.is_bot)?;
+            *seat = RoomPlayer::bot(&bot_id).with_team(seat.team);
+            Some(seat.team)
+        }).await.flatten();
+        let Some(team) = replaced else {
+            return false;
+        };
+        SEATS.lock().unwrap_or_else(|e| e.into_inner()).remove(&(room.room_id.clone(), player_id.to_string()));
+
+        let record = MatchResult {
+            id: None,
+            room_id: room.room_id.clone(),
+            user_id: player_id.to_string(),
+            outcome: GameOutcome::Loss,
+            reported_at: bson::DateTime::now(),
+        };
+        if let Err(e) = data_service.record_match_result(record).await {
+            warn!("⚠️ Failed to record the AFK loss of {} in room {}: {}", player_id, room.room_id, e);
+        }
+
+        let notice = ApiResponse::success("afk:replaced", json!({
+            "room_id": room.room_id,
+            "player_id": player_id,
+            "bot_id": bot_id,
+            "team": team,
+        }));
+        if let Some(ns) = io.of(GameModeRegistry::for_game_type(&room.config.game_type).namespace.as_str()) {
+            if let Err(e) = ns.to(room.room_id.clone()).emit("afk:replaced", notice) {
+                warn!("⚠️ Failed to broadcast afk:replaced to room {}: {}", room.room_id, e);
+            }
+        }
+        info!("🤖 Bot {} took the seat of idle player {} in room {}", bot_id, player_id, room.room_id);
+        true
+    }
+
+    // End the match: the idle player's team loses, every other human player wins
+    async fn forfeit(io: &SocketIo, data_service: &dyn DataStore, room: &GameRoom, player_id: &str) -> bool {
+        let Some(team) = room.players.iter().find(|p| p.player_id == player_id).map(|p| p.team) else {
+            return false;
+        };
+        let Some(room) = RoomManager::remove_room(&room.room_id).await else {
+            return false;
+        };
+        let results = room.players.iter().filter(|p| !p.is_bot).map(|p| {
+            let lost = p.team == team;
+            ForfeitResult {
+                player_id: p.player_id.clone(),
+                team: p.team,
+                outcome: if lost { GameOutcome::Loss } else { GameOutcome::Win },
+                forfeited: lost,
+            }
+        }).collect();
+        info!("🏳️ Idle player {} forfeits room {} for team {}", player_id, room.room_id, team);
+        RoomReaperManager::close(io, data_service, room, "afk_forfeit", results).await;
+        true
+    }
+
+    async fn record(data_service: &dyn DataStore, room: &GameRoom, player_id: &str, action: &str, idle_secs: i64) {
+        let event = AfkEvent {
+            id: None,
+            user_id: player_id.to_string(),
+            room_id: room.room_id.clone(),
+            game_type: room.config.game_type.clone(),
+            action: action.to_string(),
+            idle_secs,
+            created_at: bson::DateTime::now(),
+        };
+        if let Err(e) = data_service.record_afk_event(event).await {
+            warn!("⚠️ Failed to store the AFK event of {} in room {}: {}", player_id, room.room_id, e);
+        }
+    }
+
+    // Ok unless the player left enough recent matches idle to be in a
+    // cooldown, AFK_COOLDOWN then. Lets the player through when the history
+    // cannot be read.
+    pub async fn check_queue(data_service: &dyn DataStore, player_id: &str) -> Result<(), ApiError> {
+        let window = chrono::Duration::hours(CONFIG.afk_penalty_window_hours);
+        let since = bson::DateTime::from_millis((Utc::now() - window).timestamp_millis());
+        let events = match data_service.afk_events_since(player_id, since).await {
+            Ok(events) => events,
+            Err(e) => {
+                warn!("⚠️ Failed to read the AFK history of {}: {}", player_id, e);
+                return Ok(());
+            }
+        };
+        let removals: Vec<&AfkEvent> = events.iter().filter(|e| e.action != "warned").collect();
+        if removals.len() < CONFIG.afk_penalty_threshold {
+            return Ok(());
+        }
+
+        let doublings = ((removals.len() - CONFIG.afk_penalty_threshold) as u32).min(MAX_COOLDOWN_DOUBLINGS);
+        let cooldown = chrono::Duration::seconds(CONFIG.afk_penalty_cooldown_secs * 2_i64.pow(doublings)).min(window);
+        // Newest first
+        let latest = DateTime::from_timestamp_millis(removals[0].created_at.timestamp_millis()).unwrap_or_default();
+        let until = latest + cooldown;
+        let retry_after = until - Utc::now();
+        if retry_after <= chrono::Duration::zero() {
+            return Ok(());
+        }
+        Err(ApiError::new("AFK_COOLDOWN", "MATCHMAKING_ERROR", "player_id", "Matchmaking is paused after leaving recent matches idle")
+            .with_details(json!({
+                "player_id": player_id,
+                "offenses": removals.len(),
+                "retry_after_secs": retry_after.num_seconds() + 1,
+                "until": until.to_rfc3339(),
+            })))
+    }
+
+    // Client events:
+    //   afk:back { room_id, player_id } -> afk:back to the sender; the player is no longer idle
+    pub fn register_afk_events(socket: &SocketRef) {
+        socket.on("afk:back", |s: SocketRef, Data::<Value>(data)| {
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope("afk:back", s.id, request_id, async move {
+                if let Err(error_details) = ValidationManager::validate_room_data(&data) {
+                    let _ = s.emit("room:error", ApiError::from(error_details).on_event("room:error").for_socket(s.id));
+                    return;
+                }
+                let room_id = data["room_id"].as_str().unwrap_or_default();
+                let player_id = data["player_id"].as_str().unwrap_or_default();
+                if !RoomManager::is_seated(room_id, player_id, &s.id.to_string()).await {
+                    let error = ApiError::new("NOT_IN_ROOM", "ROOM_ERROR", "room_id", "Only players seated in the room can report back")
+                        .with_details(json!({"room_id": room_id, "player_id": player_id}));
+                    let _ = s.emit("room:error", error.on_event("room:error").for_socket(s.id));
+                    return;
+                }
+                Self::activity(room_id, player_id);
+                let _ = s.emit("afk:back", ApiResponse::success("afk:back", json!({
+                    "room_id": room_id,
+                    "player_id": player_id,
+                })).for_socket(s.id));
+            })
+        });
+    }
+}
@@ -0,0 +1,162 @@
+use socketioxide::extract::{Data, SocketRef};
+use serde_json::{json, Value};
+use std::future::Future;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::api::response::ApiError;
+use crate::database::models::{SessionStatus, UserRegister};
+use crate::database::store::DataStore;
+use crate::managers::correlation::Correlation;
+use crate::managers::error_responder::ErrorResponder;
+use crate::managers::impersonation::ImpersonationManager;
+use crate::managers::jwt::create_jwt_service;
+use crate::managers::payload_signing::{PayloadSigning, SIGNED_EVENTS};
+use crate::managers::sessions::SessionMetricsManager;
+
+// Caller identity handed to guarded handlers
+#[derive(Debug, Clone)]
+pub struct AuthContext {
+    pub mobile_no: String,
+    pub user: UserRegister,
+    pub session_token: Option<String>,  // Set when authenticated by session_token
+}
+
+#[derive(Debug, Clone, Copy)]
+enum AuthMethod {
+    Session,
+    SignedSession,  // Session plus a payload signature, see PayloadSigning
+}
+
+// Registers socket events that need an authenticated caller. The guard
+// checks credentials before the handler runs; on failure it sends a
+// standardized AUTHENTICATION_ERROR on connection_error and the handler is
+// never called. Guarded handlers also run inside the request's correlation scope.
+pub struct AuthGuard;
+
+impl AuthGuard {
+    // `mobile_no` + `session_token` from the payload; renews the session
+    pub fn require_session<H, Fut>(socket: &SocketRef, event: &'static str, data_service: Arc<dyn DataStore>, handler: H)
+    where
+        H: Fn(SocketRef, Value, AuthContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        Self::register(socket, event, data_service, AuthMethod::Session, handler);
+    }
+
+    // As require_session, and the payload must carry a valid `signature` made
+    // with the session's signing key (for events that move coins or items)
+    pub fn require_signed_session<H, Fut>(socket: &SocketRef, event: &'static str, data_service: Arc<dyn DataStore>, handler: H)
+    where
+        H: Fn(SocketRef, Value, AuthContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        debug_assert!(SIGNED_EVENTS.contains(&event), "{} is missing from SIGNED_EVENTS", event);
+        Self::register(socket, event, data_service, AuthMethod::SignedSession, handler);
+    }
+
+    fn register<H, Fut>(socket: &SocketRef, event: &'static str, data_service: Arc<dyn DataStore>, method: AuthMethod, handler: H)
+    where
+        H: Fn(SocketRef, Value, AuthContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let handler = Arc::new(handler);
+        socket.on(event, move |socket: SocketRef, Data::<Value>(data)| {
+            let ds = data_service.clone();
+            let handler = handler.clone();
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope(event, socket.id, request_id, async move {
+                let auth = match method {
+                    AuthMethod::Session => Self::verify_session(&*ds, &data).await,
+                    AuthMethod::SignedSession => match Self::verify_session(&*ds, &data).await {
+                        Ok(auth) => PayloadSigning::check(event, &data, auth.session_token.as_deref().unwrap_or_default()).map(|_| auth).map_err(|error| *error),
+                        Err(error) => Err(error),
+                    },
+                };
+                match auth {
+                    Ok(auth) => {
+                        ImpersonationManager::bind(&socket, &auth.user.user_id);
+                        SessionMetricsManager::authenticated(&socket, &auth.user.user_id);
+                        handler(socket, data, auth).await
+                    }
+                    Err(error) => {
+                        info!("🔒 {} rejected for socket {}: {}", event, socket.id, error.error_code);
+                        ErrorResponder::send(&socket, &*ds, error).await;
+                    }
+                }
+            })
+        });
+    }
+
+    pub async fn verify_session(data_service: &dyn DataStore, data: &Value) -> Result<AuthContext, ApiError> {
+        let (Some(mobile_no), Some(session_token)) = (data["mobile_no"].as_str(), data["session_token"].as_str()) else {
+            return Err(Self::missing_credentials("session_token", "mobile_no and session_token are required"));
+        };
+
+        let status = data_service.verify_session_and_mobile(mobile_no, session_token).await
+            .map_err(|e| ApiError::system("SESSION_VERIFICATION_ERROR", "session_token", "Session verification failed due to system error", &e))?;
+        let user = match status {
+            SessionStatus::Valid => Self::load_user(data_service, mobile_no, "session_token").await?,
+            _ => None,
+        };
+        let Some(user) = user else {
+            let (error_code, error_message) = status.error_details();
+            return Err(ApiError::new(error_code, "AUTHENTICATION_ERROR", "session_token", error_message)
+                .with_details(json!({"mobile_no": mobile_no})));
+        };
+
+        Ok(AuthContext {
+            mobile_no: mobile_no.to_string(),
+            user,
+            session_token: Some(session_token.to_string()),
+        })
+    }
+
+    // `jwt_token` (as issued by otp:verified), for namespaces with the jwt
+    // NamespacePolicy. If `mobile_no` is also sent it must match the token.
+    pub async fn verify_jwt(data_service: &dyn DataStore, data: &Value) -> Result<AuthContext, ApiError> {
+        let Some(token) = data["jwt_token"].as_str() else {
+            return Err(Self::missing_credentials("jwt_token", "jwt_token is required"));
+        };
+
+        let claims = match create_jwt_service().verify_token(token) {
+            Ok(claims) => claims,
+            Err(e) => {
+                let expired = e.downcast_ref::<jsonwebtoken::errors::Error>()
+                    .is_some_and(|e| matches!(e.kind(), jsonwebtoken::errors::ErrorKind::ExpiredSignature));
+                return Err(if expired {
+                    ApiError::new("TOKEN_EXPIRED", "AUTHENTICATION_ERROR", "jwt_token", "Token expired. Please login again.")
+                } else {
+                    ApiError::new("INVALID_TOKEN", "AUTHENTICATION_ERROR", "jwt_token", "Invalid token. Please login again.")
+                });
+            }
+        };
+        if data["mobile_no"].as_str().is_some_and(|mobile_no| mobile_no != claims.mobile_no) {
+            return Err(ApiError::new("INVALID_TOKEN", "AUTHENTICATION_ERROR", "jwt_token", "Invalid token. Please login again.")
+                .with_details(json!({"reason": "mobile_no does not match token"})));
+        }
+
+        // The account must still exist and match the token's subject
+        let user = Self::load_user(data_service, &claims.mobile_no, "jwt_token").await?
+            .filter(|user| user.user_id == claims.sub);
+        let Some(user) = user else {
+            return Err(ApiError::new("INVALID_TOKEN", "AUTHENTICATION_ERROR", "jwt_token", "Invalid token. Please login again."));
+        };
+
+        Ok(AuthContext {
+            mobile_no: claims.mobile_no,
+            user,
+            session_token: None,
+        })
+    }
+
+    async fn load_user(data_service: &dyn DataStore, mobile_no: &str, field: &str) -> Result<Option<UserRegister>, ApiError> {
+        data_service.get_user_by_mobile(mobile_no).await
+            .map_err(|e| ApiError::system("SESSION_VERIFICATION_ERROR", field, "Session verification failed due to system error", &e))
+    }
+
+    fn missing_credentials(field: &str, message: &str) -> ApiError {
+        ApiError::new("AUTH_REQUIRED", "AUTHENTICATION_ERROR", field, "Authentication required. Please login.")
+            .with_details(json!({"required": message}))
+    }
+}
@@ -0,0 +1,91 @@
+use chrono::{DateTime, Duration, Utc};
+use once_cell::sync::Lazy;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::api::response::ApiError;
+use crate::config::CONFIG;
+use crate::managers::token::TokenGenerator;
+
+// What a login:success nonce was issued for
+#[derive(Debug, Clone)]
+pub struct IssuedNonce {
+    pub nonce: String,
+    socket_id: String,
+    mobile_no: String,
+    session_token: String,
+    expires_at: DateTime<Utc>,
+}
+
+// Unused nonces by nonce
+static NONCES: Lazy<Mutex<HashMap<String, IssuedNonce>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// Replay protection for the OTP exchange: login:success hands out a nonce
+// bound to the socket, mobile number and session it was issued for, and
+// verify:otp must send it back from that socket before the OTP expires. A
+// nonce is used up by a successful verification, so a captured verify:otp
+// payload is refused when sent again or from another socket. Nonces live
+// in memory; a socket keeps talking to the server that issued its nonce.
+pub struct AuthNonceManager;
+
+impl AuthNonceManager {
+    fn lock() -> std::sync::MutexGuard<'static, HashMap<String, IssuedNonce>> {
+        NONCES.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // New nonce for this socket's login; replaces any earlier one for the same session
+    pub fn issue(socket_id: &str, mobile_no: &str, session_token: &str, ttl_secs: i64) -> String {
+        let now = Utc::now();
+        let issued = IssuedNonce {
+            nonce: TokenGenerator::session_token(),
+            socket_id: socket_id.to_string(),
+            mobile_no: mobile_no.to_string(),
+            session_token: session_token.to_string(),
+            expires_at: now + Duration::seconds(ttl_secs),
+        };
+        let mut nonces = Self::lock();
+        nonces.retain(|_, n| n.expires_at > now && !(n.socket_id == socket_id && n.session_token == session_token));
+        nonces.insert(issued.nonce.clone(), issued.clone());
+        issued.nonce
+    }
+
+    fn error(code: &str, message: &str, mobile_no: &str) -> ApiError {
+        ApiError::new(code, "AUTHENTICATION_ERROR", "nonce", message)
+            .with_details(json!({ "mobile_no": mobile_no }))
+            .on_event("otp:verification_failed")
+    }
+
+    // Take the nonce sent with verify:otp so no other request can use it.
+    // Ok(None) when it was left out and AUTH_NONCE_REQUIRED is off.
+    pub fn claim(socket_id: &str, mobile_no: &str, session_token: &str, nonce: Option<&str>) -> Result<Option<IssuedNonce>, ApiError> {
+        let Some(nonce) = nonce else {
+            if CONFIG.auth_nonce_required {
+                return Err(Self::error("NONCE_REQUIRED", "nonce from login:success is required. Please login again.", mobile_no));
+            }
+            return Ok(None);
+        };
+        let mut nonces = Self::lock();
+        let issued = nonces.get(nonce).filter(|n| {
+            n.socket_id == socket_id
+                && n.mobile_no == mobile_no
+                && TokenGenerator::constant_time_eq(&n.session_token, session_token)
+                && n.expires_at > Utc::now()
+        });
+        if issued.is_none() {
+            return Err(Self::error("INVALID_NONCE", "This login request is no longer valid. Please login again.", mobile_no));
+        }
+        Ok(nonces.remove(nonce))
+    }
+
+    // Give a claimed nonce back after a failed verification so the user can retry
+    pub fn release(issued: IssuedNonce) {
+        if issued.expires_at > Utc::now() {
+            Self::lock().insert(issued.nonce.clone(), issued);
+        }
+    }
+
+    pub fn remove_socket(socket_id: &str) {
+        Self::lock().retain(|_, n| n.socket_id != socket_id);
+    }
+}
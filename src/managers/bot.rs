@@ -0,0 +1,33 @@
+use serde_json::{json, Value};
+use rand::Rng;
+use uuid::Uuid;
+
+// Prefix that marks a player id as a server-side bot
+pub const BOT_ID_PREFIX: &str = "bot:";
+
+// Bots "think" for a short random time so their moves feel natural,
+// always well inside the turn deadline
+const BOT_MIN_THINK_MS: u64 = 800;
+const BOT_MAX_THINK_MS: u64 = 2500;
+
+pub struct BotPlayer;
+
+impl BotPlayer {
+    pub fn new_bot_id() -> String {
+        format!("{}{}", BOT_ID_PREFIX, Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext)))
+    }
+
+    pub fn think_time() -> std::time::Duration {
+        std::time::Duration::from_millis(rand::thread_rng().gen_range(BOT_MIN_THINK_MS..=BOT_MAX_THINK_MS))
+    }
+
+    // Pick the bot's move for the current turn. The generic turn loop has no
+    // game-specific move set yet, so the bot always plays a legal "move" action.
+    pub fn choose_action(turn_number: u32) -> Value {
+        json!({
+            "type": "move",
+            "source": "bot",
+            "turn_number": turn_number
+        })
+    }
+}
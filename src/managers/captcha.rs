@@ -0,0 +1,131 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use serde_json::json;
+use socketioxide::extract::SocketRef;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::api::middleware::ClientIp;
+use crate::api::response::ApiError;
+use crate::config::CONFIG;
+
+const VERIFY_TIMEOUT_SECS: u64 = 5;
+
+// Recent login attempts per mobile number and per client IP
+#[derive(Default)]
+struct Attempts {
+    by_mobile: HashMap<String, VecDeque<DateTime<Utc>>>,
+    by_ip: HashMap<String, VecDeque<DateTime<Utc>>>,
+}
+
+static ATTEMPTS: Lazy<Mutex<Attempts>> = Lazy::new(|| Mutex::new(Attempts::default()));
+static HTTP: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(VERIFY_TIMEOUT_SECS))
+        .build()
+        .unwrap_or_default()
+});
+
+#[derive(Deserialize)]
+struct SiteVerifyResponse {
+    success: bool,
+    #[serde(default, rename = "error-codes")]
+    error_codes: Vec<String>,
+}
+
+// Count one attempt and tell how many the key made within the window
+fn count(recent: &mut HashMap<String, VecDeque<DateTime<Utc>>>, key: &str, now: DateTime<Utc>, cutoff: DateTime<Utc>) -> usize {
+    recent.retain(|_, attempts| attempts.back().is_some_and(|at| *at > cutoff));
+    let attempts = recent.entry(key.to_string()).or_default();
+    while attempts.front().is_some_and(|at| *at <= cutoff) {
+        attempts.pop_front();
+    }
+    attempts.push_back(now);
+    attempts.len()
+}
+
+// CAPTCHA for logins that look automated. A mobile number with more than
+// CAPTCHA_MOBILE_LOGINS, or an IP with more than CAPTCHA_IP_LOGINS, login
+// attempts within CAPTCHA_WINDOW_SECS must send a `captcha_token` from the
+// hCaptcha or Turnstile widget, checked with the provider before an OTP is
+// issued. Off unless CAPTCHA_SECRET_KEY is set; test accounts never get it.
+pub struct CaptchaManager;
+
+impl CaptchaManager {
+    fn enabled() -> bool {
+        CONFIG.captcha_secret_key.is_some()
+    }
+
+    fn client_ip(socket: &SocketRef) -> Option<String> {
+        ClientIp::of(socket).map(|client| client.ip.to_string())
+    }
+
+    fn verify_url() -> &'static str {
+        match CONFIG.captcha_provider.as_str() {
+            "hcaptcha" => "https://api.hcaptcha.com/siteverify",
+            _ => "https://challenges.cloudflare.com/turnstile/v0/siteverify",
+        }
+    }
+
+    // Whether this attempt trips the velocity limits; every call counts
+    fn suspicious(mobile_no: &str, ip: Option<&str>) -> bool {
+        let now = Utc::now();
+        let cutoff = now - ChronoDuration::seconds(CONFIG.captcha_window_secs);
+        let mut attempts = ATTEMPTS.lock().unwrap_or_else(|e| e.into_inner());
+        let attempts = &mut *attempts;
+        let mobile_count = count(&mut attempts.by_mobile, mobile_no, now, cutoff);
+        let ip_count = ip.map_or(0, |ip| count(&mut attempts.by_ip, ip, now, cutoff));
+        mobile_count > CONFIG.captcha_mobile_logins || ip_count > CONFIG.captcha_ip_logins
+    }
+
+    fn required(reason: &str) -> ApiError {
+        ApiError::new("CAPTCHA_REQUIRED", "AUTHENTICATION_ERROR", "captcha_token", "Please complete the CAPTCHA challenge and try again.")
+            .with_details(json!({
+                "provider": CONFIG.captcha_provider,
+                "site_key": CONFIG.captcha_site_key,
+                "reason": reason,
+            }))
+    }
+
+    // Ok when the login may get an OTP, CAPTCHA_REQUIRED when it has to
+    // present (another) solved challenge first
+    pub async fn check_login(socket: &SocketRef, mobile_no: &str, captcha_token: Option<&str>) -> Result<(), ApiError> {
+        if !Self::enabled() || CONFIG.test_otp_for(mobile_no).is_some() {
+            return Ok(());
+        }
+        let ip = Self::client_ip(socket);
+        if !Self::suspicious(mobile_no, ip.as_deref()) {
+            return Ok(());
+        }
+        let Some(token) = captcha_token.filter(|token| !token.is_empty()) else {
+            info!("🤖 CAPTCHA required for login of mobile: {} (socket: {})", mobile_no, socket.id);
+            return Err(Self::required("challenge_required"));
+        };
+
+        let secret = CONFIG.captcha_secret_key.as_deref().unwrap_or_default();
+        let mut form = vec![("secret", secret), ("response", token)];
+        if let Some(ip) = ip.as_deref() {
+            form.push(("remoteip", ip));
+        }
+        let verified = async {
+            HTTP.post(Self::verify_url()).form(&form).send().await?
+                .error_for_status()?
+                .json::<SiteVerifyResponse>().await
+        }.await;
+        match verified {
+            Ok(response) if response.success => Ok(()),
+            Ok(response) => {
+                info!("🤖 CAPTCHA rejected for mobile: {} (socket: {}): {:?}", mobile_no, socket.id, response.error_codes);
+                Err(Self::required("challenge_failed"))
+            }
+            // An unreachable provider must not lock everyone out of logging in
+            Err(e) => {
+                warn!("⚠️ CAPTCHA verification unavailable - letting login of {} through: {}", mobile_no, e.without_url());
+                Ok(())
+            }
+        }
+    }
+}
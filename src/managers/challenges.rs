@@ -0,0 +1,257 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use socketioxide::extract::SocketRef;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{info, warn, error};
+
+use crate::api::response::{ApiError, ApiResponse};
+use crate::config::CONFIG;
+use crate::database::models::{ChallengeKind, DailyChallenge, DailyChallengeSet};
+use crate::database::store::DataStore;
+use crate::managers::auth_guard::AuthGuard;
+use crate::managers::chaos::FaultInjector;
+use crate::managers::error_responder::ErrorResponder;
+use crate::managers::scheduler::Scheduler;
+use crate::managers::validation::ValidationManager;
+
+// Wallet ledger reason for challenge rewards
+const REWARD_REASON: &str = "daily_challenge";
+
+// Pool the daily challenges are drawn from. The target is a multiple of
+// `step` in min_target..=max_target; the reward is target * coins_per_100 / 100.
+struct ChallengeTemplate {
+    kind: ChallengeKind,
+    min_target: i64,
+    max_target: i64,
+    step: i64,
+    coins_per_100: i64,
+}
+
+const TEMPLATES: &[ChallengeTemplate] = &[
+    ChallengeTemplate { kind: ChallengeKind::PlayGames, min_target: 3, max_target: 6, step: 1, coins_per_100: 2000 },
+    ChallengeTemplate { kind: ChallengeKind::WinGames, min_target: 1, max_target: 3, step: 1, coins_per_100: 6000 },
+    ChallengeTemplate { kind: ChallengeKind::EarnXp, min_target: 200, max_target: 600, step: 100, coins_per_100: 20 },
+    ChallengeTemplate { kind: ChallengeKind::TakeTurns, min_target: 20, max_target: 50, step: 5, coins_per_100: 200 },
+];
+
+// Daily challenges: a set per UTC day generated by a background job (or on
+// first use), progress counted from progress:update and player_action, and
+// rewards credited to the wallet once per user and challenge.
+pub struct ChallengeManager;
+
+impl ChallengeManager {
+    pub fn date_of(time: DateTime<Utc>) -> String {
+        time.format("%Y-%m-%d").to_string()
+    }
+
+    fn next_reset(now: DateTime<Utc>) -> DateTime<Utc> {
+        let tomorrow = now.date_naive() + Duration::days(1);
+        tomorrow.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc()
+    }
+
+    // Draw the day's challenges, seeded by the date so every instance proposes the same set
+    fn generate(date: &str) -> DailyChallengeSet {
+        let seed = NaiveDate::parse_from_str(date, "%Y-%m-%d").map_or(0, |d| d.num_days_from_ce() as u64);
+        let mut rng = StdRng::seed_from_u64(seed);
+        let count = CONFIG.daily_challenge_count.clamp(1, TEMPLATES.len());
+        let challenges = TEMPLATES.choose_multiple(&mut rng, count).map(|template| {
+            let steps = (template.max_target - template.min_target) / template.step;
+            let target = template.min_target + rng.gen_range(0..=steps) * template.step;
+            DailyChallenge {
+                challenge_id: format!("{}:{}", date, template.kind.as_str()),
+                kind: template.kind,
+                target,
+                reward_coins: (target * template.coins_per_100 / 100).max(1),
+            }
+        }).collect();
+        DailyChallengeSet {
+            id: None,
+            date: date.to_string(),
+            challenges,
+            created_at: bson::DateTime::now(),
+        }
+    }
+
+    // Today's challenges, generating them if the job has not run yet
+    pub async fn today(data_service: &dyn DataStore) -> Result<DailyChallengeSet, Box<dyn std::error::Error + Send + Sync>> {
+        let date = Self::date_of(Utc::now());
+        match data_service.get_daily_challenges(&date).await? {
+            Some(set) => Ok(set),
+            None => data_service.create_daily_challenges(Self::generate(&date)).await,
+        }
+    }
+
+    // Generate each day's challenges at startup and right after every UTC midnight
+    pub fn spawn_generator(data_service: Arc<dyn DataStore>) {
+        Scheduler::at("daily-challenges", |now| Self::next_reset(now) + Duration::seconds(1), move || {
+            let data_service = data_service.clone();
+            async move {
+                let set = Self::today(&*data_service).await?;
+                info!("🎯 Daily challenges ready for {} ({} challenges)", set.date, set.challenges.len());
+                Ok(())
+            }
+        });
+    }
+
+    // Count gameplay towards today's challenges of the matching kinds
+    pub async fn record(data_service: &dyn DataStore, user_id: &str, amounts: &[(ChallengeKind, i64)]) {
+        if amounts.iter().all(|(_, amount)| *amount <= 0) {
+            return;
+        }
+        let set = match Self::today(data_service).await {
+            Ok(set) => set,
+            Err(e) => {
+                warn!("⚠️ Challenge progress not recorded for user {}: {}", user_id, e);
+                return;
+            }
+        };
+        let increments: HashMap<String, i64> = set.challenges.iter()
+            .filter_map(|challenge| {
+                let amount: i64 = amounts.iter().filter(|(kind, _)| *kind == challenge.kind).map(|(_, amount)| amount).sum();
+                (amount > 0).then(|| (challenge.challenge_id.clone(), amount))
+            })
+            .collect();
+        if increments.is_empty() {
+            return;
+        }
+        if let Err(e) = data_service.add_challenge_progress(user_id, &set.date, &increments).await {
+            warn!("⚠️ Failed to record challenge progress for user {}: {}", user_id, e);
+        }
+    }
+
+    // Daily challenges on the main namespace:
+    //   challenge:today { mobile_no, session_token }               -> challenge:data
+    //   challenge:claim { mobile_no, session_token, challenge_id } -> challenge:claimed
+    pub fn register_challenge_events(socket: &SocketRef, data_service: Arc<dyn DataStore>) {
+        let ds = data_service.clone();
+        AuthGuard::require_session(socket, "challenge:today", data_service.clone(), move |socket, _data, auth| {
+            let ds = ds.clone();
+            async move {
+                info!("🎯 Received challenge:today from {}", socket.id);
+                let user = auth.user;
+                let set = match Self::today(&*ds).await {
+                    Ok(set) => set,
+                    Err(e) => {
+                        error!("❌ Failed to load daily challenges: {}", e);
+                        ErrorResponder::send(&socket, &*ds, ApiError::system("CHALLENGE_FETCH_FAILED", "challenge", "Failed to load daily challenges", &e)).await;
+                        return;
+                    }
+                };
+                let loaded = tokio::try_join!(
+                    ds.get_challenge_progress(&user.user_id, &set.date),
+                    ds.get_wallet_balance(&user.user_id)
+                );
+                let (progress, balance) = match loaded {
+                    Ok(loaded) => loaded,
+                    Err(e) => {
+                        error!("❌ Failed to load challenge progress for user {}: {}", user.user_id, e);
+                        ErrorResponder::send(&socket, &*ds, ApiError::system("CHALLENGE_FETCH_FAILED", "challenge", "Failed to load challenge progress", &e)).await;
+                        return;
+                    }
+                };
+
+                let challenges: Vec<Value> = set.challenges.iter().map(|challenge| {
+                    let done = progress.as_ref().and_then(|p| p.progress.get(&challenge.challenge_id)).copied().unwrap_or(0);
+                    let claimed = progress.as_ref().is_some_and(|p| p.claimed.contains(&challenge.challenge_id));
+                    json!({
+                        "challenge_id": challenge.challenge_id,
+                        "kind": challenge.kind,
+                        "target": challenge.target,
+                        "progress": done.min(challenge.target),
+                        "completed": done >= challenge.target,
+                        "claimed": claimed,
+                        "reward_coins": challenge.reward_coins
+                    })
+                }).collect();
+                let response = ApiResponse::success("challenge:data", json!({
+                    "date": set.date,
+                    "resets_at": Self::next_reset(Utc::now()).to_rfc3339(),
+                    "challenges": challenges,
+                    "balance": balance
+                })).for_socket(socket.id);
+                if let Err(e) = FaultInjector::emit(&socket, "challenge:data", response).await {
+                    warn!("⚠️ Failed to emit challenge:data to socket {}: {}", socket.id, e);
+                }
+            }
+        });
+
+        let ds = data_service.clone();
+        AuthGuard::require_signed_session(socket, "challenge:claim", data_service, move |socket, data, auth| {
+            let ds = ds.clone();
+            async move {
+                info!("🎯 Received challenge:claim from {}: {:?}", socket.id, data["challenge_id"]);
+                if let Err(error_details) = ValidationManager::validate_challenge_claim_data(&data) {
+                    info!("❌ Challenge claim validation failed for socket {}: {:?}", socket.id, error_details);
+                    ErrorResponder::send(&socket, &*ds, error_details).await;
+                    return;
+                }
+                let user = auth.user;
+                let challenge_id = data["challenge_id"].as_str().unwrap_or_default();
+
+                let set = match Self::today(&*ds).await {
+                    Ok(set) => set,
+                    Err(e) => {
+                        error!("❌ Failed to load daily challenges: {}", e);
+                        ErrorResponder::send(&socket, &*ds, ApiError::system("CHALLENGE_CLAIM_FAILED", "challenge_id", "Failed to load daily challenges", &e)).await;
+                        return;
+                    }
+                };
+                let Some(challenge) = set.challenges.iter().find(|c| c.challenge_id == challenge_id) else {
+                    let error = ApiError::new("CHALLENGE_NOT_FOUND", "CHALLENGE_ERROR", "challenge_id", "No such challenge today")
+                        .with_details(json!({"challenge_id": challenge_id, "date": set.date}));
+                    ErrorResponder::send(&socket, &*ds, error).await;
+                    return;
+                };
+
+                let done = match ds.get_challenge_progress(&user.user_id, &set.date).await {
+                    Ok(progress) => progress.and_then(|p| p.progress.get(challenge_id).copied()).unwrap_or(0),
+                    Err(e) => {
+                        error!("❌ Failed to load challenge progress for user {}: {}", user.user_id, e);
+                        ErrorResponder::send(&socket, &*ds, ApiError::system("CHALLENGE_CLAIM_FAILED", "challenge_id", "Failed to load challenge progress", &e)).await;
+                        return;
+                    }
+                };
+                if done < challenge.target {
+                    let error = ApiError::new("CHALLENGE_NOT_COMPLETED", "CHALLENGE_ERROR", "challenge_id", "Challenge is not completed yet")
+                        .with_details(json!({"challenge_id": challenge_id, "progress": done, "target": challenge.target}));
+                    ErrorResponder::send(&socket, &*ds, error).await;
+                    return;
+                }
+
+                // The wallet credits each reference once, so a repeated claim is refused there
+                let reference = format!("{}:{}:{}", REWARD_REASON, user.user_id, challenge_id);
+                let balance = match ds.credit_wallet(&user.user_id, challenge.reward_coins, REWARD_REASON, &reference).await {
+                    Ok(balance) => balance,
+                    Err(e) => {
+                        error!("❌ Failed to credit challenge reward to user {}: {}", user.user_id, e);
+                        ErrorResponder::send(&socket, &*ds, ApiError::system("CHALLENGE_CLAIM_FAILED", "challenge_id", "Failed to credit the reward", &e)).await;
+                        return;
+                    }
+                };
+                if let Err(e) = ds.mark_challenge_claimed(&user.user_id, &set.date, challenge_id).await {
+                    warn!("⚠️ Failed to mark challenge {} claimed for user {}: {}", challenge_id, user.user_id, e);
+                }
+                let Some(balance) = balance else {
+                    let error = ApiError::new("CHALLENGE_ALREADY_CLAIMED", "CHALLENGE_ERROR", "challenge_id", "Reward already claimed")
+                        .with_details(json!({"challenge_id": challenge_id}));
+                    ErrorResponder::send(&socket, &*ds, error).await;
+                    return;
+                };
+
+                let response = ApiResponse::success("challenge:claimed", json!({
+                    "challenge_id": challenge_id,
+                    "reward_coins": challenge.reward_coins,
+                    "balance": balance
+                })).for_socket(socket.id);
+                match FaultInjector::emit(&socket, "challenge:claimed", response).await {
+                    Ok(_) => info!("✅ Challenge {} claimed by user: {} (+{} coins)", challenge_id, user.user_id, challenge.reward_coins),
+                    Err(e) => warn!("⚠️ Failed to emit challenge:claimed to socket {}: {}", socket.id, e),
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,77 @@
+use rand::Rng;
+use serde::Serialize;
+use socketioxide::extract::SocketRef;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::config::CONFIG;
+use crate::managers::impersonation::ImpersonationManager;
+
+// Outcome of one roll of the dice for a targeted call
+enum Fault {
+    None,
+    Delay(Duration),
+    Fail,
+}
+
+// Dev-only fault injection (CHAOS_MODE with DEV_MODE). Delays or fails a
+// share of MongoDB calls and direct socket replies so retry and reconnect
+// handling can be exercised against a local server.
+pub struct FaultInjector;
+
+impl FaultInjector {
+    fn roll(target: &str) -> Fault {
+        if !CONFIG.chaos_targets_include(target) {
+            return Fault::None;
+        }
+        let mut rng = rand::thread_rng();
+        let dice = rng.gen_range(0.0..100.0);
+        if dice < CONFIG.chaos_failure_percent {
+            Fault::Fail
+        } else if dice < CONFIG.chaos_failure_percent + CONFIG.chaos_delay_percent {
+            Fault::Delay(Duration::from_millis(rng.gen_range(0..=CONFIG.chaos_max_delay_ms)))
+        } else {
+            Fault::None
+        }
+    }
+
+    // Called before a data store operation; an Err is returned to the caller
+    // as if MongoDB had failed.
+    pub async fn before_mongo(operation: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        match Self::roll("mongo") {
+            Fault::None => Ok(()),
+            Fault::Delay(delay) => {
+                warn!("🐒 Chaos: delaying {} by {}ms", operation, delay.as_millis());
+                tokio::time::sleep(delay).await;
+                Ok(())
+            }
+            Fault::Fail => {
+                warn!("🐒 Chaos: failing {}", operation);
+                Err(format!("chaos: injected failure in {}", operation).into())
+            }
+        }
+    }
+
+    // Emit to one socket, subject to fault injection. A failed emit is
+    // dropped and reported as an error, like a send on a dead transport.
+    pub async fn emit<T: Serialize>(socket: &SocketRef, event: &str, data: T) -> Result<(), String> {
+        match Self::roll("emit") {
+            Fault::None => {}
+            Fault::Delay(delay) => {
+                warn!("🐒 Chaos: delaying {} to socket {} by {}ms", event, socket.id, delay.as_millis());
+                tokio::time::sleep(delay).await;
+            }
+            Fault::Fail => {
+                warn!("🐒 Chaos: dropping {} to socket {}", event, socket.id);
+                return Err("chaos: emit dropped".to_string());
+            }
+        }
+        // Support may be watching this user's emits
+        let mirrored = ImpersonationManager::watched(socket).then(|| serde_json::to_value(&data).unwrap_or_default());
+        socket.emit(event.to_string(), data).map_err(|e| e.to_string())?;
+        if let Some(data) = mirrored {
+            ImpersonationManager::mirror(socket, event, data);
+        }
+        Ok(())
+    }
+}
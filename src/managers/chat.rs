@@ -0,0 +1,608 @@
+use socketioxide::extract::{Data, SocketRef};
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::api::response::{ApiError, ApiResponse};
+use crate::database::models::{ChatAttachment, ChatMessage, ChatMessageAudit, ChatReaction, ChatReadReceipt, SanctionKind};
+use crate::database::store::DataStore;
+use crate::config::CONFIG;
+use crate::managers::correlation::Correlation;
+use crate::managers::link_scan::LinkScanManager;
+use crate::managers::moderation::{ModerationManager, AUTO_MODERATOR};
+use crate::managers::party::player_room;
+use crate::managers::room::RoomManager;
+use crate::managers::scheduler::Scheduler;
+use crate::managers::snowflake::Snowflake;
+use crate::managers::tenant::TenantManager;
+use crate::managers::validation::ValidationManager;
+
+// Earlier flood mutes within this window make the next one longer
+const FLOOD_STRIKE_WINDOW_HOURS: i64 = 24;
+// chat:history page sizes
+const DEFAULT_HISTORY_LIMIT: i64 = 50;
+pub const MAX_HISTORY_LIMIT: i64 = 100;
+// Images a chat message can carry, and the longest link to one
+pub const MAX_ATTACHMENTS: usize = 4;
+pub const MAX_ATTACHMENT_URL_LENGTH: usize = 2048;
+// Reactions chat:react accepts, and the emoji clients show for them
+pub const REACTIONS: &[(&str, &str)] = &[
+    ("like", "👍"),
+    ("love", "❤️"),
+    ("laugh", "😂"),
+    ("wow", "😮"),
+    ("sad", "😢"),
+    ("fire", "🔥"),
+];
+
+// When each player sent their recent chat messages, for flood control
+static RECENT: Lazy<Mutex<HashMap<String, VecDeque<DateTime<Utc>>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+// (room_id, player_id)
+type TypingKey = (String, String);
+// When each player last had a chat:typing relayed to a room, while typing
+static TYPING: Lazy<Mutex<HashMap<TypingKey, DateTime<Utc>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// Why a chat message was not relayed
+pub enum ChatRefusal {
+    Muted { until: DateTime<Utc>, automatic: bool },
+    Unavailable,
+}
+
+// Room chat on the gameplay namespace, and the gate every chat message (room
+// or party) goes through. A muted player gets `chat:muted` with the time they
+// can speak again. Sending more than CHAT_RATE_LIMIT messages within
+// CHAT_RATE_WINDOW_SECS earns an automatic mute, doubling with each repeat.
+// Room messages are kept for CHAT_RETENTION_DAYS; senders can edit or delete
+// them for CHAT_EDIT_WINDOW_SECS, and each change is audit-logged. Typing
+// indicators are relayed at most every CHAT_TYPING_THROTTLE_MS per player and
+// never stored; read receipts keep only the newest message each player read.
+// Links and image attachments go through LinkScanManager before a message
+// or edit is stored.
+pub struct ChatManager;
+
+impl ChatManager {
+    pub fn spawn_retention(data_service: Arc<dyn DataStore>) {
+        Scheduler::every("chat-retention", std::time::Duration::from_secs(3600), move || {
+            let data_service = data_service.clone();
+            async move {
+                let before = Utc::now() - Duration::days(CONFIG.chat_retention_days);
+                let purged = data_service.purge_chat_messages(before).await?;
+                if purged > 0 {
+                    info!("🧹 Purged {} chat messages older than {} days", purged, CONFIG.chat_retention_days);
+                }
+                Ok(())
+            }
+        });
+    }
+
+    // Whether the player may send a message right now; counts it if so
+    pub async fn admit(data_service: &dyn DataStore, player_id: &str) -> Result<(), ChatRefusal> {
+        let sanctions = data_service.list_sanctions(player_id).await.map_err(|e| {
+            warn!("⚠️ Failed to check mutes of player {}: {}", player_id, e);
+            ChatRefusal::Unavailable
+        })?;
+        let now = Utc::now();
+        let bson_now = bson::DateTime::from_millis(now.timestamp_millis());
+        let active = sanctions.iter()
+            .filter(|s| s.kind == SanctionKind::Mute && s.is_active(bson_now))
+            .filter_map(|s| Some((DateTime::from_timestamp_millis(s.expires_at?.timestamp_millis())?, s.operator_id == AUTO_MODERATOR)))
+            .max();
+        if let Some((until, automatic)) = active {
+            return Err(ChatRefusal::Muted { until, automatic });
+        }
+
+        if !Self::flooding(player_id, now).await {
+            return Ok(());
+        }
+        let strike_cutoff = bson::DateTime::from_millis((now - Duration::hours(FLOOD_STRIKE_WINDOW_HOURS)).timestamp_millis());
+        let strikes = sanctions.iter()
+            .filter(|s| s.kind == SanctionKind::Mute && s.operator_id == AUTO_MODERATOR && s.created_at >= strike_cutoff)
+            .count() as u32;
+        let secs = CONFIG.chat_flood_mute_secs
+            .saturating_mul(1_i64 << strikes.min(20))
+            .min(CONFIG.chat_flood_max_mute_secs);
+        // The mute is refused for this message either way; only later ones rely on it being stored
+        if let Err(e) = ModerationManager::auto_mute(data_service, player_id, Duration::seconds(secs), "Chat flooding").await {
+            warn!("⚠️ Failed to store flood mute of player {}: {}", player_id, e);
+        }
+        Err(ChatRefusal::Muted { until: now + Duration::seconds(secs), automatic: true })
+    }
+
+    // Record a message and report whether it goes over the rate limit. The
+    // window starts over after a flood so the mute is the only penalty.
+    async fn flooding(player_id: &str, now: DateTime<Utc>) -> bool {
+        let cutoff = now - Duration::seconds(TenantManager::limits().chat_rate_window_secs);
+        let mut recent = RECENT.lock().await;
+        recent.retain(|_, sent| sent.back().is_some_and(|at| *at > cutoff));
+        let sent = recent.entry(player_id.to_string()).or_default();
+        while sent.front().is_some_and(|at| *at <= cutoff) {
+            sent.pop_front();
+        }
+        sent.push_back(now);
+        if sent.len() > TenantManager::limits().chat_rate_limit {
+            recent.remove(player_id);
+            return true;
+        }
+        false
+    }
+
+    // Tell the sender why their message was not relayed. `context` names the
+    // room or party the message was meant for.
+    pub fn refuse(s: &SocketRef, player_id: &str, context: Value, refusal: ChatRefusal) {
+        match refusal {
+            ChatRefusal::Muted { until, automatic } => {
+                let mut muted = json!({
+                    "player_id": player_id,
+                    "reason": if automatic { "flood" } else { "moderator" },
+                    "until": until.to_rfc3339(),
+                    "until_ms": until.timestamp_millis(),
+                    "retry_after_secs": (until - Utc::now()).num_seconds().max(1)
+                });
+                if let (Some(muted), Some(context)) = (muted.as_object_mut(), context.as_object()) {
+                    muted.extend(context.clone());
+                }
+                info!("🔇 Chat from player {} refused until {}", player_id, until);
+                let _ = s.emit("chat:muted", ApiResponse::success("chat:muted", muted).for_socket(s.id));
+            }
+            ChatRefusal::Unavailable => {
+                let error = ApiError::new("CHAT_UNAVAILABLE", "CHAT_ERROR", "player_id", "Unable to send the message right now")
+                    .with_details(context);
+                Self::emit_chat_error(s, error);
+            }
+        }
+    }
+
+    fn emit_chat_error(s: &SocketRef, error: ApiError) {
+        let _ = s.emit("chat:error", error.on_event("chat:error").for_socket(s.id));
+    }
+
+    fn rfc3339(date: bson::DateTime) -> String {
+        date.try_to_rfc3339_string().unwrap_or_default()
+    }
+
+    // What other players see of a message; deleted ones keep their place without the text
+    fn message_view(message: &ChatMessage) -> Value {
+        json!({
+            "message_id": message.message_id,
+            "room_id": message.room_id,
+            "player_id": message.user_id,
+            "message": message.deleted_at.is_none().then_some(&message.message),
+            "sent_at": Self::rfc3339(message.created_at),
+            "edited_at": message.edited_at.map(Self::rfc3339),
+            "deleted": message.deleted_at.is_some(),
+            "reactions": message.reactions.iter().filter(|(_, count)| **count > 0).collect::<std::collections::BTreeMap<_, _>>(),
+            "attachments": message.deleted_at.is_none().then_some(&message.attachments)
+        })
+    }
+
+    // The sender holds `player_id`'s seat in the room on this socket, so a
+    // payload cannot chat (or spend flood budget) as another player
+    async fn in_room(s: &SocketRef, room_id: &str, player_id: &str) -> bool {
+        RoomManager::is_seated(room_id, player_id, &s.id.to_string()).await
+    }
+
+    fn not_in_room(s: &SocketRef, room_id: &str, player_id: &str) {
+        let error = ApiError::new("CHAT_NOT_IN_ROOM", "CHAT_ERROR", "room_id", "Join the room before chatting")
+            .with_details(json!({"room_id": room_id, "player_id": player_id}));
+        Self::emit_chat_error(s, error);
+    }
+
+    // Send `event` to the room, except players with a block either way with
+    // `player_id` (player ids are user ids)
+    async fn relay(s: &SocketRef, data_service: &dyn DataStore, room_id: &str, player_id: &str, event: &'static str, payload: Value) {
+        Self::relay_to(s, data_service, room_id, player_id, event, payload, true).await
+    }
+
+    // As relay, leaving out the sender's own socket unless `echo`
+    async fn relay_to(s: &SocketRef, data_service: &dyn DataStore, room_id: &str, player_id: &str, event: &'static str, payload: Value, echo: bool) {
+        let blocked = match ModerationManager::blocked_user_ids(data_service, player_id).await {
+            Ok(blocked) => blocked,
+            Err(e) => {
+                warn!("⚠️ Failed to load blocks of player {}: {}", player_id, e);
+                Self::refuse(s, player_id, json!({"room_id": room_id}), ChatRefusal::Unavailable);
+                return;
+            }
+        };
+        let hidden: Vec<String> = blocked.iter().map(|user_id| player_room(user_id)).collect();
+        let operators = if echo { s.within(room_id.to_string()) } else { s.to(room_id.to_string()) };
+        if let Err(e) = operators.except(hidden).emit(event, ApiResponse::success(event, payload)) {
+            warn!("⚠️ Failed to relay {} to room {}: {}", event, room_id, e);
+        }
+    }
+
+    // The sender's own message in this room, still within the edit window
+    async fn own_recent_message(s: &SocketRef, data_service: &dyn DataStore, room_id: &str, player_id: &str, message_id: &str) -> Option<ChatMessage> {
+        let message = match data_service.get_chat_message(message_id).await {
+            Ok(message) => message,
+            Err(e) => {
+                warn!("⚠️ Failed to load chat message {}: {}", message_id, e);
+                Self::refuse(s, player_id, json!({"room_id": room_id}), ChatRefusal::Unavailable);
+                return None;
+            }
+        };
+        let details = json!({"room_id": room_id, "message_id": message_id});
+        let Some(message) = message.filter(|m| m.room_id == room_id && m.user_id == player_id && m.deleted_at.is_none()) else {
+            Self::emit_chat_error(s, ApiError::new("CHAT_MESSAGE_NOT_FOUND", "CHAT_ERROR", "message_id", "No such message of yours").with_details(details));
+            return None;
+        };
+        let cutoff = Utc::now() - Duration::seconds(CONFIG.chat_edit_window_secs);
+        if message.created_at.timestamp_millis() < cutoff.timestamp_millis() {
+            let mut details = details;
+            details["edit_window_secs"] = json!(CONFIG.chat_edit_window_secs);
+            Self::emit_chat_error(s, ApiError::new("CHAT_EDIT_WINDOW_PASSED", "CHAT_ERROR", "message_id", "This message is too old to change").with_details(details));
+            return None;
+        }
+        Some(message)
+    }
+
+    fn audit(message: &ChatMessage, action: &str, new_message: Option<&str>) -> ChatMessageAudit {
+        ChatMessageAudit {
+            id: None,
+            message_id: message.message_id.clone(),
+            room_id: message.room_id.clone(),
+            user_id: message.user_id.clone(),
+            action: action.to_string(),
+            previous_message: message.message.clone(),
+            new_message: new_message.map(str::to_string),
+            created_at: bson::DateTime::now(),
+        }
+    }
+
+    // Whether a typing indicator goes out now. Starting to type is relayed
+    // once per throttle window; stopping is relayed if a start was in the
+    // last minute, and resets the window.
+    async fn typing_due(room_id: &str, player_id: &str, typing: bool, now: DateTime<Utc>) -> bool {
+        let key = (room_id.to_string(), player_id.to_string());
+        let mut last = TYPING.lock().await;
+        if !typing {
+            return last.remove(&key).is_some();
+        }
+        let cutoff = now - Duration::milliseconds(CONFIG.chat_typing_throttle_ms);
+        last.retain(|_, at| *at > cutoff - Duration::minutes(1));
+        if last.get(&key).is_some_and(|at| *at > cutoff) {
+            return false;
+        }
+        last.insert(key, now);
+        true
+    }
+
+    fn receipt_view(receipt: &ChatReadReceipt) -> Value {
+        json!({
+            "player_id": receipt.user_id,
+            "message_id": receipt.message_id,
+            "read_at": Self::rfc3339(receipt.read_at)
+        })
+    }
+
+    // Register room chat on a gameplay namespace socket:
+    //   chat:send    { room_id, player_id, message, attachments? } -> chat:message to the room, or chat:muted / chat:error to the sender
+    //   chat:edit    { room_id, player_id, message_id, message } -> chat:edited to the room
+    //   chat:delete  { room_id, player_id, message_id }          -> chat:deleted to the room
+    //   chat:typing  { room_id, player_id, typing }              -> chat:typing to the rest of the room
+    //   chat:read    { room_id, player_id, message_id }          -> chat:read to the room
+    //   chat:react   { room_id, player_id, message_id, reaction, remove? } -> chat:reaction to the room
+    //   chat:history { room_id, player_id, before?, limit? }     -> chat:history to the sender, with read receipts
+    pub fn register_chat_events(socket: &SocketRef, data_service: Arc<dyn DataStore>) {
+        let ds = data_service.clone();
+        socket.on("chat:send", move |s: SocketRef, Data::<Value>(data)| {
+            let ds = ds.clone();
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope("chat:send", s.id, request_id, async move {
+                if let Err(error_details) = ValidationManager::validate_chat_data(&data) {
+                    Self::emit_chat_error(&s, ApiError::from(error_details));
+                    return;
+                }
+                let room_id = data["room_id"].as_str().unwrap_or_default();
+                let player_id = data["player_id"].as_str().unwrap_or_default();
+                if !Self::in_room(&s, room_id, player_id).await {
+                    Self::not_in_room(&s, room_id, player_id);
+                    return;
+                }
+                if let Err(refusal) = Self::admit(&*ds, player_id).await {
+                    Self::refuse(&s, player_id, json!({"room_id": room_id}), refusal);
+                    return;
+                }
+                let text = data["message"].as_str().unwrap_or_default().trim();
+                let attachments: Vec<ChatAttachment> = serde_json::from_value(data["attachments"].clone()).unwrap_or_default();
+                let place = format!("room {}", room_id);
+                if let Err(error) = LinkScanManager::scan(&*ds, player_id, Some(room_id), &place, text, &attachments).await {
+                    Self::emit_chat_error(&s, error);
+                    return;
+                }
+
+                let message = ChatMessage {
+                    id: None,
+                    message_id: Snowflake::generate(),
+                    room_id: room_id.to_string(),
+                    user_id: player_id.to_string(),
+                    message: text.to_string(),
+                    created_at: bson::DateTime::now(),
+                    edited_at: None,
+                    deleted_at: None,
+                    reactions: Default::default(),
+                    attachments,
+                };
+                // Unstored messages could not be edited, deleted or reviewed later
+                if let Err(e) = ds.save_chat_message(message.clone()).await {
+                    warn!("⚠️ Failed to store chat message from player {}: {}", player_id, e);
+                    Self::refuse(&s, player_id, json!({"room_id": room_id}), ChatRefusal::Unavailable);
+                    return;
+                }
+                Self::relay(&s, &*ds, room_id, player_id, "chat:message", Self::message_view(&message)).await;
+            })
+        });
+
+        let ds = data_service.clone();
+        socket.on("chat:edit", move |s: SocketRef, Data::<Value>(data)| {
+            let ds = ds.clone();
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope("chat:edit", s.id, request_id, async move {
+                if let Err(error_details) = ValidationManager::validate_chat_edit_data(&data) {
+                    Self::emit_chat_error(&s, ApiError::from(error_details));
+                    return;
+                }
+                let room_id = data["room_id"].as_str().unwrap_or_default();
+                let player_id = data["player_id"].as_str().unwrap_or_default();
+                let message_id = data["message_id"].as_str().unwrap_or_default();
+                let Some(mut message) = Self::own_recent_message(&s, &*ds, room_id, player_id, message_id).await else { return };
+                // An edit is new text, so mutes and the flood limit apply
+                if let Err(refusal) = Self::admit(&*ds, player_id).await {
+                    Self::refuse(&s, player_id, json!({"room_id": room_id}), refusal);
+                    return;
+                }
+
+                let text = data["message"].as_str().unwrap_or_default().trim();
+                let place = format!("room {}", room_id);
+                if let Err(error) = LinkScanManager::scan(&*ds, player_id, Some(room_id), &place, text, &[]).await {
+                    Self::emit_chat_error(&s, error);
+                    return;
+                }
+                match ds.edit_chat_message(message_id, text).await {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        let error = ApiError::new("CHAT_MESSAGE_NOT_FOUND", "CHAT_ERROR", "message_id", "No such message of yours")
+                            .with_details(json!({"room_id": room_id, "message_id": message_id}));
+                        Self::emit_chat_error(&s, error);
+                        return;
+                    }
+                    Err(e) => {
+                        warn!("⚠️ Failed to edit chat message {}: {}", message_id, e);
+                        Self::refuse(&s, player_id, json!({"room_id": room_id}), ChatRefusal::Unavailable);
+                        return;
+                    }
+                }
+                if let Err(e) = ds.record_chat_audit(Self::audit(&message, "edit", Some(text))).await {
+                    warn!("⚠️ Failed to audit edit of chat message {}: {}", message_id, e);
+                }
+
+                message.message = text.to_string();
+                message.edited_at = Some(bson::DateTime::now());
+                Self::relay(&s, &*ds, room_id, player_id, "chat:edited", Self::message_view(&message)).await;
+            })
+        });
+
+        let ds = data_service.clone();
+        socket.on("chat:delete", move |s: SocketRef, Data::<Value>(data)| {
+            let ds = ds.clone();
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope("chat:delete", s.id, request_id, async move {
+                if let Err(error_details) = ValidationManager::validate_chat_delete_data(&data) {
+                    Self::emit_chat_error(&s, ApiError::from(error_details));
+                    return;
+                }
+                let room_id = data["room_id"].as_str().unwrap_or_default();
+                let player_id = data["player_id"].as_str().unwrap_or_default();
+                let message_id = data["message_id"].as_str().unwrap_or_default();
+                let Some(message) = Self::own_recent_message(&s, &*ds, room_id, player_id, message_id).await else { return };
+
+                match ds.delete_chat_message(message_id).await {
+                    Ok(true) => {}
+                    // Deleted twice at once; the first one is relayed
+                    Ok(false) => return,
+                    Err(e) => {
+                        warn!("⚠️ Failed to delete chat message {}: {}", message_id, e);
+                        Self::refuse(&s, player_id, json!({"room_id": room_id}), ChatRefusal::Unavailable);
+                        return;
+                    }
+                }
+                if let Err(e) = ds.record_chat_audit(Self::audit(&message, "delete", None)).await {
+                    warn!("⚠️ Failed to audit delete of chat message {}: {}", message_id, e);
+                }
+                info!("🗑️ Player {} deleted chat message {}", player_id, message_id);
+                Self::relay(&s, &*ds, room_id, player_id, "chat:deleted", json!({
+                    "room_id": room_id,
+                    "message_id": message_id,
+                    "player_id": player_id
+                })).await;
+            })
+        });
+
+        let ds = data_service.clone();
+        socket.on("chat:typing", move |s: SocketRef, Data::<Value>(data)| {
+            let ds = ds.clone();
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope("chat:typing", s.id, request_id, async move {
+                if let Err(error_details) = ValidationManager::validate_chat_typing_data(&data) {
+                    Self::emit_chat_error(&s, ApiError::from(error_details));
+                    return;
+                }
+                let room_id = data["room_id"].as_str().unwrap_or_default();
+                let player_id = data["player_id"].as_str().unwrap_or_default();
+                let typing = data["typing"].as_bool().unwrap_or_default();
+                if !Self::in_room(&s, room_id, player_id).await {
+                    Self::not_in_room(&s, room_id, player_id);
+                    return;
+                }
+                if !Self::typing_due(room_id, player_id, typing, Utc::now()).await {
+                    return;
+                }
+                Self::relay_to(&s, &*ds, room_id, player_id, "chat:typing", json!({
+                    "room_id": room_id,
+                    "player_id": player_id,
+                    "typing": typing
+                }), false).await;
+            })
+        });
+
+        let ds = data_service.clone();
+        socket.on("chat:read", move |s: SocketRef, Data::<Value>(data)| {
+            let ds = ds.clone();
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope("chat:read", s.id, request_id, async move {
+                if let Err(error_details) = ValidationManager::validate_chat_read_data(&data) {
+                    Self::emit_chat_error(&s, ApiError::from(error_details));
+                    return;
+                }
+                let room_id = data["room_id"].as_str().unwrap_or_default();
+                let player_id = data["player_id"].as_str().unwrap_or_default();
+                let message_id = data["message_id"].as_str().unwrap_or_default();
+                if !Self::in_room(&s, room_id, player_id).await {
+                    Self::not_in_room(&s, room_id, player_id);
+                    return;
+                }
+                match ds.get_chat_message(message_id).await {
+                    Ok(Some(message)) if message.room_id == room_id => {}
+                    Ok(_) => {
+                        let error = ApiError::new("CHAT_MESSAGE_NOT_FOUND", "CHAT_ERROR", "message_id", "No such message in this room")
+                            .with_details(json!({"room_id": room_id, "message_id": message_id}));
+                        Self::emit_chat_error(&s, error);
+                        return;
+                    }
+                    Err(e) => {
+                        warn!("⚠️ Failed to load chat message {}: {}", message_id, e);
+                        Self::refuse(&s, player_id, json!({"room_id": room_id}), ChatRefusal::Unavailable);
+                        return;
+                    }
+                }
+
+                let receipt = match ds.mark_chat_read(room_id, player_id, message_id).await {
+                    Ok(receipt) => receipt,
+                    Err(e) => {
+                        warn!("⚠️ Failed to store read receipt of player {} in room {}: {}", player_id, room_id, e);
+                        Self::refuse(&s, player_id, json!({"room_id": room_id}), ChatRefusal::Unavailable);
+                        return;
+                    }
+                };
+                let mut read = Self::receipt_view(&receipt);
+                read["room_id"] = json!(room_id);
+                Self::relay(&s, &*ds, room_id, player_id, "chat:read", read).await;
+            })
+        });
+
+        let ds = data_service.clone();
+        socket.on("chat:react", move |s: SocketRef, Data::<Value>(data)| {
+            let ds = ds.clone();
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope("chat:react", s.id, request_id, async move {
+                if let Err(error_details) = ValidationManager::validate_chat_react_data(&data) {
+                    Self::emit_chat_error(&s, ApiError::from(error_details));
+                    return;
+                }
+                let room_id = data["room_id"].as_str().unwrap_or_default();
+                let player_id = data["player_id"].as_str().unwrap_or_default();
+                let message_id = data["message_id"].as_str().unwrap_or_default();
+                let reaction = data["reaction"].as_str().unwrap_or_default();
+                let add = !data["remove"].as_bool().unwrap_or(false);
+                if !Self::in_room(&s, room_id, player_id).await {
+                    Self::not_in_room(&s, room_id, player_id);
+                    return;
+                }
+                match ds.get_chat_message(message_id).await {
+                    Ok(Some(message)) if message.room_id == room_id && message.deleted_at.is_none() => {}
+                    Ok(_) => {
+                        let error = ApiError::new("CHAT_MESSAGE_NOT_FOUND", "CHAT_ERROR", "message_id", "No such message in this room")
+                            .with_details(json!({"room_id": room_id, "message_id": message_id}));
+                        Self::emit_chat_error(&s, error);
+                        return;
+                    }
+                    Err(e) => {
+                        warn!("⚠️ Failed to load chat message {}: {}", message_id, e);
+                        Self::refuse(&s, player_id, json!({"room_id": room_id}), ChatRefusal::Unavailable);
+                        return;
+                    }
+                }
+
+                let reacted = ChatReaction {
+                    id: None,
+                    message_id: message_id.to_string(),
+                    room_id: room_id.to_string(),
+                    user_id: player_id.to_string(),
+                    reaction: reaction.to_string(),
+                    created_at: bson::DateTime::now(),
+                };
+                let count = match ds.react_to_chat_message(reacted, add).await {
+                    Ok(Some(count)) => count,
+                    // Repeated, or the message was deleted meanwhile: nothing to tell the room
+                    Ok(None) => return,
+                    Err(e) => {
+                        warn!("⚠️ Failed to store reaction of player {} to message {}: {}", player_id, message_id, e);
+                        Self::refuse(&s, player_id, json!({"room_id": room_id}), ChatRefusal::Unavailable);
+                        return;
+                    }
+                };
+                Self::relay(&s, &*ds, room_id, player_id, "chat:reaction", json!({
+                    "room_id": room_id,
+                    "message_id": message_id,
+                    "player_id": player_id,
+                    "reaction": reaction,
+                    "delta": if add { 1 } else { -1 },
+                    "count": count.max(0)
+                })).await;
+            })
+        });
+
+        socket.on("chat:history", move |s: SocketRef, Data::<Value>(data)| {
+            let ds = data_service.clone();
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope("chat:history", s.id, request_id, async move {
+                if let Err(error_details) = ValidationManager::validate_chat_history_data(&data) {
+                    Self::emit_chat_error(&s, ApiError::from(error_details));
+                    return;
+                }
+                let room_id = data["room_id"].as_str().unwrap_or_default();
+                let player_id = data["player_id"].as_str().unwrap_or_default();
+                let before = data["before"].as_str();
+                let limit = data["limit"].as_i64().unwrap_or(DEFAULT_HISTORY_LIMIT);
+                if !Self::in_room(&s, room_id, player_id).await {
+                    Self::not_in_room(&s, room_id, player_id);
+                    return;
+                }
+
+                let loaded = match ModerationManager::blocked_user_ids(&*ds, player_id).await {
+                    Ok(blocked) => {
+                        let blocked: Vec<String> = blocked.into_iter().collect();
+                        match ds.chat_history(room_id, before, &blocked, limit).await {
+                            Ok(messages) => ds.chat_read_receipts(room_id, &blocked).await.map(|receipts| (messages, receipts)),
+                            Err(e) => Err(e),
+                        }
+                    }
+                    Err(e) => Err(e),
+                };
+                let (messages, receipts) = match loaded {
+                    Ok(loaded) => loaded,
+                    Err(e) => {
+                        warn!("⚠️ Failed to load chat history of room {}: {}", room_id, e);
+                        Self::refuse(&s, player_id, json!({"room_id": room_id}), ChatRefusal::Unavailable);
+                        return;
+                    }
+                };
+
+                // A full page may have more before it
+                let next_before = messages.last().filter(|_| messages.len() as i64 == limit).map(|m| m.message_id.clone());
+                let messages: Vec<Value> = messages.iter().map(Self::message_view).collect();
+                let read_receipts: Vec<Value> = receipts.iter().map(Self::receipt_view).collect();
+                let history = ApiResponse::success("chat:history", json!({
+                    "room_id": room_id,
+                    "messages": messages,
+                    "read_receipts": read_receipts,
+                    "next_before": next_before
+                })).for_socket(s.id);
+                let _ = s.emit("chat:history", history);
+            })
+        });
+    }
+}
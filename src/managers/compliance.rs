@@ -0,0 +1,156 @@
+use once_cell::sync::Lazy;
+use serde_json::json;
+use socketioxide::extract::SocketRef;
+use std::collections::HashSet;
+use tracing::{error, info, warn};
+
+use crate::api::response::ApiError;
+use crate::config::CONFIG;
+use crate::database::models::ComplianceAudit;
+use crate::database::store::DataStore;
+use crate::managers::login_policy::LoginPolicyManager;
+
+// Indian states and union territories by ISO 3166-2:IN code, so a Geo-IP
+// state code and a state name from set:profile can be compared
+const INDIAN_STATES: &[(&str, &str)] = &[
+    ("AN", "Andaman and Nicobar Islands"), ("AP", "Andhra Pradesh"), ("AR", "Arunachal Pradesh"),
+    ("AS", "Assam"), ("BR", "Bihar"), ("CH", "Chandigarh"), ("CT", "Chhattisgarh"),
+    ("DH", "Dadra and Nagar Haveli and Daman and Diu"), ("DL", "Delhi"), ("GA", "Goa"),
+    ("GJ", "Gujarat"), ("HP", "Himachal Pradesh"), ("HR", "Haryana"), ("JH", "Jharkhand"),
+    ("JK", "Jammu and Kashmir"), ("KA", "Karnataka"), ("KL", "Kerala"), ("LA", "Ladakh"),
+    ("LD", "Lakshadweep"), ("MH", "Maharashtra"), ("ML", "Meghalaya"), ("MN", "Manipur"),
+    ("MP", "Madhya Pradesh"), ("MZ", "Mizoram"), ("NL", "Nagaland"), ("OR", "Odisha"),
+    ("PB", "Punjab"), ("PY", "Puducherry"), ("RJ", "Rajasthan"), ("SK", "Sikkim"),
+    ("TG", "Telangana"), ("TN", "Tamil Nadu"), ("TR", "Tripura"), ("UP", "Uttar Pradesh"),
+    ("UT", "Uttarakhand"), ("WB", "West Bengal"),
+];
+// Older codes some Geo-IP databases still report
+const STATE_CODE_ALIASES: &[(&str, &str)] = &[("OD", "OR"), ("TS", "TG"), ("UK", "UT"), ("CG", "CT")];
+
+// Restricted states by code; names in COMPLIANCE_RESTRICTED_STATES are mapped to theirs
+static RESTRICTED: Lazy<HashSet<&'static str>> = Lazy::new(ComplianceManager::load);
+
+// Real-money gameplay compliance. Games are refused, not logins: a player
+// whose set:profile state is in COMPLIANCE_RESTRICTED_STATES, or whose IP the
+// proxy places in one (GEOIP_REGION_HEADER, believed for Indian IPs only),
+// cannot queue, queue a party or take a seat. The IP check catches players
+// whose profile names another state. COMPLIANCE_ALLOWED_USERS are let in
+// anyway and COMPLIANCE_BLOCKED_USERS never are. Every refusal, and every
+// restricted user let in by the allow list, is kept in `compliance_audit`.
+pub struct ComplianceManager;
+
+impl ComplianceManager {
+    fn load() -> HashSet<&'static str> {
+        let restricted: HashSet<&'static str> = CONFIG.compliance_restricted_states.iter()
+            .filter_map(|state| {
+                let code = Self::state_code(state);
+                if code.is_none() {
+                    error!("❌ COMPLIANCE_RESTRICTED_STATES: {:?} is not an Indian state or its code - ignored", state);
+                }
+                code
+            })
+            .collect();
+        if !restricted.is_empty() {
+            let mut codes: Vec<&str> = restricted.iter().copied().collect();
+            codes.sort_unstable();
+            info!("⚖️ Real-money games refused in {} (Geo-IP state check: {})", codes.join(", "), CONFIG.geoip_region_header.is_some());
+        }
+        restricted
+    }
+
+    // Codes of the states games are refused in, logged on first use
+    pub fn restricted_states() -> &'static HashSet<&'static str> {
+        &RESTRICTED
+    }
+
+    // Lower-case letters only, "&" read as "and"
+    fn normalize(name: &str) -> String {
+        name.replace('&', "and").chars().filter(|c| c.is_ascii_alphanumeric()).collect::<String>().to_ascii_lowercase()
+    }
+
+    // ISO 3166-2:IN code of a state name or code; "IN-TG", "TG", "Telangana" and "telangana" all give "TG"
+    pub fn state_code(state: &str) -> Option<&'static str> {
+        let state = state.trim();
+        let code = state.to_ascii_uppercase();
+        let code = code.strip_prefix("IN-").unwrap_or(&code);
+        let code = STATE_CODE_ALIASES.iter().find(|(alias, _)| *alias == code).map_or(code, |(_, current)| current);
+        if let Some((known, _)) = INDIAN_STATES.iter().find(|(known, _)| *known == code) {
+            return Some(known);
+        }
+        let name = Self::normalize(state);
+        INDIAN_STATES.iter().find(|(_, known)| Self::normalize(known) == name).map(|(code, _)| *code)
+    }
+
+    fn enabled() -> bool {
+        !RESTRICTED.is_empty() || !CONFIG.compliance_blocked_users.is_empty()
+    }
+
+    // State code of the socket's IP, when the proxy reports one for an Indian IP
+    fn ip_state(socket: &SocketRef, ip_country: Option<&str>) -> Option<&'static str> {
+        if ip_country.is_some_and(|country| country != "IN") {
+            return None;
+        }
+        let value = LoginPolicyManager::geoip_header(socket, CONFIG.geoip_region_header.as_deref()?)?;
+        Self::state_code(&value)
+    }
+
+    // Ok when `player_id` may enter a game from this socket, REGION_RESTRICTED
+    // otherwise. `event` names the entry for the audit record.
+    pub async fn check_gameplay(data_service: &dyn DataStore, socket: &SocketRef, player_id: &str, event: &str) -> Result<(), ApiError> {
+        if !Self::enabled() {
+            return Ok(());
+        }
+        // Without a known user the IP is all there is to go on
+        let user = data_service.get_user_by_id(player_id).await
+            .map_err(|e| ApiError::system("COMPLIANCE_CHECK_FAILED", "player_id", "Failed to check where games are allowed", &e))?;
+        let profile_state = user.as_ref().and_then(|user| user.state.clone());
+        let ip_country = LoginPolicyManager::ip_country(socket);
+        let ip_state = Self::ip_state(socket, ip_country.as_deref());
+
+        let profile_code = profile_state.as_deref().and_then(Self::state_code);
+        if let (Some(profile), Some(ip)) = (profile_code, ip_state) {
+            if profile != ip {
+                warn!("⚖️ Player {} has {} in their profile but connects from {} (socket: {})", player_id, profile, ip, socket.id);
+            }
+        }
+        let reason = if CONFIG.compliance_blocked_users.iter().any(|id| id == player_id) {
+            Some(("blocked_user", None))
+        } else if let Some(code) = profile_code.filter(|code| RESTRICTED.contains(code)) {
+            Some(("profile_state", Some(code)))
+        } else {
+            ip_state.filter(|code| RESTRICTED.contains(code)).map(|code| ("geoip_state", Some(code)))
+        };
+        let Some((reason, state)) = reason else {
+            return Ok(());
+        };
+
+        let overridden = reason != "blocked_user" && CONFIG.compliance_allowed_users.iter().any(|id| id == player_id);
+        let outcome = if overridden { "overridden" } else { "blocked" };
+        let audit = ComplianceAudit {
+            id: None,
+            user_id: player_id.to_string(),
+            socket_id: socket.id.to_string(),
+            event: event.to_string(),
+            outcome: outcome.to_string(),
+            reason: reason.to_string(),
+            profile_state,
+            ip_country,
+            ip_state: ip_state.map(str::to_string),
+            created_at: bson::DateTime::now(),
+        };
+        if let Err(e) = data_service.record_compliance_audit(audit).await {
+            error!("❌ Failed to store compliance audit for player {}: {}", player_id, e);
+        }
+        if overridden {
+            info!("⚖️ Let player {} into {} from a restricted state ({}) by COMPLIANCE_ALLOWED_USERS", player_id, event, reason);
+            return Ok(());
+        }
+        info!("⚖️ Refused {} for player {} ({}, state: {:?}, socket: {})", event, player_id, reason, state, socket.id);
+        Err(ApiError::new("REGION_RESTRICTED", "AUTHORIZATION_ERROR", "player_id", "Real-money games are not available in your state.")
+            .with_details(json!({
+                "player_id": player_id,
+                "reason": reason,
+                "state": state,
+            })))
+    }
+}
@@ -1,14 +1,35 @@
-use socketioxide::extract::SocketRef;
-use serde_json::json;
+use socketioxide::{SocketIo, extract::SocketRef, socket::Sid};
+use once_cell::sync::OnceCell;
+use serde_json::{json, Value};
 use chrono::Utc;
 use rand::Rng;
 use tracing::{info, warn, error};
 use std::sync::Arc;
-use crate::database::service::DataService;
+use crate::api::middleware::ClientIp;
+use crate::api::response::ApiResponse;
+use crate::database::store::DataStore;
+use crate::managers::auth_guard::AuthGuard;
+use crate::managers::funnel::{FunnelManager, FunnelStage};
+use crate::managers::health_score::HealthScoreManager;
+use crate::managers::protocol::{MIN_PROTOCOL_VERSION, PROTOCOL_VERSION};
+use crate::managers::regions::RegionManager;
+use crate::managers::room::RoomManager;
+
+// The server's Socket.IO handle, for code that keeps socket ids rather than sockets
+static IO: OnceCell<SocketIo> = OnceCell::new();
 
 pub struct ConnectionManager;
 
 impl ConnectionManager {
+    pub fn attach(io: &SocketIo) {
+        let _ = IO.set(io.clone());
+    }
+
+    // A socket by namespace and id, None once it has disconnected
+    pub fn socket(namespace: &str, socket_id: Sid) -> Option<SocketRef> {
+        IO.get()?.of(namespace)?.get_socket(socket_id)
+    }
+
     /// Mark a socket as problematic for disconnection
     pub fn mark_problematic_socket(socket_id: &str) {
         // This would be called when a socket causes issues
@@ -26,38 +47,89 @@ impl ConnectionManager {
         false
     }
 
-    pub async fn send_connect_response(socket: &SocketRef, data_service: Arc<DataService>) {
+    // State a reconnecting client would otherwise ask for one event at a
+    // time, for a `jwt_token` in the handshake auth payload. None without a
+    // token; a token that fails verification gives restored: false and the
+    // error code, so the client knows to log in again.
+    async fn restore_session(data_service: &dyn DataStore, auth: &Value) -> Option<Value> {
+        auth["jwt_token"].as_str()?;
+        let user = match AuthGuard::verify_jwt(data_service, auth).await {
+            Ok(context) => context.user,
+            Err(error) => return Some(json!({"restored": false, "error_code": error.error_code})),
+        };
+
+        let missing: Vec<&str> = [
+            ("full_name", user.full_name.is_none()),
+            ("state", user.state.is_none()),
+            ("language_code", user.language_code.is_none()),
+        ].into_iter().filter(|(_, missing)| *missing).map(|(field, _)| field).collect();
+        let unread_notifications = match data_service.count_unread_notifications(&user.user_id).await {
+            Ok(count) => Some(count),
+            Err(e) => {
+                warn!("⚠️ Failed to count unread notifications of user {}: {}", user.user_id, e);
+                None
+            }
+        };
+
+        Some(json!({
+            "restored": true,
+            "user_id": user.user_id,
+            "user_number": user.user_number,
+            "language": {
+                "language_code": user.language_code,
+                "language_name": user.language_name,
+                "region_code": user.region_code,
+                "timezone": user.timezone
+            },
+            "profile": {
+                "complete": missing.is_empty(),
+                "missing_fields": missing
+            },
+            "unread_notifications": unread_notifications,
+            "active_room_ids": RoomManager::rooms_of_player(&user.user_id).await
+        }))
+    }
+
+    pub async fn send_connect_response(socket: &SocketRef, data_service: Arc<dyn DataStore>, auth: &Value) {
         // Generate random token (6-digit number)
         let token = rand::thread_rng().gen_range(100000..999999);
         
         // Create structured JSON response
-        let connect_response = json!({
+        let mut data = json!({
             "token": token,
             "message": "Welcome to the Game Admin Server!",
-            "timestamp": Utc::now().to_rfc3339(),
-            "socket_id": socket.id.to_string(),
-            "status": "connected",
-            "event": "connect",
             "server_info": {
                 "version": "1.0.0",
+                "protocol_version": PROTOCOL_VERSION,
+                "min_protocol_version": MIN_PROTOCOL_VERSION,
                 "heartbeat_interval": 60000,
                 "ping_timeout": 60000,
                 "max_payload": 1048576
-            }
+            },
+            "regions": RegionManager::recommendations(socket)
         });
+        if let Some(session) = Self::restore_session(&*data_service, auth).await {
+            data["session"] = session;
+        }
+        let connect_response = ApiResponse::success("connect", data).with_status("connected").for_socket(socket.id);
         
         // Log the connect response data
         info!("📨 Connect response data: {:?}", connect_response);
         
         // Store connect event in MongoDB
-        match data_service.store_connect_event(&socket.id.to_string(), token, "Welcome to the Game Admin Server!", "connected").await {
+        let ip_address = ClientIp::of(socket).map(|client| client.ip.to_string());
+        match data_service.store_connect_event(&socket.id.to_string(), token, "Welcome to the Game Admin Server!", "connected", ip_address.as_deref()).await {
             Ok(_) => info!("📝 Stored connect event for socket: {}", socket.id),
             Err(e) => warn!("⚠️ Failed to store connect event for socket {}: {}", socket.id, e),
         }
         
         // Send connect response with proper error handling
         match socket.emit("connect_response", connect_response) {
-            Ok(_) => info!("✅ Sent connect response to socket: {} with token: {}", socket.id, token),
+            Ok(_) => {
+                info!("✅ Sent connect response to socket: {} with token: {}", socket.id, token);
+                FunnelManager::record(&socket.id.to_string(), FunnelStage::Connected);
+                HealthScoreManager::record_connect();
+            }
             Err(e) => {
                 error!("❌ Failed to send connect response to socket {}: {}", socket.id, e);
                 // Mark socket as problematic if it fails to send messages
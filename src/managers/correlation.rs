@@ -0,0 +1,68 @@
+use serde_json::Value;
+use std::cell::Cell;
+use std::future::Future;
+use std::time::Instant;
+use tracing::{info_span, Instrument};
+use uuid::Uuid;
+
+use crate::managers::handler_metrics::HandlerMetrics;
+use crate::managers::tenant::TenantManager;
+
+// Longest client-supplied request_id we accept; anything else gets a fresh id
+const MAX_REQUEST_ID_LENGTH: usize = 64;
+
+// State of the handler call currently running
+struct RequestContext {
+    request_id: String,
+    failed: Cell<bool>,     // Set when the handler builds an ApiError
+}
+
+tokio::task_local! {
+    static CONTEXT: RequestContext;
+}
+
+pub struct Correlation;
+
+impl Correlation {
+    // The client's request_id when it is 1-64 chars of [A-Za-z0-9_-], otherwise a new UUID v7
+    pub fn request_id_from(data: &Value) -> String {
+        data.get("request_id")
+            .and_then(|v| v.as_str())
+            .filter(|id| {
+                !id.is_empty()
+                    && id.len() <= MAX_REQUEST_ID_LENGTH
+                    && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            })
+            .map(|id| id.to_string())
+            .unwrap_or_else(|| Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext)).to_string())
+    }
+
+    // Run an event handler with its request id in scope: every log line gets a
+    // span carrying it, and responses and stored event documents pick it up
+    // through Correlation::current(). The handler also runs in the scope of
+    // the socket's tenant. The call's duration and outcome are recorded in
+    // HandlerMetrics under `event`.
+    pub fn scope<F: Future>(event: &'static str, socket_id: impl ToString, request_id: String, handler: F) -> impl Future<Output = F::Output> {
+        let socket_id = socket_id.to_string();
+        let context = RequestContext { request_id, failed: Cell::new(false) };
+        CONTEXT.scope(context, async move {
+            let tenant = TenantManager::of_socket(&socket_id).await;
+            let request_id = CONTEXT.with(|c| c.request_id.clone());
+            let span = info_span!("socket_event", event, socket_id = %socket_id, request_id = %request_id, tenant = %tenant.tenant_id);
+            let started = Instant::now();
+            let output = TenantManager::scope(tenant, handler.instrument(span)).await;
+            HandlerMetrics::record(event, started.elapsed(), CONTEXT.with(|c| c.failed.get()));
+            output
+        })
+    }
+
+    // Request id of the handler currently running, if any (None in timers and background tasks)
+    pub fn current() -> Option<String> {
+        CONTEXT.try_with(|c| c.request_id.clone()).ok()
+    }
+
+    // Count the running handler call as an error in HandlerMetrics
+    pub fn mark_failed() {
+        let _ = CONTEXT.try_with(|c| c.failed.set(true));
+    }
+}
@@ -0,0 +1,399 @@
+use data_encoding::HEXLOWER;
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use socketioxide::extract::{Data, SocketRef};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::api::response::{ApiError, ApiResponse};
+use crate::database::models::{DealerAudit, DealerOperation};
+use crate::database::store::DataStore;
+use crate::managers::correlation::Correlation;
+use crate::managers::party::player_room;
+use crate::managers::room::{GameRoom, RoomManager};
+use crate::managers::validation::ValidationManager;
+
+// Limits on dealer:shuffle and dealer:roll
+pub const MAX_DECK_SIZE: usize = 520;
+pub const MAX_CARD_LENGTH: usize = 32;
+pub const MAX_DICE: u32 = 20;
+pub const MAX_SIDES: u32 = 1000;
+
+// A shuffled deck; cards are drawn from the front
+struct Deck {
+    cards: Vec<String>,
+    drawn: usize,
+}
+
+// The random state of one room. Every shuffle and roll is derived from the
+// secret seed and its own nonce, so revealing the seed lets anyone redo them.
+struct Dealer {
+    seed: String,
+    seed_commitment: String,
+    created_at: bson::DateTime,
+    decks: HashMap<String, Deck>,
+    hands: HashMap<String, Vec<(String, String)>>,  // player_id -> (deck_id, card) dealt face down
+    operations: Vec<DealerOperation>,
+    reveal_votes: BTreeSet<String>,
+}
+
+impl Dealer {
+    fn new() -> Self {
+        let seed = HEXLOWER.encode(&rand::random::<[u8; 32]>());
+        Self {
+            seed_commitment: HEXLOWER.encode(&Sha256::digest(seed.as_bytes())),
+            seed,
+            created_at: bson::DateTime::now(),
+            decks: HashMap::new(),
+            hands: HashMap::new(),
+            operations: Vec::new(),
+            reveal_votes: BTreeSet::new(),
+        }
+    }
+
+    fn next_nonce(&self) -> u32 {
+        self.operations.len() as u32
+    }
+
+    // A number below `bound`: the first 8 bytes of SHA-256("<seed>:<nonce>:<index>")
+    // as a big-endian integer, modulo `bound`
+    fn random_below(&self, nonce: u32, index: usize, bound: usize) -> usize {
+        let digest = Sha256::digest(format!("{}:{}:{}", self.seed, nonce, index).as_bytes());
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&digest[..8]);
+        (u64::from_be_bytes(bytes) % bound as u64) as usize
+    }
+
+    // Fisher-Yates from the back: position i swaps with random_below(nonce, i, i + 1)
+    fn shuffle(&self, nonce: u32, cards: &[String]) -> Vec<String> {
+        let mut shuffled = cards.to_vec();
+        for i in (1..shuffled.len()).rev() {
+            shuffled.swap(i, self.random_below(nonce, i, i + 1));
+        }
+        shuffled
+    }
+
+    // Die k shows random_below(nonce, k, sides) + 1
+    fn roll(&self, nonce: u32, dice: u32, sides: u32) -> Vec<u32> {
+        (0..dice as usize).map(|k| self.random_below(nonce, k, sides as usize) as u32 + 1).collect()
+    }
+}
+
+// Dealers by room_id
+static DEALERS: Lazy<Mutex<HashMap<String, Dealer>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// Authoritative shuffles and dice rolls for card and board games. Only the
+// server knows a deck's order: cards dealt face down go to the player's own
+// channel (their player room) and the room only learns how many were drawn.
+// Each room has a secret seed whose SHA-256 (`seed_commitment`) comes with
+// every shuffle and roll. Once every seated human player asks for it with
+// dealer:reveal, the seed and the list of draws are sent to the room and kept
+// in `dealer_audits`, so anyone can redo each shuffle and roll and check the
+// server did not change them; the room's next draw starts a new seed. The
+// dealer lives in memory and does not survive a restart.
+pub struct DealerManager;
+
+impl DealerManager {
+    fn emit_dealer_error(s: &SocketRef, error: ApiError) {
+        let _ = s.emit("dealer:error", error.on_event("dealer:error").for_socket(s.id));
+    }
+
+    fn broadcast(s: &SocketRef, room_id: &str, event: &'static str, payload: Value) {
+        if let Err(e) = s.within(room_id.to_string()).emit(event, ApiResponse::success(event, payload)) {
+            warn!("⚠️ Failed to broadcast {} to room {}: {}", event, room_id, e);
+        }
+    }
+
+    // The room, if the player is seated in it on this socket
+    async fn seated_room(s: &SocketRef, room_id: &str, player_id: &str) -> Option<GameRoom> {
+        let room = RoomManager::get_room(room_id).await
+            .filter(|room| room.players.iter().any(|p| p.player_id == player_id && !p.is_bot && p.socket_id == s.id.to_string()));
+        if room.is_none() {
+            let error = ApiError::new("NOT_IN_ROOM", "DEALER_ERROR", "room_id", "Only players seated in the room can use its dealer")
+                .with_details(json!({"room_id": room_id, "player_id": player_id}));
+            Self::emit_dealer_error(s, error);
+        }
+        room
+    }
+
+    fn operation_view(operation: &DealerOperation) -> Value {
+        json!({
+            "nonce": operation.nonce,
+            "kind": operation.kind,
+            "player_id": operation.player_id,
+            "deck_id": operation.deck_id,
+            "cards": operation.cards,
+            "dice": operation.dice,
+            "sides": operation.sides,
+            "values": operation.values,
+            "at": operation.at.try_to_rfc3339_string().unwrap_or_default(),
+        })
+    }
+
+    // Register the dealer on a gameplay namespace socket:
+    //   dealer:shuffle { room_id, player_id, deck_id, cards }           -> dealer:shuffled to the room
+    //   dealer:draw    { room_id, player_id, deck_id, count, face_up? } -> dealer:cards to the player, dealer:drawn to the room
+    //   dealer:roll    { room_id, player_id, dice, sides }              -> dealer:rolled to the room
+    //   dealer:hand    { room_id, player_id }                           -> dealer:hand to the sender
+    //   dealer:reveal  { room_id, player_id }                           -> dealer:reveal_requested, then dealer:revealed to the room
+    pub fn register_dealer_events(socket: &SocketRef, data_service: Arc<dyn DataStore>) {
+        socket.on("dealer:shuffle", |s: SocketRef, Data::<Value>(data)| {
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope("dealer:shuffle", s.id, request_id, async move {
+                if let Err(error_details) = ValidationManager::validate_dealer_shuffle_data(&data) {
+                    Self::emit_dealer_error(&s, ApiError::from(error_details));
+                    return;
+                }
+                let room_id = data["room_id"].as_str().unwrap_or_default();
+                let player_id = data["player_id"].as_str().unwrap_or_default();
+                let deck_id = data["deck_id"].as_str().unwrap_or_default();
+                if Self::seated_room(&s, room_id, player_id).await.is_none() {
+                    return;
+                }
+                let cards: Vec<String> = serde_json::from_value(data["cards"].clone()).unwrap_or_default();
+
+                let shuffled = {
+                    let mut dealers = DEALERS.lock().await;
+                    let dealer = dealers.entry(room_id.to_string()).or_insert_with(Dealer::new);
+                    let nonce = dealer.next_nonce();
+                    let order = dealer.shuffle(nonce, &cards);
+                    dealer.decks.insert(deck_id.to_string(), Deck { cards: order, drawn: 0 });
+                    dealer.operations.push(DealerOperation {
+                        nonce,
+                        kind: "shuffle".to_string(),
+                        player_id: player_id.to_string(),
+                        deck_id: Some(deck_id.to_string()),
+                        cards: Some(cards.clone()),
+                        dice: None,
+                        sides: None,
+                        values: None,
+                        at: bson::DateTime::now(),
+                    });
+                    json!({
+                        "room_id": room_id,
+                        "deck_id": deck_id,
+                        "player_id": player_id,
+                        "size": cards.len(),
+                        "nonce": nonce,
+                        "seed_commitment": dealer.seed_commitment,
+                    })
+                };
+                info!("🃏 Player {} shuffled deck {} ({} cards) in room {}", player_id, deck_id, cards.len(), room_id);
+                Self::broadcast(&s, room_id, "dealer:shuffled", shuffled);
+            })
+        });
+
+        socket.on("dealer:draw", |s: SocketRef, Data::<Value>(data)| {
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope("dealer:draw", s.id, request_id, async move {
+                if let Err(error_details) = ValidationManager::validate_dealer_draw_data(&data) {
+                    Self::emit_dealer_error(&s, ApiError::from(error_details));
+                    return;
+                }
+                let room_id = data["room_id"].as_str().unwrap_or_default();
+                let player_id = data["player_id"].as_str().unwrap_or_default();
+                let deck_id = data["deck_id"].as_str().unwrap_or_default();
+                let count = data["count"].as_u64().unwrap_or_default() as usize;
+                let face_up = data["face_up"].as_bool().unwrap_or(false);
+                if Self::seated_room(&s, room_id, player_id).await.is_none() {
+                    return;
+                }
+
+                let drawn = {
+                    let mut dealers = DEALERS.lock().await;
+                    let Some(dealer) = dealers.get_mut(room_id).filter(|dealer| dealer.decks.contains_key(deck_id)) else {
+                        Self::emit_dealer_error(&s, ApiError::new("DECK_NOT_FOUND", "DEALER_ERROR", "deck_id", "No such deck in this room")
+                            .with_details(json!({"room_id": room_id, "deck_id": deck_id})));
+                        return;
+                    };
+                    let Some(deck) = dealer.decks.get_mut(deck_id) else { return };
+                    let remaining = deck.cards.len() - deck.drawn;
+                    if count > remaining {
+                        Self::emit_dealer_error(&s, ApiError::new("DECK_EXHAUSTED", "DEALER_ERROR", "count", "Not enough cards left in the deck")
+                            .with_details(json!({"room_id": room_id, "deck_id": deck_id, "remaining": remaining})));
+                        return;
+                    }
+                    let cards = deck.cards[deck.drawn..deck.drawn + count].to_vec();
+                    deck.drawn += count;
+                    if !face_up {
+                        dealer.hands.entry(player_id.to_string()).or_default()
+                            .extend(cards.iter().map(|card| (deck_id.to_string(), card.clone())));
+                    }
+                    (cards, remaining - count)
+                };
+                let (cards, remaining) = drawn;
+
+                // Face-down cards only go to the player's own channel
+                if !face_up {
+                    let private = ApiResponse::success("dealer:cards", json!({
+                        "room_id": room_id,
+                        "deck_id": deck_id,
+                        "cards": cards,
+                        "remaining": remaining,
+                    }));
+                    if let Err(e) = s.within(player_room(player_id)).emit("dealer:cards", private) {
+                        warn!("⚠️ Failed to deal cards to player {}: {}", player_id, e);
+                    }
+                }
+                Self::broadcast(&s, room_id, "dealer:drawn", json!({
+                    "room_id": room_id,
+                    "deck_id": deck_id,
+                    "player_id": player_id,
+                    "count": count,
+                    "remaining": remaining,
+                    "face_up": face_up,
+                    "cards": face_up.then_some(&cards),
+                }));
+            })
+        });
+
+        socket.on("dealer:roll", |s: SocketRef, Data::<Value>(data)| {
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope("dealer:roll", s.id, request_id, async move {
+                if let Err(error_details) = ValidationManager::validate_dealer_roll_data(&data) {
+                    Self::emit_dealer_error(&s, ApiError::from(error_details));
+                    return;
+                }
+                let room_id = data["room_id"].as_str().unwrap_or_default();
+                let player_id = data["player_id"].as_str().unwrap_or_default();
+                let dice = data["dice"].as_u64().unwrap_or_default() as u32;
+                let sides = data["sides"].as_u64().unwrap_or_default() as u32;
+                if Self::seated_room(&s, room_id, player_id).await.is_none() {
+                    return;
+                }
+
+                let rolled = {
+                    let mut dealers = DEALERS.lock().await;
+                    let dealer = dealers.entry(room_id.to_string()).or_insert_with(Dealer::new);
+                    let nonce = dealer.next_nonce();
+                    let values = dealer.roll(nonce, dice, sides);
+                    dealer.operations.push(DealerOperation {
+                        nonce,
+                        kind: "roll".to_string(),
+                        player_id: player_id.to_string(),
+                        deck_id: None,
+                        cards: None,
+                        dice: Some(dice),
+                        sides: Some(sides),
+                        values: Some(values.clone()),
+                        at: bson::DateTime::now(),
+                    });
+                    json!({
+                        "room_id": room_id,
+                        "player_id": player_id,
+                        "dice": dice,
+                        "sides": sides,
+                        "values": values,
+                        "total": values.iter().sum::<u32>(),
+                        "nonce": nonce,
+                        "seed_commitment": dealer.seed_commitment,
+                    })
+                };
+                Self::broadcast(&s, room_id, "dealer:rolled", rolled);
+            })
+        });
+
+        socket.on("dealer:hand", |s: SocketRef, Data::<Value>(data)| {
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope("dealer:hand", s.id, request_id, async move {
+                if let Err(error_details) = ValidationManager::validate_room_data(&data) {
+                    Self::emit_dealer_error(&s, ApiError::from(error_details));
+                    return;
+                }
+                let room_id = data["room_id"].as_str().unwrap_or_default();
+                let player_id = data["player_id"].as_str().unwrap_or_default();
+                if Self::seated_room(&s, room_id, player_id).await.is_none() {
+                    return;
+                }
+                let (cards, seed_commitment) = {
+                    let dealers = DEALERS.lock().await;
+                    let dealer = dealers.get(room_id);
+                    let cards: Vec<Value> = dealer
+                        .and_then(|dealer| dealer.hands.get(player_id))
+                        .map(|hand| hand.iter().map(|(deck_id, card)| json!({"deck_id": deck_id, "card": card})).collect())
+                        .unwrap_or_default();
+                    (cards, dealer.map(|dealer| dealer.seed_commitment.clone()))
+                };
+                let hand = ApiResponse::success("dealer:hand", json!({
+                    "room_id": room_id,
+                    "player_id": player_id,
+                    "cards": cards,
+                    "seed_commitment": seed_commitment,
+                })).for_socket(s.id);
+                let _ = s.emit("dealer:hand", hand);
+            })
+        });
+
+        socket.on("dealer:reveal", move |s: SocketRef, Data::<Value>(data)| {
+            let data_service = data_service.clone();
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope("dealer:reveal", s.id, request_id, async move {
+                if let Err(error_details) = ValidationManager::validate_room_data(&data) {
+                    Self::emit_dealer_error(&s, ApiError::from(error_details));
+                    return;
+                }
+                let room_id = data["room_id"].as_str().unwrap_or_default();
+                let player_id = data["player_id"].as_str().unwrap_or_default();
+                let Some(room) = Self::seated_room(&s, room_id, player_id).await else { return };
+                let humans: BTreeSet<&str> = room.players.iter().filter(|p| !p.is_bot).map(|p| p.player_id.as_str()).collect();
+
+                let revealed = {
+                    let mut dealers = DEALERS.lock().await;
+                    let Some(dealer) = dealers.get_mut(room_id) else {
+                        Self::emit_dealer_error(&s, ApiError::new("NOTHING_TO_REVEAL", "DEALER_ERROR", "room_id", "Nothing has been shuffled or rolled in this room")
+                            .with_details(json!({"room_id": room_id})));
+                        return;
+                    };
+                    dealer.reveal_votes.insert(player_id.to_string());
+                    let votes = dealer.reveal_votes.iter().filter(|voter| humans.contains(voter.as_str())).count();
+                    if votes < humans.len() {
+                        Self::broadcast(&s, room_id, "dealer:reveal_requested", json!({
+                            "room_id": room_id,
+                            "player_id": player_id,
+                            "votes": votes,
+                            "needed": humans.len(),
+                        }));
+                        return;
+                    }
+                    dealers.remove(room_id)
+                };
+                let Some(dealer) = revealed else { return };
+
+                info!("🃏 Revealed the dealer seed of room {} after {} draws", room_id, dealer.operations.len());
+                Self::broadcast(&s, room_id, "dealer:revealed", json!({
+                    "room_id": room_id,
+                    "seed_commitment": dealer.seed_commitment,
+                    "server_seed": dealer.seed,
+                    "operations": dealer.operations.iter().map(Self::operation_view).collect::<Vec<_>>(),
+                }));
+                Self::save_audit(&*data_service, room_id, dealer).await;
+            })
+        });
+    }
+
+    async fn save_audit(data_service: &dyn DataStore, room_id: &str, dealer: Dealer) {
+        let audit = DealerAudit {
+            id: None,
+            room_id: room_id.to_string(),
+            seed_commitment: dealer.seed_commitment,
+            server_seed: dealer.seed,
+            operations: dealer.operations,
+            created_at: dealer.created_at,
+            revealed_at: bson::DateTime::now(),
+        };
+        if let Err(e) = data_service.save_dealer_audit(audit).await {
+            warn!("⚠️ Failed to store the dealer audit of room {}: {}", room_id, e);
+        }
+    }
+
+    // Drop a closed room's dealer; a seed that was used is still kept in dealer_audits
+    pub async fn close_room(data_service: &dyn DataStore, room_id: &str) {
+        let Some(dealer) = DEALERS.lock().await.remove(room_id) else { return };
+        if !dealer.operations.is_empty() {
+            Self::save_audit(data_service, room_id, dealer).await;
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use serde_json::Value;
+
+use crate::database::models::DeepLink;
+
+// Longest id a deep-link parameter may carry
+const MAX_PARAM_LENGTH: usize = 128;
+
+// Registered deep-link actions and the ids each one needs. Clients map the
+// action to a screen; a notification can only carry an action listed here.
+const ACTIONS: &[(&str, &[&str])] = &[
+    ("open_room", &["room_id"]),
+    ("claim_challenge", &["challenge_id"]),
+    ("open_party", &["party_id"]),
+    ("open_profile", &["user_id"]),
+    ("open_gift", &["gift_id"]),
+    ("open_season", &["season_id"]),
+    ("open_report", &["report_id"]),
+    ("open_sanction", &["sanction_id"]),
+];
+
+// Structured tap actions on notifications, e.g.
+//   { "action": "open_room", "params": { "room_id": "..." } }
+// checked against the action's schema before the notification is stored, so
+// a typo never reaches a device as a dead link.
+pub struct DeepLinkManager;
+
+impl DeepLinkManager {
+    pub fn schema(action: &str) -> Option<&'static [&'static str]> {
+        ACTIONS.iter().find(|(name, _)| *name == action).map(|(_, params)| *params)
+    }
+
+    // Ok when the action is registered and `params` has exactly its ids, each
+    // a non-empty string
+    pub fn validate(link: &DeepLink) -> Result<(), String> {
+        let Some(required) = Self::schema(&link.action) else {
+            return Err(format!("Unknown deep link action {}", link.action));
+        };
+        let Some(params) = link.params.as_object() else {
+            return Err(format!("Deep link {} needs params {:?}", link.action, required));
+        };
+        if let Some(unknown) = params.keys().find(|key| !required.contains(&key.as_str())) {
+            return Err(format!("Deep link {} does not take {}", link.action, unknown));
+        }
+        for name in required {
+            match params.get(*name).and_then(Value::as_str) {
+                Some(value) if !value.is_empty() && value.len() <= MAX_PARAM_LENGTH => {}
+                _ => return Err(format!("Deep link {} needs {} as a string of 1-{} characters", link.action, name, MAX_PARAM_LENGTH)),
+            }
+        }
+        Ok(())
+    }
+}
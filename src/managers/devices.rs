@@ -0,0 +1,109 @@
+use socketioxide::extract::SocketRef;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tracing::{info, warn, error};
+
+use crate::api::response::{ApiError, ApiResponse};
+use crate::managers::auth_guard::{AuthContext, AuthGuard};
+use crate::managers::chaos::FaultInjector;
+use crate::managers::error_responder::ErrorResponder;
+use crate::managers::two_step::TwoStepManager;
+use crate::managers::validation::ValidationManager;
+use crate::database::store::DataStore;
+
+pub struct DeviceManager;
+
+impl DeviceManager {
+    // Trusted device management on the main namespace:
+    //   devices:list   { mobile_no, session_token }            -> devices:listed
+    //   devices:remove { mobile_no, session_token, device_id } -> devices:removed
+    // Devices are recorded when an OTP is verified. Removing one revokes every
+    // session opened from it (sessions are the only credential we issue - there
+    // are no refresh tokens), so the device must log in with an OTP again.
+    // Users with two-step verification also send their `two_step_code`.
+    pub fn register_device_events(socket: &SocketRef, data_service: Arc<dyn DataStore>) {
+        let ds = data_service.clone();
+        AuthGuard::require_session(socket, "devices:list", data_service.clone(), move |socket, data, auth| {
+            let ds = ds.clone();
+            async move {
+                info!("📱 Received devices:list from {}", socket.id);
+                let Some(mobile_no) = Self::validate(&socket, &*ds, &data, &auth, &["mobile_no", "session_token"]).await else {
+                    return;
+                };
+
+                match ds.list_user_devices(&mobile_no).await {
+                    Ok(devices) => {
+                        let devices: Vec<Value> = devices.iter().map(|d| json!({
+                            "device_id": d.device_id,
+                            "device_type": d.device_type,
+                            "manufacturer": d.manufacturer,
+                            "model": d.model,
+                            "firmware_version": d.firmware_version,
+                            "first_seen_at": d.first_seen_at.try_to_rfc3339_string().unwrap_or_default(),
+                            "last_seen_at": d.last_seen_at.try_to_rfc3339_string().unwrap_or_default()
+                        })).collect();
+                        let response = ApiResponse::success("devices:listed", json!({
+                            "mobile_no": mobile_no,
+                            "devices": devices
+                        })).for_socket(socket.id);
+                        if let Err(e) = FaultInjector::emit(&socket, "devices:listed", response).await {
+                            warn!("⚠️ Failed to emit devices:listed to socket {}: {}", socket.id, e);
+                        }
+                    }
+                    Err(e) => {
+                        error!("❌ Failed to list devices for mobile {}: {}", mobile_no, e);
+                        ErrorResponder::send(&socket, &*ds, ApiError::system("DEVICES_LIST_FAILED", "mobile_no", "Failed to load devices", &e)).await;
+                    }
+                }
+            }
+        });
+
+        let ds = data_service.clone();
+        AuthGuard::require_session(socket, "devices:remove", data_service, move |socket, data, auth| {
+            let ds = ds.clone();
+            async move {
+                info!("📱 Received devices:remove from {}: {:?}", socket.id, data["device_id"]);
+                let Some(mobile_no) = Self::validate(&socket, &*ds, &data, &auth, &["mobile_no", "session_token", "device_id"]).await else {
+                    return;
+                };
+                let device_id = data["device_id"].as_str().unwrap_or_default();
+                if let Err(error) = TwoStepManager::require(&*ds, &auth, &data).await {
+                    ErrorResponder::send(&socket, &*ds, error).await;
+                    return;
+                }
+
+                match ds.remove_user_device(&mobile_no, device_id).await {
+                    Ok(true) => {
+                        let response = ApiResponse::success("devices:removed", json!({
+                            "message": "Device removed and its sessions revoked",
+                            "mobile_no": mobile_no,
+                            "device_id": device_id
+                        })).for_socket(socket.id);
+                        if let Err(e) = FaultInjector::emit(&socket, "devices:removed", response).await {
+                            warn!("⚠️ Failed to emit devices:removed to socket {}: {}", socket.id, e);
+                        }
+                    }
+                    Ok(false) => {
+                        let error = ApiError::new("DEVICE_NOT_FOUND", "VALUE_ERROR", "device_id", "Device not found")
+                            .with_details(json!({"device_id": device_id}));
+                        ErrorResponder::send(&socket, &*ds, error).await;
+                    }
+                    Err(e) => {
+                        error!("❌ Failed to remove device {} for mobile {}: {}", device_id, mobile_no, e);
+                        ErrorResponder::send(&socket, &*ds, ApiError::system("DEVICE_REMOVE_FAILED", "device_id", "Failed to remove device", &e)).await;
+                    }
+                }
+            }
+        });
+    }
+
+    // Validate the payload of an authenticated request; emits connection_error and returns None on failure
+    async fn validate(socket: &SocketRef, data_service: &dyn DataStore, data: &Value, auth: &AuthContext, fields: &[&str]) -> Option<String> {
+        if let Err(error_details) = ValidationManager::validate_device_management_data(data, fields) {
+            info!("❌ Device management validation failed for socket {}: {:?}", socket.id, error_details);
+            ErrorResponder::send(socket, data_service, error_details).await;
+            return None;
+        }
+        Some(auth.mobile_no.clone())
+    }
+}
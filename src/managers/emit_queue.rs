@@ -0,0 +1,147 @@
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::{json, Value};
+use socketioxide::{extract::SocketRef, socket::Sid};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::config::CONFIG;
+use crate::database::store::DataStore;
+use crate::managers::chaos::FaultInjector;
+use crate::managers::connection::ConnectionManager;
+use crate::managers::notifications::{NotificationCategory, NotificationManager};
+use crate::managers::tenant::TenantManager;
+
+// Who to tell through the offline inbox when an event cannot be delivered,
+// and with which notification template
+#[derive(Clone)]
+pub struct OfflineFallback {
+    pub data_service: Arc<dyn DataStore>,
+    pub mobile_no: String,
+    pub notification_type: &'static str,
+}
+
+struct Pending {
+    event: String,
+    data: Value,
+    fallback: Option<OfflineFallback>,
+}
+
+// Events waiting for a retry, by socket id
+static QUEUES: Lazy<Mutex<HashMap<String, VecDeque<Pending>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// Emits that survive transient send failures. A failed emit goes into a short
+// queue for its socket, retried EMIT_RETRY_ATTEMPTS times with a delay that
+// starts at EMIT_RETRY_DELAY_MS and doubles; later emits to that socket wait
+// behind it so the order holds. What is still undelivered when the retries
+// run out or the socket disconnects goes to the user's offline inbox if the
+// emit named a fallback, and is otherwise dropped with a warning.
+pub struct EmitQueue;
+
+impl EmitQueue {
+    // Ok when the event was sent or queued for a retry
+    pub async fn emit<T: Serialize>(socket: &SocketRef, event: &str, data: T, fallback: Option<OfflineFallback>) -> Result<(), String> {
+        let data = serde_json::to_value(data).map_err(|e| e.to_string())?;
+        let socket_id = socket.id.to_string();
+        let pending = Pending { event: event.to_string(), data, fallback };
+
+        // Anything already waiting goes out first
+        if let Some(queue) = QUEUES.lock().unwrap_or_else(|e| e.into_inner()).get_mut(&socket_id) {
+            queue.push_back(pending);
+            return Ok(());
+        }
+        let error = match FaultInjector::emit(socket, event, &pending.data).await {
+            Ok(()) => return Ok(()),
+            Err(e) => e,
+        };
+        if ConnectionManager::socket(socket.ns(), socket.id).is_none() {
+            Self::give_up(socket.id, vec![pending], &error).await;
+            return Err(error);
+        }
+
+        warn!("⚠️ Failed to emit {} to socket {} - retrying: {}", event, socket_id, error);
+        QUEUES.lock().unwrap_or_else(|e| e.into_inner()).entry(socket_id).or_default().push_back(pending);
+        let (namespace, socket_id) = (socket.ns().to_string(), socket.id);
+        TenantManager::spawn(async move { Self::drain(namespace, socket_id).await });
+        Ok(())
+    }
+
+    // Retry the socket's queue front to back until it is empty or the
+    // retries run out. The socket is looked up again on every attempt so a
+    // disconnect in between ends the retries.
+    async fn drain(namespace: String, sid: Sid) {
+        let socket_id = sid.to_string();
+        let mut attempt: u32 = 0;
+        loop {
+            let delay = CONFIG.emit_retry_delay_ms.saturating_mul(1 << attempt.min(10));
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+
+            let front = QUEUES.lock().unwrap_or_else(|e| e.into_inner())
+                .get(&socket_id)
+                .and_then(|queue| queue.front().map(|pending| (pending.event.clone(), pending.data.clone())));
+            let Some((event, data)) = front else { return };
+
+            let error = if let Some(socket) = ConnectionManager::socket(&namespace, sid) {
+                match FaultInjector::emit(&socket, &event, &data).await {
+                    Ok(()) => {
+                        info!("📨 Delivered {} to socket {} on retry {}", event, socket_id, attempt + 1);
+                        let mut queues = QUEUES.lock().unwrap_or_else(|e| e.into_inner());
+                        if let Some(queue) = queues.get_mut(&socket_id) {
+                            queue.pop_front();
+                            if queue.is_empty() {
+                                queues.remove(&socket_id);
+                                return;
+                            }
+                        }
+                        attempt = 0;
+                        continue;
+                    }
+                    Err(e) => e,
+                }
+            } else {
+                "socket disconnected".to_string()
+            };
+
+            attempt += 1;
+            if attempt >= CONFIG.emit_retry_attempts || ConnectionManager::socket(&namespace, sid).is_none() {
+                let undelivered = QUEUES.lock().unwrap_or_else(|e| e.into_inner()).remove(&socket_id).unwrap_or_default();
+                Self::give_up(sid, undelivered.into(), &error).await;
+                return;
+            }
+        }
+    }
+
+    async fn give_up(socket_id: Sid, undelivered: Vec<Pending>, error: &str) {
+        for pending in undelivered {
+            let Some(fallback) = pending.fallback else {
+                warn!("⚠️ Dropped {} to socket {} after retries: {}", pending.event, socket_id, error);
+                continue;
+            };
+            warn!("⚠️ Could not deliver {} to socket {} - leaving it in the inbox of mobile {}: {}", pending.event, socket_id, fallback.mobile_no, error);
+            let user = match fallback.data_service.get_user_by_mobile(&fallback.mobile_no).await {
+                Ok(Some(user)) => user,
+                Ok(None) => continue,
+                Err(e) => {
+                    warn!("⚠️ Failed to look up mobile {} for an undelivered {}: {}", fallback.mobile_no, pending.event, e);
+                    continue;
+                }
+            };
+            // Only the event name: tokens in the payload never go to the inbox or a push
+            let data = json!({ "event": pending.event, "socket_id": socket_id.to_string() });
+            let dispatched = NotificationManager::dispatch(
+                &*fallback.data_service,
+                &user.user_id,
+                NotificationCategory::System,
+                fallback.notification_type,
+                json!({}),
+                data,
+                None,
+            ).await;
+            if let Err(e) = dispatched {
+                warn!("⚠️ Failed to leave undelivered {} in the inbox of user {}: {}", pending.event, user.user_id, e);
+            }
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use socketioxide::extract::SocketRef;
+use bson::to_document;
+use tracing::{info, warn};
+
+use crate::api::response::ApiError;
+use crate::database::store::DataStore;
+use crate::managers::chaos::FaultInjector;
+
+pub struct ErrorResponder;
+
+impl ErrorResponder {
+    // Report an error to the client in one call: store it in connection_error_events,
+    // emit it on its event (connection_error unless overridden) and log it.
+    // Accepts an ApiError or a ValidationError.
+    pub async fn send(socket: &SocketRef, data_service: &dyn DataStore, error: impl Into<ApiError>) {
+        let error = error.into().for_socket(socket.id);
+        let payload_doc = to_document(&error).unwrap_or_default();
+        if let Err(e) = data_service.store_connection_error_event(
+            &socket.id.to_string(),
+            &error.error_code,
+            &error.error_type,
+            &error.field,
+            &error.message,
+            payload_doc
+        ).await {
+            warn!("⚠️ Failed to store {} for socket {}: {}", error.error_code, socket.id, e);
+        }
+
+        if let Err(e) = FaultInjector::emit(socket, &error.event, &error).await {
+            warn!("⚠️ Failed to emit {} to socket {}: {}", error.event, socket.id, e);
+        }
+        info!("❌ Sent {} ({}) to socket {}: {} [field: {}]", error.event, error.error_code, socket.id, error.message, error.field);
+    }
+}
@@ -0,0 +1,462 @@
+use serde_json::json;
+use socketioxide::SocketIo;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::api::response::ApiResponse;
+use crate::config::CONFIG;
+use crate::database::models::{EscrowPayout, EscrowStake, EscrowStatus, GameOutcome, MatchEscrow, WalletDebit};
+use crate::database::store::DataStore;
+use crate::managers::game_modes::GameModeRegistry;
+use crate::managers::parental::ParentalManager;
+use crate::managers::revenue::RevenueManager;
+use crate::managers::room::RoomManager;
+use crate::managers::room_reaper::RoomReaperManager;
+use crate::managers::scheduler::Scheduler;
+use crate::managers::settlement::{MatchSettlement, Verdict};
+use crate::managers::snowflake::Snowflake;
+
+// Settlements that have not finished after this long are picked up again
+const SETTLEMENT_RETRY_SECS: i64 = 60;
+
+// Entry fees of wagered matches. When the first turn of a room with an
+// entry_fee is about to start, the fee is taken from every human player's
+// wallet and held in match_escrows for the room; if anyone cannot pay, the
+// others get theirs back and the match is cancelled. An opening interrupted
+// by a crash is rolled back by recovery. The pot, minus
+// MATCH_RAKE_PERCENT, goes to the winning team once every staked player has
+// reported the same result with progress:update (all draws give the stakes
+// back). Conflicting reports leave the stakes held until the room is closed,
+// which settles them by the forfeit rules of RoomReaperManager; a room closed
+// without a winner refunds them. Bot matches are never wagered.
+pub struct EscrowManager;
+
+impl EscrowManager {
+    fn debit_reference(escrow_id: &str, player_id: &str) -> String {
+        format!("escrow:{}:{}:entry", escrow_id, player_id)
+    }
+
+    fn payout_reference(escrow_id: &str, player_id: &str) -> String {
+        format!("escrow:{}:{}", escrow_id, player_id)
+    }
+
+    fn refunds(escrow: &MatchEscrow) -> Vec<EscrowPayout> {
+        escrow.stakes.iter()
+            .map(|stake| EscrowPayout { player_id: stake.player_id.clone(), amount: stake.amount })
+            .collect()
+    }
+
+    // Take one player's stake; false when they cannot pay
+    async fn debit(data_service: &dyn DataStore, escrow_id: &str, player_id: &str, entry_fee: i64) -> bool {
+        let user = match data_service.get_user_by_id(player_id).await {
+            Ok(Some(user)) => user,
+            Ok(None) => return false,
+            Err(e) => {
+                warn!("⚠️ Failed to look up player {} for the entry fee: {}", player_id, e);
+                return false;
+            }
+        };
+        // Minors without parental controls cannot wager
+        let Ok(daily_limit) = ParentalManager::spend_limit(&user) else {
+            return false;
+        };
+        let reference = Self::debit_reference(escrow_id, player_id);
+        match data_service.debit_wallet(player_id, entry_fee, "match_entry", &reference, daily_limit).await {
+            Ok(WalletDebit::Debited(_)) => true,
+            Ok(_) => false,
+            Err(e) => {
+                warn!("⚠️ Failed to take the entry fee from player {}: {}", player_id, e);
+                false
+            }
+        }
+    }
+
+    // Hold the entry fees of a room about to start its first turn. The escrow
+    // is stored as Opening before any coins move and every debit carries its
+    // escrow_id, so fees taken before a crash are found and refunded by
+    // recovery. False when the match was cancelled because someone could not
+    // pay or the escrow could not be stored.
+    pub async fn open(io: &SocketIo, data_service: &Arc<dyn DataStore>, room_id: &str) -> bool {
+        let Some(room) = RoomManager::get_room(room_id).await else {
+            return true;
+        };
+        let entry_fee = room.config.rules.entry_fee;
+        if entry_fee <= 0 || room.is_bot_match {
+            return true;
+        }
+
+        // Seats belong to authenticated users (gameplay acts for the socket's
+        // user), so every human player_id is a wallet owner
+        let escrow = MatchEscrow {
+            id: None,
+            escrow_id: Snowflake::generate(),
+            room_id: room_id.to_string(),
+            game_type: room.config.game_type.clone(),
+            entry_fee,
+            stakes: room.players.iter()
+                .filter(|p| !p.is_bot)
+                .map(|p| EscrowStake { player_id: p.player_id.clone(), team: p.team, amount: entry_fee })
+                .collect(),
+            status: EscrowStatus::Opening,
+            reports: Default::default(),
+            payouts: Vec::new(),
+            rake: 0,
+            created_at: bson::DateTime::now(),
+            settling_at: None,
+            settled_at: None,
+        };
+        if let Err(e) = data_service.create_escrow(escrow.clone()).await {
+            warn!("⚠️ Failed to store the escrow of room {} - cancelling the match: {}", room_id, e);
+            Self::cancel(io, data_service, &room.config.game_type, room_id, entry_fee, Vec::new()).await;
+            return false;
+        }
+
+        let escrow_id = &escrow.escrow_id;
+        let mut paid = Vec::new();
+        let mut unpaid = Vec::new();
+        for stake in &escrow.stakes {
+            if Self::debit(&**data_service, escrow_id, &stake.player_id, entry_fee).await {
+                paid.push(stake.clone());
+            } else {
+                unpaid.push(stake.player_id.clone());
+            }
+        }
+
+        if unpaid.is_empty() {
+            match data_service.finish_escrow_opening(escrow_id, EscrowStatus::Held, paid, Vec::new()).await {
+                Ok(true) => {
+                    info!("🔐 Holding {} coins for room {} ({} players at {})", entry_fee * escrow.stakes.len() as i64, room_id, escrow.stakes.len(), entry_fee);
+                    return true;
+                }
+                Ok(false) => warn!("⚠️ Escrow {} of room {} was rolled back by recovery - cancelling the match", escrow_id, room_id),
+                Err(e) => {
+                    warn!("⚠️ Failed to hold the escrow of room {} - cancelling the match: {}", room_id, e);
+                    Self::roll_back(&**data_service, &escrow).await;
+                }
+            }
+        } else {
+            Self::roll_back(&**data_service, &escrow).await;
+        }
+        // Refund what was taken here even if the rollback above missed it (a
+        // failed lookup, or recovery rolling back before a debit landed); the
+        // rollback credits with the same references, so nobody is paid twice
+        for stake in &escrow.stakes {
+            if unpaid.contains(&stake.player_id) {
+                continue;
+            }
+            let reference = Self::payout_reference(escrow_id, &stake.player_id);
+            if let Err(e) = data_service.credit_wallet(&stake.player_id, stake.amount, "match_refund", &reference).await {
+                warn!("⚠️ Failed to refund {} coins of escrow {} to {}: {}", stake.amount, escrow_id, stake.player_id, e);
+            }
+        }
+
+        Self::cancel(io, data_service, &room.config.game_type, room_id, entry_fee, unpaid).await;
+        false
+    }
+
+    // Refund the entry fees an opening escrow took: the stakes whose debit is
+    // in the ledger. False when that could not be settled yet.
+    async fn roll_back(data_service: &dyn DataStore, escrow: &MatchEscrow) -> bool {
+        let mut taken = Vec::new();
+        for stake in &escrow.stakes {
+            match data_service.wallet_reference_applied(&Self::debit_reference(&escrow.escrow_id, &stake.player_id)).await {
+                Ok(true) => taken.push(stake.clone()),
+                Ok(false) => {}
+                Err(e) => {
+                    warn!("⚠️ Failed to look up the entry fee of {} for escrow {} - retrying later: {}", stake.player_id, escrow.escrow_id, e);
+                    return false;
+                }
+            }
+        }
+        let refunding = MatchEscrow { stakes: taken, status: EscrowStatus::Refunding, ..escrow.clone() };
+        let refunding = MatchEscrow { payouts: Self::refunds(&refunding), ..refunding };
+        match data_service.finish_escrow_opening(&escrow.escrow_id, EscrowStatus::Refunding, refunding.stakes.clone(), refunding.payouts.clone()).await {
+            Ok(true) => Self::pay(data_service, &refunding).await,
+            Ok(false) => false,
+            Err(e) => {
+                warn!("⚠️ Failed to roll back escrow {} - retrying later: {}", escrow.escrow_id, e);
+                false
+            }
+        }
+    }
+
+    // Call a match off before its first turn
+    async fn cancel(io: &SocketIo, data_service: &Arc<dyn DataStore>, game_type: &str, room_id: &str, entry_fee: i64, unpaid: Vec<String>) {
+        info!("🚫 Cancelled room {}: entry fee of {} unpaid by {:?}", room_id, entry_fee, unpaid);
+        let cancelled = ApiResponse::success("match:cancelled", json!({
+            "room_id": room_id,
+            "reason": "entry_fee_unpaid",
+            "entry_fee": entry_fee,
+            "unpaid": unpaid,
+        }));
+        if let Some(ns) = io.of(GameModeRegistry::for_game_type(game_type).namespace.as_str()) {
+            if let Err(e) = ns.to(room_id.to_string()).emit("match:cancelled", cancelled) {
+                warn!("⚠️ Failed to broadcast match:cancelled to room {}: {}", room_id, e);
+            }
+        }
+        if let Some(room) = RoomManager::remove_room(room_id).await {
+            RoomReaperManager::close(io, &**data_service, room, "cancelled", Vec::new()).await;
+        }
+    }
+
+    // A staked player reported their result; settles the escrow once every
+    // staked player agrees on it
+    pub async fn result_reported(data_service: &dyn DataStore, room_id: &str, player_id: &str, outcome: GameOutcome) {
+        let escrow = match data_service.get_held_escrow(room_id).await {
+            Ok(Some(escrow)) => escrow,
+            Ok(None) => return,
+            Err(e) => {
+                warn!("⚠️ Failed to load the escrow of room {}: {}", room_id, e);
+                return;
+            }
+        };
+        let escrow = match data_service.report_escrow_result(&escrow.escrow_id, player_id, outcome).await {
+            Ok(Some(escrow)) => escrow,
+            Ok(None) => return,
+            Err(e) => {
+                warn!("⚠️ Failed to record the result of {} for the escrow of room {}: {}", player_id, room_id, e);
+                return;
+            }
+        };
+        let verdict = MatchSettlement::verdict(escrow.stakes.iter().map(|stake| (stake.player_id.as_str(), stake.team)), &escrow.reports);
+        match verdict {
+            Verdict::Waiting => {}
+            Verdict::Conflicting => warn!("⚖️ Conflicting results for room {} - stakes stay held until the room closes", room_id),
+            Verdict::Draw => Self::settle(data_service, &escrow, &[]).await,
+            Verdict::Won(team) => {
+                let winners: Vec<&str> = escrow.stakes.iter()
+                    .filter(|stake| stake.team == team)
+                    .map(|stake| stake.player_id.as_str())
+                    .collect();
+                Self::settle(data_service, &escrow, &winners).await;
+            }
+        }
+    }
+
+    // The room is gone: pay `winners`, or refund everyone without any
+    pub async fn room_closed(data_service: &dyn DataStore, room_id: &str, winners: &[&str]) {
+        match data_service.get_held_escrow(room_id).await {
+            Ok(Some(escrow)) => Self::settle(data_service, &escrow, winners).await,
+            Ok(None) => {}
+            Err(e) => warn!("⚠️ Failed to load the escrow of closed room {}: {}", room_id, e),
+        }
+    }
+
+    // Fix the payouts, then credit them. The pot minus the rake is shared
+    // equally by the winners; what does not divide evenly is added to the rake.
+    async fn settle(data_service: &dyn DataStore, escrow: &MatchEscrow, winners: &[&str]) {
+        let pot: i64 = escrow.stakes.iter().map(|stake| stake.amount).sum();
+        let (status, payouts, rake) = if winners.is_empty() {
+            (EscrowStatus::Refunding, Self::refunds(escrow), 0)
+        } else {
+            let rake = (pot as f64 * CONFIG.match_rake_percent / 100.0).floor() as i64;
+            let share = (pot - rake) / winners.len() as i64;
+            let payouts = winners.iter()
+                .map(|winner| EscrowPayout { player_id: winner.to_string(), amount: share })
+                .collect();
+            (EscrowStatus::Releasing, payouts, pot - share * winners.len() as i64)
+        };
+        let settling = MatchEscrow { status, payouts, rake, ..escrow.clone() };
+        match data_service.begin_escrow_settlement(&escrow.escrow_id, status, settling.payouts.clone(), rake).await {
+            Ok(true) => {}
+            Ok(false) => return,
+            Err(e) => {
+                warn!("⚠️ Failed to start settling the escrow of room {}: {}", escrow.room_id, e);
+                return;
+            }
+        }
+        info!("💰 Settling room {}: pot {}, rake {}, payouts {:?}", escrow.room_id, pot, rake,
+              settling.payouts.iter().map(|p| (p.player_id.as_str(), p.amount)).collect::<Vec<_>>());
+        Self::pay(data_service, &settling).await;
+    }
+
+    // Credit every payout of an escrow being settled, record the commission of
+    // a release and mark the settlement done. Credits and the ledger entry are
+    // idempotent, so paying again after an interruption pays nobody twice.
+    async fn pay(data_service: &dyn DataStore, escrow: &MatchEscrow) -> bool {
+        let escrow_id = &escrow.escrow_id;
+        let (reason, done) = match escrow.status {
+            EscrowStatus::Releasing => ("match_winnings", EscrowStatus::Released),
+            _ => ("match_refund", EscrowStatus::Refunded),
+        };
+        for payout in escrow.payouts.iter().filter(|payout| payout.amount > 0) {
+            let reference = Self::payout_reference(escrow_id, &payout.player_id);
+            if let Err(e) = data_service.credit_wallet(&payout.player_id, payout.amount, reason, &reference).await {
+                warn!("⚠️ Failed to pay {} coins of escrow {} to {} - retrying later: {}", payout.amount, escrow_id, payout.player_id, e);
+                return false;
+            }
+        }
+        if escrow.status == EscrowStatus::Releasing {
+            if let Err(e) = RevenueManager::record(data_service, escrow).await {
+                warn!("⚠️ Failed to record the commission of escrow {} - retrying later: {}", escrow_id, e);
+                return false;
+            }
+        }
+        if let Err(e) = data_service.finish_escrow_settlement(escrow_id, done).await {
+            warn!("⚠️ Failed to mark escrow {} settled: {}", escrow_id, e);
+            return false;
+        }
+        true
+    }
+
+    // Finish settlements interrupted by a crash or a failed credit, and roll
+    // back escrows that never finished taking their entry fees
+    pub fn spawn_recovery(data_service: Arc<dyn DataStore>) {
+        let period = Duration::from_secs(SETTLEMENT_RETRY_SECS as u64);
+        Scheduler::every("escrow-recovery", period, move || {
+            let data_service = data_service.clone();
+            async move {
+                let before = chrono::Utc::now() - chrono::Duration::seconds(SETTLEMENT_RETRY_SECS);
+                for escrow in data_service.stale_escrow_openings(before).await? {
+                    if Self::roll_back(&*data_service, &escrow).await {
+                        info!("💰 Rolled back the interrupted escrow opening of room {}", escrow.room_id);
+                    }
+                }
+                for escrow in data_service.unfinished_escrow_settlements(before).await? {
+                    if Self::pay(&*data_service, &escrow).await {
+                        info!("💰 Finished the interrupted settlement of room {}", escrow.room_id);
+                    }
+                }
+                Ok(())
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::memory::InMemoryDataStore;
+    use crate::database::models::UserRegister;
+    use crate::managers::game_config::GameConfigManager;
+    use crate::managers::room::RoomPlayer;
+
+    const ENTRY_FEE: i64 = 30;
+
+    // A user holding `coins`
+    async fn player(data_service: &dyn DataStore, coins: i64) -> String {
+        let user = UserRegister::new(Snowflake::generate(), "escrow-test".to_string(), String::new(), None, 0);
+        let (user_id, _) = data_service.import_user(user).await.unwrap().unwrap();
+        data_service.credit_wallet(&user_id, coins, "test", &format!("test:{}", user_id)).await.unwrap();
+        user_id
+    }
+
+    // A wagered room of `players` on teams 0, 1, ...
+    async fn room(players: &[&str]) -> String {
+        let room_id = Snowflake::generate();
+        let mut config = GameConfigManager::builtin("ludo");
+        config.rules.entry_fee = ENTRY_FEE;
+        let seats = players.iter().enumerate()
+            .map(|(team, player_id)| RoomPlayer::human(player_id, "socket").with_team(team as u8))
+            .collect();
+        RoomManager::create_room(&room_id, seats, Arc::new(config)).await;
+        room_id
+    }
+
+    async fn balance(data_service: &dyn DataStore, user_id: &str) -> i64 {
+        data_service.get_wallet_balance(user_id).await.unwrap()
+    }
+
+    fn rake(pot: i64) -> i64 {
+        (pot as f64 * CONFIG.match_rake_percent / 100.0).floor() as i64
+    }
+
+    #[tokio::test]
+    async fn open_holds_every_stake() {
+        let (_, io) = SocketIo::new_layer();
+        let data_service: Arc<dyn DataStore> = Arc::new(InMemoryDataStore::new());
+        let alice = player(&*data_service, 100).await;
+        let bob = player(&*data_service, 100).await;
+        let room_id = room(&[&alice, &bob]).await;
+
+        assert!(EscrowManager::open(&io, &data_service, &room_id).await);
+
+        let escrow = data_service.get_held_escrow(&room_id).await.unwrap().expect("escrow not held");
+        assert_eq!(escrow.stakes.len(), 2);
+        assert_eq!(balance(&*data_service, &alice).await, 100 - ENTRY_FEE);
+        assert_eq!(balance(&*data_service, &bob).await, 100 - ENTRY_FEE);
+    }
+
+    #[tokio::test]
+    async fn open_refunds_the_others_when_someone_cannot_pay() {
+        let (_, io) = SocketIo::new_layer();
+        let data_service: Arc<dyn DataStore> = Arc::new(InMemoryDataStore::new());
+        let alice = player(&*data_service, 100).await;
+        let bob = player(&*data_service, ENTRY_FEE - 1).await;
+        let room_id = room(&[&alice, &bob]).await;
+
+        assert!(!EscrowManager::open(&io, &data_service, &room_id).await);
+
+        // Refunded once, although both the rollback and open credit it
+        assert_eq!(balance(&*data_service, &alice).await, 100);
+        assert_eq!(balance(&*data_service, &bob).await, ENTRY_FEE - 1);
+        assert!(data_service.get_held_escrow(&room_id).await.unwrap().is_none());
+        assert!(RoomManager::get_room(&room_id).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn agreeing_reports_pay_the_winner() {
+        let (_, io) = SocketIo::new_layer();
+        let data_service: Arc<dyn DataStore> = Arc::new(InMemoryDataStore::new());
+        let alice = player(&*data_service, 100).await;
+        let bob = player(&*data_service, 100).await;
+        let room_id = room(&[&alice, &bob]).await;
+        assert!(EscrowManager::open(&io, &data_service, &room_id).await);
+
+        EscrowManager::result_reported(&*data_service, &room_id, &alice, GameOutcome::Win).await;
+        assert!(data_service.get_held_escrow(&room_id).await.unwrap().is_some());
+        EscrowManager::result_reported(&*data_service, &room_id, &bob, GameOutcome::Loss).await;
+
+        let pot = 2 * ENTRY_FEE;
+        assert!(data_service.get_held_escrow(&room_id).await.unwrap().is_none());
+        assert_eq!(balance(&*data_service, &alice).await, 100 - ENTRY_FEE + pot - rake(pot));
+        assert_eq!(balance(&*data_service, &bob).await, 100 - ENTRY_FEE);
+    }
+
+    #[tokio::test]
+    async fn conflicting_reports_hold_the_stakes_until_the_room_closes() {
+        let (_, io) = SocketIo::new_layer();
+        let data_service: Arc<dyn DataStore> = Arc::new(InMemoryDataStore::new());
+        let alice = player(&*data_service, 100).await;
+        let bob = player(&*data_service, 100).await;
+        let room_id = room(&[&alice, &bob]).await;
+        assert!(EscrowManager::open(&io, &data_service, &room_id).await);
+
+        EscrowManager::result_reported(&*data_service, &room_id, &alice, GameOutcome::Win).await;
+        EscrowManager::result_reported(&*data_service, &room_id, &bob, GameOutcome::Win).await;
+        assert!(data_service.get_held_escrow(&room_id).await.unwrap().is_some());
+
+        // Closed without a winner: everyone gets their stake back
+        EscrowManager::room_closed(&*data_service, &room_id, &[]).await;
+        assert!(data_service.get_held_escrow(&room_id).await.unwrap().is_none());
+        assert_eq!(balance(&*data_service, &alice).await, 100);
+        assert_eq!(balance(&*data_service, &bob).await, 100);
+    }
+
+    #[tokio::test]
+    async fn paying_again_pays_nobody_twice() {
+        let (_, io) = SocketIo::new_layer();
+        let data_service: Arc<dyn DataStore> = Arc::new(InMemoryDataStore::new());
+        let alice = player(&*data_service, 100).await;
+        let bob = player(&*data_service, 100).await;
+        let room_id = room(&[&alice, &bob]).await;
+        assert!(EscrowManager::open(&io, &data_service, &room_id).await);
+
+        // A settlement interrupted after its payouts were fixed, paid by
+        // recovery and then again
+        let escrow = data_service.get_held_escrow(&room_id).await.unwrap().unwrap();
+        let pot = 2 * ENTRY_FEE;
+        let payouts = vec![EscrowPayout { player_id: alice.clone(), amount: pot - rake(pot) }];
+        assert!(data_service.begin_escrow_settlement(&escrow.escrow_id, EscrowStatus::Releasing, payouts.clone(), rake(pot)).await.unwrap());
+        let releasing = MatchEscrow { status: EscrowStatus::Releasing, payouts, rake: rake(pot), ..escrow };
+        assert!(EscrowManager::pay(&*data_service, &releasing).await);
+        assert!(EscrowManager::pay(&*data_service, &releasing).await);
+
+        assert_eq!(balance(&*data_service, &alice).await, 100 - ENTRY_FEE + pot - rake(pot));
+        assert_eq!(balance(&*data_service, &bob).await, 100 - ENTRY_FEE);
+        let day = RevenueManager::date_of(chrono::Utc::now());
+        let ledger = data_service.revenue_entries(&day, None, 0, 10).await.unwrap();
+        assert_eq!(ledger.iter().filter(|entry| entry.escrow_id == releasing.escrow_id).count(), 1);
+    }
+}
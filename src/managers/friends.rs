@@ -0,0 +1,192 @@
+use socketioxide::extract::SocketRef;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tracing::{info, warn, error};
+
+use crate::api::response::{ApiError, ApiResponse};
+use crate::database::models::DeepLink;
+use crate::database::store::DataStore;
+use crate::managers::auth_guard::AuthGuard;
+use crate::managers::chaos::FaultInjector;
+use crate::managers::error_responder::ErrorResponder;
+use crate::managers::notifications::{NotificationCategory, NotificationManager};
+use crate::managers::validation::ValidationManager;
+
+// Friends list. Adding a user sends them a request; adding back accepts it.
+// Two users are friends once both have added each other, and either can end it.
+pub struct FriendManager;
+
+impl FriendManager {
+    // Friends list on the main namespace:
+    //   friend:add    { mobile_no, session_token, friend_user_id } -> friend:added
+    //   friend:remove { mobile_no, session_token, friend_user_id } -> friend:removed
+    //   friend:list   { mobile_no, session_token }                 -> friend:data
+    pub fn register_friend_events(socket: &SocketRef, data_service: Arc<dyn DataStore>) {
+        let ds = data_service.clone();
+        AuthGuard::require_session(socket, "friend:add", data_service.clone(), move |socket, data, auth| {
+            let ds = ds.clone();
+            async move {
+                info!("🤝 Received friend:add from {}: {:?}", socket.id, data["friend_user_id"]);
+                if let Err(error_details) = ValidationManager::validate_friend_data(&data) {
+                    info!("❌ Friend validation failed for socket {}: {:?}", socket.id, error_details);
+                    ErrorResponder::send(&socket, &*ds, error_details).await;
+                    return;
+                }
+                let user = auth.user;
+                let friend_user_id = data["friend_user_id"].as_str().unwrap_or_default();
+                if friend_user_id == user.user_id {
+                    let error = ApiError::new("FRIEND_SELF", "FRIEND_ERROR", "friend_user_id", "You cannot add yourself as a friend");
+                    ErrorResponder::send(&socket, &*ds, error).await;
+                    return;
+                }
+
+                match ds.get_user_by_id(friend_user_id).await {
+                    Ok(Some(friend)) if friend.is_active && !friend.is_banned => {}
+                    Ok(_) => {
+                        let error = ApiError::new("FRIEND_NOT_FOUND", "FRIEND_ERROR", "friend_user_id", "No such user")
+                            .with_details(json!({"friend_user_id": friend_user_id}));
+                        ErrorResponder::send(&socket, &*ds, error).await;
+                        return;
+                    }
+                    Err(e) => {
+                        error!("❌ Failed to look up user {}: {}", friend_user_id, e);
+                        ErrorResponder::send(&socket, &*ds, ApiError::system("FRIEND_UPDATE_FAILED", "friend_user_id", "Failed to add friend", &e)).await;
+                        return;
+                    }
+                }
+
+                // A user who blocked the caller looks like any unknown user
+                match ds.list_blocks(&user.user_id).await {
+                    Ok(blocks) => {
+                        if blocks.iter().any(|b| b.user_id == user.user_id && b.blocked_user_id == friend_user_id) {
+                            let error = ApiError::new("FRIEND_BLOCKED", "FRIEND_ERROR", "friend_user_id", "Unblock this user before adding them as a friend");
+                            ErrorResponder::send(&socket, &*ds, error).await;
+                            return;
+                        }
+                        if blocks.iter().any(|b| b.user_id == friend_user_id && b.blocked_user_id == user.user_id) {
+                            let error = ApiError::new("FRIEND_NOT_FOUND", "FRIEND_ERROR", "friend_user_id", "No such user")
+                                .with_details(json!({"friend_user_id": friend_user_id}));
+                            ErrorResponder::send(&socket, &*ds, error).await;
+                            return;
+                        }
+                    }
+                    Err(e) => {
+                        error!("❌ Failed to load blocks of user {}: {}", user.user_id, e);
+                        ErrorResponder::send(&socket, &*ds, ApiError::system("FRIEND_UPDATE_FAILED", "friend_user_id", "Failed to add friend", &e)).await;
+                        return;
+                    }
+                }
+
+                let result = match ds.add_friend(&user.user_id, friend_user_id).await {
+                    Ok(added) => ds.are_friends(&user.user_id, friend_user_id).await.map(|friends| (added, friends)),
+                    Err(e) => Err(e),
+                };
+                let (added, friends) = match result {
+                    Ok(result) => result,
+                    Err(e) => {
+                        error!("❌ Failed to add friend {} for user {}: {}", friend_user_id, user.user_id, e);
+                        ErrorResponder::send(&socket, &*ds, ApiError::system("FRIEND_UPDATE_FAILED", "friend_user_id", "Failed to add friend", &e)).await;
+                        return;
+                    }
+                };
+
+                // Only a new request or acceptance is worth a notification
+                if added {
+                    let name = user.full_name.as_deref().unwrap_or("A player");
+                    let notification_type = if friends { "friend_accepted" } else { "friend_request" };
+                    let data = json!({ "user_id": user.user_id, "friends": friends });
+                    if let Err(e) = NotificationManager::dispatch(&*ds, friend_user_id, NotificationCategory::Social, notification_type, json!({ "name": name }), data, Some(DeepLink::new("open_profile", json!({ "user_id": user.user_id })))).await {
+                        warn!("⚠️ Failed to notify user {} of friend request: {}", friend_user_id, e);
+                    }
+                }
+
+                let response = ApiResponse::success("friend:added", json!({
+                    "friend_user_id": friend_user_id,
+                    "status": if friends { "friends" } else { "requested" }
+                })).for_socket(socket.id);
+                match FaultInjector::emit(&socket, "friend:added", response).await {
+                    Ok(_) => info!("✅ User {} added friend {} (mutual: {})", user.user_id, friend_user_id, friends),
+                    Err(e) => warn!("⚠️ Failed to emit friend:added to socket {}: {}", socket.id, e),
+                }
+            }
+        });
+
+        let ds = data_service.clone();
+        AuthGuard::require_session(socket, "friend:remove", data_service.clone(), move |socket, data, auth| {
+            let ds = ds.clone();
+            async move {
+                info!("🤝 Received friend:remove from {}: {:?}", socket.id, data["friend_user_id"]);
+                if let Err(error_details) = ValidationManager::validate_friend_data(&data) {
+                    info!("❌ Friend validation failed for socket {}: {:?}", socket.id, error_details);
+                    ErrorResponder::send(&socket, &*ds, error_details).await;
+                    return;
+                }
+                let user = auth.user;
+                let friend_user_id = data["friend_user_id"].as_str().unwrap_or_default();
+                let removed = match ds.remove_friend(&user.user_id, friend_user_id).await {
+                    Ok(removed) => removed,
+                    Err(e) => {
+                        error!("❌ Failed to remove friend {} for user {}: {}", friend_user_id, user.user_id, e);
+                        ErrorResponder::send(&socket, &*ds, ApiError::system("FRIEND_UPDATE_FAILED", "friend_user_id", "Failed to remove friend", &e)).await;
+                        return;
+                    }
+                };
+
+                let response = ApiResponse::success("friend:removed", json!({
+                    "friend_user_id": friend_user_id,
+                    "removed": removed
+                })).for_socket(socket.id);
+                if let Err(e) = FaultInjector::emit(&socket, "friend:removed", response).await {
+                    warn!("⚠️ Failed to emit friend:removed to socket {}: {}", socket.id, e);
+                }
+            }
+        });
+
+        let ds = data_service.clone();
+        AuthGuard::require_session(socket, "friend:list", data_service, move |socket, _data, auth| {
+            let ds = ds.clone();
+            async move {
+                info!("🤝 Received friend:list from {}", socket.id);
+                let user = auth.user;
+                let friendships = match ds.list_friendships(&user.user_id).await {
+                    Ok(friendships) => friendships,
+                    Err(e) => {
+                        error!("❌ Failed to list friends of user {}: {}", user.user_id, e);
+                        ErrorResponder::send(&socket, &*ds, ApiError::system("FRIEND_FETCH_FAILED", "friends", "Failed to load friends", &e)).await;
+                        return;
+                    }
+                };
+
+                let rfc3339 = |date: bson::DateTime| date.try_to_rfc3339_string().unwrap_or_default();
+                let added: HashMap<&str, bson::DateTime> = friendships.iter().filter(|f| f.user_id == user.user_id).map(|f| (f.friend_user_id.as_str(), f.created_at)).collect();
+                let added_me: HashSet<&str> = friendships.iter().filter(|f| f.friend_user_id == user.user_id).map(|f| f.user_id.as_str()).collect();
+                let mut friends = Vec::new();
+                let mut incoming = Vec::new();
+                let mut outgoing = Vec::new();
+                for friendship in &friendships {
+                    if friendship.user_id == user.user_id {
+                        let other = friendship.friend_user_id.as_str();
+                        if !added_me.contains(other) {
+                            outgoing.push(json!({ "user_id": other, "requested_at": rfc3339(friendship.created_at) }));
+                        }
+                    } else if let Some(&added_at) = added.get(friendship.user_id.as_str()) {
+                        // Friends since the later of the two adds
+                        friends.push(json!({ "user_id": friendship.user_id, "since": rfc3339(added_at.max(friendship.created_at)) }));
+                    } else {
+                        incoming.push(json!({ "user_id": friendship.user_id, "requested_at": rfc3339(friendship.created_at) }));
+                    }
+                }
+
+                let response = ApiResponse::success("friend:data", json!({
+                    "friends": friends,
+                    "incoming": incoming,
+                    "outgoing": outgoing
+                })).for_socket(socket.id);
+                if let Err(e) = FaultInjector::emit(&socket, "friend:data", response).await {
+                    warn!("⚠️ Failed to emit friend:data to socket {}: {}", socket.id, e);
+                }
+            }
+        });
+    }
+}
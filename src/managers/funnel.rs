@@ -0,0 +1,167 @@
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use socketioxide::SocketIo;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::api::response::ApiResponse;
+use crate::config::CONFIG;
+use crate::managers::scheduler::Scheduler;
+use crate::managers::tenant::TenantManager;
+
+// Steps of the sign-in flow on the main namespace, in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FunnelStage {
+    Connected,      // connect_response sent
+    DeviceInfo,     // device:info accepted
+    Login,          // login:success (OTP sent)
+    OtpVerified,    // otp:verified
+    ProfileSet,     // profile:set
+}
+
+const STAGES: [FunnelStage; 5] = [
+    FunnelStage::Connected,
+    FunnelStage::DeviceInfo,
+    FunnelStage::Login,
+    FunnelStage::OtpVerified,
+    FunnelStage::ProfileSet,
+];
+
+impl FunnelStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FunnelStage::Connected => "connected",
+            FunnelStage::DeviceInfo => "device_info",
+            FunnelStage::Login => "login",
+            FunnelStage::OtpVerified => "otp_verified",
+            FunnelStage::ProfileSet => "profile_set",
+        }
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+// One tenant's funnel on this server
+struct Funnel {
+    since: DateTime<Utc>,
+    counts: [u64; STAGES.len()],
+    sockets: HashMap<String, u8>,   // socket_id -> bit per stage already counted
+    changed: bool,                  // Counted something since the last update went out
+}
+
+impl Funnel {
+    fn new() -> Self {
+        Self { since: Utc::now(), counts: [0; STAGES.len()], sockets: HashMap::new(), changed: false }
+    }
+}
+
+// Funnels by tenant_id
+static FUNNELS: Lazy<Mutex<HashMap<String, Funnel>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// Live sign-in funnel for the operations dashboard: how many connections
+// reached each step, counted once per socket as it happens. Dashboards on
+// `/admin` subscribe with funnel:subscribe and get funnel:update at most every
+// FUNNEL_BROADCAST_INTERVAL_MS while the numbers move. Counts are kept in
+// memory per server since it started, or since the last funnel:reset.
+pub struct FunnelManager;
+
+impl FunnelManager {
+    // Room of the /admin namespace that gets a tenant's updates
+    pub fn room(tenant_id: &str) -> String {
+        format!("funnel:{}", tenant_id)
+    }
+
+    // Count the socket at this step of the current tenant's funnel, once
+    pub fn record(socket_id: &str, stage: FunnelStage) {
+        let tenant_id = TenantManager::current().tenant_id.clone();
+        let mut funnels = FUNNELS.lock().unwrap_or_else(|e| e.into_inner());
+        let funnel = funnels.entry(tenant_id).or_insert_with(Funnel::new);
+        let bit = 1u8 << stage.index();
+        let seen = funnel.sockets.entry(socket_id.to_string()).or_default();
+        if *seen & bit == 0 {
+            *seen |= bit;
+            funnel.counts[stage.index()] += 1;
+            funnel.changed = true;
+        }
+    }
+
+    // Forget which steps a disconnected socket was counted at; its counts stay
+    pub fn remove_socket(socket_id: &str) {
+        for funnel in FUNNELS.lock().unwrap_or_else(|e| e.into_inner()).values_mut() {
+            funnel.sockets.remove(socket_id);
+        }
+    }
+
+    // Start counting the current tenant's funnel from zero
+    pub fn reset() {
+        let tenant_id = TenantManager::current().tenant_id.clone();
+        let mut funnels = FUNNELS.lock().unwrap_or_else(|e| e.into_inner());
+        let mut funnel = Funnel::new();
+        funnel.changed = true;
+        funnels.insert(tenant_id, funnel);
+    }
+
+    fn view(tenant_id: &str, funnel: Option<&Funnel>) -> Value {
+        let counts = funnel.map_or([0; STAGES.len()], |f| f.counts);
+        let stages: Vec<Value> = STAGES.iter().map(|stage| {
+            let count = counts[stage.index()];
+            let (from_previous, from_start) = match stage.index() {
+                0 => (None, None),
+                i => (Self::rate(count, counts[i - 1]), Self::rate(count, counts[0])),
+            };
+            json!({
+                "stage": stage.as_str(),
+                "count": count,
+                "conversion_from_previous": from_previous,
+                "conversion_from_start": from_start,
+            })
+        }).collect();
+        json!({
+            "tenant_id": tenant_id,
+            "since": funnel.map_or_else(Utc::now, |f| f.since).to_rfc3339(),
+            "open_sockets": funnel.map_or(0, |f| f.sockets.len()),
+            "stages": stages,
+        })
+    }
+
+    fn rate(count: u64, of: u64) -> Option<f64> {
+        (of > 0).then(|| (count as f64 / of as f64 * 1000.0).round() / 1000.0)
+    }
+
+    // The current tenant's funnel as sent in funnel:update
+    pub fn snapshot() -> Value {
+        let tenant_id = TenantManager::current().tenant_id.clone();
+        let funnels = FUNNELS.lock().unwrap_or_else(|e| e.into_inner());
+        Self::view(&tenant_id, funnels.get(&tenant_id))
+    }
+
+    // Send funnel:update to the subscribers of every funnel that moved
+    pub fn spawn_broadcaster(io: SocketIo) {
+        let period = Duration::from_millis(CONFIG.funnel_broadcast_interval_ms);
+        Scheduler::every_server_wide("funnel-broadcast", period, move || {
+            let io = io.clone();
+            async move {
+                let updates: Vec<(String, Value)> = FUNNELS.lock().unwrap_or_else(|e| e.into_inner())
+                    .iter_mut()
+                    .filter(|(_, funnel)| funnel.changed)
+                    .map(|(tenant_id, funnel)| {
+                        funnel.changed = false;
+                        (tenant_id.clone(), Self::view(tenant_id, Some(funnel)))
+                    })
+                    .collect();
+                for (tenant_id, update) in updates {
+                    let Some(ns) = io.of("/admin") else { break };
+                    if let Err(e) = ns.to(Self::room(&tenant_id)).emit("funnel:update", ApiResponse::success("funnel:update", update)) {
+                        warn!("⚠️ Failed to broadcast funnel:update for tenant {}: {}", tenant_id, e);
+                    }
+                }
+                Ok(())
+            }
+        });
+    }
+}
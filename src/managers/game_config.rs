@@ -0,0 +1,230 @@
+use futures_util::StreamExt;
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::config::CONFIG;
+use crate::database::models::{GameConfig, GameRules};
+use crate::database::store::DataStore;
+use crate::managers::tenant::TenantManager;
+
+// Game type of the default game mode
+pub const DEFAULT_GAME_TYPE: &str = "classic";
+// updated_by of the built-in rules
+const BUILTIN: &str = "builtin";
+
+// Built-in rules, used for game types with no stored config
+const DEFAULT_TURN_TIMEOUT_SECONDS: i64 = 30;
+const DEFAULT_STALL_TIMEOUT_THRESHOLD: u32 = 3;
+const DEFAULT_ROOM_CAPACITY: u32 = 2;
+const DEFAULT_BOT_FALLBACK_SECONDS: u64 = 20;
+
+// Limits on rules set through the admin API
+pub const MAX_ENTRY_FEE: i64 = 1_000_000;
+const TURN_TIMEOUT_RANGE: (i64, i64) = (5, 600);
+const STALL_THRESHOLD_RANGE: (u32, u32) = (1, 20);
+const ROOM_CAPACITY_RANGE: (u32, u32) = (2, 8);
+const MAX_BOT_FALLBACK_SECONDS: u64 = 600;
+const MAX_GAME_TYPE_LENGTH: usize = 32;
+
+// Latest live version per game type
+type LiveConfigs = HashMap<String, Arc<GameConfig>>;
+
+// Live configs of each tenant, by tenant id
+static CONFIGS: Lazy<RwLock<HashMap<String, LiveConfigs>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+// Game rules (entry fee, timers, room size, board parameters) per game type,
+// stored as versions in `game_configs` and edited through the admin API.
+// Every server keeps the latest versions in memory and reloads them when the
+// collection changes: through a MongoDB change stream where the deployment
+// has them, otherwise every GAME_CONFIG_POLL_SECS. Each tenant has its own
+// configs.
+pub struct GameConfigManager;
+
+impl GameConfigManager {
+    fn env_or<T: std::str::FromStr>(key: &str, default: T) -> T {
+        std::env::var(key).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+    }
+
+    // Rules of a game type with no stored config. The env vars are the ones
+    // that set these before game_configs existed.
+    pub fn builtin(game_type: &str) -> GameConfig {
+        GameConfig {
+            id: None,
+            game_type: game_type.to_string(),
+            version: 0,
+            rules: GameRules {
+                entry_fee: 0,
+                turn_timeout_secs: Self::env_or("TURN_TIMEOUT_SECONDS", DEFAULT_TURN_TIMEOUT_SECONDS),
+                stall_timeout_threshold: DEFAULT_STALL_TIMEOUT_THRESHOLD,
+                room_capacity: DEFAULT_ROOM_CAPACITY,
+                bot_fallback_secs: Self::env_or("MATCHMAKING_BOT_FALLBACK_SECONDS", DEFAULT_BOT_FALLBACK_SECONDS),
+                board: json!({}),
+            },
+            retired: false,
+            updated_by: BUILTIN.to_string(),
+            created_at: bson::DateTime::now(),
+        }
+    }
+
+    // The rules new rooms of this game type are created with, for the current tenant
+    pub fn current(game_type: &str) -> Arc<GameConfig> {
+        let tenant_id = &TenantManager::current().tenant_id;
+        let cached = CONFIGS.read().unwrap_or_else(|e| e.into_inner())
+            .get(tenant_id).and_then(|configs| configs.get(game_type)).cloned();
+        cached.unwrap_or_else(|| Arc::new(Self::builtin(game_type)))
+    }
+
+    // Replace the current tenant's cache with its latest stored versions;
+    // returns how many game types have one
+    pub async fn reload(data_service: &dyn DataStore) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let mut latest: LiveConfigs = HashMap::new();
+        // Newest version of each game type comes first
+        for config in data_service.list_game_configs(None).await? {
+            if !latest.contains_key(&config.game_type) {
+                latest.insert(config.game_type.clone(), Arc::new(config));
+            }
+        }
+        latest.retain(|_, config| !config.retired);
+        let count = latest.len();
+        let tenant_id = &TenantManager::current().tenant_id;
+        let mut configs = CONFIGS.write().unwrap_or_else(|e| e.into_inner());
+        let configs = configs.entry(tenant_id.clone()).or_default();
+        for (game_type, config) in &latest {
+            if configs.get(game_type).is_none_or(|cached| cached.version != config.version) {
+                info!("🎛️ Game config {} of tenant {} is now at version {}", game_type, tenant_id, config.version);
+            }
+        }
+        *configs = latest;
+        Ok(count)
+    }
+
+    async fn reload_logged(data_service: &dyn DataStore) {
+        if let Err(e) = Self::reload(data_service).await {
+            warn!("⚠️ Failed to reload game configs - keeping the cached ones: {}", e);
+        }
+    }
+
+    // Load every tenant's configs before the server takes connections, then
+    // keep them current for as long as it runs
+    pub async fn spawn_hot_reload(data_service: Arc<dyn DataStore>) {
+        for tenant in TenantManager::tenants() {
+            TenantManager::scope(tenant, Self::watch(data_service.clone())).await;
+        }
+    }
+
+    async fn watch(data_service: Arc<dyn DataStore>) {
+        Self::reload_logged(&*data_service).await;
+        let poll = Duration::from_secs(CONFIG.game_config_poll_secs);
+        TenantManager::spawn(async move {
+            let mut polling = false;
+            loop {
+                // Open the stream before loading so no change falls in between
+                let changes = data_service.watch_game_configs().await;
+                Self::reload_logged(&*data_service).await;
+                match changes {
+                    Ok(Some(mut changes)) => {
+                        info!("👀 Watching game_configs of tenant {} for changes", TenantManager::current().tenant_id);
+                        polling = false;
+                        while let Some(change) = changes.next().await {
+                            if let Err(e) = change {
+                                warn!("⚠️ game_configs change stream failed: {}", e);
+                                break;
+                            }
+                            Self::reload_logged(&*data_service).await;
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        if !polling {
+                            warn!("⚠️ No change stream on game_configs - polling every {:?}: {}", poll, e);
+                            polling = true;
+                        }
+                    }
+                }
+                tokio::time::sleep(poll).await;
+            }
+        });
+    }
+
+    // Store the next version of a game type's rules and use it right away on
+    // this server; the others pick it up from the change stream or next poll.
+    // Returns None if another operator stored a version at the same time.
+    pub async fn publish(data_service: &dyn DataStore, game_type: &str, rules: GameRules, retired: bool, operator_id: &str) -> Result<Option<GameConfig>, Box<dyn std::error::Error + Send + Sync>> {
+        let latest = data_service.list_game_configs(Some(game_type)).await?.into_iter().next();
+        let config = GameConfig {
+            id: None,
+            game_type: game_type.to_string(),
+            version: latest.map_or(1, |latest| latest.version + 1),
+            rules,
+            retired,
+            updated_by: operator_id.to_string(),
+            created_at: bson::DateTime::now(),
+        };
+        if !data_service.insert_game_config(config.clone()).await? {
+            return Ok(None);
+        }
+        if let Err(e) = Self::reload(data_service).await {
+            error!("❌ Stored game config {} v{} but failed to reload: {}", game_type, config.version, e);
+        }
+        Ok(Some(config))
+    }
+
+    pub fn valid_game_type(game_type: &str) -> bool {
+        !game_type.is_empty() && game_type.len() <= MAX_GAME_TYPE_LENGTH
+            && game_type.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+    }
+
+    // Apply the fields given in an admin update to `rules`. Returns the
+    // offending field and why it was refused.
+    pub fn merge_rules(mut rules: GameRules, update: &Value) -> Result<GameRules, (&'static str, String)> {
+        let Some(update) = update.as_object() else {
+            return Err(("rules", "body must be a JSON object".to_string()));
+        };
+        fn int(update: &serde_json::Map<String, Value>, field: &'static str, min: i64, max: i64) -> Result<Option<i64>, (&'static str, String)> {
+            match update.get(field) {
+                None => Ok(None),
+                Some(value) => value.as_i64()
+                    .filter(|v| (min..=max).contains(v))
+                    .map(Some)
+                    .ok_or((field, format!("{} must be an integer from {} to {}", field, min, max))),
+            }
+        }
+        if let Some(fee) = int(update, "entry_fee", 0, MAX_ENTRY_FEE)? {
+            rules.entry_fee = fee;
+        }
+        if let Some(secs) = int(update, "turn_timeout_secs", TURN_TIMEOUT_RANGE.0, TURN_TIMEOUT_RANGE.1)? {
+            rules.turn_timeout_secs = secs;
+        }
+        if let Some(threshold) = int(update, "stall_timeout_threshold", STALL_THRESHOLD_RANGE.0 as i64, STALL_THRESHOLD_RANGE.1 as i64)? {
+            rules.stall_timeout_threshold = threshold as u32;
+        }
+        if let Some(capacity) = int(update, "room_capacity", ROOM_CAPACITY_RANGE.0 as i64, ROOM_CAPACITY_RANGE.1 as i64)? {
+            rules.room_capacity = capacity as u32;
+        }
+        if let Some(secs) = int(update, "bot_fallback_secs", 0, MAX_BOT_FALLBACK_SECONDS as i64)? {
+            rules.bot_fallback_secs = secs as u64;
+        }
+        if let Some(board) = update.get("board") {
+            if !board.is_object() {
+                return Err(("board", "board must be a JSON object".to_string()));
+            }
+            rules.board = board.clone();
+        }
+        Ok(rules)
+    }
+
+    pub fn config_view(config: &GameConfig) -> Value {
+        json!({
+            "game_type": config.game_type,
+            "version": config.version,
+            "rules": config.rules,
+            "retired": config.retired,
+            "updated_by": config.updated_by,
+            "created_at": config.created_at.try_to_rfc3339_string().unwrap_or_default()
+        })
+    }
+}
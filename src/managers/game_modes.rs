@@ -0,0 +1,220 @@
+use once_cell::sync::{Lazy, OnceCell};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use tracing::{error, info, warn};
+
+use crate::managers::bot::BotPlayer;
+use crate::managers::game_config::{GameConfigManager, DEFAULT_GAME_TYPE};
+
+// Namespace of the default mode, and where rooms of unknown game types are broadcast
+pub const DEFAULT_NAMESPACE: &str = "/gameplay";
+// Namespaces a mode cannot take
+const RESERVED_NAMESPACES: [&str; 2] = ["/", "/admin"];
+
+// Matchmaking defaults for modes that leave them out
+const DEFAULT_MAX_LATENCY_GAP_MS: u64 = 150;
+const DEFAULT_MAX_PARTY_SIZE: usize = 4;
+const DEFAULT_REGION_WAIT_SECS: u64 = 10;
+
+// Game-specific rules of a mode: which moves are legal and what bots play.
+// Implementations are registered by name with GameModeRegistry::register_rules
+// and picked by the `rules` field of a mode in GAME_MODES.
+pub trait GameMode: Send + Sync {
+    // Checks the `action` of a player_action; the error is sent back to the player
+    fn validate_action(&self, action: &Value) -> Result<(), String>;
+
+    // Move a bot plays on its turn
+    fn bot_action(&self, turn_number: u32) -> Value;
+
+    // Points an accepted action scores, announced with the player_action
+    // broadcast. None for modes without per-move scoring.
+    fn score_action(&self, _action: &Value) -> Option<i64> {
+        None
+    }
+}
+
+// The original turn loop: any action is accepted and bots always move
+pub struct ClassicRules;
+
+impl GameMode for ClassicRules {
+    fn validate_action(&self, _action: &Value) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn bot_action(&self, turn_number: u32) -> Value {
+        BotPlayer::choose_action(turn_number)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MatchmakingParams {
+    #[serde(default = "default_max_latency_gap_ms")]
+    pub max_latency_gap_ms: u64,      // Largest difference in average RTT allowed between two paired teams
+    #[serde(default = "default_max_party_size")]
+    pub max_party_size: usize,        // Largest party that can queue together as one team
+    #[serde(default = "default_region_wait_secs")]
+    pub region_wait_secs: u64,        // A team waits this long for a same-region opponent before taking any region
+}
+
+impl Default for MatchmakingParams {
+    fn default() -> Self {
+        Self {
+            max_latency_gap_ms: default_max_latency_gap_ms(),
+            max_party_size: default_max_party_size(),
+            region_wait_secs: default_region_wait_secs(),
+        }
+    }
+}
+
+fn default_max_latency_gap_ms() -> u64 {
+    std::env::var("MATCHMAKING_MAX_LATENCY_GAP_MS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_MAX_LATENCY_GAP_MS)
+}
+
+fn default_region_wait_secs() -> u64 {
+    std::env::var("MATCHMAKING_REGION_WAIT_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_REGION_WAIT_SECS)
+}
+
+fn default_max_party_size() -> usize {
+    DEFAULT_MAX_PARTY_SIZE
+}
+
+// One entry of GAME_MODES
+#[derive(Debug, Clone, Deserialize)]
+struct GameModeConfig {
+    game_type: String,
+    namespace: String,
+    rules: String,
+    #[serde(default)]
+    matchmaking: MatchmakingParams,
+}
+
+// A playable game mode: its own namespace, rooms, queue and game config
+pub struct RegisteredMode {
+    pub game_type: String,            // Key of the mode's rules in game_configs
+    pub namespace: String,            // Socket.IO namespace its players connect to
+    pub rules_name: String,
+    pub rules: Arc<dyn GameMode>,
+    pub matchmaking: MatchmakingParams,
+}
+
+// Rule modules by name
+static RULES: Lazy<RwLock<HashMap<String, Arc<dyn GameMode>>>> = Lazy::new(|| {
+    let mut rules: HashMap<String, Arc<dyn GameMode>> = HashMap::new();
+    rules.insert("classic".to_string(), Arc::new(ClassicRules));
+    RwLock::new(rules)
+});
+
+// Modes read from GAME_MODES, fixed once the gameplay namespaces are registered
+static MODES: OnceCell<Vec<Arc<RegisteredMode>>> = OnceCell::new();
+
+// Game modes declared in configuration. GAME_MODES holds a JSON array of
+// {"game_type", "namespace", "rules", "matchmaking"} entries; each mode gets
+// the gameplay events on its namespace, its own matchmaking queue and the
+// rules stored under its game_type. Without GAME_MODES there is one mode,
+// `classic` on /gameplay.
+pub struct GameModeRegistry;
+
+impl GameModeRegistry {
+    // Make a rule module available to GAME_MODES. Must run before the
+    // gameplay namespaces are registered.
+    #[cfg_attr(not(feature = "wasm-plugins"), allow(dead_code))]
+    pub fn register_rules(name: &str, rules: Arc<dyn GameMode>) {
+        if MODES.get().is_some() {
+            warn!("⚠️ Rule module {} registered after game modes were loaded - it cannot be used until restart", name);
+        }
+        RULES.write().unwrap_or_else(|e| e.into_inner()).insert(name.to_string(), rules);
+    }
+
+    fn default_modes() -> Vec<GameModeConfig> {
+        vec![GameModeConfig {
+            game_type: DEFAULT_GAME_TYPE.to_string(),
+            namespace: DEFAULT_NAMESPACE.to_string(),
+            rules: "classic".to_string(),
+            matchmaking: MatchmakingParams::default(),
+        }]
+    }
+
+    fn load() -> Vec<Arc<RegisteredMode>> {
+        let configs = match std::env::var("GAME_MODES").ok().filter(|v| !v.trim().is_empty()) {
+            Some(raw) => match serde_json::from_str::<Vec<GameModeConfig>>(&raw) {
+                Ok(configs) => configs,
+                Err(e) => {
+                    error!("❌ GAME_MODES is not a valid list of game modes - using the default mode: {}", e);
+                    Self::default_modes()
+                }
+            },
+            None => Self::default_modes(),
+        };
+
+        let rules = RULES.read().unwrap_or_else(|e| e.into_inner());
+        let mut game_types = HashSet::new();
+        let mut namespaces = HashSet::new();
+        let mut modes = Vec::new();
+        for config in configs {
+            let problem = if !GameConfigManager::valid_game_type(&config.game_type) {
+                Some("game_type must be 1-32 lowercase letters, digits, '_' or '-'".to_string())
+            } else if !config.namespace.starts_with('/') || RESERVED_NAMESPACES.contains(&config.namespace.as_str()) {
+                Some(format!("namespace must start with '/' and not be one of {:?}", RESERVED_NAMESPACES))
+            } else if !rules.contains_key(&config.rules) {
+                Some(format!("no rule module named {:?}", config.rules))
+            } else if config.matchmaking.max_party_size == 0 {
+                Some("matchmaking.max_party_size must be at least 1".to_string())
+            } else if game_types.contains(&config.game_type) || namespaces.contains(&config.namespace) {
+                Some("game_type and namespace must be unique".to_string())
+            } else {
+                None
+            };
+            if let Some(problem) = problem {
+                error!("❌ Skipping game mode {} on {}: {}", config.game_type, config.namespace, problem);
+                continue;
+            }
+            game_types.insert(config.game_type.clone());
+            namespaces.insert(config.namespace.clone());
+            modes.push(Arc::new(RegisteredMode {
+                rules: rules[&config.rules].clone(),
+                game_type: config.game_type,
+                namespace: config.namespace,
+                rules_name: config.rules,
+                matchmaking: config.matchmaking,
+            }));
+        }
+        drop(rules);
+
+        if modes.is_empty() {
+            error!("❌ No usable game modes configured - using the default mode");
+            return Self::default_modes().into_iter().map(|config| Arc::new(RegisteredMode {
+                rules: Arc::new(ClassicRules),
+                game_type: config.game_type,
+                namespace: config.namespace,
+                rules_name: config.rules,
+                matchmaking: config.matchmaking,
+            })).collect();
+        }
+        for mode in &modes {
+            info!("🎲 Game mode {} on {} (rules: {})", mode.game_type, mode.namespace, mode.rules_name);
+        }
+        modes
+    }
+
+    pub fn modes() -> &'static [Arc<RegisteredMode>] {
+        MODES.get_or_init(Self::load)
+    }
+
+    pub fn get(game_type: &str) -> Option<Arc<RegisteredMode>> {
+        Self::modes().iter().find(|mode| mode.game_type == game_type).cloned()
+    }
+
+    // The mode of a room. Rooms restored after their mode was removed from
+    // GAME_MODES carry on under the first mode.
+    pub fn for_game_type(game_type: &str) -> Arc<RegisteredMode> {
+        Self::get(game_type).unwrap_or_else(|| Self::modes()[0].clone())
+    }
+}
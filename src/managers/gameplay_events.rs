@@ -1,36 +1,305 @@
-use socketioxide::{SocketIo, extract::{SocketRef, Data}};
-use tracing::{info, error};
+use socketioxide::{SocketIo, extract::{SocketRef, Data, TryData}, socket::DisconnectReason};
+use tracing::{info, warn};
 use std::sync::Arc;
-use crate::database::service::DataService;
-use serde_json::Value;
+use crate::api::response::{ApiError, ApiResponse};
+use crate::database::models::ChallengeKind;
+use crate::managers::afk::AfkManager;
+use crate::managers::challenges::ChallengeManager;
+use crate::managers::chat::ChatManager;
+use crate::managers::compliance::ComplianceManager;
+use crate::managers::chaos::FaultInjector;
+use crate::managers::correlation::Correlation;
+use crate::managers::dealer::DealerManager;
+use crate::managers::escrow::EscrowManager;
+use crate::database::store::DataStore;
+use crate::managers::game_modes::{GameModeRegistry, RegisteredMode};
+use crate::managers::latency::LatencyManager;
+use crate::managers::matchmaking::{MatchmakingManager, QueueMember};
+use crate::managers::namespace_policy::{NamespaceCaller, NamespaceGuard};
+use crate::managers::party::{player_room, PartyManager};
+use crate::managers::regions::RegionManager;
+use crate::managers::risk::RiskManager;
+use crate::managers::room::RoomManager;
+use crate::managers::room_state::RoomStateManager;
+use crate::managers::sessions::SessionMetricsManager;
+use crate::managers::spectators::SpectatorManager;
+use crate::managers::tenant::TenantManager;
+use crate::managers::time_sync::TimeSyncManager;
+use crate::managers::turn_timer::TurnTimerManager;
+use crate::managers::validation::ValidationManager;
+use serde_json::{json, Value};
 
 pub struct GameplayEventManager;
 
 impl GameplayEventManager {
-    pub fn register_gameplay_events(io: &SocketIo, data_service: Arc<DataService>) {
+    // Gameplay events on the namespace of every game mode in GameModeRegistry
+    pub fn register_gameplay_events(io: &SocketIo, data_service: Arc<dyn DataStore>) {
         info!("🏀 Registering gameplay events...");
-        
-        // Define a namespace for gameplay-related events
-        io.ns("/gameplay", move |socket: SocketRef| {
+        for mode in GameModeRegistry::modes() {
+            if !NamespaceGuard::allowed(&mode.namespace) {
+                info!("🎲 Game mode {} is off: {} is not in NAMESPACE_ALLOWLIST", mode.game_type, mode.namespace);
+                continue;
+            }
+            Self::register_mode(io, mode.clone(), data_service.clone());
+        }
+        info!("✅ Gameplay events registered!");
+    }
+
+    fn register_mode(io: &SocketIo, mode: Arc<RegisteredMode>, data_service: Arc<dyn DataStore>) {
+        let io_handle = io.clone();
+        io.ns(mode.namespace.clone(), move |socket: SocketRef, TryData::<Value>(auth)| {
             let data_service = data_service.clone();
+            let io_handle = io_handle.clone();
+            let mode = mode.clone();
             async move {
-                info!("Socket connected to gameplay namespace: {}", socket.id);
-            
-                // Example gameplay event
+                info!("Socket connected to {} namespace: {}", mode.namespace, socket.id);
+                let Some((_, caller)) = NamespaceGuard::connect(&socket, &*data_service, &auth.unwrap_or_default()).await else {
+                    let _ = socket.disconnect();
+                    return;
+                };
+                // Game namespaces require session or JWT auth (see NamespaceGuard::policy);
+                // gameplay acts for that user, never for a player_id in the payload
+                let NamespaceCaller::User(user) = caller else {
+                    let _ = socket.disconnect();
+                    return;
+                };
+                info!("🔐 Socket {} on {} authenticated as {}", socket.id, mode.namespace, user.mobile_no);
+                let user_id = user.user.user_id.clone();
+
+                // Join a game room - the turn loop starts once the room is full
+                let ds_join = data_service.clone();
+                let io_join = io_handle.clone();
+                let mode_join = mode.clone();
+                let user_join = user_id.clone();
+                socket.on("room:join", move |s: SocketRef, Data::<Value>(data)| {
+                    let ds_join = ds_join.clone();
+                    let io_join = io_join.clone();
+                    let mode_join = mode_join.clone();
+                    let user_join = user_join.clone();
+                    let request_id = Correlation::request_id_from(&data);
+                    Correlation::scope("room:join", s.id, request_id, async move {
+                        info!("🚪 Received room:join from socket {}: {:?}", s.id, data);
+                        if let Err(error_details) = ValidationManager::validate_room_data(&data) {
+                            let _ = FaultInjector::emit(&s, "room:error", ApiError::from(error_details).on_event("room:error").for_socket(s.id)).await;
+                            return;
+                        }
+                        let room_id = data["room_id"].as_str().unwrap_or_default();
+                        let player_id = user_join.as_str();
+                        // A seat is staked with its holder's coins; never take one for someone else
+                        if data["player_id"].as_str() != Some(player_id) {
+                            let error = ApiError::new("PLAYER_MISMATCH", "AUTHORIZATION_ERROR", "player_id", "player_id is not the authenticated user")
+                                .with_details(json!({"room_id": room_id}));
+                            let _ = FaultInjector::emit(&s, "room:error", error.on_event("room:error").for_socket(s.id)).await;
+                            return;
+                        }
+                        if let Err(error) = ComplianceManager::check_gameplay(&*ds_join, &s, player_id, "room:join").await {
+                            let _ = FaultInjector::emit(&s, "room:error", error.on_event("room:error").for_socket(s.id)).await;
+                            return;
+                        }
+
+                        match RoomManager::join_room(room_id, player_id, &s.id.to_string(), &mode_join.game_type).await {
+                            Ok(room) => {
+                                let _ = s.join(room_id.to_string());
+                                let _ = s.join(player_room(player_id));
+                                SpectatorManager::took_seat(&io_join, s.ns(), room_id, &s.id.to_string()).await;
+                                RiskManager::record_participant(ds_join.clone(), &s, room_id, player_id);
+                                AfkManager::activity(room_id, player_id);
+                                let players: Vec<&str> = room.players.iter().map(|p| p.player_id.as_str()).collect();
+                                // Players rejoining a running (or restored) match get its current turn
+                                let active_turn = room.active_turn.as_ref().map(|turn| json!({
+                                    "turn_id": turn.turn_id,
+                                    "player_id": turn.player_id,
+                                    "deadline": turn.deadline.to_rfc3339(),
+                                    "deadline_ms": turn.deadline.timestamp_millis()
+                                }));
+                                let joined = ApiResponse::success("room:joined", json!({
+                                    "room_id": room_id,
+                                    "player_id": player_id,
+                                    "game_type": room.config.game_type,
+                                    "players": players,
+                                    "turn_number": room.turn_number,
+                                    "active_turn": active_turn
+                                })).for_socket(s.id);
+                                if let Err(e) = s.within(room_id.to_string()).emit("room:joined", joined) {
+                                    warn!("⚠️ Failed to broadcast room:joined to room {}: {}", room_id, e);
+                                }
+
+                                if room.is_full() && room.active_turn.is_none() && room.turn_index.is_none()
+                                    && EscrowManager::open(&io_join, &ds_join, room_id).await {
+                                    TurnTimerManager::start_next_turn(io_join, ds_join, room_id).await;
+                                }
+                            }
+                            Err(code) => {
+                                let error = ApiError::new(code, "ROOM_ERROR", "room_id", "Unable to join room")
+                                    .with_details(json!({"room_id": room_id}));
+                                let _ = FaultInjector::emit(&s, "room:error", error.on_event("room:error").for_socket(s.id)).await;
+                            }
+                        }
+                    })
+                });
+
+                // Player action - only accepted from the player whose turn it is
+                let ds_action = data_service.clone();
+                let io_action = io_handle.clone();
+                let mode_action = mode.clone();
+                let user_action = user_id.clone();
                 socket.on("player_action", move |s: SocketRef, Data::<Value>(data)| {
-                    let _data_service = data_service.clone();
-                    async move {
+                    let ds_action = ds_action.clone();
+                    let io_action = io_action.clone();
+                    let mode_action = mode_action.clone();
+                    let user_action = user_action.clone();
+                    let request_id = Correlation::request_id_from(&data);
+                    Correlation::scope("player_action", s.id, request_id, async move {
                         info!("Received player_action event on socket {}: {:?}", s.id, data);
-                        // Handle player action logic here, e.g., using _data_service
-                    }
+                        if let Err(error_details) = ValidationManager::validate_room_data(&data) {
+                            let _ = FaultInjector::emit(&s, "turn:error", ApiError::from(error_details).on_event("turn:error").for_socket(s.id)).await;
+                            return;
+                        }
+                        let room_id = data["room_id"].as_str().unwrap_or_default();
+                        let player_id = user_action.as_str();
+                        // The game mode's rules decide which moves are legal
+                        if let Err(reason) = mode_action.rules.validate_action(data.get("action").unwrap_or(&Value::Null)) {
+                            let error = ApiError::new("INVALID_ACTION", "TURN_ERROR", "action", &reason)
+                                .with_details(json!({"room_id": room_id, "player_id": player_id, "game_type": mode_action.game_type}));
+                            let _ = FaultInjector::emit(&s, "turn:error", error.on_event("turn:error").for_socket(s.id)).await;
+                            return;
+                        }
+
+                        match TurnTimerManager::complete_turn(&*ds_action, room_id, player_id).await {
+                            Ok(turn) => {
+                                AfkManager::activity(room_id, player_id);
+                                let action = data.get("action").cloned().unwrap_or(Value::Null);
+                                let action = ApiResponse::success("player_action", json!({
+                                    "room_id": room_id,
+                                    "player_id": player_id,
+                                    "turn_number": turn.turn_number,
+                                    "score": mode_action.rules.score_action(&action),
+                                    "action": action
+                                }));
+                                if let Err(e) = s.within(room_id.to_string()).emit("player_action", action) {
+                                    warn!("⚠️ Failed to broadcast player_action to room {}: {}", room_id, e);
+                                }
+                                // player_id is the user's user_id
+                                let (ds_challenge, user_id) = (ds_action.clone(), player_id.to_string());
+                                TenantManager::spawn(async move {
+                                    ChallengeManager::record(&*ds_challenge, &user_id, &[(ChallengeKind::TakeTurns, 1)]).await;
+                                });
+                                TurnTimerManager::start_next_turn(io_action, ds_action, room_id).await;
+                            }
+                            Err(code) => {
+                                let error = ApiError::new(code, "TURN_ERROR", "player_id", "Action rejected: it is not this player's turn")
+                                    .with_details(json!({"room_id": room_id, "player_id": player_id}));
+                                let _ = FaultInjector::emit(&s, "turn:error", error.on_event("turn:error").for_socket(s.id)).await;
+                            }
+                        }
+                    })
                 });
 
-                socket.on("disconnect", |socket: SocketRef| {
-                    info!("Socket disconnected from gameplay namespace: {}", socket.id);
+                // Queue for a match - falls back to a bot opponent after the configured wait
+                let ds_queue = data_service.clone();
+                let io_queue = io_handle.clone();
+                let mode_queue = mode.clone();
+                let user_queue = user_id.clone();
+                socket.on("matchmaking:join", move |s: SocketRef, Data::<Value>(data)| {
+                    let ds_queue = ds_queue.clone();
+                    let io_queue = io_queue.clone();
+                    let mode_queue = mode_queue.clone();
+                    let player_id = user_queue.clone();
+                    let request_id = Correlation::request_id_from(&data);
+                    Correlation::scope("matchmaking:join", s.id, request_id, async move {
+                        info!("🎯 Received matchmaking:join from socket {}: {:?}", s.id, data);
+                        if let Err(error_details) = ValidationManager::validate_matchmaking_data(&data) {
+                            let _ = FaultInjector::emit(&s, "matchmaking:error", ApiError::from(error_details).on_event("matchmaking:error").for_socket(s.id)).await;
+                            return;
+                        }
+                        if PartyManager::party_of(&player_id).await.is_some() {
+                            let error = ApiError::new("IN_PARTY", "MATCHMAKING_ERROR", "player_id", "Party members queue together via party:queue")
+                                .with_details(json!({"player_id": player_id}));
+                            let _ = FaultInjector::emit(&s, "matchmaking:error", error.on_event("matchmaking:error").for_socket(s.id)).await;
+                            return;
+                        }
+                        if let Err(error) = ComplianceManager::check_gameplay(&*ds_queue, &s, &player_id, "matchmaking:join").await {
+                            let _ = FaultInjector::emit(&s, "matchmaking:error", error.on_event("matchmaking:error").for_socket(s.id)).await;
+                            return;
+                        }
+                        if let Err(error) = AfkManager::check_queue(&*ds_queue, &player_id).await {
+                            let _ = FaultInjector::emit(&s, "matchmaking:error", error.on_event("matchmaking:error").for_socket(s.id)).await;
+                            return;
+                        }
+                        MatchmakingManager::join_queue(io_queue, ds_queue, vec![QueueMember { player_id, socket_id: s.id }], None, mode_queue).await;
+                    })
+                });
+
+                let user_leave = user_id.clone();
+                socket.on("matchmaking:leave", move |s: SocketRef, Data::<Value>(data)| {
+                    let player_id = user_leave.clone();
+                    let request_id = Correlation::request_id_from(&data);
+                    Correlation::scope("matchmaking:leave", s.id, request_id, async move {
+                        let removed = MatchmakingManager::leave_queue(&player_id).await;
+                        let _ = FaultInjector::emit(&s, "matchmaking:left", ApiResponse::success("matchmaking:left", json!({
+                            "player_id": player_id,
+                            "was_queued": removed
+                        })).for_socket(s.id)).await;
+                    })
+                });
+
+                // Players waiting per region, so clients can show the expected wait
+                let mode_population = mode.clone();
+                socket.on("matchmaking:population", move |s: SocketRef, Data::<Value>(data)| {
+                    let mode_population = mode_population.clone();
+                    let request_id = Correlation::request_id_from(&data);
+                    Correlation::scope("matchmaking:population", s.id, request_id, async move {
+                        let regions = MatchmakingManager::population(&mode_population.game_type).await;
+                        let own_region = RegionManager::of_socket(&s).map(|r| r.region.clone());
+                        let _ = FaultInjector::emit(&s, "matchmaking:population", ApiResponse::success("matchmaking:population", json!({
+                            "game_type": mode_population.game_type,
+                            "region": own_region,
+                            "regions": regions
+                        })).for_socket(s.id)).await;
+                    })
+                });
+
+                // Clock synchronization for client-side turn countdowns
+                TimeSyncManager::register_time_sync_events(&socket);
+
+                // Heartbeat - RTT samples feed latency-aware matchmaking
+                LatencyManager::register_ping_events(&socket);
+
+                // Parties - group queueing, party chat and leader controls
+                PartyManager::register_party_events(&socket, io_handle.clone(), data_service.clone(), mode.clone(), user_id.clone());
+
+                // Spectators - watch a room without a seat, with throttled audience counts
+                SpectatorManager::register_spectator_events(&socket, io_handle.clone(), mode.game_type.clone());
+
+                // Room state - high-frequency deltas merged per room, sent as deltas against each client's
+                // last ack and only for the paths it subscribed to
+                RoomStateManager::register_state_events(&socket, io_handle.clone());
+
+                // AFK - players report back after afk:warning
+                AfkManager::register_afk_events(&socket);
+
+                // Dealer - server-side shuffles and dice rolls with committed seeds
+                DealerManager::register_dealer_events(&socket, data_service.clone());
+
+                // Room chat - flood control and mutes shared with party chat
+                ChatManager::register_chat_events(&socket, data_service.clone());
+
+                let io_disconnect = io_handle.clone();
+                socket.on_disconnect(move |socket: SocketRef, reason: DisconnectReason| {
+                    let io_disconnect = io_disconnect.clone();
+                    async move {
+                        info!("Socket disconnected from {} namespace: {} ({})", socket.ns(), socket.id, reason);
+                        MatchmakingManager::remove_socket(&socket.id.to_string()).await;
+                        RoomManager::socket_left(&socket.id.to_string()).await;
+                        PartyManager::remove_socket(&io_disconnect, &socket.id.to_string()).await;
+                        LatencyManager::remove_socket(&socket.id.to_string()).await;
+                        SpectatorManager::remove_socket(&io_disconnect, socket.ns(), &socket.id.to_string()).await;
+                        RoomStateManager::remove_socket(&socket.id.to_string());
+                        SessionMetricsManager::remove_socket(&socket.id.to_string());
+                        TenantManager::remove_socket(&socket.id.to_string()).await;
+                    }
                 });
             }
         });
-        
-        info!("✅ Gameplay events registered!");
     }
-} 
\ No newline at end of file
+}
\ No newline at end of file
@@ -0,0 +1,249 @@
+use chrono::{Duration, Utc};
+use socketioxide::extract::SocketRef;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tracing::{info, warn, error};
+
+use crate::api::response::{ApiError, ApiResponse};
+use crate::config::CONFIG;
+use crate::database::models::{DeepLink, Gift, GiftLimits, GiftSend, UserRegister};
+use crate::database::store::DataStore;
+use crate::managers::auth_guard::AuthGuard;
+use crate::managers::chaos::FaultInjector;
+use crate::managers::error_responder::ErrorResponder;
+use crate::managers::notifications::{NotificationCategory, NotificationManager};
+use crate::managers::parental::ParentalManager;
+use crate::managers::risk::RiskManager;
+use crate::managers::tenant::TenantManager;
+use crate::managers::validation::ValidationManager;
+
+// Limits for gift:send items
+pub const MAX_GIFT_ITEM_QUANTITY: i64 = 100;
+pub const MAX_ITEM_ID_LENGTH: usize = 32;
+
+// What a gift:send moves from sender to recipient
+enum GiftContent {
+    Coins(i64),
+    Item { item_id: String, quantity: i64 },
+}
+
+// Gifts of coins or inventory items between friends. Senders are screened
+// (account and device age, shared devices) before anything moves. The move,
+// both sides' ledger entries referencing the gift and the gift record commit
+// together, within the sender's daily limits.
+pub struct GiftManager;
+
+impl GiftManager {
+    fn gift_error(code: &str, field: &str, message: &str, details: Value) -> ApiError {
+        ApiError::new(code, "GIFT_ERROR", field, message).with_details(details)
+    }
+
+    // Build the gift from gift:send data already checked by ValidationManager
+    fn parse_content(data: &Value) -> GiftContent {
+        match data["item_id"].as_str() {
+            Some(item_id) => GiftContent::Item {
+                item_id: item_id.to_string(),
+                quantity: data["quantity"].as_i64().unwrap_or(1),
+            },
+            None => GiftContent::Coins(data["coins"].as_i64().unwrap_or_default()),
+        }
+    }
+
+    // Fraud checks; returns the recipient if the gift may go ahead
+    async fn screen(data_service: &dyn DataStore, sender: &UserRegister, to_user_id: &str, content: &GiftContent) -> Result<UserRegister, ApiError> {
+        let storage_error = |e: Box<dyn std::error::Error + Send + Sync>| {
+            error!("❌ Failed to check gift from user {}: {}", sender.user_id, e);
+            ApiError::system("GIFT_FAILED", "to_user_id", "Failed to check the gift", &e)
+        };
+        if to_user_id == sender.user_id {
+            return Err(Self::gift_error("GIFT_SELF", "to_user_id", "You cannot send a gift to yourself", json!({})));
+        }
+        // Minors give coins away only under parental controls
+        if let GiftContent::Coins(_) = content {
+            ParentalManager::spend_limit(sender)?;
+        }
+
+        let now = Utc::now();
+        let account_cutoff = (now - Duration::hours(CONFIG.gift_min_account_age_hours)).timestamp_millis();
+        if sender.created_at.timestamp_millis() > account_cutoff {
+            return Err(Self::gift_error("GIFT_ACCOUNT_TOO_NEW", "mobile_no", "Your account is too new to send gifts", json!({
+                "min_account_age_hours": CONFIG.gift_min_account_age_hours
+            })));
+        }
+        let sender_devices = data_service.list_user_devices(&sender.mobile_no).await.map_err(storage_error)?;
+        let device_cutoff = (now - Duration::hours(CONFIG.gift_min_device_age_hours)).timestamp_millis();
+        let device_old_enough = sender_devices.iter()
+            .find(|d| d.device_id == sender.device_id)
+            .is_some_and(|d| d.first_seen_at.timestamp_millis() <= device_cutoff);
+        if !device_old_enough {
+            return Err(Self::gift_error("GIFT_DEVICE_TOO_NEW", "device_id", "Gifts cannot be sent from a new device yet", json!({
+                "min_device_age_hours": CONFIG.gift_min_device_age_hours
+            })));
+        }
+
+        let recipient = match data_service.get_user_by_id(to_user_id).await.map_err(storage_error)? {
+            Some(recipient) if recipient.is_active && !recipient.is_banned => recipient,
+            _ => return Err(Self::gift_error("GIFT_RECIPIENT_NOT_FOUND", "to_user_id", "No such user", json!({"to_user_id": to_user_id}))),
+        };
+        if !data_service.are_friends(&sender.user_id, to_user_id).await.map_err(storage_error)? {
+            return Err(Self::gift_error("GIFT_NOT_FRIENDS", "to_user_id", "Gifts can only be sent to friends", json!({"to_user_id": to_user_id})));
+        }
+        // Accounts sharing a device are most likely one person farming gifts
+        let recipient_devices = data_service.list_user_devices(&recipient.mobile_no).await.map_err(storage_error)?;
+        if recipient_devices.iter().any(|r| sender_devices.iter().any(|s| s.device_id == r.device_id)) {
+            return Err(Self::gift_error("GIFT_SAME_DEVICE", "to_user_id", "Gifts cannot be sent between accounts on the same device", json!({})));
+        }
+        // Either side limited by the anomaly scan
+        let (sender_limited, recipient_limited) = tokio::try_join!(
+            RiskManager::is_limited(data_service, &sender.user_id),
+            RiskManager::is_limited(data_service, to_user_id)
+        ).map_err(storage_error)?;
+        if sender_limited || recipient_limited {
+            return Err(Self::gift_error("GIFT_RISK_LIMITED", "to_user_id", "Gifting is limited on this account pending review", json!({})));
+        }
+        Ok(recipient)
+    }
+
+    // Move the gift and record it. Returns what the sender has left, or the
+    // error for a sender without enough, over their parental spending limit
+    // or over the daily gift limits.
+    async fn transfer(data_service: &dyn DataStore, gift: Gift, daily_limit: Option<i64>) -> Result<Result<i64, ApiError>, Box<dyn std::error::Error + Send + Sync>> {
+        let tenant_limits = TenantManager::limits();
+        let limits = GiftLimits { gifts: tenant_limits.gift_daily_limit, coins: tenant_limits.gift_daily_coin_limit };
+        let coins = gift.coins;
+        let (item_id, quantity) = (gift.item_id.clone(), gift.quantity);
+        Ok(match data_service.send_gift(gift, limits, daily_limit).await? {
+            GiftSend::Sent(left) => Ok(left),
+            GiftSend::InsufficientBalance => Err(Self::gift_error("GIFT_INSUFFICIENT_BALANCE", "coins", "Not enough coins", json!({"coins": coins}))),
+            GiftSend::InsufficientItems => Err(Self::gift_error("GIFT_INSUFFICIENT_ITEMS", "item_id", "Not enough of this item", json!({"item_id": item_id, "quantity": quantity}))),
+            GiftSend::SpendLimitReached { spent_today, daily_limit } => Err(Self::gift_error("GIFT_SPEND_LIMIT", "coins", "This would go over the daily spending limit set by a parent", json!({
+                "coins": coins,
+                "spent_today": spent_today,
+                "daily_spend_limit": daily_limit
+            }))),
+            GiftSend::DailyLimitReached { gifts_sent, coins_sent } => Err(Self::gift_error("GIFT_DAILY_LIMIT", "coins", "Daily gift limit reached", json!({
+                "gifts_sent": gifts_sent,
+                "daily_limit": limits.gifts,
+                "coins_sent": coins_sent,
+                "daily_coin_limit": limits.coins
+            }))),
+        })
+    }
+
+    // Gifts on the main namespace:
+    //   gift:send     { mobile_no, session_token, to_user_id, coins | item_id + quantity? } -> gift:sent
+    //   inventory:get { mobile_no, session_token }                                         -> inventory:data
+    pub fn register_gift_events(socket: &SocketRef, data_service: Arc<dyn DataStore>) {
+        let ds = data_service.clone();
+        AuthGuard::require_signed_session(socket, "gift:send", data_service.clone(), move |socket, data, auth| {
+            let ds = ds.clone();
+            async move {
+                info!("🎁 Received gift:send from {}: {:?}", socket.id, data);
+                if let Err(error_details) = ValidationManager::validate_gift_data(&data) {
+                    info!("❌ Gift validation failed for socket {}: {:?}", socket.id, error_details);
+                    ErrorResponder::send(&socket, &*ds, error_details).await;
+                    return;
+                }
+                let user = auth.user;
+                let to_user_id = data["to_user_id"].as_str().unwrap_or_default();
+                let content = Self::parse_content(&data);
+                let recipient = match Self::screen(&*ds, &user, to_user_id, &content).await {
+                    Ok(recipient) => recipient,
+                    Err(error) => {
+                        info!("🚫 Gift from user {} to {} refused: {}", user.user_id, to_user_id, error.error_code);
+                        ErrorResponder::send(&socket, &*ds, error).await;
+                        return;
+                    }
+                };
+
+                let gift_id = uuid::Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext)).simple().to_string();
+                let (coins, item_id, quantity) = match &content {
+                    GiftContent::Coins(coins) => (Some(*coins), None, 0),
+                    GiftContent::Item { item_id, quantity } => (None, Some(item_id.clone()), *quantity),
+                };
+                let gift = Gift {
+                    id: None,
+                    gift_id: gift_id.clone(),
+                    from_user_id: user.user_id.clone(),
+                    to_user_id: recipient.user_id.clone(),
+                    coins,
+                    item_id: item_id.clone(),
+                    quantity,
+                    created_at: bson::DateTime::now(),
+                };
+                // screen() already refused minors without parental controls
+                let daily_limit = ParentalManager::spend_limit(&user).unwrap_or(None);
+                let left = match Self::transfer(&*ds, gift, daily_limit).await {
+                    Ok(Ok(left)) => left,
+                    Ok(Err(error)) => {
+                        info!("🚫 Gift from user {} to {} refused: {}", user.user_id, recipient.user_id, error.error_code);
+                        ErrorResponder::send(&socket, &*ds, error).await;
+                        return;
+                    }
+                    Err(e) => {
+                        error!("❌ Gift {} from user {} to {} failed: {}", gift_id, user.user_id, recipient.user_id, e);
+                        ErrorResponder::send(&socket, &*ds, ApiError::system("GIFT_FAILED", "to_user_id", "Failed to send the gift", &e)).await;
+                        return;
+                    }
+                };
+
+                let name = user.full_name.as_deref().unwrap_or("A friend");
+                let (notification_type, params) = match &content {
+                    GiftContent::Coins(coins) => ("gift_coins", json!({ "sender": name, "coins": coins })),
+                    GiftContent::Item { item_id, quantity } => ("gift_item", json!({ "sender": name, "quantity": quantity, "item_id": item_id })),
+                };
+                let notification = json!({ "gift_id": gift_id, "from_user_id": user.user_id, "coins": coins, "item_id": item_id, "quantity": quantity });
+                if let Err(e) = NotificationManager::dispatch(&*ds, &recipient.user_id, NotificationCategory::Social, notification_type, params, notification, Some(DeepLink::new("open_gift", json!({ "gift_id": gift_id })))).await {
+                    warn!("⚠️ Failed to notify user {} of gift {}: {}", recipient.user_id, gift_id, e);
+                }
+
+                let mut response = json!({
+                    "gift_id": gift_id,
+                    "to_user_id": recipient.user_id,
+                    "coins": coins,
+                    "item_id": item_id,
+                    "quantity": quantity
+                });
+                match content {
+                    GiftContent::Coins(_) => response["balance"] = json!(left),
+                    GiftContent::Item { .. } => response["remaining"] = json!(left),
+                }
+                let response = ApiResponse::success("gift:sent", response).for_socket(socket.id);
+                match FaultInjector::emit(&socket, "gift:sent", response).await {
+                    Ok(_) => info!("✅ Gift {} sent from user {} to {}", gift_id, user.user_id, recipient.user_id),
+                    Err(e) => warn!("⚠️ Failed to emit gift:sent to socket {}: {}", socket.id, e),
+                }
+            }
+        });
+
+        let ds = data_service.clone();
+        AuthGuard::require_session(socket, "inventory:get", data_service, move |socket, _data, auth| {
+            let ds = ds.clone();
+            async move {
+                info!("🎁 Received inventory:get from {}", socket.id);
+                let user = auth.user;
+                let loaded = tokio::try_join!(
+                    ds.get_inventory(&user.user_id),
+                    ds.get_wallet_balance(&user.user_id)
+                );
+                let (items, balance) = match loaded {
+                    Ok(loaded) => loaded,
+                    Err(e) => {
+                        error!("❌ Failed to load inventory of user {}: {}", user.user_id, e);
+                        ErrorResponder::send(&socket, &*ds, ApiError::system("INVENTORY_FETCH_FAILED", "inventory", "Failed to load the inventory", &e)).await;
+                        return;
+                    }
+                };
+
+                let items: Vec<Value> = items.iter().map(|item| json!({ "item_id": item.item_id, "quantity": item.quantity })).collect();
+                let response = ApiResponse::success("inventory:data", json!({
+                    "items": items,
+                    "balance": balance
+                })).for_socket(socket.id);
+                if let Err(e) = FaultInjector::emit(&socket, "inventory:data", response).await {
+                    warn!("⚠️ Failed to emit inventory:data to socket {}: {}", socket.id, e);
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,105 @@
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::managers::health_score::HealthScoreManager;
+use crate::managers::slo::SloManager;
+
+// Upper bounds (ms) of the handler duration histogram buckets
+pub const DURATION_BUCKETS_MS: [f64; 11] = [5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0];
+
+#[derive(Default)]
+struct HandlerStats {
+    buckets: [u64; DURATION_BUCKETS_MS.len()],  // Calls per bucket (not cumulative)
+    calls: u64,
+    errors: u64,
+    sum_ms: f64,
+    max_ms: f64,
+}
+
+impl HandlerStats {
+    // Upper bound of the bucket holding the q-quantile; None above the last bucket
+    fn quantile_ms(&self, q: f64) -> Option<f64> {
+        let target = (self.calls as f64 * q).ceil() as u64;
+        let mut seen = 0;
+        for (bound, count) in DURATION_BUCKETS_MS.iter().zip(self.buckets) {
+            seen += count;
+            if seen >= target {
+                return Some(*bound);
+            }
+        }
+        None
+    }
+}
+
+// Per event: call durations and calls that answered with an error
+static HANDLERS: Lazy<Mutex<BTreeMap<&'static str, HandlerStats>>> = Lazy::new(|| Mutex::new(BTreeMap::new()));
+
+// Socket event handler instrumentation. Every handler running inside
+// Correlation::scope is recorded here.
+pub struct HandlerMetrics;
+
+impl HandlerMetrics {
+    pub fn record(event: &'static str, elapsed: Duration, failed: bool) {
+        let ms = elapsed.as_secs_f64() * 1000.0;
+        let mut handlers = HANDLERS.lock().unwrap_or_else(|e| e.into_inner());
+        let stats = handlers.entry(event).or_default();
+        if let Some(bucket) = DURATION_BUCKETS_MS.iter().position(|bound| ms <= *bound) {
+            stats.buckets[bucket] += 1;
+        }
+        stats.calls += 1;
+        stats.sum_ms += ms;
+        stats.max_ms = stats.max_ms.max(ms);
+        if failed {
+            stats.errors += 1;
+        }
+        drop(handlers);
+        SloManager::record(event, elapsed, failed);
+        HealthScoreManager::record_call(elapsed, failed);
+    }
+
+    // Prometheus text format: socket_handler_duration_ms histogram and
+    // socket_handler_errors_total counter, labeled by event
+    pub fn render() -> String {
+        let handlers = HANDLERS.lock().unwrap_or_else(|e| e.into_inner());
+        if handlers.is_empty() {
+            return String::new();
+        }
+        let mut output = String::from("# TYPE socket_handler_duration_ms histogram\n");
+        for (event, stats) in handlers.iter() {
+            let mut cumulative = 0;
+            for (bound, count) in DURATION_BUCKETS_MS.iter().zip(stats.buckets) {
+                cumulative += count;
+                output.push_str(&format!("socket_handler_duration_ms_bucket{{event=\"{}\",le=\"{}\"}} {}\n", event, bound, cumulative));
+            }
+            output.push_str(&format!("socket_handler_duration_ms_bucket{{event=\"{}\",le=\"+Inf\"}} {}\n", event, stats.calls));
+            output.push_str(&format!("socket_handler_duration_ms_sum{{event=\"{}\"}} {}\n", event, stats.sum_ms));
+            output.push_str(&format!("socket_handler_duration_ms_count{{event=\"{}\"}} {}\n", event, stats.calls));
+        }
+        output.push_str("# TYPE socket_handler_errors_total counter\n");
+        for (event, stats) in handlers.iter() {
+            output.push_str(&format!("socket_handler_errors_total{{event=\"{}\"}} {}\n", event, stats.errors));
+        }
+        output
+    }
+
+    // Per-event summary for the admin dashboard. Quantiles are bucket upper
+    // bounds (null when above the largest bucket).
+    pub fn snapshot() -> Value {
+        let handlers = HANDLERS.lock().unwrap_or_else(|e| e.into_inner());
+        let events: Vec<Value> = handlers.iter().map(|(event, stats)| json!({
+            "event": event,
+            "calls": stats.calls,
+            "errors": stats.errors,
+            "error_rate": stats.errors as f64 / stats.calls as f64,
+            "avg_ms": stats.sum_ms / stats.calls as f64,
+            "max_ms": stats.max_ms,
+            "p50_ms": stats.quantile_ms(0.50),
+            "p90_ms": stats.quantile_ms(0.90),
+            "p99_ms": stats.quantile_ms(0.99)
+        })).collect();
+        json!(events)
+    }
+}
@@ -0,0 +1,21 @@
+use socketioxide::extract::SocketRef;
+use std::sync::Arc;
+
+use crate::database::store::DataStore;
+use crate::managers::handlers::EventHandlers;
+use crate::managers::mobile_change::MobileChangeManager;
+use crate::managers::parental::ParentalManager;
+use crate::managers::two_step::TwoStepManager;
+
+// Account security: changing the mobile number (account:change_mobile /
+// account:change_mobile:confirm), two-step verification (two_step:*) and
+// parental controls (parental:*)
+pub struct AccountHandlers;
+
+impl EventHandlers for AccountHandlers {
+    fn register(&self, socket: &SocketRef, data_service: Arc<dyn DataStore>) {
+        MobileChangeManager::register_events(socket, data_service.clone());
+        TwoStepManager::register_events(socket, data_service.clone());
+        ParentalManager::register_events(socket, data_service);
+    }
+}
@@ -0,0 +1,379 @@
+use socketioxide::extract::{Data, SocketRef};
+use serde_json::json;
+use tracing::{info, warn, error};
+use std::sync::Arc;
+
+use crate::api::contracts::{LoginSuccess, OtpVerified};
+use crate::api::response::{ApiError, ApiResponse};
+use crate::config::CONFIG;
+use crate::database::store::DataStore;
+use crate::managers::auth_nonce::AuthNonceManager;
+use crate::managers::captcha::CaptchaManager;
+use crate::managers::correlation::Correlation;
+use crate::managers::emit_queue::{EmitQueue, OfflineFallback};
+use crate::managers::error_responder::ErrorResponder;
+use crate::managers::funnel::{FunnelManager, FunnelStage};
+use crate::managers::handlers::EventHandlers;
+use crate::managers::jwt::create_jwt_service;
+use crate::managers::login_policy::LoginPolicyManager;
+use crate::managers::moderation::ModerationManager;
+use crate::managers::otp_delivery::OtpDeliveryManager;
+use crate::managers::payload_signing::PayloadSigning;
+use crate::managers::protocol::ProtocolManager;
+use crate::managers::token::TokenGenerator;
+use crate::managers::validation::ValidationManager;
+
+// login and verify:otp
+pub struct AuthHandlers;
+
+impl EventHandlers for AuthHandlers {
+    fn register(&self, socket: &SocketRef, data_service: Arc<dyn DataStore>) {
+        // Handle login event
+        let ds2 = data_service.clone();
+        socket.on("login", move |socket: SocketRef, Data::<serde_json::Value>(data)| {
+            let ds2 = ds2.clone();
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope("login", socket.id, request_id, async move {
+                tracing::info!("🔐 [DEBUG] Login event handler triggered");
+                info!("🔐 Received login request from {}: {:?}", socket.id, data);
+                let mobile_no = data["mobile_no"].as_str().unwrap_or("unknown");
+                let device_id = data["device_id"].as_str().unwrap_or("unknown");
+                let fcm_token = data["fcm_token"].as_str().unwrap_or("unknown");
+                let email = data["email"].as_str();
+                let _ = ds2.store_login_event(&socket.id.to_string(), mobile_no, device_id, fcm_token, email).await;
+                match ValidationManager::validate_login_data(&data) {
+                    Ok(_) => {
+                        let mobile_no = data["mobile_no"].as_str().unwrap_or("unknown");
+                        let device_id = data["device_id"].as_str().unwrap_or("unknown");
+                        if let Err(error) = ModerationManager::check_login(&*ds2, mobile_no).await {
+                            info!("🚫 Login refused for suspended mobile: {} (socket: {})", mobile_no, socket.id);
+                            ErrorResponder::send(&socket, &*ds2, error).await;
+                            return;
+                        }
+                        let policy = match LoginPolicyManager::check(&socket, mobile_no) {
+                            Ok(policy) => policy,
+                            Err(error) => {
+                                info!("🚫 Login refused from blocked region for mobile: {} (socket: {})", mobile_no, socket.id);
+                                ErrorResponder::send(&socket, &*ds2, error).await;
+                                return;
+                            }
+                        };
+                        if let Err(error) = CaptchaManager::check_login(&socket, mobile_no, data["captcha_token"].as_str()).await {
+                            ErrorResponder::send(&socket, &*ds2, error).await;
+                            return;
+                        }
+                        // Retried logins reuse the pending session instead of stacking new OTPs, but only
+                        // from the socket that started it or with its session_token as proof
+                        let reusable_session = match ds2.find_reusable_login_session(&socket.id.to_string(), mobile_no, device_id, data["session_token"].as_str()).await {
+                            Ok(session) => session,
+                            Err(e) => {
+                                warn!("Failed to look up pending login session: {}", e);
+                                None
+                            }
+                        };
+                        let session_reused = reusable_session.is_some();
+                        let (session_token, otp, is_new_user) = match reusable_session {
+                            Some(session) => (session.session_token, session.otp, session.is_new_user),
+                            None => {
+                                let session_token = TokenGenerator::session_token();
+                                // Allowlisted test accounts (QA, app-store review) get the fixed test OTP
+                                let test_otp = CONFIG.test_otp_for(mobile_no);
+                                let otp = test_otp.unwrap_or_else(|| policy.generate_otp());
+
+                                // Check if user exists in userregister collection
+                                let user_exists = ds2.user_exists(mobile_no).await;
+                                let is_new_user = match user_exists {
+                                    Ok(exists) => {
+                                        if exists {
+                                            // User exists - update login info
+                                            let update_result = ds2.update_user_login_info(mobile_no).await;
+                                            if let Err(e) = update_result {
+                                                warn!("Failed to update user login info: {}", e);
+                                            }
+                                            info!("🔄 Existing user logged in: {}", mobile_no);
+                                            false
+                                        } else {
+                                            // New user - register them
+                                            let register_result = ds2.register_new_user(mobile_no, device_id, fcm_token, email).await;
+                                            match register_result {
+                                                Ok(_) => {
+                                                    info!("🆕 New user registered: {}", mobile_no);
+                                                }
+                                                Err(e) => {
+                                                    warn!("Failed to register new user: {}", e);
+                                                }
+                                            }
+                                            true
+                                        }
+                                    }
+                                    Err(e) => {
+                                        warn!("Failed to check user existence: {}", e);
+                                        false
+                                    }
+                                };
+
+                                let store_result = ds2.store_login_success_event(&socket.id.to_string(), mobile_no, device_id, &session_token, otp, policy.otp_expiry_secs, is_new_user).await;
+                                if let Err(e) = store_result {
+                                    warn!("Failed to store login success event: {}", e);
+                                }
+                                if test_otp.is_some() {
+                                    // Test accounts bypass SMS/email delivery
+                                    if let Err(e) = ds2.store_test_otp_audit_event(&socket.id.to_string(), mobile_no, Some(device_id), "login", true).await {
+                                        error!("❌ Failed to store test OTP audit event for mobile {}: {}", mobile_no, e);
+                                    }
+                                } else if let Err(e) = OtpDeliveryManager::send_otp(&*ds2, mobile_no, email, otp, policy.otp_expiry_secs).await {
+                                    error!("❌ Failed to queue OTP delivery for mobile {}: {}", mobile_no, e);
+                                }
+                                (session_token, otp, is_new_user)
+                            }
+                        };
+
+                        // verify:otp has to come back with this from the same socket
+                        let nonce = AuthNonceManager::issue(&socket.id.to_string(), mobile_no, &session_token, policy.otp_expiry_secs);
+
+                        // The OTP is only echoed back in dev mode; otherwise it goes out via SMS/email
+                        let login_response = ApiResponse::success("login:success", LoginSuccess {
+                            message: "Login successful".to_string(),
+                            mobile_no: mobile_no.to_string(),
+                            device_id: device_id.to_string(),
+                            session_token,
+                            is_new_user,
+                            session_reused,
+                            nonce,
+                            otp: CONFIG.dev_mode.then_some(otp),
+                        }).for_socket(socket.id);
+                        // Retried on send failures so the session token is not lost
+                        let fallback = OfflineFallback { data_service: ds2.clone(), mobile_no: mobile_no.to_string(), notification_type: "login_undelivered" };
+                        FunnelManager::record(&socket.id.to_string(), FunnelStage::Login);
+                        match EmitQueue::emit(&socket, "login:success", login_response, Some(fallback)).await {
+                            Ok(_) => info!("✅ Login successful for mobile: {} (device: {}, socket: {})", mobile_no, device_id, socket.id),
+                            Err(e) => warn!("⚠️ Failed to emit login:success for mobile: {} (socket: {}): {}", mobile_no, socket.id, e),
+                        }
+                    }
+                    Err(error_details) => {
+                        info!("❌ Login failed for socket {}: {:?}", socket.id, error_details);
+                        ErrorResponder::send(&socket, &*ds2, error_details).await;
+                    }
+                }
+            })
+        });
+
+        // Handle OTP verification event
+        let ds3 = data_service.clone();
+        socket.on("verify:otp", move |socket: SocketRef, Data::<serde_json::Value>(data)| {
+            let ds3 = ds3.clone();
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope("verify:otp", socket.id, request_id, async move {
+                info!("🔢 Received OTP verification request from {}", socket.id);
+                let data = ProtocolManager::adapt(&socket, "verify:otp", data).await;
+
+                match ValidationManager::validate_otp_data(&data) {
+                    Ok(_) => {
+                        let mobile_no = data["mobile_no"].as_str().unwrap_or("unknown");
+                        let otp = data["otp"].as_str().unwrap_or("unknown");
+                        let session_token = data["session_token"].as_str().unwrap_or("unknown");
+
+                        let policy = match LoginPolicyManager::check(&socket, mobile_no) {
+                            Ok(policy) => policy,
+                            Err(error) => {
+                                info!("🚫 OTP verification refused from blocked region for mobile: {} (socket: {})", mobile_no, socket.id);
+                                ErrorResponder::send(&socket, &*ds3, error.on_event("otp:verification_failed")).await;
+                                return;
+                            }
+                        };
+
+                        // Check rate limiting before verification
+                        let rate_limit_check = ds3.check_otp_attempts(mobile_no, session_token, policy.max_verify_attempts).await;
+                        match rate_limit_check {
+                            Ok(is_allowed) => {
+                                if !is_allowed {
+                                    let error = ApiError::new("RATE_LIMIT_EXCEEDED", "AUTHENTICATION_ERROR", "otp", "Too many OTP verification attempts. Please try again later.")
+                                        .with_details(json!({
+                                            "mobile_no": mobile_no,
+                                            "max_attempts": policy.max_verify_attempts,
+                                            "otp_expiry_secs": policy.otp_expiry_secs
+                                        }))
+                                        .on_event("otp:verification_failed");
+                                    ErrorResponder::send(&socket, &*ds3, error).await;
+                                    info!("🚫 Rate limit exceeded for mobile: {} (socket: {})", mobile_no, socket.id);
+                                    return;
+                                }
+                            }
+                            Err(e) => {
+                                warn!("⚠️ Failed to check rate limit for mobile: {} (socket: {}): {}", mobile_no, socket.id, e);
+                                // Continue with verification if rate limit check fails
+                            }
+                        }
+
+                        // A ban placed after login:success still stops the session from opening
+                        if let Err(error) = ModerationManager::check_login(&*ds3, mobile_no).await {
+                            info!("🚫 OTP verification refused for suspended mobile: {} (socket: {})", mobile_no, socket.id);
+                            ErrorResponder::send(&socket, &*ds3, error.on_event("otp:verification_failed")).await;
+                            return;
+                        }
+
+                        // A replayed payload has no nonce issued to this socket
+                        let nonce = match AuthNonceManager::claim(&socket.id.to_string(), mobile_no, session_token, data["nonce"].as_str()) {
+                            Ok(nonce) => nonce,
+                            Err(error) => {
+                                info!("🔁 OTP verification refused without a valid nonce for mobile: {} (socket: {})", mobile_no, socket.id);
+                                ErrorResponder::send(&socket, &*ds3, error).await;
+                                return;
+                            }
+                        };
+
+                        // Verify the OTP
+                        let verify_result = ds3.verify_otp(&socket.id.to_string(), mobile_no, session_token, otp).await;
+                        if let Some(nonce) = nonce.filter(|_| !matches!(verify_result, Ok(crate::database::models::OtpVerificationResult::Success))) {
+                            AuthNonceManager::release(nonce);
+                        }
+                        if CONFIG.test_otp_for(mobile_no).is_some() {
+                            let is_success = matches!(verify_result, Ok(crate::database::models::OtpVerificationResult::Success));
+                            if let Err(e) = ds3.store_test_otp_audit_event(&socket.id.to_string(), mobile_no, None, "verify", is_success).await {
+                                error!("❌ Failed to store test OTP audit event for mobile {}: {}", mobile_no, e);
+                            }
+                        }
+                        match verify_result {
+                            Ok(verification_result) => {
+                                match verification_result {
+                                    crate::database::models::OtpVerificationResult::Success => {
+                                        // Get user info
+                                        let user_info = ds3.get_user_identity(mobile_no).await;
+                                        let (user_id, user_number) = match user_info {
+                                            Ok(Some(user)) => (user.user_id, user.user_number),
+                                            _ => {
+                                                // User not found, create new user
+                                                let (new_user_id, new_user_number) = ds3.register_new_user(
+                                                    mobile_no,
+                                                    data["device_id"].as_str().unwrap_or("unknown"),
+                                                    data["fcm_token"].as_str().unwrap_or("unknown"),
+                                                    data["email"].as_str()
+                                                ).await.unwrap_or(("unknown".to_string(), 0));
+                                                (new_user_id, new_user_number)
+                                            }
+                                        };
+
+                                        // Generate JWT token
+                                        let jwt_service = create_jwt_service();
+                                        let jwt_token = match jwt_service.generate_token(
+                                            &user_id,
+                                            user_number,
+                                            mobile_no,
+                                            data["device_id"].as_str().unwrap_or("unknown"),
+                                            data["fcm_token"].as_str().unwrap_or("unknown"),
+                                        ) {
+                                            Ok(token) => token,
+                                            Err(e) => {
+                                                error!("❌ Failed to generate JWT token: {}", e);
+                                                "".to_string()
+                                            }
+                                        };
+
+                                        // Check if user is new or old by checking if a profile has been set
+                                        let user_status = match ds3.get_user_profile(mobile_no).await {
+                                            Ok(Some(user)) => {
+                                                if user.full_name.is_some() {
+                                                    "existing_user"
+                                                } else {
+                                                    "new_user"
+                                                }
+                                            }
+                                            _ => "new_user", // Default to new_user if lookup fails, though it shouldn't
+                                        };
+
+                                        let success_response = ApiResponse::success("otp:verified", OtpVerified {
+                                            message: "OTP verification successful. Authentication completed.".to_string(),
+                                            mobile_no: mobile_no.to_string(),
+                                            session_token: session_token.to_string(),
+                                            user_id: user_id.clone(),
+                                            user_number,
+                                            user_status: user_status.to_string(),
+                                            jwt_token: jwt_token.clone(),
+                                            token_type: "Bearer".to_string(),
+                                            expires_in: 604800, // 7 days in seconds
+                                            signing_key: PayloadSigning::session_key(session_token),
+                                        }).for_socket(socket.id);
+
+                                        // Store OTP verification event with JWT token
+                                        let _ = ds3.store_otp_verification_event(
+                                            &socket.id.to_string(),
+                                            mobile_no,
+                                            session_token,
+                                            otp,
+                                            true,
+                                            Some(&user_id),
+                                            Some(user_number),
+                                            Some(&jwt_token)
+                                        ).await;
+
+                                        // Store user registration event if new user
+                                        if user_status == "new_user" {
+                                            let _ = ds3.store_user_registration_event(
+                                                &socket.id.to_string(),
+                                                &user_id,
+                                                user_number,
+                                                mobile_no,
+                                                data["device_id"].as_str().unwrap_or("unknown"),
+                                                data["fcm_token"].as_str().unwrap_or("unknown"),
+                                                data["email"].as_str()
+                                            ).await;
+                                        }
+
+                                        // Retried on send failures so a completed sign-in is not lost
+                                        let fallback = OfflineFallback { data_service: ds3.clone(), mobile_no: mobile_no.to_string(), notification_type: "login_undelivered" };
+                                        FunnelManager::record(&socket.id.to_string(), FunnelStage::OtpVerified);
+                                        match EmitQueue::emit(&socket, "otp:verified", success_response, Some(fallback)).await {
+                                            Ok(_) => info!("✅ OTP verification successful for mobile: {} (socket: {}, status: {}, user_id: {}, user_number: {})", mobile_no, socket.id, user_status, user_id, user_number),
+                                            Err(e) => warn!("⚠️ Failed to emit otp:verified for mobile: {} (socket: {}): {}", mobile_no, socket.id, e),
+                                        }
+                                    }
+                                    crate::database::models::OtpVerificationResult::Invalid => {
+                                        let error = ApiError::new("INVALID_OTP", "AUTHENTICATION_ERROR", "otp", "Invalid OTP. Please try again.")
+                                            .with_details(json!({
+                                                "mobile_no": mobile_no,
+                                                "otp_length": policy.otp_length,
+                                                "max_attempts": policy.max_verify_attempts
+                                            }))
+                                            .on_event("otp:verification_failed");
+                                        ErrorResponder::send(&socket, &*ds3, error).await;
+                                        info!("❌ OTP verification failed for mobile: {} (socket: {})", mobile_no, socket.id);
+                                    }
+                                    crate::database::models::OtpVerificationResult::Expired => {
+                                        let error = ApiError::new("OTP_EXPIRED", "AUTHENTICATION_ERROR", "otp", "OTP has expired. Please request a new OTP.")
+                                            .with_details(json!({
+                                                "mobile_no": mobile_no,
+                                                "otp_expiry_secs": policy.otp_expiry_secs
+                                            }))
+                                            .on_event("otp:verification_failed");
+                                        ErrorResponder::send(&socket, &*ds3, error).await;
+                                        info!("⏰ OTP expired for mobile: {} (socket: {})", mobile_no, socket.id);
+                                    }
+                                    crate::database::models::OtpVerificationResult::NotFound => {
+                                        let error = ApiError::new("SESSION_NOT_FOUND", "AUTHENTICATION_ERROR", "session_token", "Invalid session. Please login again.")
+                                            .with_details(json!({
+                                                "mobile_no": mobile_no
+                                            }))
+                                            .on_event("otp:verification_failed");
+                                        ErrorResponder::send(&socket, &*ds3, error).await;
+                                        info!("❌ Session not found for mobile: {} (socket: {})", mobile_no, socket.id);
+                                    }
+                                }
+                            }
+                            Err(e) => {
+                                let error_msg = e.to_string();
+                                let error = ApiError::system("OTP_VERIFICATION_ERROR", "otp", "OTP verification failed due to system error", &error_msg)
+                                    .on_event("otp:verification_failed");
+                                ErrorResponder::send(&socket, &*ds3, error).await;
+                                info!("❌ OTP verification system error for mobile: {} (socket: {}): {}", mobile_no, socket.id, error_msg);
+                            }
+                        }
+                    }
+                    Err(error_details) => {
+                        info!("❌ OTP verification validation failed for socket {}: {:?}", socket.id, error_details);
+                        ErrorResponder::send(&socket, &*ds3, ApiError::from(error_details).on_event("otp:verification_failed")).await;
+                    }
+                }
+            })
+        });
+    }
+}
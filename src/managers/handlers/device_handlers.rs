@@ -0,0 +1,50 @@
+use socketioxide::extract::{Data, SocketRef};
+use tracing::{info, warn};
+use std::sync::Arc;
+
+use crate::api::contracts::DeviceInfoAck;
+use crate::api::response::ApiResponse;
+use crate::database::store::DataStore;
+use crate::managers::chaos::FaultInjector;
+use crate::managers::correlation::Correlation;
+use crate::managers::devices::DeviceManager;
+use crate::managers::error_responder::ErrorResponder;
+use crate::managers::funnel::{FunnelManager, FunnelStage};
+use crate::managers::handlers::EventHandlers;
+use crate::managers::validation::ValidationManager;
+
+// device:info plus trusted device management (devices:list / devices:remove)
+pub struct DeviceHandlers;
+
+impl EventHandlers for DeviceHandlers {
+    fn register(&self, socket: &SocketRef, data_service: Arc<dyn DataStore>) {
+        // Handle device info event
+        let ds1 = data_service.clone();
+        socket.on("device:info", move |socket: SocketRef, Data::<serde_json::Value>(data)| {
+            let ds1 = ds1.clone();
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope("device:info", socket.id, request_id, async move {
+                info!("📱 Received device info from {}: {:?}", socket.id, data);
+                let _ = ds1.store_device_info_event(&socket.id.to_string(), &data).await;
+                match ValidationManager::validate_device_info(&data) {
+                    Ok(_) => {
+                        FunnelManager::record(&socket.id.to_string(), FunnelStage::DeviceInfo);
+                        let ack_response = ApiResponse::success("device:info:ack", DeviceInfoAck {
+                            message: "Device info received and validated".to_string(),
+                        }).for_socket(socket.id);
+                        match FaultInjector::emit(&socket, "device:info:ack", ack_response).await {
+                            Ok(_) => info!("Sent device info acknowledgment to: {}", socket.id),
+                            Err(e) => warn!("⚠️ Failed to emit device:info:ack for socket {}: {}", socket.id, e),
+                        }
+                    }
+                    Err(error_details) => {
+                        ErrorResponder::send(&socket, &*ds1, error_details).await;
+                    }
+                }
+            })
+        });
+
+        // Trusted device management (devices:list / devices:remove)
+        DeviceManager::register_device_events(socket, data_service.clone());
+    }
+}
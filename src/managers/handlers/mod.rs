@@ -0,0 +1,28 @@
+pub mod account_handlers;
+pub mod auth_handlers;
+pub mod device_handlers;
+pub mod profile_handlers;
+
+use socketioxide::extract::SocketRef;
+use std::sync::Arc;
+use crate::database::store::DataStore;
+
+pub use account_handlers::AccountHandlers;
+pub use auth_handlers::AuthHandlers;
+pub use device_handlers::DeviceHandlers;
+pub use profile_handlers::ProfileHandlers;
+
+// A group of related main-namespace events. EventManager calls register() for
+// every group on each new connection; a new domain only needs its own module
+// and an entry in MAIN_NAMESPACE_HANDLERS.
+pub trait EventHandlers: Send + Sync {
+    fn register(&self, socket: &SocketRef, data_service: Arc<dyn DataStore>);
+}
+
+// Registration order on the main namespace
+pub static MAIN_NAMESPACE_HANDLERS: &[&dyn EventHandlers] = &[
+    &DeviceHandlers,
+    &AuthHandlers,
+    &ProfileHandlers,
+    &AccountHandlers,
+];
@@ -0,0 +1,396 @@
+use socketioxide::extract::SocketRef;
+use serde_json::json;
+use tracing::{info, warn, error};
+use std::sync::Arc;
+
+use crate::api::contracts::{self, LanguageSet, ProfileSet};
+use crate::api::response::{ApiError, ApiResponse};
+use crate::database::store::DataStore;
+use crate::managers::auth_guard::AuthGuard;
+use crate::managers::chaos::FaultInjector;
+use crate::managers::error_responder::ErrorResponder;
+use crate::managers::handlers::EventHandlers;
+use crate::managers::challenges::ChallengeManager;
+use crate::managers::friends::FriendManager;
+use crate::managers::funnel::{FunnelManager, FunnelStage};
+use crate::managers::gifts::GiftManager;
+use crate::managers::promos::PromoManager;
+use crate::managers::moderation::ModerationManager;
+use crate::managers::preferences::PreferencesManager;
+use crate::managers::progress::ProgressManager;
+use crate::managers::seasons::SeasonManager;
+use crate::managers::settings::SettingsManager;
+use crate::managers::validation::ValidationManager;
+
+// Localized success messages structure
+#[derive(Debug, Clone)]
+struct LocalizedMessages {
+    welcome_message: String,
+    setup_complete: String,
+    ready_to_play: String,
+    next_steps: String,
+}
+
+// Function to get localized success messages based on language code
+fn get_localized_success_messages(language_code: &str) -> LocalizedMessages {
+    match language_code {
+        "en" => LocalizedMessages {
+            welcome_message: "Welcome to Game Admin! 🎮".to_string(),
+            setup_complete: "Setup completed successfully! ✅".to_string(),
+            ready_to_play: "You're all set to start gaming! 🚀".to_string(),
+            next_steps: "Explore the dashboard and start managing your game experience.".to_string(),
+        },
+        "es" => LocalizedMessages {
+            welcome_message: "¡Bienvenido a Game Admin! 🎮".to_string(),
+            setup_complete: "¡Configuración completada exitosamente! ✅".to_string(),
+            ready_to_play: "¡Estás listo para comenzar a jugar! 🚀".to_string(),
+            next_steps: "Explora el panel y comienza a gestionar tu experiencia de juego.".to_string(),
+        },
+        "fr" => LocalizedMessages {
+            welcome_message: "Bienvenue sur Game Admin ! 🎮".to_string(),
+            setup_complete: "Configuration terminée avec succès ! ✅".to_string(),
+            ready_to_play: "Vous êtes prêt à commencer à jouer ! 🚀".to_string(),
+            next_steps: "Explorez le tableau de bord et commencez à gérer votre expérience de jeu.".to_string(),
+        },
+        "de" => LocalizedMessages {
+            welcome_message: "Willkommen bei Game Admin! 🎮".to_string(),
+            setup_complete: "Setup erfolgreich abgeschlossen! ✅".to_string(),
+            ready_to_play: "Du bist bereit zum Spielen! 🚀".to_string(),
+            next_steps: "Erkunde das Dashboard und beginne mit der Verwaltung deines Spielerlebnisses.".to_string(),
+        },
+        "hi" => LocalizedMessages {
+            welcome_message: "Game Admin में आपका स्वागत है! 🎮".to_string(),
+            setup_complete: "सेटअप सफलतापूर्वक पूरा हुआ! ✅".to_string(),
+            ready_to_play: "आप गेमिंग शुरू करने के लिए तैयार हैं! 🚀".to_string(),
+            next_steps: "डैशबोर्ड का अन्वेषण करें और अपने गेमिंग अनुभव का प्रबंधन शुरू करें।".to_string(),
+        },
+        "zh" => LocalizedMessages {
+            welcome_message: "欢迎来到游戏管理！🎮".to_string(),
+            setup_complete: "设置成功完成！✅".to_string(),
+            ready_to_play: "您已准备好开始游戏！🚀".to_string(),
+            next_steps: "探索仪表板并开始管理您的游戏体验。".to_string(),
+        },
+        "ja" => LocalizedMessages {
+            welcome_message: "Game Adminへようこそ！🎮".to_string(),
+            setup_complete: "セットアップが正常に完了しました！✅".to_string(),
+            ready_to_play: "ゲームを始める準備ができました！🚀".to_string(),
+            next_steps: "ダッシュボードを探索し、ゲーム体験の管理を開始してください。".to_string(),
+        },
+        "ko" => LocalizedMessages {
+            welcome_message: "Game Admin에 오신 것을 환영합니다! 🎮".to_string(),
+            setup_complete: "설정이 성공적으로 완료되었습니다! ✅".to_string(),
+            ready_to_play: "게임을 시작할 준비가 되었습니다! 🚀".to_string(),
+            next_steps: "대시보드를 탐색하고 게임 경험 관리를 시작하세요.".to_string(),
+        },
+        "ar" => LocalizedMessages {
+            welcome_message: "مرحباً بك في إدارة الألعاب! 🎮".to_string(),
+            setup_complete: "تم إكمال الإعداد بنجاح! ✅".to_string(),
+            ready_to_play: "أنت جاهز لبدء اللعب! 🚀".to_string(),
+            next_steps: "استكشف لوحة التحكم وابدأ في إدارة تجربة اللعب الخاصة بك.".to_string(),
+        },
+        "pt" => LocalizedMessages {
+            welcome_message: "Bem-vindo ao Game Admin! 🎮".to_string(),
+            setup_complete: "Configuração concluída com sucesso! ✅".to_string(),
+            ready_to_play: "Você está pronto para começar a jogar! 🚀".to_string(),
+            next_steps: "Explore o painel e comece a gerenciar sua experiência de jogo.".to_string(),
+        },
+        "ru" => LocalizedMessages {
+            welcome_message: "Добро пожаловать в Game Admin! 🎮".to_string(),
+            setup_complete: "Настройка успешно завершена! ✅".to_string(),
+            ready_to_play: "Вы готовы начать играть! 🚀".to_string(),
+            next_steps: "Исследуйте панель управления и начните управлять своим игровым опытом.".to_string(),
+        },
+        _ => LocalizedMessages {
+            welcome_message: "Welcome to Game Admin! 🎮".to_string(),
+            setup_complete: "Setup completed successfully! ✅".to_string(),
+            ready_to_play: "You're all set to start gaming! 🚀".to_string(),
+            next_steps: "Explore the dashboard and start managing your game experience.".to_string(),
+        },
+    }
+}
+
+// set:profile, set:language and the preference stores
+pub struct ProfileHandlers;
+
+impl EventHandlers for ProfileHandlers {
+    fn register(&self, socket: &SocketRef, data_service: Arc<dyn DataStore>) {
+        // Handle user profile event
+        let ds4 = data_service.clone();
+        AuthGuard::require_session(socket, "set:profile", data_service.clone(), move |socket, data, auth| {
+            let ds4 = ds4.clone();
+            async move {
+                info!("👤 Received user profile request from {}: {:?}", socket.id, data);
+                if let Err(error_details) = ValidationManager::validate_user_profile_data(&data) {
+                    info!("❌ User profile validation failed for socket {}: {:?}", socket.id, error_details);
+                    ErrorResponder::send(&socket, &*ds4, error_details).await;
+                    return;
+                }
+
+                let mobile_no = auth.mobile_no.as_str();
+                let session_token = auth.session_token.as_deref().unwrap_or_default();
+                let full_name = data["full_name"].as_str().unwrap_or("unknown");
+                let state = data["state"].as_str().unwrap_or("unknown");
+                let referral_code = data["referral_code"].as_str().map(|s| s.to_string());
+                let referred_by = data["referred_by"].as_str().map(|s| s.to_string());
+                let profile_data = data.get("profile_data").cloned();
+                let date_of_birth = data["date_of_birth"].as_str();
+                let (user_id, user_number) = (auth.user.user_id.clone(), auth.user.user_number);
+
+                // The date of birth drives the age gate, so it is set only once
+                if let (Some(given), Some(stored)) = (date_of_birth, auth.user.date_of_birth.as_deref()) {
+                    if given != stored {
+                        let error = ApiError::new("DATE_OF_BIRTH_LOCKED", "VALIDATION_ERROR", "date_of_birth", "Date of birth cannot be changed once set");
+                        ErrorResponder::send(&socket, &*ds4, error).await;
+                        return;
+                    }
+                }
+
+                info!("🔍 [DEBUG] Extracted data - mobile: {}, name: {}, state: {}, user_id: {}", mobile_no, full_name, state, user_id);
+
+                // Check if referral code already exists (if provided)
+                let mut final_referral_code = referral_code;
+                let referred_by_code = referred_by;
+
+                if let Some(ref_code) = &final_referral_code {
+                    match ds4.check_referral_code_exists(ref_code).await {
+                        Ok(true) => {
+                            let error = ApiError::new("REFERRAL_CODE_EXISTS", "VALIDATION_ERROR", "referral_code", "Referral code already exists. Please choose a different one.")
+                                .with_details(json!({
+                                    "referral_code": ref_code
+                                }));
+                            ErrorResponder::send(&socket, &*ds4, error).await;
+                            info!("❌ User profile failed: Referral code already exists for mobile: {} (socket: {})", mobile_no, socket.id);
+                            return;
+                        }
+                        Ok(false) => {}
+                        Err(e) => {
+                            let error_msg = e.to_string();
+                            ErrorResponder::send(&socket, &*ds4, ApiError::system("REFERRAL_CODE_CHECK_ERROR", "referral_code", "Failed to check referral code due to system error", &error_msg)).await;
+                            info!("❌ User profile system error for mobile: {} (socket: {}): {}", mobile_no, socket.id, error_msg);
+                            return;
+                        }
+                    }
+                }
+
+                // Generate referral code if not provided
+                if final_referral_code.is_none() {
+                    match ds4.generate_unique_referral_code(mobile_no).await {
+                        Ok(code) => {
+                            info!("✅ Generated referral code: {} for mobile: {}", code, mobile_no);
+                            final_referral_code = Some(code);
+                        }
+                        Err(e) => {
+                            let error_msg = e.to_string();
+                            ErrorResponder::send(&socket, &*ds4, ApiError::system("REFERRAL_CODE_GENERATION_ERROR", "referral_code", "Failed to generate referral code due to system error", &error_msg)).await;
+                            info!("❌ User profile system error for mobile: {} (socket: {}): {}", mobile_no, socket.id, error_msg);
+                            return;
+                        }
+                    }
+                }
+
+                // Store user profile event
+                if let Err(e) = ds4.store_user_profile_event(
+                    &socket.id.to_string(),
+                    &user_id,
+                    user_number,
+                    mobile_no,
+                    full_name
+                ).await {
+                    warn!("Failed to store user profile event: {}", e);
+                }
+
+                // Also update userregister collection
+                match ds4.update_user_profile_in_register(
+                    mobile_no,
+                    Some(full_name.to_string()),
+                    Some(state.to_string()),
+                    final_referral_code.clone(),
+                    referred_by_code.clone(),
+                    profile_data.clone()
+                ).await {
+                    Ok(_) => {
+                        info!("✅ Successfully updated user profile in register for mobile: {}", mobile_no);
+                    }
+                    Err(e) => {
+                        error!("❌ Failed to update user profile in register for mobile {}: {}", mobile_no, e);
+                        // Continue with the flow even if update fails
+                    }
+                }
+                if let Some(date_of_birth) = date_of_birth.filter(|_| auth.user.date_of_birth.is_none()) {
+                    if let Err(e) = ds4.set_date_of_birth(mobile_no, date_of_birth).await {
+                        error!("❌ Failed to set date of birth for mobile {}: {}", mobile_no, e);
+                    }
+                }
+
+                let success_response = ApiResponse::success("profile:set", ProfileSet {
+                    message: "User profile updated successfully! 🎉".to_string(),
+                    mobile_no: mobile_no.to_string(),
+                    session_token: session_token.to_string(),
+                    full_name: full_name.to_string(),
+                    state: state.to_string(),
+                    referral_code: final_referral_code,
+                    referred_by: referred_by_code,
+                    profile_data,
+                    date_of_birth: date_of_birth.map(str::to_string).or(auth.user.date_of_birth.clone()),
+                    welcome_message: format!("Welcome {}! Your profile has been set up successfully.", full_name),
+                    next_steps: "You can now proceed to set your language preferences.".to_string(),
+                }).for_socket(socket.id);
+
+                FunnelManager::record(&socket.id.to_string(), FunnelStage::ProfileSet);
+                match FaultInjector::emit(&socket, "profile:set", success_response).await {
+                    Ok(_) => info!("✅ User profile successful for mobile: {} (name: {}, socket: {})", mobile_no, full_name, socket.id),
+                    Err(e) => warn!("⚠️ Failed to emit profile:set for mobile: {} (socket: {}): {}", mobile_no, socket.id, e),
+                }
+
+                // Add a small delay to ensure the message is sent
+                tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+            }
+        });
+
+        // Handle language setting event
+        let ds5 = data_service.clone();
+        AuthGuard::require_session(socket, "set:language", data_service.clone(), move |socket, data, auth| {
+            let ds5 = ds5.clone();
+            async move {
+                info!("🌐 Received language setting request from {}: {:?}", socket.id, data);
+                if let Err(error_details) = ValidationManager::validate_language_setting_data(&data) {
+                    info!("❌ Language setting validation failed for socket {}: {:?}", socket.id, error_details);
+                    ErrorResponder::send(&socket, &*ds5, error_details).await;
+                    return;
+                }
+
+                let mobile_no = auth.mobile_no.as_str();
+                let session_token = auth.session_token.as_deref().unwrap_or_default();
+                let language_code = data["language_code"].as_str().unwrap_or("unknown");
+                let language_name = data["language_name"].as_str().unwrap_or("unknown");
+                let region_code = data["region_code"].as_str();
+                let timezone = data["timezone"].as_str();
+                let user_preferences = data.get("user_preferences").cloned();
+
+                // Store language setting event
+                if let Err(e) = ds5.store_language_setting_event(
+                    &socket.id.to_string(),
+                    &auth.user.user_id,
+                    auth.user.user_number,
+                    mobile_no,
+                    language_code,
+                    language_name,
+                    region_code,
+                    timezone,
+                    user_preferences.as_ref().unwrap_or(&serde_json::json!({}))
+                ).await {
+                    warn!("Failed to store language setting event: {}", e);
+                }
+
+                // Also update userregister collection
+                match ds5.update_user_language_in_register(
+                    mobile_no,
+                    Some(language_code.to_string()),
+                    Some(language_name.to_string()),
+                    region_code.map(|s| s.to_string()),
+                    timezone.map(|s| s.to_string()),
+                    user_preferences.clone().unwrap_or_else(|| serde_json::json!({}))
+                ).await {
+                    Ok(_) => {
+                        info!("✅ Successfully updated user language in register for mobile: {}", mobile_no);
+                    }
+                    Err(e) => {
+                        error!("❌ Failed to update user language in register for mobile {}: {}", mobile_no, e);
+                        // Continue with the flow even if update fails
+                    }
+                }
+
+                // Prepare success response with localized messages
+                let success_messages = get_localized_success_messages(language_code);
+                let success_response = ApiResponse::success("language:set", LanguageSet {
+                    message: success_messages.welcome_message.clone(),
+                    mobile_no: mobile_no.to_string(),
+                    session_token: session_token.to_string(),
+                    language_code: language_code.to_string(),
+                    language_name: language_name.to_string(),
+                    region_code: region_code.map(|s| s.to_string()),
+                    timezone: timezone.map(|s| s.to_string()),
+                    user_preferences: user_preferences.clone(),
+                    localized_messages: contracts::LocalizedMessages {
+                        welcome: success_messages.welcome_message,
+                        setup_complete: success_messages.setup_complete,
+                        ready_to_play: success_messages.ready_to_play,
+                        next_steps: success_messages.next_steps,
+                    },
+                }).for_socket(socket.id);
+
+                match FaultInjector::emit(&socket, "language:set", success_response).await {
+                    Ok(_) => info!("✅ Language setting successful for mobile: {} (language: {}, socket: {})", mobile_no, language_code, socket.id),
+                    Err(e) => warn!("⚠️ Failed to emit language:set for mobile: {} (socket: {}): {}", mobile_no, socket.id, e),
+                }
+
+                // Add a small delay to ensure the message is sent
+                tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+            }
+        });
+
+        // Handle notification preferences event
+        let ds6 = data_service.clone();
+        AuthGuard::require_session(socket, "preferences:notifications", data_service.clone(), move |socket, data, auth| {
+            let ds6 = ds6.clone();
+            async move {
+                info!("🔔 Received notification preferences from {}: {:?}", socket.id, data);
+                if let Err(error_details) = ValidationManager::validate_notification_preferences_data(&data) {
+                    info!("❌ Notification preferences validation failed for socket {}: {:?}", socket.id, error_details);
+                    ErrorResponder::send(&socket, &*ds6, error_details).await;
+                    return;
+                }
+                let mobile_no = auth.mobile_no.as_str();
+                let user = &auth.user;
+
+                // Merge the partial update into the stored preferences
+                let mut notifications = ds6.get_notification_preferences(&user.user_id).await.unwrap_or_default();
+                notifications.apply_update(&data["notifications"]);
+
+                if let Err(e) = ds6.update_notification_preferences(&user.user_id, mobile_no, &notifications).await {
+                    error!("❌ Failed to update notification preferences for mobile {}: {}", mobile_no, e);
+                    ErrorResponder::send(&socket, &*ds6, ApiError::system("PREFERENCES_UPDATE_FAILED", "notifications", "Failed to save notification preferences", &e)).await;
+                    return;
+                }
+
+                let success_response = ApiResponse::success("preferences:notifications:updated", json!({
+                    "message": "Notification preferences updated",
+                    "mobile_no": mobile_no,
+                    "user_id": user.user_id,
+                    "notifications": notifications
+                })).for_socket(socket.id);
+                match FaultInjector::emit(&socket, "preferences:notifications:updated", success_response).await {
+                    Ok(_) => info!("✅ Notification preferences updated for mobile: {} (socket: {})", mobile_no, socket.id),
+                    Err(e) => warn!("⚠️ Failed to emit preferences:notifications:updated for mobile: {} (socket: {}): {}", mobile_no, socket.id, e),
+                }
+            }
+        });
+
+        // Generic key-value preferences (preferences:get / preferences:set)
+        PreferencesManager::register_preference_events(socket, data_service.clone());
+
+        // Typed accessibility and gameplay settings (settings:get / settings:set)
+        SettingsManager::register_events(socket, data_service.clone());
+
+        // Levels, XP and per-game stats (progress:get / progress:update)
+        ProgressManager::register_progress_events(socket, data_service.clone());
+
+        // Daily challenges (challenge:today / challenge:claim)
+        ChallengeManager::register_challenge_events(socket, data_service.clone());
+
+        // Season leaderboards (leaderboard:get)
+        SeasonManager::register_season_events(socket, data_service.clone());
+
+        // Friends list (friend:add / friend:remove / friend:list)
+        FriendManager::register_friend_events(socket, data_service.clone());
+
+        // Gifts between friends and the inventory they draw on (gift:send / inventory:get)
+        GiftManager::register_gift_events(socket, data_service.clone());
+
+        // Promo codes set up by operators (promo:redeem)
+        PromoManager::register_promo_events(socket, data_service.clone());
+
+        // Blocking and reporting other users (user:block / user:unblock / user:report)
+        ModerationManager::register_moderation_events(socket, data_service.clone());
+    }
+}
@@ -0,0 +1,109 @@
+use axum::extract::Request;
+use axum::http::header;
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+use crate::api::middleware::ClientIp;
+use crate::config::CONFIG;
+use crate::database::models::RejectedHandshakeEvent;
+use crate::database::store::DataStore;
+use crate::managers::correlation::Correlation;
+
+// Longest path and header values stored; probes send arbitrary junk
+const MAX_FIELD_LEN: usize = 512;
+
+#[derive(Default)]
+struct AuditState {
+    rejected: BTreeMap<(&'static str, u16), u64>,   // By (reason, status), since start
+    minute: Option<DateTime<Utc>>,                  // Start of the current audit minute
+    audited: u32,                                   // Stored this minute
+    skipped: u32,                                   // Over the limit this minute
+}
+
+static STATE: Lazy<Mutex<AuditState>> = Lazy::new(|| Mutex::new(AuditState::default()));
+
+// Visibility into requests the HTTP middleware turns away before they reach
+// Socket.IO: every one is counted for /metrics by reason, and up to
+// HANDSHAKE_AUDIT_PER_MINUTE a minute are logged and stored in the capped
+// rejected_handshakes collection (path, client IP, User-Agent, Origin), so a
+// flood of probes cannot flood the logs or MongoDB as well.
+pub struct HandshakeAuditManager;
+
+impl HandshakeAuditManager {
+    fn header(request: &Request, name: header::HeaderName) -> Option<String> {
+        request.headers().get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(Self::truncate)
+    }
+
+    fn truncate(value: &str) -> String {
+        match value.char_indices().nth(MAX_FIELD_LEN) {
+            Some((end, _)) => value[..end].to_string(),
+            None => value.to_string(),
+        }
+    }
+
+    // Count a refusal; true when it is within this minute's audit budget
+    fn admit(reason: &'static str, status: u16) -> bool {
+        let mut state = STATE.lock().unwrap_or_else(|e| e.into_inner());
+        *state.rejected.entry((reason, status)).or_default() += 1;
+        let now = Utc::now();
+        if !matches!(state.minute, Some(minute) if now - minute < chrono::Duration::minutes(1)) {
+            if state.skipped > 0 {
+                warn!("🚫 {} more rejected requests in the last minute were not logged", state.skipped);
+            }
+            state.minute = Some(now);
+            state.audited = 0;
+            state.skipped = 0;
+        }
+        if state.audited >= CONFIG.handshake_audit_per_minute {
+            state.skipped += 1;
+            return false;
+        }
+        state.audited += 1;
+        true
+    }
+
+    // Record a request refused with `status`; storing happens in the background
+    pub fn record(data_service: &Arc<dyn DataStore>, request: &Request, reason: &'static str, status: u16) {
+        if !Self::admit(reason, status) {
+            return;
+        }
+        let event = RejectedHandshakeEvent {
+            id: None,
+            request_id: Correlation::current(),
+            reason: reason.to_string(),
+            status,
+            method: request.method().to_string(),
+            path: Self::truncate(request.uri().path()),
+            ip_address: request.extensions().get::<ClientIp>().map(|client| client.ip.to_string()),
+            user_agent: Self::header(request, header::USER_AGENT),
+            origin: Self::header(request, header::ORIGIN),
+            timestamp: bson::DateTime::now(),
+        };
+        warn!("🚫 Rejected {} {} from {} ({}): {}", event.method, event.path,
+              event.ip_address.as_deref().unwrap_or("unknown"), event.user_agent.as_deref().unwrap_or("no user agent"), reason);
+        let data_service = data_service.clone();
+        tokio::spawn(async move {
+            if let Err(e) = data_service.store_rejected_handshake(event).await {
+                warn!("⚠️ Failed to store rejected handshake: {}", e);
+            }
+        });
+    }
+
+    // Prometheus text for /metrics
+    pub fn render() -> String {
+        let state = STATE.lock().unwrap_or_else(|e| e.into_inner());
+        if state.rejected.is_empty() {
+            return String::new();
+        }
+        let mut output = String::from("# TYPE http_rejected_requests_total counter\n");
+        for ((reason, status), count) in &state.rejected {
+            output.push_str(&format!("http_rejected_requests_total{{reason=\"{}\",status=\"{}\"}} {}\n", reason, status, count));
+        }
+        output
+    }
+}
@@ -0,0 +1,230 @@
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use socketioxide::SocketIo;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::api::response::ApiResponse;
+use crate::config::CONFIG;
+use crate::managers::handler_metrics::DURATION_BUCKETS_MS;
+use crate::managers::scheduler::Scheduler;
+
+// Room of the /admin namespace that gets health:score
+pub const HEALTH_ROOM: &str = "health";
+// Scores below this are degraded; below HEALTH_ROLLBACK_SCORE unhealthy
+const DEGRADED_BELOW: u32 = 80;
+// How much each signal weighs in the score
+const ERROR_WEIGHT: f64 = 0.5;
+const LATENCY_WEIGHT: f64 = 0.3;
+const CONNECTION_WEIGHT: f64 = 0.2;
+
+// What happened on this server during one HEALTH_SCORE_INTERVAL_SECS
+#[derive(Debug, Clone, Copy, Default)]
+struct Interval {
+    calls: u64,
+    errors: u64,
+    buckets: [u64; DURATION_BUCKETS_MS.len()],
+    slower: u64,            // Calls above the largest bucket
+    connects: u64,
+    disconnects: u64,
+}
+
+#[derive(Default)]
+struct History {
+    intervals: VecDeque<Interval>,
+    unhealthy_checks: u32,  // Consecutive checks below HEALTH_ROLLBACK_SCORE
+    latest: Option<Value>,
+}
+
+static CURRENT: Lazy<Mutex<Interval>> = Lazy::new(|| Mutex::new(Interval::default()));
+static HISTORY: Lazy<Mutex<History>> = Lazy::new(|| Mutex::new(History::default()));
+static STARTED_AT: Lazy<DateTime<Utc>> = Lazy::new(Utc::now);
+
+// Deployment health signal for rollout automation: one score from 0 to 100
+// for this server over the last HEALTH_SCORE_WINDOW_SECS, built from the
+// handler error rate (against HEALTH_MAX_ERROR_RATE), p99 handler latency
+// (against HEALTH_P99_TARGET_MS) and whether clients are reconnecting in a
+// storm (the last interval's connects at HEALTH_STORM_FACTOR times the
+// window's average). rollback_recommended turns on once the score stayed
+// below HEALTH_ROLLBACK_SCORE for HEALTH_ROLLBACK_CHECKS checks in a row.
+// Served at /api/admin/health-score and sent as health:score to /admin
+// sockets that sent health:subscribe, every HEALTH_SCORE_INTERVAL_SECS.
+pub struct HealthScoreManager;
+
+impl HealthScoreManager {
+    pub fn record_call(elapsed: Duration, failed: bool) {
+        let ms = elapsed.as_secs_f64() * 1000.0;
+        let mut current = CURRENT.lock().unwrap_or_else(|e| e.into_inner());
+        match DURATION_BUCKETS_MS.iter().position(|bound| ms <= *bound) {
+            Some(bucket) => current.buckets[bucket] += 1,
+            None => current.slower += 1,
+        }
+        current.calls += 1;
+        if failed {
+            current.errors += 1;
+        }
+    }
+
+    pub fn record_connect() {
+        CURRENT.lock().unwrap_or_else(|e| e.into_inner()).connects += 1;
+    }
+
+    pub fn record_disconnect() {
+        CURRENT.lock().unwrap_or_else(|e| e.into_inner()).disconnects += 1;
+    }
+
+    // The score of the last check; computed from the current window before the first one
+    pub fn latest() -> Value {
+        let history = HISTORY.lock().unwrap_or_else(|e| e.into_inner());
+        match &history.latest {
+            Some(latest) => latest.clone(),
+            None => Self::evaluate(&history),
+        }
+    }
+
+    fn component(value: f64) -> f64 {
+        (value.clamp(0.0, 1.0) * 1000.0).round() / 1000.0
+    }
+
+    // Upper bound of the bucket holding the p99 call; None above the largest bucket
+    fn p99_ms(window: &Interval) -> Option<f64> {
+        let target = (window.calls as f64 * 0.99).ceil() as u64;
+        let mut seen = 0;
+        for (bound, count) in DURATION_BUCKETS_MS.iter().zip(window.buckets) {
+            seen += count;
+            if seen >= target {
+                return Some(*bound);
+            }
+        }
+        None
+    }
+
+    fn evaluate(history: &History) -> Value {
+        let mut window = Interval::default();
+        for interval in &history.intervals {
+            window.calls += interval.calls;
+            window.errors += interval.errors;
+            window.slower += interval.slower;
+            window.connects += interval.connects;
+            window.disconnects += interval.disconnects;
+            for (total, count) in window.buckets.iter_mut().zip(interval.buckets) {
+                *total += count;
+            }
+        }
+
+        // Too few calls say nothing about errors or latency
+        let enough_calls = window.calls >= CONFIG.health_min_calls;
+        let error_rate = (window.calls > 0).then(|| window.errors as f64 / window.calls as f64);
+        let p99_ms = (window.calls > 0).then(|| Self::p99_ms(&window)).flatten();
+        let error_score = match error_rate {
+            Some(rate) if enough_calls => 1.0 - rate / CONFIG.health_max_error_rate,
+            _ => 1.0,
+        };
+        let latency_score = match p99_ms {
+            _ if !enough_calls => 1.0,
+            Some(p99) => 2.0 - p99 / CONFIG.health_p99_target_ms,
+            None => 0.0,
+        };
+
+        // A storm is the latest interval against the average of the ones before it
+        let recent_connects = history.intervals.back().map_or(0, |i| i.connects);
+        let earlier = history.intervals.len().saturating_sub(1);
+        let baseline = match earlier {
+            0 => 0.0,
+            n => (window.connects - recent_connects) as f64 / n as f64,
+        };
+        let storm = recent_connects >= CONFIG.health_storm_min_connects
+            && recent_connects as f64 >= CONFIG.health_storm_factor * baseline.max(1.0);
+        let connection_score = if storm { 0.0 } else { 1.0 };
+
+        let (error_score, latency_score, connection_score) = (Self::component(error_score), Self::component(latency_score), Self::component(connection_score));
+        let score = ((ERROR_WEIGHT * error_score + LATENCY_WEIGHT * latency_score + CONNECTION_WEIGHT * connection_score) * 100.0).round() as u32;
+        let health = if score < CONFIG.health_rollback_score {
+            "unhealthy"
+        } else if score < DEGRADED_BELOW {
+            "degraded"
+        } else {
+            "healthy"
+        };
+        let now = Utc::now();
+        json!({
+            "score": score,
+            "health": health,
+            "rollback_recommended": history.unhealthy_checks >= CONFIG.health_rollback_checks,
+            "unhealthy_checks": history.unhealthy_checks,
+            "version": env!("CARGO_PKG_VERSION"),
+            "environment": CONFIG.environment,
+            "started_at": STARTED_AT.to_rfc3339(),
+            "uptime_secs": (now - *STARTED_AT).num_seconds(),
+            "window_secs": history.intervals.len() as u64 * CONFIG.health_score_interval_secs,
+            "signals": {
+                "calls": window.calls,
+                "errors": window.errors,
+                "error_rate": error_rate,
+                "p99_ms": p99_ms,
+                "calls_above_largest_bucket": window.slower,
+                "connects_last_interval": recent_connects,
+                "connects_per_interval_baseline": (baseline * 10.0).round() / 10.0,
+                "disconnects": window.disconnects,
+                "reconnect_storm": storm,
+            },
+            "components": {
+                "errors": error_score,
+                "latency": latency_score,
+                "connections": connection_score,
+            },
+            "thresholds": {
+                "max_error_rate": CONFIG.health_max_error_rate,
+                "p99_target_ms": CONFIG.health_p99_target_ms,
+                "storm_factor": CONFIG.health_storm_factor,
+                "storm_min_connects": CONFIG.health_storm_min_connects,
+                "min_calls": CONFIG.health_min_calls,
+                "rollback_score": CONFIG.health_rollback_score,
+                "rollback_checks": CONFIG.health_rollback_checks,
+            },
+            "at": now.to_rfc3339(),
+        })
+    }
+
+    // Close the current interval, score the window and send health:score
+    pub fn spawn_monitor(io: SocketIo) {
+        Lazy::force(&STARTED_AT);
+        let period = Duration::from_secs(CONFIG.health_score_interval_secs);
+        let intervals_in_window = (CONFIG.health_score_window_secs / CONFIG.health_score_interval_secs).max(1) as usize;
+        Scheduler::every_server_wide("health-score", period, move || {
+            let io = io.clone();
+            async move {
+                let finished = std::mem::take(&mut *CURRENT.lock().unwrap_or_else(|e| e.into_inner()));
+                let health = {
+                    let mut history = HISTORY.lock().unwrap_or_else(|e| e.into_inner());
+                    history.intervals.push_back(finished);
+                    while history.intervals.len() > intervals_in_window {
+                        history.intervals.pop_front();
+                    }
+                    let mut health = Self::evaluate(&history);
+                    let unhealthy = health["health"] == "unhealthy";
+                    history.unhealthy_checks = if unhealthy { history.unhealthy_checks + 1 } else { 0 };
+                    let rollback = history.unhealthy_checks >= CONFIG.health_rollback_checks;
+                    if rollback && !health["rollback_recommended"].as_bool().unwrap_or(false) {
+                        warn!("🚨 Health score {} for {} checks in a row - rollback recommended", health["score"], history.unhealthy_checks);
+                    } else if !rollback && health["rollback_recommended"].as_bool().unwrap_or(false) {
+                        info!("✅ Health score back to {}", health["score"]);
+                    }
+                    health["rollback_recommended"] = json!(rollback);
+                    health["unhealthy_checks"] = json!(history.unhealthy_checks);
+                    history.latest = Some(health.clone());
+                    health
+                };
+                if let Some(ns) = io.of("/admin") {
+                    if let Err(e) = ns.to(HEALTH_ROOM).emit("health:score", ApiResponse::success("health:score", health)) {
+                        warn!("⚠️ Failed to broadcast health:score: {}", e);
+                    }
+                }
+                Ok(())
+            }
+        });
+    }
+}
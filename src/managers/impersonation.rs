@@ -0,0 +1,335 @@
+use chrono::{DateTime, Duration, Utc};
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use socketioxide::{extract::SocketRef, socket::Sid};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tracing::{info, warn};
+
+use crate::api::response::{ApiError, ApiResponse};
+use crate::config::CONFIG;
+use crate::database::models::ImpersonationSession;
+use crate::database::store::DataStore;
+use crate::managers::connection::ConnectionManager;
+use crate::managers::namespace_policy::ADMIN_NAMESPACE;
+use crate::managers::rbac::AdminIdentity;
+use crate::managers::scheduler::Scheduler;
+use crate::managers::snowflake::Snowflake;
+use crate::managers::tenant::{Tenant, TenantManager};
+
+// How often expired impersonations are looked for
+const EXPIRY_CHECK_SECS: u64 = 15;
+const MAX_REASON_LENGTH: usize = 500;
+// Credentials never copied to an operator, at any depth of a mirrored payload
+const REDACTED_FIELDS: &[&str] = &[
+    "secret", "otpauth_uri", "jwt_token", "signing_key", "session_token", "otp", "nonce",
+    "token", "refresh_token", "admin_token", "fcm_token", "pin", "password",
+];
+
+// A user's authenticated socket
+struct Bound {
+    user_id: String,
+    tenant_id: String,
+    namespace: String,
+    socket_id: Sid,
+}
+
+// A running impersonation
+struct Mirror {
+    operator_id: String,
+    user_id: String,
+    tenant: &'static Tenant,
+    admin_id: Sid,                  // The operator's socket on /admin
+    expires_at: DateTime<Utc>,
+    disclosed: bool,
+    events_mirrored: u64,
+}
+
+// Sockets that authenticated as a user, by socket id
+static SOCKETS: Lazy<Mutex<HashMap<String, Bound>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+// Running impersonations by session_id
+static SESSIONS: Lazy<Mutex<HashMap<String, Mirror>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// Read-only support impersonation. An operator with users:impersonate sends
+// impersonate:start on /admin with the user and a reason; from then on every
+// emit sent to one of the user's own sockets (see FaultInjector::emit) is
+// copied to the operator as impersonate:event, without the credentials in
+// REDACTED_FIELDS. Room broadcasts are not copied. The operator's socket
+// stays on /admin, so it cannot act as the user. A session covers one user,
+// one per admin socket, and ends on impersonate:stop, when the admin socket
+// disconnects, or after at most IMPERSONATION_MAX_MINUTES. Each one is
+// recorded in impersonation_sessions before anything is mirrored. With
+// IMPERSONATION_DISCLOSE the user's sockets get support:session when it
+// starts and ends.
+pub struct ImpersonationManager;
+
+impl ImpersonationManager {
+    // Remember which user a socket authenticated as
+    pub fn bind(socket: &SocketRef, user_id: &str) {
+        let tenant_id = TenantManager::current().tenant_id.clone();
+        {
+            let mut sockets = SOCKETS.lock().unwrap_or_else(|e| e.into_inner());
+            sockets.retain(|_, bound| ConnectionManager::socket(&bound.namespace, bound.socket_id).is_some());
+            if sockets.get(&socket.id.to_string()).is_some_and(|bound| bound.user_id == user_id) {
+                return;
+            }
+            sockets.insert(socket.id.to_string(), Bound {
+                user_id: user_id.to_string(),
+                tenant_id: tenant_id.clone(),
+                namespace: socket.ns().to_string(),
+                socket_id: socket.id,
+            });
+        }
+        // A socket joining while its user is watched is told too
+        let disclosed: Vec<(String, DateTime<Utc>)> = SESSIONS.lock().unwrap_or_else(|e| e.into_inner()).iter()
+            .filter(|(_, mirror)| mirror.disclosed && mirror.user_id == user_id && mirror.tenant.tenant_id == tenant_id)
+            .map(|(session_id, mirror)| (session_id.clone(), mirror.expires_at))
+            .collect();
+        for (session_id, expires_at) in disclosed {
+            Self::disclose_to(socket, &session_id, Some(expires_at));
+        }
+    }
+
+    // Whether emits to `socket` are being mirrored
+    pub fn watched(socket: &SocketRef) -> bool {
+        let sessions = SESSIONS.lock().unwrap_or_else(|e| e.into_inner());
+        if sessions.is_empty() {
+            return false;
+        }
+        let sockets = SOCKETS.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(bound) = sockets.get(&socket.id.to_string()) else {
+            return false;
+        };
+        sessions.values().any(|mirror| mirror.user_id == bound.user_id && mirror.tenant.tenant_id == bound.tenant_id)
+    }
+
+    // Drop credential fields from a payload before it is mirrored
+    fn redact(data: &mut Value) {
+        match data {
+            Value::Object(fields) => {
+                fields.retain(|key, _| !REDACTED_FIELDS.contains(&key.as_str()));
+                fields.values_mut().for_each(Self::redact);
+            }
+            Value::Array(items) => items.iter_mut().for_each(Self::redact),
+            _ => {}
+        }
+    }
+
+    // Copy an emit sent to a watched socket to the operators watching its user
+    pub fn mirror(socket: &SocketRef, event: &str, mut data: Value) {
+        let Some((user_id, tenant_id)) = SOCKETS.lock().unwrap_or_else(|e| e.into_inner())
+            .get(&socket.id.to_string())
+            .map(|bound| (bound.user_id.clone(), bound.tenant_id.clone())) else {
+            return;
+        };
+        Self::redact(&mut data);
+        let now = Utc::now();
+        let mut sessions = SESSIONS.lock().unwrap_or_else(|e| e.into_inner());
+        for (session_id, mirror) in sessions.iter_mut() {
+            // Expired sessions are closed by the expiry job; nothing more is sent meanwhile
+            if mirror.user_id != user_id || mirror.tenant.tenant_id != tenant_id || mirror.expires_at <= now {
+                continue;
+            }
+            let copy = ApiResponse::success("impersonate:event", json!({
+                "session_id": session_id,
+                "user_id": user_id,
+                "socket_id": socket.id.to_string(),
+                "namespace": socket.ns(),
+                "event": event,
+                "data": data,
+                "sent_at": now.to_rfc3339(),
+            }));
+            let Some(admin) = ConnectionManager::socket(ADMIN_NAMESPACE, mirror.admin_id) else {
+                continue;
+            };
+            match admin.emit("impersonate:event", copy) {
+                Ok(()) => mirror.events_mirrored += 1,
+                Err(e) => warn!("⚠️ Failed to mirror {} to admin socket {}: {}", event, mirror.admin_id, e),
+            }
+        }
+    }
+
+    fn disclose_to(socket: &SocketRef, session_id: &str, expires_at: Option<DateTime<Utc>>) {
+        let notice = ApiResponse::success("support:session", json!({
+            "session_id": session_id,
+            "active": expires_at.is_some(),
+            "expires_at": expires_at.map(|at| at.to_rfc3339()),
+        })).for_socket(socket.id);
+        if let Err(e) = socket.emit("support:session", notice) {
+            warn!("⚠️ Failed to send support:session to socket {}: {}", socket.id, e);
+        }
+    }
+
+    // Tell every socket of the user that support started (or stopped) watching
+    fn disclose(user_id: &str, tenant_id: &str, session_id: &str, expires_at: Option<DateTime<Utc>>) {
+        let sockets: Vec<SocketRef> = SOCKETS.lock().unwrap_or_else(|e| e.into_inner()).values()
+            .filter(|bound| bound.user_id == user_id && bound.tenant_id == tenant_id)
+            .filter_map(|bound| ConnectionManager::socket(&bound.namespace, bound.socket_id))
+            .collect();
+        for socket in &sockets {
+            Self::disclose_to(socket, session_id, expires_at);
+        }
+    }
+
+    fn impersonation_error(code: &str, field: &str, message: &str, details: Value) -> ApiError {
+        ApiError::new(code, "IMPERSONATION_ERROR", field, message).with_details(details).on_event("admin:error")
+    }
+
+    // impersonate:start { user_id, reason, duration_minutes } from an admin socket
+    pub async fn start(admin: &SocketRef, data_service: &dyn DataStore, identity: &AdminIdentity, data: &Value) -> Result<ImpersonationSession, ApiError> {
+        let user_id = data["user_id"].as_str().map(str::trim).unwrap_or_default();
+        let reason = data["reason"].as_str().map(str::trim).unwrap_or_default();
+        if user_id.is_empty() {
+            return Err(Self::impersonation_error("INVALID_USER_ID", "user_id", "user_id is required", json!({})));
+        }
+        if reason.is_empty() || reason.len() > MAX_REASON_LENGTH {
+            return Err(Self::impersonation_error("INVALID_REASON", "reason", "A reason of up to 500 characters is required", json!({
+                "max_length": MAX_REASON_LENGTH
+            })));
+        }
+        let minutes = match data.get("duration_minutes").filter(|v| !v.is_null()) {
+            None => CONFIG.impersonation_max_minutes,
+            Some(value) => match value.as_i64().filter(|m| (1..=CONFIG.impersonation_max_minutes).contains(m)) {
+                Some(minutes) => minutes,
+                None => return Err(Self::impersonation_error("INVALID_DURATION", "duration_minutes", "duration_minutes out of range", json!({
+                    "min": 1,
+                    "max": CONFIG.impersonation_max_minutes,
+                    "received_value": value
+                }))),
+            },
+        };
+        if let Some((session_id, _)) = SESSIONS.lock().unwrap_or_else(|e| e.into_inner()).iter().find(|(_, mirror)| mirror.admin_id == admin.id) {
+            return Err(Self::impersonation_error("IMPERSONATION_ACTIVE", "user_id", "Stop the current impersonation first", json!({
+                "session_id": session_id
+            })));
+        }
+        match data_service.get_user_by_id(user_id).await {
+            Ok(Some(_)) => {}
+            Ok(None) => return Err(Self::impersonation_error("USER_NOT_FOUND", "user_id", "No such user", json!({ "user_id": user_id }))),
+            Err(e) => return Err(ApiError::system("IMPERSONATION_FAILED", "user_id", "Failed to look up the user", &e).on_event("admin:error")),
+        }
+
+        let now = Utc::now();
+        let expires_at = now + Duration::minutes(minutes);
+        let to_bson = |time: DateTime<Utc>| bson::DateTime::from_millis(time.timestamp_millis());
+        let session = ImpersonationSession {
+            id: None,
+            session_id: Snowflake::generate(),
+            operator_id: identity.operator_id.clone(),
+            user_id: user_id.to_string(),
+            reason: reason.to_string(),
+            disclosed: CONFIG.impersonation_disclose,
+            started_at: to_bson(now),
+            expires_at: to_bson(expires_at),
+            ended_at: None,
+            end_reason: None,
+            events_mirrored: 0,
+        };
+        // Nothing is mirrored without an audit record
+        if let Err(e) = data_service.start_impersonation(session.clone()).await {
+            return Err(ApiError::system("IMPERSONATION_FAILED", "user_id", "Failed to record the impersonation", &e).on_event("admin:error"));
+        }
+        let tenant = TenantManager::current();
+        SESSIONS.lock().unwrap_or_else(|e| e.into_inner()).insert(session.session_id.clone(), Mirror {
+            operator_id: identity.operator_id.clone(),
+            user_id: user_id.to_string(),
+            tenant,
+            admin_id: admin.id,
+            expires_at,
+            disclosed: session.disclosed,
+            events_mirrored: 0,
+        });
+        if session.disclosed {
+            Self::disclose(user_id, &tenant.tenant_id, &session.session_id, Some(expires_at));
+        }
+        warn!("🕵️ Operator {} is impersonating user {} until {} ({}): {}", identity.operator_id, user_id, expires_at.to_rfc3339(), session.session_id, reason);
+        Ok(session)
+    }
+
+    // End one impersonation and close its audit record
+    pub async fn stop(data_service: &dyn DataStore, session_id: &str, end_reason: &str) {
+        let Some(mirror) = SESSIONS.lock().unwrap_or_else(|e| e.into_inner()).remove(session_id) else {
+            return;
+        };
+        if mirror.disclosed {
+            Self::disclose(&mirror.user_id, &mirror.tenant.tenant_id, session_id, None);
+        }
+        let ended = ApiResponse::success("impersonate:ended", json!({
+            "session_id": session_id,
+            "user_id": mirror.user_id,
+            "reason": end_reason,
+            "events_mirrored": mirror.events_mirrored,
+        })).for_socket(mirror.admin_id);
+        if let Some(admin) = ConnectionManager::socket(ADMIN_NAMESPACE, mirror.admin_id) {
+            let _ = admin.emit("impersonate:ended", ended);
+        }
+        let recorded = TenantManager::scope(mirror.tenant, data_service.end_impersonation(session_id, end_reason, mirror.events_mirrored)).await;
+        if let Err(e) = recorded {
+            warn!("⚠️ Failed to record the end of impersonation {}: {}", session_id, e);
+        }
+        info!("🕵️ Impersonation {} of user {} by {} ended ({}, {} events mirrored)", session_id, mirror.user_id, mirror.operator_id, end_reason, mirror.events_mirrored);
+    }
+
+    // The impersonation an admin socket is running, if any
+    pub fn session_of(admin_socket_id: &str) -> Option<String> {
+        SESSIONS.lock().unwrap_or_else(|e| e.into_inner()).iter()
+            .find(|(_, mirror)| mirror.admin_id.to_string() == admin_socket_id)
+            .map(|(session_id, _)| session_id.clone())
+    }
+
+    pub fn spawn_expiry(data_service: Arc<dyn DataStore>) {
+        let period = std::time::Duration::from_secs(EXPIRY_CHECK_SECS);
+        Scheduler::every("impersonation-expiry", period, move || {
+            let data_service = data_service.clone();
+            async move {
+                let now = Utc::now();
+                // Sessions of admin sockets gone without a disconnect are cleaned up too
+                let ended: Vec<(String, &str)> = SESSIONS.lock().unwrap_or_else(|e| e.into_inner()).iter()
+                    .filter_map(|(session_id, mirror)| match (ConnectionManager::socket(ADMIN_NAMESPACE, mirror.admin_id).is_some(), mirror.expires_at <= now) {
+                        (false, _) => Some((session_id.clone(), "operator_disconnected")),
+                        (true, true) => Some((session_id.clone(), "expired")),
+                        (true, false) => None,
+                    })
+                    .collect();
+                for (session_id, end_reason) in ended {
+                    Self::stop(&*data_service, &session_id, end_reason).await;
+                }
+                Ok(())
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redact_strips_credentials_at_any_depth() {
+        let mut data = json!({
+            "status": "success",
+            "session_token": "abc",
+            "user": {
+                "user_id": "user-1",
+                "fcm_token": "fcm",
+                "two_step": { "method": "totp", "secret": "JBSWY3DP", "otpauth_uri": "otpauth://totp/x" },
+            },
+            "devices": [
+                { "device_id": "d-1", "token": "t-1" },
+                [{ "signing_key": "k", "label": "nested" }],
+            ],
+        });
+        ImpersonationManager::redact(&mut data);
+        assert_eq!(data, json!({
+            "status": "success",
+            "user": {
+                "user_id": "user-1",
+                "two_step": { "method": "totp" },
+            },
+            "devices": [
+                { "device_id": "d-1" },
+                [{ "label": "nested" }],
+            ],
+        }));
+    }
+}
@@ -4,7 +4,9 @@ use uuid::Uuid;
 use chrono::{Utc, Duration};
 use tracing::info;
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::managers::tenant::TenantManager;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,           // User ID (UUID v7)
     pub user_number: u64,      // Sequential user number
@@ -14,6 +16,8 @@ pub struct Claims {
     pub iat: i64,             // Issued at
     pub exp: i64,             // Expiration time
     pub jti: String,          // JWT ID (unique token identifier)
+    #[serde(default)]
+    pub tenant_id: String,    // Tenant the user belongs to (empty on tokens from before tenants: the default tenant)
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -67,6 +71,7 @@ impl JwtService {
             iat: now.timestamp(),
             exp: expires_at.timestamp(),
             jti: Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext)).to_string(),
+            tenant_id: TenantManager::current().tenant_id.clone(),
         };
 
         let token = encode(
@@ -86,6 +91,14 @@ impl JwtService {
             &Validation::default(),
         )?;
 
+        // A token only opens the tenant it was issued in
+        let tenant = TenantManager::current();
+        let claimed = token_data.claims.tenant_id.as_str();
+        let token_tenant = if claimed.is_empty() { TenantManager::default_tenant().tenant_id.as_str() } else { claimed };
+        if token_tenant != tenant.tenant_id {
+            return Err("Tenant mismatch".into());
+        }
+
         info!("✅ JWT token verified for user: {} (number: {})", token_data.claims.sub, token_data.claims.user_number);
         Ok(token_data.claims)
     }
@@ -145,10 +158,13 @@ impl JwtService {
     }
 }
 
+// Secret used when JWT_SECRET_KEY is unset; refused by the startup self-check in production
+pub const DEFAULT_JWT_SECRET: &str = "your-super-secret-jwt-key-change-in-production";
+
 // Helper function to create JWT service with default secret
 pub fn create_jwt_service() -> JwtService {
     let secret_key = std::env::var("JWT_SECRET_KEY")
-        .unwrap_or_else(|_| "your-super-secret-jwt-key-change-in-production".to_string());
+        .unwrap_or_else(|_| DEFAULT_JWT_SECRET.to_string());
     
     JwtService::new(secret_key)
 } 
\ No newline at end of file
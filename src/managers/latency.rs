@@ -0,0 +1,109 @@
+use socketioxide::extract::{Data, SocketRef};
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use tokio::sync::RwLock;
+use tracing::warn;
+
+use crate::api::response::ApiResponse;
+use crate::managers::correlation::Correlation;
+use crate::managers::metrics::MetricsManager;
+
+// Number of recent RTT samples kept per socket for the rolling average
+const LATENCY_WINDOW: usize = 20;
+// Client-reported samples above this are treated as bogus and ignored
+const MAX_PLAUSIBLE_RTT_MS: u64 = 10_000;
+
+// Recent RTT samples per socket id
+static LATENCIES: Lazy<RwLock<HashMap<String, VecDeque<u64>>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+#[derive(Debug, Clone, Copy)]
+pub struct LatencyPercentiles {
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub sockets: usize,
+}
+
+pub struct LatencyManager;
+
+impl LatencyManager {
+    pub async fn record_sample(socket_id: &str, rtt_ms: u64) {
+        if rtt_ms > MAX_PLAUSIBLE_RTT_MS {
+            return;
+        }
+        let mut latencies = LATENCIES.write().await;
+        let samples = latencies.entry(socket_id.to_string()).or_default();
+        if samples.len() == LATENCY_WINDOW {
+            samples.pop_front();
+        }
+        samples.push_back(rtt_ms);
+    }
+
+    // Rolling average RTT for a socket, if any samples have been recorded
+    pub async fn average_ms(socket_id: &str) -> Option<u64> {
+        let latencies = LATENCIES.read().await;
+        let samples = latencies.get(socket_id)?;
+        if samples.is_empty() {
+            return None;
+        }
+        Some(samples.iter().sum::<u64>() / samples.len() as u64)
+    }
+
+    pub async fn remove_socket(socket_id: &str) {
+        LATENCIES.write().await.remove(socket_id);
+    }
+
+    // Percentiles of the per-socket rolling averages across connected sockets
+    pub async fn percentiles() -> Option<LatencyPercentiles> {
+        let latencies = LATENCIES.read().await;
+        let mut averages: Vec<u64> = latencies
+            .values()
+            .filter(|s| !s.is_empty())
+            .map(|s| s.iter().sum::<u64>() / s.len() as u64)
+            .collect();
+        if averages.is_empty() {
+            return None;
+        }
+        averages.sort_unstable();
+        let at = |q: f64| averages[((averages.len() - 1) as f64 * q).round() as usize];
+        Some(LatencyPercentiles {
+            p50: at(0.50),
+            p90: at(0.90),
+            p99: at(0.99),
+            sockets: averages.len(),
+        })
+    }
+
+    // Refresh the latency gauges; called before metrics are scraped
+    pub async fn export_metrics() {
+        let Some(p) = Self::percentiles().await else { return };
+        MetricsManager::set_gauge("socket_rtt_ms{quantile=\"0.5\"}", p.p50 as f64).await;
+        MetricsManager::set_gauge("socket_rtt_ms{quantile=\"0.9\"}", p.p90 as f64).await;
+        MetricsManager::set_gauge("socket_rtt_ms{quantile=\"0.99\"}", p.p99 as f64).await;
+        MetricsManager::set_gauge("socket_rtt_tracked_sockets", p.sockets as f64).await;
+    }
+
+    // Heartbeat ping handler. Clients send `client_time` (echoed back in the pong
+    // so they can time the round trip) and `rtt_ms`, the RTT they measured for
+    // their previous ping, which feeds the rolling average.
+    pub fn register_ping_events(socket: &SocketRef) {
+        socket.on("ping", |socket: SocketRef, Data::<Value>(data)| {
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope("ping", socket.id, request_id, async move {
+                if let Some(rtt_ms) = data.get("rtt_ms").and_then(|v| v.as_u64()) {
+                    Self::record_sample(&socket.id.to_string(), rtt_ms).await;
+                }
+
+                let pong_response = ApiResponse::success("pong", json!({
+                    "client_time": data.get("client_time").and_then(|v| v.as_i64()),
+                    "server_time": chrono::Utc::now().timestamp_millis(),
+                    "avg_rtt_ms": Self::average_ms(&socket.id.to_string()).await
+                })).with_status("pong").for_socket(socket.id);
+                if let Err(e) = socket.emit("pong", pong_response) {
+                    warn!("⚠️ Failed to send pong to socket {}: {}", socket.id, e);
+                }
+            })
+        });
+    }
+}
@@ -0,0 +1,207 @@
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::api::response::ApiError;
+use crate::config::CONFIG;
+use crate::database::models::{ChatAttachment, ReportCategory};
+use crate::database::store::DataStore;
+use crate::managers::moderation::ModerationManager;
+
+const LOOKUP_TIMEOUT_SECS: u64 = 3;
+const SAFE_BROWSING_URL: &str = "https://safebrowsing.googleapis.com/v4/threatMatches:find";
+const THREAT_TYPES: &[&str] = &["MALWARE", "SOCIAL_ENGINEERING", "UNWANTED_SOFTWARE", "POTENTIALLY_HARMFUL_APPLICATION"];
+// Content types an image attachment may have
+pub const IMAGE_TYPES: &[&str] = &["image/png", "image/jpeg", "image/gif", "image/webp"];
+// Characters that end a sentence rather than a link
+const TRAILING_PUNCTUATION: &str = ".,;:!?)]}'\"";
+
+static HTTP: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(LOOKUP_TIMEOUT_SECS))
+        .build()
+        .unwrap_or_default()
+});
+
+#[derive(Deserialize)]
+struct ThreatMatches {
+    #[serde(default)]
+    matches: Vec<ThreatMatch>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ThreatMatch {
+    threat_type: String,
+    threat: ThreatEntry,
+}
+
+#[derive(Deserialize)]
+struct ThreatEntry {
+    url: String,
+}
+
+// Why a message was held back: what the sender is told, and the reason that
+// goes to the moderation queue
+struct Violation {
+    code: &'static str,
+    field: &'static str,
+    message: &'static str,
+    url: String,
+    reason: String,
+}
+
+// Links and images in chat, checked before a message is stored or relayed.
+// CHAT_LINK_POLICY decides which hosts links may point to (any but
+// CHAT_LINK_DENYLIST, only CHAT_LINK_ALLOWLIST, or none); image attachments
+// must be served over https from one of CHAT_IMAGE_HOSTS, be one of
+// IMAGE_TYPES and at most CHAT_IMAGE_MAX_BYTES. With SAFE_BROWSING_API_KEY set
+// the links and images that pass are looked up with Google Safe Browsing. A
+// refused message is not relayed, the sender gets CHAT_LINK_BLOCKED,
+// CHAT_LINK_UNSAFE or CHAT_ATTACHMENT_REFUSED, and an automatic report lands
+// in the moderation queue.
+pub struct LinkScanManager;
+
+impl LinkScanManager {
+    // Links in a chat message: words with http://, https:// or www. in them,
+    // without trailing punctuation. Bare domains ("example.com") are not links.
+    pub fn extract_urls(text: &str) -> Vec<String> {
+        text.split_whitespace()
+            .filter_map(|word| {
+                // ASCII lowercasing keeps byte offsets, so `start` indexes `word` too
+                let lower = word.to_ascii_lowercase();
+                let start = ["http://", "https://", "www."].iter().filter_map(|prefix| lower.find(prefix)).min()?;
+                let url = word[start..].trim_end_matches(|c: char| TRAILING_PUNCTUATION.contains(c));
+                Self::host_of(url).map(|_| url.to_string())
+            })
+            .collect()
+    }
+
+    // Lowercase host of a link, without user info, port or trailing dot
+    fn host_of(url: &str) -> Option<String> {
+        let lower = url.to_lowercase();
+        let rest = lower.split_once("://").map_or(lower.as_str(), |(_, rest)| rest);
+        let authority = rest.split(['/', '?', '#', '\\']).next().unwrap_or_default();
+        let host = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+        let host = host.split(':').next().unwrap_or_default().trim_end_matches('.');
+        (!host.is_empty()).then(|| host.to_string())
+    }
+
+    // Whether the host is one of the listed ones or a subdomain of one
+    fn listed(host: &str, hosts: &[String]) -> bool {
+        hosts.iter().any(|listed| host == listed || host.ends_with(&format!(".{}", listed)))
+    }
+
+    fn check_link(url: &str) -> Result<(), Violation> {
+        let Some(host) = Self::host_of(url) else { return Ok(()) };
+        let reason = match CONFIG.chat_link_policy.as_str() {
+            "block" => Some("links are not allowed in chat".to_string()),
+            "allowlist" if !Self::listed(&host, &CONFIG.chat_link_allowlist) => Some(format!("{} is not on the link allowlist", host)),
+            _ if Self::listed(&host, &CONFIG.chat_link_denylist) => Some(format!("{} is on the link denylist", host)),
+            _ => None,
+        };
+        match reason {
+            Some(reason) => Err(Violation {
+                code: "CHAT_LINK_BLOCKED",
+                field: "message",
+                message: "Links to this site are not allowed in chat",
+                url: url.to_string(),
+                reason,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn check_attachment(attachment: &ChatAttachment) -> Result<(), Violation> {
+        let host = Self::host_of(&attachment.url).unwrap_or_default();
+        let content_type = attachment.content_type.to_ascii_lowercase();
+        let reason = if CONFIG.chat_image_hosts.is_empty() {
+            Some("image attachments are turned off".to_string())
+        } else if !attachment.url.to_ascii_lowercase().starts_with("https://") || !Self::listed(&host, &CONFIG.chat_image_hosts) {
+            Some(format!("images cannot be served from {}", if host.is_empty() { "this address" } else { &host }))
+        } else if !IMAGE_TYPES.contains(&content_type.as_str()) {
+            Some(format!("{} is not an accepted image type", content_type))
+        } else if attachment.size_bytes > CONFIG.chat_image_max_bytes {
+            Some(format!("the image is {} bytes, over the {} byte limit", attachment.size_bytes, CONFIG.chat_image_max_bytes))
+        } else {
+            None
+        };
+        match reason {
+            Some(reason) => Err(Violation {
+                code: "CHAT_ATTACHMENT_REFUSED",
+                field: "attachments",
+                message: "This image cannot be attached",
+                url: attachment.url.clone(),
+                reason,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    // The first of the links Safe Browsing knows as a threat, with the threat
+    // type. An unreachable lookup lets the message through rather than
+    // stalling chat.
+    async fn unsafe_link(urls: &[String]) -> Option<(String, String)> {
+        let key = CONFIG.safe_browsing_api_key.as_deref()?;
+        if urls.is_empty() {
+            return None;
+        }
+        let entries: Vec<_> = urls.iter()
+            .map(|url| {
+                let url = if url.contains("://") { url.clone() } else { format!("http://{}", url) };
+                json!({ "url": url })
+            })
+            .collect();
+        let body = json!({
+            "client": { "clientId": env!("CARGO_PKG_NAME"), "clientVersion": env!("CARGO_PKG_VERSION") },
+            "threatInfo": {
+                "threatTypes": THREAT_TYPES,
+                "platformTypes": ["ANY_PLATFORM"],
+                "threatEntryTypes": ["URL"],
+                "threatEntries": entries,
+            }
+        });
+        let found = async {
+            HTTP.post(SAFE_BROWSING_URL).query(&[("key", key)]).json(&body).send().await?
+                .error_for_status()?
+                .json::<ThreatMatches>().await
+        }.await;
+        match found {
+            Ok(found) => found.matches.into_iter().next().map(|m| (m.threat.url, m.threat_type)),
+            Err(e) => {
+                warn!("⚠️ Safe Browsing lookup unavailable - letting {} links through: {}", urls.len(), e.without_url());
+                None
+            }
+        }
+    }
+
+    // Ok when the message may be relayed. Otherwise the error to send the
+    // player, after filing the automatic report; `place` names the room or
+    // party for the report.
+    pub async fn scan(data_service: &dyn DataStore, player_id: &str, room_id: Option<&str>, place: &str, text: &str, attachments: &[ChatAttachment]) -> Result<(), ApiError> {
+        let urls = Self::extract_urls(text);
+        let mut violation = urls.iter().find_map(|url| Self::check_link(url).err())
+            .or_else(|| attachments.iter().find_map(|attachment| Self::check_attachment(attachment).err()));
+        if violation.is_none() {
+            let lookup: Vec<String> = urls.into_iter().chain(attachments.iter().map(|a| a.url.clone())).collect();
+            violation = Self::unsafe_link(&lookup).await.map(|(url, threat_type)| Violation {
+                code: "CHAT_LINK_UNSAFE",
+                field: if attachments.iter().any(|a| url.ends_with(a.url.as_str())) { "attachments" } else { "message" },
+                message: "This message links to a site flagged as unsafe",
+                url,
+                reason: format!("Safe Browsing lists it as {}", threat_type),
+            });
+        }
+        let Some(violation) = violation else { return Ok(()) };
+
+        info!("🔗 Chat message from player {} in {} refused: {} ({})", player_id, place, violation.reason, violation.url);
+        let description = format!("Chat message in {} refused automatically: {} ({})", place, violation.reason, violation.url);
+        if let Err(e) = ModerationManager::auto_report(data_service, player_id, ReportCategory::Spam, &description, room_id).await {
+            warn!("⚠️ Failed to report refused chat message of player {}: {}", player_id, e);
+        }
+        Err(ApiError::new(violation.code, "CHAT_ERROR", violation.field, violation.message)
+            .with_details(json!({ "url": violation.url, "reason": violation.reason })))
+    }
+}
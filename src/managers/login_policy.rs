@@ -0,0 +1,155 @@
+use once_cell::sync::Lazy;
+use rand::Rng;
+use serde::Deserialize;
+use serde_json::json;
+use socketioxide::extract::SocketRef;
+use tracing::info;
+
+use crate::api::middleware::ClientIp;
+use crate::api::response::ApiError;
+use crate::config::CONFIG;
+
+const OTP_LENGTH_RANGE: (u32, u32) = (4, 8);
+
+// One entry of OTP_POLICIES
+#[derive(Debug, Deserialize)]
+struct PolicyConfig {
+    country: String,                    // ISO 3166-1 alpha-2, e.g. "IN"
+    #[serde(default)]
+    calling_codes: Vec<String>,         // Country calling codes its mobile numbers start with, e.g. ["91"]
+    otp_length: Option<u32>,
+    otp_expiry_secs: Option<i64>,
+    max_verify_attempts: Option<u32>,
+    #[serde(default)]
+    blocked: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct LoginPolicy {
+    pub country: Option<String>,        // None for the default policy
+    calling_codes: Vec<String>,
+    pub otp_length: u32,
+    pub otp_expiry_secs: i64,
+    pub max_verify_attempts: u32,
+    pub blocked: bool,                  // Logins from this country are refused
+}
+
+impl LoginPolicy {
+    fn default_policy() -> Self {
+        Self {
+            country: None,
+            calling_codes: Vec::new(),
+            otp_length: CONFIG.otp_length,
+            otp_expiry_secs: CONFIG.otp_expiry_secs,
+            max_verify_attempts: CONFIG.otp_max_verify_attempts,
+            blocked: false,
+        }
+    }
+
+    // A fresh OTP of this policy's length; never starts with 0, so it reads
+    // the same as a number and as a string
+    pub fn generate_otp(&self) -> i32 {
+        let low = 10_i32.pow(self.otp_length - 1);
+        rand::thread_rng().gen_range(low..low * 10)
+    }
+}
+
+static POLICIES: Lazy<Vec<LoginPolicy>> = Lazy::new(LoginPolicyManager::load);
+static DEFAULT_POLICY: Lazy<LoginPolicy> = Lazy::new(LoginPolicy::default_policy);
+
+// OTP length, expiry, verification attempts and blocked regions per country,
+// from OTP_POLICIES; other numbers and omitted fields get OTP_LENGTH,
+// OTP_EXPIRY_SECS and OTP_MAX_VERIFY_ATTEMPTS. A login is judged by the
+// country of its mobile number (the longest matching calling code) and, when
+// GEOIP_COUNTRY_HEADER names a header the CDN or load balancer sets from the
+// client IP, also by that country: either one being blocked refuses the login.
+pub struct LoginPolicyManager;
+
+impl LoginPolicyManager {
+    fn load() -> Vec<LoginPolicy> {
+        let Some(raw) = std::env::var("OTP_POLICIES").ok().filter(|v| !v.trim().is_empty()) else {
+            return Vec::new();
+        };
+        // Running without the policies would drop the blocked regions
+        let configs: Vec<PolicyConfig> = serde_json::from_str(&raw)
+            .unwrap_or_else(|e| panic!("OTP_POLICIES is not a valid list of policies: {}", e));
+        configs.into_iter().map(|config| {
+            let country = config.country.trim().to_ascii_uppercase();
+            if country.len() != 2 || !country.chars().all(|c| c.is_ascii_uppercase()) {
+                panic!("OTP policy {:?}: country must be a 2-letter ISO code", config.country);
+            }
+            if config.calling_codes.iter().any(|code| code.is_empty() || code.len() > 4 || !code.chars().all(|c| c.is_ascii_digit())) {
+                panic!("OTP policy {}: calling_codes must be 1-4 digits each", country);
+            }
+            let otp_length = config.otp_length.unwrap_or(CONFIG.otp_length);
+            if !(OTP_LENGTH_RANGE.0..=OTP_LENGTH_RANGE.1).contains(&otp_length) {
+                panic!("OTP policy {}: otp_length must be {}-{}", country, OTP_LENGTH_RANGE.0, OTP_LENGTH_RANGE.1);
+            }
+            let policy = LoginPolicy {
+                country: Some(country),
+                calling_codes: config.calling_codes,
+                otp_length,
+                otp_expiry_secs: config.otp_expiry_secs.unwrap_or(CONFIG.otp_expiry_secs).max(60),
+                max_verify_attempts: config.max_verify_attempts.unwrap_or(CONFIG.otp_max_verify_attempts).max(1),
+                blocked: config.blocked,
+            };
+            info!("🌍 Login policy {}: {}-digit OTP valid {}s, {} attempts{}",
+                policy.country.as_deref().unwrap_or_default(), policy.otp_length, policy.otp_expiry_secs,
+                policy.max_verify_attempts, if policy.blocked { ", blocked" } else { "" });
+            policy
+        }).collect()
+    }
+
+    pub fn policies() -> &'static [LoginPolicy] {
+        &POLICIES
+    }
+
+    // Policy of the country a mobile number (digits with country code) belongs to
+    pub fn for_mobile(mobile_no: &str) -> &'static LoginPolicy {
+        POLICIES.iter()
+            .filter_map(|policy| {
+                let longest = policy.calling_codes.iter().filter(|code| mobile_no.starts_with(code.as_str())).map(String::len).max()?;
+                Some((longest, policy))
+            })
+            .max_by_key(|(longest, _)| *longest)
+            .map_or(&*DEFAULT_POLICY, |(_, policy)| policy)
+    }
+
+    fn for_country(country: &str) -> Option<&'static LoginPolicy> {
+        POLICIES.iter().find(|policy| policy.country.as_deref().is_some_and(|c| c.eq_ignore_ascii_case(country)))
+    }
+
+    // Country of the client IP as reported by the proxy in front of the server.
+    // With TRUSTED_PROXIES set, only a connection through one of them is believed.
+    pub fn ip_country(socket: &SocketRef) -> Option<String> {
+        Self::geoip_header(socket, CONFIG.geoip_country_header.as_deref()?)
+            .filter(|value| value.len() == 2)
+    }
+
+    // Upper-cased value of a Geo-IP header, believed as in ip_country
+    pub fn geoip_header(socket: &SocketRef, header: &str) -> Option<String> {
+        if !CONFIG.trusted_proxies.is_empty() && !ClientIp::of(socket).is_some_and(|client| client.via_trusted_proxy) {
+            return None;
+        }
+        socket.req_parts().headers.get(header)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.trim().to_ascii_uppercase())
+            .filter(|value| !value.is_empty())
+    }
+
+    // The policy a login or OTP verification runs under, or REGION_BLOCKED
+    pub fn check(socket: &SocketRef, mobile_no: &str) -> Result<&'static LoginPolicy, ApiError> {
+        let policy = Self::for_mobile(mobile_no);
+        let ip_country = Self::ip_country(socket);
+        let blocked_by = if policy.blocked {
+            policy.country.clone()
+        } else {
+            ip_country.filter(|country| Self::for_country(country).is_some_and(|p| p.blocked))
+        };
+        match blocked_by {
+            Some(country) => Err(ApiError::new("REGION_BLOCKED", "AUTHENTICATION_ERROR", "mobile_no", "Login is not available in your region.")
+                .with_details(json!({"country": country}))),
+            None => Ok(policy),
+        }
+    }
+}
@@ -0,0 +1,389 @@
+use socketioxide::{SocketIo, extract::SocketRef, socket::Sid};
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use chrono::{DateTime, Utc};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::api::response::ApiResponse;
+use crate::database::models::MatchRecord;
+use crate::database::store::DataStore;
+use crate::managers::bot::BotPlayer;
+use crate::managers::escrow::EscrowManager;
+use crate::managers::game_config::GameConfigManager;
+use crate::managers::game_modes::RegisteredMode;
+use crate::managers::latency::LatencyManager;
+use crate::managers::moderation::ModerationManager;
+use crate::managers::regions::RegionManager;
+use crate::managers::room::{RoomManager, RoomPlayer};
+use crate::managers::snowflake::Snowflake;
+use crate::managers::tenant::{Tenant, TenantManager};
+use crate::managers::turn_timer::TurnTimerManager;
+
+// Global matchmaking queue; tickets of different game modes or tenants are never paired
+static QUEUE: Lazy<Mutex<Vec<QueueTicket>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+// How many recent queue waits per region the expected wait is averaged over
+const WAIT_SAMPLES: usize = 20;
+
+// (tenant_id, game_type, region)
+type WaitKey = (String, String, Option<String>);
+
+// Recent queue waits in ms, newest last
+static WAITS: Lazy<Mutex<HashMap<WaitKey, VecDeque<u64>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// A queued player and the id of their socket in the mode's namespace
+#[derive(Clone)]
+pub struct QueueMember {
+    pub player_id: String,
+    pub socket_id: Sid,
+}
+
+impl QueueMember {
+    // The member's socket in a namespace, None once it has disconnected
+    pub fn socket(&self, io: &SocketIo, namespace: &str) -> Option<SocketRef> {
+        io.of(namespace)?.get_socket(self.socket_id)
+    }
+}
+
+// A queue entry is a team: a solo player or a whole party, always matched together
+#[derive(Clone)]
+struct QueueTicket {
+    ticket_id: String,
+    game_type: String,
+    tenant: &'static Tenant,
+    party_id: Option<String>,
+    members: Vec<QueueMember>,
+    region: Option<String>,             // Region of the first member (a party's leader)
+    blocked: HashSet<String>,           // Players blocked by or blocking a member
+    enqueued_at: DateTime<Utc>,
+}
+
+impl QueueTicket {
+    fn contains_player(&self, player_id: &str) -> bool {
+        self.members.iter().any(|m| m.player_id == player_id)
+    }
+
+    fn waited_ms(&self) -> u64 {
+        (Utc::now() - self.enqueued_at).num_milliseconds().max(0) as u64
+    }
+
+    fn wait_key(&self) -> WaitKey {
+        (self.tenant.tenant_id.clone(), self.game_type.clone(), self.region.clone())
+    }
+}
+
+pub struct MatchmakingManager;
+
+impl MatchmakingManager {
+    // A team plays at the pace of its slowest member. None until any member has RTT samples.
+    async fn team_latency_ms(members: &[QueueMember]) -> Option<u64> {
+        let mut worst = None;
+        for member in members {
+            if let Some(avg) = LatencyManager::average_ms(&member.socket_id.to_string()).await {
+                worst = Some(worst.map_or(avg, |w: u64| w.max(avg)));
+            }
+        }
+        worst
+    }
+
+    // Teams with unknown latency are compatible with anyone
+    fn latency_compatible(a: Option<u64>, b: Option<u64>, max_gap_ms: u64) -> bool {
+        match (a, b) {
+            (Some(a), Some(b)) => a.abs_diff(b) <= max_gap_ms,
+            _ => true,
+        }
+    }
+
+    // Players blocked by or blocking any team member; they are never paired
+    async fn team_blocks(data_service: &dyn DataStore, members: &[QueueMember]) -> HashSet<String> {
+        let mut blocked = HashSet::new();
+        for member in members {
+            match ModerationManager::blocked_user_ids(data_service, &member.player_id).await {
+                Ok(ids) => blocked.extend(ids),
+                Err(e) => warn!("⚠️ Failed to load blocks of player {}: {}", member.player_id, e),
+            }
+        }
+        blocked
+    }
+
+    // Index of the best opponent for a team among the queued tickets: same
+    // mode, tenant and size, comparable latency and no blocks either way. A
+    // team in the same region wins over one elsewhere, and a closer latency
+    // over a wider one; other regions are only considered once either team has
+    // waited `region_wait_secs`. Ties go to the ticket queued first.
+    async fn find_opponent(queue: &[QueueTicket], team: &QueueTicket, mode: &RegisteredMode) -> Option<usize> {
+        let latency = Self::team_latency_ms(&team.members).await;
+        let region_wait_ms = mode.matchmaking.region_wait_secs.saturating_mul(1000);
+        let mut best: Option<((bool, u64), usize)> = None;
+        for (index, ticket) in queue.iter().enumerate() {
+            if ticket.ticket_id == team.ticket_id || ticket.tenant.tenant_id != team.tenant.tenant_id
+                || ticket.game_type != team.game_type || ticket.members.len() != team.members.len() {
+                continue;
+            }
+            if ticket.members.iter().any(|m| team.blocked.contains(&m.player_id)) || team.members.iter().any(|m| ticket.blocked.contains(&m.player_id)) {
+                info!("🚫 Skipping ticket {} - a player there has a block with this team", ticket.ticket_id);
+                continue;
+            }
+            let cross_region = matches!((&team.region, &ticket.region), (Some(a), Some(b)) if a != b);
+            if cross_region && team.waited_ms().max(ticket.waited_ms()) < region_wait_ms {
+                continue;
+            }
+            let opponent_latency = Self::team_latency_ms(&ticket.members).await;
+            if !Self::latency_compatible(latency, opponent_latency, mode.matchmaking.max_latency_gap_ms) {
+                info!("📶 Skipping ticket {} - latency gap too large ({:?}ms vs {:?}ms)", ticket.ticket_id, latency, opponent_latency);
+                continue;
+            }
+            let gap = match (latency, opponent_latency) {
+                (Some(a), Some(b)) => a.abs_diff(b),
+                _ => 0,
+            };
+            let better = match best {
+                Some((score, _)) => (cross_region, gap) < score,
+                None => true,
+            };
+            if better {
+                best = Some(((cross_region, gap), index));
+            }
+        }
+        best.map(|(_, index)| index)
+    }
+
+    // Queue a solo player or party for a game mode; pairs immediately with the
+    // best waiting opponent (see find_opponent), otherwise waits for one, for
+    // other regions after `region_wait_secs`, and for bots after the mode's
+    // bot fallback
+    pub async fn join_queue(io: SocketIo, data_service: Arc<dyn DataStore>, members: Vec<QueueMember>, party_id: Option<String>, mode: Arc<RegisteredMode>) {
+        let blocked = Self::team_blocks(&*data_service, &members).await;
+        let region = members.first()
+            .and_then(|m| m.socket(&io, &mode.namespace))
+            .and_then(|socket| RegionManager::of_socket(&socket))
+            .map(|r| r.region.clone());
+        let ticket = QueueTicket {
+            ticket_id: Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext)).to_string(),
+            game_type: mode.game_type.clone(),
+            tenant: TenantManager::current(),
+            party_id,
+            members,
+            region,
+            blocked,
+            enqueued_at: Utc::now(),
+        };
+        let mut queue = QUEUE.lock().await;
+
+        if let Some(member) = ticket.members.iter().find(|m| queue.iter().any(|t| t.contains_player(&m.player_id))) {
+            info!("🔁 Player {} is already queued", member.player_id);
+            return;
+        }
+
+        if let Some(index) = Self::find_opponent(&queue, &ticket, &mode).await {
+            let opponents = queue.remove(index);
+            drop(queue);
+            Self::paired(io, data_service, opponents, ticket, &mode).await;
+            return;
+        }
+
+        queue.push(ticket.clone());
+        drop(queue);
+
+        // How long a team waits for human opponents before bots are assigned
+        let fallback_seconds = GameConfigManager::current(&mode.game_type).rules.bot_fallback_secs;
+        let expected_wait_seconds = Self::expected_wait_secs(&ticket.wait_key()).await;
+        for member in &ticket.members {
+            let queued = ApiResponse::success("matchmaking:queued", json!({
+                "player_id": member.player_id,
+                "party_id": ticket.party_id,
+                "team_size": ticket.members.len(),
+                "ticket_id": ticket.ticket_id,
+                "game_type": ticket.game_type,
+                "region": ticket.region,
+                "expected_wait_seconds": expected_wait_seconds,
+                "bot_fallback_seconds": fallback_seconds
+            })).with_status("queued").for_socket(member.socket_id);
+            let Some(socket) = member.socket(&io, &mode.namespace) else {
+                continue;
+            };
+            if let Err(e) = socket.emit("matchmaking:queued", queued) {
+                warn!("⚠️ Failed to emit matchmaking:queued to socket {}: {}", member.socket_id, e);
+            }
+        }
+        info!("⏳ Team of {} queued for {} in region {:?} (ticket: {}, party: {:?})", ticket.members.len(), ticket.game_type, ticket.region, ticket.ticket_id, ticket.party_id);
+
+        // Once the team may take opponents from other regions, look again
+        // among the teams already waiting
+        if ticket.region.is_some() && mode.matchmaking.region_wait_secs < fallback_seconds {
+            let (io, data_service, mode, ticket_id) = (io.clone(), data_service.clone(), mode.clone(), ticket.ticket_id.clone());
+            TenantManager::spawn(async move {
+                tokio::time::sleep(std::time::Duration::from_secs(mode.matchmaking.region_wait_secs)).await;
+                let mut queue = QUEUE.lock().await;
+                let Some(index) = queue.iter().position(|t| t.ticket_id == ticket_id) else {
+                    return;
+                };
+                let waiting = queue[index].clone();
+                let Some(opponent_index) = Self::find_opponent(&queue, &waiting, &mode).await else {
+                    return;
+                };
+                let opponents = queue.remove(opponent_index);
+                queue.retain(|t| t.ticket_id != ticket_id);
+                drop(queue);
+                info!("🌍 Pairing ticket {} across regions ({:?} vs {:?})", ticket_id, waiting.region, opponents.region);
+                Self::paired(io, data_service, opponents, waiting, &mode).await;
+            });
+        }
+
+        // Bot fallback - only fires if this exact ticket is still waiting
+        TenantManager::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_secs(fallback_seconds)).await;
+            let mut queue = QUEUE.lock().await;
+            let Some(index) = queue.iter().position(|t| t.ticket_id == ticket.ticket_id) else {
+                return;
+            };
+            let waiting = queue.remove(index);
+            drop(queue);
+
+            let waited_ms = waiting.waited_ms();
+            info!("🤖 No human opponents found for ticket {} after {}ms - assigning bots", waiting.ticket_id, waited_ms);
+            Self::record_wait(waiting.wait_key(), waited_ms).await;
+            let mut players: Vec<(RoomPlayer, Option<Sid>)> = waiting.members
+                .into_iter()
+                .map(|m| (RoomPlayer::human(&m.player_id, &m.socket_id.to_string()).with_team(0), Some(m.socket_id)))
+                .collect();
+            let bots = players.len();
+            players.extend((0..bots).map(|_| (RoomPlayer::bot(&BotPlayer::new_bot_id()).with_team(1), None)));
+            Self::create_match(io, data_service, players, &mode).await;
+        });
+    }
+
+    // Remove a player's ticket (and with it their whole party) from the queue.
+    // Returns true if they were queued.
+    pub async fn leave_queue(player_id: &str) -> bool {
+        let mut queue = QUEUE.lock().await;
+        let before = queue.len();
+        queue.retain(|t| !t.contains_player(player_id));
+        before != queue.len()
+    }
+
+    // Drop any queue tickets held by a disconnected socket
+    pub async fn remove_socket(socket_id: &str) {
+        QUEUE.lock().await.retain(|t| !t.members.iter().any(|m| m.socket_id.to_string() == socket_id));
+    }
+
+    async fn paired(io: SocketIo, data_service: Arc<dyn DataStore>, waiting: QueueTicket, arriving: QueueTicket, mode: &RegisteredMode) {
+        for ticket in [&waiting, &arriving] {
+            Self::record_wait(ticket.wait_key(), ticket.waited_ms()).await;
+        }
+        Self::create_match(io, data_service, Self::seat_teams(waiting.members, arriving.members), mode).await;
+    }
+
+    async fn record_wait(key: WaitKey, waited_ms: u64) {
+        let mut waits = WAITS.lock().await;
+        let samples = waits.entry(key).or_default();
+        samples.push_back(waited_ms);
+        if samples.len() > WAIT_SAMPLES {
+            samples.pop_front();
+        }
+    }
+
+    // Average of the recent waits for a match, None until there are any
+    async fn expected_wait_secs(key: &WaitKey) -> Option<u64> {
+        let waits = WAITS.lock().await;
+        let samples = waits.get(key).filter(|samples| !samples.is_empty())?;
+        Some(samples.iter().sum::<u64>() / samples.len() as u64 / 1000)
+    }
+
+    // Players and teams waiting in each region for a game mode of the current
+    // tenant, with the expected wait. Configured regions are always listed;
+    // teams outside every region are counted under region null.
+    pub async fn population(game_type: &str) -> Vec<Value> {
+        let tenant_id = TenantManager::current().tenant_id.clone();
+        let mut regions: Vec<Option<String>> = RegionManager::regions().iter().map(|r| Some(r.region.clone())).collect();
+        let mut counts: HashMap<Option<String>, (usize, usize)> = HashMap::new();
+        for ticket in QUEUE.lock().await.iter().filter(|t| t.tenant.tenant_id == tenant_id && t.game_type == game_type) {
+            if !regions.contains(&ticket.region) {
+                regions.push(ticket.region.clone());
+            }
+            let (players, teams) = counts.entry(ticket.region.clone()).or_default();
+            *players += ticket.members.len();
+            *teams += 1;
+        }
+        let mut population = Vec::with_capacity(regions.len());
+        for region in regions {
+            let (players, teams) = counts.get(&region).copied().unwrap_or_default();
+            let expected_wait_seconds = Self::expected_wait_secs(&(tenant_id.clone(), game_type.to_string(), region.clone())).await;
+            population.push(json!({
+                "region": region,
+                "queued_players": players,
+                "queued_teams": teams,
+                "expected_wait_seconds": expected_wait_seconds
+            }));
+        }
+        population
+    }
+
+    // Interleave two teams so turns alternate between them
+    fn seat_teams(team_a: Vec<QueueMember>, team_b: Vec<QueueMember>) -> Vec<(RoomPlayer, Option<Sid>)> {
+        let mut players = Vec::with_capacity(team_a.len() + team_b.len());
+        for (a, b) in team_a.into_iter().zip(team_b) {
+            players.push((RoomPlayer::human(&a.player_id, &a.socket_id.to_string()).with_team(0), Some(a.socket_id)));
+            players.push((RoomPlayer::human(&b.player_id, &b.socket_id.to_string()).with_team(1), Some(b.socket_id)));
+        }
+        players
+    }
+
+    async fn create_match(io: SocketIo, data_service: Arc<dyn DataStore>, players: Vec<(RoomPlayer, Option<Sid>)>, mode: &RegisteredMode) {
+        let room_id = Snowflake::generate();
+        let (room_players, socket_ids): (Vec<RoomPlayer>, Vec<Option<Sid>>) = players.into_iter().unzip();
+        let room = RoomManager::create_room(&room_id, room_players, GameConfigManager::current(&mode.game_type)).await;
+
+        if let Some(ns) = io.of(mode.namespace.as_str()) {
+            for socket in socket_ids.iter().flatten().filter_map(|id| ns.get_socket(*id)) {
+                let _ = socket.join(room_id.clone());
+            }
+        }
+
+        let player_ids: Vec<String> = room.players.iter().map(|p| p.player_id.clone()).collect();
+        let bot_player_ids: Vec<String> = room.players.iter().filter(|p| p.is_bot).map(|p| p.player_id.clone()).collect();
+        let teams: Vec<serde_json::Value> = room.players
+            .iter()
+            .map(|p| json!({"player_id": p.player_id, "team": p.team, "is_bot": p.is_bot}))
+            .collect();
+        let match_found = ApiResponse::success("match:found", json!({
+            "room_id": room_id,
+            "players": player_ids,
+            "teams": teams,
+            "is_bot_match": room.is_bot_match,
+            "rated": !room.is_bot_match,
+            "game_type": room.config.game_type,
+            "config_version": room.config.version,
+            "rules": room.config.rules
+        }));
+        if let Some(ns) = io.of(mode.namespace.as_str()) {
+            if let Err(e) = ns.to(room_id.clone()).emit("match:found", match_found) {
+                warn!("⚠️ Failed to broadcast match:found to room {}: {}", room_id, e);
+            }
+        }
+
+        // Bot matches are tagged in match history and never rated
+        let record = MatchRecord {
+            id: None,
+            match_id: room_id.clone(),
+            player_ids,
+            bot_player_ids,
+            is_bot_match: room.is_bot_match,
+            rated: !room.is_bot_match,
+            game_type: room.config.game_type.clone(),
+            config_version: room.config.version,
+            created_at: bson::DateTime::from_millis(Utc::now().timestamp_millis()),
+        };
+        if let Err(e) = data_service.store_match_record(record).await {
+            warn!("⚠️ Failed to store match record for room {}: {}", room_id, e);
+        }
+
+        if EscrowManager::open(&io, &data_service, &room_id).await {
+            TurnTimerManager::start_next_turn(io, data_service, &room_id).await;
+        }
+    }
+}
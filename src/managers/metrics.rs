@@ -0,0 +1,30 @@
+use once_cell::sync::Lazy;
+use std::collections::BTreeMap;
+use tokio::sync::RwLock;
+
+// In-process metric series keyed by Prometheus series name (including labels)
+static GAUGES: Lazy<RwLock<BTreeMap<String, f64>>> = Lazy::new(|| RwLock::new(BTreeMap::new()));
+
+pub struct MetricsManager;
+
+impl MetricsManager {
+    pub async fn set_gauge(series: &str, value: f64) {
+        GAUGES.write().await.insert(series.to_string(), value);
+    }
+
+    // Render all series in the Prometheus text exposition format
+    pub async fn render() -> String {
+        let gauges = GAUGES.read().await;
+        let mut output = String::new();
+        let mut last_name = "";
+        for (series, value) in gauges.iter() {
+            let name = series.split('{').next().unwrap_or(series);
+            if name != last_name {
+                output.push_str(&format!("# TYPE {} gauge\n", name));
+                last_name = name;
+            }
+            output.push_str(&format!("{} {}\n", series, value));
+        }
+        output
+    }
+}
@@ -0,0 +1,192 @@
+use socketioxide::extract::SocketRef;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+use crate::api::response::{ApiError, ApiResponse};
+use crate::config::CONFIG;
+use crate::database::models::{MobileChange, MobileChangeAudit};
+use crate::database::store::DataStore;
+use crate::managers::auth_guard::{AuthContext, AuthGuard};
+use crate::managers::chaos::FaultInjector;
+use crate::managers::correlation::Correlation;
+use crate::managers::error_responder::ErrorResponder;
+use crate::managers::login_policy::LoginPolicyManager;
+use crate::managers::otp_delivery::OtpDeliveryManager;
+use crate::managers::snowflake::Snowflake;
+use crate::managers::two_step::TwoStepManager;
+
+pub struct MobileChangeManager;
+
+impl MobileChangeManager {
+    // Moving an account to a new mobile number on the main namespace:
+    //   account:change_mobile         { mobile_no, session_token, new_mobile_no[, two_step_code] }
+    //                                 -> account:change_mobile:pending { change_id, expires_at }
+    //   account:change_mobile:confirm { mobile_no, session_token, change_id, old_otp, new_otp }
+    //                                 -> account:mobile_changed
+    // Both numbers get an OTP and both must be confirmed. The account keeps its
+    // user_id, so wallet, progress, friends and match history come along; the
+    // number itself moves in one transaction with an audit record, and every
+    // session of the old number is revoked. Admins can make the change without
+    // the OTPs through the admin API.
+    pub fn register_events(socket: &SocketRef, data_service: Arc<dyn DataStore>) {
+        let ds = data_service.clone();
+        AuthGuard::require_session(socket, "account:change_mobile", data_service.clone(), move |socket, data, auth| {
+            let ds = ds.clone();
+            async move {
+                info!("📱 Received account:change_mobile from {}", socket.id);
+                if let Err(error) = Self::request(&socket, &*ds, &data, &auth).await {
+                    ErrorResponder::send(&socket, &*ds, error).await;
+                }
+            }
+        });
+
+        let ds = data_service.clone();
+        AuthGuard::require_session(socket, "account:change_mobile:confirm", data_service, move |socket, data, auth| {
+            let ds = ds.clone();
+            async move {
+                info!("📱 Received account:change_mobile:confirm from {}", socket.id);
+                if let Err(error) = Self::confirm(&socket, &*ds, &data, &auth).await {
+                    ErrorResponder::send(&socket, &*ds, error).await;
+                }
+            }
+        });
+    }
+
+    async fn request(socket: &SocketRef, data_service: &dyn DataStore, data: &Value, auth: &AuthContext) -> Result<(), ApiError> {
+        let new_mobile_no = data["new_mobile_no"].as_str().unwrap_or_default();
+        if !new_mobile_no.chars().all(|c| c.is_ascii_digit()) || !(10..=15).contains(&new_mobile_no.len()) {
+            return Err(ApiError::new("INVALID_FORMAT", "FORMAT_ERROR", "new_mobile_no", "new_mobile_no must be 10 to 15 digits")
+                .with_details(json!({"min_length": 10, "max_length": 15})));
+        }
+        if new_mobile_no == auth.mobile_no {
+            return Err(ApiError::new("INVALID_VALUE", "VALUE_ERROR", "new_mobile_no", "new_mobile_no is already the account's number"));
+        }
+        TwoStepManager::require(data_service, auth, data).await?;
+        match data_service.user_exists(new_mobile_no).await {
+            Ok(false) => {}
+            Ok(true) => return Err(Self::in_use(new_mobile_no)),
+            Err(e) => return Err(ApiError::system("MOBILE_CHANGE_FAILED", "new_mobile_no", "Failed to request the mobile number change", &e)),
+        }
+
+        // Each number gets an OTP as its own country issues them; the stricter
+        // expiry and attempt limit of the two apply
+        let old_policy = LoginPolicyManager::for_mobile(&auth.mobile_no);
+        let new_policy = LoginPolicyManager::check(socket, new_mobile_no)?;
+        let expiry_secs = old_policy.otp_expiry_secs.min(new_policy.otp_expiry_secs);
+        let now = chrono::Utc::now();
+        let expires_at = now + chrono::Duration::seconds(expiry_secs);
+        let change = MobileChange {
+            id: None,
+            change_id: Snowflake::generate(),
+            user_id: auth.user.user_id.clone(),
+            old_mobile_no: auth.mobile_no.clone(),
+            new_mobile_no: new_mobile_no.to_string(),
+            old_otp: CONFIG.test_otp_for(&auth.mobile_no).unwrap_or_else(|| old_policy.generate_otp()),
+            new_otp: CONFIG.test_otp_for(new_mobile_no).unwrap_or_else(|| new_policy.generate_otp()),
+            attempts: 0,
+            max_attempts: old_policy.max_verify_attempts.min(new_policy.max_verify_attempts),
+            created_at: bson::DateTime::from_millis(now.timestamp_millis()),
+            expires_at: bson::DateTime::from_millis(expires_at.timestamp_millis()),
+            completed_at: None,
+        };
+        if let Err(e) = data_service.store_mobile_change(change.clone()).await {
+            error!("❌ Failed to store mobile change for user {}: {}", change.user_id, e);
+            return Err(ApiError::system("MOBILE_CHANGE_FAILED", "new_mobile_no", "Failed to request the mobile number change", &e));
+        }
+        let deliveries = [
+            (change.old_mobile_no.as_str(), auth.user.email.as_deref(), change.old_otp),
+            (change.new_mobile_no.as_str(), None, change.new_otp),
+        ];
+        for (mobile_no, email, otp) in deliveries {
+            if CONFIG.test_otp_for(mobile_no).is_some() {
+                continue;
+            }
+            if let Err(e) = OtpDeliveryManager::send_otp(data_service, mobile_no, email, otp, expiry_secs).await {
+                error!("❌ Failed to queue mobile change OTP for mobile {}: {}", mobile_no, e);
+            }
+        }
+        info!("📱 Mobile change {} requested for user {}: {} -> {}", change.change_id, change.user_id, change.old_mobile_no, change.new_mobile_no);
+
+        // The OTPs are only echoed back in dev mode, as on login
+        let mut response = json!({
+            "message": "OTPs sent to the current and the new mobile number",
+            "change_id": change.change_id,
+            "mobile_no": change.old_mobile_no,
+            "new_mobile_no": change.new_mobile_no,
+            "expires_at": expires_at.to_rfc3339(),
+        });
+        if CONFIG.dev_mode {
+            response["old_otp"] = json!(change.old_otp);
+            response["new_otp"] = json!(change.new_otp);
+        }
+        let response = ApiResponse::success("account:change_mobile:pending", response).for_socket(socket.id);
+        if let Err(e) = FaultInjector::emit(socket, "account:change_mobile:pending", response).await {
+            warn!("⚠️ Failed to emit account:change_mobile:pending to socket {}: {}", socket.id, e);
+        }
+        Ok(())
+    }
+
+    async fn confirm(socket: &SocketRef, data_service: &dyn DataStore, data: &Value, auth: &AuthContext) -> Result<(), ApiError> {
+        let change_id = data["change_id"].as_str().unwrap_or_default();
+        let failed = |e: &dyn std::fmt::Display| ApiError::system("MOBILE_CHANGE_FAILED", "change_id", "Failed to change the mobile number", e);
+        let change = match data_service.get_mobile_change(&auth.user.user_id, change_id).await {
+            Ok(Some(change)) if change.completed_at.is_none() && change.old_mobile_no == auth.mobile_no => change,
+            Ok(_) => return Err(ApiError::new("MOBILE_CHANGE_NOT_FOUND", "VALUE_ERROR", "change_id", "No pending mobile number change with this change_id")),
+            Err(e) => return Err(failed(&e)),
+        };
+        if change.expires_at.timestamp_millis() <= chrono::Utc::now().timestamp_millis() {
+            return Err(ApiError::new("MOBILE_CHANGE_EXPIRED", "VALUE_ERROR", "change_id", "The OTPs have expired. Please request the change again."));
+        }
+        let attempts = data_service.record_mobile_change_attempt(change_id).await.map_err(|e| failed(&e))?;
+        if attempts > change.max_attempts {
+            return Err(ApiError::new("RATE_LIMIT_EXCEEDED", "AUTHENTICATION_ERROR", "change_id", "Too many attempts. Please request the change again.")
+                .with_details(json!({"max_attempts": change.max_attempts})));
+        }
+        let old_ok = data["old_otp"].as_str() == Some(change.old_otp.to_string().as_str());
+        let new_ok = data["new_otp"].as_str() == Some(change.new_otp.to_string().as_str());
+        if !old_ok || !new_ok {
+            let field = if old_ok { "new_otp" } else { "old_otp" };
+            return Err(ApiError::new("INVALID_OTP", "AUTHENTICATION_ERROR", field, "The OTP is incorrect")
+                .with_details(json!({"attempts_left": change.max_attempts - attempts})));
+        }
+
+        let audit = MobileChangeAudit {
+            id: None,
+            request_id: Correlation::current(),
+            change_id: Some(change.change_id.clone()),
+            user_id: change.user_id.clone(),
+            old_mobile_no: change.old_mobile_no.clone(),
+            new_mobile_no: change.new_mobile_no.clone(),
+            method: "otp".to_string(),
+            operator_id: None,
+            reason: None,
+            changed_at: bson::DateTime::now(),
+        };
+        match data_service.change_user_mobile(audit).await {
+            Ok(true) => {}
+            Ok(false) => return Err(Self::in_use(&change.new_mobile_no)),
+            Err(e) => {
+                error!("❌ Failed to change mobile of user {}: {}", change.user_id, e);
+                return Err(failed(&e));
+            }
+        }
+        info!("📱 Mobile change {} completed for user {}", change.change_id, change.user_id);
+
+        let response = ApiResponse::success("account:mobile_changed", json!({
+            "message": "Mobile number changed. Please log in with the new number.",
+            "user_id": change.user_id,
+            "mobile_no": change.new_mobile_no,
+            "old_mobile_no": change.old_mobile_no,
+        })).for_socket(socket.id);
+        if let Err(e) = FaultInjector::emit(socket, "account:mobile_changed", response).await {
+            warn!("⚠️ Failed to emit account:mobile_changed to socket {}: {}", socket.id, e);
+        }
+        Ok(())
+    }
+
+    fn in_use(mobile_no: &str) -> ApiError {
+        ApiError::new("MOBILE_IN_USE", "VALUE_ERROR", "new_mobile_no", "This mobile number already belongs to an account")
+            .with_details(json!({"new_mobile_no": mobile_no}))
+    }
+}
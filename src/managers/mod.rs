@@ -3,24 +3,97 @@ pub mod validation;
 pub mod events;
 pub mod jwt;
 pub mod gameplay_events;
+pub mod room;
+pub mod spectators;
+pub mod dealer;
+pub mod game_config;
+pub mod game_modes;
+#[cfg(feature = "wasm-plugins")]
+pub mod wasm_rules;
+pub mod room_snapshots;
+pub mod room_state;
+pub mod afk;
+pub mod turn_timer;
+pub mod matchmaking;
+pub mod bot;
+pub mod party;
+pub mod notifications;
+pub mod notification_templates;
+pub mod deep_links;
+pub mod time_sync;
+pub mod latency;
+pub mod regions;
+pub mod compliance;
+pub mod sessions;
+pub mod metrics;
+pub mod token;
+pub mod snowflake;
+pub mod otp_delivery;
+pub mod login_policy;
+pub mod captcha;
+pub mod mobile_change;
+pub mod two_step;
+pub mod parental;
+pub mod devices;
+pub mod preferences;
+pub mod settings;
+pub mod progress;
+pub mod promos;
+pub mod challenges;
+pub mod scheduler;
+pub mod slo;
+pub mod health_score;
+pub mod impersonation;
+pub mod seasons;
+pub mod retention;
+pub mod revenue;
+pub mod friends;
+pub mod gifts;
+pub mod risk;
+pub mod moderation;
+pub mod chat;
+pub mod link_scan;
+pub mod error_responder;
+pub mod emit_queue;
+pub mod correlation;
+pub mod chaos;
+pub mod auth_guard;
+pub mod auth_nonce;
+pub mod payload_signing;
+pub mod protocol;
+pub mod handler_metrics;
+pub mod handshake_audit;
+pub mod funnel;
+pub mod admin;
+pub mod rbac;
+pub mod tenant;
+pub mod namespace_policy;
+pub mod room_reaper;
+pub mod escrow;
+pub mod settlement;
+pub mod startup_check;
+pub mod handlers;
 
 
 use socketioxide::SocketIo;
 use tracing::info;
 use std::sync::Arc;
-use crate::database::service::DataService;
+use crate::database::store::DataStore;
 
 pub struct GameManager;
 
 impl GameManager {
-    pub fn initialize(io: &SocketIo, data_service: Arc<DataService>) {
+    pub fn initialize(io: &SocketIo, data_service: Arc<dyn DataStore>) {
         info!("🎮 Initializing Game Manager...");
         
         // Register all custom events
         events::EventManager::register_custom_events(io, data_service.clone());
 
         // Register gameplay events
-        gameplay_events::GameplayEventManager::register_gameplay_events(io, data_service);
+        gameplay_events::GameplayEventManager::register_gameplay_events(io, data_service.clone());
+
+        // Operations dashboard
+        admin::AdminManager::register_admin_namespace(io, data_service);
         
         info!("✅ Game Manager initialized successfully!");
     }
@@ -0,0 +1,351 @@
+use socketioxide::extract::SocketRef;
+use serde_json::json;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tracing::{info, warn, error};
+
+use crate::api::response::{ApiError, ApiResponse};
+use crate::config::CONFIG;
+use crate::database::models::{DeepLink, ReportCategory, SanctionKind, UserReport, UserSanction};
+use crate::database::store::DataStore;
+use crate::managers::auth_guard::AuthGuard;
+use crate::managers::chaos::FaultInjector;
+use crate::managers::error_responder::ErrorResponder;
+use crate::managers::notifications::{NotificationCategory, NotificationManager};
+use crate::managers::tenant::TenantManager;
+use crate::managers::validation::ValidationManager;
+
+pub const MAX_REPORT_DESCRIPTION_LENGTH: usize = 500;
+// Longest mute or temporary ban an operator can hand out (30 days)
+pub const MAX_SANCTION_HOURS: i64 = 720;
+// operator_id of mutes the server gives out itself, e.g. for chat flooding,
+// and reporter_user_id of the reports it files
+pub const AUTO_MODERATOR: &str = "system";
+
+// Blocking and reporting. A block works in both directions: the two users
+// are never paired by matchmaking, do not see each other's party chat and
+// cannot send each other friend requests. Blocking also ends any friendship.
+// Reports go to the `user_reports` moderation queue, rate limited per reporter.
+// Operators work the queue through the admin API and sanction users: a warning
+// is only a notification, a mute stops party chat and a ban stops login until
+// it expires.
+pub struct ModerationManager;
+
+impl ModerationManager {
+    // Everyone the user blocked or was blocked by
+    pub async fn blocked_user_ids(data_service: &dyn DataStore, user_id: &str) -> Result<HashSet<String>, Box<dyn std::error::Error + Send + Sync>> {
+        let blocks = data_service.list_blocks(user_id).await?;
+        Ok(blocks.into_iter()
+            .map(|b| if b.user_id == user_id { b.blocked_user_id } else { b.user_id })
+            .collect())
+    }
+
+    // The user's current mute or ban, if any
+    pub async fn active_sanction(data_service: &dyn DataStore, user_id: &str, kind: SanctionKind) -> Result<Option<UserSanction>, Box<dyn std::error::Error + Send + Sync>> {
+        let now = bson::DateTime::now();
+        let sanctions = data_service.list_sanctions(user_id).await?;
+        Ok(sanctions.into_iter().find(|s| s.kind == kind && s.is_active(now)))
+    }
+
+    // Record a sanction and tell the user about it. A ban also ends every
+    // session the user has open.
+    pub async fn apply(
+        data_service: &dyn DataStore,
+        user_id: &str,
+        kind: SanctionKind,
+        duration_hours: Option<i64>,
+        reason: &str,
+        report_id: Option<&str>,
+        operator_id: &str,
+    ) -> Result<UserSanction, Box<dyn std::error::Error + Send + Sync>> {
+        let duration = duration_hours.filter(|_| kind != SanctionKind::Warn).map(chrono::Duration::hours);
+        let sanction = Self::new_sanction(user_id, kind, duration, reason, report_id, operator_id);
+        let expires_at = sanction.expires_at;
+        data_service.create_sanction(sanction.clone()).await?;
+
+        if kind == SanctionKind::Ban {
+            match data_service.get_user_by_id(user_id).await {
+                Ok(Some(user)) => {
+                    if let Err(e) = data_service.revoke_user_sessions(&user.mobile_no).await {
+                        warn!("⚠️ Failed to revoke sessions of banned user {}: {}", user_id, e);
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => warn!("⚠️ Failed to look up banned user {}: {}", user_id, e),
+            }
+        }
+
+        let until = expires_at.map(|at| at.try_to_rfc3339_string().unwrap_or_default());
+        let notification_type = match (kind, &until) {
+            (SanctionKind::Mute, Some(_)) => "sanction_mute",
+            (SanctionKind::Ban, Some(_)) => "sanction_ban",
+            _ => "sanction_warning",
+        };
+        let params = json!({ "until": until, "reason": reason });
+        let data = json!({ "sanction_id": sanction.sanction_id, "kind": kind.as_str(), "reason": reason, "expires_at": until });
+        if let Err(e) = NotificationManager::dispatch(data_service, user_id, NotificationCategory::System, notification_type, params, data, Some(DeepLink::new("open_sanction", json!({ "sanction_id": sanction.sanction_id })))).await {
+            warn!("⚠️ Failed to notify user {} of sanction {}: {}", user_id, sanction.sanction_id, e);
+        }
+        info!("🔨 {} sanctioned user {} with {}", operator_id, user_id, kind.as_str());
+        Ok(sanction)
+    }
+
+    // Mute handed out by the server rather than an operator. The user is told
+    // in the response to whatever they tried, so no notification is sent.
+    pub async fn auto_mute(data_service: &dyn DataStore, user_id: &str, duration: chrono::Duration, reason: &str) -> Result<UserSanction, Box<dyn std::error::Error + Send + Sync>> {
+        let sanction = Self::new_sanction(user_id, SanctionKind::Mute, Some(duration), reason, None, AUTO_MODERATOR);
+        data_service.create_sanction(sanction.clone()).await?;
+        info!("🔇 Muted user {} for {}s: {}", user_id, duration.num_seconds(), reason);
+        Ok(sanction)
+    }
+
+    // Report filed by the server itself, e.g. for a blocked chat link. It
+    // skips the reporter limits and nobody is told when it is resolved.
+    pub async fn auto_report(data_service: &dyn DataStore, user_id: &str, category: ReportCategory, description: &str, room_id: Option<&str>) -> Result<UserReport, Box<dyn std::error::Error + Send + Sync>> {
+        let report = UserReport {
+            id: None,
+            report_id: uuid::Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext)).simple().to_string(),
+            reporter_user_id: AUTO_MODERATOR.to_string(),
+            reported_user_id: user_id.to_string(),
+            category,
+            description: Some(description.chars().take(MAX_REPORT_DESCRIPTION_LENGTH).collect()),
+            room_id: room_id.map(str::to_string),
+            status: "open".to_string(),
+            created_at: bson::DateTime::now(),
+            resolution: None,
+            resolved_by: None,
+            resolved_at: None,
+        };
+        data_service.create_user_report(report.clone()).await?;
+        info!("🚩 Reported user {} to the moderation queue: {}", user_id, description);
+        Ok(report)
+    }
+
+    fn new_sanction(user_id: &str, kind: SanctionKind, duration: Option<chrono::Duration>, reason: &str, report_id: Option<&str>, operator_id: &str) -> UserSanction {
+        let now = chrono::Utc::now();
+        UserSanction {
+            id: None,
+            sanction_id: uuid::Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext)).simple().to_string(),
+            user_id: user_id.to_string(),
+            kind,
+            reason: reason.to_string(),
+            report_id: report_id.map(str::to_string),
+            operator_id: operator_id.to_string(),
+            created_at: bson::DateTime::from_millis(now.timestamp_millis()),
+            expires_at: duration.map(|duration| bson::DateTime::from_millis((now + duration).timestamp_millis())),
+            lifted_by: None,
+            lifted_at: None,
+        }
+    }
+
+    // Refuse login and OTP verification while the account is banned. A failed
+    // lookup lets the user through rather than locking everyone out.
+    pub async fn check_login(data_service: &dyn DataStore, mobile_no: &str) -> Result<(), ApiError> {
+        let ban = match data_service.get_user_by_mobile(mobile_no).await {
+            Ok(Some(user)) => Self::active_sanction(data_service, &user.user_id, SanctionKind::Ban).await,
+            Ok(None) => Ok(None),
+            Err(e) => Err(e),
+        };
+        match ban {
+            Ok(Some(ban)) => {
+                let until = ban.expires_at.map(|at| at.try_to_rfc3339_string().unwrap_or_default());
+                Err(ApiError::new("ACCOUNT_SUSPENDED", "AUTHENTICATION_ERROR", "mobile_no", "This account is suspended")
+                    .with_details(json!({ "until": until, "reason": ban.reason })))
+            }
+            Ok(None) => Ok(()),
+            Err(e) => {
+                warn!("⚠️ Failed to check bans for mobile {}: {}", mobile_no, e);
+                Ok(())
+            }
+        }
+    }
+
+    // Reporters may file REPORT_HOURLY_LIMIT reports per hour, and report the
+    // same user once per REPORT_REPEAT_WINDOW_HOURS
+    async fn check_report_limits(data_service: &dyn DataStore, reporter_user_id: &str, target_user_id: &str) -> Result<(), ApiError> {
+        let now = chrono::Utc::now();
+        let hour_ago = now - chrono::Duration::hours(1);
+        let repeat_cutoff = now - chrono::Duration::hours(CONFIG.report_repeat_window_hours);
+        let recent = data_service.reports_filed_since(reporter_user_id, hour_ago.min(repeat_cutoff)).await.map_err(|e| {
+            error!("❌ Failed to check reports filed by user {}: {}", reporter_user_id, e);
+            ApiError::system("REPORT_FAILED", "target_user_id", "Failed to file the report", &e)
+        })?;
+
+        let hour_ago = bson::DateTime::from_millis(hour_ago.timestamp_millis());
+        let last_hour = recent.iter().filter(|r| r.created_at >= hour_ago).count();
+        if last_hour >= TenantManager::limits().report_hourly_limit {
+            return Err(ApiError::new("REPORT_RATE_LIMITED", "REPORT_ERROR", "target_user_id", "Too many reports. Please try again later.")
+                .with_details(json!({ "hourly_limit": TenantManager::limits().report_hourly_limit })));
+        }
+        let repeat_cutoff = bson::DateTime::from_millis(repeat_cutoff.timestamp_millis());
+        if recent.iter().any(|r| r.reported_user_id == target_user_id && r.created_at >= repeat_cutoff) {
+            return Err(ApiError::new("REPORT_DUPLICATE", "REPORT_ERROR", "target_user_id", "You already reported this user recently")
+                .with_details(json!({ "target_user_id": target_user_id, "repeat_window_hours": CONFIG.report_repeat_window_hours })));
+        }
+        Ok(())
+    }
+
+    // Blocking and reporting on the main namespace:
+    //   user:block   { mobile_no, session_token, target_user_id }                                  -> user:blocked
+    //   user:unblock { mobile_no, session_token, target_user_id }                                  -> user:unblocked
+    //   user:report  { mobile_no, session_token, target_user_id, category, description?, room_id? } -> user:reported
+    pub fn register_moderation_events(socket: &SocketRef, data_service: Arc<dyn DataStore>) {
+        let ds = data_service.clone();
+        AuthGuard::require_session(socket, "user:block", data_service.clone(), move |socket, data, auth| {
+            let ds = ds.clone();
+            async move {
+                info!("🚫 Received user:block from {}: {:?}", socket.id, data["target_user_id"]);
+                if let Err(error_details) = ValidationManager::validate_block_data(&data) {
+                    info!("❌ Block validation failed for socket {}: {:?}", socket.id, error_details);
+                    ErrorResponder::send(&socket, &*ds, error_details).await;
+                    return;
+                }
+                let user = auth.user;
+                let target_user_id = data["target_user_id"].as_str().unwrap_or_default();
+                if target_user_id == user.user_id {
+                    let error = ApiError::new("BLOCK_SELF", "BLOCK_ERROR", "target_user_id", "You cannot block yourself");
+                    ErrorResponder::send(&socket, &*ds, error).await;
+                    return;
+                }
+
+                match ds.get_user_by_id(target_user_id).await {
+                    Ok(Some(_)) => {}
+                    Ok(None) => {
+                        let error = ApiError::new("BLOCK_TARGET_NOT_FOUND", "BLOCK_ERROR", "target_user_id", "No such user")
+                            .with_details(json!({"target_user_id": target_user_id}));
+                        ErrorResponder::send(&socket, &*ds, error).await;
+                        return;
+                    }
+                    Err(e) => {
+                        error!("❌ Failed to look up user {}: {}", target_user_id, e);
+                        ErrorResponder::send(&socket, &*ds, ApiError::system("BLOCK_UPDATE_FAILED", "target_user_id", "Failed to block user", &e)).await;
+                        return;
+                    }
+                }
+
+                let result = match ds.block_user(&user.user_id, target_user_id).await {
+                    Ok(blocked) => ds.remove_friend(&user.user_id, target_user_id).await.map(|_| blocked),
+                    Err(e) => Err(e),
+                };
+                let blocked = match result {
+                    Ok(blocked) => blocked,
+                    Err(e) => {
+                        error!("❌ Failed to block user {} for user {}: {}", target_user_id, user.user_id, e);
+                        ErrorResponder::send(&socket, &*ds, ApiError::system("BLOCK_UPDATE_FAILED", "target_user_id", "Failed to block user", &e)).await;
+                        return;
+                    }
+                };
+
+                let response = ApiResponse::success("user:blocked", json!({
+                    "target_user_id": target_user_id,
+                    "blocked": blocked
+                })).for_socket(socket.id);
+                match FaultInjector::emit(&socket, "user:blocked", response).await {
+                    Ok(_) => info!("✅ User {} blocked {}", user.user_id, target_user_id),
+                    Err(e) => warn!("⚠️ Failed to emit user:blocked to socket {}: {}", socket.id, e),
+                }
+            }
+        });
+
+        let ds = data_service.clone();
+        AuthGuard::require_session(socket, "user:unblock", data_service.clone(), move |socket, data, auth| {
+            let ds = ds.clone();
+            async move {
+                info!("🚫 Received user:unblock from {}: {:?}", socket.id, data["target_user_id"]);
+                if let Err(error_details) = ValidationManager::validate_block_data(&data) {
+                    info!("❌ Block validation failed for socket {}: {:?}", socket.id, error_details);
+                    ErrorResponder::send(&socket, &*ds, error_details).await;
+                    return;
+                }
+                let user = auth.user;
+                let target_user_id = data["target_user_id"].as_str().unwrap_or_default();
+                let unblocked = match ds.unblock_user(&user.user_id, target_user_id).await {
+                    Ok(unblocked) => unblocked,
+                    Err(e) => {
+                        error!("❌ Failed to unblock user {} for user {}: {}", target_user_id, user.user_id, e);
+                        ErrorResponder::send(&socket, &*ds, ApiError::system("BLOCK_UPDATE_FAILED", "target_user_id", "Failed to unblock user", &e)).await;
+                        return;
+                    }
+                };
+
+                let response = ApiResponse::success("user:unblocked", json!({
+                    "target_user_id": target_user_id,
+                    "unblocked": unblocked
+                })).for_socket(socket.id);
+                if let Err(e) = FaultInjector::emit(&socket, "user:unblocked", response).await {
+                    warn!("⚠️ Failed to emit user:unblocked to socket {}: {}", socket.id, e);
+                }
+            }
+        });
+
+        let ds = data_service.clone();
+        AuthGuard::require_session(socket, "user:report", data_service, move |socket, data, auth| {
+            let ds = ds.clone();
+            async move {
+                info!("🚩 Received user:report from {}: {:?} ({:?})", socket.id, data["target_user_id"], data["category"]);
+                if let Err(error_details) = ValidationManager::validate_report_data(&data) {
+                    info!("❌ Report validation failed for socket {}: {:?}", socket.id, error_details);
+                    ErrorResponder::send(&socket, &*ds, error_details).await;
+                    return;
+                }
+                let user = auth.user;
+                let target_user_id = data["target_user_id"].as_str().unwrap_or_default();
+                if target_user_id == user.user_id {
+                    let error = ApiError::new("REPORT_SELF", "REPORT_ERROR", "target_user_id", "You cannot report yourself");
+                    ErrorResponder::send(&socket, &*ds, error).await;
+                    return;
+                }
+                match ds.get_user_by_id(target_user_id).await {
+                    Ok(Some(_)) => {}
+                    Ok(None) => {
+                        let error = ApiError::new("REPORT_TARGET_NOT_FOUND", "REPORT_ERROR", "target_user_id", "No such user")
+                            .with_details(json!({"target_user_id": target_user_id}));
+                        ErrorResponder::send(&socket, &*ds, error).await;
+                        return;
+                    }
+                    Err(e) => {
+                        error!("❌ Failed to look up user {}: {}", target_user_id, e);
+                        ErrorResponder::send(&socket, &*ds, ApiError::system("REPORT_FAILED", "target_user_id", "Failed to file the report", &e)).await;
+                        return;
+                    }
+                }
+                if let Err(error) = Self::check_report_limits(&*ds, &user.user_id, target_user_id).await {
+                    ErrorResponder::send(&socket, &*ds, error).await;
+                    return;
+                }
+
+                let Some(category) = data["category"].as_str().and_then(ReportCategory::parse) else { return };
+                let report = UserReport {
+                    id: None,
+                    report_id: uuid::Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext)).simple().to_string(),
+                    reporter_user_id: user.user_id.clone(),
+                    reported_user_id: target_user_id.to_string(),
+                    category,
+                    description: data["description"].as_str().map(str::trim).filter(|d| !d.is_empty()).map(str::to_string),
+                    room_id: data["room_id"].as_str().map(str::to_string),
+                    status: "open".to_string(),
+                    created_at: bson::DateTime::now(),
+                    resolution: None,
+                    resolved_by: None,
+                    resolved_at: None,
+                };
+                if let Err(e) = ds.create_user_report(report.clone()).await {
+                    error!("❌ Failed to store report by user {}: {}", user.user_id, e);
+                    ErrorResponder::send(&socket, &*ds, ApiError::system("REPORT_FAILED", "target_user_id", "Failed to file the report", &e)).await;
+                    return;
+                }
+
+                let response = ApiResponse::success("user:reported", json!({
+                    "report_id": report.report_id,
+                    "target_user_id": target_user_id,
+                    "category": category.as_str(),
+                    "status": report.status
+                })).for_socket(socket.id);
+                match FaultInjector::emit(&socket, "user:reported", response).await {
+                    Ok(_) => info!("✅ User {} reported {} for {}", user.user_id, target_user_id, category.as_str()),
+                    Err(e) => warn!("⚠️ Failed to emit user:reported to socket {}: {}", socket.id, e),
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,161 @@
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use socketioxide::extract::SocketRef;
+use std::collections::HashMap;
+use tracing::{info, warn};
+
+use crate::api::response::ApiError;
+use crate::config::CONFIG;
+use crate::database::store::DataStore;
+use crate::managers::auth_guard::{AuthContext, AuthGuard};
+use crate::managers::game_modes::GameModeRegistry;
+use crate::managers::impersonation::ImpersonationManager;
+use crate::managers::rbac::{AdminIdentity, Rbac};
+use crate::managers::sessions::SessionMetricsManager;
+use crate::managers::tenant::{Tenant, TenantManager};
+
+// Hosts login, so it can only be public
+const LOGIN_NAMESPACE: &str = "/";
+pub const ADMIN_NAMESPACE: &str = "/admin";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NamespacePolicy {
+    Public,     // Anyone may connect
+    Session,    // `mobile_no` + `session_token` in the auth payload
+    Jwt,        // `jwt_token` in the auth payload
+    Admin,      // `admin_token` of ADMIN_API_TOKEN or an operator
+}
+
+impl NamespacePolicy {
+    pub fn parse(policy: &str) -> Option<Self> {
+        match policy {
+            "public" => Some(NamespacePolicy::Public),
+            "session" => Some(NamespacePolicy::Session),
+            "jwt" => Some(NamespacePolicy::Jwt),
+            "admin" => Some(NamespacePolicy::Admin),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NamespacePolicy::Public => "public",
+            NamespacePolicy::Session => "session",
+            NamespacePolicy::Jwt => "jwt",
+            NamespacePolicy::Admin => "admin",
+        }
+    }
+}
+
+// Who a namespace connection authenticated as
+#[derive(Debug, Clone)]
+pub enum NamespaceCaller {
+    Anonymous,
+    User(Box<AuthContext>),
+    Admin(AdminIdentity),
+}
+
+static POLICIES: Lazy<HashMap<String, NamespacePolicy>> = Lazy::new(NamespaceGuard::load);
+
+// Who may connect to which namespace. NAMESPACE_ALLOWLIST (when set) limits
+// the game-mode namespaces and /admin that get registered at all; "/" is
+// always served. NAMESPACE_POLICIES sets the credentials each namespace
+// needs in the Socket.IO auth payload, checked once when the socket
+// connects: unlisted namespaces are public, except /admin, which is always
+// admin-only, and game-mode namespaces, which need a jwt (or a session). A refused socket gets connection_error and is disconnected.
+pub struct NamespaceGuard;
+
+impl NamespaceGuard {
+    fn load() -> HashMap<String, NamespacePolicy> {
+        // A typo would silently leave a namespace open
+        let policies: HashMap<String, NamespacePolicy> = CONFIG.namespace_policies.iter().map(|entry| {
+            let Some((namespace, policy)) = entry.rsplit_once('=') else {
+                panic!("NAMESPACE_POLICIES entry {:?} must be <namespace>=<policy>", entry);
+            };
+            let (namespace, policy) = (namespace.trim(), policy.trim().to_ascii_lowercase());
+            let Some(policy) = NamespacePolicy::parse(&policy) else {
+                panic!("NAMESPACE_POLICIES: {} has unknown policy {:?} (public, session, jwt or admin)", namespace, policy);
+            };
+            if !namespace.starts_with('/') {
+                panic!("NAMESPACE_POLICIES: namespace {:?} must start with '/'", namespace);
+            }
+            if namespace == LOGIN_NAMESPACE && policy != NamespacePolicy::Public {
+                panic!("NAMESPACE_POLICIES: {} hosts login and must stay public", LOGIN_NAMESPACE);
+            }
+            if namespace == ADMIN_NAMESPACE && policy != NamespacePolicy::Admin {
+                panic!("NAMESPACE_POLICIES: {} is always admin-only", ADMIN_NAMESPACE);
+            }
+            if Self::is_game_namespace(namespace) && !matches!(policy, NamespacePolicy::Session | NamespacePolicy::Jwt) {
+                panic!("NAMESPACE_POLICIES: game namespace {} needs session or jwt, gameplay acts for the authenticated user", namespace);
+            }
+            (namespace.to_string(), policy)
+        }).collect();
+        for (namespace, policy) in &policies {
+            info!("🔐 Namespace {} requires {} authentication", namespace, policy.as_str());
+        }
+        policies
+    }
+
+    pub fn policy(namespace: &str) -> NamespacePolicy {
+        match POLICIES.get(namespace) {
+            Some(policy) => *policy,
+            None if namespace == ADMIN_NAMESPACE => NamespacePolicy::Admin,
+            None if Self::is_game_namespace(namespace) => NamespacePolicy::Jwt,
+            None => NamespacePolicy::Public,
+        }
+    }
+
+    fn is_game_namespace(namespace: &str) -> bool {
+        GameModeRegistry::modes().iter().any(|mode| mode.namespace == namespace)
+    }
+
+    // Whether `namespace` is registered; sockets asking for others are refused by Socket.IO
+    pub fn allowed(namespace: &str) -> bool {
+        namespace == LOGIN_NAMESPACE
+            || CONFIG.namespace_allowlist.is_empty()
+            || CONFIG.namespace_allowlist.iter().any(|allowed| allowed == namespace)
+    }
+
+    async fn authenticate(data_service: &dyn DataStore, policy: NamespacePolicy, auth: &Value) -> Result<NamespaceCaller, ApiError> {
+        match policy {
+            NamespacePolicy::Public => Ok(NamespaceCaller::Anonymous),
+            NamespacePolicy::Session => AuthGuard::verify_session(data_service, auth).await.map(|user| NamespaceCaller::User(Box::new(user))),
+            NamespacePolicy::Jwt => AuthGuard::verify_jwt(data_service, auth).await.map(|user| NamespaceCaller::User(Box::new(user))),
+            NamespacePolicy::Admin => match Rbac::authenticate(data_service, auth["admin_token"].as_str()).await {
+                Some(identity) => Ok(NamespaceCaller::Admin(identity)),
+                None => Err(ApiError::new("ADMIN_AUTH_REQUIRED", "AUTHENTICATION_ERROR", "admin_token", "Admin authentication required")),
+            },
+        }
+    }
+
+    // Resolve the socket's tenant and check the namespace's policy within it.
+    // None when the socket was refused; the caller disconnects it.
+    pub async fn connect(socket: &SocketRef, data_service: &dyn DataStore, auth: &Value) -> Option<(&'static Tenant, NamespaceCaller)> {
+        let tenant = TenantManager::connect(socket, data_service, auth).await?;
+        let namespace = socket.ns();
+        let policy = Self::policy(namespace);
+        match TenantManager::scope(tenant, Self::authenticate(data_service, policy, auth)).await {
+            Ok(caller) => {
+                if let NamespaceCaller::User(user) = &caller {
+                    TenantManager::scope(tenant, async {
+                        ImpersonationManager::bind(socket, &user.user.user_id);
+                        SessionMetricsManager::authenticated(socket, &user.user.user_id);
+                    }).await;
+                }
+                Some((tenant, caller))
+            }
+            Err(mut error) => {
+                warn!("🚫 Refused {} connection from socket {} ({} policy): {}", namespace, socket.id, policy.as_str(), error.error_code);
+                if !error.details.is_object() {
+                    error.details = json!({});
+                }
+                error.details["namespace"] = json!(namespace);
+                error.details["policy"] = json!(policy.as_str());
+                let error = error.for_socket(socket.id);
+                let _ = socket.emit("connection_error", error);
+                TenantManager::remove_socket(&socket.id.to_string()).await;
+                None
+            }
+        }
+    }
+}
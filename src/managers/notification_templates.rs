@@ -0,0 +1,196 @@
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+use crate::config::CONFIG;
+use crate::database::models::NotificationTemplate;
+use crate::database::store::DataStore;
+use crate::managers::tenant::TenantManager;
+
+// Limits on templates set through the admin API
+const MAX_TITLE_LENGTH: usize = 120;
+const MAX_BODY_LENGTH: usize = 500;
+
+// Built-in English title and body of every notification type. A stored
+// template may use the same placeholders and no others.
+const BUILTIN: &[(&str, &str, &str)] = &[
+    ("friend_request", "New friend request", "{name} wants to be your friend"),
+    ("friend_accepted", "Friend request accepted", "{name} is now your friend"),
+    ("gift_coins", "You received a gift", "{sender} sent you {coins} coins"),
+    ("gift_item", "You received a gift", "{sender} sent you {quantity} x {item_id}"),
+    ("party_invite", "Party invite", "{leader_id} invited you to their party"),
+    ("turn_reminder", "Your game is waiting", "Make a move within {seconds} seconds to keep your seat"),
+    ("season_reward", "Season rewards", "You finished {season} at rank #{rank} and earned {coins} coins"),
+    ("sanction_mute", "You have been muted", "You cannot chat until {until}. Reason: {reason}"),
+    ("sanction_ban", "Your account is suspended", "You cannot log in until {until}. Reason: {reason}"),
+    ("sanction_warning", "Warning from the moderators", "Please follow the community rules. Reason: {reason}"),
+    ("report_actioned", "Your report was reviewed", "Thanks for your report. We reviewed it and took action."),
+    ("report_dismissed", "Your report was reviewed", "Thanks for your report. We reviewed it and found no rule was broken."),
+    ("login_undelivered", "Finish signing in", "We could not reach the app while you were signing in. Open it to continue."),
+];
+
+// Stored templates by (notification_type, language_code)
+type Templates = HashMap<(String, String), NotificationTemplate>;
+// A tenant's stored templates and when they were loaded
+type CachedTemplates = (Instant, Arc<Templates>);
+
+// Cached templates of each tenant, by tenant id
+static TEMPLATES: Lazy<RwLock<HashMap<String, CachedTemplates>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+// Push and inbox notification text in the user's language. Each notification
+// type has a built-in English template; operators store translations (or
+// replace the English) per language in `notification_templates` through the
+// admin API. A notification for a user with language_code "pt-BR" uses the
+// first of pt-br, pt, NOTIFICATION_DEFAULT_LANGUAGE and the built-in text that
+// exists. Servers cache the stored templates for
+// NOTIFICATION_TEMPLATE_CACHE_SECS; the one that takes an edit drops its cache.
+pub struct NotificationTemplateManager;
+
+impl NotificationTemplateManager {
+    pub fn builtin(notification_type: &str) -> Option<(&'static str, &'static str)> {
+        BUILTIN.iter()
+            .find(|(kind, _, _)| *kind == notification_type)
+            .map(|(_, title, body)| (*title, *body))
+    }
+
+    // Lowercase language tag with '-' separators ("pt_BR" -> "pt-br"), or
+    // None when it does not look like one
+    pub fn normalize_language(language_code: &str) -> Option<String> {
+        let language = language_code.trim().to_lowercase().replace('_', "-");
+        let mut parts = language.split('-');
+        let primary = parts.next().unwrap_or_default();
+        let primary_ok = (2..=3).contains(&primary.len()) && primary.chars().all(|c| c.is_ascii_lowercase());
+        let rest_ok = parts.all(|part| (2..=8).contains(&part.len()) && part.chars().all(|c| c.is_ascii_alphanumeric()));
+        (primary_ok && rest_ok).then_some(language)
+    }
+
+    // Languages to try in order for a user's language_code
+    pub fn fallback_chain(language_code: Option<&str>) -> Vec<String> {
+        let mut chain: Vec<String> = Vec::new();
+        if let Some(language) = language_code.and_then(Self::normalize_language) {
+            let base = language.split_once('-').map(|(base, _)| base.to_string());
+            chain.push(language);
+            chain.extend(base);
+        }
+        if !chain.contains(&CONFIG.notification_default_language) {
+            chain.push(CONFIG.notification_default_language.clone());
+        }
+        chain
+    }
+
+    // Names of the {placeholders} in a template text
+    fn placeholders(text: &str) -> Vec<&str> {
+        text.split('{').skip(1).filter_map(|part| part.split_once('}').map(|(name, _)| name)).collect()
+    }
+
+    fn fill(text: &str, params: &Value) -> String {
+        let mut filled = text.to_string();
+        if let Some(params) = params.as_object() {
+            for (name, value) in params {
+                let value = match value {
+                    Value::String(value) => value.clone(),
+                    value => value.to_string(),
+                };
+                filled = filled.replace(&format!("{{{}}}", name), &value);
+            }
+        }
+        filled
+    }
+
+    // Check a template an operator wants to store: Err((field, message))
+    pub fn validate(notification_type: &str, title: &str, body: &str) -> Result<(), (&'static str, String)> {
+        let Some((builtin_title, builtin_body)) = Self::builtin(notification_type) else {
+            return Err(("notification_type", format!("Unknown notification type {}", notification_type)));
+        };
+        let allowed: Vec<&str> = Self::placeholders(builtin_title).into_iter().chain(Self::placeholders(builtin_body)).collect();
+        for (field, text, max_length) in [("title", title, MAX_TITLE_LENGTH), ("body", body, MAX_BODY_LENGTH)] {
+            if text.trim().is_empty() || text.chars().count() > max_length {
+                return Err((field, format!("{} must be 1-{} characters", field, max_length)));
+            }
+            if let Some(unknown) = Self::placeholders(text).into_iter().find(|name| !allowed.contains(name)) {
+                return Err((field, format!("Unknown placeholder {{{}}}; {} can use {:?}", unknown, notification_type, allowed)));
+            }
+        }
+        Ok(())
+    }
+
+    // The current tenant's stored templates, from the cache while it is fresh
+    async fn stored(data_service: &dyn DataStore) -> Result<Arc<Templates>, Box<dyn std::error::Error + Send + Sync>> {
+        let tenant_id = TenantManager::current().tenant_id.clone();
+        let max_age = Duration::from_secs(CONFIG.notification_template_cache_secs);
+        let cached = TEMPLATES.read().unwrap_or_else(|e| e.into_inner())
+            .get(&tenant_id)
+            .filter(|(loaded_at, _)| loaded_at.elapsed() < max_age)
+            .map(|(_, templates)| templates.clone());
+        if let Some(templates) = cached {
+            return Ok(templates);
+        }
+
+        let templates: Templates = data_service.list_notification_templates().await?
+            .into_iter()
+            .map(|template| ((template.notification_type.clone(), template.language_code.clone()), template))
+            .collect();
+        let templates = Arc::new(templates);
+        TEMPLATES.write().unwrap_or_else(|e| e.into_inner()).insert(tenant_id, (Instant::now(), templates.clone()));
+        Ok(templates)
+    }
+
+    // Drop the current tenant's cached templates after an edit
+    pub fn invalidate() {
+        TEMPLATES.write().unwrap_or_else(|e| e.into_inner()).remove(&TenantManager::current().tenant_id);
+    }
+
+    // Title, body and the language they are in ("builtin" for the built-in
+    // text) of a notification for a user with this language_code
+    pub async fn render(data_service: &dyn DataStore, notification_type: &str, language_code: Option<&str>, params: &Value) -> (String, String, String) {
+        let stored = match Self::stored(data_service).await {
+            Ok(stored) => stored,
+            Err(e) => {
+                warn!("⚠️ Failed to load notification templates - using the built-in ones: {}", e);
+                Arc::new(Templates::new())
+            }
+        };
+        for language in Self::fallback_chain(language_code) {
+            if let Some(template) = stored.get(&(notification_type.to_string(), language.clone())) {
+                return (Self::fill(&template.title, params), Self::fill(&template.body, params), language);
+            }
+        }
+        let (title, body) = Self::builtin(notification_type).unwrap_or_else(|| {
+            warn!("⚠️ No template for notification type {}", notification_type);
+            (notification_type, "")
+        });
+        (Self::fill(title, params), Self::fill(body, params), "builtin".to_string())
+    }
+
+    // Every notification type with its built-in text and the languages it is
+    // stored in, for the admin API
+    pub async fn catalog(data_service: &dyn DataStore) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
+        let stored = data_service.list_notification_templates().await?;
+        Ok(BUILTIN.iter().map(|(notification_type, title, body)| {
+            let templates: Vec<Value> = stored.iter()
+                .filter(|t| t.notification_type == *notification_type)
+                .map(Self::template_view)
+                .collect();
+            json!({
+                "notification_type": notification_type,
+                "placeholders": Self::placeholders(title).into_iter().chain(Self::placeholders(body)).collect::<Vec<_>>(),
+                "builtin": { "title": title, "body": body },
+                "templates": templates,
+            })
+        }).collect())
+    }
+
+    pub fn template_view(template: &NotificationTemplate) -> Value {
+        json!({
+            "notification_type": template.notification_type,
+            "language_code": template.language_code,
+            "title": template.title,
+            "body": template.body,
+            "updated_by": template.updated_by,
+            "updated_at": template.updated_at.try_to_rfc3339_string().unwrap_or_default(),
+        })
+    }
+}
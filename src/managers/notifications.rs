@@ -0,0 +1,90 @@
+use serde_json::Value;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::managers::correlation::Correlation;
+use crate::database::models::{ChannelPreference, DeepLink, InboxNotification};
+use crate::database::outbox::Outbox;
+use crate::database::store::DataStore;
+use crate::managers::deep_links::DeepLinkManager;
+use crate::managers::notification_templates::NotificationTemplateManager;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NotificationCategory {
+    MatchUpdates,
+    TurnReminders,
+    PartyInvites,
+    Social,     // Friend requests and gifts
+    System,     // Account/security notices - cannot be opted out of
+}
+
+impl NotificationCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationCategory::MatchUpdates => "match_updates",
+            NotificationCategory::TurnReminders => "turn_reminders",
+            NotificationCategory::PartyInvites => "party_invites",
+            NotificationCategory::Social => "social",
+            NotificationCategory::System => "system",
+        }
+    }
+}
+
+pub struct NotificationManager;
+
+impl NotificationManager {
+    // Single dispatch path for push and inbox notifications. The user's
+    // preferences for the category decide which channels are used; when both
+    // are opted out nothing is stored. The title and body come from the
+    // template of `notification_type` in the user's language, filled with
+    // `params`; `action` is where a tap takes the user and must match a
+    // registered deep-link schema. Returns the channels that were used.
+    pub async fn dispatch(
+        data_service: &dyn DataStore,
+        user_id: &str,
+        category: NotificationCategory,
+        notification_type: &str,
+        params: Value,
+        data: Value,
+        action: Option<DeepLink>,
+    ) -> Result<ChannelPreference, Box<dyn std::error::Error + Send + Sync>> {
+        if let Some(action) = &action {
+            DeepLinkManager::validate(action)?;
+        }
+        let channels = match category {
+            NotificationCategory::System => ChannelPreference::default(),
+            _ => data_service
+                .get_notification_preferences(user_id)
+                .await?
+                .category(category.as_str())
+                .unwrap_or_default(),
+        };
+
+        if !channels.push && !channels.inbox {
+            info!("🔕 Suppressed {} notification for user {} (opted out)", category.as_str(), user_id);
+            return Ok(channels);
+        }
+
+        let language_code = data_service.get_user_by_id(user_id).await?.and_then(|user| user.language_code);
+        let (title, body, language) = NotificationTemplateManager::render(data_service, notification_type, language_code.as_deref(), &params).await;
+        let notification = InboxNotification {
+            id: None,
+            request_id: Correlation::current(),
+            notification_id: Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext)).to_string(),
+            user_id: user_id.to_string(),
+            category: category.as_str().to_string(),
+            title,
+            body,
+            data,
+            action,
+            show_in_inbox: channels.inbox,
+            push_status: channels.push.then(|| "pending".to_string()),
+            read: false,
+            created_at: bson::DateTime::from_millis(chrono::Utc::now().timestamp_millis()),
+        };
+        let push = channels.push.then(|| Outbox::push(&notification)).flatten();
+        data_service.store_inbox_notification(notification, push).await?;
+        info!("🔔 Dispatched {} notification to user {} in {} (push: {}, inbox: {})", notification_type, user_id, language, channels.push, channels.inbox);
+        Ok(channels)
+    }
+}
@@ -0,0 +1,37 @@
+use tracing::info;
+
+use crate::managers::correlation::Correlation;
+use crate::database::models::OtpDeliveryRequest;
+use crate::database::store::DataStore;
+
+pub struct OtpDeliveryManager;
+
+impl OtpDeliveryManager {
+    // Queue the OTP for the SMS gateway, plus email when the user gave an address.
+    // This is the only way the OTP reaches the user outside of dev mode; the
+    // request expires with the login session's OTP.
+    pub async fn send_otp(data_service: &dyn DataStore, mobile_no: &str, email: Option<&str>, otp: i32, otp_expiry_secs: i64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let now = chrono::Utc::now();
+        let expires_at = bson::DateTime::from_millis((now + chrono::Duration::seconds(otp_expiry_secs)).timestamp_millis());
+
+        let mut channels = vec!["sms"];
+        if email.is_some() {
+            channels.push("email");
+        }
+        for channel in channels {
+            data_service.store_otp_delivery_request(OtpDeliveryRequest {
+                id: None,
+                request_id: Correlation::current(),
+                mobile_no: mobile_no.to_string(),
+                email: email.map(|e| e.to_string()),
+                channel: channel.to_string(),
+                otp,
+                status: "pending".to_string(),
+                expires_at,
+                created_at: bson::DateTime::from_millis(now.timestamp_millis()),
+            }).await?;
+        }
+        info!("📨 OTP delivery queued for mobile: {}", mobile_no);
+        Ok(())
+    }
+}
@@ -0,0 +1,223 @@
+use chrono::{NaiveDate, Utc};
+use serde_json::{json, Value};
+use socketioxide::extract::SocketRef;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+use crate::api::response::{ApiError, ApiResponse};
+use crate::config::CONFIG;
+use crate::database::models::{ParentalControls, UserRegister};
+use crate::database::store::DataStore;
+use crate::managers::auth_guard::{AuthContext, AuthGuard};
+use crate::managers::chaos::FaultInjector;
+use crate::managers::error_responder::ErrorResponder;
+use crate::managers::two_step::{TwoStepManager, PIN_LENGTH};
+
+// Oldest accepted date of birth, in years
+pub const MAX_AGE: u32 = 120;
+pub const MAX_DAILY_SPEND_LIMIT: i64 = 1_000_000;
+// Wrong parental PINs in a row before the controls lock, and for how long
+const MAX_FAILED_ATTEMPTS: u32 = 5;
+const LOCK_MINUTES: i64 = 15;
+
+// Age gate and parental controls. The date of birth given in set:profile
+// makes a user younger than AGE_OF_MAJORITY a minor (so does a missing one
+// with AGE_GATE_REQUIRE_DOB). Minors cannot spend coins until a parent sets
+// a parental PIN and a daily spending limit; the wallet refuses debits past
+// the limit for the rest of the UTC day.
+//   parental:set     { mobile_no, session_token, parental_pin, daily_spend_limit[, new_pin] } -> parental:updated
+//   parental:disable { mobile_no, session_token, parental_pin }                               -> parental:disabled
+//   parental:status  { mobile_no, session_token }                                             -> parental:status
+// The first parental:set chooses the PIN; later ones need it, and `new_pin`
+// replaces it.
+pub struct ParentalManager;
+
+impl ParentalManager {
+    pub fn register_events(socket: &SocketRef, data_service: Arc<dyn DataStore>) {
+        let ds = data_service.clone();
+        AuthGuard::require_session(socket, "parental:set", data_service.clone(), move |socket, data, auth| {
+            let ds = ds.clone();
+            async move {
+                info!("👪 Received parental:set from {}", socket.id);
+                if let Err(error) = Self::set(&socket, &*ds, &data, &auth).await {
+                    ErrorResponder::send(&socket, &*ds, error).await;
+                }
+            }
+        });
+
+        let ds = data_service.clone();
+        AuthGuard::require_session(socket, "parental:disable", data_service.clone(), move |socket, data, auth| {
+            let ds = ds.clone();
+            async move {
+                info!("👪 Received parental:disable from {}", socket.id);
+                if let Err(error) = Self::disable(&socket, &*ds, &data, &auth).await {
+                    ErrorResponder::send(&socket, &*ds, error).await;
+                }
+            }
+        });
+
+        let ds = data_service.clone();
+        AuthGuard::require_session(socket, "parental:status", data_service, move |socket, _data, auth| {
+            let ds = ds.clone();
+            async move {
+                info!("👪 Received parental:status from {}", socket.id);
+                if let Err(error) = Self::status(&socket, &*ds, &auth).await {
+                    ErrorResponder::send(&socket, &*ds, error).await;
+                }
+            }
+        });
+    }
+
+    // Age in whole years on `today` of a YYYY-MM-DD date of birth
+    pub fn age_on(date_of_birth: &str, today: NaiveDate) -> Option<u32> {
+        let born = NaiveDate::parse_from_str(date_of_birth, "%Y-%m-%d").ok()?;
+        today.years_since(born)
+    }
+
+    pub fn is_minor(user: &UserRegister) -> bool {
+        match user.date_of_birth.as_deref().and_then(|dob| Self::age_on(dob, Utc::now().date_naive())) {
+            Some(age) => age < CONFIG.age_of_majority,
+            None => CONFIG.age_gate_require_dob,
+        }
+    }
+
+    // Daily limit a coin debit of this user runs under: None when spending
+    // is unrestricted, AGE_RESTRICTED for a minor without parental controls
+    pub fn spend_limit(user: &UserRegister) -> Result<Option<i64>, ApiError> {
+        if let Some(controls) = user.parental_controls.as_ref() {
+            return Ok(Some(controls.daily_spend_limit));
+        }
+        if Self::is_minor(user) {
+            return Err(ApiError::new("AGE_RESTRICTED", "AUTHORIZATION_ERROR", "date_of_birth", "Spending coins needs parental controls on this account")
+                .with_details(json!({
+                    "age_of_majority": CONFIG.age_of_majority,
+                    "date_of_birth_set": user.date_of_birth.is_some(),
+                })));
+        }
+        Ok(None)
+    }
+
+    async fn set(socket: &SocketRef, data_service: &dyn DataStore, data: &Value, auth: &AuthContext) -> Result<(), ApiError> {
+        let daily_spend_limit = data["daily_spend_limit"].as_i64()
+            .filter(|limit| (0..=MAX_DAILY_SPEND_LIMIT).contains(limit))
+            .ok_or_else(|| ApiError::new("INVALID_VALUE", "VALUE_ERROR", "daily_spend_limit", &format!("daily_spend_limit must be a whole number from 0 to {}", MAX_DAILY_SPEND_LIMIT)))?;
+        let pin = data["parental_pin"].as_str().unwrap_or_default();
+
+        let (pin_hash, changed_pin) = match auth.user.parental_controls.as_ref() {
+            Some(controls) => {
+                let controls = Self::check_pin(data_service, &auth.mobile_no, controls, pin).await?;
+                match data["new_pin"].as_str() {
+                    Some(new_pin) => (Self::hash(new_pin, "new_pin").await?, true),
+                    None => (controls.pin_hash, false),
+                }
+            }
+            None => (Self::hash(pin, "parental_pin").await?, true),
+        };
+        let controls = ParentalControls {
+            pin_hash,
+            daily_spend_limit,
+            failed_attempts: 0,
+            locked_until: None,
+            updated_at: bson::DateTime::now(),
+        };
+        Self::save(data_service, &auth.mobile_no, Some(&controls)).await?;
+        info!("👪 Parental controls set for mobile: {} (daily limit {}, new PIN: {})", auth.mobile_no, daily_spend_limit, changed_pin);
+        Self::emit(socket, "parental:updated", json!({
+            "message": "Parental controls saved",
+            "daily_spend_limit": daily_spend_limit,
+        })).await;
+        Ok(())
+    }
+
+    async fn disable(socket: &SocketRef, data_service: &dyn DataStore, data: &Value, auth: &AuthContext) -> Result<(), ApiError> {
+        let Some(controls) = auth.user.parental_controls.as_ref() else {
+            return Err(ApiError::new("PARENTAL_CONTROLS_NOT_ENABLED", "VALUE_ERROR", "parental_pin", "Parental controls are not set up"));
+        };
+        Self::check_pin(data_service, &auth.mobile_no, controls, data["parental_pin"].as_str().unwrap_or_default()).await?;
+        Self::save(data_service, &auth.mobile_no, None).await?;
+        info!("👪 Parental controls removed for mobile: {}", auth.mobile_no);
+        Self::emit(socket, "parental:disabled", json!({"message": "Parental controls removed"})).await;
+        Ok(())
+    }
+
+    async fn status(socket: &SocketRef, data_service: &dyn DataStore, auth: &AuthContext) -> Result<(), ApiError> {
+        let user = &auth.user;
+        let parental_controls = match user.parental_controls.as_ref() {
+            Some(controls) => {
+                let day_start = Utc::now().date_naive().and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+                let spent_today = data_service.wallet_spent_since(&user.user_id, day_start).await
+                    .map_err(|e| ApiError::system("PARENTAL_STATUS_FAILED", "mobile_no", "Failed to load parental controls", &e))?;
+                json!({
+                    "daily_spend_limit": controls.daily_spend_limit,
+                    "spent_today": spent_today,
+                    "locked_until": controls.locked_until.map(|until| until.try_to_rfc3339_string().unwrap_or_default()),
+                })
+            }
+            None => Value::Null,
+        };
+        Self::emit(socket, "parental:status", json!({
+            "date_of_birth": user.date_of_birth,
+            "age": user.date_of_birth.as_deref().and_then(|dob| Self::age_on(dob, Utc::now().date_naive())),
+            "minor": Self::is_minor(user),
+            "age_of_majority": CONFIG.age_of_majority,
+            "spending_allowed": Self::spend_limit(user).is_ok(),
+            "parental_controls": parental_controls,
+        })).await;
+        Ok(())
+    }
+
+    // Check the parental PIN, counting failures towards the lock; returns the
+    // controls as they are after an accepted PIN
+    async fn check_pin(data_service: &dyn DataStore, mobile_no: &str, controls: &ParentalControls, pin: &str) -> Result<ParentalControls, ApiError> {
+        let now = Utc::now();
+        if let Some(until) = controls.locked_until.filter(|until| until.timestamp_millis() > now.timestamp_millis()) {
+            return Err(ApiError::new("PARENTAL_PIN_LOCKED", "AUTHENTICATION_ERROR", "parental_pin", "Too many wrong PINs. Please try again later.")
+                .with_details(json!({"locked_until": until.try_to_rfc3339_string().unwrap_or_default()})));
+        }
+        if pin.is_empty() {
+            return Err(ApiError::new("PARENTAL_PIN_REQUIRED", "AUTHENTICATION_ERROR", "parental_pin", "This action needs the parental PIN"));
+        }
+
+        let mut updated = controls.clone();
+        if TwoStepManager::verify_pin(pin.to_string(), controls.pin_hash.clone()).await {
+            if controls.failed_attempts > 0 || controls.locked_until.is_some() {
+                updated.failed_attempts = 0;
+                updated.locked_until = None;
+                Self::save(data_service, mobile_no, Some(&updated)).await?;
+            }
+            return Ok(updated);
+        }
+        updated.failed_attempts += 1;
+        let attempts_left = MAX_FAILED_ATTEMPTS.saturating_sub(updated.failed_attempts);
+        if attempts_left == 0 {
+            updated.failed_attempts = 0;
+            updated.locked_until = Some(bson::DateTime::from_millis((now + chrono::Duration::minutes(LOCK_MINUTES)).timestamp_millis()));
+            warn!("🔒 Parental controls locked for mobile: {}", mobile_no);
+        }
+        Self::save(data_service, mobile_no, Some(&updated)).await?;
+        Err(ApiError::new("PARENTAL_PIN_INVALID", "AUTHENTICATION_ERROR", "parental_pin", "The parental PIN is incorrect")
+            .with_details(json!({"attempts_left": attempts_left})))
+    }
+
+    async fn hash(pin: &str, field: &str) -> Result<String, ApiError> {
+        if !pin.chars().all(|c| c.is_ascii_digit()) || !(PIN_LENGTH.0..=PIN_LENGTH.1).contains(&pin.len()) {
+            return Err(ApiError::new("INVALID_FORMAT", "FORMAT_ERROR", field, &format!("{} must be {} to {} digits", field, PIN_LENGTH.0, PIN_LENGTH.1)));
+        }
+        TwoStepManager::hash_pin(pin.to_string()).await
+            .map_err(|e| ApiError::system("PARENTAL_CONTROLS_FAILED", field, "Failed to save parental controls", &e))
+    }
+
+    async fn save(data_service: &dyn DataStore, mobile_no: &str, controls: Option<&ParentalControls>) -> Result<(), ApiError> {
+        data_service.update_parental_controls(mobile_no, controls).await.map(|_| ()).map_err(|e| {
+            error!("❌ Failed to save parental controls for mobile {}: {}", mobile_no, e);
+            ApiError::system("PARENTAL_CONTROLS_FAILED", "mobile_no", "Failed to save parental controls", &e)
+        })
+    }
+
+    async fn emit(socket: &SocketRef, event: &str, data: Value) {
+        let response = ApiResponse::success(event, data).for_socket(socket.id);
+        if let Err(e) = FaultInjector::emit(socket, event, response).await {
+            warn!("⚠️ Failed to emit {} to socket {}: {}", event, socket.id, e);
+        }
+    }
+}
@@ -0,0 +1,569 @@
+use socketioxide::{SocketIo, extract::{SocketRef, Data}, socket::Sid};
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::api::response::{ApiError, ApiResponse};
+use crate::managers::afk::AfkManager;
+use crate::managers::chat::{ChatManager, ChatRefusal};
+use crate::managers::compliance::ComplianceManager;
+use crate::managers::correlation::Correlation;
+use crate::database::models::DeepLink;
+use crate::database::store::DataStore;
+use crate::managers::game_modes::RegisteredMode;
+use crate::managers::link_scan::LinkScanManager;
+use crate::managers::matchmaking::{MatchmakingManager, QueueMember};
+use crate::managers::moderation::ModerationManager;
+use crate::managers::notifications::{NotificationCategory, NotificationManager};
+use crate::managers::tenant::{Tenant, TenantManager};
+use crate::managers::validation::{ValidationError, ValidationManager};
+
+// Global in-memory party state for every game mode
+static PARTIES: Lazy<RwLock<HashMap<String, Party>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+#[derive(Clone)]
+pub struct Party {
+    pub party_id: String,
+    pub leader_id: String,
+    pub members: Vec<QueueMember>,          // Leader is always members[0]
+    pub invites: HashSet<String>,
+    pub created_at: DateTime<Utc>,
+    pub mode: Arc<RegisteredMode>,          // Game mode the party was created in and queues for
+    pub tenant: &'static Tenant,
+}
+
+impl Party {
+    fn has_member(&self, player_id: &str) -> bool {
+        self.members.iter().any(|m| m.player_id == player_id)
+    }
+
+    // Largest party that can queue together as one team
+    fn max_size(&self) -> usize {
+        self.mode.matchmaking.max_party_size
+    }
+
+    fn socket_room(&self) -> String {
+        format!("party:{}", self.party_id)
+    }
+
+    fn snapshot(&self) -> Value {
+        let members: Vec<&str> = self.members.iter().map(|m| m.player_id.as_str()).collect();
+        let invites: Vec<&String> = self.invites.iter().collect();
+        json!({
+            "party_id": self.party_id,
+            "leader_id": self.leader_id,
+            "members": members,
+            "pending_invites": invites,
+            "max_size": self.max_size(),
+            "game_type": self.mode.game_type,
+            "created_at": self.created_at.to_rfc3339()
+        })
+    }
+}
+
+// Personal socket room used to reach a player outside of any party (invites,
+// kicks) and to hide chat from players with a block
+pub fn player_room(player_id: &str) -> String {
+    format!("player:{}", player_id)
+}
+
+fn find_party_id(parties: &HashMap<String, Party>, player_id: &str) -> Option<String> {
+    parties.values().find(|p| p.has_member(player_id)).map(|p| p.party_id.clone())
+}
+
+pub struct PartyManager;
+
+impl PartyManager {
+    pub async fn create_party(player_id: &str, socket_id: Sid, mode: Arc<RegisteredMode>) -> Result<Party, &'static str> {
+        let mut parties = PARTIES.write().await;
+        if find_party_id(&parties, player_id).is_some() {
+            return Err("ALREADY_IN_PARTY");
+        }
+
+        let party = Party {
+            party_id: Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext)).to_string(),
+            leader_id: player_id.to_string(),
+            members: vec![QueueMember { player_id: player_id.to_string(), socket_id }],
+            invites: HashSet::new(),
+            created_at: Utc::now(),
+            mode,
+            tenant: TenantManager::current(),
+        };
+        parties.insert(party.party_id.clone(), party.clone());
+        info!("🎉 Player {} created party {}", player_id, party.party_id);
+        Ok(party)
+    }
+
+    pub async fn invite(leader_id: &str, target_id: &str) -> Result<Party, &'static str> {
+        let mut parties = PARTIES.write().await;
+        if find_party_id(&parties, target_id).is_some() {
+            return Err("ALREADY_IN_PARTY");
+        }
+        let party_id = find_party_id(&parties, leader_id).ok_or("NOT_IN_PARTY")?;
+        let party = parties.get_mut(&party_id).ok_or("PARTY_NOT_FOUND")?;
+        if party.leader_id != leader_id {
+            return Err("NOT_PARTY_LEADER");
+        }
+        if party.members.len() >= party.max_size() {
+            return Err("PARTY_FULL");
+        }
+
+        party.invites.insert(target_id.to_string());
+        info!("✉️ Player {} invited {} to party {}", leader_id, target_id, party_id);
+        Ok(party.clone())
+    }
+
+    // Invites can only be accepted from the namespace of the party's game mode
+    pub async fn accept_invite(player_id: &str, party_id: &str, socket_id: Sid, game_type: &str) -> Result<Party, &'static str> {
+        let mut parties = PARTIES.write().await;
+        if find_party_id(&parties, player_id).is_some() {
+            return Err("ALREADY_IN_PARTY");
+        }
+        let party = parties.get_mut(party_id)
+            .filter(|party| party.tenant.tenant_id == TenantManager::current().tenant_id)
+            .ok_or("PARTY_NOT_FOUND")?;
+        if !party.invites.contains(player_id) {
+            return Err("NOT_INVITED");
+        }
+        if party.mode.game_type != game_type {
+            return Err("WRONG_GAME_MODE");
+        }
+        if party.members.len() >= party.max_size() {
+            return Err("PARTY_FULL");
+        }
+
+        party.invites.remove(player_id);
+        party.members.push(QueueMember { player_id: player_id.to_string(), socket_id });
+        info!("🤝 Player {} joined party {} ({}/{})", player_id, party_id, party.members.len(), party.max_size());
+        Ok(party.clone())
+    }
+
+    // Remove a player from their party. Leadership passes to the longest-standing
+    // member; the party is disbanded once empty. Returns the departing member and
+    // what is left of the party.
+    pub async fn leave_party(player_id: &str) -> Result<(String, QueueMember, Option<Party>), &'static str> {
+        let mut parties = PARTIES.write().await;
+        let party_id = find_party_id(&parties, player_id).ok_or("NOT_IN_PARTY")?;
+        let (member, remaining) = Self::remove_member(&mut parties, &party_id, player_id);
+        Ok((party_id, member, remaining))
+    }
+
+    pub async fn kick(leader_id: &str, target_id: &str) -> Result<(QueueMember, Party), &'static str> {
+        let mut parties = PARTIES.write().await;
+        let party_id = find_party_id(&parties, leader_id).ok_or("NOT_IN_PARTY")?;
+        let party = parties.get(&party_id).ok_or("PARTY_NOT_FOUND")?;
+        if party.leader_id != leader_id {
+            return Err("NOT_PARTY_LEADER");
+        }
+        if leader_id == target_id || !party.has_member(target_id) {
+            return Err("INVALID_TARGET");
+        }
+
+        let (member, remaining) = Self::remove_member(&mut parties, &party_id, target_id);
+        info!("👢 Player {} kicked {} from party {}", leader_id, target_id, party_id);
+        Ok((member, remaining.ok_or("PARTY_NOT_FOUND")?))
+    }
+
+    pub async fn promote(leader_id: &str, target_id: &str) -> Result<Party, &'static str> {
+        let mut parties = PARTIES.write().await;
+        let party_id = find_party_id(&parties, leader_id).ok_or("NOT_IN_PARTY")?;
+        let party = parties.get_mut(&party_id).ok_or("PARTY_NOT_FOUND")?;
+        if party.leader_id != leader_id {
+            return Err("NOT_PARTY_LEADER");
+        }
+        let index = party.members.iter().position(|m| m.player_id == target_id).ok_or("INVALID_TARGET")?;
+
+        let new_leader = party.members.remove(index);
+        party.members.insert(0, new_leader);
+        party.leader_id = target_id.to_string();
+        info!("👑 Player {} promoted {} to leader of party {}", leader_id, target_id, party_id);
+        Ok(party.clone())
+    }
+
+    pub async fn party_of(player_id: &str) -> Option<Party> {
+        let parties = PARTIES.read().await;
+        find_party_id(&parties, player_id).and_then(|id| parties.get(&id).cloned())
+    }
+
+    // Drop a disconnected socket from whichever party it belongs to
+    pub async fn remove_socket(io: &SocketIo, socket_id: &str) {
+        let mut parties = PARTIES.write().await;
+        let Some((party_id, player_id)) = parties.values().find_map(|p| {
+            p.members
+                .iter()
+                .find(|m| m.socket_id.to_string() == socket_id)
+                .map(|m| (p.party_id.clone(), m.player_id.clone()))
+        }) else {
+            return;
+        };
+
+        let (_, remaining) = Self::remove_member(&mut parties, &party_id, &player_id);
+        drop(parties);
+        MatchmakingManager::leave_queue(&player_id).await;
+        if let Some(party) = remaining {
+            Self::broadcast_update(io, &party);
+        }
+    }
+
+    fn remove_member(parties: &mut HashMap<String, Party>, party_id: &str, player_id: &str) -> (QueueMember, Option<Party>) {
+        let party = parties.get_mut(party_id).expect("party located under the same lock");
+        let index = party.members.iter().position(|m| m.player_id == player_id).expect("member located under the same lock");
+        let member = party.members.remove(index);
+
+        if party.members.is_empty() {
+            parties.remove(party_id);
+            info!("💨 Party {} disbanded", party_id);
+            return (member, None);
+        }
+
+        if party.leader_id == player_id {
+            party.leader_id = party.members[0].player_id.clone();
+            info!("👑 Leadership of party {} passed to {}", party_id, party.leader_id);
+        }
+        (member, Some(party.clone()))
+    }
+
+    fn broadcast_update(io: &SocketIo, party: &Party) {
+        let update = ApiResponse::success("party:updated", json!({
+            "party": party.snapshot()
+        }));
+        if let Some(ns) = io.of(party.mode.namespace.as_str()) {
+            if let Err(e) = ns.to(party.socket_room()).emit("party:updated", update) {
+                warn!("⚠️ Failed to broadcast party:updated to party {}: {}", party.party_id, e);
+            }
+        }
+    }
+
+    fn emit_validation_error(s: &SocketRef, error_details: Box<ValidationError>) {
+        let _ = s.emit("party:error", ApiError::from(error_details).on_event("party:error").for_socket(s.id));
+    }
+
+    fn emit_party_error(s: &SocketRef, code: &str, message: &str, details: Value) {
+        let error = ApiError::new(code, "PARTY_ERROR", "player_id", message).with_details(details);
+        let _ = s.emit("party:error", error.on_event("party:error").for_socket(s.id));
+    }
+
+    // The authenticated user the event acts for. A payload player_id naming
+    // anyone else is refused, so nobody is put in a party (or a staked match)
+    // by another client.
+    fn acting_player<'a>(s: &SocketRef, data: &Value, user_id: &'a str) -> Option<&'a str> {
+        if data["player_id"].as_str() == Some(user_id) {
+            return Some(user_id);
+        }
+        Self::emit_party_error(s, "PLAYER_MISMATCH", "player_id is not the authenticated user", json!({"player_id": data["player_id"]}));
+        None
+    }
+
+    // Register party events on the namespace socket of a game mode for the
+    // authenticated user_id
+    pub fn register_party_events(socket: &SocketRef, io: SocketIo, data_service: Arc<dyn DataStore>, mode: Arc<RegisteredMode>, user_id: String) {
+        // Create a party with the caller as leader
+        let io_create = io.clone();
+        let mode_create = mode.clone();
+        let user_create = user_id.clone();
+        socket.on("party:create", move |s: SocketRef, Data::<Value>(data)| {
+            let user_create = user_create.clone();
+            let io_create = io_create.clone();
+            let mode_create = mode_create.clone();
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope("party:create", s.id, request_id, async move {
+                info!("🎉 Received party:create from socket {}: {:?}", s.id, data);
+                if let Err(error_details) = ValidationManager::validate_party_data(&data, &["player_id"]) {
+                    Self::emit_validation_error(&s, error_details);
+                    return;
+                }
+                let Some(player_id) = Self::acting_player(&s, &data, &user_create) else { return };
+                let _ = s.join(player_room(player_id));
+
+                match Self::create_party(player_id, s.id, mode_create).await {
+                    Ok(party) => {
+                        let _ = s.join(party.socket_room());
+                        Self::broadcast_update(&io_create, &party);
+                    }
+                    Err(code) => Self::emit_party_error(&s, code, "Unable to create party", json!({"player_id": player_id})),
+                }
+            })
+        });
+
+        // Leader invites another player - delivered to the invitee's personal room
+        let io_invite = io.clone();
+        let ds_invite = data_service.clone();
+        let user_invite = user_id.clone();
+        socket.on("party:invite", move |s: SocketRef, Data::<Value>(data)| {
+            let user_invite = user_invite.clone();
+            let io_invite = io_invite.clone();
+            let ds_invite = ds_invite.clone();
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope("party:invite", s.id, request_id, async move {
+                info!("✉️ Received party:invite from socket {}: {:?}", s.id, data);
+                if let Err(error_details) = ValidationManager::validate_party_data(&data, &["player_id", "target_player_id"]) {
+                    Self::emit_validation_error(&s, error_details);
+                    return;
+                }
+                let Some(player_id) = Self::acting_player(&s, &data, &user_invite) else { return };
+                let target_id = data["target_player_id"].as_str().unwrap_or_default();
+
+                match Self::invite(player_id, target_id).await {
+                    Ok(party) => {
+                        let invited = ApiResponse::success("party:invited", json!({
+                            "party_id": party.party_id,
+                            "leader_id": party.leader_id,
+                            "player_id": target_id
+                        }));
+                        if let Some(ns) = io_invite.of(party.mode.namespace.as_str()) {
+                            if let Err(e) = ns.to(player_room(target_id)).emit("party:invited", invited) {
+                                warn!("⚠️ Failed to deliver party:invited to player {}: {}", target_id, e);
+                            }
+                        }
+                        Self::broadcast_update(&io_invite, &party);
+
+                        // Reach invitees who are not connected, subject to their preferences
+                        let dispatched = NotificationManager::dispatch(
+                            &*ds_invite,
+                            target_id,
+                            NotificationCategory::PartyInvites,
+                            "party_invite",
+                            json!({"leader_id": party.leader_id}),
+                            json!({"party_id": party.party_id, "leader_id": party.leader_id}),
+                            Some(DeepLink::new("open_party", json!({"party_id": party.party_id}))),
+                        ).await;
+                        if let Err(e) = dispatched {
+                            warn!("⚠️ Failed to dispatch party invite notification to {}: {}", target_id, e);
+                        }
+                    }
+                    Err(code) => Self::emit_party_error(&s, code, "Unable to send party invite", json!({"player_id": player_id, "target_player_id": target_id})),
+                }
+            })
+        });
+
+        // Accept a pending invite
+        let io_accept = io.clone();
+        let game_type = mode.game_type.clone();
+        let user_accept = user_id.clone();
+        socket.on("party:accept", move |s: SocketRef, Data::<Value>(data)| {
+            let user_accept = user_accept.clone();
+            let io_accept = io_accept.clone();
+            let game_type = game_type.clone();
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope("party:accept", s.id, request_id, async move {
+                info!("🤝 Received party:accept from socket {}: {:?}", s.id, data);
+                if let Err(error_details) = ValidationManager::validate_party_data(&data, &["player_id", "party_id"]) {
+                    Self::emit_validation_error(&s, error_details);
+                    return;
+                }
+                let Some(player_id) = Self::acting_player(&s, &data, &user_accept) else { return };
+                let party_id = data["party_id"].as_str().unwrap_or_default();
+                let _ = s.join(player_room(player_id));
+
+                match Self::accept_invite(player_id, party_id, s.id, &game_type).await {
+                    Ok(party) => {
+                        // The queued team no longer matches the party - requeue explicitly
+                        MatchmakingManager::leave_queue(&party.leader_id).await;
+                        let _ = s.join(party.socket_room());
+                        Self::broadcast_update(&io_accept, &party);
+                    }
+                    Err(code) => Self::emit_party_error(&s, code, "Unable to join party", json!({"player_id": player_id, "party_id": party_id})),
+                }
+            })
+        });
+
+        // Leave the current party
+        let io_leave = io.clone();
+        let mode_leave = mode.clone();
+        let user_leave = user_id.clone();
+        socket.on("party:leave", move |s: SocketRef, Data::<Value>(data)| {
+            let user_leave = user_leave.clone();
+            let io_leave = io_leave.clone();
+            let mode_leave = mode_leave.clone();
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope("party:leave", s.id, request_id, async move {
+                info!("🚶 Received party:leave from socket {}: {:?}", s.id, data);
+                if let Err(error_details) = ValidationManager::validate_party_data(&data, &["player_id"]) {
+                    Self::emit_validation_error(&s, error_details);
+                    return;
+                }
+                let Some(player_id) = Self::acting_player(&s, &data, &user_leave) else { return };
+
+                match Self::leave_party(player_id).await {
+                    Ok((party_id, member, remaining)) => {
+                        MatchmakingManager::leave_queue(player_id).await;
+                        if let Some(socket) = member.socket(&io_leave, &mode_leave.namespace) {
+                            let _ = socket.leave(format!("party:{}", party_id));
+                        }
+                        let left = ApiResponse::success("party:left", json!({
+                            "party_id": party_id,
+                            "player_id": player_id,
+                            "disbanded": remaining.is_none()
+                        })).for_socket(s.id);
+                        let _ = s.emit("party:left", left);
+                        if let Some(party) = remaining {
+                            Self::broadcast_update(&io_leave, &party);
+                        }
+                    }
+                    Err(code) => Self::emit_party_error(&s, code, "Unable to leave party", json!({"player_id": player_id})),
+                }
+            })
+        });
+
+        // Leader control - remove a member
+        let io_kick = io.clone();
+        let user_kick = user_id.clone();
+        socket.on("party:kick", move |s: SocketRef, Data::<Value>(data)| {
+            let user_kick = user_kick.clone();
+            let io_kick = io_kick.clone();
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope("party:kick", s.id, request_id, async move {
+                info!("👢 Received party:kick from socket {}: {:?}", s.id, data);
+                if let Err(error_details) = ValidationManager::validate_party_data(&data, &["player_id", "target_player_id"]) {
+                    Self::emit_validation_error(&s, error_details);
+                    return;
+                }
+                let Some(player_id) = Self::acting_player(&s, &data, &user_kick) else { return };
+                let target_id = data["target_player_id"].as_str().unwrap_or_default();
+
+                match Self::kick(player_id, target_id).await {
+                    Ok((member, party)) => {
+                        MatchmakingManager::leave_queue(player_id).await;
+                        if let Some(socket) = member.socket(&io_kick, &party.mode.namespace) {
+                            let _ = socket.leave(party.socket_room());
+                            let _ = socket.emit("party:kicked", ApiResponse::success("party:kicked", json!({
+                                "party_id": party.party_id,
+                                "player_id": target_id
+                            })).for_socket(member.socket_id));
+                        }
+                        Self::broadcast_update(&io_kick, &party);
+                    }
+                    Err(code) => Self::emit_party_error(&s, code, "Unable to kick party member", json!({"player_id": player_id, "target_player_id": target_id})),
+                }
+            })
+        });
+
+        // Leader control - hand leadership to another member
+        let io_promote = io.clone();
+        let user_promote = user_id.clone();
+        socket.on("party:promote", move |s: SocketRef, Data::<Value>(data)| {
+            let user_promote = user_promote.clone();
+            let io_promote = io_promote.clone();
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope("party:promote", s.id, request_id, async move {
+                info!("👑 Received party:promote from socket {}: {:?}", s.id, data);
+                if let Err(error_details) = ValidationManager::validate_party_data(&data, &["player_id", "target_player_id"]) {
+                    Self::emit_validation_error(&s, error_details);
+                    return;
+                }
+                let Some(player_id) = Self::acting_player(&s, &data, &user_promote) else { return };
+                let target_id = data["target_player_id"].as_str().unwrap_or_default();
+
+                match Self::promote(player_id, target_id).await {
+                    Ok(party) => Self::broadcast_update(&io_promote, &party),
+                    Err(code) => Self::emit_party_error(&s, code, "Unable to promote party member", json!({"player_id": player_id, "target_player_id": target_id})),
+                }
+            })
+        });
+
+        // Party chat - relayed to every member's socket, except members with a
+        // block either way with the sender. Muted and flooding players get chat:muted.
+        let ds_chat = data_service.clone();
+        let user_chat = user_id.clone();
+        socket.on("party:chat", move |s: SocketRef, Data::<Value>(data)| {
+            let user_chat = user_chat.clone();
+            let ds_chat = ds_chat.clone();
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope("party:chat", s.id, request_id, async move {
+                if let Err(error_details) = ValidationManager::validate_party_chat_data(&data) {
+                    Self::emit_validation_error(&s, error_details);
+                    return;
+                }
+                let Some(player_id) = Self::acting_player(&s, &data, &user_chat) else { return };
+
+                let Some(party) = Self::party_of(player_id).await else {
+                    Self::emit_party_error(&s, "NOT_IN_PARTY", "Join a party before chatting", json!({"player_id": player_id}));
+                    return;
+                };
+                match ChatManager::admit(&*ds_chat, player_id).await {
+                    Ok(()) => {}
+                    Err(ChatRefusal::Unavailable) => {
+                        Self::emit_party_error(&s, "CHAT_UNAVAILABLE", "Unable to send the message right now", json!({"player_id": player_id}));
+                        return;
+                    }
+                    Err(refusal) => {
+                        ChatManager::refuse(&s, player_id, json!({"party_id": party.party_id}), refusal);
+                        return;
+                    }
+                }
+                let place = format!("party {}", party.party_id);
+                if let Err(error) = LinkScanManager::scan(&*ds_chat, player_id, None, &place, data["message"].as_str().unwrap_or_default(), &[]).await {
+                    let _ = s.emit("party:error", error.on_event("party:error").for_socket(s.id));
+                    return;
+                }
+                let chat = ApiResponse::success("party:chat", json!({
+                    "party_id": party.party_id,
+                    "player_id": player_id,
+                    "message": data["message"].as_str().unwrap_or_default().trim()
+                }));
+                let blocked = match ModerationManager::blocked_user_ids(&*ds_chat, player_id).await {
+                    Ok(blocked) => blocked,
+                    Err(e) => {
+                        warn!("⚠️ Failed to load blocks of player {}: {}", player_id, e);
+                        Self::emit_party_error(&s, "CHAT_UNAVAILABLE", "Unable to send the message right now", json!({"player_id": player_id}));
+                        return;
+                    }
+                };
+                let hidden: Vec<String> = party.members.iter()
+                    .filter(|m| blocked.contains(&m.player_id))
+                    .map(|m| player_room(&m.player_id))
+                    .collect();
+                if let Err(e) = s.within(party.socket_room()).except(hidden).emit("party:chat", chat) {
+                    warn!("⚠️ Failed to relay party:chat to party {}: {}", party.party_id, e);
+                }
+            })
+        });
+
+        // Leader control - queue the whole party as one team
+        let user_queue = user_id.clone();
+        socket.on("party:queue", move |s: SocketRef, Data::<Value>(data)| {
+            let user_queue = user_queue.clone();
+            let io = io.clone();
+            let data_service = data_service.clone();
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope("party:queue", s.id, request_id, async move {
+                info!("🎯 Received party:queue from socket {}: {:?}", s.id, data);
+                if let Err(error_details) = ValidationManager::validate_party_data(&data, &["player_id"]) {
+                    Self::emit_validation_error(&s, error_details);
+                    return;
+                }
+                let Some(player_id) = Self::acting_player(&s, &data, &user_queue) else { return };
+
+                let Some(party) = Self::party_of(player_id).await else {
+                    Self::emit_party_error(&s, "NOT_IN_PARTY", "Join a party before queueing as a party", json!({"player_id": player_id}));
+                    return;
+                };
+                if party.leader_id != player_id {
+                    Self::emit_party_error(&s, "NOT_PARTY_LEADER", "Only the party leader can queue the party", json!({"player_id": player_id, "party_id": party.party_id}));
+                    return;
+                }
+                // One restricted member, or one in an AFK cooldown, keeps the whole party out
+                for member in &party.members {
+                    let Some(member_socket) = member.socket(&io, &party.mode.namespace) else {
+                        Self::emit_party_error(&s, "MEMBER_DISCONNECTED", "A party member is no longer connected", json!({"player_id": member.player_id, "party_id": party.party_id}));
+                        return;
+                    };
+                    if let Err(error) = ComplianceManager::check_gameplay(&*data_service, &member_socket, &member.player_id, "party:queue").await {
+                        let _ = s.emit("party:error", error.on_event("party:error").for_socket(s.id));
+                        return;
+                    }
+                    if let Err(error) = AfkManager::check_queue(&*data_service, &member.player_id).await {
+                        let _ = s.emit("party:error", error.on_event("party:error").for_socket(s.id));
+                        return;
+                    }
+                }
+                MatchmakingManager::join_queue(io, data_service, party.members, Some(party.party_id), party.mode).await;
+            })
+        });
+    }
+}
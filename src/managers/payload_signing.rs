@@ -0,0 +1,305 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tracing::warn;
+
+use crate::api::response::ApiError;
+use crate::config::CONFIG;
+use crate::managers::jwt::DEFAULT_JWT_SECRET;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// Error code and message of a signature that was not accepted
+type Refusal = (&'static str, &'static str);
+
+// Payload field carrying the hex HMAC
+pub const SIGNATURE_FIELD: &str = "signature";
+// Payload field with the client's signing time in Unix milliseconds
+pub const SIGNED_AT_FIELD: &str = "signed_at";
+// Events registered with AuthGuard::require_signed_session
+pub const SIGNED_EVENTS: &[&str] = &["gift:send", "challenge:claim", "promo:redeem"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SigningMode {
+    Off,        // Signatures are ignored
+    Report,     // Missing or bad signatures are logged, the event still runs
+    Enforce,    // Missing or bad signatures are refused
+}
+
+impl SigningMode {
+    pub fn parse(mode: &str) -> Option<Self> {
+        match mode {
+            "off" => Some(SigningMode::Off),
+            "report" => Some(SigningMode::Report),
+            "enforce" => Some(SigningMode::Enforce),
+            _ => None,
+        }
+    }
+}
+
+// Accepted signatures until they are too old to be replayed, by signature
+static SEEN: Lazy<Mutex<HashMap<String, i64>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+static MODE: Lazy<SigningMode> = Lazy::new(|| {
+    SigningMode::parse(&CONFIG.payload_signing_mode)
+        .unwrap_or_else(|| panic!("PAYLOAD_SIGNING_MODE {:?} is unknown (off, report or enforce)", CONFIG.payload_signing_mode))
+});
+static SECRET: Lazy<String> = Lazy::new(PayloadSigning::load_secret);
+
+// HMAC signatures on events that move coins or items. otp:verified hands the
+// client a signing_key derived from its session_token; signed events carry
+// `signed_at` (Unix ms) and `signature`, the hex HMAC-SHA256 with that key of
+// "<event>\n<payload>", where <payload> is the event's JSON without
+// `signature`, keys sorted and no whitespace. A signature is accepted once,
+// within PAYLOAD_SIGNATURE_MAX_AGE_SECS of signed_at. Keys are derived, not
+// stored, from PAYLOAD_SIGNING_SECRET, which must be set and differ from the
+// JWT secret. PAYLOAD_SIGNING_MODE is off, report (log only) or enforce (the
+// default); an unknown mode stops startup.
+pub struct PayloadSigning;
+
+impl PayloadSigning {
+    fn load_secret() -> String {
+        let Some(secret) = CONFIG.payload_signing_secret.clone() else {
+            panic!("PAYLOAD_SIGNING_SECRET is not set - it derives the payload signing keys");
+        };
+        let jwt_secret = std::env::var("JWT_SECRET_KEY").unwrap_or_else(|_| DEFAULT_JWT_SECRET.to_string());
+        if secret == jwt_secret || secret == DEFAULT_JWT_SECRET {
+            panic!("PAYLOAD_SIGNING_SECRET must not be the JWT secret");
+        }
+        secret
+    }
+
+    // Check PAYLOAD_SIGNING_MODE and PAYLOAD_SIGNING_SECRET; panics when either is wrong
+    pub fn settings() -> SigningMode {
+        Lazy::force(&SECRET);
+        *MODE
+    }
+
+    fn mode() -> SigningMode {
+        *MODE
+    }
+
+    fn secret() -> &'static str {
+        &SECRET
+    }
+
+    fn mac(key: &[u8]) -> HmacSha256 {
+        // HMAC takes keys of any length
+        HmacSha256::new_from_slice(key).expect("HMAC accepts any key length")
+    }
+
+    // The session's signing key for otp:verified, base64url encoded
+    pub fn session_key(session_token: &str) -> String {
+        URL_SAFE_NO_PAD.encode(Self::raw_key(session_token))
+    }
+
+    fn raw_key(session_token: &str) -> Vec<u8> {
+        Self::derive_key(Self::secret(), session_token)
+    }
+
+    fn derive_key(secret: &str, session_token: &str) -> Vec<u8> {
+        let mut mac = Self::mac(secret.as_bytes());
+        mac.update(b"payload-signing:");
+        mac.update(session_token.as_bytes());
+        mac.finalize().into_bytes().to_vec()
+    }
+
+    // JSON with object keys sorted and no whitespace, whatever order serde_json keeps
+    fn canonical(value: &Value, out: &mut String) {
+        match value {
+            Value::Object(map) => {
+                let mut keys: Vec<&String> = map.keys().collect();
+                keys.sort();
+                out.push('{');
+                for (i, key) in keys.into_iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    out.push_str(&Value::String(key.clone()).to_string());
+                    out.push(':');
+                    Self::canonical(&map[key], out);
+                }
+                out.push('}');
+            }
+            Value::Array(values) => {
+                out.push('[');
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    Self::canonical(value, out);
+                }
+                out.push(']');
+            }
+            other => out.push_str(&other.to_string()),
+        }
+    }
+
+    // What the client signs for `event` with this payload
+    pub fn signing_input(event: &str, data: &Value) -> String {
+        let mut unsigned = data.clone();
+        if let Some(map) = unsigned.as_object_mut() {
+            map.remove(SIGNATURE_FIELD);
+        }
+        let mut input = format!("{}\n", event);
+        Self::canonical(&unsigned, &mut input);
+        input
+    }
+
+    fn error(code: &str, message: &str, event: &str) -> ApiError {
+        ApiError::new(code, "AUTHENTICATION_ERROR", SIGNATURE_FIELD, message)
+            .with_details(json!({ "event": event, "max_age_secs": CONFIG.payload_signature_max_age_secs }))
+    }
+
+    fn verify(event: &str, data: &Value, key: &[u8]) -> Result<(), Refusal> {
+        let (Some(signature), Some(signed_at)) = (data[SIGNATURE_FIELD].as_str(), data[SIGNED_AT_FIELD].as_i64()) else {
+            return Err(("SIGNATURE_REQUIRED", "This event must be signed with the session's signing key"));
+        };
+        let now = chrono::Utc::now().timestamp_millis();
+        let max_age_ms = CONFIG.payload_signature_max_age_secs as i64 * 1000;
+        if (now - signed_at).abs() > max_age_ms {
+            return Err(("SIGNATURE_EXPIRED", "The signature is too old; sign the event again"));
+        }
+        let Ok(expected) = data_encoding::HEXLOWER_PERMISSIVE.decode(signature.as_bytes()) else {
+            return Err(("SIGNATURE_INVALID", "The signature does not match the payload"));
+        };
+        let mut mac = Self::mac(key);
+        mac.update(Self::signing_input(event, data).as_bytes());
+        if mac.verify_slice(&expected).is_err() {
+            return Err(("SIGNATURE_INVALID", "The signature does not match the payload"));
+        }
+
+        let mut seen = SEEN.lock().unwrap_or_else(|e| e.into_inner());
+        seen.retain(|_, expires_at| *expires_at > now);
+        if seen.insert(signature.to_ascii_lowercase(), signed_at + max_age_ms).is_some() {
+            return Err(("SIGNATURE_REPLAYED", "This signed event was already received"));
+        }
+        Ok(())
+    }
+
+    // Check a signed event of an authenticated session. Err only in enforce
+    // mode; report mode logs what it would have refused.
+    pub fn check(event: &str, data: &Value, session_token: &str) -> Result<(), Box<ApiError>> {
+        let mode = Self::mode();
+        if mode == SigningMode::Off {
+            return Ok(());
+        }
+        Self::apply(mode, event, Self::verify(event, data, &Self::raw_key(session_token)))
+    }
+
+    // What `mode` makes of a verification result
+    fn apply(mode: SigningMode, event: &str, verified: Result<(), Refusal>) -> Result<(), Box<ApiError>> {
+        match verified {
+            Ok(()) => Ok(()),
+            Err(_) if mode == SigningMode::Off => Ok(()),
+            Err((code, message)) if mode == SigningMode::Enforce => Err(Box::new(Self::error(code, message, event))),
+            Err((code, _)) => {
+                warn!("🔏 {} would be refused in enforce mode: {}", event, code);
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "payload-signing-test-secret";
+
+    fn key(session_token: &str) -> Vec<u8> {
+        PayloadSigning::derive_key(SECRET, session_token)
+    }
+
+    // `data` with signed_at and the signature a client holding `key` sends
+    fn signed(event: &str, mut data: Value, signed_at: i64, key: &[u8]) -> Value {
+        data[SIGNED_AT_FIELD] = json!(signed_at);
+        let mut mac = PayloadSigning::mac(key);
+        mac.update(PayloadSigning::signing_input(event, &data).as_bytes());
+        data[SIGNATURE_FIELD] = json!(data_encoding::HEXLOWER.encode(&mac.finalize().into_bytes()));
+        data
+    }
+
+    fn now() -> i64 {
+        chrono::Utc::now().timestamp_millis()
+    }
+
+    fn code(verified: Result<(), Refusal>) -> &'static str {
+        verified.expect_err("signature accepted").0
+    }
+
+    #[test]
+    fn signing_input_sorts_keys_and_drops_the_signature() {
+        let data = json!({ "b": 1, "a": { "d": [1, "x"], "c": null }, "signature": "ff" });
+        assert_eq!(PayloadSigning::signing_input("gift:send", &data), "gift:send\n{\"a\":{\"c\":null,\"d\":[1,\"x\"]},\"b\":1}");
+    }
+
+    #[test]
+    fn valid_signature_is_accepted() {
+        let key = key("session-valid");
+        let data = signed("gift:send", json!({ "to": "user-1", "gift_id": "rose" }), now(), &key);
+        assert!(PayloadSigning::verify("gift:send", &data, &key).is_ok());
+    }
+
+    #[test]
+    fn tampered_payload_is_refused() {
+        let session_key = key("session-tampered");
+        let mut data = signed("gift:send", json!({ "to": "user-1", "coins": 10 }), now(), &session_key);
+        data["coins"] = json!(10_000);
+        assert_eq!(code(PayloadSigning::verify("gift:send", &data, &session_key)), "SIGNATURE_INVALID");
+
+        // Signed for another event or with another session's key
+        let data = signed("gift:send", json!({ "to": "user-2" }), now(), &session_key);
+        assert_eq!(code(PayloadSigning::verify("promo:redeem", &data, &session_key)), "SIGNATURE_INVALID");
+        let other_session = key("another-session");
+        assert_eq!(code(PayloadSigning::verify("gift:send", &data, &other_session)), "SIGNATURE_INVALID");
+    }
+
+    #[test]
+    fn missing_signature_is_refused() {
+        let data = json!({ "to": "user-1", "signed_at": now() });
+        assert_eq!(code(PayloadSigning::verify("gift:send", &data, &key("session-missing"))), "SIGNATURE_REQUIRED");
+    }
+
+    #[test]
+    fn stale_signature_is_refused() {
+        let key = key("session-stale");
+        let max_age_ms = CONFIG.payload_signature_max_age_secs as i64 * 1000;
+        let stale = signed("challenge:claim", json!({ "challenge_id": "c-1" }), now() - max_age_ms - 1000, &key);
+        assert_eq!(code(PayloadSigning::verify("challenge:claim", &stale, &key)), "SIGNATURE_EXPIRED");
+        let future = signed("challenge:claim", json!({ "challenge_id": "c-2" }), now() + max_age_ms + 1000, &key);
+        assert_eq!(code(PayloadSigning::verify("challenge:claim", &future, &key)), "SIGNATURE_EXPIRED");
+    }
+
+    #[test]
+    fn replayed_signature_is_refused() {
+        let key = key("session-replayed");
+        let data = signed("promo:redeem", json!({ "code": "WELCOME" }), now(), &key);
+        assert!(PayloadSigning::verify("promo:redeem", &data, &key).is_ok());
+        assert_eq!(code(PayloadSigning::verify("promo:redeem", &data, &key)), "SIGNATURE_REPLAYED");
+    }
+
+    #[test]
+    fn modes_decide_what_a_bad_signature_does() {
+        let refused = Err(("SIGNATURE_INVALID", "The signature does not match the payload"));
+        let error = PayloadSigning::apply(SigningMode::Enforce, "gift:send", refused).expect_err("refusal ignored");
+        assert_eq!((error.error_code.as_str(), error.details["event"].as_str()), ("SIGNATURE_INVALID", Some("gift:send")));
+        assert!(PayloadSigning::apply(SigningMode::Report, "gift:send", refused).is_ok());
+        assert!(PayloadSigning::apply(SigningMode::Off, "gift:send", refused).is_ok());
+        for mode in [SigningMode::Off, SigningMode::Report, SigningMode::Enforce] {
+            assert!(PayloadSigning::apply(mode, "gift:send", Ok(())).is_ok());
+        }
+    }
+
+    #[test]
+    fn modes_parse() {
+        assert_eq!(SigningMode::parse("enforce"), Some(SigningMode::Enforce));
+        assert_eq!(SigningMode::parse("report"), Some(SigningMode::Report));
+        assert_eq!(SigningMode::parse("off"), Some(SigningMode::Off));
+        assert_eq!(SigningMode::parse("Enforce"), None);
+    }
+}
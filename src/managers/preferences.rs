@@ -0,0 +1,203 @@
+use socketioxide::extract::SocketRef;
+use serde_json::{json, Map, Value};
+use std::sync::Arc;
+use tracing::{info, warn, error};
+
+use crate::api::response::{ApiError, ApiResponse};
+use crate::managers::auth_guard::AuthGuard;
+use crate::managers::chaos::FaultInjector;
+use crate::managers::error_responder::ErrorResponder;
+use crate::managers::validation::ValidationManager;
+use crate::database::models::UserRegister;
+use crate::database::store::DataStore;
+
+// Limits for preferences:set
+pub const MAX_KEYS_PER_UPDATE: usize = 32;
+pub const MAX_VALUE_BYTES: usize = 1024;
+pub const MAX_TOTAL_BYTES: usize = 16 * 1024;
+pub const MAX_SEGMENT_LENGTH: usize = 32;
+
+// Client-owned namespace: any key and JSON value, subject only to the size limits
+pub const FREEFORM_NAMESPACE: &str = "client";
+
+// Accepted values for a key in a known namespace
+#[derive(Debug, Clone, Copy)]
+pub enum PreferenceRule {
+    OneOf(&'static [&'static str]),
+    IntRange(i64, i64),
+    Bool,
+}
+
+impl PreferenceRule {
+    pub fn accepts(&self, value: &Value) -> bool {
+        match self {
+            PreferenceRule::OneOf(allowed) => value.as_str().is_some_and(|v| allowed.contains(&v)),
+            PreferenceRule::IntRange(min, max) => value.as_i64().is_some_and(|v| (*min..=*max).contains(&v)),
+            PreferenceRule::Bool => value.is_boolean(),
+        }
+    }
+
+    pub fn describe(&self) -> Value {
+        match self {
+            PreferenceRule::OneOf(allowed) => json!({"expected_type": "string", "allowed_values": allowed}),
+            PreferenceRule::IntRange(min, max) => json!({"expected_type": "integer", "min": min, "max": max}),
+            PreferenceRule::Bool => json!({"expected_type": "boolean"}),
+        }
+    }
+}
+
+// Server-validated namespaces. Notification preferences have their own
+// event (preferences:notifications) and are not part of this store.
+const KNOWN_NAMESPACES: &[(&str, &[(&str, PreferenceRule)])] = &[
+    ("display", &[
+        ("date_format", PreferenceRule::OneOf(&["MM/DD/YYYY", "DD/MM/YYYY", "YYYY-MM-DD"])),
+        ("time_format", PreferenceRule::OneOf(&["12h", "24h"])),
+        ("theme", PreferenceRule::OneOf(&["light", "dark", "system"])),
+    ]),
+    ("audio", &[
+        ("music_volume", PreferenceRule::IntRange(0, 100)),
+        ("sfx_volume", PreferenceRule::IntRange(0, 100)),
+        ("muted", PreferenceRule::Bool),
+    ]),
+    ("gameplay", &[
+        ("show_hints", PreferenceRule::Bool),
+        ("confirm_moves", PreferenceRule::Bool),
+        ("vibration", PreferenceRule::Bool),
+    ]),
+];
+
+// How a namespaced key is validated
+pub enum KeyRule {
+    Freeform,
+    Known(PreferenceRule),
+    UnknownNamespace,
+    UnknownKey(Vec<&'static str>),
+}
+
+pub struct PreferencesManager;
+
+impl PreferencesManager {
+    // All namespaces accepted by preferences:get / preferences:set
+    pub fn namespaces() -> Vec<&'static str> {
+        KNOWN_NAMESPACES.iter().map(|(ns, _)| *ns).chain(std::iter::once(FREEFORM_NAMESPACE)).collect()
+    }
+
+    // Split "namespace.key"; both parts must be 1-32 chars of [a-z0-9_]
+    pub fn split_key(key: &str) -> Option<(&str, &str)> {
+        let (namespace, name) = key.split_once('.')?;
+        let valid = |s: &str| !s.is_empty() && s.len() <= MAX_SEGMENT_LENGTH && s.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+        (valid(namespace) && valid(name)).then_some((namespace, name))
+    }
+
+    pub fn rule_for(namespace: &str, key: &str) -> KeyRule {
+        if namespace == FREEFORM_NAMESPACE {
+            return KeyRule::Freeform;
+        }
+        match KNOWN_NAMESPACES.iter().find(|(ns, _)| *ns == namespace) {
+            Some((_, keys)) => match keys.iter().find(|(k, _)| *k == key) {
+                Some((_, rule)) => KeyRule::Known(*rule),
+                None => KeyRule::UnknownKey(keys.iter().map(|(k, _)| *k).collect()),
+            },
+            None => KeyRule::UnknownNamespace,
+        }
+    }
+
+    // Namespaced view of a user's stored preferences, optionally limited to one namespace
+    fn namespaced_view(user: &UserRegister, namespace: Option<&str>) -> Value {
+        let stored = user.user_preferences.as_ref().and_then(|p| p.as_object());
+        let mut view = Map::new();
+        for ns in Self::namespaces() {
+            if namespace.is_none_or(|n| n == ns) {
+                let values = stored.and_then(|p| p.get(ns)).filter(|v| v.is_object()).cloned().unwrap_or_else(|| json!({}));
+                view.insert(ns.to_string(), values);
+            }
+        }
+        Value::Object(view)
+    }
+
+    // Generic preference store on the main namespace:
+    //   preferences:get { mobile_no, session_token, namespace? }  -> preferences:data
+    //   preferences:set { mobile_no, session_token, preferences } -> preferences:updated
+    // `preferences` maps "namespace.key" to a value. Updates are merged key by
+    // key into what is stored; a null value removes the key.
+    pub fn register_preference_events(socket: &SocketRef, data_service: Arc<dyn DataStore>) {
+        let ds = data_service.clone();
+        AuthGuard::require_session(socket, "preferences:get", data_service.clone(), move |socket, data, auth| {
+            let ds = ds.clone();
+            async move {
+                info!("⚙️ Received preferences:get from {}", socket.id);
+                if let Err(error_details) = ValidationManager::validate_preferences_get_data(&data) {
+                    info!("❌ Preferences validation failed for socket {}: {:?}", socket.id, error_details);
+                    ErrorResponder::send(&socket, &*ds, error_details).await;
+                    return;
+                }
+                let user = auth.user;
+
+                let response = ApiResponse::success("preferences:data", json!({
+                    "mobile_no": user.mobile_no,
+                    "preferences": Self::namespaced_view(&user, data["namespace"].as_str())
+                })).for_socket(socket.id);
+                if let Err(e) = FaultInjector::emit(&socket, "preferences:data", response).await {
+                    warn!("⚠️ Failed to emit preferences:data to socket {}: {}", socket.id, e);
+                }
+            }
+        });
+
+        let ds = data_service.clone();
+        AuthGuard::require_session(socket, "preferences:set", data_service, move |socket, data, auth| {
+            let ds = ds.clone();
+            async move {
+                info!("⚙️ Received preferences:set from {}: {:?}", socket.id, data["preferences"]);
+                if let Err(error_details) = ValidationManager::validate_preferences_set_data(&data) {
+                    info!("❌ Preferences validation failed for socket {}: {:?}", socket.id, error_details);
+                    ErrorResponder::send(&socket, &*ds, error_details).await;
+                    return;
+                }
+                let user = auth.user;
+                let updates = data["preferences"].as_object().cloned().unwrap_or_default();
+
+                // Enforce the overall size limit against the merged result
+                let mut merged = user.user_preferences.clone().filter(|p| p.is_object()).unwrap_or_else(|| json!({}));
+                for (path, value) in &updates {
+                    if let Some((namespace, key)) = Self::split_key(path) {
+                        let entry = merged
+                            .as_object_mut()
+                            .map(|m| m.entry(namespace).or_insert_with(|| json!({})));
+                        if let Some(Value::Object(ns)) = entry {
+                            if value.is_null() {
+                                ns.remove(key);
+                            } else {
+                                ns.insert(key.to_string(), value.clone());
+                            }
+                        }
+                    }
+                }
+                let total_bytes = merged.to_string().len();
+                if total_bytes > MAX_TOTAL_BYTES {
+                    let error = ApiError::new("PREFERENCES_TOO_LARGE", "LENGTH_ERROR", "preferences", "Stored preferences would exceed the size limit")
+                        .with_details(json!({"max_bytes": MAX_TOTAL_BYTES, "resulting_bytes": total_bytes}));
+                    ErrorResponder::send(&socket, &*ds, error).await;
+                    return;
+                }
+
+                if let Err(e) = ds.merge_user_preferences(&user.mobile_no, &updates).await {
+                    error!("❌ Failed to save preferences for mobile {}: {}", user.mobile_no, e);
+                    ErrorResponder::send(&socket, &*ds, ApiError::system("PREFERENCES_UPDATE_FAILED", "preferences", "Failed to save preferences", &e)).await;
+                    return;
+                }
+
+                let user = UserRegister { user_preferences: Some(merged), ..user };
+                let response = ApiResponse::success("preferences:updated", json!({
+                    "message": "Preferences updated",
+                    "mobile_no": user.mobile_no,
+                    "updated_keys": updates.keys().collect::<Vec<_>>(),
+                    "preferences": Self::namespaced_view(&user, None)
+                })).for_socket(socket.id);
+                match FaultInjector::emit(&socket, "preferences:updated", response).await {
+                    Ok(_) => info!("✅ Preferences updated for mobile: {} ({} key(s))", user.mobile_no, updates.len()),
+                    Err(e) => warn!("⚠️ Failed to emit preferences:updated to socket {}: {}", socket.id, e),
+                }
+            }
+        });
+    }
+}
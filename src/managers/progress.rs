@@ -0,0 +1,149 @@
+use socketioxide::extract::SocketRef;
+use serde_json::{json, Map, Value};
+use std::sync::Arc;
+use tracing::{info, warn, error};
+
+use crate::api::response::{ApiError, ApiResponse};
+use crate::database::models::{GameOutcome, GameResult, GameplayProgress, MatchResult, ProgressUpdate};
+use crate::database::store::DataStore;
+use crate::managers::auth_guard::AuthGuard;
+use crate::managers::chaos::FaultInjector;
+use crate::managers::error_responder::ErrorResponder;
+use crate::managers::escrow::EscrowManager;
+use crate::managers::room::RoomManager;
+use crate::managers::settlement::MatchSettlement;
+use crate::managers::validation::ValidationManager;
+
+// Limits for progress:update
+pub const MAX_XP_PER_UPDATE: i64 = 100_000;
+pub const MAX_LEVEL: i64 = 1_000;
+pub const MAX_SCORE: i64 = 1_000_000_000;
+pub const MAX_GAME_ID_LENGTH: usize = 32;
+
+pub struct ProgressManager;
+
+impl ProgressManager {
+    // Build the update from progress:update data already checked by ValidationManager
+    fn parse_update(data: &Value) -> ProgressUpdate {
+        let game = data.get("game").filter(|g| g.is_object()).and_then(|game| {
+            Some(GameResult {
+                game_id: game["game_id"].as_str()?.to_string(),
+                outcome: GameOutcome::parse(game["result"].as_str()?)?,
+                score: game["score"].as_i64(),
+                room_id: game["room_id"].as_str().map(str::to_string),
+            })
+        });
+        ProgressUpdate {
+            xp: data["xp"].as_i64().unwrap_or(0),
+            level: data["level"].as_u64().map(|level| level as u32),
+            game,
+        }
+    }
+
+    fn view(progress: &GameplayProgress) -> Value {
+        let rfc3339 = |date: bson::DateTime| date.try_to_rfc3339_string().unwrap_or_default();
+        let games: Map<String, Value> = progress.games.iter().map(|(game_id, stats)| (game_id.clone(), json!({
+            "played": stats.played,
+            "wins": stats.wins,
+            "losses": stats.losses,
+            "draws": stats.draws,
+            "total_score": stats.total_score,
+            "best_score": stats.best_score,
+            "last_played_at": stats.last_played_at.map(rfc3339)
+        }))).collect();
+        json!({
+            "level": progress.level,
+            "xp": progress.xp,
+            "games": games,
+            "updated_at": rfc3339(progress.updated_at)
+        })
+    }
+
+    // Per-user progression on the main namespace:
+    //   progress:get    { mobile_no, session_token }                      -> progress:data
+    //   progress:update { mobile_no, session_token, xp?, level?, game? }  -> progress:updated
+    // `game` is { game_id, result: win|loss|draw, score? }. XP, game counts and
+    // total score are added; level and best score keep the highest value seen.
+    pub fn register_progress_events(socket: &SocketRef, data_service: Arc<dyn DataStore>) {
+        let ds = data_service.clone();
+        AuthGuard::require_session(socket, "progress:get", data_service.clone(), move |socket, _data, auth| {
+            let ds = ds.clone();
+            async move {
+                info!("📊 Received progress:get from {}", socket.id);
+                let user = auth.user;
+                let progress = match ds.get_gameplay_progress(&user.user_id).await {
+                    Ok(progress) => progress.unwrap_or_else(|| GameplayProgress::new(user.user_id.clone())),
+                    Err(e) => {
+                        error!("❌ Failed to load gameplay progress for user {}: {}", user.user_id, e);
+                        ErrorResponder::send(&socket, &*ds, ApiError::system("PROGRESS_FETCH_FAILED", "progress", "Failed to load gameplay progress", &e)).await;
+                        return;
+                    }
+                };
+
+                let response = ApiResponse::success("progress:data", json!({
+                    "mobile_no": user.mobile_no,
+                    "user_id": user.user_id,
+                    "progress": Self::view(&progress)
+                })).for_socket(socket.id);
+                if let Err(e) = FaultInjector::emit(&socket, "progress:data", response).await {
+                    warn!("⚠️ Failed to emit progress:data to socket {}: {}", socket.id, e);
+                }
+            }
+        });
+
+        let ds = data_service.clone();
+        AuthGuard::require_session(socket, "progress:update", data_service, move |socket, data, auth| {
+            let ds = ds.clone();
+            async move {
+                info!("📊 Received progress:update from {}: {:?}", socket.id, data);
+                if let Err(error_details) = ValidationManager::validate_progress_update_data(&data) {
+                    info!("❌ Progress validation failed for socket {}: {:?}", socket.id, error_details);
+                    ErrorResponder::send(&socket, &*ds, error_details).await;
+                    return;
+                }
+                let user = auth.user;
+                let update = Self::parse_update(&data);
+
+                let progress = match ds.update_gameplay_progress(&user.user_id, &update).await {
+                    Ok(progress) => progress,
+                    Err(e) => {
+                        error!("❌ Failed to save gameplay progress for user {}: {}", user.user_id, e);
+                        ErrorResponder::send(&socket, &*ds, ApiError::system("PROGRESS_UPDATE_FAILED", "progress", "Failed to save gameplay progress", &e)).await;
+                        return;
+                    }
+                };
+
+                let response = ApiResponse::success("progress:updated", json!({
+                    "message": "Progress updated",
+                    "mobile_no": user.mobile_no,
+                    "user_id": user.user_id,
+                    "progress": Self::view(&progress)
+                })).for_socket(socket.id);
+                match FaultInjector::emit(&socket, "progress:updated", response).await {
+                    Ok(_) => info!("✅ Progress updated for user: {} (level {}, xp {})", user.user_id, progress.level, progress.xp),
+                    Err(e) => warn!("⚠️ Failed to emit progress:updated to socket {}: {}", socket.id, e),
+                }
+
+                // Challenges and ratings only count a result once the room settles it
+                if let Some(game) = &update.game {
+                    if let Some(room_id) = &game.room_id {
+                        let result = MatchResult {
+                            id: None,
+                            room_id: room_id.clone(),
+                            user_id: user.user_id.clone(),
+                            outcome: game.outcome,
+                            reported_at: bson::DateTime::now(),
+                        };
+                        if let Err(e) = ds.record_match_result(result).await {
+                            warn!("⚠️ Failed to record result of room {} for user {}: {}", room_id, user.user_id, e);
+                        }
+                        EscrowManager::result_reported(&*ds, room_id, &user.user_id, game.outcome).await;
+                        if let Some(settled) = RoomManager::report_result(room_id, &user.user_id, game.outcome).await {
+                            MatchSettlement::settled(&*ds, &settled).await;
+                        }
+                    }
+                }
+            }
+        });
+    }
+}
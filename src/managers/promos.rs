@@ -0,0 +1,197 @@
+use chrono::{DateTime, Duration, Utc};
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use socketioxide::extract::SocketRef;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use tracing::{error, info, warn};
+
+use crate::api::middleware::ClientIp;
+use crate::api::response::{ApiError, ApiResponse};
+use crate::config::CONFIG;
+use crate::database::models::{PromoCode, PromoRedeem, PromoRedemption, PromoReward};
+use crate::database::store::DataStore;
+use crate::managers::auth_guard::AuthGuard;
+use crate::managers::chaos::FaultInjector;
+use crate::managers::error_responder::ErrorResponder;
+use crate::managers::risk::RiskManager;
+use crate::managers::snowflake::Snowflake;
+use crate::managers::validation::ValidationManager;
+
+// Limits for promo codes and their rewards
+pub const MIN_CODE_LENGTH: usize = 3;
+pub const MAX_CODE_LENGTH: usize = 32;
+pub const MAX_PROMO_COINS: i64 = 1_000_000;
+pub const MAX_PROMO_ITEMS: usize = 10;
+pub const MAX_PROMO_ITEM_QUANTITY: i64 = 1000;
+
+// Unknown codes each user sent in the last hour, oldest first
+static FAILURES: Lazy<Mutex<HashMap<String, VecDeque<DateTime<Utc>>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// Promo codes defined by operators through the admin API. promo:redeem checks
+// the code (active, started, not expired, under max_uses and the user's
+// per_user_limit) and credits its coins and items in the same MongoDB
+// transaction that counts the use and stores the redemption, so a failure
+// leaves nothing half done. Redemptions keep the user's device and IP for
+// fraud review. Users limited by the anomaly scan cannot redeem, and a user
+// who sends PROMO_MAX_FAILURES_PER_HOUR unknown codes is refused for the rest
+// of the hour, so codes cannot be guessed.
+pub struct PromoManager;
+
+impl PromoManager {
+    // Codes are matched in upper case; None when `code` is not a valid code
+    pub fn normalize(code: &str) -> Option<String> {
+        let code = code.trim();
+        let valid = (MIN_CODE_LENGTH..=MAX_CODE_LENGTH).contains(&code.len())
+            && code.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        valid.then(|| code.to_ascii_uppercase())
+    }
+
+    pub fn promo_view(promo: &PromoCode) -> Value {
+        let rfc3339 = |date: bson::DateTime| date.try_to_rfc3339_string().unwrap_or_default();
+        json!({
+            "code": promo.code,
+            "description": promo.description,
+            "reward": promo.reward,
+            "max_uses": promo.max_uses,
+            "uses": promo.uses,
+            "per_user_limit": promo.per_user_limit,
+            "starts_at": promo.starts_at.map(rfc3339),
+            "expires_at": promo.expires_at.map(rfc3339),
+            "active": promo.active,
+            "created_by": promo.created_by,
+            "created_at": rfc3339(promo.created_at),
+            "updated_at": rfc3339(promo.updated_at),
+        })
+    }
+
+    pub fn redemption_view(redemption: &PromoRedemption) -> Value {
+        json!({
+            "redemption_id": redemption.redemption_id,
+            "code": redemption.code,
+            "user_id": redemption.user_id,
+            "device_id": redemption.device_id,
+            "ip_address": redemption.ip_address,
+            "reward": redemption.reward,
+            "redeemed_at": redemption.redeemed_at.try_to_rfc3339_string().unwrap_or_default(),
+        })
+    }
+
+    fn promo_error(code: &str, message: &str, details: Value) -> ApiError {
+        ApiError::new(code, "PROMO_ERROR", "code", message).with_details(details)
+    }
+
+    // Whether the user already sent too many unknown codes this hour
+    fn throttled(user_id: &str, now: DateTime<Utc>) -> bool {
+        let cutoff = now - Duration::hours(1);
+        let mut failures = FAILURES.lock().unwrap_or_else(|e| e.into_inner());
+        failures.retain(|_, sent| sent.back().is_some_and(|at| *at > cutoff));
+        failures.get(user_id).is_some_and(|sent| sent.iter().filter(|at| **at > cutoff).count() >= CONFIG.promo_max_failures_per_hour)
+    }
+
+    fn record_failure(user_id: &str, now: DateTime<Utc>) {
+        let mut failures = FAILURES.lock().unwrap_or_else(|e| e.into_inner());
+        let sent = failures.entry(user_id.to_string()).or_default();
+        while sent.front().is_some_and(|at| *at <= now - Duration::hours(1)) {
+            sent.pop_front();
+        }
+        sent.push_back(now);
+    }
+
+    fn refusal(code: &str, result: &PromoRedeem) -> ApiError {
+        match result {
+            PromoRedeem::NotStarted => Self::promo_error("PROMO_NOT_STARTED", "This promo code cannot be used yet", json!({ "code": code })),
+            PromoRedeem::Expired => Self::promo_error("PROMO_EXPIRED", "This promo code has expired", json!({ "code": code })),
+            PromoRedeem::Exhausted => Self::promo_error("PROMO_EXHAUSTED", "This promo code has been fully redeemed", json!({ "code": code })),
+            PromoRedeem::UserLimitReached { limit } => Self::promo_error("PROMO_ALREADY_REDEEMED", "You have already redeemed this promo code", json!({
+                "code": code,
+                "per_user_limit": limit
+            })),
+            _ => Self::promo_error("PROMO_NOT_FOUND", "No such promo code", json!({ "code": code })),
+        }
+    }
+
+    // Promo codes on the main namespace:
+    //   promo:redeem { mobile_no, session_token, code } -> promo:redeemed
+    pub fn register_promo_events(socket: &SocketRef, data_service: Arc<dyn DataStore>) {
+        let ds = data_service.clone();
+        AuthGuard::require_signed_session(socket, "promo:redeem", data_service, move |socket, data, auth| {
+            let ds = ds.clone();
+            async move {
+                info!("🎟️ Received promo:redeem from {}", socket.id);
+                if let Err(error_details) = ValidationManager::validate_promo_redeem_data(&data) {
+                    info!("❌ Promo redeem validation failed for socket {}: {:?}", socket.id, error_details);
+                    ErrorResponder::send(&socket, &*ds, error_details).await;
+                    return;
+                }
+                let user = auth.user;
+                let code = Self::normalize(data["code"].as_str().unwrap_or_default()).unwrap_or_default();
+                let now = Utc::now();
+                if Self::throttled(&user.user_id, now) {
+                    warn!("🚫 Promo redemption by user {} refused: too many unknown codes", user.user_id);
+                    let error = Self::promo_error("PROMO_RATE_LIMITED", "Too many invalid promo codes; try again later", json!({
+                        "max_failures_per_hour": CONFIG.promo_max_failures_per_hour
+                    }));
+                    ErrorResponder::send(&socket, &*ds, error).await;
+                    return;
+                }
+                match RiskManager::is_limited(&*ds, &user.user_id).await {
+                    Ok(false) => {}
+                    Ok(true) => {
+                        let error = Self::promo_error("PROMO_RISK_LIMITED", "Promo codes are limited on this account pending review", json!({}));
+                        ErrorResponder::send(&socket, &*ds, error).await;
+                        return;
+                    }
+                    Err(e) => {
+                        error!("❌ Failed to check risk of user {}: {}", user.user_id, e);
+                        ErrorResponder::send(&socket, &*ds, ApiError::system("PROMO_REDEEM_FAILED", "code", "Failed to redeem the promo code", &e)).await;
+                        return;
+                    }
+                }
+
+                let redemption = PromoRedemption {
+                    id: None,
+                    redemption_id: Snowflake::generate(),
+                    code: code.clone(),
+                    user_id: user.user_id.clone(),
+                    device_id: Some(user.device_id.clone()).filter(|id| !id.is_empty()),
+                    ip_address: ClientIp::of(&socket).map(|client| client.ip.to_string()),
+                    reward: PromoReward::default(),
+                    redeemed_at: bson::DateTime::from_millis(now.timestamp_millis()),
+                };
+                let (reward, balance, held) = match ds.redeem_promo_code(redemption).await {
+                    Ok(PromoRedeem::Redeemed { reward, balance, held }) => (reward, balance, held),
+                    Ok(result) => {
+                        if result == PromoRedeem::NotFound {
+                            Self::record_failure(&user.user_id, now);
+                        }
+                        let error = Self::refusal(&code, &result);
+                        info!("🚫 Promo code {} refused for user {}: {}", code, user.user_id, error.error_code);
+                        ErrorResponder::send(&socket, &*ds, error).await;
+                        return;
+                    }
+                    Err(e) => {
+                        error!("❌ Failed to redeem promo code {} for user {}: {}", code, user.user_id, e);
+                        ErrorResponder::send(&socket, &*ds, ApiError::system("PROMO_REDEEM_FAILED", "code", "Failed to redeem the promo code", &e)).await;
+                        return;
+                    }
+                };
+
+                let response = ApiResponse::success("promo:redeemed", json!({
+                    "code": code,
+                    "coins": reward.coins,
+                    "items": reward.items.iter().map(|item| json!({
+                        "item_id": item.item_id,
+                        "quantity": item.quantity,
+                        "held": held.iter().find(|(item_id, _)| *item_id == item.item_id).map(|(_, quantity)| quantity),
+                    })).collect::<Vec<_>>(),
+                    "balance": balance
+                })).for_socket(socket.id);
+                match FaultInjector::emit(&socket, "promo:redeemed", response).await {
+                    Ok(_) => info!("✅ Promo code {} redeemed by user: {}", code, user.user_id),
+                    Err(e) => warn!("⚠️ Failed to emit promo:redeemed to socket {}: {}", socket.id, e),
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,154 @@
+use socketioxide::extract::SocketRef;
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+use crate::api::response::{ApiError, ApiResponse};
+use crate::database::store::DataStore;
+use crate::managers::chaos::FaultInjector;
+use crate::managers::error_responder::ErrorResponder;
+
+// Payload protocol spoken by this server. When a client payload changes
+// shape, bump this and add a LegacyAdapter for the old shape.
+pub const PROTOCOL_VERSION: u32 = 2;
+// Oldest protocol still accepted on connect
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+// Assumed for clients that do not send protocol_version (apps released before versioning)
+const UNVERSIONED_PROTOCOL: u32 = 1;
+
+// Rewrites one legacy payload shape of an event into the current shape
+struct LegacyAdapter {
+    event: &'static str,
+    since: u32,                     // Protocol version that replaced the legacy shape
+    deprecated: &'static str,
+    replacement: &'static str,
+    adapt: fn(&mut Value) -> bool,  // Returns false when the payload was already current
+}
+
+// Protocol history:
+//   2 - verify:otp takes `otp` as a 6-digit string (protocol 1 apps sent the
+//       number echoed by login:success)
+static LEGACY_ADAPTERS: &[LegacyAdapter] = &[
+    LegacyAdapter {
+        event: "verify:otp",
+        since: 2,
+        deprecated: "numeric otp",
+        replacement: "otp as a 6-digit string",
+        adapt: otp_number_to_string,
+    },
+];
+
+fn otp_number_to_string(data: &mut Value) -> bool {
+    let Some(otp) = data.get("otp").and_then(|v| v.as_u64()) else { return false };
+    data["otp"] = json!(otp.to_string());
+    true
+}
+
+struct SocketProtocol {
+    version: u32,
+    warned: HashSet<(&'static str, &'static str)>,  // Legacy shapes already reported to this socket
+}
+
+// Negotiated protocol per socket id
+static SOCKETS: Lazy<RwLock<HashMap<String, SocketProtocol>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+pub struct ProtocolManager;
+
+impl ProtocolManager {
+    // Reads `protocol_version` from the connect auth payload, or the handshake
+    // query for clients that cannot send auth. Unsupported versions get a
+    // connection_error and false, and the caller disconnects the socket;
+    // outdated ones get a `deprecation` warning.
+    pub async fn negotiate(socket: &SocketRef, data_service: &dyn DataStore, auth: &Value) -> bool {
+        let requested = auth.get("protocol_version").cloned()
+            .or_else(|| Self::query_param(socket, "protocol_version").map(Value::String));
+        let version = match &requested {
+            None => Some(UNVERSIONED_PROTOCOL),
+            Some(v) => v.as_u64().or_else(|| v.as_str().and_then(|s| s.parse().ok()))
+                .and_then(|v| u32::try_from(v).ok())
+                .filter(|v| (MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION).contains(v)),
+        };
+        let Some(version) = version else {
+            warn!("🚫 Unsupported protocol_version {:?} from socket {}", requested, socket.id);
+            let error = ApiError::new("UNSUPPORTED_PROTOCOL_VERSION", "VALIDATION_ERROR", "protocol_version", "Unsupported protocol version. Please update the app.")
+                .with_details(json!({
+                    "requested": requested,
+                    "min_protocol_version": MIN_PROTOCOL_VERSION,
+                    "current_protocol_version": PROTOCOL_VERSION
+                }));
+            ErrorResponder::send(socket, data_service, error).await;
+            return false;
+        };
+
+        SOCKETS.write().await.insert(socket.id.to_string(), SocketProtocol { version, warned: HashSet::new() });
+        info!("📜 Socket {} speaks protocol {}", socket.id, version);
+        if version < PROTOCOL_VERSION {
+            Self::send_deprecation(socket, json!({
+                "kind": "protocol",
+                "protocol_version": version,
+                "current_protocol_version": PROTOCOL_VERSION,
+                "min_protocol_version": MIN_PROTOCOL_VERSION,
+                "message": format!("Protocol version {} is deprecated. Please update the app.", version)
+            })).await;
+        }
+        true
+    }
+
+    // Protocol negotiated on connect
+    pub async fn version(socket_id: &str) -> u32 {
+        SOCKETS.read().await.get(socket_id).map(|p| p.version).unwrap_or(UNVERSIONED_PROTOCOL)
+    }
+
+    // Converts legacy payload shapes of `event` to the current shape. Each
+    // legacy shape is reported to the socket once with a `deprecation` warning.
+    // Handlers whose payload has changed call this before validating.
+    pub async fn adapt(socket: &SocketRef, event: &str, mut data: Value) -> Value {
+        for adapter in LEGACY_ADAPTERS.iter().filter(|a| a.event == event) {
+            if !(adapter.adapt)(&mut data) {
+                continue;
+            }
+            let socket_id = socket.id.to_string();
+            let first_use = match SOCKETS.write().await.get_mut(&socket_id) {
+                Some(protocol) => protocol.warned.insert((adapter.event, adapter.deprecated)),
+                None => true,
+            };
+            if !first_use {
+                continue;
+            }
+            info!("📜 Socket {} sent legacy {} on {}", socket.id, adapter.deprecated, event);
+            Self::send_deprecation(socket, json!({
+                "kind": "payload",
+                "deprecated_event": event,
+                "deprecated": adapter.deprecated,
+                "replacement": adapter.replacement,
+                "since_protocol_version": adapter.since,
+                "protocol_version": Self::version(&socket_id).await,
+                "current_protocol_version": PROTOCOL_VERSION,
+                "message": format!("{} on {} is deprecated; send {}", adapter.deprecated, event, adapter.replacement)
+            })).await;
+        }
+        data
+    }
+
+    pub async fn remove_socket(socket_id: &str) {
+        SOCKETS.write().await.remove(socket_id);
+    }
+
+    async fn send_deprecation(socket: &SocketRef, data: Value) {
+        let response = ApiResponse::success("deprecation", data).with_status("warning").for_socket(socket.id);
+        if let Err(e) = FaultInjector::emit(socket, "deprecation", response).await {
+            warn!("⚠️ Failed to send deprecation warning to socket {}: {}", socket.id, e);
+        }
+    }
+
+    // Value of a handshake query parameter
+    pub fn query_param(socket: &SocketRef, name: &str) -> Option<String> {
+        socket.req_parts().uri.query()?
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value.to_string())
+    }
+}
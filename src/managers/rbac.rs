@@ -0,0 +1,158 @@
+use tracing::warn;
+
+use crate::database::models::AdminOperator;
+use crate::database::store::DataStore;
+use crate::managers::admin::AdminManager;
+use crate::managers::token::TokenGenerator;
+
+// operator_id of whoever authenticates with ADMIN_API_TOKEN itself
+pub const ROOT_OPERATOR: &str = "root";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AdminRole {
+    Admin,
+    Support,
+    Moderator,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Permission {
+    UsersRead,          // Search and look up users
+    UsersImport,
+    UsersExport,
+    UsersChangeMobile,  // Move an account to another mobile number without its OTPs
+    UsersResetTwoStep,  // Remove a user's second factor for account recovery
+    MetricsRead,        // Handler metrics on the /admin namespace
+    ErrorsRead,         // Connection error analytics
+    OperatorsManage,    // Add operators and assign roles
+    SeasonsManage,      // Schedule ranked seasons
+    RiskReview,         // Anomaly scan flags and user risk scores
+    ModerationManage,   // Work the report queue and sanction users
+    GameConfigsManage,  // Change game rules
+    NotificationsManage, // Edit notification templates
+    BackupsManage,      // Start backups and follow their progress
+    EventsExport,       // Export event collections for the data team
+    DatabaseRead,       // Index usage report
+    FinanceRead,        // Match commission ledger and daily revenue
+    PromosManage,       // Create and disable promo codes, review redemptions
+    UsersImpersonate,   // Watch the emits sent to a user, read-only
+}
+
+impl AdminRole {
+    pub fn parse(role: &str) -> Option<Self> {
+        match role {
+            "admin" => Some(AdminRole::Admin),
+            "support" => Some(AdminRole::Support),
+            "moderator" => Some(AdminRole::Moderator),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AdminRole::Admin => "admin",
+            AdminRole::Support => "support",
+            AdminRole::Moderator => "moderator",
+        }
+    }
+
+    pub fn allows(self, permission: Permission) -> bool {
+        match self {
+            AdminRole::Admin => true,
+            AdminRole::Support => matches!(permission, Permission::UsersRead | Permission::ErrorsRead | Permission::UsersResetTwoStep | Permission::UsersImpersonate),
+            AdminRole::Moderator => matches!(permission, Permission::UsersRead | Permission::MetricsRead | Permission::ErrorsRead | Permission::RiskReview | Permission::ModerationManage),
+        }
+    }
+}
+
+impl Permission {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Permission::UsersRead => "users:read",
+            Permission::UsersImport => "users:import",
+            Permission::UsersExport => "users:export",
+            Permission::UsersChangeMobile => "users:change_mobile",
+            Permission::UsersResetTwoStep => "users:reset_two_step",
+            Permission::MetricsRead => "metrics:read",
+            Permission::ErrorsRead => "errors:read",
+            Permission::OperatorsManage => "operators:manage",
+            Permission::SeasonsManage => "seasons:manage",
+            Permission::RiskReview => "risk:review",
+            Permission::ModerationManage => "moderation:manage",
+            Permission::GameConfigsManage => "game_configs:manage",
+            Permission::NotificationsManage => "notifications:manage",
+            Permission::BackupsManage => "backups:manage",
+            Permission::EventsExport => "events:export",
+            Permission::DatabaseRead => "database:read",
+            Permission::FinanceRead => "finance:read",
+            Permission::PromosManage => "promos:manage",
+            Permission::UsersImpersonate => "users:impersonate",
+        }
+    }
+}
+
+// Who is making an admin request
+#[derive(Debug, Clone)]
+pub struct AdminIdentity {
+    pub operator_id: String,
+    pub role: AdminRole,
+}
+
+// Role-based access to the admin REST API and the /admin namespace
+pub struct Rbac;
+
+impl Rbac {
+    // ADMIN_API_TOKEN authenticates as the root admin; operators use the
+    // "<operator_id>.<secret>" token they were issued.
+    pub async fn authenticate(data_service: &dyn DataStore, token: Option<&str>) -> Option<AdminIdentity> {
+        let token = token?;
+        if AdminManager::authorized(Some(token)) {
+            return Some(AdminIdentity { operator_id: ROOT_OPERATOR.to_string(), role: AdminRole::Admin });
+        }
+        let (operator_id, secret) = token.split_once('.')?;
+        let operator = match data_service.get_admin_operator(operator_id).await {
+            Ok(operator) => operator?,
+            Err(e) => {
+                warn!("⚠️ Failed to look up admin operator {}: {}", operator_id, e);
+                return None;
+            }
+        };
+        if !TokenGenerator::constant_time_eq(&operator.secret, secret) {
+            return None;
+        }
+        let Some(role) = AdminRole::parse(&operator.role) else {
+            warn!("⚠️ Admin operator {} has unknown role {}", operator.operator_id, operator.role);
+            return None;
+        };
+        Some(AdminIdentity { operator_id: operator.operator_id, role })
+    }
+
+    // Denials are logged and stored in admin_access_denied_events
+    pub async fn authorize(data_service: &dyn DataStore, identity: &AdminIdentity, permission: Permission, target: &str) -> bool {
+        if identity.role.allows(permission) {
+            return true;
+        }
+        warn!("🚫 Admin operator {} ({}) denied {} on {}", identity.operator_id, identity.role.as_str(), permission.as_str(), target);
+        if let Err(e) = data_service.store_admin_access_denied_event(&identity.operator_id, identity.role.as_str(), permission.as_str(), target).await {
+            warn!("⚠️ Failed to store admin access denial for {}: {}", identity.operator_id, e);
+        }
+        false
+    }
+
+    // New operator and the API token to hand them (only shown once)
+    pub fn new_operator(name: &str, role: AdminRole, created_by: &str) -> (AdminOperator, String) {
+        let now = bson::DateTime::from_millis(chrono::Utc::now().timestamp_millis());
+        let operator = AdminOperator {
+            id: None,
+            operator_id: uuid::Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext)).simple().to_string(),
+            name: name.to_string(),
+            role: role.as_str().to_string(),
+            secret: TokenGenerator::session_token(),
+            created_by: created_by.to_string(),
+            created_at: now,
+            updated_at: now,
+        };
+        let token = format!("{}.{}", operator.operator_id, operator.secret);
+        (operator, token)
+    }
+}
@@ -0,0 +1,79 @@
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use socketioxide::extract::SocketRef;
+use tracing::{error, info};
+
+use crate::managers::login_policy::LoginPolicyManager;
+
+// One entry of MATCHMAKING_REGIONS
+#[derive(Debug, Clone, Deserialize)]
+pub struct Region {
+    pub region: String,                 // e.g. "eu-west"
+    pub endpoint: String,               // URL clients in this region should connect to
+    #[serde(default)]
+    pub countries: Vec<String>,         // ISO 3166-1 alpha-2 codes served by this region
+}
+
+static REGIONS: Lazy<Vec<Region>> = Lazy::new(RegionManager::load);
+
+// Server regions from MATCHMAKING_REGIONS. A socket's region is the one that
+// serves the country GEOIP_COUNTRY_HEADER reports for its IP; matchmaking
+// prefers opponents in the same region and connect_response recommends the
+// region's endpoint. Without the header or the regions every socket is in no
+// region, and matchmaking pairs on latency alone.
+pub struct RegionManager;
+
+impl RegionManager {
+    fn load() -> Vec<Region> {
+        let Some(raw) = std::env::var("MATCHMAKING_REGIONS").ok().filter(|v| !v.trim().is_empty()) else {
+            return Vec::new();
+        };
+        let regions: Vec<Region> = match serde_json::from_str(&raw) {
+            Ok(regions) => regions,
+            Err(e) => {
+                error!("❌ MATCHMAKING_REGIONS is not a valid list of regions - matching without regions: {}", e);
+                return Vec::new();
+            }
+        };
+        regions.into_iter()
+            .filter(|region| {
+                let usable = !region.region.trim().is_empty() && !region.endpoint.trim().is_empty();
+                if !usable {
+                    error!("❌ Skipping matchmaking region {:?}: region and endpoint are required", region.region);
+                }
+                usable
+            })
+            .map(|mut region| {
+                region.countries = region.countries.iter().map(|c| c.trim().to_ascii_uppercase()).collect();
+                info!("🌍 Matchmaking region {} at {} ({} countries)", region.region, region.endpoint, region.countries.len());
+                region
+            })
+            .collect()
+    }
+
+    pub fn regions() -> &'static [Region] {
+        &REGIONS
+    }
+
+    // Region serving the country of the socket's IP, if any
+    pub fn of_socket(socket: &SocketRef) -> Option<&'static Region> {
+        let country = LoginPolicyManager::ip_country(socket)?;
+        REGIONS.iter().find(|region| region.countries.contains(&country))
+    }
+
+    // Every region with its endpoint for connect_response, the socket's own
+    // region first and marked recommended
+    pub fn recommendations(socket: &SocketRef) -> Vec<Value> {
+        let own = Self::of_socket(socket).map(|region| region.region.as_str());
+        let mut regions: Vec<&Region> = REGIONS.iter().collect();
+        regions.sort_by_key(|region| Some(region.region.as_str()) != own);
+        regions.into_iter()
+            .map(|region| json!({
+                "region": region.region,
+                "endpoint": region.endpoint,
+                "recommended": Some(region.region.as_str()) == own
+            }))
+            .collect()
+    }
+}
@@ -0,0 +1,73 @@
+use chrono::{DateTime, Duration, Utc};
+use std::sync::Arc;
+use tracing::info;
+
+use crate::config::CONFIG;
+use crate::database::models::{week_start_ms, RetentionReport};
+use crate::database::store::DataStore;
+use crate::managers::scheduler::Scheduler;
+
+// Days after registration a cohort's return rate is reported for
+pub const RETENTION_DAYS: [u32; 3] = [1, 7, 30];
+
+// Cohort retention for the admin API: users bucketed by the UTC week they
+// registered in, and the share of them who completed a login 1, 7 and 30
+// days later. The aggregation scans every registration of the last
+// RETENTION_REPORT_WEEKS weeks, so it runs once a day and the admin API reads
+// the stored report.
+pub struct RetentionManager;
+
+impl RetentionManager {
+    fn date_of(time: DateTime<Utc>) -> String {
+        time.format("%Y-%m-%d").to_string()
+    }
+
+    // A few minutes into the next UTC day, once the previous day's logins are stored
+    fn next_run(now: DateTime<Utc>) -> DateTime<Utc> {
+        let tomorrow = now.date_naive() + Duration::days(1);
+        tomorrow.and_hms_opt(0, 5, 0).unwrap_or_default().and_utc()
+    }
+
+    // Compute today's report and store it in place of any earlier one of today
+    pub async fn generate(data_service: &dyn DataStore) -> Result<RetentionReport, Box<dyn std::error::Error + Send + Sync>> {
+        let now = Utc::now();
+        let weeks = CONFIG.retention_report_weeks;
+        let this_week = week_start_ms(now.timestamp_millis());
+        let since = DateTime::from_timestamp_millis(this_week).unwrap_or(now) - Duration::weeks(weeks as i64 - 1);
+        let cohorts = data_service.retention_cohorts(since, now, &RETENTION_DAYS).await?;
+        let report = RetentionReport {
+            id: None,
+            report_date: Self::date_of(now),
+            weeks,
+            cohorts,
+            generated_at: bson::DateTime::from_millis(now.timestamp_millis()),
+        };
+        data_service.save_retention_report(report.clone()).await?;
+        info!("📊 Retention report for {} ready ({} cohorts)", report.report_date, report.cohorts.len());
+        Ok(report)
+    }
+
+    // The stored report; computed first when none exists yet
+    pub async fn latest(data_service: &dyn DataStore) -> Result<RetentionReport, Box<dyn std::error::Error + Send + Sync>> {
+        match data_service.latest_retention_report().await? {
+            Some(report) => Ok(report),
+            None => Self::generate(data_service).await,
+        }
+    }
+
+    // Compute each day's report at startup and after every UTC midnight,
+    // unless another server already did
+    pub fn spawn_daily_report(data_service: Arc<dyn DataStore>) {
+        Scheduler::at("retention-report", Self::next_run, move || {
+            let data_service = data_service.clone();
+            async move {
+                let today = Self::date_of(Utc::now());
+                let current = data_service.latest_retention_report().await?;
+                if !matches!(current, Some(report) if report.report_date == today) {
+                    Self::generate(&*data_service).await?;
+                }
+                Ok(())
+            }
+        });
+    }
+}
@@ -0,0 +1,72 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use std::sync::Arc;
+use tracing::info;
+
+use crate::config::CONFIG;
+use crate::database::models::{MatchEscrow, RevenueEntry, RevenueRollup};
+use crate::database::store::DataStore;
+use crate::managers::scheduler::Scheduler;
+
+// Platform commission for finance reconciliation. Every released escrow
+// writes its pot, rake and payout to revenue_ledger once, dated by the UTC day
+// it settled on. revenue_rollups keeps each day's totals by game type; today's
+// and yesterday's are recomputed every REVENUE_ROLLUP_INTERVAL_SECS (a
+// settlement finishing around midnight lands in yesterday), and any day can be
+// recomputed from the ledger through the admin API.
+pub struct RevenueManager;
+
+impl RevenueManager {
+    pub fn date_of(time: DateTime<Utc>) -> String {
+        time.format("%Y-%m-%d").to_string()
+    }
+
+    // A YYYY-MM-DD day, normalised; None when it is not a date
+    pub fn parse_day(day: &str) -> Option<NaiveDate> {
+        NaiveDate::parse_from_str(day, "%Y-%m-%d").ok()
+    }
+
+    // Record the commission of an escrow being released. Safe to repeat:
+    // the ledger keeps one entry per escrow.
+    pub async fn record(data_service: &dyn DataStore, escrow: &MatchEscrow) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let now = Utc::now();
+        let pot: i64 = escrow.stakes.iter().map(|stake| stake.amount).sum();
+        let entry = RevenueEntry {
+            id: None,
+            escrow_id: escrow.escrow_id.clone(),
+            room_id: escrow.room_id.clone(),
+            game_type: escrow.game_type.clone(),
+            entry_fee: escrow.entry_fee,
+            players: escrow.stakes.len() as u32,
+            pot,
+            rake: escrow.rake,
+            paid_out: escrow.payouts.iter().map(|payout| payout.amount).sum(),
+            day: Self::date_of(now),
+            recorded_at: bson::DateTime::from_millis(now.timestamp_millis()),
+        };
+        if data_service.record_revenue(entry).await? {
+            info!("🏦 Recorded {} coins of commission from room {} ({})", escrow.rake, escrow.room_id, escrow.game_type);
+        }
+        Ok(())
+    }
+
+    // Recompute a day's rollups from the ledger and store them
+    pub async fn rollup(data_service: &dyn DataStore, day: &str) -> Result<Vec<RevenueRollup>, Box<dyn std::error::Error + Send + Sync>> {
+        let rollups = data_service.revenue_day_totals(day).await?;
+        data_service.save_revenue_rollups(day, rollups.clone()).await?;
+        Ok(rollups)
+    }
+
+    pub fn spawn_rollups(data_service: Arc<dyn DataStore>) {
+        let period = std::time::Duration::from_secs(CONFIG.revenue_rollup_interval_secs);
+        Scheduler::every("revenue-rollup", period, move || {
+            let data_service = data_service.clone();
+            async move {
+                let now = Utc::now();
+                for day in [Self::date_of(now - Duration::days(1)), Self::date_of(now)] {
+                    Self::rollup(&*data_service, &day).await?;
+                }
+                Ok(())
+            }
+        });
+    }
+}
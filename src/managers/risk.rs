@@ -0,0 +1,259 @@
+use socketioxide::extract::SocketRef;
+use serde_json::json;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::api::middleware::ClientIp;
+use crate::config::CONFIG;
+use crate::database::models::{GameOutcome, Gift, MatchParticipant, MatchResult, RiskFlag, RiskSignal, UserRisk};
+use crate::database::store::DataStore;
+use crate::managers::scheduler::Scheduler;
+use crate::managers::tenant::TenantManager;
+
+// Rule weights; a user's score is the sum of their signals, capped at 100
+const SAME_IP_WEIGHT: u32 = 30;
+const SAME_IP_WEIGHT_PER_EXTRA_ROOM: u32 = 5;
+const SAME_IP_MAX_WEIGHT: u32 = 50;
+const WIN_TRADING_WEIGHT: u32 = 40;
+const MATCH_DUMPING_WEIGHT: u32 = 40;
+const GIFT_DUMPING_WEIGHT: u32 = 30;
+const MAX_SCORE: u32 = 100;
+
+// Win trading: both players win at least this share of their games against
+// each other, and those games are at least this share of all their games
+const WIN_TRADING_MIN_WIN_SHARE: f64 = 0.3;
+const WIN_TRADING_MIN_PAIR_SHARE: f64 = 0.5;
+// Match dumping: one player loses at least this share against the other
+const MATCH_DUMPING_LOSS_SHARE: f64 = 0.9;
+// Gift dumping: coins sent back are under this share of the coins received
+const GIFT_DUMPING_RETURN_SHARE: f64 = 0.1;
+
+// Anomaly detection over recent matches and gifts. Each scan scores every
+// user with suspicious patterns (same-IP opponents, win trading, match and
+// gift dumping), opens a review flag per finding and marks high scores as
+// limited, which blocks gifting and ranked results until a later scan clears it.
+pub struct RiskManager;
+
+impl RiskManager {
+    pub fn spawn_scanner(data_service: Arc<dyn DataStore>) {
+        let period = Duration::from_secs(CONFIG.risk_scan_interval_secs);
+        Scheduler::every("risk-scan", period, move || {
+            let data_service = data_service.clone();
+            async move { Self::scan(&*data_service).await }
+        });
+    }
+
+    async fn scan(data_service: &dyn DataStore) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let started = bson::DateTime::now();
+        let since = chrono::Utc::now() - chrono::Duration::hours(CONFIG.risk_scan_window_hours);
+        let (participants, results, gifts, dismissed) = tokio::try_join!(
+            data_service.match_participants_since(since),
+            data_service.match_results_since(since),
+            data_service.gifts_since(since),
+            data_service.dismissed_risk_flag_keys()
+        )?;
+        let dismissed: HashSet<String> = dismissed.into_iter().collect();
+
+        let mut signals: HashMap<String, Vec<RiskSignal>> = HashMap::new();
+        let found = Self::same_ip_signals(&participants)
+            .into_iter()
+            .chain(Self::match_signals(&results))
+            .chain(Self::gift_signals(&gifts));
+        for (user_id, signal) in found {
+            if !dismissed.contains(&Self::flag_key(&user_id, &signal)) {
+                signals.entry(user_id).or_default().push(signal);
+            }
+        }
+
+        let mut limited = 0;
+        let mut flagged = 0;
+        for (user_id, signals) in signals {
+            let score = signals.iter().map(|s| s.weight).sum::<u32>().min(MAX_SCORE);
+            let risk = UserRisk {
+                id: None,
+                user_id: user_id.clone(),
+                score,
+                signals: signals.clone(),
+                limited: score >= CONFIG.risk_limit_score,
+                updated_at: bson::DateTime::now(),
+            };
+            limited += risk.limited as usize;
+            data_service.save_user_risk(risk).await?;
+
+            for signal in signals {
+                let flag = RiskFlag {
+                    id: None,
+                    flag_id: uuid::Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext)).simple().to_string(),
+                    flag_key: Self::flag_key(&user_id, &signal),
+                    user_id: user_id.clone(),
+                    signal,
+                    score,
+                    status: "open".to_string(),
+                    created_at: bson::DateTime::now(),
+                    resolved_by: None,
+                    resolved_at: None,
+                };
+                flagged += data_service.create_risk_flag(flag).await? as usize;
+            }
+        }
+
+        // Users without findings in this scan go back to a clean score
+        let cleared = data_service.clear_stale_user_risk(started).await?;
+        info!("🕵️ Risk scan done: {} new flags, {} limited users, {} scores cleared", flagged, limited, cleared);
+        Ok(())
+    }
+
+    fn flag_key(user_id: &str, signal: &RiskSignal) -> String {
+        format!("{}:{}:{}", signal.rule, user_id, signal.counterparty)
+    }
+
+    fn signal(rule: &str, counterparty: &str, weight: u32, details: serde_json::Value) -> RiskSignal {
+        RiskSignal { rule: rule.to_string(), counterparty: counterparty.to_string(), weight, details }
+    }
+
+    // Players of the same room joining from one IP address
+    fn same_ip_signals(participants: &[MatchParticipant]) -> Vec<(String, RiskSignal)> {
+        let mut rooms: HashMap<(&str, &str), BTreeSet<&str>> = HashMap::new();
+        for participant in participants {
+            if let Some(ip) = participant.ip_address.as_deref() {
+                rooms.entry((participant.room_id.as_str(), ip)).or_default().insert(participant.user_id.as_str());
+            }
+        }
+
+        // Rooms each pair of users shared an address in
+        let mut shared: HashMap<(&str, &str), BTreeSet<&str>> = HashMap::new();
+        for ((room_id, _), users) in &rooms {
+            let users: Vec<&str> = users.iter().copied().collect();
+            for (i, a) in users.iter().enumerate() {
+                for b in &users[i + 1..] {
+                    shared.entry((a, b)).or_default().insert(room_id);
+                }
+            }
+        }
+
+        let mut signals = Vec::new();
+        for ((a, b), rooms) in shared {
+            let extra = rooms.len() as u32 - 1;
+            let weight = (SAME_IP_WEIGHT + extra * SAME_IP_WEIGHT_PER_EXTRA_ROOM).min(SAME_IP_MAX_WEIGHT);
+            let details = json!({ "rooms": rooms.len() });
+            signals.push((a.to_string(), Self::signal("same_ip_opponents", b, weight, details.clone())));
+            signals.push((b.to_string(), Self::signal("same_ip_opponents", a, weight, details)));
+        }
+        signals
+    }
+
+    // Head-to-head records from rooms where one player won and another lost
+    fn match_signals(results: &[MatchResult]) -> Vec<(String, RiskSignal)> {
+        // The last result each player reported per room
+        let mut rooms: HashMap<&str, HashMap<&str, GameOutcome>> = HashMap::new();
+        for result in results {
+            rooms.entry(result.room_id.as_str()).or_default().insert(result.user_id.as_str(), result.outcome);
+        }
+
+        // wins[(winner, loser)] and each player's decided games
+        let mut wins: HashMap<(&str, &str), usize> = HashMap::new();
+        let mut decided: HashMap<&str, usize> = HashMap::new();
+        for outcomes in rooms.values() {
+            let winners = outcomes.iter().filter(|(_, o)| **o == GameOutcome::Win).map(|(u, _)| *u);
+            let losers: Vec<&str> = outcomes.iter().filter(|(_, o)| **o == GameOutcome::Loss).map(|(u, _)| *u).collect();
+            if losers.is_empty() {
+                continue;
+            }
+            for winner in winners {
+                for loser in &losers {
+                    *wins.entry((winner, loser)).or_default() += 1;
+                }
+            }
+            for (user_id, outcome) in outcomes {
+                if *outcome != GameOutcome::Draw {
+                    *decided.entry(user_id).or_default() += 1;
+                }
+            }
+        }
+
+        let pairs: BTreeSet<(&str, &str)> = wins.keys().map(|&(a, b)| (a.min(b), a.max(b))).collect();
+        let mut signals = Vec::new();
+        for (a, b) in pairs {
+            let a_wins = wins.get(&(a, b)).copied().unwrap_or(0);
+            let b_wins = wins.get(&(b, a)).copied().unwrap_or(0);
+            let games = a_wins + b_wins;
+            if games < CONFIG.risk_min_pair_matches {
+                continue;
+            }
+            let share = |n: usize, of: usize| n as f64 / of.max(1) as f64;
+            let details = json!({ "games": games, "wins": { a: a_wins, b: b_wins } });
+
+            // One side handing the other its wins
+            let dumped = if share(a_wins, games) >= MATCH_DUMPING_LOSS_SHARE {
+                Some((a, b))
+            } else if share(b_wins, games) >= MATCH_DUMPING_LOSS_SHARE {
+                Some((b, a))
+            } else {
+                None
+            };
+            if let Some((winner, loser)) = dumped {
+                signals.push((loser.to_string(), Self::signal("match_dumping", winner, MATCH_DUMPING_WEIGHT, details.clone())));
+                signals.push((winner.to_string(), Self::signal("match_dumping", loser, MATCH_DUMPING_WEIGHT, details)));
+                continue;
+            }
+
+            // Two players taking turns to win, mostly against each other
+            let trading = share(a_wins, games) >= WIN_TRADING_MIN_WIN_SHARE
+                && share(b_wins, games) >= WIN_TRADING_MIN_WIN_SHARE
+                && share(games, decided[a]) >= WIN_TRADING_MIN_PAIR_SHARE
+                && share(games, decided[b]) >= WIN_TRADING_MIN_PAIR_SHARE;
+            if trading {
+                signals.push((a.to_string(), Self::signal("win_trading", b, WIN_TRADING_WEIGHT, details.clone())));
+                signals.push((b.to_string(), Self::signal("win_trading", a, WIN_TRADING_WEIGHT, details)));
+            }
+        }
+        signals
+    }
+
+    // Coins flowing one way between two users
+    fn gift_signals(gifts: &[Gift]) -> Vec<(String, RiskSignal)> {
+        let mut sent: HashMap<(&str, &str), i64> = HashMap::new();
+        for gift in gifts {
+            if let Some(coins) = gift.coins {
+                *sent.entry((gift.from_user_id.as_str(), gift.to_user_id.as_str())).or_default() += coins;
+            }
+        }
+
+        let mut signals = Vec::new();
+        for (&(from, to), &coins) in &sent {
+            let returned = sent.get(&(to, from)).copied().unwrap_or(0);
+            if coins < CONFIG.risk_gift_dump_coins || returned as f64 >= coins as f64 * GIFT_DUMPING_RETURN_SHARE {
+                continue;
+            }
+            let details = json!({ "from_user_id": from, "to_user_id": to, "coins": coins, "returned": returned });
+            signals.push((from.to_string(), Self::signal("gift_dumping", to, GIFT_DUMPING_WEIGHT, details.clone())));
+            signals.push((to.to_string(), Self::signal("gift_dumping", from, GIFT_DUMPING_WEIGHT, details)));
+        }
+        signals
+    }
+
+    // Remember who joined a room and from which address, for the same-IP rule
+    pub fn record_participant(data_service: Arc<dyn DataStore>, socket: &SocketRef, room_id: &str, user_id: &str) {
+        let ip_address = ClientIp::of(socket).map(|client| client.ip.to_string());
+        let participant = MatchParticipant {
+            id: None,
+            room_id: room_id.to_string(),
+            user_id: user_id.to_string(),
+            ip_address,
+            joined_at: bson::DateTime::now(),
+        };
+        let room_id = room_id.to_string();
+        TenantManager::spawn(async move {
+            if let Err(e) = data_service.record_match_participant(participant).await {
+                warn!("⚠️ Failed to record participant of room {}: {}", room_id, e);
+            }
+        });
+    }
+
+    // Whether the last scan limited this user
+    pub async fn is_limited(data_service: &dyn DataStore, user_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(data_service.get_user_risk(user_id).await?.is_some_and(|risk| risk.limited))
+    }
+}
@@ -0,0 +1,371 @@
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use chrono::{DateTime, Utc};
+use tracing::info;
+
+use crate::database::models::{ActiveTurnSnapshot, GameConfig, GameOutcome, RoomPlayerSnapshot, RoomSnapshot};
+use crate::managers::game_config::{GameConfigManager, DEFAULT_GAME_TYPE};
+use crate::managers::game_modes::{GameModeRegistry, RegisteredMode};
+use crate::managers::settlement::{MatchSettlement, SettledMatch};
+use crate::managers::tenant::{Tenant, TenantManager};
+use crate::managers::turn_timer::ActiveTurn;
+
+// Global in-memory room state for the gameplay namespace
+static ROOMS: Lazy<RwLock<HashMap<String, GameRoom>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+#[derive(Debug, Clone)]
+pub struct RoomPlayer {
+    pub player_id: String,
+    pub socket_id: String,
+    pub joined_at: DateTime<Utc>,
+    pub is_bot: bool,
+    pub team: u8,
+    pub left_at: Option<DateTime<Utc>>,     // Set while a human player's socket is gone
+}
+
+impl RoomPlayer {
+    pub fn human(player_id: &str, socket_id: &str) -> Self {
+        Self {
+            player_id: player_id.to_string(),
+            socket_id: socket_id.to_string(),
+            joined_at: Utc::now(),
+            is_bot: false,
+            team: 0,
+            left_at: None,
+        }
+    }
+
+    pub fn bot(player_id: &str) -> Self {
+        Self {
+            player_id: player_id.to_string(),
+            socket_id: String::new(),
+            joined_at: Utc::now(),
+            is_bot: true,
+            team: 0,
+            left_at: None,
+        }
+    }
+
+    pub fn with_team(mut self, team: u8) -> Self {
+        self.team = team;
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GameRoom {
+    pub room_id: String,
+    pub players: Vec<RoomPlayer>,
+    pub created_at: DateTime<Utc>,
+    pub turn_index: Option<usize>,              // Index into `players` of the current/last turn
+    pub turn_number: u32,                       // Monotonic turn counter for the match
+    pub active_turn: Option<ActiveTurn>,
+    pub consecutive_timeouts: HashMap<String, u32>,
+    pub is_bot_match: bool,                     // Bot matches are unrated
+    pub reports: HashMap<String, GameOutcome>,  // Results the human players reported, by player_id
+    pub settled: bool,                          // The reports agreed; the result was counted
+    pub revision: u64,                          // Bumped on every change; tells the snapshotter what is unsaved
+    pub config: Arc<GameConfig>,                // Rules for the whole match, fixed when the room is created
+    pub tenant: &'static Tenant,                // Only players of this tenant can join
+}
+
+impl GameRoom {
+    pub fn new(room_id: String, config: Arc<GameConfig>) -> Self {
+        Self {
+            room_id,
+            players: Vec::new(),
+            created_at: Utc::now(),
+            turn_index: None,
+            turn_number: 0,
+            active_turn: None,
+            consecutive_timeouts: HashMap::new(),
+            is_bot_match: false,
+            reports: HashMap::new(),
+            settled: false,
+            revision: 0,
+            config,
+            tenant: TenantManager::current(),
+        }
+    }
+
+    // Players required before the turn loop starts
+    pub fn capacity(&self) -> usize {
+        self.config.rules.room_capacity as usize
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.players.len() >= self.capacity()
+    }
+
+    // When the last human player left, if none is still here
+    pub fn abandoned_since(&self) -> Option<DateTime<Utc>> {
+        let humans: Vec<&RoomPlayer> = self.players.iter().filter(|p| !p.is_bot).collect();
+        if humans.iter().any(|p| p.left_at.is_none()) {
+            return None;
+        }
+        humans.iter().filter_map(|p| p.left_at).max()
+    }
+
+    pub fn to_snapshot(&self) -> RoomSnapshot {
+        let bson_time = |time: DateTime<Utc>| bson::DateTime::from_millis(time.timestamp_millis());
+        RoomSnapshot {
+            id: None,
+            room_id: self.room_id.clone(),
+            players: self.players.iter().map(|p| RoomPlayerSnapshot {
+                player_id: p.player_id.clone(),
+                is_bot: p.is_bot,
+                team: p.team,
+                joined_at: bson_time(p.joined_at),
+            }).collect(),
+            turn_index: self.turn_index.map(|i| i as u32),
+            turn_number: self.turn_number,
+            active_turn: self.active_turn.as_ref().map(|t| ActiveTurnSnapshot {
+                turn_id: t.turn_id.clone(),
+                player_id: t.player_id.clone(),
+                turn_number: t.turn_number,
+                started_at: bson_time(t.started_at),
+                deadline: bson_time(t.deadline),
+                is_bot: t.is_bot,
+            }),
+            consecutive_timeouts: self.consecutive_timeouts.clone(),
+            is_bot_match: self.is_bot_match,
+            config: Some((*self.config).clone()),
+            reports: self.reports.clone(),
+            settled: self.settled,
+            created_at: bson_time(self.created_at),
+            saved_at: bson_time(Utc::now()),
+        }
+    }
+
+    // Human players come back without a socket until they send room:join again,
+    // so they count as having left at restore time. Rooms saved before configs
+    // were stamped get the current rules. Restored in the scope of the tenant
+    // whose database held the snapshot.
+    pub fn from_snapshot(snapshot: RoomSnapshot) -> Self {
+        let chrono_time = |time: bson::DateTime| DateTime::from_timestamp_millis(time.timestamp_millis()).unwrap_or_default();
+        let restored_at = Utc::now();
+        Self {
+            room_id: snapshot.room_id,
+            players: snapshot.players.into_iter().map(|p| RoomPlayer {
+                player_id: p.player_id,
+                socket_id: String::new(),
+                joined_at: chrono_time(p.joined_at),
+                is_bot: p.is_bot,
+                team: p.team,
+                left_at: (!p.is_bot).then_some(restored_at),
+            }).collect(),
+            created_at: chrono_time(snapshot.created_at),
+            turn_index: snapshot.turn_index.map(|i| i as usize),
+            turn_number: snapshot.turn_number,
+            active_turn: snapshot.active_turn.map(|t| ActiveTurn {
+                turn_id: t.turn_id,
+                player_id: t.player_id,
+                turn_number: t.turn_number,
+                started_at: chrono_time(t.started_at),
+                deadline: chrono_time(t.deadline),
+                is_bot: t.is_bot,
+            }),
+            consecutive_timeouts: snapshot.consecutive_timeouts,
+            is_bot_match: snapshot.is_bot_match,
+            reports: snapshot.reports,
+            settled: snapshot.settled,
+            revision: 0,
+            config: snapshot.config.map(Arc::new).unwrap_or_else(|| GameConfigManager::current(DEFAULT_GAME_TYPE)),
+            tenant: TenantManager::current(),
+        }
+    }
+}
+
+pub struct RoomManager;
+
+impl RoomManager {
+    // Add a player to a room of a game type, creating the room if needed.
+    // Room ids are shared by all tenants, so another tenant's room is unavailable.
+    // Returns the updated room.
+    pub async fn join_room(room_id: &str, player_id: &str, socket_id: &str, game_type: &str) -> Result<GameRoom, &'static str> {
+        let mut rooms = ROOMS.write().await;
+        let room = rooms
+            .entry(room_id.to_string())
+            .or_insert_with(|| GameRoom::new(room_id.to_string(), GameConfigManager::current(game_type)));
+        if room.tenant.tenant_id != TenantManager::current().tenant_id {
+            return Err("ROOM_UNAVAILABLE");
+        }
+        if room.config.game_type != game_type {
+            return Err("WRONG_GAME_MODE");
+        }
+
+        if let Some(existing) = room.players.iter_mut().find(|p| p.player_id == player_id) {
+            // Rejoin after reconnect - keep the seat, refresh the socket
+            existing.socket_id = socket_id.to_string();
+            existing.left_at = None;
+            room.revision += 1;
+            info!("🔄 Player {} rejoined room {} (socket: {})", player_id, room_id, socket_id);
+            return Ok(room.clone());
+        }
+
+        if room.is_full() {
+            return Err("ROOM_FULL");
+        }
+
+        // Ad-hoc rooms seat every player on their own team
+        let team = room.players.len() as u8;
+        room.players.push(RoomPlayer::human(player_id, socket_id).with_team(team));
+        room.revision += 1;
+        info!("🚪 Player {} joined room {} ({}/{})", player_id, room_id, room.players.len(), room.capacity());
+        Ok(room.clone())
+    }
+
+    // Create a room for a matchmade group of players
+    pub async fn create_room(room_id: &str, players: Vec<RoomPlayer>, config: Arc<GameConfig>) -> GameRoom {
+        let mut room = GameRoom::new(room_id.to_string(), config);
+        room.is_bot_match = players.iter().any(|p| p.is_bot);
+        room.players = players;
+        info!("🏟️ Created room {} with {} players (bot match: {})", room_id, room.players.len(), room.is_bot_match);
+        ROOMS.write().await.insert(room_id.to_string(), room.clone());
+        room
+    }
+
+    // Run a closure against a room while holding the write lock
+    pub async fn with_room<R>(room_id: &str, f: impl FnOnce(&mut GameRoom) -> R) -> Option<R> {
+        let mut rooms = ROOMS.write().await;
+        rooms.get_mut(room_id).map(|room| {
+            room.revision += 1;
+            f(room)
+        })
+    }
+
+    // A room of the current tenant
+    pub async fn get_room(room_id: &str) -> Option<GameRoom> {
+        ROOMS.read().await.get(room_id)
+            .filter(|room| room.tenant.tenant_id == TenantManager::current().tenant_id)
+            .cloned()
+    }
+
+    // Whether the player holds a seat in the room on this socket
+    pub async fn is_seated(room_id: &str, player_id: &str, socket_id: &str) -> bool {
+        ROOMS.read().await.get(room_id)
+            .filter(|room| room.tenant.tenant_id == TenantManager::current().tenant_id)
+            .is_some_and(|room| room.players.iter().any(|p| p.player_id == player_id && !p.is_bot && p.socket_id == socket_id))
+    }
+
+    // Whether any player holds a seat in the room on this socket
+    pub async fn has_seat_on(room_id: &str, socket_id: &str) -> bool {
+        ROOMS.read().await.get(room_id)
+            .filter(|room| room.tenant.tenant_id == TenantManager::current().tenant_id)
+            .is_some_and(|room| room.players.iter().any(|p| !p.is_bot && p.socket_id == socket_id))
+    }
+
+    // Record a seated human player's reported result. Once every human player
+    // of the room agrees, the room is settled and the agreed outcome of each
+    // of them is returned, exactly once. Without human opponents (a lone human
+    // against bots) nobody confirms a report, so only the forfeit rules settle.
+    pub async fn report_result(room_id: &str, player_id: &str, outcome: GameOutcome) -> Option<SettledMatch> {
+        let mut rooms = ROOMS.write().await;
+        let room = rooms.get_mut(room_id)
+            .filter(|room| room.tenant.tenant_id == TenantManager::current().tenant_id)
+            .filter(|room| !room.settled && room.players.iter().any(|p| p.player_id == player_id && !p.is_bot))?;
+        room.reports.insert(player_id.to_string(), outcome);
+        room.revision += 1;
+        let humans = || room.players.iter().filter(|p| !p.is_bot);
+        if humans().map(|p| p.team).collect::<std::collections::HashSet<u8>>().len() < 2 {
+            return None;
+        }
+        let verdict = MatchSettlement::verdict(humans().map(|p| (p.player_id.as_str(), p.team)), &room.reports);
+        let results: Vec<(String, GameOutcome)> = humans()
+            .filter_map(|p| verdict.outcome(p.team).map(|outcome| (p.player_id.clone(), outcome)))
+            .collect();
+        if results.is_empty() {
+            return None;
+        }
+        room.settled = true;
+        Some(SettledMatch { room_id: room_id.to_string(), results, rated: !room.is_bot_match })
+    }
+
+    // Rooms of the current tenant where a player has a seat
+    pub async fn rooms_of_player(player_id: &str) -> Vec<String> {
+        let tenant_id = &TenantManager::current().tenant_id;
+        let mut room_ids: Vec<String> = ROOMS.read().await.values()
+            .filter(|room| &room.tenant.tenant_id == tenant_id && room.players.iter().any(|p| p.player_id == player_id))
+            .map(|room| room.room_id.clone())
+            .collect();
+        room_ids.sort();
+        room_ids
+    }
+
+    // Mark the seats held by a disconnected socket as left
+    pub async fn socket_left(socket_id: &str) {
+        let now = Utc::now();
+        for room in ROOMS.write().await.values_mut() {
+            for player in room.players.iter_mut().filter(|p| !p.is_bot && p.socket_id == socket_id) {
+                player.left_at = Some(now);
+                room.revision += 1;
+            }
+        }
+    }
+
+    // Rooms of the current tenant whose human players all left before `cutoff`
+    pub async fn abandoned_rooms(cutoff: DateTime<Utc>) -> Vec<String> {
+        let tenant_id = &TenantManager::current().tenant_id;
+        ROOMS.read().await.values()
+            .filter(|room| &room.tenant.tenant_id == tenant_id)
+            .filter(|room| room.abandoned_since().is_some_and(|since| since < cutoff))
+            .map(|room| room.room_id.clone())
+            .collect()
+    }
+
+    // Rooms of the current tenant with a match under way and a human player still there
+    pub async fn running_rooms() -> Vec<GameRoom> {
+        let tenant_id = &TenantManager::current().tenant_id;
+        ROOMS.read().await.values()
+            .filter(|room| &room.tenant.tenant_id == tenant_id)
+            .filter(|room| room.turn_index.is_some() && room.abandoned_since().is_none())
+            .cloned()
+            .collect()
+    }
+
+    // Take a room out for good, unless a player came back since it was found abandoned
+    pub async fn remove_abandoned(room_id: &str, cutoff: DateTime<Utc>) -> Option<GameRoom> {
+        let mut rooms = ROOMS.write().await;
+        let abandoned = rooms.get(room_id).and_then(GameRoom::abandoned_since).is_some_and(|since| since < cutoff);
+        if !abandoned {
+            return None;
+        }
+        rooms.remove(room_id)
+    }
+
+    // Take a room out for good
+    pub async fn remove_room(room_id: &str) -> Option<GameRoom> {
+        ROOMS.write().await.remove(room_id)
+    }
+
+    // Game mode a room is played in
+    pub async fn mode_of(room_id: &str) -> Option<Arc<RegisteredMode>> {
+        ROOMS.read().await.get(room_id).map(|room| GameModeRegistry::for_game_type(&room.config.game_type))
+    }
+
+    // Snapshot of one room with the revision it was taken at
+    pub async fn snapshot(room_id: &str) -> Option<(u64, RoomSnapshot)> {
+        ROOMS.read().await.get(room_id).map(|room| (room.revision, room.to_snapshot()))
+    }
+
+    // Snapshots of the rooms whose revision differs from `saved`, with their tenant
+    pub async fn changed_snapshots(saved: &HashMap<String, u64>) -> Vec<(&'static Tenant, u64, RoomSnapshot)> {
+        ROOMS.read().await.values()
+            .filter(|room| saved.get(&room.room_id) != Some(&room.revision))
+            .map(|room| (room.tenant, room.revision, room.to_snapshot()))
+            .collect()
+    }
+
+    // Put a room recovered from a snapshot back, unless it already exists
+    pub async fn restore_room(room: GameRoom) -> bool {
+        let mut rooms = ROOMS.write().await;
+        if rooms.contains_key(&room.room_id) {
+            return false;
+        }
+        info!("♻️ Restored room {} at turn {} ({} players)", room.room_id, room.turn_number, room.players.len());
+        rooms.insert(room.room_id.clone(), room);
+        true
+    }
+}
@@ -0,0 +1,153 @@
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use socketioxide::SocketIo;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::api::response::ApiResponse;
+use crate::config::CONFIG;
+use crate::database::models::{ArchivedRoom, ForfeitResult, GameOutcome, MatchResult};
+use crate::database::store::DataStore;
+use crate::managers::afk::AfkManager;
+use crate::managers::dealer::DealerManager;
+use crate::managers::escrow::EscrowManager;
+use crate::managers::game_modes::GameModeRegistry;
+use crate::managers::room::{GameRoom, RoomManager};
+use crate::managers::room_snapshots::RoomSnapshotManager;
+use crate::managers::room_state::RoomStateManager;
+use crate::managers::scheduler::Scheduler;
+use crate::managers::settlement::{MatchSettlement, SettledMatch};
+
+// Closes gameplay rooms every human player left. A room is reaped once its
+// last human player has been gone for ROOM_ABANDON_AFTER_SECS (coming back
+// with room:join before then keeps the seat). A match in which no turn was
+// played is aborted without a result. Otherwise it is settled by forfeit:
+// the team of the last human player to leave wins if an opposing team had a
+// human player, who left first; every other team forfeits, and so does a
+// lone human playing bots. Results go to match_results, held stakes to the
+// winners (or back to the players of an aborted match), the room to
+// archived_rooms, and the room's snapshot, dealer and AFK tracking are dropped.
+pub struct RoomReaperManager;
+
+impl RoomReaperManager {
+    pub fn spawn_reaper(io: SocketIo, data_service: Arc<dyn DataStore>) {
+        let period = Duration::from_secs(CONFIG.room_reaper_interval_secs);
+        Scheduler::every("room-reaper", period, move || {
+            let io = io.clone();
+            let data_service = data_service.clone();
+            async move {
+                Self::reap(&io, &*data_service).await;
+                Ok(())
+            }
+        });
+    }
+
+    async fn reap(io: &SocketIo, data_service: &dyn DataStore) {
+        let cutoff = Utc::now() - chrono::Duration::seconds(CONFIG.room_abandon_after_secs);
+        for room_id in RoomManager::abandoned_rooms(cutoff).await {
+            // A player may have rejoined since the rooms were listed
+            if let Some(room) = RoomManager::remove_abandoned(&room_id, cutoff).await {
+                let last_left_at = room.abandoned_since().unwrap_or_else(Utc::now);
+                // No turn played: nobody won or lost
+                let (end_reason, results) = match room.turn_number {
+                    0 => ("aborted", Vec::new()),
+                    _ => ("abandoned", Self::settle(&room)),
+                };
+                info!("🧹 Closing room {} ({}, {} turns, last player left {})", room.room_id, end_reason, room.turn_number, last_left_at.to_rfc3339());
+                Self::close(io, data_service, room, end_reason, results).await;
+            }
+        }
+    }
+
+    // How each human player of an abandoned match ends up
+    fn settle(room: &GameRoom) -> Vec<ForfeitResult> {
+        let humans: Vec<_> = room.players.iter().filter(|p| !p.is_bot).collect();
+        let last_team = humans.iter().max_by_key(|p| p.left_at).map(|p| p.team);
+        let human_teams: BTreeSet<u8> = humans.iter().map(|p| p.team).collect();
+        let winner = last_team.filter(|_| human_teams.len() > 1);
+        humans.iter().map(|p| {
+            let won = winner == Some(p.team);
+            ForfeitResult {
+                player_id: p.player_id.clone(),
+                team: p.team,
+                outcome: if won { GameOutcome::Win } else { GameOutcome::Loss },
+                forfeited: !won,
+            }
+        }).collect()
+    }
+
+    // Settle and archive a room already taken out of RoomManager. Stakes held
+    // for it go to the winners in `results`, or back to the players without any.
+    pub async fn close(io: &SocketIo, data_service: &dyn DataStore, room: GameRoom, end_reason: &str, results: Vec<ForfeitResult>) {
+        let last_left_at = room.abandoned_since().unwrap_or_else(Utc::now);
+        let winners: Vec<&str> = results.iter().filter(|r| r.outcome == GameOutcome::Win).map(|r| r.player_id.as_str()).collect();
+        EscrowManager::room_closed(data_service, &room.room_id, &winners).await;
+        // Forfeits settle the match unless the players already agreed on its result
+        if !room.settled && !results.is_empty() {
+            let settled = SettledMatch {
+                room_id: room.room_id.clone(),
+                results: results.iter().map(|r| (r.player_id.clone(), r.outcome)).collect(),
+                rated: !room.is_bot_match,
+            };
+            MatchSettlement::settled(data_service, &settled).await;
+        }
+
+        for result in &results {
+            let record = MatchResult {
+                id: None,
+                room_id: room.room_id.clone(),
+                user_id: result.player_id.clone(),
+                outcome: result.outcome,
+                reported_at: bson::DateTime::now(),
+            };
+            if let Err(e) = data_service.record_match_result(record).await {
+                warn!("⚠️ Failed to record the forfeit result of room {} for {}: {}", room.room_id, result.player_id, e);
+            }
+        }
+
+        // Spectators still watching learn the match is over
+        let closed = ApiResponse::success("room:closed", json!({
+            "room_id": room.room_id,
+            "reason": end_reason,
+            "results": results.iter().map(|r| json!({
+                "player_id": r.player_id,
+                "team": r.team,
+                "outcome": r.outcome,
+                "forfeited": r.forfeited,
+            })).collect::<Vec<_>>(),
+        }));
+        if let Some(ns) = io.of(GameModeRegistry::for_game_type(&room.config.game_type).namespace.as_str()) {
+            if let Err(e) = ns.to(room.room_id.clone()).emit("room:closed", closed) {
+                warn!("⚠️ Failed to broadcast room:closed to room {}: {}", room.room_id, e);
+            }
+        }
+
+        DealerManager::close_room(data_service, &room.room_id).await;
+        RoomSnapshotManager::forget(&room.room_id);
+        RoomStateManager::forget(&room.room_id);
+        AfkManager::forget(&room.room_id);
+        let snapshot = room.to_snapshot();
+        let archived = ArchivedRoom {
+            id: None,
+            room_id: room.room_id.clone(),
+            game_type: room.config.game_type.clone(),
+            players: snapshot.players,
+            turn_number: room.turn_number,
+            is_bot_match: room.is_bot_match,
+            end_reason: end_reason.to_string(),
+            results,
+            created_at: snapshot.created_at,
+            last_left_at: Self::bson_time(last_left_at),
+            archived_at: bson::DateTime::now(),
+        };
+        if let Err(e) = data_service.archive_room(archived).await {
+            warn!("⚠️ Failed to archive room {}: {}", room.room_id, e);
+        }
+    }
+
+    fn bson_time(time: DateTime<Utc>) -> bson::DateTime {
+        bson::DateTime::from_millis(time.timestamp_millis())
+    }
+}
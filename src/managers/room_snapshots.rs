@@ -0,0 +1,119 @@
+use once_cell::sync::Lazy;
+use socketioxide::SocketIo;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::config::CONFIG;
+use crate::database::health::DatabaseHealth;
+use crate::database::models::RoomSnapshot;
+use crate::database::store::DataStore;
+use crate::managers::room::{GameRoom, RoomManager};
+use crate::managers::tenant::TenantManager;
+use crate::managers::turn_timer::TurnTimerManager;
+
+// Last saved revision per room id
+static SAVED: Lazy<Mutex<HashMap<String, u64>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// Saves gameplay room state to room_snapshots every ROOM_SNAPSHOT_INTERVAL_SECS
+// (only rooms that changed) and after every ROOM_SNAPSHOT_EVERY_TURNS turns.
+// On startup, rooms saved within ROOM_RECOVERY_MAX_AGE_SECS are put back and
+// their turn loops restarted, so players who reconnect and send room:join get
+// their seat in the same match.
+pub struct RoomSnapshotManager;
+
+impl RoomSnapshotManager {
+    fn enabled() -> bool {
+        CONFIG.room_snapshot_interval_secs > 0
+    }
+
+    pub fn spawn_snapshotter(data_service: Arc<dyn DataStore>) {
+        if !Self::enabled() {
+            return;
+        }
+        let period = Duration::from_secs(CONFIG.room_snapshot_interval_secs);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                // Rooms stay marked as changed and are saved once MongoDB is back
+                if !DatabaseHealth::is_healthy() {
+                    continue;
+                }
+                let saved = SAVED.lock().unwrap_or_else(|e| e.into_inner()).clone();
+                for (tenant, revision, snapshot) in RoomManager::changed_snapshots(&saved).await {
+                    TenantManager::scope(tenant, Self::save(&*data_service, revision, snapshot)).await;
+                }
+            }
+        });
+        info!("📸 Room snapshots every {:?}", period);
+    }
+
+    // Called when a turn starts; saves the room every ROOM_SNAPSHOT_EVERY_TURNS turns
+    pub fn turn_started(data_service: &Arc<dyn DataStore>, room_id: &str, turn_number: u32) {
+        let every = CONFIG.room_snapshot_every_turns;
+        if !Self::enabled() || every == 0 || !turn_number.is_multiple_of(every) || !DatabaseHealth::is_healthy() {
+            return;
+        }
+        let data_service = data_service.clone();
+        let room_id = room_id.to_string();
+        TenantManager::spawn(async move {
+            if let Some((revision, snapshot)) = RoomManager::snapshot(&room_id).await {
+                Self::save(&*data_service, revision, snapshot).await;
+            }
+        });
+    }
+
+    async fn save(data_service: &dyn DataStore, revision: u64, snapshot: RoomSnapshot) {
+        let room_id = snapshot.room_id.clone();
+        match data_service.save_room_snapshot(snapshot).await {
+            Ok(()) => {
+                SAVED.lock().unwrap_or_else(|e| e.into_inner()).insert(room_id, revision);
+            }
+            Err(e) => warn!("⚠️ Failed to snapshot room {}: {}", room_id, e),
+        }
+    }
+
+    // A closed room is no longer tracked
+    pub fn forget(room_id: &str) {
+        SAVED.lock().unwrap_or_else(|e| e.into_inner()).remove(room_id);
+    }
+
+    // Put back rooms saved before the last shutdown or crash, for every
+    // tenant. Runs before the server starts accepting connections.
+    pub async fn restore(io: &SocketIo, data_service: Arc<dyn DataStore>) {
+        if !Self::enabled() {
+            return;
+        }
+        for tenant in TenantManager::tenants() {
+            TenantManager::scope(tenant, Self::restore_tenant(io, data_service.clone())).await;
+        }
+    }
+
+    async fn restore_tenant(io: &SocketIo, data_service: Arc<dyn DataStore>) {
+        let since = chrono::Utc::now() - chrono::Duration::seconds(CONFIG.room_recovery_max_age_secs);
+        let snapshots = match data_service.load_room_snapshots(since).await {
+            Ok(snapshots) => snapshots,
+            Err(e) => {
+                warn!("⚠️ Failed to load room snapshots - no rooms restored: {}", e);
+                return;
+            }
+        };
+
+        let mut restored = 0;
+        for snapshot in snapshots {
+            let room = GameRoom::from_snapshot(snapshot);
+            let room_id = room.room_id.clone();
+            if !RoomManager::restore_room(room).await {
+                continue;
+            }
+            SAVED.lock().unwrap_or_else(|e| e.into_inner()).insert(room_id.clone(), 0);
+            TurnTimerManager::resume_turn(io.clone(), data_service.clone(), &room_id).await;
+            restored += 1;
+        }
+        if restored > 0 {
+            info!("♻️ Restored {} rooms of tenant {} from snapshots - players rejoin with room:join", restored, TenantManager::current().tenant_id);
+        }
+    }
+}
@@ -0,0 +1,462 @@
+use once_cell::sync::Lazy;
+use serde_json::{json, Map, Value};
+use socketioxide::{SocketIo, extract::{Data, SocketRef}};
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tracing::warn;
+
+use crate::api::response::{ApiError, ApiResponse};
+use crate::config::CONFIG;
+use crate::managers::afk::AfkManager;
+use crate::managers::correlation::Correlation;
+use crate::managers::room::RoomManager;
+use crate::managers::spectators::SpectatorManager;
+use crate::managers::tenant::TenantManager;
+use crate::managers::validation::ValidationManager;
+
+// Largest delta one room:update may carry, as JSON
+pub const MAX_DELTA_BYTES: usize = 16 * 1024;
+// Limits on subscribe: paths a socket may follow in one room, and the length of each
+pub const MAX_SUBSCRIPTIONS: usize = 32;
+pub const MAX_PATH_LENGTH: usize = 128;
+// Broadcast states kept to diff against; a client whose last ack is older gets a keyframe
+const STATE_HISTORY: usize = 32;
+
+// Updates of a room waiting for the end of its window
+struct Pending {
+    updates: u64,
+    players: BTreeSet<String>,
+}
+
+#[derive(Default)]
+struct RoomState {
+    state: Map<String, Value>,                      // Every update applied, sent or not
+    seq: u64,                                       // Number of the last room:state sent
+    history: VecDeque<(u64, Map<String, Value>)>,   // The state as sent at each recent seq, oldest first
+    acked: HashMap<String, u64>,                    // socket_id -> last seq the client acknowledged
+    subscriptions: HashMap<String, BTreeSet<String>>,   // socket_id -> the only paths it is sent
+    pending: Option<Pending>,
+}
+
+impl RoomState {
+    fn sent_at(&self, seq: u64) -> Option<&Map<String, Value>> {
+        self.history.iter().find(|(at, _)| *at == seq).map(|(_, state)| state)
+    }
+}
+
+// State by room_id
+static ROOMS: Lazy<Mutex<HashMap<String, RoomState>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static UPDATES_RECEIVED: AtomicU64 = AtomicU64::new(0);
+static STATES_SENT: AtomicU64 = AtomicU64::new(0);
+// room:state messages and their bytes, deltas first, then keyframes
+static MESSAGES_SENT: [AtomicU64; 2] = [AtomicU64::new(0), AtomicU64::new(0)];
+static BYTES_SENT: [AtomicU64; 2] = [AtomicU64::new(0), AtomicU64::new(0)];
+
+// High-frequency game state for fast games. Seated players send room:update
+// with a delta in JSON merge patch form (RFC 7396: nested objects merge, a
+// null removes the key), applied to the room's state at once. Rather than one
+// broadcast per update, the updates a room gets within ROOM_UPDATE_WINDOW_MS
+// of the first go out together as room:state, numbered by `seq`; a window of
+// 0 sends every update on its own. Each client gets the merge patch from the
+// state it last acknowledged with room:state_ack to the new one. A client
+// without a recent acknowledgement gets a keyframe with the whole state, as
+// does everyone every ROOM_STATE_KEYFRAME_EVERY broadcasts and a client that
+// asks with room:state_sync. In big rooms (tournaments, lobbies) a client can
+// subscribe to the paths of the state its view shows, such as its table or
+// bracket, and is then sent only those. /metrics reports the share of updates
+// merged away and the bytes sent as deltas and as keyframes.
+pub struct RoomStateManager;
+
+impl RoomStateManager {
+    fn emit_room_error(s: &SocketRef, error: ApiError) {
+        let _ = s.emit("room:error", error.on_event("room:error").for_socket(s.id));
+    }
+
+    // Apply a merge patch to `state`
+    fn apply(state: &mut Map<String, Value>, patch: &Map<String, Value>) {
+        for (key, value) in patch {
+            match value {
+                Value::Null => {
+                    state.remove(key);
+                }
+                Value::Object(nested) => {
+                    let entry = state.entry(key.clone()).or_insert_with(|| Value::Object(Map::new()));
+                    if !entry.is_object() {
+                        *entry = Value::Object(Map::new());
+                    }
+                    if let Value::Object(inner) = entry {
+                        Self::apply(inner, nested);
+                    }
+                }
+                value => {
+                    state.insert(key.clone(), value.clone());
+                }
+            }
+        }
+    }
+
+    // The merge patch that turns `from` into `to`
+    fn diff(from: &Map<String, Value>, to: &Map<String, Value>) -> Map<String, Value> {
+        let mut patch: Map<String, Value> = from.keys()
+            .filter(|key| !to.contains_key(*key))
+            .map(|key| (key.clone(), Value::Null))
+            .collect();
+        for (key, value) in to {
+            match (from.get(key), value) {
+                (Some(old), new) if old == new => {}
+                (Some(Value::Object(old)), Value::Object(new)) => {
+                    patch.insert(key.clone(), Value::Object(Self::diff(old, new)));
+                }
+                (_, new) => {
+                    patch.insert(key.clone(), new.clone());
+                }
+            }
+        }
+        patch
+    }
+
+    // The parts of `state` under the subscribed paths (dot-separated keys), or
+    // all of it without subscriptions
+    fn view<'a>(state: &'a Map<String, Value>, paths: Option<&BTreeSet<String>>) -> Cow<'a, Map<String, Value>> {
+        let Some(paths) = paths else { return Cow::Borrowed(state) };
+        let mut view = Map::new();
+        'paths: for path in paths {
+            let keys: Vec<&str> = path.split('.').collect();
+            let Some((last, parents)) = keys.split_last() else { continue };
+            let mut source = state;
+            for key in parents {
+                let Some(Value::Object(inner)) = source.get(*key) else { continue 'paths };
+                source = inner;
+            }
+            let Some(value) = source.get(*last) else { continue };
+            let mut target = &mut view;
+            for key in parents {
+                let Value::Object(inner) = target.entry(*key).or_insert_with(|| Value::Object(Map::new())) else { continue 'paths };
+                target = inner;
+            }
+            target.insert(last.to_string(), value.clone());
+        }
+        Cow::Owned(view)
+    }
+
+    fn update(io: &SocketIo, namespace: &str, room_id: &str, player_id: &str, delta: Map<String, Value>) {
+        UPDATES_RECEIVED.fetch_add(1, Ordering::Relaxed);
+        let opens_window = {
+            let mut rooms = ROOMS.lock().unwrap_or_else(|e| e.into_inner());
+            let room = rooms.entry(room_id.to_string()).or_default();
+            Self::apply(&mut room.state, &delta);
+            match &mut room.pending {
+                Some(pending) => {
+                    pending.updates += 1;
+                    pending.players.insert(player_id.to_string());
+                    false
+                }
+                None => {
+                    room.pending = Some(Pending { updates: 1, players: BTreeSet::from([player_id.to_string()]) });
+                    true
+                }
+            }
+        };
+        if !opens_window {
+            return;
+        }
+        if CONFIG.room_update_window_ms == 0 {
+            Self::flush(io, namespace, room_id);
+            return;
+        }
+        let (io, namespace, room_id) = (io.clone(), namespace.to_string(), room_id.to_string());
+        TenantManager::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(CONFIG.room_update_window_ms)).await;
+            Self::flush(&io, &namespace, &room_id);
+        });
+    }
+
+    // room:state as of `seq`: a delta from `base`, or a keyframe without one
+    fn message(room_id: &str, seq: u64, state: &Map<String, Value>, base: Option<(u64, &Map<String, Value>)>, pending: Option<&Pending>) -> ApiResponse {
+        let mut message = json!({
+            "room_id": room_id,
+            "seq": seq,
+            "keyframe": base.is_none(),
+            "updates": pending.map_or(0, |p| p.updates),
+            "players": pending.map_or(&BTreeSet::new(), |p| &p.players),
+        });
+        match base {
+            Some((base_seq, base_state)) => {
+                message["base_seq"] = json!(base_seq);
+                message["delta"] = Value::Object(Self::diff(base_state, state));
+            }
+            None => message["state"] = Value::Object(state.clone()),
+        }
+        let keyframe = usize::from(base.is_none());
+        MESSAGES_SENT[keyframe].fetch_add(1, Ordering::Relaxed);
+        BYTES_SENT[keyframe].fetch_add(serde_json::to_vec(&message).map_or(0, |bytes| bytes.len() as u64), Ordering::Relaxed);
+        ApiResponse::success("room:state", message)
+    }
+
+    // Send every socket in the room what changed since the state it acknowledged
+    fn flush(io: &SocketIo, namespace: &str, room_id: &str) {
+        let Some(ns) = io.of(namespace) else { return };
+        let sockets = ns.to(room_id.to_string()).sockets().unwrap_or_default();
+        let sends: Vec<(SocketRef, ApiResponse)> = {
+            let mut rooms = ROOMS.lock().unwrap_or_else(|e| e.into_inner());
+            let Some(room) = rooms.get_mut(room_id) else { return };
+            let Some(pending) = room.pending.take() else { return };
+            room.seq += 1;
+            room.history.push_back((room.seq, room.state.clone()));
+            if room.history.len() > STATE_HISTORY {
+                room.history.pop_front();
+            }
+            STATES_SENT.fetch_add(1, Ordering::Relaxed);
+
+            let keyframe_due = CONFIG.room_state_keyframe_every > 0 && room.seq % CONFIG.room_state_keyframe_every == 0;
+            // Clients that acknowledged the same seq and follow the same paths get the same message
+            let mut messages: HashMap<(Option<u64>, Option<&BTreeSet<String>>), ApiResponse> = HashMap::new();
+            sockets.into_iter().map(|socket| {
+                let socket_id = socket.id.to_string();
+                let base_seq = room.acked.get(&socket_id).copied()
+                    .filter(|seq| !keyframe_due && room.sent_at(*seq).is_some());
+                let paths = room.subscriptions.get(&socket_id);
+                let message = messages.entry((base_seq, paths)).or_insert_with(|| {
+                    let base = base_seq.and_then(|seq| room.sent_at(seq).map(|state| (seq, Self::view(state, paths))));
+                    let base = base.as_ref().map(|(seq, state)| (*seq, state.as_ref()));
+                    Self::message(room_id, room.seq, &Self::view(&room.state, paths), base, Some(&pending))
+                });
+                (socket, message.clone())
+            }).collect()
+        };
+        for (socket, message) in sends {
+            if let Err(e) = socket.emit("room:state", message) {
+                warn!("⚠️ Failed to send room:state of room {} to socket {}: {}", room_id, socket.id, e);
+            }
+        }
+    }
+
+    // A client has applied `seq`; its next deltas are made against it
+    fn acknowledge(room_id: &str, socket_id: &str, seq: u64) -> bool {
+        let mut rooms = ROOMS.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(room) = rooms.get_mut(room_id).filter(|room| seq <= room.seq) else { return false };
+        let acked = room.acked.entry(socket_id.to_string()).or_default();
+        *acked = (*acked).max(seq);
+        true
+    }
+
+    // Keyframe of the room's state as last sent, in the socket's view
+    fn keyframe(room_id: &str, socket_id: &str) -> ApiResponse {
+        let rooms = ROOMS.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(room) = rooms.get(room_id) else {
+            return Self::message(room_id, 0, &Map::new(), None, None);
+        };
+        let paths = room.subscriptions.get(socket_id);
+        match room.history.back() {
+            Some((seq, state)) => Self::message(room_id, *seq, &Self::view(state, paths), None, None),
+            None => Self::message(room_id, 0, &Map::new(), None, None),
+        }
+    }
+
+    // Add paths to the socket's subscriptions in a room, or take them out
+    // (all of them when `remove` is None and nothing is added). Its deltas were made for the old view, so they stop until it
+    // acknowledges the keyframe that follows. Err(paths) when subscribing
+    // would take it past MAX_SUBSCRIPTIONS.
+    fn change_subscriptions(room_id: &str, socket_id: &str, add: &[String], remove: Option<&[String]>) -> Result<Vec<String>, usize> {
+        let mut rooms = ROOMS.lock().unwrap_or_else(|e| e.into_inner());
+        let room = rooms.entry(room_id.to_string()).or_default();
+        let mut paths = room.subscriptions.get(socket_id).cloned().unwrap_or_default();
+        paths.extend(add.iter().cloned());
+        match remove {
+            Some(remove) => paths.retain(|path| !remove.contains(path)),
+            None if add.is_empty() => paths.clear(),
+            None => {}
+        }
+        if paths.len() > MAX_SUBSCRIPTIONS {
+            return Err(paths.len());
+        }
+        room.acked.remove(socket_id);
+        let list = paths.iter().cloned().collect();
+        if paths.is_empty() {
+            room.subscriptions.remove(socket_id);
+        } else {
+            room.subscriptions.insert(socket_id.to_string(), paths);
+        }
+        Ok(list)
+    }
+
+    // Whether players of the room send it real-time updates
+    pub fn has_state(room_id: &str) -> bool {
+        ROOMS.lock().unwrap_or_else(|e| e.into_inner()).get(room_id)
+            .is_some_and(|room| room.seq > 0 || room.pending.is_some())
+    }
+
+    // Drop a closed room's state, including updates still waiting
+    pub fn forget(room_id: &str) {
+        ROOMS.lock().unwrap_or_else(|e| e.into_inner()).remove(room_id);
+    }
+
+    // Forget a disconnected socket's acknowledgements and subscriptions
+    pub fn remove_socket(socket_id: &str) {
+        for room in ROOMS.lock().unwrap_or_else(|e| e.into_inner()).values_mut() {
+            room.acked.remove(socket_id);
+            room.subscriptions.remove(socket_id);
+        }
+    }
+
+    // Prometheus text format: updates received, room:state broadcasts, the
+    // share of updates merged into another one's broadcast, and the messages
+    // and bytes sent as deltas and as keyframes
+    pub fn render() -> String {
+        let received = UPDATES_RECEIVED.load(Ordering::Relaxed);
+        let sent = STATES_SENT.load(Ordering::Relaxed);
+        if received == 0 {
+            return String::new();
+        }
+        let merged_ratio = received.saturating_sub(sent) as f64 / received as f64;
+        let mut output = format!(
+            "# TYPE room_updates_received_total counter\nroom_updates_received_total {}\n\
+             # TYPE room_state_broadcasts_total counter\nroom_state_broadcasts_total {}\n\
+             # TYPE room_updates_merged_ratio gauge\nroom_updates_merged_ratio {}\n",
+            received, sent, merged_ratio,
+        );
+        output.push_str("# TYPE room_state_messages_total counter\n");
+        for (kind, count) in ["delta", "keyframe"].iter().zip(&MESSAGES_SENT) {
+            output.push_str(&format!("room_state_messages_total{{kind=\"{}\"}} {}\n", kind, count.load(Ordering::Relaxed)));
+        }
+        output.push_str("# TYPE room_state_bytes_total counter\n");
+        for (kind, bytes) in ["delta", "keyframe"].iter().zip(&BYTES_SENT) {
+            output.push_str(&format!("room_state_bytes_total{{kind=\"{}\"}} {}\n", kind, bytes.load(Ordering::Relaxed)));
+        }
+        output
+    }
+
+    // Register state updates on a gameplay namespace socket:
+    //   room:update     { room_id, player_id, delta } -> room:state to the room once the window closes
+    //   room:state_ack  { room_id, seq }              -> nothing; later room:state deltas are made against seq
+    //   room:state_sync { room_id }                   -> room:state keyframe to the sender
+    //   subscribe       { room_id, paths }            -> subscribed and a room:state keyframe to the sender
+    //   unsubscribe     { room_id, paths? }           -> subscribed and a room:state keyframe to the sender
+    pub fn register_state_events(socket: &SocketRef, io: SocketIo) {
+        socket.on("room:update", move |s: SocketRef, Data::<Value>(data)| {
+            let io = io.clone();
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope("room:update", s.id, request_id, async move {
+                if let Err(error_details) = ValidationManager::validate_room_update_data(&data) {
+                    Self::emit_room_error(&s, ApiError::from(error_details));
+                    return;
+                }
+                let room_id = data["room_id"].as_str().unwrap_or_default();
+                let player_id = data["player_id"].as_str().unwrap_or_default();
+                if !RoomManager::is_seated(room_id, player_id, &s.id.to_string()).await {
+                    let error = ApiError::new("NOT_IN_ROOM", "ROOM_ERROR", "room_id", "Only players seated in the room can update its state")
+                        .with_details(json!({"room_id": room_id, "player_id": player_id}));
+                    Self::emit_room_error(&s, error);
+                    return;
+                }
+                let Some(Value::Object(delta)) = data.get("delta").cloned() else { return };
+                AfkManager::activity(room_id, player_id);
+                Self::update(&io, s.ns(), room_id, player_id, delta);
+            })
+        });
+
+        socket.on("room:state_ack", |s: SocketRef, Data::<Value>(data)| {
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope("room:state_ack", s.id, request_id, async move {
+                if let Err(error_details) = ValidationManager::validate_room_state_ack_data(&data) {
+                    Self::emit_room_error(&s, ApiError::from(error_details));
+                    return;
+                }
+                let room_id = data["room_id"].as_str().unwrap_or_default();
+                let seq = data["seq"].as_u64().unwrap_or_default();
+                if !Self::acknowledge(room_id, &s.id.to_string(), seq) {
+                    let error = ApiError::new("UNKNOWN_STATE", "ROOM_ERROR", "seq", "The room has not sent this state")
+                        .with_details(json!({"room_id": room_id, "seq": seq}));
+                    Self::emit_room_error(&s, error);
+                }
+            })
+        });
+
+        socket.on("room:state_sync", |s: SocketRef, Data::<Value>(data)| {
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope("room:state_sync", s.id, request_id, async move {
+                if let Err(error_details) = ValidationManager::validate_room_state_sync_data(&data) {
+                    Self::emit_room_error(&s, ApiError::from(error_details));
+                    return;
+                }
+                let room_id = data["room_id"].as_str().unwrap_or_default();
+                if !Self::follows(&s, room_id).await {
+                    return;
+                }
+                Self::send_keyframe(&s, room_id);
+            })
+        });
+
+        socket.on("subscribe", |s: SocketRef, Data::<Value>(data)| {
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope("subscribe", s.id, request_id, async move {
+                if let Err(error_details) = ValidationManager::validate_subscribe_data(&data, true) {
+                    Self::emit_room_error(&s, ApiError::from(error_details));
+                    return;
+                }
+                let paths: Vec<String> = serde_json::from_value(data["paths"].clone()).unwrap_or_default();
+                Self::subscriptions_changed(&s, data["room_id"].as_str().unwrap_or_default(), &paths, None).await;
+            })
+        });
+
+        socket.on("unsubscribe", |s: SocketRef, Data::<Value>(data)| {
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope("unsubscribe", s.id, request_id, async move {
+                if let Err(error_details) = ValidationManager::validate_subscribe_data(&data, false) {
+                    Self::emit_room_error(&s, ApiError::from(error_details));
+                    return;
+                }
+                // Without paths every subscription goes, and the whole state is sent again
+                let paths: Option<Vec<String>> = serde_json::from_value(data["paths"].clone()).unwrap_or_default();
+                Self::subscriptions_changed(&s, data["room_id"].as_str().unwrap_or_default(), &[], paths.as_deref()).await;
+            })
+        });
+    }
+
+    fn send_keyframe(s: &SocketRef, room_id: &str) {
+        if let Err(e) = s.emit("room:state", Self::keyframe(room_id, &s.id.to_string()).for_socket(s.id)) {
+            warn!("⚠️ Failed to send a room:state keyframe of room {} to socket {}: {}", room_id, s.id, e);
+        }
+    }
+
+    // Whether the socket may be sent the room's state: it holds a seat in the
+    // room or spectates it. Answers room:error otherwise.
+    async fn follows(s: &SocketRef, room_id: &str) -> bool {
+        let socket_id = s.id.to_string();
+        let error = if RoomManager::get_room(room_id).await.is_none() {
+            ApiError::new("ROOM_NOT_FOUND", "ROOM_ERROR", "room_id", "No such room")
+        } else if RoomManager::has_seat_on(room_id, &socket_id).await || SpectatorManager::is_watching(room_id, &socket_id).await {
+            return true;
+        } else {
+            ApiError::new("NOT_IN_ROOM", "ROOM_ERROR", "room_id", "Only players seated in the room and its spectators can follow its state")
+        };
+        Self::emit_room_error(s, error.with_details(json!({"room_id": room_id})));
+        false
+    }
+
+    async fn subscriptions_changed(s: &SocketRef, room_id: &str, add: &[String], remove: Option<&[String]>) {
+        if !Self::follows(s, room_id).await {
+            return;
+        }
+        let paths = match Self::change_subscriptions(room_id, &s.id.to_string(), add, remove) {
+            Ok(paths) => paths,
+            Err(count) => {
+                let error = ApiError::new("TOO_MANY_SUBSCRIPTIONS", "ROOM_ERROR", "paths", &format!("A socket can follow at most {} paths of a room", MAX_SUBSCRIPTIONS))
+                    .with_details(json!({"room_id": room_id, "max_paths": MAX_SUBSCRIPTIONS, "requested_paths": count}));
+                Self::emit_room_error(s, error);
+                return;
+            }
+        };
+        let subscribed = ApiResponse::success("subscribed", json!({
+            "room_id": room_id,
+            "paths": paths,
+        })).for_socket(s.id);
+        if let Err(e) = s.emit("subscribed", subscribed) {
+            warn!("⚠️ Failed to emit subscribed to socket {}: {}", s.id, e);
+        }
+        Self::send_keyframe(s, room_id);
+    }
+}
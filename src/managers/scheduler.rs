@@ -0,0 +1,78 @@
+use chrono::{DateTime, Utc};
+use std::future::Future;
+use tracing::{info, warn};
+
+use crate::managers::tenant::TenantManager;
+
+// Delay before a failed job runs again
+const RETRY_SECS: i64 = 60;
+
+type JobResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+// Recurring background jobs. Each job runs in its own task, right away and
+// then on its schedule; a run never overlaps the previous one. A failed run is
+// logged and retried after RETRY_SECS (or at the next scheduled time, if sooner).
+// Every run does the job once for each tenant, in that tenant's scope, except
+// for server-wide jobs.
+pub struct Scheduler;
+
+impl Scheduler {
+    // Run `job` every `period`
+    pub fn every<F, Fut>(name: &'static str, period: std::time::Duration, job: F)
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = JobResult> + Send,
+    {
+        let period = chrono::Duration::from_std(period).unwrap_or(chrono::Duration::seconds(RETRY_SECS));
+        Self::at(name, move |last_run| last_run + period, job);
+    }
+
+    // Run `job` once per run, outside any tenant scope, every `period`. For
+    // jobs about the server itself rather than a tenant's data.
+    pub fn every_server_wide<F, Fut>(name: &'static str, period: std::time::Duration, job: F)
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = JobResult> + Send,
+    {
+        let period = chrono::Duration::from_std(period).unwrap_or(chrono::Duration::seconds(RETRY_SECS));
+        Self::schedule(name, move |last_run| last_run + period, false, job);
+    }
+
+    // Run `job` at the times returned by `next_run`, given the start of the previous run
+    pub fn at<N, F, Fut>(name: &'static str, next_run: N, job: F)
+    where
+        N: Fn(DateTime<Utc>) -> DateTime<Utc> + Send + 'static,
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = JobResult> + Send,
+    {
+        Self::schedule(name, next_run, true, job);
+    }
+
+    fn schedule<N, F, Fut>(name: &'static str, next_run: N, per_tenant: bool, job: F)
+    where
+        N: Fn(DateTime<Utc>) -> DateTime<Utc> + Send + 'static,
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = JobResult> + Send,
+    {
+        info!("⏰ Scheduled job {}", name);
+        tokio::spawn(async move {
+            loop {
+                let started = Utc::now();
+                let mut next = next_run(started);
+                if per_tenant {
+                    for tenant in TenantManager::tenants() {
+                        if let Err(e) = TenantManager::scope(tenant, job()).await {
+                            warn!("⚠️ Scheduled job {} failed for tenant {} - retrying in {}s: {}", name, tenant.tenant_id, RETRY_SECS, e);
+                            next = next.min(Utc::now() + chrono::Duration::seconds(RETRY_SECS));
+                        }
+                    }
+                } else if let Err(e) = job().await {
+                    warn!("⚠️ Scheduled job {} failed - retrying in {}s: {}", name, RETRY_SECS, e);
+                    next = next.min(Utc::now() + chrono::Duration::seconds(RETRY_SECS));
+                }
+                let wait = (next - Utc::now()).to_std().unwrap_or_default();
+                tokio::time::sleep(wait).await;
+            }
+        });
+    }
+}
@@ -0,0 +1,251 @@
+use socketioxide::extract::SocketRef;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn, error};
+
+use crate::api::response::{ApiError, ApiResponse};
+use crate::config::CONFIG;
+use crate::database::models::{DeepLink, GameOutcome, Season, SeasonRating, SeasonStatus};
+use crate::database::store::DataStore;
+use crate::managers::auth_guard::AuthGuard;
+use crate::managers::chaos::FaultInjector;
+use crate::managers::error_responder::ErrorResponder;
+use crate::managers::notifications::{NotificationCategory, NotificationManager};
+use crate::managers::risk::RiskManager;
+use crate::managers::scheduler::Scheduler;
+use crate::managers::validation::ValidationManager;
+
+// Season rating change per reported game result
+const WIN_POINTS: i64 = 25;
+const LOSS_POINTS: i64 = -20;
+const DRAW_POINTS: i64 = 5;
+
+// Wallet ledger reason for end-of-season rewards
+const REWARD_REASON: &str = "season_reward";
+
+pub const DEFAULT_LEADERBOARD_LIMIT: i64 = 20;
+pub const MAX_LEADERBOARD_LIMIT: i64 = 100;
+pub const MAX_LEADERBOARD_OFFSET: i64 = 10_000;
+
+// Ranked seasons. Results reported with progress:update move the player's
+// rating in the active season. The rollover job starts scheduled seasons
+// (seeding ratings from the previous season with a soft reset) and, once a
+// season ends, pays its rank rewards into wallets and notifies the winners.
+pub struct SeasonManager;
+
+impl SeasonManager {
+    fn rating_change(outcome: GameOutcome) -> i64 {
+        match outcome {
+            GameOutcome::Win => WIN_POINTS,
+            GameOutcome::Loss => LOSS_POINTS,
+            GameOutcome::Draw => DRAW_POINTS,
+        }
+    }
+
+    pub fn spawn_rollover(data_service: Arc<dyn DataStore>) {
+        let period = Duration::from_secs(CONFIG.season_check_interval_secs);
+        Scheduler::every("season-rollover", period, move || {
+            let data_service = data_service.clone();
+            async move { Self::rollover(&*data_service).await }
+        });
+    }
+
+    // End seasons past end_at, then start the next scheduled one if none is active.
+    // Every step is a status compare-and-set, so instances never repeat each other's work.
+    async fn rollover(data_service: &dyn DataStore) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let now = bson::DateTime::now();
+        for season in data_service.seasons_with_status(SeasonStatus::Active).await? {
+            if season.end_at <= now && data_service.transition_season(&season.season_id, SeasonStatus::Active, SeasonStatus::Finalizing).await? {
+                info!("🏁 Season {} ({}) ended", season.name, season.season_id);
+            }
+        }
+
+        // Includes seasons left finalizing by a crash; paying out again is safe
+        for season in data_service.seasons_with_status(SeasonStatus::Finalizing).await? {
+            Self::pay_rewards(data_service, &season).await?;
+            if data_service.transition_season(&season.season_id, SeasonStatus::Finalizing, SeasonStatus::Ended).await? {
+                info!("🏆 Season {} rewards paid", season.name);
+            }
+        }
+
+        if !data_service.seasons_with_status(SeasonStatus::Active).await?.is_empty() {
+            return Ok(());
+        }
+        let scheduled = data_service.seasons_with_status(SeasonStatus::Scheduled).await?;
+        let Some(next) = scheduled.into_iter().find(|s| s.start_at <= now) else {
+            return Ok(());
+        };
+        if !data_service.transition_season(&next.season_id, SeasonStatus::Scheduled, SeasonStatus::Active).await? {
+            return Ok(());
+        }
+        let previous = data_service.seasons_with_status(SeasonStatus::Ended).await?
+            .into_iter()
+            .max_by_key(|s| s.end_at);
+        let carried = match &previous {
+            Some(previous) => data_service.carry_over_season_ratings(
+                &previous.season_id,
+                &next.season_id,
+                CONFIG.season_base_rating,
+                CONFIG.season_soft_reset_factor,
+            ).await?,
+            None => 0,
+        };
+        info!("🏁 Season {} ({}) started with {} carried-over ratings", next.name, next.season_id, carried);
+        Ok(())
+    }
+
+    // Credit each rewarded rank once; only new credits are notified
+    async fn pay_rewards(data_service: &dyn DataStore, season: &Season) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let ranked = data_service.season_leaderboard(&season.season_id, 0, season.rewarded_ranks() as i64).await?;
+        for (index, rating) in ranked.iter().enumerate() {
+            let rank = index as u32 + 1;
+            let Some(coins) = season.reward_for(rank) else { continue };
+            let reference = format!("{}:{}:{}", REWARD_REASON, season.season_id, rating.user_id);
+            if data_service.credit_wallet(&rating.user_id, coins, REWARD_REASON, &reference).await?.is_none() {
+                continue;
+            }
+            let params = json!({ "season": season.name, "rank": rank, "coins": coins });
+            let data = json!({ "season_id": season.season_id, "rank": rank, "coins": coins });
+            if let Err(e) = NotificationManager::dispatch(data_service, &rating.user_id, NotificationCategory::MatchUpdates, "season_reward", params, data, Some(DeepLink::new("open_season", json!({ "season_id": season.season_id })))).await {
+                warn!("⚠️ Failed to notify user {} of season reward: {}", rating.user_id, e);
+            }
+        }
+        Ok(())
+    }
+
+    // Apply a settled match result (see MatchSettlement) to the active season.
+    // Results of users limited by the risk scan do not count.
+    pub async fn record_result(data_service: &dyn DataStore, user_id: &str, outcome: GameOutcome) {
+        match RiskManager::is_limited(data_service, user_id).await {
+            Ok(false) => {}
+            Ok(true) => {
+                info!("🕵️ Season result of limited user {} ignored", user_id);
+                return;
+            }
+            Err(e) => {
+                warn!("⚠️ Season result not recorded for user {}: {}", user_id, e);
+                return;
+            }
+        }
+        let now = bson::DateTime::now();
+        let active = match data_service.seasons_with_status(SeasonStatus::Active).await {
+            Ok(seasons) => seasons.into_iter().find(|s| s.start_at <= now && now < s.end_at),
+            Err(e) => {
+                warn!("⚠️ Season result not recorded for user {}: {}", user_id, e);
+                return;
+            }
+        };
+        let Some(season) = active else { return };
+        if let Err(e) = data_service.record_season_result(&season.season_id, user_id, outcome, Self::rating_change(outcome), CONFIG.season_base_rating).await {
+            warn!("⚠️ Failed to record season result for user {}: {}", user_id, e);
+        }
+    }
+
+    pub fn season_view(season: &Season) -> Value {
+        let rfc3339 = |date: bson::DateTime| date.try_to_rfc3339_string().unwrap_or_default();
+        json!({
+            "season_id": season.season_id,
+            "name": season.name,
+            "start_at": rfc3339(season.start_at),
+            "end_at": rfc3339(season.end_at),
+            "status": season.status.as_str(),
+            "rewards": season.rewards
+        })
+    }
+
+    fn rating_view(rating: &SeasonRating, rank: u64) -> Value {
+        json!({
+            "rank": rank,
+            "user_id": rating.user_id,
+            "rating": rating.rating,
+            "wins": rating.wins,
+            "losses": rating.losses,
+            "draws": rating.draws
+        })
+    }
+
+    // The season a leaderboard request refers to: the given one, else the
+    // active season, else the one that ended last
+    async fn requested_season(data_service: &dyn DataStore, season_id: Option<&str>) -> Result<Option<Season>, Box<dyn std::error::Error + Send + Sync>> {
+        if let Some(season_id) = season_id {
+            return data_service.get_season(season_id).await;
+        }
+        if let Some(active) = data_service.seasons_with_status(SeasonStatus::Active).await?.into_iter().next() {
+            return Ok(Some(active));
+        }
+        let seasons = data_service.list_seasons().await?;
+        Ok(seasons.into_iter().filter(|s| s.status != SeasonStatus::Scheduled).max_by_key(|s| s.end_at))
+    }
+
+    // Season leaderboards on the main namespace:
+    //   leaderboard:get { mobile_no, session_token, season_id?, limit?, offset? } -> leaderboard:data
+    pub fn register_season_events(socket: &SocketRef, data_service: Arc<dyn DataStore>) {
+        let ds = data_service.clone();
+        AuthGuard::require_session(socket, "leaderboard:get", data_service, move |socket, data, auth| {
+            let ds = ds.clone();
+            async move {
+                info!("🏆 Received leaderboard:get from {}: {:?}", socket.id, data);
+                if let Err(error_details) = ValidationManager::validate_leaderboard_data(&data) {
+                    info!("❌ Leaderboard validation failed for socket {}: {:?}", socket.id, error_details);
+                    ErrorResponder::send(&socket, &*ds, error_details).await;
+                    return;
+                }
+                let user = auth.user;
+                let limit = data["limit"].as_i64().unwrap_or(DEFAULT_LEADERBOARD_LIMIT);
+                let offset = data["offset"].as_u64().unwrap_or(0);
+
+                let season = match Self::requested_season(&*ds, data["season_id"].as_str()).await {
+                    Ok(Some(season)) => season,
+                    Ok(None) => {
+                        let error = ApiError::new("SEASON_NOT_FOUND", "SEASON_ERROR", "season_id", "No such season")
+                            .with_details(json!({"season_id": data["season_id"]}));
+                        ErrorResponder::send(&socket, &*ds, error).await;
+                        return;
+                    }
+                    Err(e) => {
+                        error!("❌ Failed to load season: {}", e);
+                        ErrorResponder::send(&socket, &*ds, ApiError::system("LEADERBOARD_FETCH_FAILED", "season_id", "Failed to load the season", &e)).await;
+                        return;
+                    }
+                };
+
+                let loaded = tokio::try_join!(
+                    ds.season_leaderboard(&season.season_id, offset, limit),
+                    ds.get_season_rating(&season.season_id, &user.user_id)
+                );
+                let (entries, own_rating) = match loaded {
+                    Ok(loaded) => loaded,
+                    Err(e) => {
+                        error!("❌ Failed to load leaderboard of season {}: {}", season.season_id, e);
+                        ErrorResponder::send(&socket, &*ds, ApiError::system("LEADERBOARD_FETCH_FAILED", "season_id", "Failed to load the leaderboard", &e)).await;
+                        return;
+                    }
+                };
+                let me = match own_rating {
+                    Some(rating) => match ds.season_rank(&rating).await {
+                        Ok(rank) => Self::rating_view(&rating, rank),
+                        Err(e) => {
+                            warn!("⚠️ Failed to rank user {} in season {}: {}", user.user_id, season.season_id, e);
+                            Value::Null
+                        }
+                    },
+                    None => Value::Null,
+                };
+
+                let entries: Vec<Value> = entries.iter().enumerate()
+                    .map(|(index, rating)| Self::rating_view(rating, offset + index as u64 + 1))
+                    .collect();
+                let response = ApiResponse::success("leaderboard:data", json!({
+                    "season": Self::season_view(&season),
+                    "offset": offset,
+                    "entries": entries,
+                    "me": me
+                })).for_socket(socket.id);
+                if let Err(e) = FaultInjector::emit(&socket, "leaderboard:data", response).await {
+                    warn!("⚠️ Failed to emit leaderboard:data to socket {}: {}", socket.id, e);
+                }
+            }
+        });
+    }
+}
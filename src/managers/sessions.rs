@@ -0,0 +1,179 @@
+use chrono::{DateTime, DurationRound, Utc};
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use socketioxide::extract::SocketRef;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::config::CONFIG;
+use crate::database::models::SessionSample;
+use crate::database::store::DataStore;
+use crate::managers::metrics::MetricsManager;
+use crate::managers::scheduler::Scheduler;
+use crate::managers::slo::SloManager;
+use crate::managers::tenant::TenantManager;
+
+const SAMPLE_SECS: u64 = 60;
+
+// Authenticated sockets of one tenant on this server
+#[derive(Default)]
+struct TenantSessions {
+    sockets: HashMap<String, String>,           // Socket id -> user id
+    minute_peak: u64,                           // Most users at once since the last sample
+    peak: u64,                                  // Most users at once since the server started
+    peak_at: Option<DateTime<Utc>>,
+}
+
+impl TenantSessions {
+    fn users(&self) -> u64 {
+        self.sockets.values().collect::<HashSet<_>>().len() as u64
+    }
+
+    fn note_peak(&mut self) {
+        let users = self.users();
+        self.minute_peak = self.minute_peak.max(users);
+        if users > self.peak {
+            self.peak = users;
+            self.peak_at = Some(Utc::now());
+        }
+    }
+}
+
+static TENANTS: Lazy<Mutex<HashMap<&'static str, TenantSessions>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+// Whether the capacity alert is firing
+static NEAR_CAP: AtomicBool = AtomicBool::new(false);
+
+// Concurrent sessions: the users with an authenticated socket (a user on
+// several namespaces or devices counts once). Every minute each server writes
+// one SessionSample per tenant to `session_metrics` with the current count,
+// the peak since the last sample and its main-namespace connections, and sets
+// the sessions_current / sessions_peak / socket_connections gauges. With
+// CONNECTION_CAP set, an alert goes to the log and the SLO webhooks once the
+// server's connections reach CONNECTION_ALERT_RATIO of it, and again when
+// they drop back. /api/admin/sessions serves the live counts and the history.
+pub struct SessionMetricsManager;
+
+impl SessionMetricsManager {
+    // A socket authenticated as `user_id`, in the tenant in scope
+    pub fn authenticated(socket: &SocketRef, user_id: &str) {
+        let tenant_id = TenantManager::current().tenant_id.as_str();
+        let mut tenants = TENANTS.lock().unwrap_or_else(|e| e.into_inner());
+        let sessions = tenants.entry(tenant_id).or_default();
+        if sessions.sockets.get(&socket.id.to_string()).is_some_and(|bound| bound == user_id) {
+            return;
+        }
+        sessions.sockets.insert(socket.id.to_string(), user_id.to_string());
+        sessions.note_peak();
+    }
+
+    pub fn remove_socket(socket_id: &str) {
+        let mut tenants = TENANTS.lock().unwrap_or_else(|e| e.into_inner());
+        for sessions in tenants.values_mut() {
+            sessions.sockets.remove(socket_id);
+        }
+    }
+
+    // This server's sessions of the tenant in scope, for the admin API
+    pub async fn live() -> Value {
+        let tenant_id = TenantManager::current().tenant_id.as_str();
+        let connections = TenantManager::socket_counts().await.get(tenant_id).copied().unwrap_or(0);
+        let tenants = TENANTS.lock().unwrap_or_else(|e| e.into_inner());
+        let (sessions, peak, peak_at) = tenants.get(tenant_id)
+            .map_or((0, 0, None), |s| (s.users(), s.peak, s.peak_at));
+        json!({
+            "node_id": CONFIG.snowflake_node_id,
+            "sessions": sessions,
+            "connections": connections,
+            "peak_since_start": peak,
+            "peak_since_start_at": peak_at.map(|at| at.to_rfc3339()),
+            "connection_cap": (CONFIG.connection_cap > 0).then_some(CONFIG.connection_cap),
+            "near_cap": NEAR_CAP.load(Ordering::Relaxed),
+        })
+    }
+
+    pub fn spawn_sampler(data_service: Arc<dyn DataStore>) {
+        Scheduler::every_server_wide("session-metrics", Duration::from_secs(SAMPLE_SECS), move || {
+            let data_service = data_service.clone();
+            async move {
+                Self::sample(&*data_service).await;
+                Ok(())
+            }
+        });
+    }
+
+    async fn sample(data_service: &dyn DataStore) {
+        let now = Utc::now();
+        let minute = now.duration_trunc(chrono::Duration::minutes(1)).unwrap_or(now);
+        let connections = TenantManager::socket_counts().await;
+        let samples: Vec<(&'static str, SessionSample)> = {
+            let mut tenants = TENANTS.lock().unwrap_or_else(|e| e.into_inner());
+            TenantManager::tenants().iter().map(|tenant| {
+                let sessions = tenants.entry(tenant.tenant_id.as_str()).or_default();
+                let users = sessions.users();
+                let sample = SessionSample {
+                    id: None,
+                    node_id: CONFIG.snowflake_node_id,
+                    minute: bson::DateTime::from_millis(minute.timestamp_millis()),
+                    sessions: users,
+                    peak_sessions: sessions.minute_peak.max(users),
+                    connections: connections.get(tenant.tenant_id.as_str()).copied().unwrap_or(0),
+                };
+                sessions.minute_peak = users;
+                (tenant.tenant_id.as_str(), sample)
+            }).collect()
+        };
+
+        for (tenant_id, sample) in samples {
+            let labels = format!("{{tenant=\"{}\"}}", tenant_id);
+            MetricsManager::set_gauge(&format!("sessions_current{}", labels), sample.sessions as f64).await;
+            MetricsManager::set_gauge(&format!("sessions_peak{}", labels), sample.peak_sessions as f64).await;
+            MetricsManager::set_gauge(&format!("socket_connections{}", labels), sample.connections as f64).await;
+            let Some(tenant) = TenantManager::get(tenant_id) else { continue };
+            if let Err(e) = TenantManager::scope(tenant, data_service.record_session_sample(sample)).await {
+                error!("❌ Failed to store session metrics of tenant {}: {}", tenant_id, e);
+            }
+        }
+        Self::check_capacity(connections.values().sum()).await;
+    }
+
+    // Alert when the server's connections cross CONNECTION_ALERT_RATIO of
+    // CONNECTION_CAP, either way
+    async fn check_capacity(connections: u64) {
+        if CONFIG.connection_cap == 0 {
+            return;
+        }
+        let usage = connections as f64 / CONFIG.connection_cap as f64;
+        let near = usage >= CONFIG.connection_alert_ratio;
+        if NEAR_CAP.swap(near, Ordering::Relaxed) == near {
+            return;
+        }
+        let summary = format!(
+            "Connections on node {} {}: {} of {} ({:.0}%, alert at {:.0}%)",
+            CONFIG.snowflake_node_id,
+            if near { "near the cap" } else { "back under the alert level" },
+            connections,
+            CONFIG.connection_cap,
+            usage * 100.0,
+            CONFIG.connection_alert_ratio * 100.0,
+        );
+        if near {
+            warn!("🔥 {}", summary);
+        } else {
+            info!("✅ {}", summary);
+        }
+        let alert = json!({
+            "status": if near { "near_cap" } else { "recovered" },
+            "alert": "connection_cap",
+            "node_id": CONFIG.snowflake_node_id,
+            "connections": connections,
+            "connection_cap": CONFIG.connection_cap,
+            "usage": usage,
+            "alert_ratio": CONFIG.connection_alert_ratio,
+            "at": Utc::now().to_rfc3339(),
+        });
+        SloManager::notify(&alert, &summary).await;
+    }
+}
@@ -0,0 +1,285 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use socketioxide::extract::SocketRef;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+use crate::api::contracts::SettingsData;
+use crate::api::response::{ApiError, ApiResponse};
+use crate::database::models::UserRegister;
+use crate::database::store::DataStore;
+use crate::managers::auth_guard::{AuthContext, AuthGuard};
+use crate::managers::chaos::FaultInjector;
+use crate::managers::error_responder::ErrorResponder;
+
+// Platforms with their own defaults; the device_type values of device:info
+pub const PLATFORMS: &[&str] = &["mobile", "tablet", "desktop"];
+const DEFAULT_PLATFORM: &str = "mobile";
+// Where the user's own choices are kept in user_preferences
+pub const SETTINGS_KEY: &str = "settings";
+pub const MAX_VOLUME: u8 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "contracts", derive(ts_rs::TS, schemars::JsonSchema))]
+pub enum ColorblindMode {
+    Off,
+    Protanopia,
+    Deuteranopia,
+    Tritanopia,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "contracts", derive(ts_rs::TS, schemars::JsonSchema))]
+pub enum TextSize {
+    Small,
+    Medium,
+    Large,
+    ExtraLarge,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[cfg_attr(feature = "contracts", derive(ts_rs::TS, schemars::JsonSchema))]
+pub struct SoundSettings {
+    pub enabled: bool,
+    pub music_volume: u8,               // 0-100
+    pub sfx_volume: u8,                 // 0-100
+}
+
+// Accessibility and gameplay settings that follow the user across devices
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[cfg_attr(feature = "contracts", derive(ts_rs::TS, schemars::JsonSchema))]
+pub struct GameSettings {
+    pub colorblind_mode: ColorblindMode,
+    pub haptics: bool,
+    pub text_size: TextSize,
+    pub sound: SoundSettings,
+}
+
+impl GameSettings {
+    fn builtin(platform: &str) -> Self {
+        Self {
+            colorblind_mode: ColorblindMode::Off,
+            haptics: platform != "desktop",
+            text_size: if platform == "tablet" { TextSize::Large } else { TextSize::Medium },
+            sound: SoundSettings { enabled: true, music_volume: 70, sfx_volume: 80 },
+        }
+    }
+
+    // Ranges serde cannot express; Err names the offending field
+    fn check(&self) -> Result<(), &'static str> {
+        if self.sound.music_volume > MAX_VOLUME {
+            return Err("sound.music_volume");
+        }
+        if self.sound.sfx_volume > MAX_VOLUME {
+            return Err("sound.sfx_volume");
+        }
+        Ok(())
+    }
+
+    // Settings as described by a JSON object of (partial) settings over these
+    fn overlaid(&self, patch: &Value) -> Result<Self, String> {
+        let mut merged = serde_json::to_value(self).map_err(|e| e.to_string())?;
+        overlay(&mut merged, patch);
+        let settings: Self = serde_json::from_value(merged).map_err(|e| e.to_string())?;
+        settings.check().map_err(|field| format!("{} must be between 0 and {}", field, MAX_VOLUME))?;
+        Ok(settings)
+    }
+}
+
+// Lay `patch` over `base` object by object; a null in the patch removes the key
+fn overlay(base: &mut Value, patch: &Value) {
+    let (Value::Object(base), Value::Object(patch)) = (&mut *base, patch) else {
+        *base = patch.clone();
+        return;
+    };
+    for (key, value) in patch {
+        if value.is_null() {
+            base.remove(key);
+            continue;
+        }
+        match base.get_mut(key) {
+            Some(existing) if existing.is_object() && value.is_object() => overlay(existing, value),
+            _ => {
+                base.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+// Dotted user_preferences paths of a settings update, leaves only
+fn update_paths(prefix: &str, update: &Map<String, Value>, paths: &mut Map<String, Value>) {
+    for (key, value) in update {
+        let path = format!("{}.{}", prefix, key);
+        match value {
+            Value::Object(inner) if !inner.is_empty() => update_paths(&path, inner, paths),
+            _ => {
+                paths.insert(path, value.clone());
+            }
+        }
+    }
+}
+
+static DEFAULTS: Lazy<Vec<(&'static str, GameSettings)>> = Lazy::new(SettingsManager::load_defaults);
+
+// Typed settings sync on the main namespace:
+//   settings:get { mobile_no, session_token[, platform] }           -> settings:data
+//   settings:set { mobile_no, session_token, settings[, platform] } -> settings:updated
+// Only what the user changed is stored (under user_preferences.settings), so
+// everything else follows the defaults of the platform the client runs on:
+// the `platform` sent, else the device_type of the device the user logged in
+// with. SETTINGS_DEFAULTS overrides the built-in defaults per platform, e.g.
+// {"desktop": {"text_size": "small"}}. In an update a null puts a setting
+// back to its default.
+pub struct SettingsManager;
+
+impl SettingsManager {
+    fn load_defaults() -> Vec<(&'static str, GameSettings)> {
+        let overrides: Map<String, Value> = match std::env::var("SETTINGS_DEFAULTS").ok().filter(|v| !v.trim().is_empty()) {
+            // Defaults that do not parse would hand every client broken settings
+            Some(raw) => serde_json::from_str(&raw).unwrap_or_else(|e| panic!("SETTINGS_DEFAULTS is not a JSON object: {}", e)),
+            None => Map::new(),
+        };
+        if let Some(platform) = overrides.keys().find(|platform| !PLATFORMS.contains(&platform.as_str())) {
+            panic!("SETTINGS_DEFAULTS: unknown platform {:?}, expected one of {:?}", platform, PLATFORMS);
+        }
+        PLATFORMS.iter().map(|platform| {
+            let builtin = GameSettings::builtin(platform);
+            let defaults = match overrides.get(*platform) {
+                Some(patch) => builtin.overlaid(patch).unwrap_or_else(|e| panic!("SETTINGS_DEFAULTS for {}: {}", platform, e)),
+                None => builtin,
+            };
+            (*platform, defaults)
+        }).collect()
+    }
+
+    pub fn defaults(platform: &str) -> &'static GameSettings {
+        let platform = if PLATFORMS.contains(&platform) { platform } else { DEFAULT_PLATFORM };
+        DEFAULTS.iter()
+            .find(|(p, _)| *p == platform)
+            .map(|(_, defaults)| defaults)
+            .expect("every platform has defaults")
+    }
+
+    // The user's stored choices, an object of partial settings
+    fn stored(user: &UserRegister) -> Value {
+        user.user_preferences.as_ref()
+            .and_then(|preferences| preferences.get(SETTINGS_KEY))
+            .filter(|settings| settings.is_object())
+            .cloned()
+            .unwrap_or_else(|| json!({}))
+    }
+
+    // Defaults with the user's choices on top; choices that no longer fit
+    // the schema are dropped as a whole rather than failing the request
+    pub fn effective(user: &UserRegister, platform: &str) -> GameSettings {
+        let defaults = Self::defaults(platform);
+        defaults.overlaid(&Self::stored(user)).unwrap_or_else(|e| {
+            warn!("⚠️ Ignoring stored settings of user {}: {}", user.user_id, e);
+            defaults.clone()
+        })
+    }
+
+    async fn platform(data_service: &dyn DataStore, user: &UserRegister, data: &Value) -> Result<&'static str, ApiError> {
+        if let Some(requested) = data.get("platform").filter(|v| !v.is_null()) {
+            return requested.as_str()
+                .and_then(|requested| PLATFORMS.iter().find(|p| **p == requested).copied())
+                .ok_or_else(|| ApiError::new("INVALID_VALUE", "VALUE_ERROR", "platform", "Unknown platform")
+                    .with_details(json!({"allowed_values": PLATFORMS, "received_value": requested})));
+        }
+        let device_type = match data_service.list_user_devices(&user.mobile_no).await {
+            Ok(devices) => devices.into_iter()
+                .find(|device| device.device_id == user.device_id)
+                .and_then(|device| device.device_type)
+                .map(|device_type| device_type.to_ascii_lowercase()),
+            Err(e) => {
+                warn!("⚠️ Failed to look up the device of user {} for settings: {}", user.user_id, e);
+                None
+            }
+        };
+        Ok(device_type.and_then(|device_type| PLATFORMS.iter().find(|p| **p == device_type).copied()).unwrap_or(DEFAULT_PLATFORM))
+    }
+
+    pub fn register_events(socket: &SocketRef, data_service: Arc<dyn DataStore>) {
+        let ds = data_service.clone();
+        AuthGuard::require_session(socket, "settings:get", data_service.clone(), move |socket, data, auth| {
+            let ds = ds.clone();
+            async move {
+                info!("🎛️ Received settings:get from {}", socket.id);
+                if let Err(error) = Self::get(&socket, &*ds, &data, &auth).await {
+                    ErrorResponder::send(&socket, &*ds, error).await;
+                }
+            }
+        });
+
+        let ds = data_service.clone();
+        AuthGuard::require_session(socket, "settings:set", data_service, move |socket, data, auth| {
+            let ds = ds.clone();
+            async move {
+                info!("🎛️ Received settings:set from {}: {:?}", socket.id, data["settings"]);
+                if let Err(error) = Self::set(&socket, &*ds, &data, &auth).await {
+                    ErrorResponder::send(&socket, &*ds, error).await;
+                }
+            }
+        });
+    }
+
+    async fn get(socket: &SocketRef, data_service: &dyn DataStore, data: &Value, auth: &AuthContext) -> Result<(), ApiError> {
+        let platform = Self::platform(data_service, &auth.user, data).await?;
+        Self::emit(socket, "settings:data", Self::view(&auth.user, platform)).await;
+        Ok(())
+    }
+
+    async fn set(socket: &SocketRef, data_service: &dyn DataStore, data: &Value, auth: &AuthContext) -> Result<(), ApiError> {
+        let update = data["settings"].as_object().filter(|update| !update.is_empty()).ok_or_else(|| {
+            ApiError::new("MISSING_FIELD", "FIELD_ERROR", "settings", "settings is required and must be a non-empty object")
+                .with_details(json!({"field_type": "object", "required": true, "example": {"text_size": "large", "sound": {"music_volume": 40}}}))
+        })?;
+        let platform = Self::platform(data_service, &auth.user, data).await?;
+
+        // The stored choices after the update must still describe valid settings
+        let mut stored = Self::stored(&auth.user);
+        overlay(&mut stored, &Value::Object(update.clone()));
+        Self::defaults(platform).overlaid(&stored).map_err(|reason| {
+            ApiError::new("INVALID_VALUE", "VALUE_ERROR", "settings", "Invalid settings")
+                .with_details(json!({"reason": reason, "received_value": update}))
+        })?;
+
+        let mut paths = Map::new();
+        update_paths(SETTINGS_KEY, update, &mut paths);
+        if let Err(e) = data_service.merge_user_preferences(&auth.mobile_no, &paths).await {
+            error!("❌ Failed to save settings for mobile {}: {}", auth.mobile_no, e);
+            return Err(ApiError::system("SETTINGS_UPDATE_FAILED", "settings", "Failed to save settings", &e));
+        }
+        info!("✅ Settings updated for mobile: {} ({} key(s))", auth.mobile_no, paths.len());
+
+        let mut preferences = auth.user.user_preferences.clone().filter(|p| p.is_object()).unwrap_or_else(|| json!({}));
+        preferences[SETTINGS_KEY] = stored;
+        let user = UserRegister { user_preferences: Some(preferences), ..auth.user.clone() };
+        let updated_keys = paths.keys().map(|path| path[SETTINGS_KEY.len() + 1..].to_string()).collect();
+        Self::emit(socket, "settings:updated", SettingsData { updated_keys: Some(updated_keys), ..Self::view(&user, platform) }).await;
+        Ok(())
+    }
+
+    fn view(user: &UserRegister, platform: &str) -> SettingsData {
+        SettingsData {
+            mobile_no: user.mobile_no.clone(),
+            platform: platform.to_string(),
+            settings: Self::effective(user, platform),
+            defaults: Self::defaults(platform).clone(),
+            customized: Self::stored(user),
+            updated_keys: None,
+        }
+    }
+
+    async fn emit(socket: &SocketRef, event: &str, data: SettingsData) {
+        let response = ApiResponse::success(event, data).for_socket(socket.id);
+        if let Err(e) = FaultInjector::emit(socket, event, response).await {
+            warn!("⚠️ Failed to emit {} to socket {}: {}", event, socket.id, e);
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use std::collections::{BTreeSet, HashMap};
+use tracing::info;
+
+use crate::database::models::{ChallengeKind, GameOutcome};
+use crate::database::store::DataStore;
+use crate::managers::challenges::ChallengeManager;
+use crate::managers::seasons::SeasonManager;
+
+// XP a settled match counts towards earn_xp challenges
+const WIN_XP: i64 = 100;
+const DRAW_XP: i64 = 50;
+const LOSS_XP: i64 = 25;
+
+// What the players' reported results say about a match
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Verdict {
+    Waiting,        // Someone has not reported yet
+    Conflicting,    // The reports do not describe one result
+    Draw,
+    Won(u8),        // The team that won; everyone else lost
+}
+
+impl Verdict {
+    // A player's outcome under an agreed verdict
+    pub fn outcome(&self, team: u8) -> Option<GameOutcome> {
+        match self {
+            Verdict::Draw => Some(GameOutcome::Draw),
+            Verdict::Won(winner) => Some(if *winner == team { GameOutcome::Win } else { GameOutcome::Loss }),
+            Verdict::Waiting | Verdict::Conflicting => None,
+        }
+    }
+}
+
+// The result of a room, decided by the server
+#[derive(Debug, Clone)]
+pub struct SettledMatch {
+    pub room_id: String,
+    pub results: Vec<(String, GameOutcome)>,  // Outcome of every human player
+    pub rated: bool,                          // Bot matches are unrated
+}
+
+// Match results decided by the server, never by one client: every human
+// player reported the same result with progress:update, or the room was
+// closed by the forfeit rules of RoomReaperManager. Only settled results count
+// towards daily challenges and season ratings, once per room.
+pub struct MatchSettlement;
+
+impl MatchSettlement {
+    // Whether the reports of `players` (player_id, team) agree: all draws, or
+    // one team winning and every other player losing
+    pub fn verdict<'a>(players: impl IntoIterator<Item = (&'a str, u8)>, reports: &HashMap<String, GameOutcome>) -> Verdict {
+        let mut outcomes = Vec::new();
+        for (player_id, team) in players {
+            let Some(outcome) = reports.get(player_id) else {
+                return Verdict::Waiting;
+            };
+            outcomes.push((team, *outcome));
+        }
+        if outcomes.iter().all(|(_, outcome)| *outcome == GameOutcome::Draw) {
+            return Verdict::Draw;
+        }
+        let winning_teams: BTreeSet<u8> = outcomes.iter()
+            .filter(|(_, outcome)| *outcome == GameOutcome::Win)
+            .map(|(team, _)| *team)
+            .collect();
+        let [winner] = winning_teams.into_iter().collect::<Vec<_>>()[..] else {
+            return Verdict::Conflicting;
+        };
+        let verdict = Verdict::Won(winner);
+        if outcomes.iter().all(|(team, outcome)| verdict.outcome(*team) == Some(*outcome)) {
+            verdict
+        } else {
+            Verdict::Conflicting
+        }
+    }
+
+    // Count the settled results of a room
+    pub async fn settled(data_service: &dyn DataStore, settled: &SettledMatch) {
+        info!("🏁 Settled room {}: {:?}", settled.room_id, settled.results);
+        for (player_id, outcome) in &settled.results {
+            let (won, xp) = match outcome {
+                GameOutcome::Win => (1, WIN_XP),
+                GameOutcome::Draw => (0, DRAW_XP),
+                GameOutcome::Loss => (0, LOSS_XP),
+            };
+            ChallengeManager::record(data_service, player_id, &[
+                (ChallengeKind::PlayGames, 1),
+                (ChallengeKind::WinGames, won),
+                (ChallengeKind::EarnXp, xp),
+            ]).await;
+            if settled.rated {
+                SeasonManager::record_result(data_service, player_id, *outcome).await;
+            }
+        }
+    }
+}
@@ -0,0 +1,259 @@
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::config::CONFIG;
+use crate::managers::scheduler::Scheduler;
+
+// Objectives on this event cover every socket event
+const ALL_EVENTS: &str = "*";
+const DEFAULT_QUANTILE: f64 = 0.99;
+// Fewer calls in the window than this say nothing about an objective
+const DEFAULT_MIN_CALLS: u64 = 20;
+const WEBHOOK_TIMEOUT_SECS: u64 = 10;
+
+// One entry of SLO_OBJECTIVES. Give latency_ms (with quantile) or max_error_rate.
+#[derive(Debug, Clone, Deserialize)]
+struct ObjectiveConfig {
+    name: String,
+    event: String,
+    latency_ms: Option<u64>,
+    quantile: Option<f64>,
+    max_error_rate: Option<f64>,
+    min_calls: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Indicator {
+    Latency { threshold_ms: f64 },  // A call slower than this is bad
+    Errors,                         // A call that answered with an error is bad
+}
+
+#[derive(Debug, Clone)]
+struct Objective {
+    name: String,
+    event: String,
+    indicator: Indicator,
+    budget: f64,        // Share of calls allowed to be bad
+    min_calls: u64,
+    target: String,     // The objective as written, for alerts
+}
+
+impl Objective {
+    fn parse(config: ObjectiveConfig) -> Result<Self, String> {
+        let min_calls = config.min_calls.unwrap_or(DEFAULT_MIN_CALLS).max(1);
+        match (config.latency_ms, config.max_error_rate) {
+            (Some(latency_ms), None) => {
+                let quantile = config.quantile.unwrap_or(DEFAULT_QUANTILE);
+                if !(quantile > 0.0 && quantile < 1.0) {
+                    return Err(format!("quantile must be between 0 and 1, got {}", quantile));
+                }
+                Ok(Self {
+                    target: format!("p{} {} < {}ms", quantile * 100.0, config.event, latency_ms),
+                    name: config.name,
+                    event: config.event,
+                    indicator: Indicator::Latency { threshold_ms: latency_ms as f64 },
+                    budget: 1.0 - quantile,
+                    min_calls,
+                })
+            }
+            (None, Some(max_error_rate)) => {
+                if !(max_error_rate > 0.0 && max_error_rate < 1.0) {
+                    return Err(format!("max_error_rate must be between 0 and 1, got {}", max_error_rate));
+                }
+                Ok(Self {
+                    target: format!("{} error rate < {}%", config.event, max_error_rate * 100.0),
+                    name: config.name,
+                    event: config.event,
+                    indicator: Indicator::Errors,
+                    budget: max_error_rate,
+                    min_calls,
+                })
+            }
+            _ => Err("give either latency_ms or max_error_rate".to_string()),
+        }
+    }
+
+    fn covers(&self, event: &str) -> bool {
+        self.event == ALL_EVENTS || self.event == event
+    }
+
+    fn is_bad(&self, ms: f64, failed: bool) -> bool {
+        match self.indicator {
+            Indicator::Latency { threshold_ms } => ms > threshold_ms,
+            Indicator::Errors => failed,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Counts {
+    calls: u64,
+    bad: u64,
+}
+
+// Calls of one objective: the check interval in progress and the finished ones in the window
+#[derive(Default)]
+struct Window {
+    current: Counts,
+    intervals: VecDeque<Counts>,
+    burning: bool,
+}
+
+static OBJECTIVES: Lazy<Vec<Objective>> = Lazy::new(SloManager::load);
+static WINDOWS: Lazy<Mutex<Vec<Window>>> = Lazy::new(|| Mutex::new(OBJECTIVES.iter().map(|_| Window::default()).collect()));
+static HTTP: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(WEBHOOK_TIMEOUT_SECS))
+        .build()
+        .unwrap_or_default()
+});
+
+// Service level objectives on socket event handlers, e.g. "99% of verify:otp
+// calls under 300ms" or "under 1% of all calls answer with an error".
+// SLO_OBJECTIVES holds a JSON array of {"name", "event", "latency_ms",
+// "quantile"} or {"name", "event", "max_error_rate"} entries ("*" for every
+// event). Calls are counted as HandlerMetrics records them. Every
+// SLO_CHECK_INTERVAL_SECS the scheduler compares the share of bad calls over
+// the last SLO_WINDOW_SECS with the error budget; when it is used up, and
+// again when the objective recovers, an alert goes to the log,
+// SLO_ALERT_WEBHOOK_URL and SLO_SLACK_WEBHOOK_URL.
+pub struct SloManager;
+
+impl SloManager {
+    fn load() -> Vec<Objective> {
+        let Some(raw) = std::env::var("SLO_OBJECTIVES").ok().filter(|v| !v.trim().is_empty()) else {
+            return Vec::new();
+        };
+        let configs = match serde_json::from_str::<Vec<ObjectiveConfig>>(&raw) {
+            Ok(configs) => configs,
+            Err(e) => {
+                error!("❌ SLO_OBJECTIVES is not a valid list of objectives - no SLOs checked: {}", e);
+                return Vec::new();
+            }
+        };
+        configs.into_iter().filter_map(|config| {
+            let name = config.name.clone();
+            Objective::parse(config)
+                .inspect(|objective| info!("🎯 SLO {}: {}", objective.name, objective.target))
+                .inspect_err(|e| error!("❌ Ignoring SLO {}: {}", name, e))
+                .ok()
+        }).collect()
+    }
+
+    // Count a handler call against the objectives covering its event
+    pub fn record(event: &str, elapsed: Duration, failed: bool) {
+        if OBJECTIVES.is_empty() {
+            return;
+        }
+        let ms = elapsed.as_secs_f64() * 1000.0;
+        let mut windows = WINDOWS.lock().unwrap_or_else(|e| e.into_inner());
+        for (objective, window) in OBJECTIVES.iter().zip(windows.iter_mut()) {
+            if objective.covers(event) {
+                window.current.calls += 1;
+                if objective.is_bad(ms, failed) {
+                    window.current.bad += 1;
+                }
+            }
+        }
+    }
+
+    pub fn spawn_checker() {
+        if OBJECTIVES.is_empty() {
+            return;
+        }
+        let period = Duration::from_secs(CONFIG.slo_check_interval_secs);
+        Scheduler::every_server_wide("slo-check", period, || async {
+            Self::check().await;
+            Ok(())
+        });
+    }
+
+    // Close the current interval of every objective and alert on budgets that
+    // were used up or recovered since the last check
+    async fn check() {
+        let intervals_in_window = (CONFIG.slo_window_secs / CONFIG.slo_check_interval_secs).max(1) as usize;
+        let mut alerts = Vec::new();
+        {
+            let mut windows = WINDOWS.lock().unwrap_or_else(|e| e.into_inner());
+            for (objective, window) in OBJECTIVES.iter().zip(windows.iter_mut()) {
+                let finished = std::mem::take(&mut window.current);
+                window.intervals.push_back(finished);
+                while window.intervals.len() > intervals_in_window {
+                    window.intervals.pop_front();
+                }
+                let calls: u64 = window.intervals.iter().map(|c| c.calls).sum();
+                let bad: u64 = window.intervals.iter().map(|c| c.bad).sum();
+                if calls < objective.min_calls {
+                    continue;
+                }
+                let bad_rate = bad as f64 / calls as f64;
+                let burn_rate = bad_rate / objective.budget;
+                let burning = burn_rate >= 1.0;
+                if burning != window.burning {
+                    window.burning = burning;
+                    alerts.push(Self::alert(objective, burning, calls, bad, bad_rate, burn_rate));
+                }
+            }
+        }
+        for alert in alerts {
+            Self::send(alert).await;
+        }
+    }
+
+    fn alert(objective: &Objective, burning: bool, calls: u64, bad: u64, bad_rate: f64, burn_rate: f64) -> Value {
+        json!({
+            "status": if burning { "burning" } else { "recovered" },
+            "objective": objective.name,
+            "event": objective.event,
+            "target": objective.target,
+            "window_secs": CONFIG.slo_window_secs,
+            "calls": calls,
+            "bad_calls": bad,
+            "bad_rate": bad_rate,
+            "error_budget": objective.budget,
+            "burn_rate": burn_rate,
+            "at": chrono::Utc::now().to_rfc3339(),
+        })
+    }
+
+    async fn send(alert: Value) {
+        let summary = format!(
+            "SLO {} {}: {} - {:.2}% bad calls over the last {}s ({}x the error budget)",
+            alert["objective"].as_str().unwrap_or_default(),
+            alert["status"].as_str().unwrap_or_default(),
+            alert["target"].as_str().unwrap_or_default(),
+            alert["bad_rate"].as_f64().unwrap_or_default() * 100.0,
+            CONFIG.slo_window_secs,
+            (alert["burn_rate"].as_f64().unwrap_or_default() * 10.0).round() / 10.0,
+        );
+        if alert["status"] == "burning" {
+            warn!("🔥 {}", summary);
+        } else {
+            info!("✅ {}", summary);
+        }
+        Self::notify(&alert, &summary).await;
+    }
+
+    // Send an alert to SLO_ALERT_WEBHOOK_URL as JSON and its summary to
+    // SLO_SLACK_WEBHOOK_URL; other monitors alert through here too
+    pub async fn notify(alert: &Value, summary: &str) {
+        if let Some(url) = CONFIG.slo_alert_webhook_url.as_deref() {
+            Self::post("SLO_ALERT_WEBHOOK_URL", url, alert).await;
+        }
+        if let Some(url) = CONFIG.slo_slack_webhook_url.as_deref() {
+            Self::post("SLO_SLACK_WEBHOOK_URL", url, &json!({ "text": summary })).await;
+        }
+    }
+
+    // Webhook URLs carry their credentials, so only the setting is logged
+    async fn post(setting: &str, url: &str, body: &Value) {
+        if let Err(e) = HTTP.post(url).json(body).send().await.and_then(|response| response.error_for_status()) {
+            warn!("⚠️ Failed to deliver alert to {}: {}", setting, e.without_url());
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use std::sync::Mutex;
+
+use crate::config::CONFIG;
+
+// Layout of an id, high bits first: milliseconds since the Unix epoch (42
+// bits, good until 2109), node id (10 bits), sequence within the millisecond
+// (12 bits)
+const NODE_BITS: u32 = 10;
+const SEQUENCE_BITS: u32 = 12;
+pub const MAX_NODE_ID: u64 = (1 << NODE_BITS) - 1;
+const MAX_SEQUENCE: u64 = (1 << SEQUENCE_BITS) - 1;
+
+// Millisecond and sequence of the last id handed out
+static LAST: Lazy<Mutex<(u64, u64)>> = Lazy::new(|| Mutex::new((0, 0)));
+
+// Sortable unique ids for matches, chat messages and wallet and inventory
+// transactions, made without a round-trip to MongoDB. Every server needs its
+// own SNOWFLAKE_NODE_ID (0-1023). Ids are stored as 16 lowercase hex digits,
+// so string order is creation order and they sort after the UUID v7 ids
+// these entities had before.
+pub struct Snowflake;
+
+impl Snowflake {
+    fn next_id() -> u64 {
+        let now = Utc::now().timestamp_millis().max(0) as u64;
+        let mut last = LAST.lock().unwrap_or_else(|e| e.into_inner());
+        let (last_ms, sequence) = *last;
+        // A clock that steps back keeps counting from the last millisecond,
+        // and a full millisecond borrows the next one
+        let (ms, sequence) = if now > last_ms {
+            (now, 0)
+        } else if sequence < MAX_SEQUENCE {
+            (last_ms, sequence + 1)
+        } else {
+            (last_ms + 1, 0)
+        };
+        *last = (ms, sequence);
+        (ms << (NODE_BITS + SEQUENCE_BITS)) | ((CONFIG.snowflake_node_id & MAX_NODE_ID) << SEQUENCE_BITS) | sequence
+    }
+
+    // A new id in its stored form
+    pub fn generate() -> String {
+        format!("{:016x}", Self::next_id())
+    }
+}
@@ -0,0 +1,237 @@
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use socketioxide::{SocketIo, extract::{Data, SocketRef}};
+use std::collections::{BTreeSet, HashMap};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::api::response::{ApiError, ApiResponse};
+use crate::config::CONFIG;
+use crate::managers::chaos::FaultInjector;
+use crate::managers::correlation::Correlation;
+use crate::managers::room::{GameRoom, RoomManager};
+use crate::managers::tenant::TenantManager;
+use crate::managers::validation::ValidationManager;
+
+// Most player ids a viewer list shows; the count is always complete
+pub const MAX_VIEWER_LIST: usize = 100;
+
+// Who is watching a room
+#[derive(Default)]
+struct Audience {
+    spectators: HashMap<String, String>,    // socket_id -> player_id
+    viewer_list: bool,                      // Seated players opted in to showing who watches
+    last_broadcast: Option<Instant>,
+    broadcast_pending: bool,
+}
+
+impl Audience {
+    fn viewers(&self) -> BTreeSet<&str> {
+        self.spectators.values().map(String::as_str).collect()
+    }
+}
+
+// Audiences by room_id
+static AUDIENCES: Lazy<Mutex<HashMap<String, Audience>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// Spectators on the gameplay namespace. A spectator joins the room's socket
+// room, so they see every turn and action, but has no seat and cannot chat.
+// The room gets `room:spectators` with the number of players watching when
+// spectators come and go, at most once every SPECTATOR_BROADCAST_INTERVAL_MS;
+// changes in between are folded into the next update. Streamer-style rooms
+// can opt in to also listing who watches.
+pub struct SpectatorManager;
+
+impl SpectatorManager {
+    fn emit_room_error(s: &SocketRef, error: ApiError) {
+        let _ = s.emit("room:error", error.on_event("room:error").for_socket(s.id));
+    }
+
+    fn view(room_id: &str, audience: &Audience) -> Value {
+        let viewers = audience.viewers();
+        json!({
+            "room_id": room_id,
+            "count": viewers.len(),
+            "viewers": audience.viewer_list.then(|| viewers.iter().take(MAX_VIEWER_LIST).collect::<Vec<_>>()),
+        })
+    }
+
+    // Send the room an update now, or when the throttle allows if one went out
+    // recently. Only one update per room waits at a time.
+    async fn changed(io: &SocketIo, namespace: &str, room_id: &str) {
+        let wait = {
+            let mut audiences = AUDIENCES.lock().await;
+            let Some(audience) = audiences.get_mut(room_id) else { return };
+            if audience.broadcast_pending {
+                return;
+            }
+            audience.broadcast_pending = true;
+            let interval = Duration::from_millis(CONFIG.spectator_broadcast_interval_ms);
+            audience.last_broadcast.map_or(Duration::ZERO, |at| interval.saturating_sub(at.elapsed()))
+        };
+        let (io, namespace, room_id) = (io.clone(), namespace.to_string(), room_id.to_string());
+        TenantManager::spawn(async move {
+            tokio::time::sleep(wait).await;
+            Self::broadcast(&io, &namespace, &room_id).await;
+        });
+    }
+
+    async fn broadcast(io: &SocketIo, namespace: &str, room_id: &str) {
+        let update = {
+            let mut audiences = AUDIENCES.lock().await;
+            let Some(audience) = audiences.get_mut(room_id) else { return };
+            audience.broadcast_pending = false;
+            audience.last_broadcast = Some(Instant::now());
+            let update = Self::view(room_id, audience);
+            if audience.spectators.is_empty() && !audience.viewer_list {
+                audiences.remove(room_id);
+            }
+            update
+        };
+        if let Some(ns) = io.of(namespace) {
+            if let Err(e) = ns.to(room_id.to_string()).emit("room:spectators", ApiResponse::success("room:spectators", update)) {
+                warn!("⚠️ Failed to broadcast room:spectators to room {}: {}", room_id, e);
+            }
+        }
+    }
+
+    // Forget a disconnected socket in every room it watched
+    pub async fn remove_socket(io: &SocketIo, namespace: &str, socket_id: &str) {
+        let room_ids: Vec<String> = AUDIENCES.lock().await.iter_mut()
+            .filter_map(|(room_id, audience)| audience.spectators.remove(socket_id).map(|_| room_id.clone()))
+            .collect();
+        for room_id in room_ids {
+            Self::changed(io, namespace, &room_id).await;
+        }
+    }
+
+    // A spectator who takes a seat in the room stops spectating it
+    pub async fn took_seat(io: &SocketIo, namespace: &str, room_id: &str, socket_id: &str) {
+        let was_spectating = AUDIENCES.lock().await.get_mut(room_id)
+            .is_some_and(|audience| audience.spectators.remove(socket_id).is_some());
+        if was_spectating {
+            Self::changed(io, namespace, room_id).await;
+        }
+    }
+
+    // Whether this socket is spectating the room
+    pub async fn is_watching(room_id: &str, socket_id: &str) -> bool {
+        AUDIENCES.lock().await.get(room_id).is_some_and(|audience| audience.spectators.contains_key(socket_id))
+    }
+
+    // The room, if the player may watch it from this namespace
+    async fn watchable(s: &SocketRef, room_id: &str, player_id: &str, game_type: &str) -> Option<GameRoom> {
+        let error = match RoomManager::get_room(room_id).await {
+            Some(room) if room.config.game_type != game_type => ApiError::new("WRONG_GAME_MODE", "ROOM_ERROR", "room_id", "This room is played in another game mode"),
+            Some(room) if room.players.iter().any(|p| p.player_id == player_id) => ApiError::new("ALREADY_SEATED", "ROOM_ERROR", "room_id", "Players cannot spectate their own room"),
+            Some(room) => return Some(room),
+            None => ApiError::new("ROOM_NOT_FOUND", "ROOM_ERROR", "room_id", "No such room"),
+        };
+        Self::emit_room_error(s, error.with_details(json!({"room_id": room_id})));
+        None
+    }
+
+    // Register spectating on a gameplay namespace socket:
+    //   room:spectate    { room_id, player_id }          -> room:spectating to the sender, room:spectators to the room
+    //   room:unspectate  { room_id, player_id }          -> room:unspectated to the sender, room:spectators to the room
+    //   room:viewer_list { room_id, player_id, enabled } -> room:spectators to the room (seated players only)
+    pub fn register_spectator_events(socket: &SocketRef, io: SocketIo, game_type: String) {
+        let io_spectate = io.clone();
+        socket.on("room:spectate", move |s: SocketRef, Data::<Value>(data)| {
+            let io_spectate = io_spectate.clone();
+            let game_type = game_type.clone();
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope("room:spectate", s.id, request_id, async move {
+                if let Err(error_details) = ValidationManager::validate_room_data(&data) {
+                    Self::emit_room_error(&s, ApiError::from(error_details));
+                    return;
+                }
+                let room_id = data["room_id"].as_str().unwrap_or_default();
+                let player_id = data["player_id"].as_str().unwrap_or_default();
+                let Some(room) = Self::watchable(&s, room_id, player_id, &game_type).await else { return };
+
+                let _ = s.join(room_id.to_string());
+                let count = {
+                    let mut audiences = AUDIENCES.lock().await;
+                    let audience = audiences.entry(room_id.to_string()).or_default();
+                    audience.spectators.insert(s.id.to_string(), player_id.to_string());
+                    audience.viewers().len()
+                };
+                info!("👀 Player {} is spectating room {} ({} watching)", player_id, room_id, count);
+
+                let players: Vec<&str> = room.players.iter().map(|p| p.player_id.as_str()).collect();
+                let active_turn = room.active_turn.as_ref().map(|turn| json!({
+                    "turn_id": turn.turn_id,
+                    "player_id": turn.player_id,
+                    "deadline": turn.deadline.to_rfc3339(),
+                    "deadline_ms": turn.deadline.timestamp_millis()
+                }));
+                let spectating = ApiResponse::success("room:spectating", json!({
+                    "room_id": room_id,
+                    "player_id": player_id,
+                    "game_type": room.config.game_type,
+                    "players": players,
+                    "turn_number": room.turn_number,
+                    "active_turn": active_turn,
+                    "spectators": count
+                })).for_socket(s.id);
+                if let Err(e) = FaultInjector::emit(&s, "room:spectating", spectating).await {
+                    warn!("⚠️ Failed to emit room:spectating to socket {}: {}", s.id, e);
+                }
+                Self::changed(&io_spectate, s.ns(), room_id).await;
+            })
+        });
+
+        let io_unspectate = io.clone();
+        socket.on("room:unspectate", move |s: SocketRef, Data::<Value>(data)| {
+            let io_unspectate = io_unspectate.clone();
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope("room:unspectate", s.id, request_id, async move {
+                if let Err(error_details) = ValidationManager::validate_room_data(&data) {
+                    Self::emit_room_error(&s, ApiError::from(error_details));
+                    return;
+                }
+                let room_id = data["room_id"].as_str().unwrap_or_default();
+                let player_id = data["player_id"].as_str().unwrap_or_default();
+                let was_spectating = AUDIENCES.lock().await.get_mut(room_id)
+                    .is_some_and(|audience| audience.spectators.remove(&s.id.to_string()).is_some());
+                if was_spectating {
+                    let _ = s.leave(room_id.to_string());
+                    info!("👀 Player {} stopped spectating room {}", player_id, room_id);
+                    Self::changed(&io_unspectate, s.ns(), room_id).await;
+                }
+                let _ = FaultInjector::emit(&s, "room:unspectated", ApiResponse::success("room:unspectated", json!({
+                    "room_id": room_id,
+                    "player_id": player_id,
+                    "was_spectating": was_spectating
+                })).for_socket(s.id)).await;
+            })
+        });
+
+        socket.on("room:viewer_list", move |s: SocketRef, Data::<Value>(data)| {
+            let io = io.clone();
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope("room:viewer_list", s.id, request_id, async move {
+                if let Err(error_details) = ValidationManager::validate_viewer_list_data(&data) {
+                    Self::emit_room_error(&s, ApiError::from(error_details));
+                    return;
+                }
+                let room_id = data["room_id"].as_str().unwrap_or_default();
+                let player_id = data["player_id"].as_str().unwrap_or_default();
+                let enabled = data["enabled"].as_bool().unwrap_or_default();
+                let seated = RoomManager::get_room(room_id).await
+                    .is_some_and(|room| room.players.iter().any(|p| p.player_id == player_id && p.socket_id == s.id.to_string()));
+                if !seated {
+                    let error = ApiError::new("NOT_IN_ROOM", "ROOM_ERROR", "room_id", "Only players seated in the room can change its viewer list")
+                        .with_details(json!({"room_id": room_id, "player_id": player_id}));
+                    Self::emit_room_error(&s, error);
+                    return;
+                }
+                AUDIENCES.lock().await.entry(room_id.to_string()).or_default().viewer_list = enabled;
+                info!("👀 Player {} turned the viewer list of room {} {}", player_id, room_id, if enabled { "on" } else { "off" });
+                Self::changed(&io, s.ns(), room_id).await;
+            })
+        });
+    }
+}
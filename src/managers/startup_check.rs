@@ -0,0 +1,129 @@
+use futures_util::TryStreamExt;
+use mongodb::bson::{doc, Document};
+use mongodb::Database;
+use tracing::{error, info, warn};
+
+use crate::config::CONFIG;
+use crate::database::{DatabaseManager, SCHEMA_VERSION};
+use crate::managers::jwt::DEFAULT_JWT_SECRET;
+use crate::managers::snowflake::MAX_NODE_ID;
+use crate::managers::tenant::TenantManager;
+
+// Shortest JWT_SECRET_KEY / ADMIN_API_TOKEN accepted in production
+const MIN_SECRET_LENGTH: usize = 32;
+// _id of the document the write probe deletes; it never exists
+const WRITE_PROBE_ID: &str = "__startup_self_check__";
+
+// Verifies at startup what would otherwise fail later in the middle of a
+// request: required settings (no default JWT secret in production), the
+// indexes of every tenant database, that each collection accepts writes and
+// that the database schema is not newer than this server. Problems are
+// reported together; with STARTUP_FAIL_FAST (default) the server refuses to
+// start, otherwise the report is only logged.
+pub struct StartupCheck;
+
+impl StartupCheck {
+    pub async fn run() -> Result<(), Box<dyn std::error::Error>> {
+        let mut problems = Self::check_settings();
+        if !CONFIG.in_memory_store {
+            for tenant in TenantManager::tenants() {
+                let database = DatabaseManager::tenant_database(tenant);
+                problems.extend(Self::check_database(database).await
+                    .into_iter()
+                    .map(|problem| format!("{}: {}", database.name(), problem)));
+            }
+        }
+
+        if problems.is_empty() {
+            info!("✅ Startup self-check passed");
+            return Ok(());
+        }
+        error!("❌ Startup self-check found {} problem(s):", problems.len());
+        for problem in &problems {
+            error!("   - {}", problem);
+        }
+        if !CONFIG.startup_fail_fast {
+            warn!("⚠️ STARTUP_FAIL_FAST=false - starting anyway");
+            return Ok(());
+        }
+        Err(format!("Startup self-check failed with {} problem(s) - see the report above", problems.len()).into())
+    }
+
+    // Settings production must not run with (elsewhere they are only warned
+    // about) and settings that are wrong anywhere
+    fn check_settings() -> Vec<String> {
+        let mut problems = Vec::new();
+        let production = CONFIG.is_production();
+        let mut production_only = |problem: String| {
+            if production {
+                problems.push(problem);
+            } else {
+                warn!("⚠️ {} (refused with APP_ENVIRONMENT=production)", problem);
+            }
+        };
+
+        match std::env::var("JWT_SECRET_KEY").ok().filter(|secret| !secret.trim().is_empty()) {
+            None => production_only("JWT_SECRET_KEY is not set - tokens are signed with the built-in default secret".to_string()),
+            Some(secret) if secret == DEFAULT_JWT_SECRET => production_only("JWT_SECRET_KEY is the default from env-template.txt".to_string()),
+            Some(secret) if secret.len() < MIN_SECRET_LENGTH => production_only(format!("JWT_SECRET_KEY is shorter than {} characters", MIN_SECRET_LENGTH)),
+            Some(_) => {}
+        }
+        if CONFIG.admin_api_token.as_ref().is_some_and(|token| token.len() < MIN_SECRET_LENGTH) {
+            production_only(format!("ADMIN_API_TOKEN is shorter than {} characters", MIN_SECRET_LENGTH));
+        }
+        if CONFIG.dev_mode {
+            production_only("DEV_MODE is enabled - OTPs are sent back to clients".to_string());
+        }
+        if CONFIG.in_memory_store {
+            production_only("DATA_STORE=memory - data is lost on restart".to_string());
+        } else if std::env::var("MONGODB_URI").is_err() {
+            production_only("MONGODB_URI is not set - connecting to mongodb://localhost:27017".to_string());
+        }
+        if CONFIG.snowflake_node_id > MAX_NODE_ID {
+            problems.push(format!("SNOWFLAKE_NODE_ID must be between 0 and {}, got {}", MAX_NODE_ID, CONFIG.snowflake_node_id));
+        }
+        problems
+    }
+
+    async fn check_database(database: &Database) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        // A schema newer than this server means a newer release migrated the
+        // database; an older or unrecorded one is brought up to date
+        match DatabaseManager::schema_version(database).await {
+            Ok(Some(version)) if version > SCHEMA_VERSION => problems.push(format!(
+                "schema version {} is newer than this server's {} - the database was migrated by a newer release", version, SCHEMA_VERSION
+            )),
+            Ok(Some(version)) if version == SCHEMA_VERSION => {}
+            Ok(_) => if let Err(e) = DatabaseManager::record_schema_version(database).await {
+                problems.push(format!("failed to record schema version {}: {}", SCHEMA_VERSION, e));
+            },
+            Err(e) => problems.push(format!("failed to read the schema version: {}", e)),
+        }
+
+        for (name, indexes) in DatabaseManager::required_indexes() {
+            let collection = database.collection::<Document>(&DatabaseManager::collection_name(name));
+
+            // Deleting a document that never exists needs write access and changes nothing
+            if let Err(e) = collection.delete_one(doc! { "_id": WRITE_PROBE_ID }, None).await {
+                problems.push(format!("{} is not writable: {}", collection.name(), e));
+                continue;
+            }
+
+            let listed = async { collection.list_indexes(None).await?.try_collect::<Vec<_>>().await }.await;
+            let existing: Vec<Document> = match listed {
+                Ok(models) => models.into_iter().map(|model| model.keys).collect(),
+                Err(e) => {
+                    problems.push(format!("failed to list the indexes of {}: {}", collection.name(), e));
+                    continue;
+                }
+            };
+            for index in indexes {
+                if !existing.contains(&index.keys) {
+                    problems.push(format!("{} is missing the index {}", collection.name(), index.keys));
+                }
+            }
+        }
+        problems
+    }
+}
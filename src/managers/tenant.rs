@@ -0,0 +1,223 @@
+use once_cell::sync::{Lazy, OnceCell};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use socketioxide::extract::SocketRef;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::api::response::ApiError;
+use crate::config::CONFIG;
+use crate::database::store::DataStore;
+use crate::database::DatabaseManager;
+use crate::managers::error_responder::ErrorResponder;
+use crate::managers::protocol::ProtocolManager;
+
+// The only tenant when TENANTS is not set
+const DEFAULT_TENANT_ID: &str = "default";
+const MAX_TENANT_ID_LENGTH: usize = 32;
+// Header carrying the tenant on admin API requests and socket handshakes
+pub const TENANT_HEADER: &str = "x-tenant-id";
+
+// Rate limits a tenant sets instead of the server-wide ones in CONFIG
+#[derive(Debug, Clone, Default, Deserialize)]
+struct TenantLimitOverrides {
+    chat_rate_limit: Option<usize>,
+    chat_rate_window_secs: Option<i64>,
+    report_hourly_limit: Option<usize>,
+    gift_daily_limit: Option<usize>,
+    gift_daily_coin_limit: Option<i64>,
+}
+
+// One entry of TENANTS
+#[derive(Debug, Clone, Deserialize)]
+struct TenantConfig {
+    tenant_id: String,
+    database: Option<String>,
+    #[serde(default)]
+    limits: TenantLimitOverrides,
+}
+
+// Rate limits in force for a tenant
+#[derive(Debug, Clone)]
+pub struct TenantLimits {
+    pub chat_rate_limit: usize,
+    pub chat_rate_window_secs: i64,
+    pub report_hourly_limit: usize,
+    pub gift_daily_limit: usize,
+    pub gift_daily_coin_limit: i64,
+}
+
+impl TenantLimits {
+    fn resolve(overrides: &TenantLimitOverrides) -> Self {
+        Self {
+            chat_rate_limit: overrides.chat_rate_limit.unwrap_or(CONFIG.chat_rate_limit).max(1),
+            chat_rate_window_secs: overrides.chat_rate_window_secs.unwrap_or(CONFIG.chat_rate_window_secs).max(1),
+            report_hourly_limit: overrides.report_hourly_limit.unwrap_or(CONFIG.report_hourly_limit).max(1),
+            gift_daily_limit: overrides.gift_daily_limit.unwrap_or(CONFIG.gift_daily_limit),
+            gift_daily_coin_limit: overrides.gift_daily_coin_limit.unwrap_or(CONFIG.gift_daily_coin_limit),
+        }
+    }
+}
+
+// A game title served by this backend, with its own MongoDB database
+#[derive(Debug)]
+pub struct Tenant {
+    pub tenant_id: String,
+    pub database: String,
+    pub limits: TenantLimits,
+}
+
+// Tenants read from TENANTS; the first one is the default
+static TENANTS: OnceCell<Vec<Tenant>> = OnceCell::new();
+
+// Tenant of each connected socket, by socket id
+static SOCKETS: Lazy<RwLock<HashMap<String, &'static Tenant>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+tokio::task_local! {
+    static CURRENT: &'static Tenant;
+}
+
+// Game titles sharing this backend. TENANTS holds a JSON array of
+// {"tenant_id", "database", "limits"} entries; each tenant's data lives in
+// its own database (default <MONGODB_DATABASE>_<tenant_id>), so every
+// repository is scoped by picking the database of the tenant in scope.
+// Socket handlers run in the scope of the tenant the socket connected as,
+// admin API requests in the one named by X-Tenant-Id, and background jobs once
+// per tenant. Without TENANTS there is one tenant, `default`, on
+// MONGODB_DATABASE.
+pub struct TenantManager;
+
+impl TenantManager {
+    fn valid_tenant_id(tenant_id: &str) -> bool {
+        !tenant_id.is_empty() && tenant_id.len() <= MAX_TENANT_ID_LENGTH
+            && tenant_id.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+    }
+
+    fn default_tenants() -> Vec<TenantConfig> {
+        vec![TenantConfig {
+            tenant_id: DEFAULT_TENANT_ID.to_string(),
+            database: Some(DatabaseManager::database_name()),
+            limits: TenantLimitOverrides::default(),
+        }]
+    }
+
+    fn load() -> Vec<Tenant> {
+        let configs = match std::env::var("TENANTS").ok().filter(|v| !v.trim().is_empty()) {
+            Some(raw) => match serde_json::from_str::<Vec<TenantConfig>>(&raw) {
+                Ok(configs) => configs,
+                // Falling back to a shared default tenant could mix the titles' data
+                Err(e) => panic!("TENANTS is not a valid list of tenants: {}", e),
+            },
+            None => Self::default_tenants(),
+        };
+
+        let base = DatabaseManager::database_name();
+        let mut tenant_ids = HashSet::new();
+        let mut databases = HashSet::new();
+        let mut tenants = Vec::new();
+        for config in configs {
+            let database = config.database.clone().unwrap_or_else(|| format!("{}_{}", base, config.tenant_id));
+            if !Self::valid_tenant_id(&config.tenant_id) {
+                panic!("Tenant {:?}: tenant_id must be 1-32 lowercase letters, digits, '_' or '-'", config.tenant_id);
+            }
+            if !tenant_ids.insert(config.tenant_id.clone()) || !databases.insert(database.clone()) {
+                panic!("Tenant {}: tenant_id and database must be unique", config.tenant_id);
+            }
+            info!("🏢 Tenant {} on database {}", config.tenant_id, database);
+            tenants.push(Tenant {
+                limits: TenantLimits::resolve(&config.limits),
+                tenant_id: config.tenant_id,
+                database,
+            });
+        }
+        if tenants.is_empty() {
+            panic!("TENANTS lists no tenants");
+        }
+        tenants
+    }
+
+    pub fn tenants() -> &'static [Tenant] {
+        TENANTS.get_or_init(Self::load)
+    }
+
+    pub fn get(tenant_id: &str) -> Option<&'static Tenant> {
+        Self::tenants().iter().find(|tenant| tenant.tenant_id == tenant_id)
+    }
+
+    // Tenant of connections and requests that do not name one
+    pub fn default_tenant() -> &'static Tenant {
+        &Self::tenants()[0]
+    }
+
+    // Tenant whose data the running task works on; the default tenant outside any scope
+    pub fn current() -> &'static Tenant {
+        CURRENT.try_with(|tenant| *tenant).unwrap_or_else(|_| Self::default_tenant())
+    }
+
+    pub fn limits() -> &'static TenantLimits {
+        &Self::current().limits
+    }
+
+    pub fn scope<F: Future>(tenant: &'static Tenant, future: F) -> impl Future<Output = F::Output> {
+        CURRENT.scope(tenant, future)
+    }
+
+    // tokio::spawn that keeps the current tenant. Tasks started from a handler
+    // or background job must use this so their writes reach the same tenant.
+    pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        tokio::spawn(CURRENT.scope(Self::current(), future))
+    }
+
+    // Resolves the tenant a socket connects as: `tenant_id` in the connect auth
+    // payload, the handshake query or the X-Tenant-Id header, otherwise the
+    // default tenant. Unknown tenants get a connection_error and None, and the
+    // caller disconnects the socket.
+    pub async fn connect(socket: &SocketRef, data_service: &dyn DataStore, auth: &Value) -> Option<&'static Tenant> {
+        let requested = auth.get("tenant_id").and_then(|v| v.as_str()).map(|v| v.to_string())
+            .or_else(|| ProtocolManager::query_param(socket, "tenant_id"))
+            .or_else(|| socket.req_parts().headers.get(TENANT_HEADER).and_then(|h| h.to_str().ok()).map(|v| v.to_string()));
+        let tenant = match &requested {
+            None => Self::default_tenant(),
+            Some(tenant_id) => match Self::get(tenant_id) {
+                Some(tenant) => tenant,
+                None => {
+                    warn!("🚫 Unknown tenant {:?} from socket {}", tenant_id, socket.id);
+                    let error = ApiError::new("TENANT_UNKNOWN", "VALIDATION_ERROR", "tenant_id", "Unknown tenant")
+                        .with_details(json!({ "tenant_id": tenant_id }));
+                    ErrorResponder::send(socket, data_service, error).await;
+                    return None;
+                }
+            },
+        };
+        SOCKETS.write().await.insert(socket.id.to_string(), tenant);
+        if requested.is_some() {
+            info!("🏢 Socket {} connected as tenant {}", socket.id, tenant.tenant_id);
+        }
+        Some(tenant)
+    }
+
+    // Tenant a socket connected as
+    pub async fn of_socket(socket_id: &str) -> &'static Tenant {
+        SOCKETS.read().await.get(socket_id).copied().unwrap_or_else(Self::default_tenant)
+    }
+
+    pub async fn remove_socket(socket_id: &str) {
+        SOCKETS.write().await.remove(socket_id);
+    }
+
+    // Connected sockets of each tenant that has any
+    pub async fn socket_counts() -> HashMap<&'static str, u64> {
+        let mut counts = HashMap::new();
+        for tenant in SOCKETS.read().await.values() {
+            *counts.entry(tenant.tenant_id.as_str()).or_insert(0) += 1;
+        }
+        counts
+    }
+}
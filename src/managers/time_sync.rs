@@ -0,0 +1,92 @@
+use socketioxide::extract::{Data, SocketRef};
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::api::response::ApiResponse;
+use crate::managers::correlation::Correlation;
+use crate::managers::latency::LatencyManager;
+
+// Unanswered sync probes are dropped after this long
+const SYNC_PROBE_TTL_MS: i64 = 30_000;
+
+// Outstanding sync probes keyed by socket id: (sync_id, server_send_time in ms)
+static PENDING_SYNCS: Lazy<Mutex<HashMap<String, (String, i64)>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub struct TimeSyncManager;
+
+impl TimeSyncManager {
+    // Clock synchronization, NTP style:
+    //   client -> time:sync      { client_time }
+    //   server -> time:sync      { client_time, server_receive_time, server_send_time, sync_id }
+    //   client -> time:sync:ack  { sync_id }
+    //   server -> time:sync:rtt  { rtt_ms, server_time }
+    // The first exchange is enough for the client to compute its offset; the
+    // ack lets the server measure RTT itself and report it back.
+    pub fn register_time_sync_events(socket: &SocketRef) {
+        socket.on("time:sync", |s: SocketRef, Data::<Value>(data)| {
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope("time:sync", s.id, request_id, async move {
+                let server_receive_time = chrono::Utc::now().timestamp_millis();
+                let sync_id = Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext)).to_string();
+
+                {
+                    let mut pending = PENDING_SYNCS.lock().await;
+                    pending.retain(|_, (_, sent_at)| server_receive_time - *sent_at < SYNC_PROBE_TTL_MS);
+                    pending.insert(s.id.to_string(), (sync_id.clone(), server_receive_time));
+                }
+
+                let server_send_time = chrono::Utc::now().timestamp_millis();
+                let response = ApiResponse::success("time:sync", json!({
+                    "sync_id": sync_id,
+                    "client_time": data.get("client_time").and_then(|v| v.as_i64()),
+                    "server_receive_time": server_receive_time,
+                    "server_send_time": server_send_time,
+                    "server_time": server_send_time
+                })).for_socket(s.id);
+                if let Err(e) = s.emit("time:sync", response) {
+                    warn!("⚠️ Failed to send time:sync to socket {}: {}", s.id, e);
+                }
+            })
+        });
+
+        socket.on("time:sync:ack", |s: SocketRef, Data::<Value>(data)| {
+            let request_id = Correlation::request_id_from(&data);
+            Correlation::scope("time:sync:ack", s.id, request_id, async move {
+                let now = chrono::Utc::now().timestamp_millis();
+                let sync_id = data["sync_id"].as_str().unwrap_or_default();
+
+                let sent_at = {
+                    let mut pending = PENDING_SYNCS.lock().await;
+                    match pending.get(&s.id.to_string()) {
+                        Some((id, sent_at)) if id == sync_id => {
+                            let sent_at = *sent_at;
+                            pending.remove(&s.id.to_string());
+                            Some(sent_at)
+                        }
+                        _ => None,
+                    }
+                };
+                let Some(sent_at) = sent_at else {
+                    warn!("⚠️ Unknown or expired time:sync:ack from socket {}: {:?}", s.id, data);
+                    return;
+                };
+
+                let rtt_ms = now - sent_at;
+                info!("⏱️ Measured RTT for socket {}: {}ms", s.id, rtt_ms);
+                LatencyManager::record_sample(&s.id.to_string(), rtt_ms.max(0) as u64).await;
+                let response = ApiResponse::success("time:sync:rtt", json!({
+                    "sync_id": sync_id,
+                    "rtt_ms": rtt_ms,
+                    "server_time": chrono::Utc::now().timestamp_millis()
+                })).for_socket(s.id);
+                if let Err(e) = s.emit("time:sync:rtt", response) {
+                    warn!("⚠️ Failed to send time:sync:rtt to socket {}: {}", s.id, e);
+                }
+            })
+        });
+    }
+}
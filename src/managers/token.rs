@@ -0,0 +1,26 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use rand::{rngs::OsRng, RngCore};
+
+// 256 bits of entropy per session token
+const SESSION_TOKEN_BYTES: usize = 32;
+
+pub struct TokenGenerator;
+
+impl TokenGenerator {
+    // Opaque session token: 32 bytes from the OS CSPRNG, base64url encoded (43 chars)
+    pub fn session_token() -> String {
+        let mut bytes = [0u8; SESSION_TOKEN_BYTES];
+        OsRng.fill_bytes(&mut bytes);
+        URL_SAFE_NO_PAD.encode(bytes)
+    }
+
+    // Compare secrets without short-circuiting on the first differing byte,
+    // so response timing does not reveal how much of a guess was correct
+    pub fn constant_time_eq(a: &str, b: &str) -> bool {
+        let (a, b) = (a.as_bytes(), b.as_bytes());
+        if a.len() != b.len() {
+            return false;
+        }
+        a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+    }
+}
@@ -0,0 +1,253 @@
+use socketioxide::SocketIo;
+use serde_json::json;
+use chrono::{DateTime, Utc};
+use tracing::{info, warn};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::managers::correlation::Correlation;
+use crate::api::response::ApiResponse;
+use crate::database::models::TurnTimingEvent;
+use crate::database::store::DataStore;
+use crate::managers::bot::BotPlayer;
+use crate::managers::game_modes::GameModeRegistry;
+use crate::managers::room::RoomManager;
+use crate::managers::room_snapshots::RoomSnapshotManager;
+use crate::managers::tenant::TenantManager;
+
+#[derive(Debug, Clone)]
+pub struct ActiveTurn {
+    pub turn_id: String,
+    pub player_id: String,
+    pub turn_number: u32,
+    pub started_at: DateTime<Utc>,
+    pub deadline: DateTime<Utc>,
+    pub is_bot: bool,
+}
+
+pub struct TurnTimerManager;
+
+impl TurnTimerManager {
+    // Start the next player's turn in a room and arm its countdown
+    pub async fn start_next_turn(io: SocketIo, data_service: Arc<dyn DataStore>, room_id: &str) {
+        if let Some(turn) = Self::begin_turn(&io, &data_service, room_id).await {
+            Self::arm_timer(io, data_service, room_id.to_string(), turn);
+        }
+    }
+
+    // Restart the turn loop of a room restored from a snapshot. The active turn
+    // gets a fresh deadline so its player has time to reconnect; a room saved
+    // between turns starts the next one.
+    pub async fn resume_turn(io: SocketIo, data_service: Arc<dyn DataStore>, room_id: &str) {
+        let resumed = RoomManager::with_room(room_id, |room| match room.active_turn.as_mut() {
+            Some(turn) => {
+                let now = Utc::now();
+                turn.started_at = now;
+                turn.deadline = now + chrono::Duration::seconds(room.config.rules.turn_timeout_secs);
+                Ok(turn.clone())
+            }
+            None => Err(room.is_full() && room.turn_index.is_some()),
+        }).await;
+
+        match resumed {
+            Some(Ok(turn)) => {
+                info!("⏱️ Resumed turn {} in room {} for player {} (deadline: {})", turn.turn_number, room_id, turn.player_id, turn.deadline);
+                Self::arm_timer(io, data_service, room_id.to_string(), turn);
+            }
+            Some(Err(true)) => Self::start_next_turn(io, data_service, room_id).await,
+            _ => {}
+        }
+    }
+
+    // Advance the room to the next player and broadcast `turn:started`. The
+    // turn lasts as long as the room's config says.
+    async fn begin_turn(io: &SocketIo, data_service: &Arc<dyn DataStore>, room_id: &str) -> Option<ActiveTurn> {
+        let turn = RoomManager::with_room(room_id, |room| {
+            if room.players.is_empty() {
+                return None;
+            }
+            let next_index = match room.turn_index {
+                Some(index) => (index + 1) % room.players.len(),
+                None => 0,
+            };
+            let now = Utc::now();
+            let duration = chrono::Duration::seconds(room.config.rules.turn_timeout_secs);
+            room.turn_index = Some(next_index);
+            room.turn_number += 1;
+            let player = &room.players[next_index];
+            let turn = ActiveTurn {
+                turn_id: Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext)).to_string(),
+                player_id: player.player_id.clone(),
+                turn_number: room.turn_number,
+                started_at: now,
+                deadline: now + duration,
+                is_bot: player.is_bot,
+            };
+            room.active_turn = Some(turn.clone());
+            Some((turn, room.config.game_type.clone()))
+        }).await.flatten();
+
+        let Some((turn, game_type)) = turn else {
+            warn!("⚠️ Cannot start turn for room {}: room missing or empty", room_id);
+            return None;
+        };
+
+        let turn_started = ApiResponse::success("turn:started", json!({
+            "room_id": room_id,
+            "turn_id": turn.turn_id,
+            "player_id": turn.player_id,
+            "is_bot": turn.is_bot,
+            "turn_number": turn.turn_number,
+            "deadline": turn.deadline.to_rfc3339(),
+            "deadline_ms": turn.deadline.timestamp_millis(),
+            "duration_ms": (turn.deadline - turn.started_at).num_milliseconds(),
+            "server_time": Utc::now().timestamp_millis()
+        }));
+        if let Some(ns) = io.of(GameModeRegistry::for_game_type(&game_type).namespace.as_str()) {
+            if let Err(e) = ns.to(room_id.to_string()).emit("turn:started", turn_started) {
+                warn!("⚠️ Failed to broadcast turn:started to room {}: {}", room_id, e);
+            }
+        }
+        info!("⏱️ Turn {} started in room {} for player {} (deadline: {})", turn.turn_number, room_id, turn.player_id, turn.deadline);
+        RoomSnapshotManager::turn_started(data_service, room_id, turn.turn_number);
+        Some(turn)
+    }
+
+    // Countdown task - keeps driving the room for as long as turns end without
+    // a human action (timeouts and bot moves)
+    fn arm_timer(io: SocketIo, data_service: Arc<dyn DataStore>, room_id: String, turn: ActiveTurn) {
+        TenantManager::spawn(async move {
+            let mut turn = turn;
+            loop {
+                let next = if turn.is_bot {
+                    tokio::time::sleep(BotPlayer::think_time()).await;
+                    Self::play_bot_turn(&io, &data_service, &room_id, &turn).await
+                } else {
+                    let wait = (turn.deadline - Utc::now()).to_std().unwrap_or_default();
+                    tokio::time::sleep(wait).await;
+                    // The turn id guards against acting on a turn that already ended
+                    Self::handle_timeout(&io, &data_service, &room_id, &turn.turn_id).await
+                };
+                match next {
+                    Some(next_turn) => turn = next_turn,
+                    None => break,
+                }
+            }
+        });
+    }
+
+    // Play the bot's move, chosen by the room's game mode, and advance the room
+    async fn play_bot_turn(io: &SocketIo, data_service: &Arc<dyn DataStore>, room_id: &str, turn: &ActiveTurn) -> Option<ActiveTurn> {
+        let mode = RoomManager::mode_of(room_id).await?;
+        let turn = Self::complete_turn(&**data_service, room_id, &turn.player_id).await.ok()?;
+        let action = mode.rules.bot_action(turn.turn_number);
+        let action = ApiResponse::success("player_action", json!({
+            "room_id": room_id,
+            "player_id": turn.player_id,
+            "turn_number": turn.turn_number,
+            "score": mode.rules.score_action(&action),
+            "action": action,
+            "is_bot": true
+        }));
+        if let Some(ns) = io.of(mode.namespace.as_str()) {
+            if let Err(e) = ns.to(room_id.to_string()).emit("player_action", action) {
+                warn!("⚠️ Failed to broadcast bot player_action to room {}: {}", room_id, e);
+            }
+        }
+        info!("🤖 Bot {} played turn {} in room {}", turn.player_id, turn.turn_number, room_id);
+        Self::begin_turn(io, data_service, room_id).await
+    }
+
+    // Complete the active turn for a player who acted in time
+    pub async fn complete_turn(data_service: &dyn DataStore, room_id: &str, player_id: &str) -> Result<ActiveTurn, &'static str> {
+        let result = RoomManager::with_room(room_id, |room| {
+            match &room.active_turn {
+                Some(turn) if turn.player_id == player_id => {
+                    room.consecutive_timeouts.insert(player_id.to_string(), 0);
+                    Ok(room.active_turn.take().unwrap())
+                }
+                Some(_) => Err("NOT_YOUR_TURN"),
+                None => Err("NO_ACTIVE_TURN"),
+            }
+        }).await.unwrap_or(Err("ROOM_NOT_FOUND"));
+
+        let turn = result?;
+        let ended_at = Utc::now();
+        Self::persist_timing(data_service, room_id, &turn, ended_at, false, None, 0).await;
+        Ok(turn)
+    }
+
+    // Auto-play a skip when the deadline passes without the player acting
+    // Returns the next turn when the timeout advanced the room
+    async fn handle_timeout(io: &SocketIo, data_service: &Arc<dyn DataStore>, room_id: &str, turn_id: &str) -> Option<ActiveTurn> {
+        let expired = RoomManager::with_room(room_id, |room| {
+            match &room.active_turn {
+                Some(turn) if turn.turn_id == turn_id => {
+                    let turn = room.active_turn.take().unwrap();
+                    let count = room.consecutive_timeouts.entry(turn.player_id.clone()).or_insert(0);
+                    *count += 1;
+                    Some((turn, *count, room.config.rules.stall_timeout_threshold, room.config.game_type.clone()))
+                }
+                _ => None,
+            }
+        }).await.flatten();
+
+        // Turn already completed (or room is gone) - nothing to do
+        let (turn, consecutive_timeouts, stall_threshold, game_type) = expired?;
+
+        let stall_detected = consecutive_timeouts >= stall_threshold;
+        if stall_detected {
+            warn!("🐢 Player {} has timed out {} turns in a row in room {}", turn.player_id, consecutive_timeouts, room_id);
+        }
+
+        Self::persist_timing(&**data_service, room_id, &turn, Utc::now(), true, Some("skip"), consecutive_timeouts).await;
+
+        let timeout_notice = ApiResponse::success("turn:timeout", json!({
+            "room_id": room_id,
+            "turn_id": turn.turn_id,
+            "player_id": turn.player_id,
+            "turn_number": turn.turn_number,
+            "auto_action": "skip",
+            "consecutive_timeouts": consecutive_timeouts,
+            "stall_detected": stall_detected
+        })).with_status("timeout");
+        if let Some(ns) = io.of(GameModeRegistry::for_game_type(&game_type).namespace.as_str()) {
+            if let Err(e) = ns.to(room_id.to_string()).emit("turn:timeout", timeout_notice) {
+                warn!("⚠️ Failed to broadcast turn:timeout to room {}: {}", room_id, e);
+            }
+        }
+        info!("⌛ Turn {} timed out in room {} for player {} - skipped", turn.turn_number, room_id, turn.player_id);
+
+        Self::begin_turn(io, data_service, room_id).await
+    }
+
+    async fn persist_timing(
+        data_service: &dyn DataStore,
+        room_id: &str,
+        turn: &ActiveTurn,
+        ended_at: DateTime<Utc>,
+        timed_out: bool,
+        auto_action: Option<&str>,
+        consecutive_timeouts: u32,
+    ) {
+        let event = TurnTimingEvent {
+            id: None,
+            request_id: Correlation::current(),
+            room_id: room_id.to_string(),
+            player_id: turn.player_id.clone(),
+            turn_id: turn.turn_id.clone(),
+            turn_number: turn.turn_number,
+            started_at: bson::DateTime::from_millis(turn.started_at.timestamp_millis()),
+            deadline: bson::DateTime::from_millis(turn.deadline.timestamp_millis()),
+            ended_at: bson::DateTime::from_millis(ended_at.timestamp_millis()),
+            duration_ms: (ended_at - turn.started_at).num_milliseconds(),
+            timed_out,
+            auto_action: auto_action.map(|a| a.to_string()),
+            consecutive_timeouts,
+            timestamp: bson::DateTime::from_millis(Utc::now().timestamp_millis()),
+        };
+        if let Err(e) = data_service.store_turn_timing_event(event).await {
+            warn!("⚠️ Failed to store turn timing for room {}: {}", room_id, e);
+        }
+    }
+}
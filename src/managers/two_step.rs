@@ -0,0 +1,287 @@
+use argon2::password_hash::{rand_core::OsRng as PasswordRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use rand::{rngs::OsRng, RngCore};
+use serde_json::{json, Value};
+use sha1::Sha1;
+use socketioxide::extract::SocketRef;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+use crate::api::response::{ApiError, ApiResponse};
+use crate::config::CONFIG;
+use crate::database::models::TwoStepFactor;
+use crate::database::store::DataStore;
+use crate::managers::auth_guard::{AuthContext, AuthGuard};
+use crate::managers::chaos::FaultInjector;
+use crate::managers::error_responder::ErrorResponder;
+
+// Digits in a PIN, also for the parental PIN
+pub const PIN_LENGTH: (usize, usize) = (4, 8);
+// RFC 6238 as authenticator apps implement it: HMAC-SHA1, 30s steps, 6 digits
+const TOTP_SECRET_BYTES: usize = 20;
+const TOTP_STEP_SECS: i64 = 30;
+const TOTP_DIGITS: u32 = 6;
+// Codes of the neighbouring steps are accepted too, for clock drift
+const TOTP_SKEW_STEPS: i64 = 1;
+// Wrong codes in a row before the factor locks, and for how long
+const MAX_FAILED_ATTEMPTS: u32 = 5;
+const LOCK_MINUTES: i64 = 15;
+
+// Optional second factor, a PIN or an authenticator app (TOTP), that
+// device changes (and withdrawals, through `require`) ask for on top of the
+// session:
+//   two_step:setup   { mobile_no, session_token, method: "pin", pin }  -> two_step:enabled
+//                    { mobile_no, session_token, method: "totp" }      -> two_step:totp_setup
+//   two_step:verify  { mobile_no, session_token, code }                -> two_step:enabled | two_step:verified
+//   two_step:disable { mobile_no, session_token, two_step_code }       -> two_step:disabled
+// A TOTP setup is only enabled once two_step:verify confirms a code from the
+// app. Replacing or disabling a factor needs the current one. Users who lost
+// theirs are reset by support through the admin API.
+pub struct TwoStepManager;
+
+impl TwoStepManager {
+    pub fn register_events(socket: &SocketRef, data_service: Arc<dyn DataStore>) {
+        let ds = data_service.clone();
+        AuthGuard::require_session(socket, "two_step:setup", data_service.clone(), move |socket, data, auth| {
+            let ds = ds.clone();
+            async move {
+                info!("🔐 Received two_step:setup from {}", socket.id);
+                if let Err(error) = Self::setup(&socket, &*ds, &data, &auth).await {
+                    ErrorResponder::send(&socket, &*ds, error).await;
+                }
+            }
+        });
+
+        let ds = data_service.clone();
+        AuthGuard::require_session(socket, "two_step:verify", data_service.clone(), move |socket, data, auth| {
+            let ds = ds.clone();
+            async move {
+                info!("🔐 Received two_step:verify from {}", socket.id);
+                if let Err(error) = Self::verify(&socket, &*ds, &data, &auth).await {
+                    ErrorResponder::send(&socket, &*ds, error).await;
+                }
+            }
+        });
+
+        let ds = data_service.clone();
+        AuthGuard::require_session(socket, "two_step:disable", data_service, move |socket, data, auth| {
+            let ds = ds.clone();
+            async move {
+                info!("🔐 Received two_step:disable from {}", socket.id);
+                if let Err(error) = Self::disable(&socket, &*ds, &data, &auth).await {
+                    ErrorResponder::send(&socket, &*ds, error).await;
+                }
+            }
+        });
+    }
+
+    // For sensitive actions: Ok when the user has no second factor or the
+    // payload's `two_step_code` is right
+    pub async fn require(data_service: &dyn DataStore, auth: &AuthContext, data: &Value) -> Result<(), ApiError> {
+        match auth.user.two_step.as_ref().filter(|factor| factor.enabled_at.is_some()) {
+            Some(factor) => Self::check(data_service, &auth.mobile_no, factor, data["two_step_code"].as_str(), "two_step_code").await.map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    async fn setup(socket: &SocketRef, data_service: &dyn DataStore, data: &Value, auth: &AuthContext) -> Result<(), ApiError> {
+        Self::require(data_service, auth, data).await?;
+        let method = data["method"].as_str().unwrap_or_default();
+        let factor = match method {
+            "pin" => {
+                let pin = data["pin"].as_str().unwrap_or_default();
+                if !pin.chars().all(|c| c.is_ascii_digit()) || !(PIN_LENGTH.0..=PIN_LENGTH.1).contains(&pin.len()) {
+                    return Err(ApiError::new("INVALID_FORMAT", "FORMAT_ERROR", "pin", &format!("pin must be {} to {} digits", PIN_LENGTH.0, PIN_LENGTH.1)));
+                }
+                let pin_hash = Self::hash_pin(pin.to_string()).await
+                    .map_err(|e| ApiError::system("TWO_STEP_SETUP_FAILED", "pin", "Failed to set up two-step verification", &e))?;
+                TwoStepFactor {
+                    pin_hash: Some(pin_hash),
+                    enabled_at: Some(bson::DateTime::now()),
+                    ..Self::factor("pin")
+                }
+            }
+            "totp" => {
+                let mut secret = [0u8; TOTP_SECRET_BYTES];
+                OsRng.fill_bytes(&mut secret);
+                TwoStepFactor { totp_secret: Some(BASE32_NOPAD.encode(&secret)), ..Self::factor("totp") }
+            }
+            _ => return Err(ApiError::new("INVALID_VALUE", "VALUE_ERROR", "method", "method must be pin or totp")),
+        };
+        Self::save(data_service, &auth.mobile_no, Some(&factor)).await?;
+
+        match factor.totp_secret.as_deref() {
+            Some(secret) => {
+                info!("🔐 TOTP setup started for mobile: {}", auth.mobile_no);
+                Self::emit(socket, "two_step:totp_setup", json!({
+                    "message": "Add the secret to an authenticator app, then send two_step:verify with its code",
+                    "method": "totp",
+                    "secret": secret,
+                    "otpauth_uri": Self::otpauth_uri(secret, &auth.mobile_no),
+                    "digits": TOTP_DIGITS,
+                    "period": TOTP_STEP_SECS,
+                })).await;
+            }
+            None => {
+                info!("🔐 PIN two-step verification enabled for mobile: {}", auth.mobile_no);
+                Self::emit(socket, "two_step:enabled", json!({"message": "Two-step verification enabled", "method": "pin"})).await;
+            }
+        }
+        Ok(())
+    }
+
+    async fn verify(socket: &SocketRef, data_service: &dyn DataStore, data: &Value, auth: &AuthContext) -> Result<(), ApiError> {
+        let Some(factor) = auth.user.two_step.as_ref() else {
+            return Err(Self::not_enabled());
+        };
+        let mut checked = Self::check(data_service, &auth.mobile_no, factor, data["code"].as_str(), "code").await?;
+        if factor.enabled_at.is_some() {
+            Self::emit(socket, "two_step:verified", json!({"message": "Code accepted", "method": factor.method})).await;
+            return Ok(());
+        }
+
+        // First code from the authenticator app completes the TOTP setup
+        checked.enabled_at = Some(bson::DateTime::now());
+        Self::save(data_service, &auth.mobile_no, Some(&checked)).await?;
+        info!("🔐 TOTP two-step verification enabled for mobile: {}", auth.mobile_no);
+        Self::emit(socket, "two_step:enabled", json!({"message": "Two-step verification enabled", "method": "totp"})).await;
+        Ok(())
+    }
+
+    async fn disable(socket: &SocketRef, data_service: &dyn DataStore, data: &Value, auth: &AuthContext) -> Result<(), ApiError> {
+        if auth.user.two_step.is_none() {
+            return Err(Self::not_enabled());
+        }
+        Self::require(data_service, auth, data).await?;
+        Self::save(data_service, &auth.mobile_no, None).await?;
+        info!("🔐 Two-step verification disabled for mobile: {}", auth.mobile_no);
+        Self::emit(socket, "two_step:disabled", json!({"message": "Two-step verification disabled"})).await;
+        Ok(())
+    }
+
+    // Check a code against the factor, counting failures towards the lock;
+    // returns the factor as stored after an accepted code
+    async fn check(data_service: &dyn DataStore, mobile_no: &str, factor: &TwoStepFactor, code: Option<&str>, field: &str) -> Result<TwoStepFactor, ApiError> {
+        let now = chrono::Utc::now();
+        if let Some(until) = factor.locked_until.filter(|until| until.timestamp_millis() > now.timestamp_millis()) {
+            return Err(ApiError::new("TWO_STEP_LOCKED", "AUTHENTICATION_ERROR", field, "Too many wrong codes. Please try again later.")
+                .with_details(json!({"locked_until": until.try_to_rfc3339_string().unwrap_or_default()})));
+        }
+        let Some(code) = code.filter(|code| !code.is_empty()) else {
+            return Err(ApiError::new("TWO_STEP_REQUIRED", "AUTHENTICATION_ERROR", field, "This action needs your two-step verification code")
+                .with_details(json!({"method": factor.method})));
+        };
+
+        let mut updated = factor.clone();
+        let accepted = match (factor.pin_hash.clone(), factor.totp_secret.as_deref()) {
+            (Some(pin_hash), _) => Self::verify_pin(code.to_string(), pin_hash).await,
+            (None, Some(secret)) => match Self::totp_step(secret, code, now.timestamp()) {
+                // A code is used once, even within its step
+                Some(step) if factor.totp_last_step.is_none_or(|last| step > last) => {
+                    updated.totp_last_step = Some(step);
+                    true
+                }
+                _ => false,
+            },
+            (None, None) => false,
+        };
+
+        if accepted {
+            updated.failed_attempts = 0;
+            updated.locked_until = None;
+            if updated.failed_attempts != factor.failed_attempts || updated.locked_until != factor.locked_until || updated.totp_last_step != factor.totp_last_step {
+                Self::save(data_service, mobile_no, Some(&updated)).await?;
+            }
+            return Ok(updated);
+        }
+        updated.failed_attempts += 1;
+        let attempts_left = MAX_FAILED_ATTEMPTS.saturating_sub(updated.failed_attempts);
+        if attempts_left == 0 {
+            updated.failed_attempts = 0;
+            updated.locked_until = Some(bson::DateTime::from_millis((now + chrono::Duration::minutes(LOCK_MINUTES)).timestamp_millis()));
+            warn!("🔒 Two-step verification locked for mobile: {}", mobile_no);
+        }
+        Self::save(data_service, mobile_no, Some(&updated)).await?;
+        Err(ApiError::new("TWO_STEP_INVALID", "AUTHENTICATION_ERROR", field, "The two-step verification code is incorrect")
+            .with_details(json!({"attempts_left": attempts_left})))
+    }
+
+    fn factor(method: &str) -> TwoStepFactor {
+        TwoStepFactor {
+            method: method.to_string(),
+            pin_hash: None,
+            totp_secret: None,
+            totp_last_step: None,
+            enabled_at: None,
+            failed_attempts: 0,
+            locked_until: None,
+        }
+    }
+
+    async fn save(data_service: &dyn DataStore, mobile_no: &str, factor: Option<&TwoStepFactor>) -> Result<(), ApiError> {
+        data_service.update_two_step(mobile_no, factor).await.map(|_| ()).map_err(|e| {
+            error!("❌ Failed to save two-step verification for mobile {}: {}", mobile_no, e);
+            ApiError::system("TWO_STEP_FAILED", "mobile_no", "Failed to save two-step verification", &e)
+        })
+    }
+
+    // Argon2 is deliberately slow, so it runs off the async workers
+    pub async fn hash_pin(pin: String) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        tokio::task::spawn_blocking(move || {
+            let salt = SaltString::generate(&mut PasswordRng);
+            Argon2::default().hash_password(pin.as_bytes(), &salt).map(|hash| hash.to_string())
+        }).await?.map_err(|e| e.to_string().into())
+    }
+
+    pub async fn verify_pin(pin: String, pin_hash: String) -> bool {
+        tokio::task::spawn_blocking(move || {
+            PasswordHash::new(&pin_hash).is_ok_and(|hash| Argon2::default().verify_password(pin.as_bytes(), &hash).is_ok())
+        }).await.unwrap_or(false)
+    }
+
+    fn totp(secret: &[u8], step: i64) -> u32 {
+        let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC takes keys of any length");
+        mac.update(&(step as u64).to_be_bytes());
+        let hash = mac.finalize().into_bytes();
+        let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+        let value = u32::from_be_bytes([hash[offset] & 0x7f, hash[offset + 1], hash[offset + 2], hash[offset + 3]]);
+        value % 10_u32.pow(TOTP_DIGITS)
+    }
+
+    // The time step whose code matches, if any
+    fn totp_step(secret: &str, code: &str, now_secs: i64) -> Option<i64> {
+        if code.len() != TOTP_DIGITS as usize {
+            return None;
+        }
+        let code: u32 = code.parse().ok()?;
+        let secret = BASE32_NOPAD.decode(secret.as_bytes()).ok()?;
+        let current = now_secs / TOTP_STEP_SECS;
+        (current - TOTP_SKEW_STEPS..=current + TOTP_SKEW_STEPS).find(|step| Self::totp(&secret, *step) == code)
+    }
+
+    fn otpauth_uri(secret: &str, mobile_no: &str) -> String {
+        let Ok(mut uri) = reqwest::Url::parse("otpauth://totp/") else {
+            return String::new();
+        };
+        uri.set_path(&format!("{}:{}", CONFIG.two_step_issuer, mobile_no));
+        uri.query_pairs_mut()
+            .append_pair("secret", secret)
+            .append_pair("issuer", &CONFIG.two_step_issuer)
+            .append_pair("digits", &TOTP_DIGITS.to_string())
+            .append_pair("period", &TOTP_STEP_SECS.to_string());
+        uri.to_string()
+    }
+
+    fn not_enabled() -> ApiError {
+        ApiError::new("TWO_STEP_NOT_ENABLED", "VALUE_ERROR", "method", "Two-step verification is not set up")
+    }
+
+    async fn emit(socket: &SocketRef, event: &str, data: Value) {
+        let response = ApiResponse::success(event, data).for_socket(socket.id);
+        if let Err(e) = FaultInjector::emit(socket, event, response).await {
+            warn!("⚠️ Failed to emit {} to socket {}: {}", event, socket.id, e);
+        }
+    }
+}
@@ -1,7 +1,21 @@
 use serde_json::{json, Value};
 use tracing::info;
 
-// Error details structure
+use crate::database::models::{GameOutcome, NotificationPreferences, ReportCategory};
+use crate::managers::preferences::{self, KeyRule, PreferencesManager};
+use crate::config::CONFIG;
+use crate::managers::chat;
+use crate::managers::dealer;
+use crate::managers::gifts;
+use crate::managers::login_policy::LoginPolicyManager;
+use crate::managers::moderation;
+use crate::managers::parental::{self, ParentalManager};
+use crate::managers::progress;
+use crate::managers::promos;
+use crate::managers::room_state;
+use crate::managers::seasons;
+
+// Error details structure, returned boxed to keep validation results small
 #[derive(Debug)]
 pub struct ValidationError {
     pub code: String,
@@ -15,129 +29,142 @@ pub struct ValidationManager;
 
 impl ValidationManager {
     // Validate device info data
-    pub fn validate_device_info(data: &Value) -> Result<(), ValidationError> {
+    pub fn validate_device_info(data: &Value) -> Result<(), Box<ValidationError>> {
         // Check if data is an object
-        let obj = data.as_object().ok_or(ValidationError {
+        let obj = data.as_object().ok_or(Box::new(ValidationError {
             code: "INVALID_FORMAT".to_string(),
             error_type: "FORMAT_ERROR".to_string(),
             field: "root".to_string(),
             message: "Device info must be a JSON object".to_string(),
             details: json!({"received_type": if data.is_object() { "object" } else if data.is_array() { "array" } else if data.is_string() { "string" } else if data.is_number() { "number" } else if data.is_boolean() { "boolean" } else { "null" }}),
-        })?;
+        }))?;
         
         // Required fields (mandatory)
         let device_id = obj
             .get("device_id")
             .and_then(|v| v.as_str())
-            .ok_or(ValidationError {
+            .ok_or(Box::new(ValidationError {
                 code: "MISSING_FIELD".to_string(),
                 error_type: "FIELD_ERROR".to_string(),
                 field: "device_id".to_string(),
                 message: "device_id is required and must be a string".to_string(),
                 details: json!({"field_type": "string", "required": true}),
-            })?;
+            }))?;
         
         let device_type =
             obj.get("device_type")
                 .and_then(|v| v.as_str())
-                .ok_or(ValidationError {
+                .ok_or(Box::new(ValidationError {
                     code: "MISSING_FIELD".to_string(),
                     error_type: "FIELD_ERROR".to_string(),
                     field: "device_type".to_string(),
                     message: "device_type is required and must be a string".to_string(),
                     details: json!({"field_type": "string", "required": true}),
-                })?;
+                }))?;
         
         let timestamp = obj
             .get("timestamp")
             .and_then(|v| v.as_str())
-            .ok_or(ValidationError {
+            .ok_or(Box::new(ValidationError {
                 code: "MISSING_FIELD".to_string(),
                 error_type: "FIELD_ERROR".to_string(),
                 field: "timestamp".to_string(),
                 message: "timestamp is required and must be a string".to_string(),
                 details: json!({"field_type": "string", "required": true}),
-            })?;
+            }))?;
         
         // Optional fields (not mandatory)
         let manufacturer = obj.get("manufacturer").and_then(|v| v.as_str());
         let model = obj.get("model").and_then(|v| v.as_str());
         let firmware_version = obj.get("firmware_version").and_then(|v| v.as_str());
+        let app_version = obj.get("app_version").and_then(|v| v.as_str());
         let capabilities = obj.get("capabilities").and_then(|v| v.as_array());
         
         // Validate required field values
         if device_id.is_empty() {
-            return Err(ValidationError {
+            return Err(Box::new(ValidationError {
                 code: "EMPTY_FIELD".to_string(),
                 error_type: "VALUE_ERROR".to_string(),
                 field: "device_id".to_string(),
                 message: "device_id cannot be empty".to_string(),
                 details: json!({"min_length": 1, "received_length": 0, "required": true}),
-            });
+            }));
         }
         
         if device_type.is_empty() {
-            return Err(ValidationError {
+            return Err(Box::new(ValidationError {
                 code: "EMPTY_FIELD".to_string(),
                 error_type: "VALUE_ERROR".to_string(),
                 field: "device_type".to_string(),
                 message: "device_type cannot be empty".to_string(),
                 details: json!({"min_length": 1, "received_length": 0, "required": true}),
-            });
+            }));
         }
         
         // Validate optional fields if they are present
         if let Some(manufacturer_val) = manufacturer {
             if manufacturer_val.is_empty() {
-                return Err(ValidationError {
+                return Err(Box::new(ValidationError {
                     code: "EMPTY_FIELD".to_string(),
                     error_type: "VALUE_ERROR".to_string(),
                     field: "manufacturer".to_string(),
                     message: "manufacturer cannot be empty if provided".to_string(),
                     details: json!({"min_length": 1, "received_length": 0, "required": false}),
-                });
+                }));
             }
         }
         
         if let Some(model_val) = model {
             if model_val.is_empty() {
-                return Err(ValidationError {
+                return Err(Box::new(ValidationError {
                     code: "EMPTY_FIELD".to_string(),
                     error_type: "VALUE_ERROR".to_string(),
                     field: "model".to_string(),
                     message: "model cannot be empty if provided".to_string(),
                     details: json!({"min_length": 1, "received_length": 0, "required": false}),
-                });
+                }));
             }
         }
         
         if let Some(firmware_val) = firmware_version {
             if firmware_val.is_empty() {
-                return Err(ValidationError {
+                return Err(Box::new(ValidationError {
                     code: "EMPTY_FIELD".to_string(),
                     error_type: "VALUE_ERROR".to_string(),
                     field: "firmware_version".to_string(),
                     message: "firmware_version cannot be empty if provided".to_string(),
                     details: json!({"min_length": 1, "received_length": 0, "required": false}),
-                });
+                }));
+            }
+        }
+
+        if let Some(app_version_val) = app_version {
+            if app_version_val.is_empty() {
+                return Err(Box::new(ValidationError {
+                    code: "EMPTY_FIELD".to_string(),
+                    error_type: "VALUE_ERROR".to_string(),
+                    field: "app_version".to_string(),
+                    message: "app_version cannot be empty if provided".to_string(),
+                    details: json!({"min_length": 1, "received_length": 0, "required": false}),
+                }));
             }
         }
         
         if let Some(capabilities_val) = capabilities {
             if capabilities_val.is_empty() {
-                return Err(ValidationError {
+                return Err(Box::new(ValidationError {
                     code: "EMPTY_FIELD".to_string(),
                     error_type: "VALUE_ERROR".to_string(),
                     field: "capabilities".to_string(),
                     message: "capabilities cannot be empty if provided".to_string(),
                     details: json!({"min_length": 1, "received_length": 0, "required": false}),
-                });
+                }));
             }
             
             // Validate capabilities array contains only strings
             for (index, capability) in capabilities_val.iter().enumerate() {
                 if !capability.is_string() {
-                    return Err(ValidationError {
+                    return Err(Box::new(ValidationError {
                         code: "INVALID_TYPE".to_string(),
                         error_type: "TYPE_ERROR".to_string(),
                         field: format!("capabilities[{}]", index),
@@ -149,14 +176,14 @@ impl ValidationManager {
                             "array_index": index,
                             "required": false
                         }),
-                    });
+                    }));
                 }
             }
         }
         
         // Validate timestamp format (basic ISO format check)
         if !timestamp.contains('T') || !timestamp.contains('Z') {
-            return Err(ValidationError {
+            return Err(Box::new(ValidationError {
                 code: "INVALID_FORMAT".to_string(),
                 error_type: "FORMAT_ERROR".to_string(),
                 field: "timestamp".to_string(),
@@ -167,7 +194,7 @@ impl ValidationManager {
                     "received_value": timestamp,
                     "required": true
                 }),
-            });
+            }));
         }
         
         info!("✅ Device info validation passed for device: {}", device_id);
@@ -175,87 +202,87 @@ impl ValidationManager {
     }
 
     // Validate login data
-    pub fn validate_login_data(data: &Value) -> Result<(), ValidationError> {
+    pub fn validate_login_data(data: &Value) -> Result<(), Box<ValidationError>> {
         // Check if data is an object
-        let obj = data.as_object().ok_or(ValidationError {
+        let obj = data.as_object().ok_or(Box::new(ValidationError {
             code: "INVALID_FORMAT".to_string(),
             error_type: "FORMAT_ERROR".to_string(),
             field: "root".to_string(),
             message: "Login data must be a JSON object".to_string(),
             details: json!({"received_type": if data.is_object() { "object" } else if data.is_array() { "array" } else if data.is_string() { "string" } else if data.is_number() { "number" } else if data.is_boolean() { "boolean" } else { "null" }}),
-        })?;
+        }))?;
         
         // Required fields (mandatory)
         let mobile_no = obj
             .get("mobile_no")
             .and_then(|v| v.as_str())
-            .ok_or(ValidationError {
+            .ok_or(Box::new(ValidationError {
                 code: "MISSING_FIELD".to_string(),
                 error_type: "FIELD_ERROR".to_string(),
                 field: "mobile_no".to_string(),
                 message: "mobile_no is required and must be a string".to_string(),
                 details: json!({"field_type": "string", "required": true}),
-            })?;
+            }))?;
         
         let device_id = obj
             .get("device_id")
             .and_then(|v| v.as_str())
-            .ok_or(ValidationError {
+            .ok_or(Box::new(ValidationError {
                 code: "MISSING_FIELD".to_string(),
                 error_type: "FIELD_ERROR".to_string(),
                 field: "device_id".to_string(),
                 message: "device_id is required and must be a string".to_string(),
                 details: json!({"field_type": "string", "required": true}),
-            })?;
+            }))?;
         
         let fcm_token = obj
             .get("fcm_token")
             .and_then(|v| v.as_str())
-            .ok_or(ValidationError {
+            .ok_or(Box::new(ValidationError {
                 code: "MISSING_FIELD".to_string(),
                 error_type: "FIELD_ERROR".to_string(),
                 field: "fcm_token".to_string(),
                 message: "fcm_token is required and must be a string".to_string(),
                 details: json!({"field_type": "string", "required": true}),
-            })?;
+            }))?;
         
         // Optional fields
         let timestamp = obj.get("timestamp").and_then(|v| v.as_str());
         
         // Validate required field values
         if mobile_no.is_empty() {
-            return Err(ValidationError {
+            return Err(Box::new(ValidationError {
                 code: "EMPTY_FIELD".to_string(),
                 error_type: "VALUE_ERROR".to_string(),
                 field: "mobile_no".to_string(),
                 message: "mobile_no cannot be empty".to_string(),
                 details: json!({"min_length": 1, "received_length": 0, "required": true}),
-            });
+            }));
         }
         
         if device_id.is_empty() {
-            return Err(ValidationError {
+            return Err(Box::new(ValidationError {
                 code: "EMPTY_FIELD".to_string(),
                 error_type: "VALUE_ERROR".to_string(),
                 field: "device_id".to_string(),
                 message: "device_id cannot be empty".to_string(),
                 details: json!({"min_length": 1, "received_length": 0, "required": true}),
-            });
+            }));
         }
         
         if fcm_token.is_empty() {
-            return Err(ValidationError {
+            return Err(Box::new(ValidationError {
                 code: "EMPTY_FIELD".to_string(),
                 error_type: "VALUE_ERROR".to_string(),
                 field: "fcm_token".to_string(),
                 message: "fcm_token cannot be empty".to_string(),
                 details: json!({"min_length": 1, "received_length": 0, "required": true}),
-            });
+            }));
         }
         
         // Validate mobile number format (basic validation for 10-15 digits)
         if !mobile_no.chars().all(|c| c.is_digit(10)) {
-            return Err(ValidationError {
+            return Err(Box::new(ValidationError {
                 code: "INVALID_FORMAT".to_string(),
                 error_type: "FORMAT_ERROR".to_string(),
                 field: "mobile_no".to_string(),
@@ -265,11 +292,11 @@ impl ValidationManager {
                     "received_value": mobile_no,
                     "required": true
                 }),
-            });
+            }));
         }
         
         if mobile_no.len() < 10 || mobile_no.len() > 15 {
-            return Err(ValidationError {
+            return Err(Box::new(ValidationError {
                 code: "INVALID_LENGTH".to_string(),
                 error_type: "LENGTH_ERROR".to_string(),
                 field: "mobile_no".to_string(),
@@ -280,12 +307,12 @@ impl ValidationManager {
                     "received_length": mobile_no.len(),
                     "required": true
                 }),
-            });
+            }));
         }
         
         // Validate device_id format (alphanumeric and underscore only, 3-50 characters)
         if !device_id.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-') {
-            return Err(ValidationError {
+            return Err(Box::new(ValidationError {
                 code: "INVALID_FORMAT".to_string(),
                 error_type: "FORMAT_ERROR".to_string(),
                 field: "device_id".to_string(),
@@ -295,11 +322,11 @@ impl ValidationManager {
                     "received_value": device_id,
                     "required": true
                 }),
-            });
+            }));
         }
         
         if device_id.len() < 3 || device_id.len() > 50 {
-            return Err(ValidationError {
+            return Err(Box::new(ValidationError {
                 code: "INVALID_LENGTH".to_string(),
                 error_type: "LENGTH_ERROR".to_string(),
                 field: "device_id".to_string(),
@@ -310,12 +337,12 @@ impl ValidationManager {
                     "received_length": device_id.len(),
                     "required": true
                 }),
-            });
+            }));
         }
         
         // Validate FCM token format (basic validation for Firebase token)
         if fcm_token.len() < 100 || fcm_token.len() > 500 {
-            return Err(ValidationError {
+            return Err(Box::new(ValidationError {
                 code: "INVALID_LENGTH".to_string(),
                 error_type: "LENGTH_ERROR".to_string(),
                 field: "fcm_token".to_string(),
@@ -326,13 +353,13 @@ impl ValidationManager {
                     "received_length": fcm_token.len(),
                     "required": true
                 }),
-            });
+            }));
         }
         
         // Validate optional timestamp if provided
         if let Some(timestamp_val) = timestamp {
             if !timestamp_val.contains('T') || !timestamp_val.contains('Z') {
-                return Err(ValidationError {
+                return Err(Box::new(ValidationError {
                     code: "INVALID_FORMAT".to_string(),
                     error_type: "FORMAT_ERROR".to_string(),
                     field: "timestamp".to_string(),
@@ -343,7 +370,7 @@ impl ValidationManager {
                         "received_value": timestamp_val,
                         "required": false
                     }),
-                });
+                }));
             }
         }
         
@@ -352,77 +379,77 @@ impl ValidationManager {
     }
 
     // Validate OTP verification data
-    pub fn validate_otp_data(data: &Value) -> Result<(), ValidationError> {
+    pub fn validate_otp_data(data: &Value) -> Result<(), Box<ValidationError>> {
         // Check if data is an object
-        let obj = data.as_object().ok_or(ValidationError {
+        let obj = data.as_object().ok_or(Box::new(ValidationError {
             code: "INVALID_FORMAT".to_string(),
             error_type: "FORMAT_ERROR".to_string(),
             field: "root".to_string(),
             message: "OTP data must be a JSON object".to_string(),
             details: json!({"received_type": if data.is_object() { "object" } else if data.is_array() { "array" } else if data.is_string() { "string" } else if data.is_number() { "number" } else if data.is_boolean() { "boolean" } else { "null" }}),
-        })?;
+        }))?;
         
         // Required fields (mandatory)
         let mobile_no = obj
             .get("mobile_no")
             .and_then(|v| v.as_str())
-            .ok_or(ValidationError {
+            .ok_or(Box::new(ValidationError {
                 code: "MISSING_FIELD".to_string(),
                 error_type: "FIELD_ERROR".to_string(),
                 field: "mobile_no".to_string(),
                 message: "mobile_no is required and must be a string".to_string(),
                 details: json!({"field_type": "string", "required": true}),
-            })?;
+            }))?;
         
         let otp = obj
             .get("otp")
             .and_then(|v| v.as_str())
-            .ok_or(ValidationError {
+            .ok_or(Box::new(ValidationError {
                 code: "MISSING_FIELD".to_string(),
                 error_type: "FIELD_ERROR".to_string(),
                 field: "otp".to_string(),
                 message: "otp is required and must be a string".to_string(),
                 details: json!({"field_type": "string", "required": true}),
-            })?;
+            }))?;
         
         let session_token = obj
             .get("session_token")
             .and_then(|v| v.as_str())
-            .ok_or(ValidationError {
+            .ok_or(Box::new(ValidationError {
                 code: "MISSING_FIELD".to_string(),
                 error_type: "FIELD_ERROR".to_string(),
                 field: "session_token".to_string(),
                 message: "session_token is required and must be a string".to_string(),
                 details: json!({"field_type": "string", "required": true}),
-            })?;
+            }))?;
         
         // Optional fields
         let timestamp = obj.get("timestamp").and_then(|v| v.as_str());
         
         // Validate required field values
         if mobile_no.is_empty() {
-            return Err(ValidationError {
+            return Err(Box::new(ValidationError {
                 code: "EMPTY_FIELD".to_string(),
                 error_type: "VALUE_ERROR".to_string(),
                 field: "mobile_no".to_string(),
                 message: "mobile_no cannot be empty".to_string(),
                 details: json!({"min_length": 1, "received_length": 0, "required": true}),
-            });
+            }));
         }
         
         if otp.is_empty() {
-            return Err(ValidationError {
+            return Err(Box::new(ValidationError {
                 code: "EMPTY_FIELD".to_string(),
                 error_type: "VALUE_ERROR".to_string(),
                 field: "otp".to_string(),
                 message: "otp cannot be empty".to_string(),
                 details: json!({"min_length": 1, "received_length": 0, "required": true}),
-            });
+            }));
         }
         
         // Validate mobile number format (basic validation for 10-15 digits)
         if !mobile_no.chars().all(|c| c.is_digit(10)) {
-            return Err(ValidationError {
+            return Err(Box::new(ValidationError {
                 code: "INVALID_FORMAT".to_string(),
                 error_type: "FORMAT_ERROR".to_string(),
                 field: "mobile_no".to_string(),
@@ -432,11 +459,11 @@ impl ValidationManager {
                     "received_value": mobile_no,
                     "required": true
                 }),
-            });
+            }));
         }
         
         if mobile_no.len() < 10 || mobile_no.len() > 15 {
-            return Err(ValidationError {
+            return Err(Box::new(ValidationError {
                 code: "INVALID_LENGTH".to_string(),
                 error_type: "LENGTH_ERROR".to_string(),
                 field: "mobile_no".to_string(),
@@ -447,12 +474,12 @@ impl ValidationManager {
                     "received_length": mobile_no.len(),
                     "required": true
                 }),
-            });
+            }));
         }
         
-        // Validate OTP format (6 digits only)
+        // Validate OTP format (digits only, as many as the mobile's country policy issues)
         if !otp.chars().all(|c| c.is_digit(10)) {
-            return Err(ValidationError {
+            return Err(Box::new(ValidationError {
                 code: "INVALID_FORMAT".to_string(),
                 error_type: "FORMAT_ERROR".to_string(),
                 field: "otp".to_string(),
@@ -462,26 +489,31 @@ impl ValidationManager {
                     "received_value": otp,
                     "required": true
                 }),
-            });
+            }));
         }
         
-        if otp.len() != 6 {
-            return Err(ValidationError {
+        // Test accounts keep the fixed TEST_OTP_CODE whatever their country
+        let expected_length = match CONFIG.test_otp_for(mobile_no) {
+            Some(code) => code.to_string().len(),
+            None => LoginPolicyManager::for_mobile(mobile_no).otp_length as usize,
+        };
+        if otp.len() != expected_length {
+            return Err(Box::new(ValidationError {
                 code: "INVALID_LENGTH".to_string(),
                 error_type: "LENGTH_ERROR".to_string(),
                 field: "otp".to_string(),
-                message: "otp must be exactly 6 digits".to_string(),
+                message: format!("otp must be exactly {} digits", expected_length),
                 details: json!({
-                    "expected_length": 6,
+                    "expected_length": expected_length,
                     "received_length": otp.len(),
                     "required": true
                 }),
-            });
+            }));
         }
         
         // Validate session token (should not be empty)
         if session_token.is_empty() {
-            return Err(ValidationError {
+            return Err(Box::new(ValidationError {
                 code: "INVALID_VALUE".to_string(),
                 error_type: "VALUE_ERROR".to_string(),
                 field: "session_token".to_string(),
@@ -491,13 +523,13 @@ impl ValidationManager {
                     "received_length": session_token.len(),
                     "required": true
                 }),
-            });
+            }));
         }
         
         // Validate optional timestamp if provided
         if let Some(timestamp_val) = timestamp {
             if !timestamp_val.contains('T') || !timestamp_val.contains('Z') {
-                return Err(ValidationError {
+                return Err(Box::new(ValidationError {
                     code: "INVALID_FORMAT".to_string(),
                     error_type: "FORMAT_ERROR".to_string(),
                     field: "timestamp".to_string(),
@@ -508,7 +540,7 @@ impl ValidationManager {
                         "received_value": timestamp_val,
                         "required": false
                     }),
-                });
+                }));
             }
         }
         
@@ -517,60 +549,60 @@ impl ValidationManager {
     }
 
     // Validate language setting data
-    pub fn validate_language_setting_data(data: &Value) -> Result<(), ValidationError> {
+    pub fn validate_language_setting_data(data: &Value) -> Result<(), Box<ValidationError>> {
         // Check if data is an object
-        let obj = data.as_object().ok_or(ValidationError {
+        let obj = data.as_object().ok_or(Box::new(ValidationError {
             code: "INVALID_FORMAT".to_string(),
             error_type: "FORMAT_ERROR".to_string(),
             field: "root".to_string(),
             message: "Language setting data must be a JSON object".to_string(),
             details: json!({"received_type": if data.is_object() { "object" } else if data.is_array() { "array" } else if data.is_string() { "string" } else if data.is_number() { "number" } else if data.is_boolean() { "boolean" } else { "null" }}),
-        })?;
+        }))?;
         
         // Required fields (mandatory)
         let mobile_no = obj
             .get("mobile_no")
             .and_then(|v| v.as_str())
-            .ok_or(ValidationError {
+            .ok_or(Box::new(ValidationError {
                 code: "MISSING_FIELD".to_string(),
                 error_type: "FIELD_ERROR".to_string(),
                 field: "mobile_no".to_string(),
                 message: "mobile_no is required and must be a string".to_string(),
                 details: json!({"field_type": "string", "required": true}),
-            })?;
+            }))?;
         
         let session_token = obj
             .get("session_token")
             .and_then(|v| v.as_str())
-            .ok_or(ValidationError {
+            .ok_or(Box::new(ValidationError {
                 code: "MISSING_FIELD".to_string(),
                 error_type: "FIELD_ERROR".to_string(),
                 field: "session_token".to_string(),
                 message: "session_token is required and must be a string".to_string(),
                 details: json!({"field_type": "string", "required": true}),
-            })?;
+            }))?;
         
         let language_code = obj
             .get("language_code")
             .and_then(|v| v.as_str())
-            .ok_or(ValidationError {
+            .ok_or(Box::new(ValidationError {
                 code: "MISSING_FIELD".to_string(),
                 error_type: "FIELD_ERROR".to_string(),
                 field: "language_code".to_string(),
                 message: "language_code is required and must be a string".to_string(),
                 details: json!({"field_type": "string", "required": true}),
-            })?;
+            }))?;
         
         let language_name = obj
             .get("language_name")
             .and_then(|v| v.as_str())
-            .ok_or(ValidationError {
+            .ok_or(Box::new(ValidationError {
                 code: "MISSING_FIELD".to_string(),
                 error_type: "FIELD_ERROR".to_string(),
                 field: "language_name".to_string(),
                 message: "language_name is required and must be a string".to_string(),
                 details: json!({"field_type": "string", "required": true}),
-            })?;
+            }))?;
         
         // Optional fields
         let region_code = obj.get("region_code").and_then(|v| v.as_str());
@@ -580,48 +612,48 @@ impl ValidationManager {
         
         // Validate required field values
         if mobile_no.is_empty() {
-            return Err(ValidationError {
+            return Err(Box::new(ValidationError {
                 code: "EMPTY_FIELD".to_string(),
                 error_type: "VALUE_ERROR".to_string(),
                 field: "mobile_no".to_string(),
                 message: "mobile_no cannot be empty".to_string(),
                 details: json!({"min_length": 1, "received_length": 0, "required": true}),
-            });
+            }));
         }
         
         if session_token.is_empty() {
-            return Err(ValidationError {
+            return Err(Box::new(ValidationError {
                 code: "EMPTY_FIELD".to_string(),
                 error_type: "VALUE_ERROR".to_string(),
                 field: "session_token".to_string(),
                 message: "session_token cannot be empty".to_string(),
                 details: json!({"min_length": 1, "received_length": 0, "required": true}),
-            });
+            }));
         }
         
         if language_code.is_empty() {
-            return Err(ValidationError {
+            return Err(Box::new(ValidationError {
                 code: "EMPTY_FIELD".to_string(),
                 error_type: "VALUE_ERROR".to_string(),
                 field: "language_code".to_string(),
                 message: "language_code cannot be empty".to_string(),
                 details: json!({"min_length": 1, "received_length": 0, "required": true}),
-            });
+            }));
         }
         
         if language_name.is_empty() {
-            return Err(ValidationError {
+            return Err(Box::new(ValidationError {
                 code: "EMPTY_FIELD".to_string(),
                 error_type: "VALUE_ERROR".to_string(),
                 field: "language_name".to_string(),
                 message: "language_name cannot be empty".to_string(),
                 details: json!({"min_length": 1, "received_length": 0, "required": true}),
-            });
+            }));
         }
         
         // Validate mobile number format (basic validation for 10-15 digits)
         if !mobile_no.chars().all(|c| c.is_digit(10)) {
-            return Err(ValidationError {
+            return Err(Box::new(ValidationError {
                 code: "INVALID_FORMAT".to_string(),
                 error_type: "FORMAT_ERROR".to_string(),
                 field: "mobile_no".to_string(),
@@ -631,11 +663,11 @@ impl ValidationManager {
                     "received_value": mobile_no,
                     "required": true
                 }),
-            });
+            }));
         }
         
         if mobile_no.len() < 10 || mobile_no.len() > 15 {
-            return Err(ValidationError {
+            return Err(Box::new(ValidationError {
                 code: "INVALID_LENGTH".to_string(),
                 error_type: "LENGTH_ERROR".to_string(),
                 field: "mobile_no".to_string(),
@@ -646,12 +678,12 @@ impl ValidationManager {
                     "received_length": mobile_no.len(),
                     "required": true
                 }),
-            });
+            }));
         }
         
         // Validate language code format (ISO 639-1: 2 letters)
         if !language_code.chars().all(|c| c.is_ascii_lowercase()) {
-            return Err(ValidationError {
+            return Err(Box::new(ValidationError {
                 code: "INVALID_FORMAT".to_string(),
                 error_type: "FORMAT_ERROR".to_string(),
                 field: "language_code".to_string(),
@@ -662,11 +694,11 @@ impl ValidationManager {
                     "received_value": language_code,
                     "required": true
                 }),
-            });
+            }));
         }
         
         if language_code.len() != 2 {
-            return Err(ValidationError {
+            return Err(Box::new(ValidationError {
                 code: "INVALID_LENGTH".to_string(),
                 error_type: "LENGTH_ERROR".to_string(),
                 field: "language_code".to_string(),
@@ -676,12 +708,12 @@ impl ValidationManager {
                     "received_length": language_code.len(),
                     "required": true
                 }),
-            });
+            }));
         }
         
         // Validate language name (should be reasonable length)
         if language_name.len() < 2 || language_name.len() > 50 {
-            return Err(ValidationError {
+            return Err(Box::new(ValidationError {
                 code: "INVALID_LENGTH".to_string(),
                 error_type: "LENGTH_ERROR".to_string(),
                 field: "language_name".to_string(),
@@ -692,13 +724,13 @@ impl ValidationManager {
                     "received_length": language_name.len(),
                     "required": true
                 }),
-            });
+            }));
         }
         
         // Validate optional region code if provided (ISO 3166-1 alpha-2: 2 uppercase letters)
         if let Some(region_val) = region_code {
             if !region_val.chars().all(|c| c.is_ascii_uppercase()) {
-                return Err(ValidationError {
+                return Err(Box::new(ValidationError {
                     code: "INVALID_FORMAT".to_string(),
                     error_type: "FORMAT_ERROR".to_string(),
                     field: "region_code".to_string(),
@@ -709,11 +741,11 @@ impl ValidationManager {
                         "received_value": region_val,
                         "required": false
                     }),
-                });
+                }));
             }
             
             if region_val.len() != 2 {
-                return Err(ValidationError {
+                return Err(Box::new(ValidationError {
                     code: "INVALID_LENGTH".to_string(),
                     error_type: "LENGTH_ERROR".to_string(),
                     field: "region_code".to_string(),
@@ -723,24 +755,24 @@ impl ValidationManager {
                         "received_length": region_val.len(),
                         "required": false
                     }),
-                });
+                }));
             }
         }
         
         // Validate optional timezone if provided (basic format check)
         if let Some(timezone_val) = timezone {
             if timezone_val.is_empty() {
-                return Err(ValidationError {
+                return Err(Box::new(ValidationError {
                     code: "EMPTY_FIELD".to_string(),
                     error_type: "VALUE_ERROR".to_string(),
                     field: "timezone".to_string(),
                     message: "timezone cannot be empty if provided".to_string(),
                     details: json!({"min_length": 1, "received_length": 0, "required": false}),
-                });
+                }));
             }
             
             if timezone_val.len() < 3 || timezone_val.len() > 50 {
-                return Err(ValidationError {
+                return Err(Box::new(ValidationError {
                     code: "INVALID_LENGTH".to_string(),
                     error_type: "LENGTH_ERROR".to_string(),
                     field: "timezone".to_string(),
@@ -751,14 +783,14 @@ impl ValidationManager {
                         "received_length": timezone_val.len(),
                         "required": false
                     }),
-                });
+                }));
             }
         }
         
         // Validate optional timestamp if provided
         if let Some(timestamp_val) = timestamp {
             if !timestamp_val.contains('T') || !timestamp_val.contains('Z') {
-                return Err(ValidationError {
+                return Err(Box::new(ValidationError {
                     code: "INVALID_FORMAT".to_string(),
                     error_type: "FORMAT_ERROR".to_string(),
                     field: "timestamp".to_string(),
@@ -769,7 +801,7 @@ impl ValidationManager {
                         "received_value": timestamp_val,
                         "required": false
                     }),
-                });
+                }));
             }
         }
         
@@ -778,111 +810,112 @@ impl ValidationManager {
     }
 
     // Validate user profile data
-    pub fn validate_user_profile_data(data: &Value) -> Result<(), ValidationError> {
+    pub fn validate_user_profile_data(data: &Value) -> Result<(), Box<ValidationError>> {
         // Check if data is an object
-        let obj = data.as_object().ok_or(ValidationError {
+        let obj = data.as_object().ok_or(Box::new(ValidationError {
             code: "INVALID_FORMAT".to_string(),
             error_type: "FORMAT_ERROR".to_string(),
             field: "root".to_string(),
             message: "User profile data must be a JSON object".to_string(),
             details: json!({"received_type": if data.is_object() { "object" } else if data.is_array() { "array" } else if data.is_string() { "string" } else if data.is_number() { "number" } else if data.is_boolean() { "boolean" } else { "null" }}),
-        })?;
+        }))?;
         
         // Required fields (mandatory)
         let mobile_no = obj
             .get("mobile_no")
             .and_then(|v| v.as_str())
-            .ok_or(ValidationError {
+            .ok_or(Box::new(ValidationError {
                 code: "MISSING_FIELD".to_string(),
                 error_type: "FIELD_ERROR".to_string(),
                 field: "mobile_no".to_string(),
                 message: "mobile_no is required and must be a string".to_string(),
                 details: json!({"field_type": "string", "required": true}),
-            })?;
+            }))?;
         
         let session_token = obj
             .get("session_token")
             .and_then(|v| v.as_str())
-            .ok_or(ValidationError {
+            .ok_or(Box::new(ValidationError {
                 code: "MISSING_FIELD".to_string(),
                 error_type: "FIELD_ERROR".to_string(),
                 field: "session_token".to_string(),
                 message: "session_token is required and must be a string".to_string(),
                 details: json!({"field_type": "string", "required": true}),
-            })?;
+            }))?;
         
         let full_name = obj
             .get("full_name")
             .and_then(|v| v.as_str())
-            .ok_or(ValidationError {
+            .ok_or(Box::new(ValidationError {
                 code: "MISSING_FIELD".to_string(),
                 error_type: "FIELD_ERROR".to_string(),
                 field: "full_name".to_string(),
                 message: "full_name is required and must be a string".to_string(),
                 details: json!({"field_type": "string", "required": true}),
-            })?;
+            }))?;
         
         let state = obj
             .get("state")
             .and_then(|v| v.as_str())
-            .ok_or(ValidationError {
+            .ok_or(Box::new(ValidationError {
                 code: "MISSING_FIELD".to_string(),
                 error_type: "FIELD_ERROR".to_string(),
                 field: "state".to_string(),
                 message: "state is required and must be a string".to_string(),
                 details: json!({"field_type": "string", "required": true}),
-            })?;
+            }))?;
         
         // Optional fields
         let referral_code = obj.get("referral_code").and_then(|v| v.as_str()).filter(|s| !s.trim().is_empty());
         let referred_by = obj.get("referred_by").and_then(|v| v.as_str()).filter(|s| !s.trim().is_empty());
         let _profile_data = obj.get("profile_data");
+        let date_of_birth = obj.get("date_of_birth").filter(|v| !v.is_null());
         let timestamp = obj.get("timestamp").and_then(|v| v.as_str());
         
         // Validate required field values
         if mobile_no.is_empty() {
-            return Err(ValidationError {
+            return Err(Box::new(ValidationError {
                 code: "EMPTY_FIELD".to_string(),
                 error_type: "VALUE_ERROR".to_string(),
                 field: "mobile_no".to_string(),
                 message: "mobile_no cannot be empty".to_string(),
                 details: json!({"min_length": 1, "received_length": 0, "required": true}),
-            });
+            }));
         }
         
         if session_token.is_empty() {
-            return Err(ValidationError {
+            return Err(Box::new(ValidationError {
                 code: "EMPTY_FIELD".to_string(),
                 error_type: "VALUE_ERROR".to_string(),
                 field: "session_token".to_string(),
                 message: "session_token cannot be empty".to_string(),
                 details: json!({"min_length": 1, "received_length": 0, "required": true}),
-            });
+            }));
         }
         
         if full_name.is_empty() {
-            return Err(ValidationError {
+            return Err(Box::new(ValidationError {
                 code: "EMPTY_FIELD".to_string(),
                 error_type: "VALUE_ERROR".to_string(),
                 field: "full_name".to_string(),
                 message: "full_name cannot be empty".to_string(),
                 details: json!({"min_length": 1, "received_length": 0, "required": true}),
-            });
+            }));
         }
         
         if state.is_empty() {
-            return Err(ValidationError {
+            return Err(Box::new(ValidationError {
                 code: "EMPTY_FIELD".to_string(),
                 error_type: "VALUE_ERROR".to_string(),
                 field: "state".to_string(),
                 message: "state cannot be empty".to_string(),
                 details: json!({"min_length": 1, "received_length": 0, "required": true}),
-            });
+            }));
         }
         
         // Validate mobile number format (basic validation for 10-15 digits)
         if !mobile_no.chars().all(|c| c.is_digit(10)) {
-            return Err(ValidationError {
+            return Err(Box::new(ValidationError {
                 code: "INVALID_FORMAT".to_string(),
                 error_type: "FORMAT_ERROR".to_string(),
                 field: "mobile_no".to_string(),
@@ -892,11 +925,11 @@ impl ValidationManager {
                     "received_value": mobile_no,
                     "required": true
                 }),
-            });
+            }));
         }
         
         if mobile_no.len() < 10 || mobile_no.len() > 15 {
-            return Err(ValidationError {
+            return Err(Box::new(ValidationError {
                 code: "INVALID_LENGTH".to_string(),
                 error_type: "LENGTH_ERROR".to_string(),
                 field: "mobile_no".to_string(),
@@ -907,12 +940,12 @@ impl ValidationManager {
                     "received_length": mobile_no.len(),
                     "required": true
                 }),
-            });
+            }));
         }
         
         // Validate full name (should be reasonable length and contain letters)
         if full_name.len() < 2 || full_name.len() > 100 {
-            return Err(ValidationError {
+            return Err(Box::new(ValidationError {
                 code: "INVALID_LENGTH".to_string(),
                 error_type: "LENGTH_ERROR".to_string(),
                 field: "full_name".to_string(),
@@ -923,12 +956,12 @@ impl ValidationManager {
                     "received_length": full_name.len(),
                     "required": true
                 }),
-            });
+            }));
         }
         
         // Check if full name contains at least some letters
         if !full_name.chars().any(|c| c.is_alphabetic()) {
-            return Err(ValidationError {
+            return Err(Box::new(ValidationError {
                 code: "INVALID_FORMAT".to_string(),
                 error_type: "FORMAT_ERROR".to_string(),
                 field: "full_name".to_string(),
@@ -938,12 +971,12 @@ impl ValidationManager {
                     "received_value": full_name,
                     "required": true
                 }),
-            });
+            }));
         }
         
         // Validate state (should be reasonable length)
         if state.len() < 2 || state.len() > 50 {
-            return Err(ValidationError {
+            return Err(Box::new(ValidationError {
                 code: "INVALID_LENGTH".to_string(),
                 error_type: "LENGTH_ERROR".to_string(),
                 field: "state".to_string(),
@@ -954,23 +987,23 @@ impl ValidationManager {
                     "received_length": state.len(),
                     "required": true
                 }),
-            });
+            }));
         }
         
         // Validate optional referral code if provided
         if let Some(ref_code) = referral_code {
             if ref_code.is_empty() {
-                return Err(ValidationError {
+                return Err(Box::new(ValidationError {
                     code: "EMPTY_FIELD".to_string(),
                     error_type: "VALUE_ERROR".to_string(),
                     field: "referral_code".to_string(),
                     message: "referral_code cannot be empty if provided".to_string(),
                     details: json!({"min_length": 1, "received_length": 0, "required": false}),
-                });
+                }));
             }
             
             if ref_code.len() < 4 || ref_code.len() > 20 {
-                return Err(ValidationError {
+                return Err(Box::new(ValidationError {
                     code: "INVALID_LENGTH".to_string(),
                     error_type: "LENGTH_ERROR".to_string(),
                     field: "referral_code".to_string(),
@@ -981,12 +1014,12 @@ impl ValidationManager {
                         "received_length": ref_code.len(),
                         "required": false
                     }),
-                });
+                }));
             }
             
             // Check if referral code contains only alphanumeric characters
             if !ref_code.chars().all(|c| c.is_alphanumeric()) {
-                return Err(ValidationError {
+                return Err(Box::new(ValidationError {
                     code: "INVALID_FORMAT".to_string(),
                     error_type: "FORMAT_ERROR".to_string(),
                     field: "referral_code".to_string(),
@@ -996,24 +1029,24 @@ impl ValidationManager {
                         "received_value": ref_code,
                         "required": false
                     }),
-                });
+                }));
             }
         }
         
         // Validate optional referred_by if provided
         if let Some(ref_by) = referred_by {
             if ref_by.is_empty() {
-                return Err(ValidationError {
+                return Err(Box::new(ValidationError {
                     code: "EMPTY_FIELD".to_string(),
                     error_type: "VALUE_ERROR".to_string(),
                     field: "referred_by".to_string(),
                     message: "referred_by cannot be empty if provided".to_string(),
                     details: json!({"min_length": 1, "received_length": 0, "required": false}),
-                });
+                }));
             }
             
             if ref_by.len() < 4 || ref_by.len() > 20 {
-                return Err(ValidationError {
+                return Err(Box::new(ValidationError {
                     code: "INVALID_LENGTH".to_string(),
                     error_type: "LENGTH_ERROR".to_string(),
                     field: "referred_by".to_string(),
@@ -1024,12 +1057,12 @@ impl ValidationManager {
                         "received_length": ref_by.len(),
                         "required": false
                     }),
-                });
+                }));
             }
             
             // Check if referred_by contains only alphanumeric characters
             if !ref_by.chars().all(|c| c.is_alphanumeric()) {
-                return Err(ValidationError {
+                return Err(Box::new(ValidationError {
                     code: "INVALID_FORMAT".to_string(),
                     error_type: "FORMAT_ERROR".to_string(),
                     field: "referred_by".to_string(),
@@ -1039,14 +1072,34 @@ impl ValidationManager {
                         "received_value": ref_by,
                         "required": false
                     }),
-                });
+                }));
+            }
+        }
+        
+        // Validate optional date of birth if provided (a past date, within a lifetime)
+        if let Some(dob) = date_of_birth {
+            let age = dob.as_str().and_then(|dob| ParentalManager::age_on(dob, chrono::Utc::now().date_naive()));
+            if !matches!(age, Some(age) if age <= parental::MAX_AGE) {
+                return Err(Box::new(ValidationError {
+                    code: "INVALID_FORMAT".to_string(),
+                    error_type: "FORMAT_ERROR".to_string(),
+                    field: "date_of_birth".to_string(),
+                    message: "date_of_birth must be a past date in the format YYYY-MM-DD".to_string(),
+                    details: json!({
+                        "expected_format": "YYYY-MM-DD",
+                        "example": "2008-04-23",
+                        "max_age": parental::MAX_AGE,
+                        "received_value": dob,
+                        "required": false
+                    }),
+                }));
             }
         }
         
         // Validate optional timestamp if provided
         if let Some(timestamp_val) = timestamp {
             if !timestamp_val.contains('T') || !timestamp_val.contains('Z') {
-                return Err(ValidationError {
+                return Err(Box::new(ValidationError {
                     code: "INVALID_FORMAT".to_string(),
                     error_type: "FORMAT_ERROR".to_string(),
                     field: "timestamp".to_string(),
@@ -1057,11 +1110,799 @@ impl ValidationManager {
                         "received_value": timestamp_val,
                         "required": false
                     }),
-                });
+                }));
             }
         }
         
         info!("✅ User profile data validation passed for mobile: {} (name: {})", mobile_no, full_name);
         Ok(())
     }
+
+    // Validate notification preference updates
+    pub fn validate_notification_preferences_data(data: &Value) -> Result<(), Box<ValidationError>> {
+        // Check if data is an object
+        let obj = data.as_object().ok_or(Box::new(ValidationError {
+            code: "INVALID_FORMAT".to_string(),
+            error_type: "FORMAT_ERROR".to_string(),
+            field: "root".to_string(),
+            message: "Notification preferences data must be a JSON object".to_string(),
+            details: json!({"received_type": if data.is_array() { "array" } else if data.is_string() { "string" } else if data.is_number() { "number" } else if data.is_boolean() { "boolean" } else { "null" }}),
+        }))?;
+
+        // Required fields (mandatory)
+        for field in ["mobile_no", "session_token"] {
+            obj.get(field)
+                .and_then(|v| v.as_str())
+                .ok_or(Box::new(ValidationError {
+                    code: "MISSING_FIELD".to_string(),
+                    error_type: "FIELD_ERROR".to_string(),
+                    field: field.to_string(),
+                    message: format!("{} is required and must be a string", field),
+                    details: json!({"field_type": "string", "required": true}),
+                }))?;
+        }
+
+        let notifications = obj
+            .get("notifications")
+            .and_then(|v| v.as_object())
+            .ok_or(Box::new(ValidationError {
+                code: "MISSING_FIELD".to_string(),
+                error_type: "FIELD_ERROR".to_string(),
+                field: "notifications".to_string(),
+                message: "notifications is required and must be an object".to_string(),
+                details: json!({"field_type": "object", "required": true, "categories": NotificationPreferences::CATEGORIES}),
+            }))?;
+
+        for (category, channels) in notifications {
+            if !NotificationPreferences::CATEGORIES.contains(&category.as_str()) {
+                return Err(Box::new(ValidationError {
+                    code: "INVALID_VALUE".to_string(),
+                    error_type: "VALUE_ERROR".to_string(),
+                    field: format!("notifications.{}", category),
+                    message: format!("Unknown notification category: {}", category),
+                    details: json!({"allowed_values": NotificationPreferences::CATEGORIES, "received_value": category}),
+                }));
+            }
+
+            let channels = channels.as_object().ok_or(Box::new(ValidationError {
+                code: "INVALID_TYPE".to_string(),
+                error_type: "TYPE_ERROR".to_string(),
+                field: format!("notifications.{}", category),
+                message: format!("notifications.{} must be an object", category),
+                details: json!({"expected_type": "object", "example": {"push": false, "inbox": true}}),
+            }))?;
+
+            for (channel, enabled) in channels {
+                if !["push", "inbox"].contains(&channel.as_str()) || !enabled.is_boolean() {
+                    return Err(Box::new(ValidationError {
+                        code: "INVALID_VALUE".to_string(),
+                        error_type: "VALUE_ERROR".to_string(),
+                        field: format!("notifications.{}.{}", category, channel),
+                        message: "Channel must be 'push' or 'inbox' with a boolean value".to_string(),
+                        details: json!({"allowed_channels": ["push", "inbox"], "expected_type": "boolean", "received_value": enabled}),
+                    }));
+                }
+            }
+        }
+
+        info!("✅ Notification preferences validation passed for mobile: {}", data["mobile_no"]);
+        Ok(())
+    }
+
+    // Validate device management data (devices:list, devices:remove)
+    pub fn validate_device_management_data(data: &Value, fields: &[&str]) -> Result<(), Box<ValidationError>> {
+        Self::validate_gameplay_fields(data, "Device management data", fields)?;
+        info!("✅ Device management data validation passed for mobile: {}", data["mobile_no"]);
+        Ok(())
+    }
+
+    // Validate preferences:get data - session fields plus an optional namespace filter
+    pub fn validate_preferences_get_data(data: &Value) -> Result<(), Box<ValidationError>> {
+        Self::validate_gameplay_fields(data, "Preferences data", &["mobile_no", "session_token"])?;
+
+        if let Some(namespace) = data.get("namespace").filter(|v| !v.is_null()) {
+            let namespaces = PreferencesManager::namespaces();
+            if !namespace.as_str().is_some_and(|ns| namespaces.contains(&ns)) {
+                return Err(Box::new(ValidationError {
+                    code: "INVALID_VALUE".to_string(),
+                    error_type: "VALUE_ERROR".to_string(),
+                    field: "namespace".to_string(),
+                    message: "Unknown preferences namespace".to_string(),
+                    details: json!({"allowed_values": namespaces, "received_value": namespace}),
+                }));
+            }
+        }
+
+        info!("✅ Preferences get validation passed for mobile: {}", data["mobile_no"]);
+        Ok(())
+    }
+
+    // Validate preferences:set data - namespaced keys, size limits and the
+    // value schema of known namespaces. null values (removals) are always allowed.
+    pub fn validate_preferences_set_data(data: &Value) -> Result<(), Box<ValidationError>> {
+        Self::validate_gameplay_fields(data, "Preferences data", &["mobile_no", "session_token"])?;
+
+        let updates = data
+            .get("preferences")
+            .and_then(|v| v.as_object())
+            .ok_or(Box::new(ValidationError {
+                code: "MISSING_FIELD".to_string(),
+                error_type: "FIELD_ERROR".to_string(),
+                field: "preferences".to_string(),
+                message: "preferences is required and must be an object".to_string(),
+                details: json!({"field_type": "object", "required": true, "example": {"audio.music_volume": 80}}),
+            }))?;
+
+        if updates.is_empty() || updates.len() > preferences::MAX_KEYS_PER_UPDATE {
+            return Err(Box::new(ValidationError {
+                code: "INVALID_LENGTH".to_string(),
+                error_type: "LENGTH_ERROR".to_string(),
+                field: "preferences".to_string(),
+                message: format!("preferences must contain between 1 and {} keys", preferences::MAX_KEYS_PER_UPDATE),
+                details: json!({"min_keys": 1, "max_keys": preferences::MAX_KEYS_PER_UPDATE, "received_keys": updates.len()}),
+            }));
+        }
+
+        for (key, value) in updates {
+            let field = format!("preferences.{}", key);
+            let (namespace, name) = PreferencesManager::split_key(key).ok_or(Box::new(ValidationError {
+                code: "INVALID_FORMAT".to_string(),
+                error_type: "FORMAT_ERROR".to_string(),
+                field: field.clone(),
+                message: "Preference keys must look like 'namespace.key' (lowercase letters, digits and underscores)".to_string(),
+                details: json!({"example": "audio.music_volume", "max_segment_length": preferences::MAX_SEGMENT_LENGTH, "received_value": key}),
+            }))?;
+
+            let value_bytes = value.to_string().len();
+            if value_bytes > preferences::MAX_VALUE_BYTES {
+                return Err(Box::new(ValidationError {
+                    code: "INVALID_LENGTH".to_string(),
+                    error_type: "LENGTH_ERROR".to_string(),
+                    field,
+                    message: format!("Preference values must be at most {} bytes", preferences::MAX_VALUE_BYTES),
+                    details: json!({"max_bytes": preferences::MAX_VALUE_BYTES, "received_bytes": value_bytes}),
+                }));
+            }
+
+            match PreferencesManager::rule_for(namespace, name) {
+                KeyRule::Freeform => {}
+                KeyRule::Known(rule) => {
+                    if !value.is_null() && !rule.accepts(value) {
+                        return Err(Box::new(ValidationError {
+                            code: "INVALID_VALUE".to_string(),
+                            error_type: "VALUE_ERROR".to_string(),
+                            field,
+                            message: format!("Invalid value for {}", key),
+                            details: json!({"rule": rule.describe(), "received_value": value}),
+                        }));
+                    }
+                }
+                KeyRule::UnknownKey(allowed) => {
+                    return Err(Box::new(ValidationError {
+                        code: "INVALID_VALUE".to_string(),
+                        error_type: "VALUE_ERROR".to_string(),
+                        field,
+                        message: format!("Unknown key '{}' in namespace '{}'", name, namespace),
+                        details: json!({"allowed_keys": allowed, "received_value": name}),
+                    }));
+                }
+                KeyRule::UnknownNamespace => {
+                    return Err(Box::new(ValidationError {
+                        code: "INVALID_VALUE".to_string(),
+                        error_type: "VALUE_ERROR".to_string(),
+                        field,
+                        message: format!("Unknown preferences namespace: {}", namespace),
+                        details: json!({"allowed_values": PreferencesManager::namespaces(), "received_value": namespace}),
+                    }));
+                }
+            }
+        }
+
+        info!("✅ Preferences set validation passed for mobile: {} ({} key(s))", data["mobile_no"], updates.len());
+        Ok(())
+    }
+
+    // Validate progress:update data - xp gained, level reached and an optional
+    // finished game. At least one of xp, level and game is required.
+    pub fn validate_progress_update_data(data: &Value) -> Result<(), Box<ValidationError>> {
+        Self::validate_gameplay_fields(data, "Progress data", &["mobile_no", "session_token"])?;
+
+        Self::validate_optional_int(data, "xp", "xp", 0, progress::MAX_XP_PER_UPDATE)?;
+        Self::validate_optional_int(data, "level", "level", 1, progress::MAX_LEVEL)?;
+
+        let present = |field: &str| data.get(field).is_some_and(|v| !v.is_null());
+        if !["xp", "level", "game"].iter().any(|field| present(field)) {
+            return Err(Box::new(ValidationError {
+                code: "MISSING_FIELD".to_string(),
+                error_type: "FIELD_ERROR".to_string(),
+                field: "root".to_string(),
+                message: "progress:update needs at least one of xp, level and game".to_string(),
+                details: json!({"fields": ["xp", "level", "game"], "example": {"xp": 120, "game": {"game_id": "ludo", "result": "win", "score": 42}}}),
+            }));
+        }
+
+        if let Some(game) = data.get("game").filter(|v| !v.is_null()) {
+            let game_id = game.get("game_id").and_then(|v| v.as_str()).ok_or(Box::new(ValidationError {
+                code: "MISSING_FIELD".to_string(),
+                error_type: "FIELD_ERROR".to_string(),
+                field: "game.game_id".to_string(),
+                message: "game.game_id is required and must be a string".to_string(),
+                details: json!({"field_type": "string", "required": true}),
+            }))?;
+            let valid_id = !game_id.is_empty()
+                && game_id.len() <= progress::MAX_GAME_ID_LENGTH
+                && game_id.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-');
+            if !valid_id {
+                return Err(Box::new(ValidationError {
+                    code: "INVALID_FORMAT".to_string(),
+                    error_type: "FORMAT_ERROR".to_string(),
+                    field: "game.game_id".to_string(),
+                    message: "game.game_id must be lowercase letters, digits, '_' or '-'".to_string(),
+                    details: json!({"max_length": progress::MAX_GAME_ID_LENGTH, "received_value": game_id}),
+                }));
+            }
+
+            let result = game.get("result").and_then(|v| v.as_str());
+            if result.and_then(GameOutcome::parse).is_none() {
+                return Err(Box::new(ValidationError {
+                    code: "INVALID_VALUE".to_string(),
+                    error_type: "VALUE_ERROR".to_string(),
+                    field: "game.result".to_string(),
+                    message: "game.result must be one of win, loss, draw".to_string(),
+                    details: json!({"allowed_values": ["win", "loss", "draw"], "received_value": game.get("result")}),
+                }));
+            }
+
+            Self::validate_optional_int(game, "score", "game.score", 0, progress::MAX_SCORE)?;
+
+            // The /gameplay room the game was played in, for the anomaly scans
+            if let Some(room_id) = game.get("room_id").filter(|v| !v.is_null()) {
+                let valid = room_id.as_str().is_some_and(|id| !id.is_empty() && id.len() <= 64);
+                if !valid {
+                    return Err(Box::new(ValidationError {
+                        code: "INVALID_VALUE".to_string(),
+                        error_type: "VALUE_ERROR".to_string(),
+                        field: "game.room_id".to_string(),
+                        message: "game.room_id must be a non-empty string of at most 64 characters".to_string(),
+                        details: json!({"field_type": "string", "max_length": 64, "received_value": room_id}),
+                    }));
+                }
+            }
+        }
+
+        info!("✅ Progress update validation passed for mobile: {}", data["mobile_no"]);
+        Ok(())
+    }
+
+    // Validate challenge:claim data
+    pub fn validate_challenge_claim_data(data: &Value) -> Result<(), Box<ValidationError>> {
+        Self::validate_gameplay_fields(data, "Challenge data", &["mobile_no", "session_token", "challenge_id"])?;
+        info!("✅ Challenge claim validation passed for mobile: {}", data["mobile_no"]);
+        Ok(())
+    }
+
+    // Validate leaderboard:get data - an optional season_id and paging
+    pub fn validate_leaderboard_data(data: &Value) -> Result<(), Box<ValidationError>> {
+        Self::validate_gameplay_fields(data, "Leaderboard data", &["mobile_no", "session_token"])?;
+
+        if let Some(season_id) = data.get("season_id").filter(|v| !v.is_null()) {
+            if !season_id.as_str().is_some_and(|id| !id.trim().is_empty() && id.len() <= 64) {
+                return Err(Box::new(ValidationError {
+                    code: "INVALID_VALUE".to_string(),
+                    error_type: "VALUE_ERROR".to_string(),
+                    field: "season_id".to_string(),
+                    message: "season_id must be a non-empty string".to_string(),
+                    details: json!({"field_type": "string", "max_length": 64, "received_value": season_id}),
+                }));
+            }
+        }
+        Self::validate_optional_int(data, "limit", "limit", 1, seasons::MAX_LEADERBOARD_LIMIT)?;
+        Self::validate_optional_int(data, "offset", "offset", 0, seasons::MAX_LEADERBOARD_OFFSET)?;
+
+        info!("✅ Leaderboard validation passed for mobile: {}", data["mobile_no"]);
+        Ok(())
+    }
+
+    // Validate friend:add and friend:remove data
+    pub fn validate_friend_data(data: &Value) -> Result<(), Box<ValidationError>> {
+        Self::validate_gameplay_fields(data, "Friend data", &["mobile_no", "session_token", "friend_user_id"])?;
+        info!("✅ Friend validation passed for mobile: {}", data["mobile_no"]);
+        Ok(())
+    }
+
+    // Validate gift:send data - a recipient and either coins or an item
+    pub fn validate_gift_data(data: &Value) -> Result<(), Box<ValidationError>> {
+        Self::validate_gameplay_fields(data, "Gift data", &["mobile_no", "session_token", "to_user_id"])?;
+
+        let present = |field: &str| data.get(field).is_some_and(|v| !v.is_null());
+        if present("coins") == present("item_id") {
+            return Err(Box::new(ValidationError {
+                code: "INVALID_VALUE".to_string(),
+                error_type: "VALUE_ERROR".to_string(),
+                field: "coins".to_string(),
+                message: "gift:send needs either coins or item_id".to_string(),
+                details: json!({"fields": ["coins", "item_id"], "example": {"to_user_id": "...", "coins": 100}}),
+            }));
+        }
+
+        if present("coins") {
+            Self::validate_optional_int(data, "coins", "coins", 1, CONFIG.gift_max_coins)?;
+            if present("quantity") {
+                return Err(Box::new(ValidationError {
+                    code: "INVALID_VALUE".to_string(),
+                    error_type: "VALUE_ERROR".to_string(),
+                    field: "quantity".to_string(),
+                    message: "quantity only applies to item gifts".to_string(),
+                    details: json!({"received_value": data["quantity"]}),
+                }));
+            }
+        } else {
+            let item_id = data["item_id"].as_str().unwrap_or_default();
+            let valid_id = !item_id.is_empty()
+                && item_id.len() <= gifts::MAX_ITEM_ID_LENGTH
+                && item_id.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-');
+            if !valid_id {
+                return Err(Box::new(ValidationError {
+                    code: "INVALID_FORMAT".to_string(),
+                    error_type: "FORMAT_ERROR".to_string(),
+                    field: "item_id".to_string(),
+                    message: "item_id must be lowercase letters, digits, '_' or '-'".to_string(),
+                    details: json!({"max_length": gifts::MAX_ITEM_ID_LENGTH, "received_value": data["item_id"]}),
+                }));
+            }
+            Self::validate_optional_int(data, "quantity", "quantity", 1, gifts::MAX_GIFT_ITEM_QUANTITY)?;
+        }
+
+        info!("✅ Gift validation passed for mobile: {}", data["mobile_no"]);
+        Ok(())
+    }
+
+    // Validate promo:redeem data - a code of letters, digits, '_' or '-'
+    pub fn validate_promo_redeem_data(data: &Value) -> Result<(), Box<ValidationError>> {
+        Self::validate_gameplay_fields(data, "Promo data", &["mobile_no", "session_token", "code"])?;
+
+        if promos::PromoManager::normalize(data["code"].as_str().unwrap_or_default()).is_none() {
+            return Err(Box::new(ValidationError {
+                code: "INVALID_FORMAT".to_string(),
+                error_type: "FORMAT_ERROR".to_string(),
+                field: "code".to_string(),
+                message: "code must be letters, digits, '_' or '-'".to_string(),
+                details: json!({
+                    "min_length": promos::MIN_CODE_LENGTH,
+                    "max_length": promos::MAX_CODE_LENGTH,
+                    "received_length": data["code"].as_str().map(str::len)
+                }),
+            }));
+        }
+
+        info!("✅ Promo redeem validation passed for mobile: {}", data["mobile_no"]);
+        Ok(())
+    }
+
+    // Validate user:block and user:unblock data
+    pub fn validate_block_data(data: &Value) -> Result<(), Box<ValidationError>> {
+        Self::validate_gameplay_fields(data, "Block data", &["mobile_no", "session_token", "target_user_id"])?;
+        info!("✅ Block validation passed for mobile: {}", data["mobile_no"]);
+        Ok(())
+    }
+
+    // Validate user:report data - the reported user, a category, and an
+    // optional description and room
+    pub fn validate_report_data(data: &Value) -> Result<(), Box<ValidationError>> {
+        Self::validate_gameplay_fields(data, "Report data", &["mobile_no", "session_token", "target_user_id"])?;
+
+        let category = data.get("category").and_then(|v| v.as_str());
+        if category.and_then(ReportCategory::parse).is_none() {
+            let allowed: Vec<&str> = ReportCategory::ALL.iter().map(|c| c.as_str()).collect();
+            return Err(Box::new(ValidationError {
+                code: "INVALID_VALUE".to_string(),
+                error_type: "VALUE_ERROR".to_string(),
+                field: "category".to_string(),
+                message: format!("category must be one of {}", allowed.join(", ")),
+                details: json!({"allowed_values": allowed, "received_value": data.get("category")}),
+            }));
+        }
+
+        if let Some(description) = data.get("description").filter(|v| !v.is_null()) {
+            let valid = description.as_str().is_some_and(|d| d.trim().chars().count() <= moderation::MAX_REPORT_DESCRIPTION_LENGTH);
+            if !valid {
+                return Err(Box::new(ValidationError {
+                    code: "INVALID_LENGTH".to_string(),
+                    error_type: "LENGTH_ERROR".to_string(),
+                    field: "description".to_string(),
+                    message: format!("description must be a string of at most {} characters", moderation::MAX_REPORT_DESCRIPTION_LENGTH),
+                    details: json!({"field_type": "string", "max_length": moderation::MAX_REPORT_DESCRIPTION_LENGTH}),
+                }));
+            }
+        }
+
+        if let Some(room_id) = data.get("room_id").filter(|v| !v.is_null()) {
+            if !room_id.as_str().is_some_and(|id| !id.is_empty() && id.len() <= 64) {
+                return Err(Box::new(ValidationError {
+                    code: "INVALID_VALUE".to_string(),
+                    error_type: "VALUE_ERROR".to_string(),
+                    field: "room_id".to_string(),
+                    message: "room_id must be a non-empty string of at most 64 characters".to_string(),
+                    details: json!({"field_type": "string", "max_length": 64, "received_value": room_id}),
+                }));
+            }
+        }
+
+        info!("✅ Report validation passed for mobile: {}", data["mobile_no"]);
+        Ok(())
+    }
+
+    // Validate gameplay room data (room:join, player_action)
+    pub fn validate_room_data(data: &Value) -> Result<(), Box<ValidationError>> {
+        Self::validate_gameplay_fields(data, "Room data", &["room_id", "player_id"])?;
+        info!("✅ Room data validation passed for room: {}", data["room_id"]);
+        Ok(())
+    }
+
+    // Validate room:viewer_list data - `enabled` turns the list of spectators on or off
+    pub fn validate_viewer_list_data(data: &Value) -> Result<(), Box<ValidationError>> {
+        Self::validate_gameplay_fields(data, "Viewer list data", &["room_id", "player_id"])?;
+        if !data["enabled"].is_boolean() {
+            return Err(Box::new(ValidationError {
+                code: "INVALID_TYPE".to_string(),
+                error_type: "TYPE_ERROR".to_string(),
+                field: "enabled".to_string(),
+                message: "enabled is required and must be a boolean".to_string(),
+                details: json!({"expected_type": "boolean", "received_value": data["enabled"]}),
+            }));
+        }
+        Ok(())
+    }
+
+    // Validate dealer:shuffle data - the deck's cards, each a short label
+    pub fn validate_dealer_shuffle_data(data: &Value) -> Result<(), Box<ValidationError>> {
+        Self::validate_gameplay_fields(data, "Dealer shuffle data", &["room_id", "player_id", "deck_id"])?;
+        let cards_ok = data["cards"].as_array().is_some_and(|cards| {
+            (1..=dealer::MAX_DECK_SIZE).contains(&cards.len())
+                && cards.iter().all(|card| card.as_str().is_some_and(|card| !card.is_empty() && card.chars().count() <= dealer::MAX_CARD_LENGTH))
+        });
+        if !cards_ok {
+            return Err(Box::new(ValidationError {
+                code: "INVALID_FORMAT".to_string(),
+                error_type: "FORMAT_ERROR".to_string(),
+                field: "cards".to_string(),
+                message: format!("cards must be 1 to {} strings of 1 to {} characters", dealer::MAX_DECK_SIZE, dealer::MAX_CARD_LENGTH),
+                details: json!({"max_items": dealer::MAX_DECK_SIZE, "max_length": dealer::MAX_CARD_LENGTH}),
+            }));
+        }
+        Ok(())
+    }
+
+    // Validate dealer:draw data - how many cards, optionally face up
+    pub fn validate_dealer_draw_data(data: &Value) -> Result<(), Box<ValidationError>> {
+        Self::validate_gameplay_fields(data, "Dealer draw data", &["room_id", "player_id", "deck_id"])?;
+        Self::validate_required_int(data, "count", 1, dealer::MAX_DECK_SIZE as i64)?;
+        if !data["face_up"].is_null() && !data["face_up"].is_boolean() {
+            return Err(Box::new(ValidationError {
+                code: "INVALID_TYPE".to_string(),
+                error_type: "TYPE_ERROR".to_string(),
+                field: "face_up".to_string(),
+                message: "face_up must be a boolean".to_string(),
+                details: json!({"expected_type": "boolean", "received_value": data["face_up"]}),
+            }));
+        }
+        Ok(())
+    }
+
+    // Validate dealer:roll data - how many dice and how many sides each
+    pub fn validate_dealer_roll_data(data: &Value) -> Result<(), Box<ValidationError>> {
+        Self::validate_gameplay_fields(data, "Dealer roll data", &["room_id", "player_id"])?;
+        Self::validate_required_int(data, "dice", 1, dealer::MAX_DICE as i64)?;
+        Self::validate_required_int(data, "sides", 2, dealer::MAX_SIDES as i64)?;
+        Ok(())
+    }
+
+    // Validate room:update data - a non-empty delta object of limited size
+    pub fn validate_room_update_data(data: &Value) -> Result<(), Box<ValidationError>> {
+        Self::validate_gameplay_fields(data, "Room update data", &["room_id", "player_id"])?;
+        let size = serde_json::to_vec(&data["delta"]).map_or(0, |bytes| bytes.len());
+        let delta_ok = data["delta"].as_object().is_some_and(|delta| !delta.is_empty()) && size <= room_state::MAX_DELTA_BYTES;
+        if !delta_ok {
+            return Err(Box::new(ValidationError {
+                code: "INVALID_FORMAT".to_string(),
+                error_type: "FORMAT_ERROR".to_string(),
+                field: "delta".to_string(),
+                message: format!("delta must be a non-empty object of at most {} bytes", room_state::MAX_DELTA_BYTES),
+                details: json!({"expected_type": "object", "max_bytes": room_state::MAX_DELTA_BYTES, "received_bytes": size}),
+            }));
+        }
+        Ok(())
+    }
+
+    // Validate room:state_ack data - the seq of the room:state the client applied
+    pub fn validate_room_state_ack_data(data: &Value) -> Result<(), Box<ValidationError>> {
+        Self::validate_gameplay_fields(data, "Room state ack data", &["room_id"])?;
+        Self::validate_required_int(data, "seq", 0, i64::MAX)?;
+        Ok(())
+    }
+
+    // Validate room:state_sync data
+    pub fn validate_room_state_sync_data(data: &Value) -> Result<(), Box<ValidationError>> {
+        Self::validate_gameplay_fields(data, "Room state sync data", &["room_id"])
+    }
+
+    // Validate subscribe and unsubscribe data - dot-separated paths of the
+    // room's state; required for subscribe, optional for unsubscribe
+    pub fn validate_subscribe_data(data: &Value, paths_required: bool) -> Result<(), Box<ValidationError>> {
+        Self::validate_gameplay_fields(data, "Subscription data", &["room_id"])?;
+        if !paths_required && data["paths"].is_null() {
+            return Ok(());
+        }
+        let paths_ok = data["paths"].as_array().is_some_and(|paths| {
+            (1..=room_state::MAX_SUBSCRIPTIONS).contains(&paths.len())
+                && paths.iter().all(|path| path.as_str().is_some_and(|path| {
+                    path.chars().count() <= room_state::MAX_PATH_LENGTH && path.split('.').all(|key| !key.is_empty())
+                }))
+        });
+        if !paths_ok {
+            return Err(Box::new(ValidationError {
+                code: "INVALID_FORMAT".to_string(),
+                error_type: "FORMAT_ERROR".to_string(),
+                field: "paths".to_string(),
+                message: format!("paths must be 1 to {} dot-separated paths of at most {} characters", room_state::MAX_SUBSCRIPTIONS, room_state::MAX_PATH_LENGTH),
+                details: json!({"example": "tables.3", "max_items": room_state::MAX_SUBSCRIPTIONS, "max_length": room_state::MAX_PATH_LENGTH}),
+            }));
+        }
+        Ok(())
+    }
+
+    // Validate matchmaking data (matchmaking:join, matchmaking:leave)
+    pub fn validate_matchmaking_data(data: &Value) -> Result<(), Box<ValidationError>> {
+        Self::validate_gameplay_fields(data, "Matchmaking data", &["player_id"])?;
+        info!("✅ Matchmaking data validation passed for player: {}", data["player_id"]);
+        Ok(())
+    }
+
+    // Validate party data (party:create, party:invite, party:accept, ...)
+    pub fn validate_party_data(data: &Value, fields: &[&str]) -> Result<(), Box<ValidationError>> {
+        Self::validate_gameplay_fields(data, "Party data", fields)?;
+        info!("✅ Party data validation passed for player: {}", data["player_id"]);
+        Ok(())
+    }
+
+    // Validate party chat data - player_id plus a bounded, non-empty message
+    pub fn validate_party_chat_data(data: &Value) -> Result<(), Box<ValidationError>> {
+        Self::validate_gameplay_fields(data, "Party chat data", &["player_id"])?;
+        Self::validate_chat_message(data)?;
+        info!("✅ Party chat validation passed for player: {}", data["player_id"]);
+        Ok(())
+    }
+
+    // Validate chat:send data - room_id, player_id and the message
+    pub fn validate_chat_data(data: &Value) -> Result<(), Box<ValidationError>> {
+        Self::validate_gameplay_fields(data, "Chat data", &["room_id", "player_id"])?;
+        Self::validate_chat_message(data)?;
+        Self::validate_chat_attachments(data)?;
+        info!("✅ Chat validation passed for player: {}", data["player_id"]);
+        Ok(())
+    }
+
+    // Validate chat:edit data - the message to edit and its new text
+    pub fn validate_chat_edit_data(data: &Value) -> Result<(), Box<ValidationError>> {
+        Self::validate_gameplay_fields(data, "Chat edit data", &["room_id", "player_id", "message_id"])?;
+        Self::validate_chat_message(data)?;
+        info!("✅ Chat edit validation passed for message: {}", data["message_id"]);
+        Ok(())
+    }
+
+    // Validate chat:delete data
+    pub fn validate_chat_delete_data(data: &Value) -> Result<(), Box<ValidationError>> {
+        Self::validate_gameplay_fields(data, "Chat delete data", &["room_id", "player_id", "message_id"])?;
+        info!("✅ Chat delete validation passed for message: {}", data["message_id"]);
+        Ok(())
+    }
+
+    // Validate chat:typing data - `typing` is whether the player started or stopped
+    pub fn validate_chat_typing_data(data: &Value) -> Result<(), Box<ValidationError>> {
+        Self::validate_gameplay_fields(data, "Chat typing data", &["room_id", "player_id"])?;
+        if !data["typing"].is_boolean() {
+            return Err(Box::new(ValidationError {
+                code: "INVALID_TYPE".to_string(),
+                error_type: "TYPE_ERROR".to_string(),
+                field: "typing".to_string(),
+                message: "typing is required and must be a boolean".to_string(),
+                details: json!({"expected_type": "boolean", "received_value": data["typing"]}),
+            }));
+        }
+        Ok(())
+    }
+
+    // Validate chat:read data - the newest message the player has seen
+    pub fn validate_chat_read_data(data: &Value) -> Result<(), Box<ValidationError>> {
+        Self::validate_gameplay_fields(data, "Chat read data", &["room_id", "player_id", "message_id"])?;
+        Ok(())
+    }
+
+    // Validate chat:react data - one of the fixed reactions, optional `remove`
+    pub fn validate_chat_react_data(data: &Value) -> Result<(), Box<ValidationError>> {
+        Self::validate_gameplay_fields(data, "Chat react data", &["room_id", "player_id", "message_id"])?;
+        let allowed: Vec<&str> = chat::REACTIONS.iter().map(|(name, _)| *name).collect();
+        if !data["reaction"].as_str().is_some_and(|reaction| allowed.contains(&reaction)) {
+            return Err(Box::new(ValidationError {
+                code: "INVALID_VALUE".to_string(),
+                error_type: "VALUE_ERROR".to_string(),
+                field: "reaction".to_string(),
+                message: "reaction must be one of the supported reactions".to_string(),
+                details: json!({"allowed_values": allowed, "received_value": data["reaction"]}),
+            }));
+        }
+        if !data["remove"].is_null() && !data["remove"].is_boolean() {
+            return Err(Box::new(ValidationError {
+                code: "INVALID_TYPE".to_string(),
+                error_type: "TYPE_ERROR".to_string(),
+                field: "remove".to_string(),
+                message: "remove must be a boolean".to_string(),
+                details: json!({"expected_type": "boolean", "received_value": data["remove"]}),
+            }));
+        }
+        Ok(())
+    }
+
+    // Validate chat:history data - optional `before` cursor and page `limit`
+    pub fn validate_chat_history_data(data: &Value) -> Result<(), Box<ValidationError>> {
+        Self::validate_gameplay_fields(data, "Chat history data", &["room_id", "player_id"])?;
+        if data.get("before").is_some_and(|before| !before.is_null()) {
+            Self::validate_gameplay_fields(data, "Chat history data", &["before"])?;
+        }
+        Self::validate_optional_int(data, "limit", "limit", 1, chat::MAX_HISTORY_LIMIT)?;
+        info!("✅ Chat history validation passed for room: {}", data["room_id"]);
+        Ok(())
+    }
+
+    // Optional `attachments`: up to chat::MAX_ATTACHMENTS of { url, content_type, size_bytes }.
+    // Which hosts, types and sizes are accepted is chat policy, checked later.
+    fn validate_chat_attachments(data: &Value) -> Result<(), Box<ValidationError>> {
+        let attachments = match &data["attachments"] {
+            Value::Null => return Ok(()),
+            Value::Array(attachments) => attachments,
+            other => return Err(Box::new(ValidationError {
+                code: "INVALID_TYPE".to_string(),
+                error_type: "TYPE_ERROR".to_string(),
+                field: "attachments".to_string(),
+                message: "attachments must be an array".to_string(),
+                details: json!({"expected_type": "array", "received_value": other}),
+            })),
+        };
+        if attachments.len() > chat::MAX_ATTACHMENTS {
+            return Err(Box::new(ValidationError {
+                code: "INVALID_LENGTH".to_string(),
+                error_type: "LENGTH_ERROR".to_string(),
+                field: "attachments".to_string(),
+                message: format!("attachments can hold at most {} images", chat::MAX_ATTACHMENTS),
+                details: json!({"max_items": chat::MAX_ATTACHMENTS, "received_items": attachments.len()}),
+            }));
+        }
+        for (index, attachment) in attachments.iter().enumerate() {
+            let url_ok = attachment["url"].as_str().is_some_and(|url| !url.is_empty() && url.len() <= chat::MAX_ATTACHMENT_URL_LENGTH);
+            let content_type_ok = attachment["content_type"].as_str().is_some_and(|content_type| !content_type.is_empty());
+            let size_ok = attachment["size_bytes"].as_i64().is_some_and(|size| size >= 0);
+            if !(url_ok && content_type_ok && size_ok) {
+                return Err(Box::new(ValidationError {
+                    code: "INVALID_FORMAT".to_string(),
+                    error_type: "FORMAT_ERROR".to_string(),
+                    field: "attachments".to_string(),
+                    message: format!("attachments[{}] must have a url of up to {} characters, a content_type and a whole size_bytes", index, chat::MAX_ATTACHMENT_URL_LENGTH),
+                    details: json!({"index": index, "received_value": attachment}),
+                }));
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_chat_message(data: &Value) -> Result<(), Box<ValidationError>> {
+        let message = data
+            .get("message")
+            .and_then(|v| v.as_str())
+            .ok_or(Box::new(ValidationError {
+                code: "MISSING_FIELD".to_string(),
+                error_type: "FIELD_ERROR".to_string(),
+                field: "message".to_string(),
+                message: "message is required and must be a string".to_string(),
+                details: json!({"field_type": "string", "required": true}),
+            }))?;
+
+        if message.trim().is_empty() {
+            return Err(Box::new(ValidationError {
+                code: "EMPTY_FIELD".to_string(),
+                error_type: "VALUE_ERROR".to_string(),
+                field: "message".to_string(),
+                message: "message cannot be empty".to_string(),
+                details: json!({"min_length": 1, "received_length": 0, "required": true}),
+            }));
+        }
+
+        if message.chars().count() > 500 {
+            return Err(Box::new(ValidationError {
+                code: "INVALID_LENGTH".to_string(),
+                error_type: "LENGTH_ERROR".to_string(),
+                field: "message".to_string(),
+                message: "message must be at most 500 characters".to_string(),
+                details: json!({"max_length": 500, "received_length": message.chars().count(), "required": true}),
+            }));
+        }
+        Ok(())
+    }
+
+    // An optional integer field within min..=max; null counts as absent
+    fn validate_optional_int(data: &Value, key: &str, field: &str, min: i64, max: i64) -> Result<(), Box<ValidationError>> {
+        let Some(value) = data.get(key).filter(|v| !v.is_null()) else {
+            return Ok(());
+        };
+        if !value.as_i64().is_some_and(|v| (min..=max).contains(&v)) {
+            return Err(Box::new(ValidationError {
+                code: "INVALID_VALUE".to_string(),
+                error_type: "VALUE_ERROR".to_string(),
+                field: field.to_string(),
+                message: format!("{} must be an integer between {} and {}", field, min, max),
+                details: json!({"expected_type": "integer", "min": min, "max": max, "received_value": value}),
+            }));
+        }
+        Ok(())
+    }
+
+    fn validate_required_int(data: &Value, field: &str, min: i64, max: i64) -> Result<(), Box<ValidationError>> {
+        if data[field].is_null() {
+            return Err(Box::new(ValidationError {
+                code: "MISSING_FIELD".to_string(),
+                error_type: "FIELD_ERROR".to_string(),
+                field: field.to_string(),
+                message: format!("{} is required and must be an integer", field),
+                details: json!({"field_type": "integer", "required": true}),
+            }));
+        }
+        Self::validate_optional_int(data, field, field, min, max)
+    }
+
+    // Shared checks for gameplay payloads: required, non-empty, bounded string identifiers
+    fn validate_gameplay_fields(data: &Value, label: &str, fields: &[&str]) -> Result<(), Box<ValidationError>> {
+        // Check if data is an object
+        let obj = data.as_object().ok_or(Box::new(ValidationError {
+            code: "INVALID_FORMAT".to_string(),
+            error_type: "FORMAT_ERROR".to_string(),
+            field: "root".to_string(),
+            message: format!("{} must be a JSON object", label),
+            details: json!({"received_type": if data.is_array() { "array" } else if data.is_string() { "string" } else if data.is_number() { "number" } else if data.is_boolean() { "boolean" } else { "null" }}),
+        }))?;
+
+        // Required fields (mandatory)
+        for field in fields {
+            let value = obj
+                .get(*field)
+                .and_then(|v| v.as_str())
+                .ok_or(Box::new(ValidationError {
+                    code: "MISSING_FIELD".to_string(),
+                    error_type: "FIELD_ERROR".to_string(),
+                    field: field.to_string(),
+                    message: format!("{} is required and must be a string", field),
+                    details: json!({"field_type": "string", "required": true}),
+                }))?;
+
+            if value.is_empty() {
+                return Err(Box::new(ValidationError {
+                    code: "EMPTY_FIELD".to_string(),
+                    error_type: "VALUE_ERROR".to_string(),
+                    field: field.to_string(),
+                    message: format!("{} cannot be empty", field),
+                    details: json!({"min_length": 1, "received_length": 0, "required": true}),
+                }));
+            }
+
+            if value.len() > 64 {
+                return Err(Box::new(ValidationError {
+                    code: "INVALID_LENGTH".to_string(),
+                    error_type: "LENGTH_ERROR".to_string(),
+                    field: field.to_string(),
+                    message: format!("{} must be at most 64 characters", field),
+                    details: json!({"max_length": 64, "received_length": value.len(), "required": true}),
+                }));
+            }
+        }
+
+        Ok(())
+    }
 } 
\ No newline at end of file
@@ -0,0 +1,224 @@
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+use tracing::{error, info, warn};
+use wasmtime::{Config, Engine, Instance, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+use crate::config::CONFIG;
+use crate::managers::bot::BotPlayer;
+use crate::managers::game_modes::{GameMode, GameModeRegistry};
+
+// Largest action a plugin may produce for a bot
+const MAX_BOT_ACTION_BYTES: usize = 64 * 1024;
+
+// What a plugin can use while it runs: its memory limit and nothing else
+struct PluginState {
+    limits: StoreLimits,
+}
+
+// A rule module compiled from <name>.wasm. Every call runs in a fresh
+// instance with no imports, so a plugin cannot reach the host, keep state
+// between calls or run past GAME_RULE_PLUGIN_FUEL; a trap or exhausted fuel
+// rejects the action (or falls back to the classic bot) instead of failing
+// the room.
+//
+// Exports a plugin provides (actions are passed as UTF-8 JSON):
+//   memory
+//   alloc(len: i32) -> i32                   buffer for the host to write an action into
+//   validate_action(ptr: i32, len: i32) -> i32   0 accepts, anything else rejects
+//   score_action(ptr: i32, len: i32) -> i64      optional, points of an accepted action
+//   bot_action(turn_number: i32) -> i64          optional, (ptr << 32) | len of the bot's action
+pub struct WasmRules {
+    name: String,
+    path: PathBuf,
+    engine: Engine,
+    module: RwLock<Module>,
+    modified: Mutex<Option<SystemTime>>,
+}
+
+impl WasmRules {
+    fn load(engine: &Engine, name: &str, path: &Path) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        let module = Module::new(engine, std::fs::read(path)?)?;
+        Ok(Self {
+            name: name.to_string(),
+            path: path.to_path_buf(),
+            engine: engine.clone(),
+            module: RwLock::new(module),
+            modified: Mutex::new(modified),
+        })
+    }
+
+    // Recompile the plugin if its file changed. A plugin that no longer
+    // compiles keeps running its previous version.
+    fn refresh(&self) {
+        let Ok(modified) = std::fs::metadata(&self.path).and_then(|m| m.modified()) else {
+            return;
+        };
+        let mut last = self.modified.lock().unwrap_or_else(|e| e.into_inner());
+        if *last == Some(modified) {
+            return;
+        }
+        *last = Some(modified);
+        match std::fs::read(&self.path).map_err(|e| e.to_string())
+            .and_then(|bytes| Module::new(&self.engine, bytes).map_err(|e| e.to_string()))
+        {
+            Ok(module) => {
+                *self.module.write().unwrap_or_else(|e| e.into_inner()) = module;
+                info!("🧩 Reloaded rule plugin {} from {}", self.name, self.path.display());
+            }
+            Err(e) => error!("❌ Rule plugin {} changed but failed to compile - keeping the previous version: {}", self.name, e),
+        }
+    }
+
+    fn instantiate(&self) -> Result<(Store<PluginState>, Instance), Box<dyn std::error::Error + Send + Sync>> {
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(CONFIG.rule_plugin_memory_mb * 1024 * 1024)
+            .instances(1)
+            .build();
+        let mut store = Store::new(&self.engine, PluginState { limits });
+        store.limiter(|state| &mut state.limits);
+        store.set_fuel(CONFIG.rule_plugin_fuel)?;
+        let module = self.module.read().unwrap_or_else(|e| e.into_inner()).clone();
+        let instance = Instance::new(&mut store, &module, &[])?;
+        Ok((store, instance))
+    }
+
+    // Copy an action into a new instance; returns its pointer and length
+    fn write_action(store: &mut Store<PluginState>, instance: &Instance, action: &Value) -> Result<(i32, i32), Box<dyn std::error::Error + Send + Sync>> {
+        let bytes = serde_json::to_vec(action)?;
+        let len = i32::try_from(bytes.len())?;
+        let alloc = instance.get_typed_func::<i32, i32>(&mut *store, "alloc")?;
+        let ptr = alloc.call(&mut *store, len)?;
+        let memory = instance.get_memory(&mut *store, "memory").ok_or("plugin exports no memory")?;
+        memory.write(&mut *store, ptr as u32 as usize, &bytes)?;
+        Ok((ptr, len))
+    }
+
+    fn try_validate(&self, action: &Value) -> Result<i32, Box<dyn std::error::Error + Send + Sync>> {
+        let (mut store, instance) = self.instantiate()?;
+        let (ptr, len) = Self::write_action(&mut store, &instance, action)?;
+        let validate = instance.get_typed_func::<(i32, i32), i32>(&mut store, "validate_action")?;
+        Ok(validate.call(&mut store, (ptr, len))?)
+    }
+
+    fn try_score(&self, action: &Value) -> Result<Option<i64>, Box<dyn std::error::Error + Send + Sync>> {
+        let (mut store, instance) = self.instantiate()?;
+        let Some(score) = instance.get_func(&mut store, "score_action") else {
+            return Ok(None);
+        };
+        let score = score.typed::<(i32, i32), i64>(&store)?;
+        let (ptr, len) = Self::write_action(&mut store, &instance, action)?;
+        Ok(Some(score.call(&mut store, (ptr, len))?))
+    }
+
+    fn try_bot_action(&self, turn_number: u32) -> Result<Option<Value>, Box<dyn std::error::Error + Send + Sync>> {
+        let (mut store, instance) = self.instantiate()?;
+        let Some(bot_action) = instance.get_func(&mut store, "bot_action") else {
+            return Ok(None);
+        };
+        let bot_action = bot_action.typed::<i32, i64>(&store)?;
+        let packed = bot_action.call(&mut store, turn_number as i32)? as u64;
+        let (ptr, len) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+        if len > MAX_BOT_ACTION_BYTES {
+            return Err(format!("bot action of {} bytes is over the {} byte limit", len, MAX_BOT_ACTION_BYTES).into());
+        }
+        let memory = instance.get_memory(&mut store, "memory").ok_or("plugin exports no memory")?;
+        let mut bytes = vec![0; len];
+        memory.read(&store, ptr, &mut bytes)?;
+        Ok(Some(serde_json::from_slice(&bytes)?))
+    }
+}
+
+impl GameMode for WasmRules {
+    fn validate_action(&self, action: &Value) -> Result<(), String> {
+        match self.try_validate(action) {
+            Ok(0) => Ok(()),
+            Ok(code) => Err(format!("Action refused by the {} rules (code {})", self.name, code)),
+            Err(e) => {
+                warn!("⚠️ Rule plugin {} failed to validate an action: {}", self.name, e);
+                Err(format!("The {} rules could not check this action", self.name))
+            }
+        }
+    }
+
+    fn bot_action(&self, turn_number: u32) -> Value {
+        match self.try_bot_action(turn_number) {
+            Ok(Some(action)) => action,
+            Ok(None) => BotPlayer::choose_action(turn_number),
+            Err(e) => {
+                warn!("⚠️ Rule plugin {} failed to choose a bot action - using the classic bot: {}", self.name, e);
+                BotPlayer::choose_action(turn_number)
+            }
+        }
+    }
+
+    fn score_action(&self, action: &Value) -> Option<i64> {
+        self.try_score(action).unwrap_or_else(|e| {
+            warn!("⚠️ Rule plugin {} failed to score an action: {}", self.name, e);
+            None
+        })
+    }
+}
+
+// Loads the rule plugins in GAME_RULE_PLUGINS_DIR as rule modules for
+// GAME_MODES, named after their files, and recompiles them when a file is
+// replaced so rules can change without a redeploy. New plugin files are only
+// picked up on restart.
+pub struct WasmRulesHost;
+
+impl WasmRulesHost {
+    // Must run before the gameplay namespaces are registered
+    pub fn load_plugins() {
+        let Some(dir) = CONFIG.rule_plugins_dir.as_deref() else {
+            return;
+        };
+        let engine = match Engine::new(Config::new().consume_fuel(true)) {
+            Ok(engine) => engine,
+            Err(e) => {
+                error!("❌ Failed to start the WASM engine - no rule plugins loaded: {}", e);
+                return;
+            }
+        };
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                error!("❌ Failed to read rule plugin directory {}: {}", dir, e);
+                return;
+            }
+        };
+
+        let mut plugins = Vec::new();
+        for path in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+            if path.extension().and_then(|ext| ext.to_str()) != Some("wasm") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()).map(str::to_string) else {
+                continue;
+            };
+            match WasmRules::load(&engine, &name, &path) {
+                Ok(rules) => {
+                    let rules = Arc::new(rules);
+                    GameModeRegistry::register_rules(&name, rules.clone());
+                    info!("🧩 Loaded rule plugin {} from {}", name, path.display());
+                    plugins.push(rules);
+                }
+                Err(e) => error!("❌ Failed to load rule plugin {}: {}", path.display(), e),
+            }
+        }
+        if plugins.is_empty() {
+            return;
+        }
+
+        let poll = Duration::from_secs(CONFIG.rule_plugin_poll_secs);
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(poll).await;
+                let plugins = plugins.clone();
+                // Compiling is CPU-bound; keep it off the async workers
+                let _ = tokio::task::spawn_blocking(move || plugins.iter().for_each(|plugin| plugin.refresh())).await;
+            }
+        });
+    }
+}
@@ -0,0 +1,198 @@
+// End-to-end login -> OTP -> profile flow against a real MongoDB.
+// Needs Docker for the MongoDB container: cargo test -- --ignored
+mod common;
+
+use bson::{doc, Document};
+use common::TestServer;
+use futures_util::TryStreamExt;
+use serde_json::json;
+
+const MOBILE_NO: &str = "9876543210";
+const DEVICE_ID: &str = "integration-device-1";
+
+fn fcm_token() -> String {
+    "f".repeat(152)
+}
+
+async fn find_all(server: &TestServer, collection: &str, filter: Document) -> Vec<Document> {
+    server.db.collection::<Document>(collection)
+        .find(filter, None)
+        .await
+        .expect("query failed")
+        .try_collect()
+        .await
+        .expect("cursor failed")
+}
+
+#[tokio::test]
+#[ignore = "needs Docker (MongoDB testcontainer)"]
+async fn login_otp_profile_flow_persists_documents() {
+    let server = TestServer::start().await;
+    let mut client = server.connect().await;
+
+    client.emit_and_expect("device:info", json!({
+        "device_id": DEVICE_ID,
+        "device_type": "android",
+        "manufacturer": "Pixel",
+        "model": "8",
+        "timestamp": "2024-01-15T10:30:00Z"
+    }), "device:info:ack").await;
+
+    let login = client.emit_and_expect("login", json!({
+        "mobile_no": MOBILE_NO,
+        "device_id": DEVICE_ID,
+        "fcm_token": fcm_token(),
+        "request_id": "it-login-1"
+    }), "login:success").await;
+    assert_eq!(login["status"], "success");
+    assert_eq!(login["is_new_user"], true);
+    assert_eq!(login["request_id"], "it-login-1");
+    let session_token = login["session_token"].as_str().expect("session_token missing").to_string();
+    let otp = login["otp"].as_i64().expect("otp missing (DEV_MODE off?)");
+
+    let verified = client.emit_and_expect("verify:otp", json!({
+        "mobile_no": MOBILE_NO,
+        "session_token": session_token,
+        "otp": otp.to_string(),
+        "nonce": login["nonce"],
+        "device_id": DEVICE_ID,
+        "fcm_token": fcm_token()
+    }), "otp:verified").await;
+    assert_eq!(verified["user_status"], "new_user");
+    assert_eq!(verified["user_number"], 1);
+    assert!(!verified["jwt_token"].as_str().unwrap_or_default().is_empty());
+
+    let profile = client.emit_and_expect("set:profile", json!({
+        "mobile_no": MOBILE_NO,
+        "session_token": session_token,
+        "full_name": "Integration Tester",
+        "state": "Karnataka"
+    }), "profile:set").await;
+    assert_eq!(profile["status"], "success");
+    client.disconnect().await;
+
+    // Event trail
+    let logins = find_all(&server, "login_events", doc! { "mobile_no": MOBILE_NO }).await;
+    assert_eq!(logins.len(), 1);
+    assert_eq!(logins[0].get_str("request_id").unwrap(), "it-login-1");
+
+    let login_sessions = find_all(&server, "login_success_events", doc! { "mobile_no": MOBILE_NO }).await;
+    assert_eq!(login_sessions.len(), 1);
+    assert_eq!(login_sessions[0].get_str("session_token").unwrap(), session_token);
+    assert!(login_sessions[0].get_datetime("verified_at").is_ok(), "login session not marked verified");
+
+    let verifications = find_all(&server, "otp_verification_events", doc! { "mobile_no": MOBILE_NO }).await;
+    assert_eq!(verifications.len(), 1);
+    assert!(verifications[0].get_bool("is_success").unwrap());
+
+    assert_eq!(find_all(&server, "otp_delivery_queue", doc! { "mobile_no": MOBILE_NO }).await.len(), 1);
+    assert_eq!(find_all(&server, "user_registration_events", doc! { "mobile_no": MOBILE_NO }).await.len(), 1);
+    assert_eq!(find_all(&server, "user_profile_events", doc! { "mobile_no": MOBILE_NO }).await.len(), 1);
+
+    // Current state
+    let sessions = find_all(&server, "sessions", doc! { "mobile_no": MOBILE_NO }).await;
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].get_str("device_id").unwrap(), DEVICE_ID);
+    assert!(!sessions[0].get_bool("revoked").unwrap());
+
+    let devices = find_all(&server, "user_devices", doc! { "mobile_no": MOBILE_NO }).await;
+    assert_eq!(devices.len(), 1);
+    assert_eq!(devices[0].get_str("device_type").unwrap(), "android");
+
+    let users = find_all(&server, "userregister", doc! { "mobile_no": MOBILE_NO }).await;
+    assert_eq!(users.len(), 1);
+    assert_eq!(users[0].get_str("full_name").unwrap(), "Integration Tester");
+    assert_eq!(users[0].get_str("state").unwrap(), "Karnataka");
+    assert_eq!(users[0].get_str("user_id").unwrap(), verified["user_id"].as_str().unwrap());
+}
+
+#[tokio::test]
+#[ignore = "needs Docker (MongoDB testcontainer)"]
+async fn wrong_otp_is_rejected_and_recorded() {
+    let server = TestServer::start().await;
+    let mut client = server.connect().await;
+
+    let login = client.emit_and_expect("login", json!({
+        "mobile_no": MOBILE_NO,
+        "device_id": DEVICE_ID,
+        "fcm_token": fcm_token()
+    }), "login:success").await;
+    let session_token = login["session_token"].as_str().unwrap().to_string();
+    let otp = login["otp"].as_i64().unwrap();
+    let wrong_otp = if otp == 999999 { 100000 } else { otp + 1 }.to_string();
+
+    let failure = client.emit_and_expect("verify:otp", json!({
+        "mobile_no": MOBILE_NO,
+        "session_token": session_token,
+        "otp": wrong_otp,
+        "nonce": login["nonce"]
+    }), "otp:verification_failed").await;
+    assert_eq!(failure["error_code"], "INVALID_OTP");
+    client.disconnect().await;
+
+    assert!(find_all(&server, "sessions", doc! { "mobile_no": MOBILE_NO }).await.is_empty());
+    assert!(find_all(&server, "userregister", doc! { "mobile_no": MOBILE_NO }).await.is_empty());
+    let errors = find_all(&server, "connection_error_events", doc! { "error_code": "INVALID_OTP" }).await;
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].get_str("field").unwrap(), "otp");
+}
+
+#[tokio::test]
+#[ignore = "needs Docker (MongoDB testcontainer)"]
+async fn legacy_numeric_otp_is_adapted_with_deprecation() {
+    let server = TestServer::start().await;
+    let mut client = server.connect_legacy().await;
+
+    let warning = client.expect("deprecation").await;
+    assert_eq!(warning["kind"], "protocol");
+    assert_eq!(warning["protocol_version"], 1);
+
+    let login = client.emit_and_expect("login", json!({
+        "mobile_no": MOBILE_NO,
+        "device_id": DEVICE_ID,
+        "fcm_token": fcm_token()
+    }), "login:success").await;
+
+    client.emit("verify:otp", json!({
+        "mobile_no": MOBILE_NO,
+        "session_token": login["session_token"],
+        "otp": login["otp"].as_i64().unwrap(),
+        "nonce": login["nonce"]
+    })).await;
+    let warning = client.expect("deprecation").await;
+    assert_eq!(warning["kind"], "payload");
+    assert_eq!(warning["deprecated_event"], "verify:otp");
+    let verified = client.expect("otp:verified").await;
+    assert_eq!(verified["mobile_no"], MOBILE_NO);
+    client.disconnect().await;
+}
+
+#[tokio::test]
+#[ignore = "needs Docker (MongoDB testcontainer)"]
+async fn verify_otp_replayed_from_another_socket_is_rejected() {
+    let server = TestServer::start().await;
+    let mut client = server.connect().await;
+
+    let login = client.emit_and_expect("login", json!({
+        "mobile_no": MOBILE_NO,
+        "device_id": DEVICE_ID,
+        "fcm_token": fcm_token()
+    }), "login:success").await;
+    let payload = json!({
+        "mobile_no": MOBILE_NO,
+        "session_token": login["session_token"],
+        "otp": login["otp"].as_i64().unwrap().to_string(),
+        "nonce": login["nonce"]
+    });
+
+    let mut attacker = server.connect().await;
+    let failure = attacker.emit_and_expect("verify:otp", payload.clone(), "otp:verification_failed").await;
+    assert_eq!(failure["error_code"], "INVALID_NONCE");
+    assert!(find_all(&server, "sessions", doc! { "mobile_no": MOBILE_NO }).await.is_empty());
+
+    client.emit_and_expect("verify:otp", payload.clone(), "otp:verified").await;
+    let failure = client.emit_and_expect("verify:otp", payload, "otp:verification_failed").await;
+    assert_eq!(failure["error_code"], "INVALID_NONCE");
+    attacker.disconnect().await;
+    client.disconnect().await;
+}
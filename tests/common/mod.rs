@@ -0,0 +1,178 @@
+// Shared harness for the integration tests: the server binary, backed by a
+// throwaway MongoDB container or the in-memory store, and a scripted
+// Socket.IO client.
+#![allow(dead_code)]
+
+use futures_util::FutureExt;
+use mongodb::{Client as MongoClient, Database};
+use rust_socketio::{
+    asynchronous::{Client, ClientBuilder},
+    Payload,
+};
+use serde_json::{json, Value};
+use std::process::Stdio;
+use std::time::Duration;
+use testcontainers_modules::{
+    mongo::Mongo,
+    testcontainers::{runners::AsyncRunner, ContainerAsync},
+};
+use tokio::process::{Child, Command};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+const EVENT_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub const TEST_DATABASE: &str = "game_admin_test";
+// Must match PROTOCOL_VERSION in src/managers/protocol.rs
+pub const PROTOCOL_VERSION: u32 = 2;
+
+pub struct TestServer {
+    pub url: String,
+    pub db: Database,
+    server: Child,
+    _mongo: ContainerAsync<Mongo>,
+}
+
+impl TestServer {
+    // Start MongoDB and the server on a free port. DEV_MODE is on so
+    // login:success carries the OTP.
+    pub async fn start() -> Self {
+        let mongo = Mongo::default().start().await.expect("failed to start MongoDB container");
+        let mongo_port = mongo.get_host_port_ipv4(27017).await.expect("MongoDB port not mapped");
+        let mongodb_uri = format!("mongodb://127.0.0.1:{}", mongo_port);
+
+        let (server, address) = spawn_server(&[
+            ("MONGODB_URI", mongodb_uri.as_str()),
+            ("MONGODB_DATABASE", TEST_DATABASE),
+            ("DATA_STORE", "mongodb"),
+        ]).await;
+
+        let db = MongoClient::with_uri_str(&mongodb_uri)
+            .await
+            .expect("failed to connect to MongoDB")
+            .database(TEST_DATABASE);
+
+        Self {
+            url: format!("http://{}", address),
+            db,
+            server,
+            _mongo: mongo,
+        }
+    }
+
+    // Client declaring the current payload protocol
+    pub async fn connect(&self) -> TestClient {
+        TestClient::connect(&self.url, Some(json!({ "protocol_version": PROTOCOL_VERSION }))).await
+    }
+
+    // Client that predates protocol versions (sends no auth payload)
+    pub async fn connect_legacy(&self) -> TestClient {
+        TestClient::connect(&self.url, None).await
+    }
+}
+
+// Server with DATA_STORE=memory, so no Docker is needed. Every instance
+// starts empty.
+pub struct MemoryServer {
+    pub url: String,
+    server: Child,
+}
+
+impl MemoryServer {
+    pub async fn start() -> Self {
+        let (server, address) = spawn_server(&[("DATA_STORE", "memory")]).await;
+        Self { url: format!("http://{}", address), server }
+    }
+}
+
+// Run the server binary on a free port with DEV_MODE on and wait until it
+// accepts connections. Returns the process and its address.
+async fn spawn_server(env: &[(&str, &str)]) -> (Child, String) {
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("no free port")
+        .port();
+
+    let server = Command::new(env!("CARGO_BIN_EXE_game-admin-backend"))
+        .envs(env.iter().copied())
+        .env("SERVER_HOST", "127.0.0.1")
+        .env("SERVER_PORT", port.to_string())
+        .env("DEV_MODE", "true")
+        .env("PAYLOAD_SIGNING_SECRET", "contract-test-payload-signing-secret")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .expect("failed to start server");
+
+    let address = format!("127.0.0.1:{}", port);
+    let started = tokio::time::Instant::now();
+    while tokio::net::TcpStream::connect(&address).await.is_err() {
+        assert!(started.elapsed() < STARTUP_TIMEOUT, "server did not start listening on {}", address);
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+    (server, address)
+}
+
+// Socket.IO client that queues every received event so a test can wait for
+// the one it expects, in order.
+pub struct TestClient {
+    client: Client,
+    events: UnboundedReceiver<(String, Value)>,
+}
+
+impl TestClient {
+    pub async fn connect(url: &str, auth: Option<Value>) -> Self {
+        let (tx, events) = unbounded_channel();
+        let mut builder = ClientBuilder::new(url).namespace("/");
+        if let Some(auth) = auth {
+            builder = builder.auth(auth);
+        }
+        let client = builder
+            .on_any(move |event, payload, _| {
+                let tx = tx.clone();
+                async move {
+                    let data = match payload {
+                        Payload::Text(mut values) if !values.is_empty() => values.remove(0),
+                        _ => Value::Null,
+                    };
+                    let _ = tx.send((String::from(event), data));
+                }
+                .boxed()
+            })
+            .connect()
+            .await
+            .expect("failed to connect Socket.IO client");
+        Self { client, events }
+    }
+
+    pub async fn emit(&self, event: &str, data: Value) {
+        self.client.emit(event, data).await.expect("failed to emit");
+    }
+
+    // Wait for `event`, skipping anything else received first. Fails the
+    // test if connection_error arrives instead.
+    pub async fn expect(&mut self, event: &str) -> Value {
+        let wait = async {
+            while let Some((name, data)) = self.events.recv().await {
+                if name == event {
+                    return data;
+                }
+                assert_ne!(name, "connection_error", "expected {} but got connection_error: {}", event, data);
+            }
+            panic!("connection closed while waiting for {}", event);
+        };
+        tokio::time::timeout(EVENT_TIMEOUT, wait)
+            .await
+            .unwrap_or_else(|_| panic!("timed out waiting for {}", event))
+    }
+
+    pub async fn emit_and_expect(&mut self, event: &str, data: Value, response: &str) -> Value {
+        self.emit(event, data).await;
+        self.expect(response).await
+    }
+
+    pub async fn disconnect(self) {
+        let _ = self.client.disconnect().await;
+    }
+}
@@ -0,0 +1,174 @@
+// Replays the captured client payloads in tests/contracts/fixtures through a
+// server on the in-memory store and compares every expected response with its
+// snapshot in tests/contracts/snapshots, so a change to a response's shape
+// shows up as a test failure instead of a broken client.
+//
+//   cargo test --test contract_replay                       check against the snapshots
+//   UPDATE_SNAPSHOTS=1 cargo test --test contract_replay    rewrite them after an intended change
+//
+// A fixture without a snapshot fails; record one with UPDATE_SNAPSHOTS=1 and
+// commit the new file. Fixtures start with { "expect": "connect_response" }
+// (or the first event a legacy client gets) so nothing is emitted before the
+// server has registered its handlers.
+//
+// Fixture format:
+//   {
+//     "description": "...",
+//     "protocol_version": 2,          // Sent in the auth payload; null connects as a legacy client
+//     "steps": [
+//       { "emit": "login", "data": { ... }, "expect": "login:success" },
+//       { "expect": "deprecation" }   // Wait for a server-initiated event
+//     ]
+//   }
+// Strings in `data` may use ${<event>.<field>} to insert a field of the last
+// response received for <event>, e.g. "${login:success.session_token}";
+// {"$ref": "<event>.<field>"} inserts the field with its JSON type.
+mod common;
+
+use common::{MemoryServer, TestClient};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+// Values that differ on every run; snapshots store a placeholder instead
+const VOLATILE_FIELDS: &[&str] = &[
+    "timestamp", "socket_id", "request_id", "token", "session_token", "otp", "jwt_token",
+    "user_id", "referral_code", "server_time", "signing_key", "nonce",
+];
+const REDACTED: &str = "[redacted]";
+
+fn contracts_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("contracts")
+}
+
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object.iter_mut() {
+                if !value.is_null() && (VOLATILE_FIELDS.contains(&key.as_str()) || key.ends_with("_at")) {
+                    *value = json!(REDACTED);
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+// Field of the last response received for `event`, from an "<event>.<field>" reference
+fn lookup<'a>(reference: &str, responses: &'a HashMap<String, Value>) -> &'a Value {
+    let (event, field) = reference.split_once('.')
+        .unwrap_or_else(|| panic!("reference {:?} must be <event>.<field>", reference));
+    responses.get(event)
+        .map(|response| &response[field])
+        .filter(|value| !value.is_null())
+        .unwrap_or_else(|| panic!("no {} received with a {} field for {:?}", event, field, reference))
+}
+
+// Resolve ${event.field} references in strings and {"$ref": ...} objects in `data`
+fn interpolate(data: &Value, responses: &HashMap<String, Value>) -> Value {
+    match data {
+        Value::String(text) => {
+            let mut result = String::new();
+            let mut rest = text.as_str();
+            while let Some(start) = rest.find("${") {
+                let end = rest[start..].find('}').map(|end| start + end)
+                    .unwrap_or_else(|| panic!("unterminated reference in {:?}", text));
+                result.push_str(&rest[..start]);
+                match lookup(&rest[start + 2..end], responses) {
+                    Value::String(s) => result.push_str(s),
+                    other => result.push_str(&other.to_string()),
+                }
+                rest = &rest[end + 1..];
+            }
+            result.push_str(rest);
+            Value::String(result)
+        }
+        Value::Object(object) => match object.get("$ref").and_then(Value::as_str) {
+            Some(reference) if object.len() == 1 => lookup(reference, responses).clone(),
+            _ => Value::Object(
+                object.iter().map(|(key, value)| (key.clone(), interpolate(value, responses))).collect::<Map<_, _>>()
+            ),
+        },
+        Value::Array(items) => Value::Array(items.iter().map(|item| interpolate(item, responses)).collect()),
+        other => other.clone(),
+    }
+}
+
+// Run one fixture against a fresh server; returns the redacted responses in order
+async fn replay(fixture: &Value) -> Value {
+    let server = MemoryServer::start().await;
+    let auth = match &fixture["protocol_version"] {
+        Value::Null => None,
+        version => Some(json!({ "protocol_version": version })),
+    };
+    let mut client = TestClient::connect(&server.url, auth).await;
+
+    let mut responses = HashMap::new();
+    let mut transcript = Vec::new();
+    for step in fixture["steps"].as_array().expect("fixture has no steps") {
+        if let Some(event) = step["emit"].as_str() {
+            client.emit(event, interpolate(&step["data"], &responses)).await;
+        }
+        let expected = step["expect"].as_str().expect("every step needs an expect");
+        let response = client.expect(expected).await;
+        responses.insert(expected.to_string(), response.clone());
+
+        let mut response = response;
+        redact(&mut response);
+        transcript.push(json!({ "emit": step["emit"], "expect": expected, "response": response }));
+    }
+    client.disconnect().await;
+    Value::Array(transcript)
+}
+
+#[tokio::test]
+async fn fixtures_match_response_snapshots() {
+    let update = std::env::var("UPDATE_SNAPSHOTS").is_ok_and(|v| v == "1");
+    let mut fixtures: Vec<PathBuf> = std::fs::read_dir(contracts_dir().join("fixtures"))
+        .expect("tests/contracts/fixtures missing")
+        .map(|entry| entry.expect("unreadable fixture entry").path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    fixtures.sort();
+    assert!(!fixtures.is_empty(), "no fixtures in tests/contracts/fixtures");
+
+    let mut mismatches = Vec::new();
+    for path in &fixtures {
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        let fixture: Value = serde_json::from_str(&std::fs::read_to_string(path).unwrap())
+            .unwrap_or_else(|e| panic!("{} is not valid JSON: {}", name, e));
+        let actual = replay(&fixture).await;
+
+        let snapshot_path = contracts_dir().join("snapshots").join(&name);
+        let expected: Option<Value> = std::fs::read_to_string(&snapshot_path).ok()
+            .map(|text| serde_json::from_str(&text).unwrap_or_else(|e| panic!("snapshot {} is not valid JSON: {}", name, e)));
+        if expected.as_ref() == Some(&actual) {
+            continue;
+        }
+        if update {
+            std::fs::create_dir_all(snapshot_path.parent().unwrap()).unwrap();
+            std::fs::write(&snapshot_path, serde_json::to_string_pretty(&actual).unwrap() + "\n").unwrap();
+            eprintln!("📸 Wrote snapshot {}", snapshot_path.display());
+            continue;
+        }
+        let Some(expected) = expected else {
+            mismatches.push(format!("{}\n--- no snapshot (UPDATE_SNAPSHOTS=1 to record one)", name));
+            continue;
+        };
+        mismatches.push(format!(
+            "{}\n--- snapshot\n{}\n+++ actual\n{}",
+            name,
+            serde_json::to_string_pretty(&expected).unwrap(),
+            serde_json::to_string_pretty(&actual).unwrap()
+        ));
+    }
+    assert!(
+        mismatches.is_empty(),
+        "{} fixture(s) no longer match their snapshots (UPDATE_SNAPSHOTS=1 to accept):\n\n{}",
+        mismatches.len(),
+        mismatches.join("\n\n")
+    );
+}